@@ -0,0 +1,35 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform sampler source_sampler;
+layout(set = 0, binding = 1) uniform texture2D source_map;
+
+layout(set = 1, binding = 0, rgba16f) uniform writeonly image2D output_map;
+layout(set = 1, binding = 1) uniform Bloom {
+    float threshold;
+    uint radius;
+    uvec2 direction;
+};
+
+// One direction of a separable gaussian blur, direction is either (1, 0) or (0, 1).
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(output_map);
+    if (coords.x >= size.x || coords.y >= size.y) {
+        return;
+    }
+
+    float sigma = max(float(radius) * 0.5, 1.0);
+    int r = int(radius);
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+    for (int i = -r; i <= r; ++i) {
+        ivec2 sample_coords = clamp(coords + ivec2(direction) * i, ivec2(0), size - 1);
+        float weight = exp(-float(i * i) / (2.0 * sigma * sigma));
+        color += texelFetch(sampler2D(source_map, source_sampler), sample_coords, 0).rgb * weight;
+        total_weight += weight;
+    }
+
+    imageStore(output_map, coords, vec4(color / total_weight, 1.0));
+}
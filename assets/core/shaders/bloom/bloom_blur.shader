@@ -0,0 +1 @@
+bloom_blur.comp.glsl
\ No newline at end of file
@@ -0,0 +1,15 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler bloom_sampler;
+layout(set = 0, binding = 1) uniform texture2D bloom_map;
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    // The bloom map is half size so sample it with uv's based off of the fragment position.
+    vec2 size = vec2(textureSize(sampler2D(bloom_map, bloom_sampler), 0)) * 2.0;
+    vec2 uv = gl_FragCoord.xy / size;
+    // Intensity is applied using the blend color.
+    outColor = vec4(texture(sampler2D(bloom_map, bloom_sampler), uv).rgb, 1.0);
+}
@@ -0,0 +1,30 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform sampler hdr_sampler;
+layout(set = 0, binding = 1) uniform texture2D hdr_map;
+
+layout(set = 1, binding = 0, rgba16f) uniform writeonly image2D bright_map;
+layout(set = 1, binding = 1) uniform Bloom {
+    float threshold;
+    uint radius;
+    uvec2 direction;
+};
+
+void main() {
+    ivec2 coords = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(bright_map);
+    if (coords.x >= size.x || coords.y >= size.y) {
+        return;
+    }
+
+    // The bright map is smaller than the hdr framebuffer so we use linear filtering to downsample.
+    vec2 uv = (vec2(coords) + 0.5) / vec2(size);
+    vec3 color = textureLod(sampler2D(hdr_map, hdr_sampler), uv, 0).rgb;
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+
+    // Only keep what's above the threshold so the transition is smooth.
+    vec3 bright = color * (max(luminance - threshold, 0.0) / max(luminance, 0.0001));
+    imageStore(bright_map, coords, vec4(bright, 1.0));
+}
@@ -0,0 +1 @@
+bloom_threshold.comp.glsl
\ No newline at end of file
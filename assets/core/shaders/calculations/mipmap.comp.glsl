@@ -0,0 +1,37 @@
+#version 450
+
+// Compiled by the mipmap generator, FORMAT_RGBA16F picks the output format and SRGB_ENCODE is set for sRGB textures.
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform texture2D source_map;
+layout(set = 0, binding = 1) uniform sampler source_sampler;
+#ifdef FORMAT_RGBA16F
+layout(set = 0, binding = 2, rgba16f) uniform writeonly image2D output_map;
+#else
+layout(set = 0, binding = 2, rgba8) uniform writeonly image2D output_map;
+#endif
+
+vec3 linear_to_srgb(vec3 x) {
+    vec3 low = x * 12.92;
+    vec3 high = 1.055 * pow(x, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(x, vec3(0.0031308)));
+}
+
+void main() {
+    ivec2 size = imageSize(output_map);
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+    if (texel.x >= size.x || texel.y >= size.y) {
+        return;
+    }
+
+    // Sampling between the 4 source texels with a linear sampler averages them.
+    vec2 uv = (vec2(texel) + 0.5) / vec2(size);
+    vec4 color = textureLod(sampler2D(source_map, source_sampler), uv, 0.0);
+
+#ifdef SRGB_ENCODE
+    // Storage textures can't be sRGB so the color is encoded here and copied into the sRGB texture.
+    color.rgb = linear_to_srgb(clamp(color.rgb, 0.0, 1.0));
+#endif
+
+    imageStore(output_map, texel, color);
+}
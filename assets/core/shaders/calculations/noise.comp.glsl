@@ -0,0 +1,107 @@
+#version 450
+
+// Compiled by the noise generator, see `NoiseTextureDesc`.
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform NoiseParams {
+    // (frequency, persistence, octaves, noise type)
+    vec4 params;
+};
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D output_map;
+
+const float NOISE_PERLIN = 0.0;
+const float NOISE_SIMPLEX = 1.0;
+
+// Pseudo random gradient direction for a lattice point.
+vec2 gradient(vec2 p) {
+    float angle = fract(sin(dot(p, vec2(127.1, 311.7))) * 43758.5453) * 6.2831853;
+    return vec2(cos(angle), sin(angle));
+}
+
+vec2 random2(vec2 p) {
+    return fract(sin(vec2(dot(p, vec2(127.1, 311.7)), dot(p, vec2(269.5, 183.3)))) * 43758.5453);
+}
+
+// Gradient noise between -1.0 and 1.0.
+float perlin(vec2 p) {
+    vec2 cell = floor(p);
+    vec2 f = fract(p);
+    // Quintic fade so the derivative is continuous across cells.
+    vec2 u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+
+    float a = dot(gradient(cell), f);
+    float b = dot(gradient(cell + vec2(1.0, 0.0)), f - vec2(1.0, 0.0));
+    float c = dot(gradient(cell + vec2(0.0, 1.0)), f - vec2(0.0, 1.0));
+    float d = dot(gradient(cell + vec2(1.0, 1.0)), f - vec2(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y) * 1.414;
+}
+
+// 2D simplex noise between -1.0 and 1.0.
+float simplex(vec2 p) {
+    const float F2 = 0.366025404; // (sqrt(3) - 1) / 2
+    const float G2 = 0.211324865; // (3 - sqrt(3)) / 6
+
+    vec2 cell = floor(p + (p.x + p.y) * F2);
+    vec2 x0 = p - cell + (cell.x + cell.y) * G2;
+    vec2 offset = x0.x > x0.y ? vec2(1.0, 0.0) : vec2(0.0, 1.0);
+    vec2 x1 = x0 - offset + G2;
+    vec2 x2 = x0 - 1.0 + 2.0 * G2;
+
+    vec3 falloff = max(0.5 - vec3(dot(x0, x0), dot(x1, x1), dot(x2, x2)), 0.0);
+    falloff = falloff * falloff * falloff * falloff;
+    vec3 contributions = vec3(
+        dot(gradient(cell), x0),
+        dot(gradient(cell + offset), x1),
+        dot(gradient(cell + 1.0), x2)
+    );
+    return dot(falloff, contributions) * 70.0;
+}
+
+// Distance to the closest feature point, between 0.0 and 1.0.
+float worley(vec2 p) {
+    vec2 cell = floor(p);
+    vec2 f = fract(p);
+    float closest = 1.0;
+    for (int y = -1; y <= 1; y++) {
+        for (int x = -1; x <= 1; x++) {
+            vec2 neighbour = vec2(float(x), float(y));
+            vec2 point = neighbour + random2(cell + neighbour);
+            closest = min(closest, length(point - f));
+        }
+    }
+    return closest;
+}
+
+// One octave of noise remapped to 0.0 - 1.0.
+float noise(vec2 p) {
+    if (params.w == NOISE_PERLIN) {
+        return perlin(p) * 0.5 + 0.5;
+    } else if (params.w == NOISE_SIMPLEX) {
+        return simplex(p) * 0.5 + 0.5;
+    }
+    return worley(p);
+}
+
+void main() {
+    ivec2 size = imageSize(output_map);
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+    if (texel.x >= size.x || texel.y >= size.y) {
+        return;
+    }
+
+    // Each octave doubles the frequency and scales the amplitude by the persistence.
+    vec2 uv = (vec2(texel) + 0.5) / vec2(size);
+    float frequency = params.x;
+    float amplitude = 1.0;
+    float total = 0.0;
+    float max_total = 0.0;
+    for (int octave = 0; octave < int(params.z); octave++) {
+        total += noise(uv * frequency) * amplitude;
+        max_total += amplitude;
+        frequency *= 2.0;
+        amplitude *= params.y;
+    }
+    float value = clamp(total / max(max_total, 0.0001), 0.0, 1.0);
+
+    imageStore(output_map, texel, vec4(vec3(value), 1.0));
+}
@@ -0,0 +1,43 @@
+#version 450
+
+// Compiled by `Skybox::from_hdr_panorama`, writes one texel of every face of the cube map per invocation.
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform texture2D equirectangular_texture;
+layout(set = 0, binding = 1) uniform sampler equirectangular_sampler;
+layout(set = 0, binding = 2, rgba16f) uniform writeonly image2DArray output_cube;
+
+// Converts from [-Pi, Pi] on X to [-0.5, 0.5], and [-Pi/2, Pi/2] on Y to [-0.5, 0.5]
+const vec2 normalize_spherical_coords = vec2(0.1591, 0.3183);
+vec2 SampleSphericalMap(vec3 v)
+{
+    vec2 uv = vec2(atan(v.x, v.z), asin(-v.y));
+    uv *= normalize_spherical_coords;
+    uv += 0.5;
+    return uv;
+}
+
+// The direction through a texel of a face, faces are ordered X+ X- Y+ Y- Z+ Z-.
+vec3 face_direction(uint face, vec2 uv) {
+    switch (face) {
+        case 0: return vec3(1.0, -uv.y, -uv.x);
+        case 1: return vec3(-1.0, -uv.y, uv.x);
+        case 2: return vec3(uv.x, 1.0, uv.y);
+        case 3: return vec3(uv.x, -1.0, -uv.y);
+        case 4: return vec3(uv.x, -uv.y, 1.0);
+        default: return vec3(-uv.x, -uv.y, -1.0);
+    }
+}
+
+void main() {
+    ivec3 size = imageSize(output_cube);
+    ivec3 texel = ivec3(gl_GlobalInvocationID.xyz);
+    if (texel.x >= size.x || texel.y >= size.y) {
+        return;
+    }
+
+    vec2 uv = (vec2(texel.xy) + 0.5) / vec2(size.xy) * 2.0 - 1.0;
+    vec3 direction = normalize(face_direction(uint(texel.z), uv));
+    vec3 color = textureLod(sampler2D(equirectangular_texture, equirectangular_sampler), SampleSphericalMap(direction), 0.0).rgb;
+    imageStore(output_cube, texel, vec4(color, 1.0));
+}
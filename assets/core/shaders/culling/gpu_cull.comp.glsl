@@ -0,0 +1,55 @@
+#version 450
+
+layout(local_size_x = 64) in;
+
+// Planes point into the frustum, xyz is the normal and w the distance from the origin.
+layout(set = 0, binding = 0) uniform Cull {
+    vec4 planes[5];
+    uint draw_count;
+};
+
+struct Draw {
+    mat4 world;
+    // xyz is the center in model space and w is the radius.
+    vec4 bounding_sphere;
+    uint index_count;
+    uint _padding0;
+    uint _padding1;
+    uint _padding2;
+};
+
+layout(set = 0, binding = 1) readonly buffer Draws {
+    Draw draws[];
+};
+
+// Matches the layout draw_indexed_indirect reads.
+struct DrawIndexedIndirectArgs {
+    uint index_count;
+    uint instance_count;
+    uint first_index;
+    int base_vertex;
+    uint first_instance;
+};
+
+layout(set = 0, binding = 2) writeonly buffer Commands {
+    DrawIndexedIndirectArgs commands[];
+};
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= draw_count) {
+        return;
+    }
+
+    Draw draw = draws[index];
+    vec3 center = (draw.world * vec4(draw.bounding_sphere.xyz, 1.0)).xyz;
+    float radius = draw.bounding_sphere.w;
+
+    bool visible = true;
+    for (int i = 0; i < 5; i++) {
+        visible = visible && dot(planes[i].xyz, center) + planes[i].w >= -radius;
+    }
+
+    // Culled draws are kept with no instances so each draw keeps its slot in the buffer.
+    commands[index] = DrawIndexedIndirectArgs(draw.index_count, visible ? 1 : 0, 0, 0, 0);
+}
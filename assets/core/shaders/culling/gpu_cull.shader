@@ -0,0 +1 @@
+gpu_cull.comp.glsl
\ No newline at end of file
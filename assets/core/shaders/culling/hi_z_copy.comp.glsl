@@ -0,0 +1,19 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+// The previous frame's depth buffer.
+layout(set = 0, binding = 0) uniform texture2D depth_texture;
+layout(set = 0, binding = 1) uniform sampler depth_sampler;
+// The first mip of the hi-z buffer, it's the same size as the depth buffer.
+layout(set = 0, binding = 2, r32f) writeonly uniform image2D hi_z;
+
+void main() {
+    ivec2 coord = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(coord, imageSize(hi_z)))) {
+        return;
+    }
+
+    float depth = texelFetch(sampler2D(depth_texture, depth_sampler), coord, 0).r;
+    imageStore(hi_z, coord, vec4(depth));
+}
@@ -0,0 +1 @@
+hi_z_copy.comp.glsl
\ No newline at end of file
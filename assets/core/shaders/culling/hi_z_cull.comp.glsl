@@ -0,0 +1,91 @@
+#version 450
+
+layout(local_size_x = 64) in;
+
+layout(set = 0, binding = 0) uniform Cull {
+    // The view projection the hi-z buffer's depth was rendered with.
+    mat4 view_projection;
+    // x is the number of spheres, y the number of hi-z mips and zw the size of the first mip.
+    uvec4 counts;
+};
+
+struct Sphere {
+    // xyz is the center in world space and w is the radius.
+    vec4 sphere;
+    // x is the transform index the visibility bit is written to.
+    uvec4 index;
+};
+
+layout(set = 0, binding = 1) readonly buffer Spheres {
+    Sphere spheres[];
+};
+
+// One bit per transform index, cleared before every cull.
+layout(set = 0, binding = 2) buffer Visibility {
+    uint visibility[];
+};
+
+layout(set = 0, binding = 3) uniform texture2D hi_z;
+layout(set = 0, binding = 4) uniform sampler hi_z_sampler;
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= counts.x) {
+        return;
+    }
+
+    Sphere sphere = spheres[index];
+    vec3 center = sphere.sphere.xyz;
+    float radius = sphere.sphere.w;
+
+    // The screen space rectangle and nearest depth of the box around the sphere.
+    vec2 min_uv = vec2(1.0);
+    vec2 max_uv = vec2(0.0);
+    float min_depth = 1.0;
+    bool visible = false;
+    for (int corner = 0; corner < 8; corner++) {
+        vec3 offset = vec3(
+            (corner & 1) != 0 ? radius : -radius,
+            (corner & 2) != 0 ? radius : -radius,
+            (corner & 4) != 0 ? radius : -radius
+        );
+        vec4 clip = view_projection * vec4(center + offset, 1.0);
+        // Spheres that reach behind the camera can't be tested.
+        if (clip.w <= 0.0) {
+            visible = true;
+            break;
+        }
+        vec3 ndc = clip.xyz / clip.w;
+        vec2 uv = vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+        min_uv = min(min_uv, uv);
+        max_uv = max(max_uv, uv);
+        min_depth = min(min_depth, ndc.z);
+    }
+
+    if (!visible) {
+        min_uv = clamp(min_uv, vec2(0.0), vec2(1.0));
+        max_uv = clamp(max_uv, vec2(0.0), vec2(1.0));
+
+        // Picks the mip where the rectangle covers at most 2x2 texels.
+        vec2 extent = (max_uv - min_uv) * vec2(counts.zw);
+        int level = int(ceil(log2(max(max(extent.x, extent.y), 1.0))));
+        level = min(level, int(counts.y) - 1);
+        ivec2 level_size = max(ivec2(counts.zw) >> level, ivec2(1));
+        ivec2 min_texel = clamp(ivec2(min_uv * vec2(level_size)), ivec2(0), level_size - 1);
+        ivec2 max_texel = clamp(ivec2(max_uv * vec2(level_size)), ivec2(0), level_size - 1);
+
+        float max_depth = 0.0;
+        for (int y = min_texel.y; y <= max_texel.y; y++) {
+            for (int x = min_texel.x; x <= max_texel.x; x++) {
+                max_depth = max(max_depth, texelFetch(sampler2D(hi_z, hi_z_sampler), ivec2(x, y), level).r);
+            }
+        }
+        // Hidden if it's nearest point is behind everything drawn over it.
+        visible = min_depth <= max_depth;
+    }
+
+    if (visible) {
+        uint transform_index = sphere.index.x;
+        atomicOr(visibility[transform_index / 32], 1u << (transform_index % 32));
+    }
+}
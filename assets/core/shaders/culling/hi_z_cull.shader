@@ -0,0 +1 @@
+hi_z_cull.comp.glsl
\ No newline at end of file
@@ -0,0 +1,30 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0, r32f) readonly uniform image2D source;
+layout(set = 0, binding = 1, r32f) writeonly uniform image2D destination;
+
+void main() {
+    ivec2 coord = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(destination);
+    if (any(greaterThanEqual(coord, size))) {
+        return;
+    }
+
+    // When the source has an odd size the last texel of the destination covers 3 source texels,
+    // otherwise the furthest depth of that texel would be lost.
+    ivec2 source_size = imageSize(source);
+    int extent_x = coord.x == size.x - 1 && (source_size.x & 1) == 1 ? 3 : 2;
+    int extent_y = coord.y == size.y - 1 && (source_size.y & 1) == 1 ? 3 : 2;
+
+    // Each texel keeps the furthest depth of the block it covers.
+    float depth = 0.0;
+    for (int y = 0; y < extent_y; y++) {
+        for (int x = 0; x < extent_x; x++) {
+            ivec2 source_coord = min(coord * 2 + ivec2(x, y), source_size - 1);
+            depth = max(depth, imageLoad(source, source_coord).r);
+        }
+    }
+    imageStore(destination, coord, vec4(depth));
+}
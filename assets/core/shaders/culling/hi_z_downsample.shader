@@ -0,0 +1 @@
+hi_z_downsample.comp.glsl
\ No newline at end of file
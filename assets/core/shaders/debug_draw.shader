@@ -0,0 +1,2 @@
+debug_draw.frag.glsl
+debug_draw.vert.glsl
\ No newline at end of file
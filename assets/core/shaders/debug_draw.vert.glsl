@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec3 i_position;
+layout(location = 1) in vec4 i_color;
+layout(location = 0) out vec4 o_color;
+
+layout(set = 0, binding = 0) uniform Globals {
+    mat4 view_projection;
+    vec4 camera_pos;
+    mat4 view;
+    mat4 projection;
+};
+
+void main() {
+    o_color = i_color;
+    gl_Position = view_projection * vec4(i_position, 1.0);
+}
@@ -0,0 +1,55 @@
+#version 450
+
+#include "library/common.glsl"
+
+layout(set = 0, binding = 0) uniform sampler gbuffer_sampler;
+layout(set = 0, binding = 1) uniform texture2D depth_map;
+layout(set = 0, binding = 2) uniform texture2D material_map;
+
+layout(set = 2, binding = 0) uniform sampler decal_sampler;
+layout(set = 2, binding = 1) uniform texture2D decal_map;
+layout(set = 2, binding = 2) uniform texture2D decal_normal_map;
+
+layout(location = 0) flat in mat4 i_inverse_model;
+layout(location = 4) flat in mat3 i_tbn;
+layout(location = 7) flat in float i_fade;
+layout(location = 8) flat in float i_has_normal;
+
+layout(location = 0) out vec4 o_albedo;
+layout(location = 1) out vec4 o_normal;
+
+void main() {
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(sampler2D(depth_map, gbuffer_sampler), coords, 0).r;
+    // The material's alpha is set when it receives decals.
+    float receives_decals = texelFetch(sampler2D(material_map, gbuffer_sampler), coords, 0).a;
+    if (depth >= 1.0 || receives_decals < 0.5) {
+        discard;
+    }
+
+    // Reconstruct the world position from the depth, the same way the lighting pass does.
+    vec2 screen_uv = gl_FragCoord.xy / vec2(textureSize(sampler2D(depth_map, gbuffer_sampler), 0));
+    vec4 clip_position = vec4(screen_uv.x * 2.0 - 1.0, 1.0 - screen_uv.y * 2.0, depth, 1.0);
+    vec4 world_position = inverse(view_projection) * clip_position;
+    vec3 position = world_position.xyz / world_position.w;
+
+    // Geometry behind or in front of the box is covered by the box's faces but lies outside of it.
+    vec3 local_position = (i_inverse_model * vec4(position, 1.0)).xyz;
+    if (any(greaterThan(abs(local_position), vec3(0.5)))) {
+        discard;
+    }
+
+    // Textures start at the top left.
+    vec2 uv = vec2(local_position.x + 0.5, 0.5 - local_position.y);
+    vec4 color = texture(sampler2D(decal_map, decal_sampler), uv);
+    color.a *= i_fade;
+    o_albedo = color;
+
+    // A zero alpha leaves the surface's normal alone.
+    o_normal = vec4(0.0);
+    if (i_has_normal > 0.5) {
+        vec3 normal = texture(sampler2D(decal_normal_map, decal_sampler), uv).rgb * 2.0 - 1.0;
+        vec3 N = normalize(i_tbn * normalize(normal));
+        o_normal = vec4(N * 0.5 + 0.5, color.a);
+    }
+}
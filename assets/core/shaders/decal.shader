@@ -0,0 +1,2 @@
+decal.frag.glsl
+decal.vert.glsl
\ No newline at end of file
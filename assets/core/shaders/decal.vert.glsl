@@ -0,0 +1,52 @@
+#version 450
+
+#include "library/common.glsl"
+
+// See `DecalInstance`.
+layout(location = 0) in vec4 i_model_0;
+layout(location = 1) in vec4 i_model_1;
+layout(location = 2) in vec4 i_model_2;
+layout(location = 3) in vec4 i_model_3;
+layout(location = 4) in vec4 i_inverse_model_0;
+layout(location = 5) in vec4 i_inverse_model_1;
+layout(location = 6) in vec4 i_inverse_model_2;
+layout(location = 7) in vec4 i_inverse_model_3;
+layout(location = 8) in vec4 i_info;
+
+layout(location = 0) flat out mat4 o_inverse_model;
+layout(location = 4) flat out mat3 o_tbn;
+layout(location = 7) flat out float o_fade;
+layout(location = 8) flat out float o_has_normal;
+
+// The corners of a face in the order they're emitted, counter clockwise seen from the outside.
+const ivec2 CORNERS[4] = ivec2[](ivec2(0, 0), ivec2(1, 0), ivec2(1, 1), ivec2(0, 1));
+const int POSITIVE_ORDER[6] = int[](0, 1, 2, 0, 2, 3);
+const int NEGATIVE_ORDER[6] = int[](0, 2, 1, 0, 3, 2);
+
+void main() {
+    mat4 model = mat4(i_model_0, i_model_1, i_model_2, i_model_3);
+
+    // Builds the unit cube, two faces per axis.
+    int face = gl_VertexIndex / 6;
+    int axis = face / 2;
+    bool positive = face % 2 == 0;
+    int corner_index = positive ? POSITIVE_ORDER[gl_VertexIndex % 6] : NEGATIVE_ORDER[gl_VertexIndex % 6];
+    ivec2 corner = CORNERS[corner_index];
+    vec3 position;
+    position[axis] = positive ? 0.5 : -0.5;
+    position[(axis + 1) % 3] = float(corner.x) - 0.5;
+    position[(axis + 2) % 3] = float(corner.y) - 0.5;
+
+    // Fades out over the last quarter of the fade distance.
+    float fade_distance = i_info.x;
+    o_fade = 1.0;
+    if (fade_distance > 0.0) {
+        float distance = length(camera_pos.xyz - model[3].xyz);
+        o_fade = clamp((fade_distance - distance) / (fade_distance * 0.25), 0.0, 1.0);
+    }
+
+    o_inverse_model = mat4(i_inverse_model_0, i_inverse_model_1, i_inverse_model_2, i_inverse_model_3);
+    o_tbn = mat3(normalize(model[0].xyz), normalize(model[1].xyz), normalize(model[2].xyz));
+    o_has_normal = i_info.y;
+    gl_Position = view_projection * model * vec4(position, 1.0);
+}
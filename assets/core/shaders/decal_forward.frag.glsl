@@ -0,0 +1,14 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform sampler decal_sampler;
+layout(set = 1, binding = 1) uniform texture2D decal_map;
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 1) flat in float i_fade;
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    vec4 color = texture(sampler2D(decal_map, decal_sampler), i_uv);
+    // Decals are added to the frame so they don't need to be sorted.
+    outColor = vec4(color.rgb * color.a * i_fade, 0.0);
+}
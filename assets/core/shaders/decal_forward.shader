@@ -0,0 +1,2 @@
+decal_forward.frag.glsl
+decal_forward.vert.glsl
\ No newline at end of file
@@ -0,0 +1,45 @@
+#version 450
+
+// See `DecalInstance`, the inverse model matrix at locations 4 to 7 isn't needed here.
+layout(location = 0) in vec4 i_model_0;
+layout(location = 1) in vec4 i_model_1;
+layout(location = 2) in vec4 i_model_2;
+layout(location = 3) in vec4 i_model_3;
+layout(location = 8) in vec4 i_info;
+
+layout(location = 0) out vec2 o_uv;
+layout(location = 1) flat out float o_fade;
+
+layout(set = 0, binding = 0) uniform Globals {
+    mat4 view_projection;
+    vec4 camera_pos;
+    mat4 view;
+    mat4 projection;
+};
+
+// The corners of the quad through the middle of the box in the order they're emitted.
+const vec2 CORNERS[6] = vec2[](
+    vec2(0.0, 0.0),
+    vec2(1.0, 0.0),
+    vec2(1.0, 1.0),
+    vec2(0.0, 0.0),
+    vec2(1.0, 1.0),
+    vec2(0.0, 1.0)
+);
+
+void main() {
+    mat4 model = mat4(i_model_0, i_model_1, i_model_2, i_model_3);
+    vec2 corner = CORNERS[gl_VertexIndex];
+
+    // Fades out over the last quarter of the fade distance.
+    float fade_distance = i_info.x;
+    o_fade = 1.0;
+    if (fade_distance > 0.0) {
+        float distance = length(camera_pos.xyz - model[3].xyz);
+        o_fade = clamp((fade_distance - distance) / (fade_distance * 0.25), 0.0, 1.0);
+    }
+
+    // Textures start at the top left, the quad at the bottom left.
+    o_uv = vec2(corner.x, 1.0 - corner.y);
+    gl_Position = view_projection * model * vec4(corner - 0.5, 0.0, 1.0);
+}
@@ -0,0 +1,67 @@
+#version 450
+
+#include "library/common.glsl"
+
+layout(set = 2, binding = 0) uniform Material {
+    vec4 color;
+    // (metallic, roughness, metallic_amount, roughness_amount)
+    vec4 pbr_info;
+    // (x, y, width, height) of the texture region to sample.
+    vec4 uv_rect;
+    vec4 emissive;
+    // x is 1.0 when decals are drawn on the material, y is the alpha cutoff or 0.0 when the material isn't masked.
+    vec4 flags;
+};
+
+layout(set = 2, binding = 1) uniform sampler tex_sampler;
+layout(set = 2, binding = 2) uniform sampler brdf_sampler;
+layout(set = 2, binding = 3) uniform texture2D main_map;
+layout(set = 2, binding = 4) uniform texture2D normal_map;
+layout(set = 2, binding = 5) uniform texture2D metallic_roughness_map;
+
+// See `UvAnimation`.
+layout(set = 2, binding = 7) uniform UvAnimation {
+    vec2 uv_offset;
+    vec2 uv_scale;
+};
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 1) in vec3 i_normal;
+layout(location = 2) in vec3 i_position;
+layout(location = 3) in vec3 i_tangent;
+layout(location = 4) in float i_tbn_handedness;
+layout(location = 5) in vec4 i_clip_position;
+layout(location = 6) in vec4 i_view_position;
+layout(location = 7) in vec3 i_vertex;
+
+layout(location = 0) out vec4 o_albedo;
+layout(location = 1) out vec4 o_normal;
+layout(location = 2) out vec4 o_material;
+
+void main() {
+    vec2 uv = uv_rect.xy + (i_uv * uv_scale + uv_offset) * uv_rect.zw;
+    vec4 main_color = texture(sampler2D(main_map, tex_sampler), uv) * color;
+    // Masked materials cut out pixels below the cutoff, see `PBRMaterial::alpha_cutoff`.
+    if (flags.y > 0.0 && main_color.a < flags.y) {
+        discard;
+    }
+
+    vec2 metallic_roughness = texture(sampler2D(metallic_roughness_map, tex_sampler), uv).xy;
+    float metallic = mix(metallic_roughness.x, pbr_info.x, pbr_info.z);
+    float roughness = mix(metallic_roughness.y, pbr_info.y, pbr_info.w);
+
+    vec3 normal = texture(sampler2D(normal_map, tex_sampler), uv).rgb;
+    normal = normal * 2.0 - 1.0;
+    vec3 N = normalize(i_normal);
+    vec3 T = normalize(i_tangent);
+    vec3 B = cross(N, T) * i_tbn_handedness;
+    mat3 TBN = mat3(T, B, N);
+    N = normalize(TBN * normalize(normal));
+
+    o_albedo = main_color;
+    // Pack the normal into 0-1 as the normal target is unorm.
+    o_normal = vec4(N * 0.5 + 0.5, 1.0);
+    // TODO: Support ambient occlusion maps, for now ao is always 1.0.
+    // The alpha tells the decal pass which surfaces it may draw on.
+    o_material = vec4(metallic, roughness, 1.0, flags.x);
+}
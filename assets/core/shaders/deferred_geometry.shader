@@ -0,0 +1,2 @@
+deferred_geometry.frag.glsl
+pbr.vert.glsl
\ No newline at end of file
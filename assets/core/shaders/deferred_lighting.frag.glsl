@@ -0,0 +1,165 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "library/lighting.glsl"
+#include "library/pbr.glsl"
+#include "library/common.glsl"
+#include "clustered/frustum.glsl"
+
+layout(set = 0, binding = 0) uniform sampler gbuffer_sampler;
+layout(set = 0, binding = 1) uniform texture2D albedo_map;
+layout(set = 0, binding = 2) uniform texture2D normal_map;
+layout(set = 0, binding = 3) uniform texture2D material_map;
+layout(set = 0, binding = 4) uniform texture2D depth_map;
+
+layout(set = 1, binding = 2) readonly buffer Frustums {
+    Frustum frustums[];
+};
+
+layout(set = 1, binding = 3) readonly buffer GlobalIndices {
+    LightIndexSet light_index_list[];
+};
+
+layout(set = 2, binding = 0) uniform textureCube irradiance_cube_map;
+layout(set = 2, binding = 1) uniform textureCube spec_cube_map;
+layout(set = 2, binding = 2) uniform texture2D spec_brdf_map;
+
+layout(set = 3, binding = 0) uniform sampler ssao_sampler;
+layout(set = 3, binding = 1) uniform texture2D ssao_map;
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 0) out vec4 outColor;
+
+uvec3 compute_froxel(vec3 clip_position, vec4 view_position) {
+    // normalize clip position to 0-1 in xy
+    vec2 scale = clip_position.xy * 0.5 + 0.5;
+    vec2 frustum_raw = scale * vec2(cluster_count.xy);
+    uvec2 frustum_xy = uvec2(floor(frustum_raw));
+    float depth = view_position.z;
+    uint depth_frustum = uint(floor((depth / light_num.w) * cluster_count.z)); // light_num.w is the max depth.
+    return uvec3(frustum_xy, min(depth_frustum, cluster_count.z - 1));
+}
+
+vec3 light_contribution(vec3 N, vec3 V, vec3 L, vec3 radiance, vec3 F0, vec3 main_color, float metallic, float roughness) {
+    vec3 H = normalize(V + L);
+
+    // cook-torrance brdf
+    float NDF = DistributionGGX(N, H, roughness);
+    float G   = GeometrySmith(N, V, L, roughness);
+    vec3 F    = fresnelSchlick(max(dot(H, V), 0.0), F0);
+
+    vec3 kS = F;
+    vec3 kD = vec3(1.0) - kS;
+    kD *= 1.0 - metallic;
+
+    vec3 numerator    = NDF * G * F;
+    float denominator = 4.0 * max(dot(N, V), 0.0) * max(dot(N, L), 0.0);
+    vec3 specular     = numerator / max(denominator, 0.001);
+
+    float NdotL = max(dot(N, L), 0.0);
+    return (kD * main_color / PI + specular) * radiance * NdotL;
+}
+
+void main() {
+    // Use the fragment coordinates directly so we don't have to worry about the uv's orientation.
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(sampler2D(depth_map, gbuffer_sampler), coords, 0).r;
+
+    // Nothing was written here by the geometry pass so let the skybox show through.
+    if (depth >= 1.0) {
+        discard;
+    }
+
+    vec4 albedo = texelFetch(sampler2D(albedo_map, gbuffer_sampler), coords, 0);
+    vec3 N = normalize(texelFetch(sampler2D(normal_map, gbuffer_sampler), coords, 0).rgb * 2.0 - 1.0);
+    vec3 material = texelFetch(sampler2D(material_map, gbuffer_sampler), coords, 0).rgb;
+    vec3 main_color = albedo.rgb;
+    float metallic = material.r;
+    float roughness = material.g;
+    // Combine the material's baked occlusion with screen space occlusion.
+    float ao = material.b * texelFetch(sampler2D(ssao_map, ssao_sampler), coords, 0).r;
+
+    // Reconstruct the world position from the depth.
+    vec2 screen_uv = gl_FragCoord.xy / vec2(textureSize(sampler2D(depth_map, gbuffer_sampler), 0));
+    vec4 clip_position = vec4(screen_uv.x * 2.0 - 1.0, 1.0 - screen_uv.y * 2.0, depth, 1.0);
+    vec4 world_position = inverse(view_projection) * clip_position;
+    vec3 position = world_position.xyz / world_position.w;
+    vec4 view_position = view * vec4(position, 1.0);
+
+    vec3 V = normalize(camera_pos.xyz - position);
+    vec3 R = reflect(V, N);
+
+    vec3 ambient_irradiance = texture(samplerCube(irradiance_cube_map, gbuffer_sampler), N).rgb;
+
+    // Convert irradiance to radiance
+    ambient_irradiance = (ambient_irradiance / PI) * 1.0; // 1.0 is enviroment scale
+
+    vec3 F0 = vec3(0.04);
+    F0 = mix(F0, main_color, metallic);
+
+    float NdotV = abs(dot(N, V)) + 0.00001;
+    vec3 F = fresnelSchlickRoughness(NdotV, F0, roughness);
+
+    vec3 kS = F;
+    vec3 kD = 1.0 - kS;
+    kD *= 1.0 - metallic;
+    vec3 diffuse = ambient_irradiance * main_color;
+
+    vec3 specularColor = textureLod(samplerCube(spec_cube_map, gbuffer_sampler), R, roughness * MAX_SPEC_LOD).rgb;
+    vec2 brdf = texture(sampler2D(spec_brdf_map, gbuffer_sampler), vec2(NdotV, roughness)).rg;
+    vec3 specular = specularColor * (F * brdf.x + brdf.y);
+
+    vec3 ambient = (kD * diffuse + specular) * ao;
+
+    // Directional Lighting
+    vec3 light_acc = vec3(0.0);
+    for (int i=0; i < int(light_num.x) && i < MAX_LIGHTS; ++i) {
+        DirectionalLight light = directional_lights[i];
+        vec3 L = normalize(light.direction.xyz);
+        vec3 radiance = light.color.xyz * light.color.w; // w is intensity
+        light_acc += light_contribution(N, V, L, radiance, F0, main_color, metallic, roughness);
+    }
+
+    // Point Lighting
+    uvec3 froxel = compute_froxel(clip_position.xyz, view_position);
+    uint froxel_index = get_cluster_list_index(froxel, cluster_count.xyz);
+    uint count = light_index_list[froxel_index].count;
+    for (uint l = 0; l < count; ++l) {
+        PointLight light = point_lights[light_index_list[froxel_index].indices[l]];
+        vec3 L = light.position.xyz - position;
+
+        const float dist2 = dot(L, L);
+        const float range2 = light.attenuation.x * light.attenuation.x;
+
+        if (dist2 < range2) {
+            float dist = sqrt(dist2);
+            L /= dist;
+            float att = saturate(1.0 - (dist2 / range2));
+            vec3 radiance = light.color.xyz * light.color.w * att * att; // w is intensity
+
+            float shadow = 1.0;
+            if (light.attenuation.y > 0) {
+                vec3 frag_ls = light.position.xyz - position;
+                vec3 abs_position_ls = abs(frag_ls);
+                float major_axis_magnitude = max(abs_position_ls.x, max(abs_position_ls.y, abs_position_ls.z));
+                vec4 clip = light.shadow_matrix * vec4(0.0, 0.0, major_axis_magnitude * 0.5, 1.0);
+                float shadow_depth = (clip.z / clip.w) * 0.5 + 0.5;
+
+                int quad_id = int(light.attenuation.z);
+                if (quad_id == 0) {
+                    shadow = texture(samplerCubeArrayShadow(omni_shadow_quad_1, shadow_sampler), vec4(-frag_ls, int(light.attenuation.w)), shadow_depth);
+                } else if (quad_id == 1) {
+                    shadow = texture(samplerCubeArrayShadow(omni_shadow_quad_2, shadow_sampler), vec4(-frag_ls, int(light.attenuation.w)), shadow_depth);
+                } else if (quad_id == 2) {
+                    shadow = texture(samplerCubeArrayShadow(omni_shadow_quad_3, shadow_sampler), vec4(-frag_ls, int(light.attenuation.w)), shadow_depth);
+                } else if (quad_id == 3) {
+                    shadow = texture(samplerCubeArrayShadow(omni_shadow_quad_4, shadow_sampler), vec4(-frag_ls, int(light.attenuation.w)), shadow_depth);
+                }
+            }
+
+            light_acc += light_contribution(N, V, L, radiance, F0, main_color, metallic, roughness) * shadow;
+        }
+    }
+
+    outColor = vec4(ambient + light_acc, albedo.a);
+}
@@ -0,0 +1,2 @@
+depth_prepass.vert.glsl
+depth_prepass.frag.glsl
\ No newline at end of file
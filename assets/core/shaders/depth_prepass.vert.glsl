@@ -0,0 +1,16 @@
+#version 450
+
+#include "library/common.glsl"
+
+layout(location = 0) in vec3 i_Pos;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 world;
+};
+
+// Has to match the pbr vertex shader exactly, the pbr pass only shades fragments with an equal depth.
+invariant gl_Position;
+
+void main() {
+    gl_Position = projection * view * world * vec4(i_Pos, 1.0);
+}
@@ -0,0 +1,151 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler color_sampler;
+layout(set = 0, binding = 1) uniform texture2D color_map;
+layout(set = 0, binding = 2) uniform FxaaUniform {
+    float subpixel_quality;
+    float edge_threshold;
+    float edge_threshold_min;
+};
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 0) out vec4 outColor;
+
+#define ITERATIONS 12
+
+// How far to step along the edge at each iteration, from FXAA 3.11's quality preset 39.
+const float QUALITY[ITERATIONS] = float[](1.0, 1.0, 1.0, 1.0, 1.0, 1.5, 2.0, 2.0, 2.0, 2.0, 4.0, 8.0);
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+vec3 sample_color(vec2 uv) {
+    return textureLod(sampler2D(color_map, color_sampler), uv, 0.0).rgb;
+}
+
+float sample_luma(vec2 uv) {
+    return luma(sample_color(uv));
+}
+
+float sample_luma(vec2 uv, vec2 texel, ivec2 offset) {
+    return luma(sample_color(uv + vec2(offset) * texel));
+}
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(sampler2D(color_map, color_sampler), 0));
+    // The target is the same size as the swap chain so we can work out the uv from the pixel position.
+    vec2 uv = gl_FragCoord.xy * texel;
+
+    vec3 color_center = sample_color(uv);
+    float luma_center = luma(color_center);
+    float luma_down = sample_luma(uv, texel, ivec2(0, 1));
+    float luma_up = sample_luma(uv, texel, ivec2(0, -1));
+    float luma_left = sample_luma(uv, texel, ivec2(-1, 0));
+    float luma_right = sample_luma(uv, texel, ivec2(1, 0));
+
+    float luma_min = min(luma_center, min(min(luma_down, luma_up), min(luma_left, luma_right)));
+    float luma_max = max(luma_center, max(max(luma_down, luma_up), max(luma_left, luma_right)));
+    float luma_range = luma_max - luma_min;
+
+    // Not an edge, or too dark to notice.
+    if (luma_range < max(edge_threshold_min, luma_max * edge_threshold)) {
+        outColor = vec4(color_center, 1.0);
+        return;
+    }
+
+    float luma_down_left = sample_luma(uv, texel, ivec2(-1, 1));
+    float luma_up_right = sample_luma(uv, texel, ivec2(1, -1));
+    float luma_up_left = sample_luma(uv, texel, ivec2(-1, -1));
+    float luma_down_right = sample_luma(uv, texel, ivec2(1, 1));
+
+    float luma_down_up = luma_down + luma_up;
+    float luma_left_right = luma_left + luma_right;
+    float luma_left_corners = luma_down_left + luma_up_left;
+    float luma_down_corners = luma_down_left + luma_down_right;
+    float luma_right_corners = luma_down_right + luma_up_right;
+    float luma_up_corners = luma_up_right + luma_up_left;
+
+    float edge_horizontal = abs(-2.0 * luma_left + luma_left_corners)
+        + abs(-2.0 * luma_center + luma_down_up) * 2.0
+        + abs(-2.0 * luma_right + luma_right_corners);
+    float edge_vertical = abs(-2.0 * luma_up + luma_up_corners)
+        + abs(-2.0 * luma_center + luma_left_right) * 2.0
+        + abs(-2.0 * luma_down + luma_down_corners);
+    bool is_horizontal = edge_horizontal >= edge_vertical;
+
+    // Pick the side of the edge with the steepest gradient.
+    float luma_1 = is_horizontal ? luma_up : luma_left;
+    float luma_2 = is_horizontal ? luma_down : luma_right;
+    float gradient_1 = luma_1 - luma_center;
+    float gradient_2 = luma_2 - luma_center;
+    bool is_1_steepest = abs(gradient_1) >= abs(gradient_2);
+    float gradient_scaled = 0.25 * max(abs(gradient_1), abs(gradient_2));
+
+    float step_length = is_horizontal ? texel.y : texel.x;
+    float luma_local_average;
+    if (is_1_steepest) {
+        step_length = -step_length;
+        luma_local_average = 0.5 * (luma_1 + luma_center);
+    } else {
+        luma_local_average = 0.5 * (luma_2 + luma_center);
+    }
+
+    // Move half a pixel onto the edge.
+    vec2 current_uv = uv;
+    if (is_horizontal) {
+        current_uv.y += step_length * 0.5;
+    } else {
+        current_uv.x += step_length * 0.5;
+    }
+
+    // Walk along the edge in both directions until we reach its ends.
+    vec2 offset = is_horizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+    vec2 uv_1 = current_uv - offset * QUALITY[0];
+    vec2 uv_2 = current_uv + offset * QUALITY[0];
+
+    float luma_end_1 = sample_luma(uv_1) - luma_local_average;
+    float luma_end_2 = sample_luma(uv_2) - luma_local_average;
+    bool reached_1 = abs(luma_end_1) >= gradient_scaled;
+    bool reached_2 = abs(luma_end_2) >= gradient_scaled;
+
+    for (int i = 1; i < ITERATIONS && !(reached_1 && reached_2); ++i) {
+        if (!reached_1) {
+            uv_1 -= offset * QUALITY[i];
+            luma_end_1 = sample_luma(uv_1) - luma_local_average;
+            reached_1 = abs(luma_end_1) >= gradient_scaled;
+        }
+        if (!reached_2) {
+            uv_2 += offset * QUALITY[i];
+            luma_end_2 = sample_luma(uv_2) - luma_local_average;
+            reached_2 = abs(luma_end_2) >= gradient_scaled;
+        }
+    }
+
+    float distance_1 = is_horizontal ? (uv.x - uv_1.x) : (uv.y - uv_1.y);
+    float distance_2 = is_horizontal ? (uv_2.x - uv.x) : (uv_2.y - uv.y);
+    bool is_direction_1 = distance_1 < distance_2;
+    float distance_final = min(distance_1, distance_2);
+    float edge_thickness = distance_1 + distance_2;
+
+    // Only blend if the end we're closest to moves the other way from the center.
+    bool is_luma_center_smaller = luma_center < luma_local_average;
+    bool correct_variation = ((is_direction_1 ? luma_end_1 : luma_end_2) < 0.0) != is_luma_center_smaller;
+    float pixel_offset = correct_variation ? -distance_final / edge_thickness + 0.5 : 0.0;
+
+    // Sub-pixel aliasing, for single pixel features the edge search misses.
+    float luma_average = (1.0 / 12.0) * (2.0 * (luma_down_up + luma_left_right) + luma_left_corners + luma_right_corners);
+    float sub_pixel_offset_1 = clamp(abs(luma_average - luma_center) / luma_range, 0.0, 1.0);
+    float sub_pixel_offset_2 = (-2.0 * sub_pixel_offset_1 + 3.0) * sub_pixel_offset_1 * sub_pixel_offset_1;
+    float sub_pixel_offset = sub_pixel_offset_2 * sub_pixel_offset_2 * subpixel_quality;
+
+    pixel_offset = max(pixel_offset, sub_pixel_offset);
+
+    vec2 final_uv = uv;
+    if (is_horizontal) {
+        final_uv.y += pixel_offset * step_length;
+    } else {
+        final_uv.x += pixel_offset * step_length;
+    }
+    outColor = vec4(sample_color(final_uv), 1.0);
+}
@@ -0,0 +1,71 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler hdr_sampler;
+layout(set = 0, binding = 1) uniform texture2D hdr_map;
+
+layout(set = 1, binding = 0) uniform ToneMap {
+    // 0 is aces, 1 is reinhard, 2 is uncharted 2 and 3 is passthrough.
+    uint mode;
+    float exposure;
+    // Set when the output isn't an sRGB texture so the conversion isn't done by the hardware.
+    uint srgb_encode;
+};
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 0) out vec4 outColor;
+
+// Narkowicz 2015, "ACES Filmic Tone Mapping Curve"
+vec3 aces(vec3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+vec3 reinhard(vec3 x) {
+    return x / (1.0 + x);
+}
+
+vec3 uncharted2_curve(vec3 x) {
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 uncharted2(vec3 x) {
+    const float W = 11.2;
+    const float exposure_bias = 2.0;
+    return uncharted2_curve(x * exposure_bias) / uncharted2_curve(vec3(W));
+}
+
+vec3 linear_to_srgb(vec3 x) {
+    vec3 low = x * 12.92;
+    vec3 high = 1.055 * pow(x, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(x, vec3(0.0031308)));
+}
+
+void main() {
+    // The swap chain is the same size as the hdr framebuffer so we can fetch the texels directly.
+    vec4 hdr = texelFetch(sampler2D(hdr_map, hdr_sampler), ivec2(gl_FragCoord.xy), 0);
+    vec3 color = hdr.rgb * exposure;
+
+    if (mode == 0) {
+        color = aces(color);
+    } else if (mode == 1) {
+        color = reinhard(color);
+    } else if (mode == 2) {
+        color = uncharted2(color);
+    }
+
+    if (srgb_encode != 0) {
+        color = linear_to_srgb(clamp(color, 0.0, 1.0));
+    }
+
+    outColor = vec4(color, hdr.a);
+}
@@ -3,4 +3,11 @@ layout(set = 1, binding = 0) uniform Globals {
     vec4 camera_pos;
     mat4 view;
     mat4 projection;
+    // Seconds since the application started and since the last frame.
+    float time;
+    float delta_time;
+    // See `FogParams`.
+    vec4 fog_color;
+    // (density, start, end, mode)
+    vec4 fog_params;
 };
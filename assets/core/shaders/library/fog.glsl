@@ -0,0 +1,22 @@
+// Needs the Globals from library/common.glsl, see `FogParams`.
+
+const float FOG_LINEAR = 0.0;
+const float FOG_EXPONENTIAL = 1.0;
+
+// How much of a pixel `depth` away from the camera is fog, matches `FogParams::amount`.
+float fog_amount(float depth) {
+    float density = fog_params.x;
+    float amount;
+    if (fog_params.w == FOG_LINEAR) {
+        amount = (depth - fog_params.y) / max(fog_params.z - fog_params.y, 0.0001);
+    } else if (fog_params.w == FOG_EXPONENTIAL) {
+        amount = 1.0 - exp(-density * depth);
+    } else {
+        amount = 1.0 - exp(-(density * depth) * (density * depth));
+    }
+    return clamp(amount, 0.0, 1.0) * fog_color.a;
+}
+
+vec3 apply_fog(vec3 color, float depth) {
+    return mix(color, fog_color.rgb, fog_amount(depth));
+}
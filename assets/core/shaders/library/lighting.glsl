@@ -29,6 +29,26 @@ layout(set = 1, binding = 5) uniform textureCubeArray omni_shadow_quad_1;
 layout(set = 1, binding = 6) uniform textureCubeArray omni_shadow_quad_2;
 layout(set = 1, binding = 7) uniform textureCubeArray omni_shadow_quad_3;
 layout(set = 1, binding = 8) uniform textureCubeArray omni_shadow_quad_4;
+layout(set = 1, binding = 9) uniform texture2DArray cascade_shadow_map;
+
+layout(set = 1, binding = 10) uniform CascadeData {
+    mat4 cascade_matrices[4];
+    vec4 cascade_splits; // The view space depth where each cascade ends.
+    vec4 cascade_info; // x is the number of cascades.
+};
+
+// Returns 0.0 if the position is in the directional light's shadow and 1.0 if it's lit.
+float get_cascade_shadow(vec3 world_position, float view_depth) {
+    for (int i = 0; i < int(cascade_info.x); ++i) {
+        if (view_depth <= cascade_splits[i]) {
+            vec4 light_clip = cascade_matrices[i] * vec4(world_position, 1.0);
+            vec3 ndc = light_clip.xyz / light_clip.w;
+            vec2 uv = vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+            return texture(sampler2DArrayShadow(cascade_shadow_map, shadow_sampler), vec4(uv, i, ndc.z));
+        }
+    }
+    return 1.0;
+}
 
 DirectionalLight get_directional_light(int index) {
     return directional_lights[index];
@@ -0,0 +1,64 @@
+#version 450
+
+layout(local_size_x = 64) in;
+
+// One weight per morph target.
+layout(set = 0, binding = 0) readonly buffer Weights {
+    float weights[];
+};
+
+// MeshVertexData: position, normal, uv, tangent.
+layout(set = 0, binding = 1) readonly buffer BaseVertices {
+    float base_vertices[];
+};
+
+// Every target's deltas one after the other, laid out like MeshVertexData.
+layout(set = 0, binding = 2) readonly buffer MorphDeltas {
+    float deltas[];
+};
+
+layout(set = 0, binding = 3) writeonly buffer Vertices {
+    float vertices[];
+};
+
+const uint VERTEX_STRIDE = 12;
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    uint vertex_count = uint(base_vertices.length()) / VERTEX_STRIDE;
+    if (index >= vertex_count) {
+        return;
+    }
+
+    uint i = index * VERTEX_STRIDE;
+    vec3 position = vec3(base_vertices[i], base_vertices[i + 1], base_vertices[i + 2]);
+    vec3 normal = vec3(base_vertices[i + 3], base_vertices[i + 4], base_vertices[i + 5]);
+    vec2 uv = vec2(base_vertices[i + 6], base_vertices[i + 7]);
+    vec4 tangent = vec4(base_vertices[i + 8], base_vertices[i + 9], base_vertices[i + 10], base_vertices[i + 11]);
+
+    for (uint morph_target = 0; morph_target < uint(weights.length()); morph_target++) {
+        float weight = weights[morph_target];
+        uint d = (morph_target * vertex_count + index) * VERTEX_STRIDE;
+        position += weight * vec3(deltas[d], deltas[d + 1], deltas[d + 2]);
+        normal += weight * vec3(deltas[d + 3], deltas[d + 4], deltas[d + 5]);
+        tangent.xyz += weight * vec3(deltas[d + 8], deltas[d + 9], deltas[d + 10]);
+    }
+
+    if (length(normal) > 0.0) {
+        normal = normalize(normal);
+    }
+
+    uint o = index * VERTEX_STRIDE;
+    vertices[o] = position.x;
+    vertices[o + 1] = position.y;
+    vertices[o + 2] = position.z;
+    vertices[o + 3] = normal.x;
+    vertices[o + 4] = normal.y;
+    vertices[o + 5] = normal.z;
+    vertices[o + 6] = uv.x;
+    vertices[o + 7] = uv.y;
+    vertices[o + 8] = tangent.x;
+    vertices[o + 9] = tangent.y;
+    vertices[o + 10] = tangent.z;
+    vertices[o + 11] = tangent.w;
+}
@@ -0,0 +1 @@
+morph.comp.glsl
\ No newline at end of file
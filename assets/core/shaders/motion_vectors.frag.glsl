@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) in vec4 i_current_position;
+layout(location = 1) in vec4 i_previous_position;
+layout(location = 0) out vec2 outVelocity;
+
+void main() {
+    // How far the pixel moved since the last frame in uv units, uvs start at the top left so y is flipped.
+    vec2 current = i_current_position.xy / i_current_position.w;
+    vec2 previous = i_previous_position.xy / i_previous_position.w;
+    outVelocity = (current - previous) * vec2(0.5, -0.5);
+}
@@ -0,0 +1,2 @@
+motion_vectors.frag.glsl
+motion_vectors.vert.glsl
\ No newline at end of file
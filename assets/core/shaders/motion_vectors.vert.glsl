@@ -0,0 +1,26 @@
+#version 450
+
+layout(location = 0) in vec3 i_position;
+layout(location = 0) out vec4 o_current_position;
+layout(location = 1) out vec4 o_previous_position;
+
+layout(set = 0, binding = 0) uniform MotionVectorGlobals {
+    mat4 view_projection;
+    mat4 previous_view_projection;
+};
+
+struct MotionVectorLocals {
+    mat4 world;
+    mat4 previous_world;
+};
+
+layout(set = 1, binding = 0) readonly buffer MotionVectorLocalsBuffer {
+    MotionVectorLocals locals[];
+};
+
+void main() {
+    MotionVectorLocals transforms = locals[gl_InstanceIndex];
+    o_current_position = view_projection * transforms.world * vec4(i_position, 1.0);
+    o_previous_position = previous_view_projection * transforms.previous_world * vec4(i_position, 1.0);
+    gl_Position = o_current_position;
+}
@@ -0,0 +1,17 @@
+#ifndef PARTICLE_INCLUDES
+#define PARTICLE_INCLUDES
+
+const int GRADIENT_SAMPLES = 8;
+const int MAX_VELOCITY_FIELDS = 4;
+
+struct Particle {
+    vec4 position; // w is the age in seconds, negative until it's first emitted.
+    vec4 velocity;
+};
+
+struct SortKey {
+    float depth; // Distance to the camera, -1 for dead particles.
+    uint index;
+};
+
+#endif
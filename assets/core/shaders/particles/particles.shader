@@ -0,0 +1,2 @@
+particles.frag.glsl
+particles.vert.glsl
\ No newline at end of file
@@ -0,0 +1,60 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "particle.glsl"
+
+layout(location = 0) out vec2 o_uv;
+layout(location = 1) out vec4 o_color;
+
+layout(set = 0, binding = 0) uniform Globals {
+    mat4 view_projection;
+    vec4 camera_pos;
+    mat4 view;
+    mat4 projection;
+};
+
+layout(set = 1, binding = 0) uniform ParticleSettings {
+    mat4 emitter;
+    vec4 camera_position;
+    vec4 gravity; // w is the drag.
+    vec4 shape; // x is the shape, yzw are it's parameters.
+    vec4 timing; // (time, delta time, lifetime, start speed)
+    vec4 sizes; // (start size, end size)
+    uvec4 counts; // (max particles, sort count)
+    vec4 colors[GRADIENT_SAMPLES];
+};
+
+layout(set = 1, binding = 1) readonly buffer Particles {
+    Particle particles[];
+};
+
+layout(set = 1, binding = 2) readonly buffer SortKeys {
+    SortKey sort_keys[];
+};
+
+const vec2 CORNERS[4] = vec2[4](
+    vec2(-1.0, -1.0),
+    vec2(1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, 1.0)
+);
+
+void main() {
+    // Living particles are sorted to the front, so the instance index is the draw order.
+    Particle particle = particles[sort_keys[gl_InstanceIndex].index];
+    float t = clamp(particle.position.w / timing.z, 0.0, 1.0);
+    float size = mix(sizes.x, sizes.y, t);
+
+    // Faces the camera using the view's right and up axes.
+    vec2 corner = CORNERS[gl_VertexIndex];
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+    vec3 position = particle.position.xyz + (right * corner.x + up * corner.y) * size * 0.5;
+    gl_Position = view_projection * vec4(position, 1.0);
+
+    float gradient = t * float(GRADIENT_SAMPLES - 1);
+    int from = int(floor(gradient));
+    int to = min(from + 1, GRADIENT_SAMPLES - 1);
+    o_color = mix(colors[from], colors[to], fract(gradient));
+    o_uv = corner * 0.5 + 0.5;
+}
@@ -0,0 +1,166 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "particle.glsl"
+
+layout(local_size_x = 64) in;
+
+layout(set = 0, binding = 0) uniform ParticleSettings {
+    mat4 emitter;
+    vec4 camera_position;
+    vec4 gravity; // w is the drag.
+    vec4 shape; // x is the shape, yzw are it's parameters.
+    vec4 timing; // (time, delta time, lifetime, start speed)
+    vec4 sizes; // (start size, end size)
+    uvec4 counts; // (max particles, sort count)
+    vec4 colors[GRADIENT_SAMPLES];
+};
+
+layout(set = 0, binding = 1) buffer Particles {
+    Particle particles[];
+};
+
+layout(set = 0, binding = 2) buffer SortKeys {
+    SortKey sort_keys[];
+};
+
+// Matches the layout draw_indexed_indirect reads.
+layout(set = 0, binding = 3) buffer DrawArgs {
+    uint index_count;
+    uint instance_count;
+    uint first_index;
+    int base_vertex;
+    uint first_instance;
+};
+
+layout(set = 1, binding = 0) uniform VelocityFields {
+    vec4 field_min[MAX_VELOCITY_FIELDS]; // w is the strength.
+    vec4 field_max[MAX_VELOCITY_FIELDS];
+    uvec4 field_count;
+};
+layout(set = 1, binding = 1) uniform sampler field_sampler;
+layout(set = 1, binding = 2) uniform texture3D field_texture_0;
+layout(set = 1, binding = 3) uniform texture3D field_texture_1;
+layout(set = 1, binding = 4) uniform texture3D field_texture_2;
+layout(set = 1, binding = 5) uniform texture3D field_texture_3;
+
+const float PI = 3.14159265359;
+
+uint hash(uint x) {
+    x ^= x >> 16;
+    x *= 0x7feb352dU;
+    x ^= x >> 15;
+    x *= 0x846ca68bU;
+    x ^= x >> 16;
+    return x;
+}
+
+// A random number between 0 and 1, seed is advanced each call.
+float random(inout uint seed) {
+    seed = hash(seed);
+    return float(seed) / 4294967295.0;
+}
+
+vec3 random_direction(inout uint seed) {
+    float z = random(seed) * 2.0 - 1.0;
+    float angle = random(seed) * 2.0 * PI;
+    float r = sqrt(1.0 - z * z);
+    return vec3(r * cos(angle), r * sin(angle), z);
+}
+
+vec3 sample_field(int index, vec3 uvw) {
+    if (index == 0) {
+        return textureLod(sampler3D(field_texture_0, field_sampler), uvw, 0.0).xyz;
+    } else if (index == 1) {
+        return textureLod(sampler3D(field_texture_1, field_sampler), uvw, 0.0).xyz;
+    } else if (index == 2) {
+        return textureLod(sampler3D(field_texture_2, field_sampler), uvw, 0.0).xyz;
+    }
+    return textureLod(sampler3D(field_texture_3, field_sampler), uvw, 0.0).xyz;
+}
+
+// The velocity every field containing `position` adds.
+vec3 field_velocity(vec3 position) {
+    vec3 velocity = vec3(0.0);
+    for (int i = 0; i < min(int(field_count.x), MAX_VELOCITY_FIELDS); i++) {
+        vec3 uvw = (position - field_min[i].xyz) / max(field_max[i].xyz - field_min[i].xyz, vec3(0.0001));
+        if (all(greaterThanEqual(uvw, vec3(0.0))) && all(lessThanEqual(uvw, vec3(1.0)))) {
+            velocity += sample_field(i, uvw) * field_min[i].w;
+        }
+    }
+    return velocity;
+}
+
+// Picks a position and direction on the emitter in the emitter's space.
+void emit(inout uint seed, out vec3 position, out vec3 direction) {
+    int shape_type = int(shape.x);
+    if (shape_type == 1) {
+        // Sphere
+        direction = random_direction(seed);
+        position = direction * shape.y * pow(random(seed), 1.0 / 3.0);
+    } else if (shape_type == 2) {
+        // Box
+        position = (vec3(random(seed), random(seed), random(seed)) * 2.0 - 1.0) * shape.yzw;
+        direction = vec3(0.0, 1.0, 0.0);
+    } else if (shape_type == 3) {
+        // Cone
+        float angle = random(seed) * 2.0 * PI;
+        float radius = sqrt(random(seed)) * shape.z;
+        position = vec3(cos(angle) * radius, 0.0, sin(angle) * radius);
+        float spread = random(seed) * shape.y;
+        direction = vec3(cos(angle) * sin(spread), cos(spread), sin(angle) * sin(spread));
+    } else {
+        // Point
+        position = vec3(0.0);
+        direction = random_direction(seed);
+    }
+}
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= counts.y) {
+        return;
+    }
+    // Padding up to the sort count sorts after every particle.
+    if (index >= counts.x) {
+        sort_keys[index] = SortKey(-1.0, index);
+        return;
+    }
+
+    Particle particle = particles[index];
+    float delta = timing.y;
+    float lifetime = timing.z;
+    float previous_age = particle.position.w;
+    float age = previous_age + delta;
+
+    // Particles are emitted the first time their age passes 0 and again each time they die.
+    bool emitted = previous_age < 0.0 && age >= 0.0;
+    if (age >= lifetime) {
+        age = mod(age, lifetime);
+        emitted = true;
+    }
+
+    if (emitted) {
+        uint seed = hash(index ^ hash(floatBitsToUint(timing.x)));
+        vec3 position;
+        vec3 direction;
+        emit(seed, position, direction);
+        particle.position.xyz = (emitter * vec4(position, 1.0)).xyz;
+        particle.velocity.xyz = normalize(mat3(emitter) * direction) * timing.w;
+    } else if (age >= 0.0) {
+        // Euler integration with gravity and drag, velocity fields move particles without accelerating them.
+        particle.velocity.xyz += gravity.xyz * delta;
+        particle.velocity.xyz *= max(1.0 - gravity.w * delta, 0.0);
+        vec3 field = field_velocity(particle.position.xyz);
+        particle.position.xyz += (particle.velocity.xyz + field) * delta;
+    }
+    particle.position.w = age;
+    particles[index] = particle;
+
+    if (age >= 0.0) {
+        sort_keys[index] = SortKey(distance(particle.position.xyz, camera_position.xyz), index);
+        atomicAdd(instance_count, 1);
+    } else {
+        sort_keys[index] = SortKey(-1.0, index);
+    }
+}
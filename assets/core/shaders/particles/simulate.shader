@@ -0,0 +1 @@
+simulate.comp.glsl
\ No newline at end of file
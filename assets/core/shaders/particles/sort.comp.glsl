@@ -0,0 +1,34 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "particle.glsl"
+
+layout(local_size_x = 64) in;
+
+// One step of a bitonic sort, k is the size of the sequences being merged and j the compare distance.
+layout(set = 0, binding = 0) uniform SortStep {
+    uint k;
+    uint j;
+    uint count;
+};
+
+layout(set = 0, binding = 1) buffer SortKeys {
+    SortKey sort_keys[];
+};
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    uint partner = index ^ j;
+    if (index >= count || partner <= index) {
+        return;
+    }
+
+    // Sorts from farthest to closest so particles blend back to front and dead ones end up last.
+    bool descending = (index & k) == 0;
+    SortKey a = sort_keys[index];
+    SortKey b = sort_keys[partner];
+    if (descending ? a.depth < b.depth : a.depth > b.depth) {
+        sort_keys[index] = b;
+        sort_keys[partner] = a;
+    }
+}
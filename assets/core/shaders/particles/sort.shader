@@ -0,0 +1 @@
+sort.comp.glsl
\ No newline at end of file
@@ -4,12 +4,18 @@
 #include "library/lighting.glsl"
 #include "library/pbr.glsl"
 #include "library/common.glsl"
+#include "library/fog.glsl"
 #include "clustered/frustum.glsl"
 
 layout(set = 2, binding = 0) uniform Material {
     vec4 color;
     // (metallic, roughness, metallic_amount, roughness_amount)
     vec4 pbr_info;
+    // (x, y, width, height) of the texture region to sample.
+    vec4 uv_rect;
+    vec4 emissive;
+    // y is the alpha cutoff, 0.0 when the material isn't masked.
+    vec4 flags;
 };
 
 layout(set = 2, binding = 1) uniform sampler tex_sampler;
@@ -17,11 +23,34 @@ layout(set = 2, binding = 2) uniform sampler brdf_sampler;
 layout(set = 2, binding = 3) uniform texture2D main_map;
 layout(set = 2, binding = 4) uniform texture2D normal_map;
 layout(set = 2, binding = 5) uniform texture2D metallic_roughness_map;
+layout(set = 2, binding = 6) uniform texture2D emissive_map;
+
+// See `UvAnimation`.
+layout(set = 2, binding = 7) uniform UvAnimation {
+    vec2 uv_offset;
+    vec2 uv_scale;
+};
 
 layout(set = 3, binding = 0) uniform textureCube irradiance_cube_map;
 layout(set = 3, binding = 1) uniform textureCube spec_cube_map;
 layout(set = 3, binding = 2) uniform texture2D spec_brdf_map;
 
+const int MAX_SPOT_LIGHTS = 16;
+
+struct SpotLight {
+    vec4 position; // w is the range.
+    vec4 direction;
+    vec4 color; // w is intensity.
+    vec4 cone; // x is cos(inner_angle), y is cos(outer_angle).
+};
+
+layout(set = 4, binding = 0) readonly buffer Lights {
+    uvec4 light_count; // (directional, point, spot)
+    DirectionalLight all_directional_lights[4];
+    PointLight all_point_lights[MAX_LIGHTS];
+    SpotLight spot_lights[MAX_SPOT_LIGHTS];
+};
+
 layout(set = 1, binding = 2) readonly buffer Frustums {
     Frustum frustums[];
 };
@@ -58,6 +87,7 @@ uvec3 compute_froxel() {
 
 // TODO: Point-lights?
 void main() {
+    vec2 uv = uv_rect.xy + (i_uv * uv_scale + uv_offset) * uv_rect.zw;
 
     // Debug froxel code:
     // TODO: Perhaps move this into it's own shader that we can render for debugging?
@@ -79,13 +109,20 @@ void main() {
     // }
     // return;
 
-    vec3 main_color = texture(sampler2D(main_map, tex_sampler), i_uv).rgb * color.rgb;
+    vec4 main_sample = texture(sampler2D(main_map, tex_sampler), uv);
+    vec3 main_color = main_sample.rgb * color.rgb;
+    // Only used by the transparent pipeline, opaque pipelines replace the alpha.
+    float alpha = main_sample.a * color.a;
+    // Masked materials cut out pixels below the cutoff, see `PBRMaterial::alpha_cutoff`.
+    if (flags.y > 0.0 && alpha < flags.y) {
+        discard;
+    }
     
-    vec2 metallic_roughness = texture(sampler2D(metallic_roughness_map, tex_sampler), i_uv).xy;
+    vec2 metallic_roughness = texture(sampler2D(metallic_roughness_map, tex_sampler), uv).xy;
     float metallic = mix(metallic_roughness.x, pbr_info.x, pbr_info.z);
     float roughness = mix(metallic_roughness.y, pbr_info.y, pbr_info.w);
     
-    vec3 normal = texture(sampler2D(normal_map, tex_sampler), i_uv).rgb;
+    vec3 normal = texture(sampler2D(normal_map, tex_sampler), uv).rgb;
     normal = normal * 2.0 - 1.0;
     vec3 V = normalize(camera_pos.xyz - i_position.xyz);
     vec3 N = normalize(i_normal);
@@ -150,7 +187,13 @@ void main() {
             
         // add to outgoing radiance Lo
         float NdotL = max(dot(N, L), 0.0);                
-        light_acc += (kD * main_color / PI + specular) * radiance * NdotL; 
+
+        // Only the shadow casting light has a w of 1.
+        float shadow = 1.0;
+        if (light.direction.w > 0.0) {
+            shadow = get_cascade_shadow(i_position, i_view_position.z);
+        }
+        light_acc += (kD * main_color / PI + specular) * radiance * (NdotL * shadow); 
     }
 
     // Point Lighting
@@ -215,7 +258,47 @@ void main() {
         }
     }
 
+    // Spot Lighting
+    for (uint i = 0; i < light_count.z && i < MAX_SPOT_LIGHTS; ++i) {
+        SpotLight light = spot_lights[i];
+        vec3 L = light.position.xyz - i_position.xyz;
+
+        const float dist2 = dot(L, L);
+        const float range2 = light.position.w * light.position.w;
+
+        if (dist2 < range2)
+        {
+            float dist = sqrt(dist2);
+            L /= dist;
+            vec3 H = normalize(V + L);
+            vec3 radiance = light.color.xyz * light.color.w; // w is intensity
+
+            // cook-torrance brdf
+            float NDF = DistributionGGX(N, H, roughness);
+            float G   = GeometrySmith(N, V, L, roughness);
+            vec3 F    = fresnelSchlick(max(dot(H, V), 0.0), F0);
+
+            vec3 kS = F;
+            vec3 kD = vec3(1.0) - kS;
+            kD *= 1.0 - metallic;
+
+            vec3 numerator    = NDF * G * F;
+            float denominator = 4.0 * max(dot(N, V), 0.0) * max(dot(N, L), 0.0);
+            vec3 specular     = numerator / max(denominator, 0.001);
+
+            float att = saturate(1.0 - (dist2 / range2));
+            // Fades out between the inner and outer angle.
+            float cone = saturate((dot(-L, light.direction.xyz) - light.cone.y) / max(light.cone.x - light.cone.y, 0.0001));
+            radiance *= att * att * cone * cone;
+            float NdotL = max(dot(N, L), 0.0);
+
+            light_acc += (kD * main_color / PI + specular) * radiance * NdotL;
+        }
+    }
+
     vec3 color = ambient + light_acc; //Uncharted2ToneMapping(ambient + light_acc);
+    color += emissive.rgb * texture(sampler2D(emissive_map, tex_sampler), uv).rgb;
+    color = apply_fog(color, i_view_position.z);
 
-    outColor = vec4(color, 1.0);
+    outColor = vec4(color, alpha);
 }
\ No newline at end of file
@@ -15,9 +15,23 @@ layout(location = 5) out vec4 o_clip_position;
 layout(location = 6) out vec4 o_view_position;
 layout(location = 7) out vec3 o_vertex;
 
+#ifndef PUSH_CONSTANT_TRANSFORM
+#define PUSH_CONSTANT_TRANSFORM 0
+#endif
+
+// See `TransformUploadStrategy`.
+#if PUSH_CONSTANT_TRANSFORM
+layout(push_constant) uniform Locals {
+    mat4 world;
+};
+#else
 layout(set = 0, binding = 0) uniform Locals {
     mat4 world;
 };
+#endif
+
+// The depth prepass computes the same position, see `RenderSettings::use_depth_prepass`.
+invariant gl_Position;
 
 void main() {
     o_vertex = i_Pos;
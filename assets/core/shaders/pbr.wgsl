@@ -0,0 +1,139 @@
+# A WGSL example of the metallic roughness lighting in pbr.shader.
+# Only directional lights are applied, without image based lighting, point lights, shadows or normal maps.
+# Both stages are in one file, each is exported as "main".
+
+const PI : f32 = 3.14159265358979323;
+
+type Locals = [[block]] struct {
+    [[offset 0]] world : mat4x4<f32>;
+};
+
+type Globals = [[block]] struct {
+    [[offset 0]] view_projection : mat4x4<f32>;
+    [[offset 64]] camera_pos : vec4<f32>;
+    [[offset 80]] view : mat4x4<f32>;
+    [[offset 144]] projection : mat4x4<f32>;
+};
+
+type DirectionalLight = struct {
+    [[offset 0]] direction : vec4<f32>;
+    # w is the intensity.
+    [[offset 16]] color : vec4<f32>;
+};
+
+# The start of the lighting uniform, the point lights that follow aren't used.
+type LightingData = [[block]] struct {
+    [[offset 0]] cluster_count : vec4<u32>;
+    [[offset 16]] light_num : vec4<f32>;
+    [[offset 32]] directional_lights : [[stride 32]] array<DirectionalLight, 4>;
+};
+
+type Material = [[block]] struct {
+    [[offset 0]] color : vec4<f32>;
+    # (metallic, roughness, metallic_amount, roughness_amount)
+    [[offset 16]] pbr_info : vec4<f32>;
+    # (x, y, width, height) of the texture region to sample.
+    [[offset 32]] uv_rect : vec4<f32>;
+    [[offset 48]] emissive : vec4<f32>;
+};
+
+# See `UvAnimation`.
+type UvAnimation = [[block]] struct {
+    [[offset 0]] offset : vec2<f32>;
+    [[offset 8]] scale : vec2<f32>;
+};
+
+[[binding 0, set 0]] var<uniform> locals : Locals;
+[[binding 0, set 1]] var<uniform> globals : Globals;
+[[binding 1, set 1]] var<uniform> lighting : LightingData;
+
+[[location 0]] var<in> i_position : vec3<f32>;
+[[location 1]] var<in> i_normal : vec3<f32>;
+[[location 2]] var<in> i_uv : vec2<f32>;
+[[location 0]] var<out> v_uv : vec2<f32>;
+[[location 1]] var<out> v_normal : vec3<f32>;
+[[location 2]] var<out> v_position : vec3<f32>;
+[[builtin position]] var<out> o_position : vec4<f32>;
+
+fn vs_main() -> void {
+    var world_position : vec4<f32> = locals.world * vec4<f32>(i_position, 1.0);
+    v_uv = i_uv;
+    # Assumes the world matrix is uniformly scaled.
+    v_normal = (locals.world * vec4<f32>(i_normal, 0.0)).xyz;
+    v_position = world_position.xyz;
+    o_position = globals.view_projection * world_position;
+    return;
+}
+
+[[binding 0, set 2]] var<uniform> material : Material;
+[[binding 1, set 2]] var<uniform_constant> tex_sampler : sampler;
+[[binding 3, set 2]] var<uniform_constant> main_map : texture_sampled_2d<f32>;
+[[binding 5, set 2]] var<uniform_constant> metallic_roughness_map : texture_sampled_2d<f32>;
+[[binding 6, set 2]] var<uniform_constant> emissive_map : texture_sampled_2d<f32>;
+[[binding 7, set 2]] var<uniform> uv_animation : UvAnimation;
+
+[[location 0]] var<in> f_uv : vec2<f32>;
+[[location 1]] var<in> f_normal : vec3<f32>;
+[[location 2]] var<in> f_position : vec3<f32>;
+[[location 0]] var<out> o_color : vec4<f32>;
+
+fn distribution_ggx(n_dot_h : f32, roughness : f32) -> f32 {
+    var a2 : f32 = roughness * roughness * roughness * roughness;
+    var denominator : f32 = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+fn geometry_schlick_ggx(n_dot_v : f32, roughness : f32) -> f32 {
+    var r : f32 = roughness + 1.0;
+    var k : f32 = r * r / 8.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+fn fresnel_schlick(cos_theta : f32, f0 : vec3<f32>) -> vec3<f32> {
+    return f0 + (vec3<f32>(1.0, 1.0, 1.0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+fn fs_main() -> void {
+    var animated_uv : vec2<f32> = f_uv * uv_animation.scale + uv_animation.offset;
+    var uv : vec2<f32> = material.uv_rect.xy + animated_uv * material.uv_rect.zw;
+    var main_color : vec3<f32> = textureSample(main_map, tex_sampler, uv).rgb * material.color.rgb;
+    var metallic_roughness : vec2<f32> = textureSample(metallic_roughness_map, tex_sampler, uv).xy;
+    var metallic : f32 = mix(metallic_roughness.x, material.pbr_info.x, material.pbr_info.z);
+    var roughness : f32 = mix(metallic_roughness.y, material.pbr_info.y, material.pbr_info.w);
+
+    var n : vec3<f32> = normalize(f_normal);
+    var v : vec3<f32> = normalize(globals.camera_pos.xyz - f_position);
+    var n_dot_v : f32 = max(dot(n, v), 0.0);
+    var f0 : vec3<f32> = mix(vec3<f32>(0.04, 0.04, 0.04), main_color, metallic);
+
+    var light_acc : vec3<f32> = vec3<f32>(0.0, 0.0, 0.0);
+    var i : i32 = 0;
+    loop {
+        if (i >= i32(lighting.light_num.x) || i >= 4) {
+            break;
+        }
+        var light : DirectionalLight = lighting.directional_lights[i];
+        var l : vec3<f32> = normalize(light.direction.xyz);
+        var h : vec3<f32> = normalize(v + l);
+        var n_dot_l : f32 = max(dot(n, l), 0.0);
+        var radiance : vec3<f32> = light.color.xyz * light.color.w;
+
+        var ndf : f32 = distribution_ggx(max(dot(n, h), 0.0), roughness);
+        var g : f32 = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+        var f : vec3<f32> = fresnel_schlick(max(dot(h, v), 0.0), f0);
+        var specular : vec3<f32> = ndf * g * f / max(4.0 * n_dot_v * n_dot_l, 0.001);
+        var k_d : vec3<f32> = (vec3<f32>(1.0, 1.0, 1.0) - f) * (1.0 - metallic);
+
+        light_acc = light_acc + (k_d * main_color / PI + specular) * radiance * n_dot_l;
+        continuing {
+            i = i + 1;
+        }
+    }
+
+    var emissive : vec3<f32> = textureSample(emissive_map, tex_sampler, uv).rgb * material.emissive.rgb;
+    o_color = vec4<f32>(light_acc + emissive, 1.0);
+    return;
+}
+
+entry_point vertex as "main" = vs_main;
+entry_point fragment as "main" = fs_main;
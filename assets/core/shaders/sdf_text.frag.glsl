@@ -0,0 +1,20 @@
+#version 450
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 1) in vec4 i_color;
+layout(location = 0) out vec4 outColor;
+
+layout(set = 1, binding = 0) uniform texture2D t_atlas;
+layout(set = 1, binding = 1) uniform sampler s_atlas;
+
+void main() {
+    // The atlas stores the distance to the glyph's outline in alpha, 0.5 is the outline.
+    // Smoothing over the distance covered by one pixel keeps the edges sharp at any size.
+    float distance = texture(sampler2D(t_atlas, s_atlas), i_uv).a;
+    float width = fwidth(distance) * 0.5;
+    float alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+    if (alpha < 0.01) {
+        discard;
+    }
+    outColor = vec4(i_color.rgb, i_color.a * alpha);
+}
@@ -0,0 +1,2 @@
+sdf_text.frag.glsl
+sdf_text.vert.glsl
\ No newline at end of file
@@ -0,0 +1,67 @@
+#version 450
+
+layout(local_size_x = 64) in;
+
+layout(set = 0, binding = 0) readonly buffer Joints {
+    mat4 joints[256];
+};
+
+// SkinnedMeshVertexData: position, normal, uv, tangent, joint indices (4 x u8), joint weights.
+layout(set = 0, binding = 1) readonly buffer SkinnedVertices {
+    float skinned_vertices[];
+};
+
+// MeshVertexData: position, normal, uv, tangent.
+layout(set = 0, binding = 2) writeonly buffer Vertices {
+    float vertices[];
+};
+
+const uint SKINNED_VERTEX_STRIDE = 17;
+const uint VERTEX_STRIDE = 12;
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= uint(skinned_vertices.length()) / SKINNED_VERTEX_STRIDE) {
+        return;
+    }
+
+    uint i = index * SKINNED_VERTEX_STRIDE;
+    vec3 position = vec3(skinned_vertices[i], skinned_vertices[i + 1], skinned_vertices[i + 2]);
+    vec3 normal = vec3(skinned_vertices[i + 3], skinned_vertices[i + 4], skinned_vertices[i + 5]);
+    vec2 uv = vec2(skinned_vertices[i + 6], skinned_vertices[i + 7]);
+    vec4 tangent = vec4(skinned_vertices[i + 8], skinned_vertices[i + 9], skinned_vertices[i + 10], skinned_vertices[i + 11]);
+
+    // The joint indices are packed as 4 bytes.
+    uint packed_joints = floatBitsToUint(skinned_vertices[i + 12]);
+    uvec4 joint_indices = uvec4(
+        packed_joints & 0xFF,
+        (packed_joints >> 8) & 0xFF,
+        (packed_joints >> 16) & 0xFF,
+        (packed_joints >> 24) & 0xFF
+    );
+    vec4 joint_weights = vec4(skinned_vertices[i + 13], skinned_vertices[i + 14], skinned_vertices[i + 15], skinned_vertices[i + 16]);
+
+    mat4 skin =
+        joint_weights.x * joints[joint_indices.x] +
+        joint_weights.y * joints[joint_indices.y] +
+        joint_weights.z * joints[joint_indices.z] +
+        joint_weights.w * joints[joint_indices.w];
+
+    position = (skin * vec4(position, 1.0)).xyz;
+    normal = normalize(mat3(skin) * normal);
+    tangent.xyz = normalize(mat3(skin) * tangent.xyz);
+
+    uint o = index * VERTEX_STRIDE;
+    vertices[o] = position.x;
+    vertices[o + 1] = position.y;
+    vertices[o + 2] = position.z;
+    vertices[o + 3] = normal.x;
+    vertices[o + 4] = normal.y;
+    vertices[o + 5] = normal.z;
+    vertices[o + 6] = uv.x;
+    vertices[o + 7] = uv.y;
+    vertices[o + 8] = tangent.x;
+    vertices[o + 9] = tangent.y;
+    vertices[o + 10] = tangent.z;
+    vertices[o + 11] = tangent.w;
+}
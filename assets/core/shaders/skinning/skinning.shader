@@ -0,0 +1 @@
+skinning.comp.glsl
\ No newline at end of file
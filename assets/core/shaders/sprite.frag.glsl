@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 1) in vec4 i_color;
+layout(location = 0) out vec4 outColor;
+
+layout(set = 1, binding = 0) uniform texture2D t_sprite;
+layout(set = 1, binding = 1) uniform sampler s_sprite;
+
+void main() {
+    vec4 color = texture(sampler2D(t_sprite, s_sprite), i_uv) * i_color;
+    // Sprites write depth, so transparent pixels would hide the sprites behind them.
+    if (color.a < 0.01) {
+        discard;
+    }
+    outColor = color;
+}
@@ -0,0 +1,2 @@
+sprite.frag.glsl
+sprite.vert.glsl
\ No newline at end of file
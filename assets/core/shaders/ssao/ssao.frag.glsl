@@ -0,0 +1,71 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "library/common.glsl"
+
+layout(set = 0, binding = 0) uniform sampler gbuffer_sampler;
+layout(set = 0, binding = 2) uniform texture2D normal_map;
+layout(set = 0, binding = 4) uniform texture2D depth_map;
+
+layout(set = 2, binding = 0) uniform Ssao {
+    vec4 samples[64];
+    vec4 settings; // x: kernel size, y: radius, z: bias
+};
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 0) out float outOcclusion;
+
+vec3 view_position(vec2 screen_uv, float depth) {
+    vec4 clip_position = vec4(screen_uv.x * 2.0 - 1.0, 1.0 - screen_uv.y * 2.0, depth, 1.0);
+    vec4 position = inverse(projection) * clip_position;
+    return position.xyz / position.w;
+}
+
+// Used to rotate the kernel per pixel, the blur pass hides the resulting noise.
+float interleaved_gradient_noise(vec2 position) {
+    return fract(52.9829189 * fract(dot(position, vec2(0.06711056, 0.00583715))));
+}
+
+void main() {
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(sampler2D(depth_map, gbuffer_sampler), coords, 0).r;
+
+    // Nothing was written here by the geometry pass.
+    if (depth >= 1.0) {
+        outOcclusion = 1.0;
+        return;
+    }
+
+    vec2 size = vec2(textureSize(sampler2D(depth_map, gbuffer_sampler), 0));
+    vec3 position = view_position(gl_FragCoord.xy / size, depth);
+    vec3 world_normal = texelFetch(sampler2D(normal_map, gbuffer_sampler), coords, 0).rgb * 2.0 - 1.0;
+    vec3 N = normalize(mat3(view) * world_normal);
+
+    float angle = interleaved_gradient_noise(gl_FragCoord.xy) * 6.28318530718;
+    vec3 random_vec = vec3(cos(angle), sin(angle), 0.0);
+    vec3 T = normalize(random_vec - N * dot(random_vec, N));
+    vec3 B = cross(N, T);
+    mat3 TBN = mat3(T, B, N);
+
+    int kernel_size = int(settings.x);
+    float radius = settings.y;
+    float bias = settings.z;
+
+    float occlusion = 0.0;
+    for (int i = 0; i < kernel_size; ++i) {
+        vec3 sample_position = position + TBN * samples[i].xyz * radius;
+
+        vec4 offset = projection * vec4(sample_position, 1.0);
+        offset.xy /= offset.w;
+        vec2 sample_uv = vec2(offset.x * 0.5 + 0.5, 0.5 - offset.y * 0.5);
+
+        float sample_depth = texelFetch(sampler2D(depth_map, gbuffer_sampler), ivec2(sample_uv * size), 0).r;
+        float scene_depth = view_position(sample_uv, sample_depth).z;
+
+        // Fade out occluders that are far outside of the sample radius.
+        float range_check = smoothstep(0.0, 1.0, radius / abs(position.z - scene_depth));
+        occlusion += (scene_depth >= sample_position.z + bias ? 1.0 : 0.0) * range_check;
+    }
+
+    outOcclusion = 1.0 - (occlusion / float(kernel_size));
+}
@@ -0,0 +1,2 @@
+ssao.frag.glsl
+../calculations/full_screen_quad.vert.glsl
\ No newline at end of file
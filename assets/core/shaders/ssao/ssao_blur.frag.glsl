@@ -0,0 +1,21 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler ssao_sampler;
+layout(set = 0, binding = 1) uniform texture2D ssao_map;
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 0) out float outOcclusion;
+
+void main() {
+    // A 4x4 box blur to hide the noise from rotating the kernel per pixel.
+    ivec2 coords = ivec2(gl_FragCoord.xy);
+    ivec2 size = textureSize(sampler2D(ssao_map, ssao_sampler), 0) - 1;
+    float result = 0.0;
+    for (int x = -2; x < 2; ++x) {
+        for (int y = -2; y < 2; ++y) {
+            ivec2 offset = clamp(coords + ivec2(x, y), ivec2(0), size);
+            result += texelFetch(sampler2D(ssao_map, ssao_sampler), offset, 0).r;
+        }
+    }
+    outOcclusion = result / 16.0;
+}
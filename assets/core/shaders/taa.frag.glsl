@@ -0,0 +1,64 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler color_sampler;
+layout(set = 0, binding = 1) uniform texture2D current_map;
+layout(set = 0, binding = 2) uniform texture2D history_map;
+layout(set = 0, binding = 3) uniform texture2D motion_vector_map;
+layout(set = 0, binding = 4) uniform TaaUniform {
+    // x: blend alpha, y and z: the size of a texel, w: 1.0 when the history holds a previous frame.
+    vec4 settings;
+};
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 0) out vec4 outColor;
+
+// Clamping in YCoCg keeps the luma and chroma ranges apart, which gives a tighter box than rgb.
+vec3 rgb_to_ycocg(vec3 color) {
+    return vec3(
+        0.25 * color.r + 0.5 * color.g + 0.25 * color.b,
+        0.5 * color.r - 0.5 * color.b,
+        -0.25 * color.r + 0.5 * color.g - 0.25 * color.b
+    );
+}
+
+vec3 ycocg_to_rgb(vec3 color) {
+    return vec3(
+        color.x + color.y - color.z,
+        color.x + color.z,
+        color.x - color.y - color.z
+    );
+}
+
+vec3 sample_current(vec2 uv) {
+    return rgb_to_ycocg(textureLod(sampler2D(current_map, color_sampler), uv, 0.0).rgb);
+}
+
+void main() {
+    // Uvs from the fragment position match the textures, as the full screen triangle's are flipped.
+    vec2 uv = gl_FragCoord.xy * settings.yz;
+    vec3 current = sample_current(uv);
+    vec2 velocity = textureLod(sampler2D(motion_vector_map, color_sampler), uv, 0.0).xy;
+    vec2 history_uv = uv - velocity;
+
+    // Without a history, or when the pixel wasn't on screen last frame, the current frame is used as is.
+    if (settings.w < 0.5 || any(lessThan(history_uv, vec2(0.0))) || any(greaterThan(history_uv, vec2(1.0)))) {
+        outColor = vec4(ycocg_to_rgb(current), 1.0);
+        return;
+    }
+
+    // The history is clamped to the colors around the pixel so disoccluded areas don't ghost.
+    vec3 neighbourhood_min = current;
+    vec3 neighbourhood_max = current;
+    for (int y = -1; y <= 1; y++) {
+        for (int x = -1; x <= 1; x++) {
+            vec3 neighbour = sample_current(uv + vec2(x, y) * settings.yz);
+            neighbourhood_min = min(neighbourhood_min, neighbour);
+            neighbourhood_max = max(neighbourhood_max, neighbour);
+        }
+    }
+
+    vec3 history = rgb_to_ycocg(textureLod(sampler2D(history_map, color_sampler), history_uv, 0.0).rgb);
+    history = clamp(history, neighbourhood_min, neighbourhood_max);
+
+    outColor = vec4(ycocg_to_rgb(mix(history, current, settings.x)), 1.0);
+}
@@ -1,15 +1,37 @@
 #version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "library/common.glsl"
+#include "library/fog.glsl"
 
 layout(location = 0) in vec2 v_TexCoord;
+layout(location = 2) in float v_view_depth;
 layout(location = 0) out vec4 outColor;
 
+#ifndef ENABLE_VERTEX_COLOR
+#define ENABLE_VERTEX_COLOR 0
+#endif
+
+#if ENABLE_VERTEX_COLOR
+layout(location = 1) in vec4 v_color;
+#endif
+
 layout(set = 2, binding = 0) uniform Locals {
     vec3 color;
 };
 layout(set = 2, binding = 1) uniform texture2D t_Color;
 layout(set = 2, binding = 2) uniform sampler s_Color;
 
+// See `UvAnimation`.
+layout(set = 2, binding = 3) uniform UvAnimation {
+    vec2 uv_offset;
+    vec2 uv_scale;
+};
+
 void main() {
-    vec4 tex = texture(sampler2D(t_Color, s_Color), v_TexCoord);
-    outColor = tex;
+    vec4 tex = texture(sampler2D(t_Color, s_Color), v_TexCoord * uv_scale + uv_offset);
+#if ENABLE_VERTEX_COLOR
+    tex *= v_color;
+#endif
+    outColor = vec4(apply_fog(tex.rgb, v_view_depth), tex.a);
 }
\ No newline at end of file
@@ -1,14 +1,23 @@
 #version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "library/common.glsl"
 
 layout(location = 0) in vec3 i_Pos;
 layout(location = 1) in vec3 i_normal;
 layout(location = 2) in vec2 i_uv;
 layout(location = 3) in vec4 i_tangent;
 layout(location = 0) out vec2 v_TexCoord;
+layout(location = 2) out float v_view_depth;
 
-layout(set = 1, binding = 0) uniform Globals {
-    mat4 view_projection;
-};
+#ifndef ENABLE_VERTEX_COLOR
+#define ENABLE_VERTEX_COLOR 0
+#endif
+
+#if ENABLE_VERTEX_COLOR
+layout(location = 4) in vec4 i_color;
+layout(location = 1) out vec4 v_color;
+#endif
 
 layout(set = 0, binding = 0) uniform Locals {
     mat4 world;
@@ -16,5 +25,9 @@ layout(set = 0, binding = 0) uniform Locals {
 
 void main() {
     v_TexCoord = i_uv;
+#if ENABLE_VERTEX_COLOR
+    v_color = i_color;
+#endif
+    v_view_depth = (view * world * vec4(i_Pos, 1.0)).z;
     gl_Position = view_projection * world * vec4(i_Pos, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,45 @@
+# The WGSL version of unlit.shader without vertex colors.
+# Both stages are in one file, each is exported as "main".
+
+type Locals = [[block]] struct {
+    [[offset 0]] world : mat4x4<f32>;
+};
+
+# See `UvAnimation`.
+type UvAnimation = [[block]] struct {
+    [[offset 0]] offset : vec2<f32>;
+    [[offset 8]] scale : vec2<f32>;
+};
+
+type Globals = [[block]] struct {
+    [[offset 0]] view_projection : mat4x4<f32>;
+};
+
+[[binding 0, set 0]] var<uniform> locals : Locals;
+[[binding 0, set 1]] var<uniform> globals : Globals;
+
+[[location 0]] var<in> i_position : vec3<f32>;
+[[location 2]] var<in> i_uv : vec2<f32>;
+[[location 0]] var<out> v_uv : vec2<f32>;
+[[builtin position]] var<out> o_position : vec4<f32>;
+
+fn vs_main() -> void {
+    v_uv = i_uv;
+    o_position = globals.view_projection * locals.world * vec4<f32>(i_position, 1.0);
+    return;
+}
+
+[[binding 1, set 2]] var<uniform_constant> t_color : texture_sampled_2d<f32>;
+[[binding 2, set 2]] var<uniform_constant> s_color : sampler;
+[[binding 3, set 2]] var<uniform> uv_animation : UvAnimation;
+
+[[location 0]] var<in> f_uv : vec2<f32>;
+[[location 0]] var<out> o_color : vec4<f32>;
+
+fn fs_main() -> void {
+    o_color = textureSample(t_color, s_color, f_uv * uv_animation.scale + uv_animation.offset);
+    return;
+}
+
+entry_point vertex as "main" = vs_main;
+entry_point fragment as "main" = fs_main;
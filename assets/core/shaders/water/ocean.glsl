@@ -0,0 +1,23 @@
+#ifndef OCEAN_INCLUDES
+#define OCEAN_INCLUDES
+
+const float PI = 3.14159265359;
+const float GRAVITY = 9.81;
+
+layout(set = 0, binding = 0) uniform OceanSettings {
+    vec4 settings; // (patch size, amplitude, time, 0)
+    vec4 wind; // xy is the wind direction, z is the wind speed.
+    uvec4 counts; // (fft size, grid vertices per side)
+};
+
+// The wave vector of a texel, the spectrum is centered so texel fft_size / 2 is the zero frequency.
+vec2 wave_vector(ivec2 texel) {
+    float size = float(counts.x);
+    return 2.0 * PI * (vec2(texel) - size * 0.5) / settings.x;
+}
+
+vec2 complex_mul(vec2 a, vec2 b) {
+    return vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+#endif
@@ -0,0 +1,46 @@
+#version 450
+
+// One radix 2 pass of a Stockham formulated Cooley-Tukey inverse FFT, along rows or columns.
+// The passes read and write alternating images so no bit reversal is needed.
+layout(local_size_x = 8, local_size_y = 8) in;
+
+// (butterfly span, 1 if the pass runs along columns, fft size)
+layout(set = 0, binding = 0) uniform FftPass {
+    uvec4 fft_pass;
+};
+layout(set = 0, binding = 1, rgba32f) uniform readonly image2D source;
+layout(set = 0, binding = 2, rgba32f) uniform writeonly image2D destination;
+
+const float PI = 3.14159265359;
+
+vec2 complex_mul(vec2 a, vec2 b) {
+    return vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+ivec2 texel(uint index, uint line) {
+    return fft_pass.y == 1 ? ivec2(line, index) : ivec2(index, line);
+}
+
+void main() {
+    uint size = fft_pass.z;
+    uint j = gl_GlobalInvocationID.x;
+    uint line = gl_GlobalInvocationID.y;
+    if (j >= size / 2 || line >= size) {
+        return;
+    }
+
+    uint span = fft_pass.x;
+    uint k = j & (span - 1);
+    // Positive for the inverse transform.
+    float angle = PI * float(k) / float(span);
+    vec2 twiddle = vec2(cos(angle), sin(angle));
+
+    // Each texel holds two complex numbers that are transformed together.
+    vec4 a = imageLoad(source, texel(j, line));
+    vec4 b = imageLoad(source, texel(j + size / 2, line));
+    b = vec4(complex_mul(b.xy, twiddle), complex_mul(b.zw, twiddle));
+
+    uint index = (j / span) * span * 2 + k;
+    imageStore(destination, texel(index, line), a + b);
+    imageStore(destination, texel(index + span, line), a - b);
+}
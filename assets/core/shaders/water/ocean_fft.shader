@@ -0,0 +1 @@
+ocean_fft.comp.glsl
\ No newline at end of file
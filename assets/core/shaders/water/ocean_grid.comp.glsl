@@ -0,0 +1,38 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "ocean.glsl"
+
+// Tessellates the flat grid the ocean is drawn with, runs once when the ocean is created.
+layout(local_size_x = 64) in;
+
+// Matches `MeshVertexData`, written as floats since vec3 members are padded in std430.
+layout(set = 0, binding = 2) buffer Vertices {
+    float vertices[];
+};
+
+const uint VERTEX_FLOATS = 12;
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    uint side = counts.y;
+    if (index >= side * side) {
+        return;
+    }
+
+    vec2 uv = vec2(index % side, index / side) / float(side - 1);
+    vec2 position = (uv - 0.5) * settings.x;
+    float data[VERTEX_FLOATS] = float[](
+        // position
+        position.x, 0.0, position.y,
+        // normal
+        0.0, 1.0, 0.0,
+        // uv
+        uv.x, uv.y,
+        // tangent
+        1.0, 0.0, 0.0, 1.0
+    );
+    for (uint i = 0; i < VERTEX_FLOATS; i++) {
+        vertices[index * VERTEX_FLOATS + i] = data[i];
+    }
+}
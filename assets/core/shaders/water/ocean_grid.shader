@@ -0,0 +1 @@
+ocean_grid.comp.glsl
\ No newline at end of file
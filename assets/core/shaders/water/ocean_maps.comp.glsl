@@ -0,0 +1,28 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "ocean.glsl"
+
+// Turns the result of the IFFT into the height and normal maps the water is drawn with.
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 1, rgba32f) uniform readonly image2D heights;
+layout(set = 0, binding = 2, rgba16f) uniform writeonly image2D height_map;
+layout(set = 0, binding = 3, rgba16f) uniform writeonly image2D normal_map;
+
+void main() {
+    int size = int(counts.x);
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+    if (texel.x >= size || texel.y >= size) {
+        return;
+    }
+
+    // The spectrum is centered, which flips the sign of every other texel after the transform.
+    float flip = ((texel.x + texel.y) & 1) == 0 ? 1.0 : -1.0;
+    vec4 values = imageLoad(heights, texel) * flip;
+    float height = values.x;
+    vec3 normal = normalize(vec3(-values.y, 1.0, -values.z));
+
+    imageStore(height_map, texel, vec4(height, 0.0, 0.0, 1.0));
+    imageStore(normal_map, texel, vec4(normal, 1.0));
+}
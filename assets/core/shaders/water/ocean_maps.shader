@@ -0,0 +1 @@
+ocean_maps.comp.glsl
\ No newline at end of file
@@ -0,0 +1,62 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "ocean.glsl"
+
+// Fills in the initial wave amplitudes using the Phillips spectrum, runs once when the ocean is created.
+layout(local_size_x = 8, local_size_y = 8) in;
+
+// xy is h0(k), zw is the conjugate of h0(-k).
+layout(set = 0, binding = 1, rgba32f) uniform writeonly image2D initial_spectrum;
+
+uint hash(uint x) {
+    x ^= x >> 16;
+    x *= 0x7feb352dU;
+    x ^= x >> 15;
+    x *= 0x846ca68bU;
+    x ^= x >> 16;
+    return x;
+}
+
+// Two normally distributed random numbers for a texel, using the Box-Muller transform.
+vec2 gaussian(ivec2 texel) {
+    uint seed = hash(uint(texel.x) ^ hash(uint(texel.y) + 0x9e3779b9U));
+    float u1 = max(float(hash(seed)) / 4294967295.0, 1e-6);
+    float u2 = float(hash(seed + 1U)) / 4294967295.0;
+    float radius = sqrt(-2.0 * log(u1));
+    return vec2(radius * cos(2.0 * PI * u2), radius * sin(2.0 * PI * u2));
+}
+
+float phillips(vec2 k) {
+    float k_length = length(k);
+    if (k_length < 0.0001) {
+        return 0.0;
+    }
+    float wind_speed = max(wind.z, 0.0001);
+    // The largest wave the wind can create.
+    float largest_wave = wind_speed * wind_speed / GRAVITY;
+    float k_dot_wind = dot(k / k_length, normalize(wind.xy + vec2(0.0, 1e-6)));
+    float k2 = k_length * k_length;
+    // Tiny waves are damped so they don't alias.
+    float damping = largest_wave * 0.001;
+    return settings.y * exp(-1.0 / (k2 * largest_wave * largest_wave)) / (k2 * k2)
+        * k_dot_wind * k_dot_wind * exp(-k2 * damping * damping);
+}
+
+vec2 initial_amplitude(ivec2 texel) {
+    return gaussian(texel) * sqrt(phillips(wave_vector(texel)) * 0.5);
+}
+
+void main() {
+    int size = int(counts.x);
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+    if (texel.x >= size || texel.y >= size) {
+        return;
+    }
+
+    // -k is mirrored around the center of the spectrum.
+    ivec2 mirrored = (ivec2(size) - texel) % size;
+    vec2 h0 = initial_amplitude(texel);
+    vec2 h0_mirrored = initial_amplitude(mirrored);
+    imageStore(initial_spectrum, texel, vec4(h0, h0_mirrored.x, -h0_mirrored.y));
+}
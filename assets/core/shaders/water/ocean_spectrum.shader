@@ -0,0 +1 @@
+ocean_spectrum.comp.glsl
\ No newline at end of file
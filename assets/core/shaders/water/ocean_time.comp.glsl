@@ -0,0 +1,32 @@
+#version 450
+#extension GL_GOOGLE_include_directive : enable
+
+#include "ocean.glsl"
+
+// Moves the spectrum forward to the current time, the waves are then turned into heights by the IFFT.
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 1, rgba32f) uniform readonly image2D initial_spectrum;
+// xy is the height plus i times the x slope, zw is the z slope.
+// Both heights and slopes are real after the IFFT so two of them can share a complex number.
+layout(set = 0, binding = 2, rgba32f) uniform writeonly image2D spectrum;
+
+void main() {
+    int size = int(counts.x);
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+    if (texel.x >= size || texel.y >= size) {
+        return;
+    }
+
+    vec4 h0 = imageLoad(initial_spectrum, texel);
+    vec2 k = wave_vector(texel);
+    // Deep water dispersion.
+    float frequency = sqrt(GRAVITY * length(k));
+    float phase = frequency * settings.z;
+    vec2 rotation = vec2(cos(phase), sin(phase));
+    vec2 height = complex_mul(h0.xy, rotation) + complex_mul(h0.zw, vec2(rotation.x, -rotation.y));
+
+    vec2 slope_x = complex_mul(vec2(0.0, k.x), height);
+    vec2 slope_z = complex_mul(vec2(0.0, k.y), height);
+    imageStore(spectrum, texel, vec4(height.x - slope_x.y, height.y + slope_x.x, slope_z));
+}
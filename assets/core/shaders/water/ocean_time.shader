@@ -0,0 +1 @@
+ocean_time.comp.glsl
\ No newline at end of file
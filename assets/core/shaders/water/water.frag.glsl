@@ -0,0 +1,42 @@
+#version 450
+
+layout(location = 0) in vec3 i_position;
+layout(location = 1) in vec2 i_uv;
+
+layout(location = 0) out vec4 o_color;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 world;
+};
+
+layout(set = 1, binding = 0) uniform Globals {
+    mat4 view_projection;
+    vec4 camera_pos;
+    mat4 view;
+    mat4 projection;
+};
+
+layout(set = 2, binding = 0) uniform sampler ocean_sampler;
+layout(set = 2, binding = 2) uniform texture2D normal_map;
+
+// The same probe the pbr pipeline reads, it's first mip is the sharp skybox.
+layout(set = 3, binding = 1) uniform textureCube spec_cube_map;
+
+// Reflectance of water looking straight down at it.
+const float WATER_F0 = 0.02;
+const vec3 DEEP_COLOR = vec3(0.0, 0.04, 0.08);
+
+void main() {
+    vec3 normal = texture(sampler2D(normal_map, ocean_sampler), i_uv).xyz;
+    normal = normalize(mat3(world) * normal);
+    vec3 view_direction = normalize(camera_pos.xyz - i_position);
+
+    // Schlick's approximation of the Fresnel term.
+    float cos_theta = clamp(dot(normal, view_direction), 0.0, 1.0);
+    float fresnel = WATER_F0 + (1.0 - WATER_F0) * pow(1.0 - cos_theta, 5.0);
+
+    vec3 reflected = reflect(-view_direction, normal);
+    vec3 reflection = textureLod(samplerCube(spec_cube_map, ocean_sampler), reflected, 0.0).rgb;
+
+    o_color = vec4(mix(DEEP_COLOR, reflection, fresnel), 1.0);
+}
@@ -0,0 +1,2 @@
+water.frag.glsl
+water.vert.glsl
\ No newline at end of file
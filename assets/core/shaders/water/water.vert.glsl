@@ -0,0 +1,33 @@
+#version 450
+
+layout(location = 0) in vec3 i_position;
+layout(location = 1) in vec3 i_normal;
+layout(location = 2) in vec2 i_uv;
+layout(location = 3) in vec4 i_tangent;
+
+layout(location = 0) out vec3 o_position;
+layout(location = 1) out vec2 o_uv;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 world;
+};
+
+layout(set = 1, binding = 0) uniform Globals {
+    mat4 view_projection;
+    vec4 camera_pos;
+    mat4 view;
+    mat4 projection;
+};
+
+layout(set = 2, binding = 0) uniform sampler ocean_sampler;
+layout(set = 2, binding = 1) uniform texture2D height_map;
+
+void main() {
+    // The height map tiles so neighboring patches line up.
+    float height = textureLod(sampler2D(height_map, ocean_sampler), i_uv, 0.0).r;
+    vec4 position = world * vec4(i_position + vec3(0.0, height, 0.0), 1.0);
+
+    o_position = position.xyz;
+    o_uv = i_uv;
+    gl_Position = view_projection * position;
+}
@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) out vec4 outColor;
+
+// See `WIREFRAME_COLOR`.
+layout(push_constant) uniform Wireframe {
+    vec4 color;
+};
+
+void main() {
+    outColor = color;
+}
@@ -0,0 +1,2 @@
+wireframe.frag.glsl
+wireframe.vert.glsl
\ No newline at end of file
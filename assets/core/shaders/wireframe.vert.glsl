@@ -0,0 +1,13 @@
+#version 450
+
+#include "library/common.glsl"
+
+layout(location = 0) in vec3 i_Pos;
+
+layout(set = 0, binding = 0) uniform Locals {
+    mat4 world;
+};
+
+void main() {
+    gl_Position = view_projection * world * vec4(i_Pos, 1.0);
+}
@@ -10,7 +10,10 @@ use winit::{
 use harmony::scene::components::{CameraData, DirectionalLightData, LightType, Mesh, Transform};
 use harmony::scene::{resources::DeltaTime, Scene};
 use harmony::{
-    graphics::resources::{ProbeFormat, ProbeQuality},
+    graphics::{
+        renderer::RenderSettings,
+        resources::{ProbeFormat, ProbeQuality},
+    },
     AssetManager, WinitState, core::input::Input,
 };
 
@@ -24,11 +27,20 @@ const WINDOW_SIZE: WindowSize = WindowSize {
     height: 768,
 };
 
-struct AppState {}
+// How many frames the average frame time is measured over.
+const FRAME_SAMPLES: u32 = 120;
+
+struct AppState {
+    frame_time_total: f32,
+    frame_count: u32,
+}
 
 impl AppState {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            frame_time_total: 0.0,
+            frame_count: 0,
+        }
     }
 }
 
@@ -139,21 +151,26 @@ impl harmony::AppState for AppState {
         // in a friendly way. For now we only have 1 GLTF file and 1 material in the file so our material index is 0.
         // 3. The transform which allows us to render the mesh using it's world cords. This also includes stuff like
         // rotation and scale.
+        // Layers of cubes behind each other cause overdraw, which the depth prepass removes.
         let size = 50;
+        let layers = 4;
         let scale = 3.0;
         for x in 0..size {
             for y in 0..size {
-                let mut transform = Transform::new(app);
-                transform.position.x = x as f32 * scale;
-                transform.position.y = y as f32 * scale;
-                transform.update();
-                app.current_scene.world.insert(
-                    (),
-                    vec![(
-                        Mesh::new(mesh_handle.clone()),
-                        transform, // Transform
-                    )],
-                );
+                for z in 0..layers {
+                    let mut transform = Transform::new(app);
+                    transform.position.x = x as f32 * scale;
+                    transform.position.y = y as f32 * scale;
+                    transform.position.z = z as f32 * scale;
+                    transform.update();
+                    app.current_scene.world.insert(
+                        (),
+                        vec![(
+                            Mesh::new(mesh_handle.clone()),
+                            transform, // Transform
+                        )],
+                    );
+                }
             }
         }
 
@@ -163,7 +180,8 @@ impl harmony::AppState for AppState {
             app,
             "example/textures/venice_sunrise_4k.hdr",
             2048.0,
-        );
+        )
+        .unwrap();
         // Skybox needs to be added as an entity in legion. (we only should have one).
         app.current_scene.world.insert((), vec![(skybox,)]);
 
@@ -189,6 +207,7 @@ impl harmony::AppState for AppState {
                 direction: Vec3::new(0.0, 1.0, -0.5),
                 color: Vec3::new(1.0, 1.0, 1.0),
                 intensity: 5.0,
+                cast_shadow: false,
             }),
             light_transform,
         );
@@ -221,6 +240,38 @@ impl harmony::AppState for AppState {
         );
         harmony::scene::entities::camera::create(&mut app.current_scene.world, camera_data);
     }
+
+    fn update(&mut self, app: &mut harmony::Application) {
+        // Press P to toggle the depth prepass and compare frame times.
+        let toggle = app
+            .resources
+            .get::<Input>()
+            .unwrap()
+            .is_key_pressed(VirtualKeyCode::P);
+        if toggle {
+            let mut render_settings = app.resources.get_mut::<RenderSettings>().unwrap();
+            render_settings.use_depth_prepass = !render_settings.use_depth_prepass;
+            self.frame_time_total = 0.0;
+            self.frame_count = 0;
+        }
+
+        self.frame_time_total += app.frame_time;
+        self.frame_count += 1;
+        if self.frame_count == FRAME_SAMPLES {
+            let use_depth_prepass = app
+                .resources
+                .get::<RenderSettings>()
+                .unwrap()
+                .use_depth_prepass;
+            println!(
+                "Depth prepass: {}, average frame time: {:.2}ms",
+                use_depth_prepass,
+                self.frame_time_total / FRAME_SAMPLES as f32
+            );
+            self.frame_time_total = 0.0;
+            self.frame_count = 0;
+        }
+    }
 }
 
 fn main() {
@@ -117,11 +117,14 @@ impl harmony::AppState for AppState {
             app,
             "example/textures/venice_sunrise_4k.hdr",
             2048.0,
-        );
+        )
+        .unwrap();
         // Or create a realtime skybox:
         // Note: realtime skybox will use the first directional light as the sun position.
         // let skybox =
         //     harmony::graphics::material::Skybox::create_realtime();
+        // Or load a cube map skybox from six face images (skybox_px.png...skybox_nz.png):
+        // let skybox = app.resources.get::<AssetManager>().unwrap().load_skybox("skybox");
         // Skybox needs to be added as an entity in legion (we only should have one for now..).
         app.current_scene.world.insert((), vec![(skybox,)]);
 
@@ -141,6 +144,7 @@ impl harmony::AppState for AppState {
                 direction: Vec3::new(0.0, 1.0, 0.0),
                 color: Vec3::new(0.9, 0.55, 0.42),
                 intensity: 10.0,
+                cast_shadow: true,
             }),
             light_transform,
         );
@@ -212,6 +216,7 @@ fn main() {
     let mut app_state = AppState::new();
     // Call application load to have harmony load all the required assets.
     application.load(&mut app_state);
+    // Press ` in the window to toggle the performance metrics and render stats overlay.
 
     // Standard winit event loop here.
     event_loop.run(move |event, _, control_flow| {
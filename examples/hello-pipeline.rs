@@ -9,7 +9,7 @@ use harmony::{
     graphics::{
         pipeline_manager::{PipelineDesc, PipelineManager},
         resources::{BindGroup, GPUResourceManager},
-        CommandBufferQueue, CommandQueueItem,
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
     },
     AssetManager, WinitState,
 };
@@ -80,6 +80,7 @@ pub fn create_triangle_render_system() -> Box<dyn Schedulable> {
                     .push(CommandQueueItem {
                         buffer: encoder.finish(),
                         name: "triangle".to_string(),
+                        priority: RenderPriority::OPAQUE,
                     })
                     .unwrap();
             },
@@ -118,27 +119,31 @@ impl harmony::AppState for AppState {
 
         // The pipeline manager helps manage pipelines. It's somewhat smart and will cache your pipeline.
         // Remember that adding new pipelines is expensive and should be avoided at runtime.
-        pipeline_manager.add_pipeline(
-            "triangle",                   // Name of pipeline.
-            &triangle_desc,               // Pipeline description
-            vec!["skybox"], // Dependencies list as names. Uses skybox so that the triangle draws "after" the clear pass.
-            &device,        // The wgpu device.
-            &asset_manager, // asset manager from where we can load shaders.
-            gpu_resource_manager.clone(), // The gpu resource manager.
-        );
+        pipeline_manager
+            .add_pipeline(
+                "triangle",                   // Name of pipeline.
+                &triangle_desc,               // Pipeline description
+                vec!["hdr_blit"], // Dependencies list as names. Uses hdr_blit so that the triangle draws "after" the scene is copied to the frame.
+                &device,        // The wgpu device.
+                &asset_manager, // asset manager from where we can load shaders.
+                gpu_resource_manager.clone(), // The gpu resource manager.
+            )
+            .unwrap();
 
         // Pipeline manager is smart enough to not add a new pipeline even if we call pipeline_manager.add again!
         // Note: There are ways to add a variation of a pipeline by cloning the description modifying it and adding
         // it with the same name. This is useful for example if you want to render your pipeline/shader to the
         // frame buffer and to a render target(with a different format).
-        pipeline_manager.add_pipeline(
-            "triangle",                   // Name of pipeline.
-            &triangle_desc,               // Pipeline description
-            vec!["skybox"],               // Dependencies list as names.
-            &device,                      // The wgpu device.
-            &asset_manager,               // asset manager from where we can load shaders.
-            gpu_resource_manager.clone(), // The gpu resource manager.
-        );
+        pipeline_manager
+            .add_pipeline(
+                "triangle",                   // Name of pipeline.
+                &triangle_desc,               // Pipeline description
+                vec!["hdr_blit"],             // Dependencies list as names.
+                &device,                      // The wgpu device.
+                &asset_manager,               // asset manager from where we can load shaders.
+                gpu_resource_manager.clone(), // The gpu resource manager.
+            )
+            .unwrap();
 
         // Create a clear color
         let clear_color =
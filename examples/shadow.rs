@@ -112,7 +112,8 @@ impl harmony::AppState for AppState {
             app,
             "example/textures/venice_sunrise_4k.hdr",
             2048.0,
-        );
+        )
+        .unwrap();
         // Or create a realtime skybox:
         // Note: realtime skybox will use the first directional light as the sun position.
         // let skybox =
@@ -8,24 +8,34 @@ use imgui::*;
 use legion::prelude::*;
 
 use crate::{
+    assets::material::PBRMaterialRon,
     core::input::Input,
     graphics::{
         self,
         material::Skybox,
         pipeline_manager::PipelineManager,
-        resources::{CurrentRenderTarget, GPUResourceManager, ProbeManager},
+        pipelines::{
+            bloom::BloomPass, fxaa::FxaaPass, motion_vectors::MotionVectorPass, ssao::SsaoPass,
+            taa::TaaPass,
+        },
+        resources::{
+            CurrentRenderTarget, GBuffer, GPUResourceManager, HdrFramebuffer, ProbeManager,
+            ReflectionProbes, RenderTarget,
+        },
         systems::create_render_schedule_builder,
         RenderGraph, Renderer,
     },
     scene::Scene,
-    AssetManager, TransformCount,
+    AssetManager, SkinCount, TransformCount,
 };
 use graphics::{
     material::skybox::SkyboxType,
-    renderer::{DepthTexture, DEPTH_FORMAT},
+    renderer::{
+        create_depth_texture, MsaaConfig, MsaaFramebuffer, HDR_FORMAT, MSAA_PIPELINES,
+    },
     // pipelines::{LinePipelineDesc, UnlitPipelineDesc},
     CommandBufferQueue,
-    CommandQueueItem, lighting::cluster::Clustering, shadows::{ShadowCamera, OmniShadowManager},
+    CommandQueueItem, RenderPriority, lighting::cluster::Clustering, shadows::{ShadowCamera, OmniShadowManager},
 };
 use nalgebra_glm::Vec2;
 
@@ -66,6 +76,10 @@ pub struct Application {
     pub(crate) imgui_renderer: imgui_wgpu::Renderer,
     last_cursor: Option<imgui::MouseCursor>,
     last_frame: Instant,
+    // The sample count the render targets and pipelines were last created with.
+    msaa_sample_count: u32,
+    // Kept alive for the `AudioManager`, nothing plays once it's dropped.
+    audio_stream: Option<rodio::OutputStream>,
 }
 
 impl Application {
@@ -93,6 +107,22 @@ impl Application {
         // Add resources
         let mut resources = Resources::default();
         resources.insert(crate::scene::resources::DeltaTime(0.05));
+        resources.insert(crate::scene::resources::EngineTime::default());
+        resources.insert(crate::scene::resources::ActiveCamera::default());
+        resources.insert(crate::scene::resources::TweenEvents::default());
+        resources.insert(crate::scene::Bvh::default());
+        resources.insert(crate::scene::resources::BvhDirty(true));
+        resources.insert(crate::scene::VoxelWorld::default());
+        resources.insert(crate::scene::Splines::default());
+
+        let (audio_stream, audio_handle) = match rodio::OutputStream::try_default() {
+            Ok((stream, handle)) => (Some(stream), Some(handle)),
+            Err(error) => {
+                log::warn!("No audio device found, audio is disabled: {}", error);
+                (None, None)
+            }
+        };
+        resources.insert(crate::scene::AudioManager::new(audio_handle));
 
         let renderer = futures::executor::block_on(Renderer::new(window, size, &mut resources));
 
@@ -125,15 +155,43 @@ impl Application {
             1000.0,
         ));
 
-        let mut render_schedule_builder = create_render_schedule_builder();
+        // Every render system is timed, see `Profiler`.
+        let profiler = crate::core::Profiler::new();
+        let mut render_schedule_builder = create_render_schedule_builder(&profiler);
         render_schedule_builder =
             render_schedule_builder
-                .add_system(crate::graphics::systems::shadow::create())
-                .add_system(crate::graphics::systems::mesh::create());
+                .add_system(profiler.wrap(crate::graphics::systems::shadow::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::skinning::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::morph::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::particles::create_velocity_fields()))
+                .add_system(profiler.wrap(crate::graphics::systems::particles::create_simulation()))
+                .add_system(profiler.wrap(crate::graphics::systems::water::create_simulation()))
+                .add_system(profiler.wrap(crate::graphics::systems::lights::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::hi_z::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::mesh::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::motion_vectors::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::deferred::create_geometry_pass()))
+                .add_system(profiler.wrap(crate::graphics::systems::decal::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::ssao::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::deferred::create_lighting_pass()))
+                .add_system(profiler.wrap(crate::graphics::systems::water::create_render()))
+                .add_system(profiler.wrap(crate::graphics::systems::wireframe::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::debug_draw::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::particles::create_render()))
+                .add_system(profiler.wrap(crate::graphics::systems::sprite::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::text::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::taa::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::bloom::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::hdr::create()))
+                .add_system(profiler.wrap(crate::graphics::systems::fxaa::create()))
+                // Runs last so the motion vectors of the next frame see this frame's transforms.
+                .add_system(profiler.wrap(
+                    crate::graphics::systems::motion_vectors::create_previous_transform_update(),
+                ));
 
         for index in 0..render_systems.len() {
             let system = render_systems.remove(index);
-            render_schedule_builder = render_schedule_builder.add_system(system);
+            render_schedule_builder = render_schedule_builder.add_system(profiler.wrap(system));
         }
 
         let render_schedule = render_schedule_builder
@@ -141,8 +199,11 @@ impl Application {
             .add_thread_local_fn(graphics::systems::render::create())
             .build();
 
+        resources.insert(profiler);
         resources.insert(TransformCount(0));
+        resources.insert(SkinCount(0));
         resources.insert(CurrentRenderTarget(None));
+        resources.insert(ReflectionProbes::default());
 
         resources.insert(Input::new());
 
@@ -195,6 +256,7 @@ impl Application {
         let last_frame = Instant::now();
 
         resources.insert(crate::core::PerformanceMetrics::new());
+        resources.insert(crate::graphics::resources::RenderStats::default());
 
         Application {
             renderer,
@@ -212,6 +274,8 @@ impl Application {
             imgui_renderer,
             last_frame,
             last_cursor: None,
+            msaa_sample_count: 1,
+            audio_stream,
         }
     }
 
@@ -289,15 +353,50 @@ impl Application {
         // Global Node
         {
             let mut pipeline_manager = self.resources.get_mut::<PipelineManager>().unwrap();
-            pipeline_manager.add_node("globals", vec!["shadow"]);
+            pipeline_manager
+                .add_node("globals", vec!["shadow"])
+                .unwrap();
         }
 
         // Create new pipelines
         crate::graphics::pipelines::skybox::create(&self.resources);
         crate::graphics::pipelines::realtime_sky::create(&self.resources);
 
+        // Skinning runs before anything that draws meshes.
+        super::graphics::pipelines::skinning::create(&self.resources);
+        super::graphics::pipelines::morph::create(&self.resources);
+
         // PBR pipeline
-        super::graphics::pipelines::pbr::create(&self.resources);
+        super::graphics::pipelines::pbr::create(&mut self.resources);
+
+        // Deferred pipeline, off by default. Insert `DeferredRendering(true)` to use it.
+        super::graphics::pipelines::deferred::create(&mut self.resources);
+
+        // Decals are blended into the gbuffer with deferred rendering and added to the frame otherwise.
+        super::graphics::pipelines::decal::create(&mut self.resources);
+
+        // Gpu driven culling for the pbr pipeline, off by default. Set `RenderGraph::use_gpu_driven` to use it.
+        super::graphics::pipelines::gpu_cull::create(&mut self.resources);
+
+        // Occlusion culling against the previous frame's depth, off by default. Set `HiZCulling::enabled` to use it.
+        super::graphics::pipelines::hi_z::create(&mut self.resources);
+
+        // Motion vectors for effects like temporal anti aliasing, off by default. Insert `MotionVectorRendering(true)` to render them.
+        super::graphics::pipelines::motion_vectors::create(&mut self.resources);
+
+        // The scene is rendered into a hdr framebuffer which is post processed and then copied to the swap chain.
+        // Wireframes, debug shapes, water, particles, sprites and text are drawn over the scene before post processing.
+        super::graphics::pipelines::debug_draw::create(&mut self.resources);
+        super::graphics::pipelines::wireframe::create(&self.resources);
+        super::graphics::pipelines::particles::create(&mut self.resources);
+        super::graphics::pipelines::water::create(&mut self.resources);
+        super::graphics::pipelines::sprite::create(&mut self.resources);
+        super::graphics::pipelines::text::create(&mut self.resources);
+
+        super::graphics::pipelines::hdr::create(&mut self.resources);
+        super::graphics::pipelines::taa::create(&mut self.resources);
+        super::graphics::pipelines::bloom::create(&mut self.resources);
+        super::graphics::pipelines::fxaa::create(&mut self.resources);
 
         {
             let mut asset_manager = self.resources.get_mut::<AssetManager>().unwrap();
@@ -311,7 +410,9 @@ impl Application {
             let resource_manager = self.resources.get::<Arc<GPUResourceManager>>().unwrap();
             let query = <(Write<Skybox>,)>::query();
             for (mut skybox,) in query.iter_mut(&mut self.current_scene.world) {
-                if skybox.skybox_type == SkyboxType::HdrCubemap {
+                if skybox.skybox_type == SkyboxType::HdrCubemap
+                    || skybox.skybox_type == SkyboxType::Cubemap
+                {
                     let device = self.resources.get::<Arc<wgpu::Device>>().unwrap();
                     let material_layout = resource_manager
                         .get_bind_group_layout("skybox_material")
@@ -329,6 +430,119 @@ impl Application {
         }
     }
 
+    // Recreates every render target that depends on the size of the window or the msaa sample count.
+    fn resize_render_targets(&mut self, width: u32, height: u32) {
+        let sample_count = self.msaa_sample_count;
+        let device = self.resources.get::<Arc<wgpu::Device>>().unwrap().clone();
+        let resource_manager = self
+            .resources
+            .get::<Arc<GPUResourceManager>>()
+            .unwrap()
+            .clone();
+
+        self.resources
+            .insert(create_depth_texture(&device, width, height, sample_count));
+
+        let msaa_framebuffer = if sample_count > 1 {
+            Some(RenderTarget::new_multisampled(
+                &device,
+                width,
+                height,
+                HDR_FORMAT,
+                sample_count,
+            ))
+        } else {
+            None
+        };
+        self.resources.insert(MsaaFramebuffer(msaa_framebuffer));
+
+        let gbuffer_layout = resource_manager
+            .get_bind_group_layout("gbuffer_layout")
+            .unwrap();
+        self.resources
+            .insert(GBuffer::new(&device, &gbuffer_layout, width, height));
+        self.resources
+            .insert(SsaoPass::new(&device, &resource_manager, width, height));
+        self.resources.insert(MotionVectorPass::new(
+            &device,
+            &resource_manager,
+            width,
+            height,
+        ));
+
+        // Resize the hdr framebuffer and post processing targets.
+        let hdr_texture_layout = resource_manager
+            .get_bind_group_layout("hdr_texture_layout")
+            .unwrap();
+        self.resources.insert(HdrFramebuffer::new(
+            &device,
+            &hdr_texture_layout,
+            width,
+            height,
+        ));
+        let taa_pass = TaaPass::new(
+            &device,
+            &resource_manager,
+            &self.resources.get::<HdrFramebuffer>().unwrap(),
+            &self.resources.get::<MotionVectorPass>().unwrap(),
+        );
+        self.resources.insert(taa_pass);
+        self.resources
+            .insert(BloomPass::new(&device, &resource_manager, width, height));
+        let format = self.resources.get::<wgpu::SwapChainDescriptor>().unwrap().format;
+        self.resources.insert(FxaaPass::new(
+            &device,
+            &resource_manager,
+            format,
+            width,
+            height,
+        ));
+
+        self.resources
+            .get::<AssetManager>()
+            .unwrap()
+            .resize_window_render_textures(width, height);
+    }
+
+    // Checks `MsaaConfig` and if the sample count changed recreates the pipelines and render targets using it.
+    fn update_msaa(&mut self) {
+        let sample_count = self.resources.get::<MsaaConfig>().unwrap().sample_count;
+        if sample_count == self.msaa_sample_count {
+            return;
+        }
+        self.msaa_sample_count = sample_count;
+
+        {
+            let mut pipeline_manager = self.resources.get_mut::<PipelineManager>().unwrap();
+            let device = self.resources.get::<Arc<wgpu::Device>>().unwrap();
+            let asset_manager = self.resources.get::<AssetManager>().unwrap();
+            let resource_manager = self.resources.get::<Arc<GPUResourceManager>>().unwrap();
+            for name in MSAA_PIPELINES.iter() {
+                // The push constant pipelines only exist on devices that support them.
+                let mut desc = match pipeline_manager.get(*name, None) {
+                    Some(pipeline) => pipeline.desc.clone(),
+                    None => continue,
+                };
+                desc.sample_count = sample_count;
+                let hash = desc.create_hash();
+                pipeline_manager
+                    .add_pipeline(
+                        *name,
+                        &desc,
+                        vec![],
+                        &device,
+                        &asset_manager,
+                        resource_manager.clone(),
+                    )
+                    .unwrap();
+                pipeline_manager.set_current_pipeline_hash(*name, hash);
+            }
+        }
+
+        let size = self.renderer.size;
+        self.resize_render_targets(size.width, size.height);
+    }
+
     /// Run's the application which means two things.
     /// 1. Update all internal state and call app_state.update()
     /// 2. Draw all rendering data to the current screen and call app_state.update()
@@ -382,6 +596,16 @@ impl Application {
                     .expect("Failed to prepare frame");
                 let mut ui = self.imgui.frame();
 
+                // Recreate render targets and pipelines if the msaa sample count changed.
+                self.update_msaa();
+
+                // Pick up any material files that changed on disk and files added to watched directories.
+                {
+                    let asset_manager = self.resources.get::<AssetManager>().unwrap();
+                    asset_manager.poll_material_reloads::<PBRMaterialRon>();
+                    asset_manager.poll();
+                }
+
                 // Store current frame buffer.
                 {
                     let output = Arc::new(self.renderer.render().output);
@@ -392,6 +616,8 @@ impl Application {
                 {
                     self.probe_manager
                         .render(&mut self.resources, &mut self.current_scene);
+                    self.probe_manager
+                        .capture_probes(&mut self.resources, &mut self.current_scene);
                 }
 
                 // Allow user to render UI stuff.
@@ -401,6 +627,13 @@ impl Application {
                     let mut performance_metrics = self.resources.get_mut::<crate::core::PerformanceMetrics>().unwrap();
                     let input = self.resources.get::<crate::core::input::Input>().unwrap();
                     performance_metrics.display(&mut ui, &input);
+                    if performance_metrics.visible {
+                        let render_stats = self
+                            .resources
+                            .get::<crate::graphics::resources::RenderStats>()
+                            .unwrap();
+                        render_stats.display(&ui);
+                    }
                 }
 
                 app_state.draw_ui(
@@ -434,6 +667,7 @@ impl Application {
                         .push(CommandQueueItem {
                             buffer: encoder.finish(),
                             name: "UI".to_string(),
+                            priority: RenderPriority::UI,
                         })
                         .unwrap();
                 }
@@ -441,6 +675,10 @@ impl Application {
                 // Next render's our scene.
                 self.render_schedule
                     .execute(&mut self.current_scene.world, &mut self.resources);
+                self.resources
+                    .get_mut::<crate::core::Profiler>()
+                    .unwrap()
+                    .update();
 
                 // We need to let the swap drop so the frame renderers.
                 let _swap_chain_output = self
@@ -466,27 +704,14 @@ impl Application {
                     self.renderer.size = *size;
                     self.renderer.swap_chain =
                         device.create_swap_chain(&self.renderer.surface, &sc_desc);
+
+                    self.resources
+                        .get_mut::<PipelineManager>()
+                        .unwrap()
+                        .notify_resize(&device, size.width, size.height);
                 }
 
-                // Resize depth buffer too
-                let depth_texture = {
-                    let device = self.resources.get::<Arc<wgpu::Device>>().unwrap();
-                    device.create_texture(&wgpu::TextureDescriptor {
-                        size: wgpu::Extent3d {
-                            width: size.width,
-                            height: size.height,
-                            depth: 1,
-                        },
-                        mip_level_count: 1,
-                        sample_count: 1,
-                        dimension: wgpu::TextureDimension::D2,
-                        format: DEPTH_FORMAT,
-                        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-                        label: None,
-                    })
-                };
-                self.resources
-                    .insert(DepthTexture(depth_texture.create_default_view()));
+                self.resize_render_targets(size.width, size.height);
 
                 app_state.resize(self);
             }
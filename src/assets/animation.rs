@@ -0,0 +1,159 @@
+use super::skeleton::{JointTransform, Skeleton};
+use nalgebra_glm::{Quat, Vec3};
+
+/// How a channel blends between keyframes.
+/// Cubic spline channels are loaded as linear, their tangents are dropped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+}
+
+/// The keyframe values of a channel, one per entry in `AnimationChannel::times`.
+#[derive(Debug, Clone)]
+pub enum AnimationTrack {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+}
+
+/// Animates a single property of one node.
+#[derive(Debug, Clone)]
+pub struct AnimationChannel {
+    /// The gltf node the channel moves. Matched to a joint through `Skeleton::joint_nodes`.
+    pub node: usize,
+    pub interpolation: Interpolation,
+    /// Keyframe times in seconds, sorted from first to last.
+    pub times: Vec<f32>,
+    pub track: AnimationTrack,
+}
+
+impl AnimationChannel {
+    /// Writes the channel's value at `time` into the joint transform.
+    /// Times before the first or after the last keyframe hold that keyframe's value.
+    pub fn sample(&self, time: f32, joint: &mut JointTransform) {
+        let (from, to, factor) = match self.keyframes(time) {
+            Some(keyframes) => keyframes,
+            None => return,
+        };
+        let factor = match self.interpolation {
+            Interpolation::Step => 0.0,
+            Interpolation::Linear => factor,
+        };
+
+        match &self.track {
+            AnimationTrack::Translation(values) => {
+                joint.translation = nalgebra_glm::lerp(&values[from], &values[to], factor);
+            }
+            AnimationTrack::Rotation(values) => {
+                joint.rotation = nalgebra_glm::quat_slerp(&values[from], &values[to], factor);
+            }
+            AnimationTrack::Scale(values) => {
+                joint.scale = nalgebra_glm::lerp(&values[from], &values[to], factor);
+            }
+        }
+    }
+
+    // Returns the keyframes on either side of `time` and how far between them it is.
+    fn keyframes(&self, time: f32) -> Option<(usize, usize, f32)> {
+        let value_count = match &self.track {
+            AnimationTrack::Translation(values) | AnimationTrack::Scale(values) => values.len(),
+            AnimationTrack::Rotation(values) => values.len(),
+        };
+        let count = self.times.len().min(value_count);
+        if count == 0 {
+            return None;
+        }
+
+        let next = self.times[..count].partition_point(|keyframe| *keyframe <= time);
+        if next == 0 {
+            return Some((0, 0, 0.0));
+        }
+        if next == count {
+            return Some((count - 1, count - 1, 0.0));
+        }
+
+        let (start, end) = (self.times[next - 1], self.times[next]);
+        let factor = if end > start {
+            (time - start) / (end - start)
+        } else {
+            0.0
+        };
+        Some((next - 1, next, factor))
+    }
+}
+
+/// An animation loaded from a gltf file, stored in `Gltf::animations`.
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub name: String,
+    /// Time of the last keyframe in seconds.
+    pub duration: f32,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl AnimationClip {
+    pub fn new(name: String, channels: Vec<AnimationChannel>) -> Self {
+        let duration = channels
+            .iter()
+            .filter_map(|channel| channel.times.last())
+            .fold(0.0, |duration: f32, time| duration.max(*time));
+        Self {
+            name,
+            duration,
+            channels,
+        }
+    }
+
+    /// Poses the skeleton's joints at `time`. Joints without a channel are left as they are in `pose`.
+    pub fn sample(&self, time: f32, skeleton: &Skeleton, pose: &mut [JointTransform]) {
+        for channel in self.channels.iter() {
+            let joint = skeleton
+                .joint_nodes
+                .iter()
+                .position(|node| *node == channel.node)
+                .and_then(|index| pose.get_mut(index));
+            if let Some(joint) = joint {
+                channel.sample(time, joint);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimationChannel, AnimationTrack, Interpolation};
+    use crate::assets::skeleton::JointTransform;
+    use nalgebra_glm::Vec3;
+
+    fn translation_channel(interpolation: Interpolation) -> AnimationChannel {
+        AnimationChannel {
+            node: 0,
+            interpolation,
+            times: vec![1.0, 2.0],
+            track: AnimationTrack::Translation(vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(2.0, 0.0, 0.0),
+            ]),
+        }
+    }
+
+    #[test]
+    fn should_interpolate_keyframes() {
+        let channel = translation_channel(Interpolation::Linear);
+        let mut joint = JointTransform::default();
+
+        channel.sample(1.5, &mut joint);
+        assert_eq!(joint.translation, Vec3::new(1.0, 0.0, 0.0));
+
+        // Outside of the keyframes the closest one is held.
+        channel.sample(0.0, &mut joint);
+        assert_eq!(joint.translation, Vec3::new(0.0, 0.0, 0.0));
+        channel.sample(3.0, &mut joint);
+        assert_eq!(joint.translation, Vec3::new(2.0, 0.0, 0.0));
+
+        let channel = translation_channel(Interpolation::Step);
+        channel.sample(1.5, &mut joint);
+        assert_eq!(joint.translation, Vec3::new(0.0, 0.0, 0.0));
+    }
+}
@@ -1,17 +1,51 @@
 use super::{
-    file_manager::{AssetHandle, FileManager},
-    material::Material,
+    audio::AudioClip,
+    directory_watcher::{AssetKind, DirectoryWatcher, WatchEvent},
+    file_manager::{AssetError, AssetHandle, FileManager},
+    font_manager::{FontManager, SdfFont},
+    lod::{generate_lod, lod_mesh_name, LodError},
+    manifest::{AssetManifest, ManifestError, PreloadedManifest},
+    material::{BlendMode, Material, PBRMaterial, PBRMaterialRon},
     material_manager::MaterialManager,
     mesh::Gltf,
     mesh_manager::MeshManager,
-    shader::Shader,
+    morph::MorphTargetError,
+    obj::{load_obj, ObjLoadError},
+    shader::{Shader, SpecializationConstant},
     shader_manager::ShaderManager,
-    texture::Texture,
+    terrain::generate_terrain,
+    texture::{RenderTextureDesc, Texture},
+    texture_atlas::TextureAtlasHandle,
     texture_manager::TextureManager,
+    voxel::{generate_voxel_chunk, VoxelFaces},
+    Image, NoiseTextureDesc,
+};
+use crate::{
+    graphics::{
+        material::{
+            skybox::{convert_hdr_panorama, PanoramaCubemap, CUBEMAP_FACES},
+            Skybox, SkyboxError,
+        },
+        resources::GPUResourceManager,
+        PipelineCache,
+    },
+    scene::{
+        components::{Material as MaterialComponent, Mesh, SkinnedMesh, Terrain, Transform},
+        VoxelWorld,
+    },
+    Application,
+};
+use legion::{
+    prelude::{Entity, Resources},
+    systems::resource::Resource,
+};
+use std::{
+    any::TypeId,
+    convert::TryFrom,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
-use crate::graphics::resources::GPUResourceManager;
-use legion::{prelude::Resources, systems::resource::Resource};
-use std::{any::TypeId, convert::TryFrom, fmt::Debug, path::PathBuf, sync::Arc};
 use walkdir::WalkDir;
 
 pub struct AssetManager {
@@ -19,12 +53,23 @@ pub struct AssetManager {
     texture_manager: Arc<TextureManager>,
     shader_manager: Arc<ShaderManager>,
     mesh_manager: Arc<MeshManager>,
+    font_manager: FontManager,
     device: Arc<wgpu::Device>,
     queue: Arc<wgpu::Queue>,
     path: PathBuf,
     gpu_resource_manager: Arc<GPUResourceManager>,
+    directory_watchers: Vec<DirectoryWatcher>,
+    // Cube maps converted by `load_hdr_panorama` keyed by the panorama's path.
+    panoramas: dashmap::DashMap<PathBuf, PanoramaCubemap>,
+    // Clips read by `load_audio` keyed by their path.
+    audio_clips: dashmap::DashMap<PathBuf, AudioClip>,
+    // The handles of manifests loaded by `preload_manifest` keyed by the manifest's path.
+    manifests: dashmap::DashMap<PathBuf, PreloadedManifest>,
 }
 
+// How many bound materials are kept in memory before the least recently used is evicted.
+const MATERIAL_CACHE_CAPACITY: usize = 512;
+
 impl AssetManager {
     pub fn new(
         path: PathBuf,
@@ -42,8 +87,10 @@ impl AssetManager {
             texture_manager.clone(),
             gpu_resource_manager.clone(),
             path.clone(),
+            MATERIAL_CACHE_CAPACITY,
         ));
         let mesh_manager = Arc::new(MeshManager::new(device.clone(), material_manager.clone()));
+        let font_manager = FontManager::new(texture_manager.clone());
 
         loaders.insert(material_manager);
         Self {
@@ -51,10 +98,15 @@ impl AssetManager {
             texture_manager,
             shader_manager,
             mesh_manager,
+            font_manager,
             device,
             queue,
             path,
             gpu_resource_manager,
+            directory_watchers: Vec::new(),
+            panoramas: dashmap::DashMap::new(),
+            audio_clips: dashmap::DashMap::new(),
+            manifests: dashmap::DashMap::new(),
         }
     }
 
@@ -76,6 +128,59 @@ impl AssetManager {
         }
     }
 
+    /// Watches a directory inside the asset directory and loads files added to it when `poll` is called.
+    /// Materials (.ron), textures (.png, .jpg) and meshes (.obj, .gltf) are supported.
+    /// Handles to removed files return `AssetError::FileNotFound`.
+    pub fn watch_directory(&mut self, path: &str) -> notify::Result<()> {
+        let watcher = DirectoryWatcher::new(&self.path, Path::new(path))?;
+        self.directory_watchers.push(watcher);
+        Ok(())
+    }
+
+    /// Loads any files that were added to watched directories since the last call. Call this once per frame.
+    pub fn poll(&self) {
+        for event in self.directory_watchers.iter().flat_map(|watcher| watcher.events()) {
+            match event {
+                WatchEvent::Created(path) => self.load_new_file(path),
+                WatchEvent::Removed(path) => self.mark_removed(path),
+            }
+        }
+    }
+
+    fn load_new_file(&self, path: PathBuf) {
+        match AssetKind::from_path(&path) {
+            Some(AssetKind::Material) => {
+                self.get_material::<PBRMaterialRon, _>(path);
+            }
+            Some(AssetKind::Texture) => {
+                self.get_texture(path);
+            }
+            Some(AssetKind::Obj) => {
+                if let Err(error) = self.load_obj(path.to_str().unwrap()) {
+                    log::warn!("Failed to load {:?}: {:?}", path, error);
+                }
+            }
+            Some(AssetKind::Gltf) => {
+                self.get_mesh(path);
+            }
+            None => {}
+        }
+    }
+
+    fn mark_removed(&self, path: PathBuf) {
+        let path = self.path.join(path);
+        match AssetKind::from_path(&path) {
+            Some(AssetKind::Material) => {
+                if let Some(loader) = self.loaders.get::<Arc<MaterialManager<PBRMaterialRon>>>() {
+                    loader.mark_removed(&path);
+                }
+            }
+            Some(AssetKind::Texture) => self.texture_manager.mark_removed(&path),
+            Some(AssetKind::Obj) | Some(AssetKind::Gltf) => self.mesh_manager.mark_removed(&path),
+            None => {}
+        }
+    }
+
     pub fn register<T: Resource + TryFrom<(PathBuf, Vec<u8>)>>(&mut self) {
         if self.loaders.contains::<FileManager<T>>() {
             log::warn!("Duplicate registration of key: {:?}", TypeId::of::<T>());
@@ -105,6 +210,7 @@ impl AssetManager {
             self.texture_manager.clone(),
             self.gpu_resource_manager.clone(),
             self.path.clone(),
+            MATERIAL_CACHE_CAPACITY,
         );
         self.loaders.insert(Arc::new(loader));
     }
@@ -134,17 +240,381 @@ impl AssetManager {
         self.texture_manager.get(path)
     }
 
+    // Registers an image created in memory as a texture, it can be retrieved with `get_texture(name)` afterwards.
+    pub fn insert_image(&self, name: &str, image: Arc<Image>) -> Arc<AssetHandle<Texture>> {
+        let path = self.path.join(name);
+        self.texture_manager.insert_image(path, image)
+    }
+
+    // Creates a texture that's only rendered to on the GPU, it can be retrieved with `get_texture(name)` afterwards.
+    pub fn get_render_texture(
+        &self,
+        name: &str,
+        desc: RenderTextureDesc,
+    ) -> Arc<AssetHandle<Texture>> {
+        let path = self.path.join(name);
+        self.texture_manager.get_render_texture(path, desc)
+    }
+
+    // Generates a noise texture on the GPU, it can be retrieved with `get_texture(name)` afterwards.
+    pub fn generate_noise(&self, name: &str, desc: NoiseTextureDesc) -> Arc<AssetHandle<Texture>> {
+        let path = self.path.join(name);
+        self.texture_manager.generate_noise(path, desc)
+    }
+
+    // True if the texture was created with `get_render_texture`.
+    pub fn is_render_texture(&self, name: &str) -> bool {
+        let path = self.path.join(name);
+        self.texture_manager.is_render_texture(path)
+    }
+
+    // Recreates a render texture at a new size.
+    pub fn resize_render_texture(
+        &self,
+        name: &str,
+        width: u32,
+        height: u32,
+        device: &wgpu::Device,
+    ) {
+        let path = self.path.join(name);
+        self.texture_manager
+            .resize_render_texture(path, width, height, device);
+    }
+
+    // Resizes the render textures that follow the window's size.
+    pub(crate) fn resize_window_render_textures(&self, width: u32, height: u32) {
+        self.texture_manager
+            .resize_window_render_textures(width, height);
+    }
+
+    // Returns the pixels of a texture on the CPU, blocks until the texture is loaded.
+    pub fn get_image<K: Into<PathBuf>>(&self, path: K) -> Option<Arc<Image>> {
+        let path = self.path.join(path.into());
+        self.texture_manager.get_image(path)
+    }
+
+    // Packs the textures into a single atlas, blocks until they are loaded.
+    // The uv rects are keyed by the paths passed in.
+    pub fn get_texture_atlas(&self, paths: &[&str]) -> TextureAtlasHandle {
+        let full_paths: Vec<String> = paths
+            .iter()
+            .map(|path| self.path.join(path).to_str().unwrap().to_string())
+            .collect();
+        let full_paths: Vec<&str> = full_paths.iter().map(|path| path.as_str()).collect();
+        let handle = self.texture_manager.get_atlas(&full_paths);
+
+        let rects = paths
+            .iter()
+            .zip(full_paths.iter())
+            .filter_map(|(path, full_path)| handle.rect(full_path).map(|rect| (path.to_string(), rect)))
+            .collect();
+        TextureAtlasHandle {
+            atlas: handle.atlas.clone(),
+            rects: Arc::new(rects),
+        }
+    }
+
+    // Returns a font rasterized into a signed distance field atlas, blocks the first time the font is requested.
+    pub fn get_font<K: Into<PathBuf>>(&self, path: K) -> Result<Arc<SdfFont>, Arc<AssetError>> {
+        let path = self.path.join(path.into());
+        self.font_manager.get(path)
+    }
+
     // Instantly returns Arc<AssetHandle<Shader>> from a path.
+    // The path is a `.shader` file listing one file per stage, or a single `.wgsl` or `.spv` file holding every stage.
     pub fn get_shader<K: Into<PathBuf>>(&self, path: K) -> Arc<AssetHandle<Shader>> {
         let path = self.path.join(path.into());
         self.shader_manager.get(path)
     }
 
+    /// Shaders loaded from now on reuse the SPIR-V in `cache` instead of compiling, see `PipelineManager::set_cache`.
+    pub fn set_pipeline_cache(&self, cache: Arc<PipelineCache>) {
+        self.shader_manager.set_pipeline_cache(cache);
+    }
+
+    // Same as `get_shader` but compiles the shader with the given specialization constants.
+    pub fn get_specialized_shader<K: Into<PathBuf>>(
+        &self,
+        path: K,
+        constants: &[SpecializationConstant],
+    ) -> Arc<AssetHandle<Shader>> {
+        let path = self.path.join(path.into());
+        self.shader_manager.get_specialized(path, constants)
+    }
+
+    /// The directory every asset path is relative to.
+    pub(crate) fn asset_path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn get_mesh<K: Into<PathBuf>>(&self, path: K) -> Arc<AssetHandle<Gltf>> {
         let path = self.path.join(path.into());
         self.mesh_manager.get(path)
     }
 
+    /// Loads an obj file and registers it as a mesh so it can be retrieved with `get_mesh` using the same path.
+    /// Textures referenced by the mtl file are loaded in the background by the texture manager.
+    /// Note: This blocks until the obj file has been parsed and uploaded to the GPU.
+    pub fn load_obj(&self, path: &str) -> Result<(), ObjLoadError> {
+        let path = self.path.join(path);
+        let material_manager = self
+            .loaders
+            .get::<Arc<MaterialManager<PBRMaterialRon>>>()
+            .unwrap()
+            .clone();
+        let obj = load_obj(&self.device, &material_manager, path.clone())?;
+
+        log::info!("{:?} loaded.", path.file_name().unwrap());
+        self.mesh_manager.insert(path, obj);
+        Ok(())
+    }
+
+    /// Generates simplified versions of an already loaded mesh using vertex clustering.
+    /// Each level is the size of the clustering grid cells in model space, so larger values give simpler meshes.
+    /// Level `i` can be retrieved with `get_mesh(lod_mesh_name(name, i))`.
+    pub fn generate_mesh_lods(&self, name: &str, levels: &[f32]) -> Result<(), LodError> {
+        let path = self.path.join(name);
+        let gltf = self
+            .mesh_manager
+            .get(path.clone())
+            .get()
+            .map_err(|_| LodError::NotLoaded(path.clone()))?;
+
+        let skinned = gltf
+            .meshes
+            .iter()
+            .any(|mesh| mesh.meshes.values().any(|sub_mesh| sub_mesh.skin.is_some()));
+        if skinned {
+            return Err(LodError::Skinned(path));
+        }
+
+        if let Some(level) = levels.iter().find(|level| !(**level > 0.0)) {
+            return Err(LodError::InvalidLevel(*level));
+        }
+
+        for (i, cell_size) in levels.iter().enumerate() {
+            let lod = generate_lod(&self.device, &gltf, *cell_size);
+            self.mesh_manager
+                .insert(self.path.join(lod_mesh_name(name, i)), lod);
+        }
+        Ok(())
+    }
+
+    // A plain white material for generated meshes that weren't given one, registered under `name`.
+    fn insert_white_material(&self, name: &str) -> Arc<AssetHandle<PBRMaterial>> {
+        self.loaders
+            .get::<Arc<MaterialManager<PBRMaterialRon>>>()
+            .unwrap()
+            .insert(
+                PBRMaterialRon {
+                    main_texture: "core/white.png".to_string(),
+                    normal_texture: "core/empty_normal.png".to_string(),
+                    roughness_texture: "core/pbr_flat.png".to_string(),
+                    roughness: 1.0,
+                    metallic: 0.0,
+                    roughness_override: 1.0,
+                    metallic_override: 1.0,
+                    color: nalgebra_glm::Vec4::new(1.0, 1.0, 1.0, 1.0),
+                    uv_rect: None,
+                    emissive_color: None,
+                    emissive_texture: None,
+                    blend_mode: BlendMode::Opaque,
+                    receive_decals: true,
+                    alpha_cutoff: None,
+                },
+                self.path.join(name),
+            )
+    }
+
+    /// Builds the chunk meshes of a terrain and registers them so they can be retrieved with `get_mesh(name)`.
+    /// Mesh `i` of the returned file is chunk `i`, counted row by row along x.
+    pub fn insert_terrain(&self, name: &str, terrain: &Terrain) -> Arc<AssetHandle<Gltf>> {
+        let material = match &terrain.material {
+            Some(material) => material.clone(),
+            None => self.insert_white_material(name),
+        };
+
+        let gltf = generate_terrain(&self.device, terrain, material);
+        self.mesh_manager.insert(self.path.join(name), gltf);
+        self.get_mesh(name)
+    }
+
+    /// Uploads the faces built by the voxel mesher as a single mesh, replacing any mesh already registered under `name`.
+    /// Voxels without an entry in `world.materials` use the world's white material, which is created on first use.
+    pub(crate) fn insert_voxel_chunk(
+        &self,
+        name: &str,
+        faces: VoxelFaces,
+        world: &mut VoxelWorld,
+    ) -> Arc<AssetHandle<Gltf>> {
+        if world.default_material.is_none() {
+            world.default_material = Some(self.insert_white_material("voxel_world"));
+        }
+
+        let faces = faces
+            .into_iter()
+            .map(|(id, (vertices, indices))| {
+                let material = world
+                    .materials
+                    .get(&id)
+                    .or_else(|| world.default_material.as_ref())
+                    .unwrap()
+                    .clone();
+                (material, vertices, indices)
+            })
+            .collect();
+        let gltf = generate_voxel_chunk(&self.device, name, faces);
+        self.mesh_manager.insert(self.path.join(name), gltf);
+        self.get_mesh(name)
+    }
+
+    /// Sets the weight of a morph target on every mesh in the file that has a target with that name.
+    /// Entities with a `MorphTargetWeights` component use their own weights instead.
+    pub fn set_morph_weight(
+        &self,
+        mesh_name: &str,
+        target_name: &str,
+        weight: f32,
+    ) -> Result<(), MorphTargetError> {
+        let path = self.path.join(mesh_name);
+        let gltf = self
+            .mesh_manager
+            .get(path.clone())
+            .get()
+            .map_err(|_| MorphTargetError::NotLoaded(path))?;
+
+        let mut found = false;
+        for mesh in gltf.meshes.iter() {
+            let index = mesh
+                .morph_target_names()
+                .iter()
+                .position(|name| *name == target_name);
+            if let Some(index) = index {
+                mesh.morph_weights.write().unwrap()[index] = weight;
+                found = true;
+            }
+        }
+
+        if found {
+            Ok(())
+        } else {
+            Err(MorphTargetError::NotFound(target_name.to_string()))
+        }
+    }
+
+    /// Loads a gltf file and creates an entity for every node in the file that has a mesh attached.
+    /// Each entity is given a `Mesh`, `Material` and `Transform` component. The transform is taken from the gltf node.
+    /// Note: Unlike `get_mesh` this blocks until the gltf file has finished loading.
+    pub fn load_gltf<K: Into<PathBuf>>(app: &mut Application, path: K) -> Vec<Entity> {
+        let mesh_handle = {
+            let asset_manager = app.resources.get::<AssetManager>().unwrap();
+            asset_manager.get_mesh(path)
+        };
+
+        let gltf = futures::executor::block_on(mesh_handle.get_async());
+        if gltf.is_err() {
+            log::error!(
+                "Couldn't load gltf file: {:?} {:?}",
+                mesh_handle.handle_id,
+                gltf.err().unwrap()
+            );
+            return Vec::new();
+        }
+        let gltf = gltf.unwrap();
+
+        let mut entities = Vec::new();
+        for node in gltf.nodes.iter() {
+            if node.mesh_index.is_none() {
+                continue;
+            }
+
+            let mut transform = Transform::new(app);
+            transform.position = node.position;
+            transform.rotation = node.rotation;
+            transform.scale = node.scale;
+            transform.update();
+
+            let mesh = Mesh::new_with_index(mesh_handle.clone(), node.mesh_index.unwrap());
+            // Every primitive is drawn with its own material, the component records the first one.
+            let material_index = node.material_indices.first().copied().unwrap_or(0);
+            let material = MaterialComponent::new(material_index as u32);
+
+            let entity = app
+                .current_scene
+                .world
+                .insert((), vec![(mesh, material, transform)])[0];
+
+            // Skinned nodes get their own copy of the skeleton so they can be posed separately.
+            if let Some(skin_index) = node.skin_index {
+                let skinned_mesh = SkinnedMesh::new(app, gltf.skins[skin_index].clone());
+                app.current_scene
+                    .world
+                    .add_component(entity, skinned_mesh)
+                    .unwrap();
+            }
+
+            entities.push(entity);
+        }
+
+        entities
+    }
+
+    /// Loads a cube map skybox from six face images named `{prefix}_{face}.png` where face is one of
+    /// px, nx, py, ny, pz or nz. Ex: `load_skybox("skybox")` loads `skybox_px.png`...`skybox_nz.png`.
+    /// Note: This blocks until every face has loaded. If a face fails to load, or the faces aren't square
+    /// and the same size and format, a black clear color skybox is returned.
+    pub fn load_skybox(&self, prefix: &str) -> Skybox {
+        let mut faces = Vec::new();
+        for face in CUBEMAP_FACES.iter() {
+            let path = self.path.join(format!("{}_{}.png", prefix, face));
+            let texture_handle = self.texture_manager.get_sync(path.clone());
+            match texture_handle.get() {
+                Ok(texture) => faces.push(texture),
+                Err(error) => {
+                    log::error!("Couldn't load skybox face {:?}: {:?}", path, error);
+                    return Skybox::create_clear_color(nalgebra_glm::Vec3::zeros());
+                }
+            }
+        }
+
+        match Skybox::new_cubemap(&self.device, &self.queue, &faces) {
+            Ok(skybox) => skybox,
+            Err(error) => {
+                log::error!("Couldn't create skybox {:?}: {:?}", prefix, error);
+                Skybox::create_clear_color(nalgebra_glm::Vec3::zeros())
+            }
+        }
+    }
+
+    /// Loads an equirectangular `.hdr` image as a cube map skybox, see `Skybox::from_hdr_panorama`.
+    /// The converted cube map is cached so loading the same panorama again doesn't convert it again.
+    pub fn load_hdr_panorama(&self, path: &str) -> Result<Skybox, SkyboxError> {
+        let path = self.path.join(path);
+        let cubemap = match self.panoramas.get(&path) {
+            Some(cubemap) => cubemap.clone(),
+            None => {
+                let cubemap = convert_hdr_panorama(&self.device, &self.queue, &path)?;
+                self.panoramas.insert(path, cubemap.clone());
+                cubemap
+            }
+        };
+
+        Ok(Skybox::from_panorama_cubemap(&self.device, cubemap))
+    }
+
+    /// Loads an `.ogg` or `.wav` file, blocks the first time the file is requested.
+    /// The clip is cached so every `SpatialAudioSource` playing it shares the same bytes.
+    pub fn load_audio(&self, path: &str) -> Result<AudioClip, Arc<AssetError>> {
+        let path = self.path.join(path);
+        if let Some(clip) = self.audio_clips.get(&path) {
+            return Ok(clip.clone());
+        }
+
+        let clip = AudioClip::load(&path)?;
+        self.audio_clips.insert(path, clip.clone());
+        Ok(clip)
+    }
+
     // Instantly returns a Arc<AssetHandle<T::BindMaterialType>> from a path.
     // Note: If materials have textures they take longer to load as it'll await the loading of the textures.
     pub fn get_material<
@@ -164,6 +634,102 @@ impl AssetManager {
         loader.get(path)
     }
 
+    /// Gets several materials at once, the ones that aren't loaded yet are loaded together in a single task.
+    /// See `MaterialManager::get_batch`.
+    pub fn get_material_batch<
+        T: TryFrom<(PathBuf, Vec<u8>)> + Debug + Material + Send + Sync + 'static,
+    >(
+        &self,
+        paths: &[PathBuf],
+    ) -> Vec<Arc<AssetHandle<T::BindMaterialType>>> {
+        let paths: Vec<PathBuf> = paths.iter().map(|path| self.path.join(path)).collect();
+        let loader = self.loaders.get::<Arc<MaterialManager<T>>>();
+        if loader.is_none() {
+            panic!("Couldn't find material asset loader for the requested file.");
+        }
+
+        let loader = loader.unwrap();
+        loader.get_batch(&paths)
+    }
+
+    /// Reloads any materials of type `T` whose ron file changed on disk.
+    pub fn poll_material_reloads<
+        T: TryFrom<(PathBuf, Vec<u8>)> + Debug + Material + Send + Sync + 'static,
+    >(
+        &self,
+    ) {
+        if let Some(loader) = self.loaders.get::<Arc<MaterialManager<T>>>() {
+            loader.poll_reloads();
+        }
+    }
+
+    /// Starts loading every asset listed in an `AssetManifest` ron file, use `manifest_ready` to follow the progress.
+    /// Meshes and textures load in the background and the materials are loaded together in a single task.
+    /// Note: Obj meshes block while they're parsed, see `load_obj`.
+    pub fn preload_manifest(&self, path: &str) -> Result<(), ManifestError> {
+        let path = self.path.join(path);
+        let bytes =
+            std::fs::read(&path).map_err(|_| ManifestError::FileNotFound(path.clone()))?;
+        let manifest = AssetManifest::try_from((path.clone(), bytes))
+            .map_err(|error| ManifestError::ParseError(error))?;
+
+        let mut preloaded = PreloadedManifest::default();
+        for mesh in manifest.meshes.iter() {
+            if mesh.ends_with(".obj") {
+                if let Err(error) = self.load_obj(mesh) {
+                    log::warn!("Failed to load {:?}: {:?}", mesh, error);
+                    preloaded.failed += 1;
+                    continue;
+                }
+            }
+            preloaded.meshes.push(self.get_mesh(mesh));
+        }
+        preloaded.textures = manifest
+            .textures
+            .iter()
+            .map(|texture| self.get_texture(texture))
+            .collect();
+        let materials: Vec<PathBuf> = manifest.materials.iter().map(PathBuf::from).collect();
+        preloaded.materials = self.get_material_batch::<PBRMaterialRon>(&materials);
+
+        self.manifests.insert(path, preloaded);
+        Ok(())
+    }
+
+    /// The fraction of a preloaded manifest's assets that have finished loading, from 0.0 to 1.0.
+    /// Assets that failed to load count as finished. Returns 0.0 if `preload_manifest` wasn't called for `path`.
+    pub fn manifest_ready(&self, path: &str) -> f32 {
+        self.manifests
+            .get(&self.path.join(path))
+            .map_or(0.0, |manifest| manifest.progress())
+    }
+
+    /// Marks materials of type `T` as used this frame, see `MaterialManager::mark_used`.
+    pub(crate) fn mark_materials_used<
+        'a,
+        T: TryFrom<(PathBuf, Vec<u8>)> + Debug + Material + Send + Sync + 'static,
+    >(
+        &self,
+        paths: impl Iterator<Item = &'a PathBuf>,
+    ) {
+        if let Some(loader) = self.loaders.get::<Arc<MaterialManager<T>>>() {
+            loader.mark_used(paths);
+        }
+    }
+
+    /// Rebinds any materials of type `T` whose bind group was created with a layout other than `layout_hash`.
+    pub fn validate_materials<
+        T: TryFrom<(PathBuf, Vec<u8>)> + Debug + Material + Send + Sync + 'static,
+    >(
+        &self,
+        layout_hash: u64,
+    ) -> usize {
+        match self.loaders.get::<Arc<MaterialManager<T>>>() {
+            Some(loader) => loader.validate_materials(layout_hash),
+            None => 0,
+        }
+    }
+
     pub(crate) fn get_all_materials<
         T: TryFrom<(PathBuf, Vec<u8>)> + Debug + Material + Send + Sync + 'static,
     >(
@@ -224,7 +790,11 @@ mod tests {
             device.clone(),
             ShadowQuality::Medium
         );
-        let gpu_resource_manager = Arc::new(GPUResourceManager::new(device.clone(), &omni_manager));
+        let cascade_manager = crate::graphics::shadows::CascadeShadowManager::new(
+            device.clone(),
+            ShadowQuality::Medium
+        );
+        let gpu_resource_manager = Arc::new(GPUResourceManager::new(device.clone(), &omni_manager, &cascade_manager));
 
         let pbr_bind_group_layout = create_pbr_bindgroup_layout(device.clone());
         gpu_resource_manager.add_bind_group_layout("pbr_material_layout", pbr_bind_group_layout);
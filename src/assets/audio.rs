@@ -0,0 +1,42 @@
+use super::file_manager::AssetError;
+use std::{io::Cursor, path::Path, sync::Arc};
+
+/// The encoded bytes of an `.ogg` or `.wav` file. Clips are decoded while they play so they stay small in memory.
+#[derive(Debug, Clone)]
+pub struct AudioClip {
+    data: Arc<Vec<u8>>,
+}
+
+impl AsRef<[u8]> for AudioClip {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl AudioClip {
+    /// Reads an `.ogg` or `.wav` file, it's decoded once to check that it's valid.
+    pub fn load(path: &Path) -> Result<Self, Arc<AssetError>> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ogg") | Some("wav") => {}
+            _ => return Err(Arc::new(AssetError::InvalidData)),
+        }
+
+        let data = std::fs::read(path).map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => Arc::new(AssetError::FileNotFound),
+            _ => Arc::new(AssetError::OtherError(error)),
+        })?;
+        let clip = Self {
+            data: Arc::new(data),
+        };
+        clip.decoder()
+            .map_err(|_| Arc::new(AssetError::InvalidData))?;
+        Ok(clip)
+    }
+
+    /// Returns a source that plays the clip from the start.
+    pub fn decoder(
+        &self,
+    ) -> Result<rodio::Decoder<Cursor<AudioClip>>, rodio::decoder::DecoderError> {
+        rodio::Decoder::new(Cursor::new(self.clone()))
+    }
+}
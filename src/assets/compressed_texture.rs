@@ -0,0 +1,204 @@
+use ddsfile::{D3DFormat, Dds, DxgiFormat};
+
+/// The block compressed formats that can be loaded from DDS files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BcFormat {
+    /// RGB with 1 bit alpha, also known as DXT1.
+    Bc1,
+    /// RGBA, also known as DXT5.
+    Bc3,
+    /// Two channels, used for normal maps.
+    Bc5,
+    /// High quality RGBA.
+    Bc7,
+}
+
+impl BcFormat {
+    fn from_dds(dds: &Dds) -> Option<Self> {
+        if let Some(format) = dds.get_dxgi_format() {
+            return match format {
+                DxgiFormat::BC1_UNorm | DxgiFormat::BC1_UNorm_sRGB => Some(BcFormat::Bc1),
+                DxgiFormat::BC3_UNorm | DxgiFormat::BC3_UNorm_sRGB => Some(BcFormat::Bc3),
+                DxgiFormat::BC5_UNorm => Some(BcFormat::Bc5),
+                DxgiFormat::BC7_UNorm | DxgiFormat::BC7_UNorm_sRGB => Some(BcFormat::Bc7),
+                _ => None,
+            };
+        }
+        match dds.get_d3d_format() {
+            Some(D3DFormat::DXT1) => Some(BcFormat::Bc1),
+            Some(D3DFormat::DXT5) => Some(BcFormat::Bc3),
+            _ => None,
+        }
+    }
+
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        match self {
+            BcFormat::Bc1 => wgpu::TextureFormat::Bc1RgbaUnorm,
+            BcFormat::Bc3 => wgpu::TextureFormat::Bc3RgbaUnorm,
+            BcFormat::Bc5 => wgpu::TextureFormat::Bc5RgUnorm,
+            BcFormat::Bc7 => wgpu::TextureFormat::Bc7RgbaUnorm,
+        }
+    }
+
+    /// Bytes used by each 4x4 block of pixels.
+    pub fn block_size(&self) -> usize {
+        match self {
+            BcFormat::Bc1 => 8,
+            BcFormat::Bc3 | BcFormat::Bc5 | BcFormat::Bc7 => 16,
+        }
+    }
+
+    /// Bytes used by a mip level of the given size, partial blocks at the edges are stored whole.
+    pub fn level_size(&self, width: u32, height: u32) -> usize {
+        let blocks_wide = ((width + 3) / 4).max(1) as usize;
+        let blocks_high = ((height + 3) / 4).max(1) as usize;
+        blocks_wide * blocks_high * self.block_size()
+    }
+}
+
+/// A block compressed image and its mip levels, read from a DDS file.
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    pub width: u32,
+    pub height: u32,
+    pub format: BcFormat,
+    /// The blocks of each mip level, starting with the full size image.
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    /// Reads the first image of a DDS file. Returns None if it isn't a BC1, BC3, BC5 or BC7 file.
+    pub fn from_dds(bytes: &[u8]) -> Option<Self> {
+        let dds = Dds::read(&mut std::io::Cursor::new(bytes)).ok()?;
+        let format = BcFormat::from_dds(&dds)?;
+        let (width, height) = (dds.get_width(), dds.get_height());
+
+        let mut levels = Vec::new();
+        let mut offset = 0;
+        for level in 0..dds.get_num_mipmap_levels().max(1) {
+            let (level_width, level_height) = ((width >> level).max(1), (height >> level).max(1));
+            let size = format.level_size(level_width, level_height);
+            levels.push(dds.data.get(offset..offset + size)?.to_vec());
+            offset += size;
+        }
+
+        Some(Self {
+            width,
+            height,
+            format,
+            levels,
+        })
+    }
+
+    /// Size of a mip level in pixels.
+    pub fn level_extent(&self, level: usize) -> (u32, u32) {
+        (
+            (self.width >> level as u32).max(1),
+            (self.height >> level as u32).max(1),
+        )
+    }
+
+    /// Decodes every mip level to RGBA8 pixels, used when the GPU can't sample the compressed format.
+    pub fn decompress(&self) -> Vec<Vec<u8>> {
+        self.levels
+            .iter()
+            .enumerate()
+            .map(|(level, blocks)| {
+                let (width, height) = self.level_extent(level);
+                decompress_level(blocks, self.format, width, height)
+            })
+            .collect()
+    }
+}
+
+// Decodes a whole number of blocks and crops them to the level's size.
+fn decompress_level(blocks: &[u8], format: BcFormat, width: u32, height: u32) -> Vec<u8> {
+    let padded_width = ((width + 3) / 4 * 4) as usize;
+    let padded_height = ((height + 3) / 4 * 4) as usize;
+    let mut pixels = vec![0u32; padded_width * padded_height];
+    let decode = match format {
+        BcFormat::Bc1 => texture2ddecoder::decode_bc1,
+        BcFormat::Bc3 => texture2ddecoder::decode_bc3,
+        BcFormat::Bc5 => texture2ddecoder::decode_bc5,
+        BcFormat::Bc7 => texture2ddecoder::decode_bc7,
+    };
+    if let Err(error) = decode(blocks, padded_width, padded_height, &mut pixels) {
+        log::warn!("Couldn't decompress {:?} texture: {}", format, error);
+    }
+
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for row in pixels.chunks(padded_width).take(height as usize) {
+        for pixel in row.iter().take(width as usize) {
+            // The decoder packs pixels as BGRA.
+            let [b, g, r, a] = pixel.to_le_bytes();
+            rgba.extend_from_slice(&[r, g, b, a]);
+        }
+    }
+    rgba
+}
+
+/// Compresses images offline so they can be saved as DDS files and loaded with `TextureManager::load_dds`.
+#[cfg(feature = "texture_compressor")]
+pub struct TextureCompressor;
+
+#[cfg(feature = "texture_compressor")]
+impl TextureCompressor {
+    /// Returns the blocks of the image in `format`, the image is padded to a multiple of 4 pixels by repeating its edges.
+    pub fn compress(image: &image::DynamicImage, format: BcFormat) -> Vec<u8> {
+        let rgba = image.to_rgba();
+        let (width, height) = rgba.dimensions();
+        let (padded_width, padded_height) = ((width + 3) / 4 * 4, (height + 3) / 4 * 4);
+        let padded = image::RgbaImage::from_fn(padded_width, padded_height, |x, y| {
+            *rgba.get_pixel(x.min(width - 1), y.min(height - 1))
+        });
+
+        let surface = intel_tex::RgbaSurface {
+            data: &padded,
+            width: padded_width,
+            height: padded_height,
+            stride: padded_width * 4,
+        };
+        match format {
+            BcFormat::Bc1 => intel_tex::bc1::compress_blocks(&surface),
+            BcFormat::Bc3 => intel_tex::bc3::compress_blocks(&surface),
+            BcFormat::Bc7 => {
+                intel_tex::bc7::compress_blocks(&intel_tex::bc7::alpha_basic_settings(), &surface)
+            }
+            BcFormat::Bc5 => {
+                let rg: Vec<u8> = padded
+                    .pixels()
+                    .flat_map(|pixel| vec![pixel[0], pixel[1]])
+                    .collect();
+                intel_tex::bc5::compress_blocks(&intel_tex::RgSurface {
+                    data: &rg,
+                    width: padded_width,
+                    height: padded_height,
+                    stride: padded_width * 2,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress_level, BcFormat};
+
+    #[test]
+    fn should_size_levels_in_blocks() {
+        assert_eq!(BcFormat::Bc1.level_size(8, 8), 32);
+        assert_eq!(BcFormat::Bc7.level_size(8, 8), 64);
+        // Levels smaller than a block still take a whole block.
+        assert_eq!(BcFormat::Bc1.level_size(1, 1), 8);
+        assert_eq!(BcFormat::Bc5.level_size(6, 2), 32);
+    }
+
+    #[test]
+    fn should_decompress_bc1() {
+        // Both endpoints are pure red in RGB565 and every index picks the first endpoint.
+        let block = [0x00, 0xf8, 0x00, 0xf8, 0, 0, 0, 0];
+        let rgba = decompress_level(&block, BcFormat::Bc1, 2, 2);
+        assert_eq!(rgba.len(), 2 * 2 * 4);
+        assert_eq!(&rgba[0..4], &[255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,121 @@
+use crossbeam::channel::Receiver;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The kinds of files `AssetManager::watch_directory` knows how to load.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum AssetKind {
+    Material,
+    Texture,
+    Obj,
+    Gltf,
+}
+
+impl AssetKind {
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "ron" => Some(AssetKind::Material),
+            "png" | "jpg" | "jpeg" => Some(AssetKind::Texture),
+            "obj" => Some(AssetKind::Obj),
+            "gltf" => Some(AssetKind::Gltf),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum WatchEvent {
+    Created(PathBuf),
+    Removed(PathBuf),
+}
+
+/// Watches a directory inside the asset directory on notify's background thread.
+/// Paths are reported relative to the asset directory so they can be passed straight to the asset manager.
+pub(crate) struct DirectoryWatcher {
+    // Kept alive so the directory keeps being watched.
+    _watcher: Mutex<RecommendedWatcher>,
+    receiver: Receiver<WatchEvent>,
+}
+
+impl DirectoryWatcher {
+    pub(crate) fn new(asset_path: &Path, directory: &Path) -> notify::Result<Self> {
+        let (sender, receiver) = crossbeam::channel::unbounded();
+        let root = asset_path.join(directory);
+        let canonical_root = root.canonicalize().unwrap_or_else(|_| root.clone());
+        let event_root = root.clone();
+        let directory = directory.to_path_buf();
+        let mut watcher =
+            RecommendedWatcher::new_immediate(move |result: notify::Result<notify::Event>| {
+                let event = match result {
+                    Ok(event) => event,
+                    Err(_) => return,
+                };
+                for path in event.paths {
+                    let path = match to_relative_path(&event_root, &canonical_root, &path) {
+                        Some(relative) => directory.join(relative),
+                        None => continue,
+                    };
+                    let watch_event = match event.kind {
+                        EventKind::Create(_) => WatchEvent::Created(path),
+                        EventKind::Remove(_) => WatchEvent::Removed(path),
+                        _ => continue,
+                    };
+                    sender.send(watch_event).ok();
+                }
+            })?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            _watcher: Mutex::new(watcher),
+            receiver,
+        })
+    }
+
+    /// Returns every event received since the last call.
+    pub(crate) fn events(&self) -> Vec<WatchEvent> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+// The watcher might report absolute paths even if the root is relative.
+fn to_relative_path<'a>(root: &Path, canonical_root: &Path, path: &'a Path) -> Option<&'a Path> {
+    path.strip_prefix(canonical_root)
+        .or_else(|_| path.strip_prefix(root))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_relative_path, AssetKind};
+    use std::path::Path;
+
+    #[test]
+    fn should_detect_asset_kind() {
+        assert_eq!(AssetKind::from_path(Path::new("a/b.ron")), Some(AssetKind::Material));
+        assert_eq!(AssetKind::from_path(Path::new("b.PNG")), Some(AssetKind::Texture));
+        assert_eq!(AssetKind::from_path(Path::new("b.jpg")), Some(AssetKind::Texture));
+        assert_eq!(AssetKind::from_path(Path::new("b.obj")), Some(AssetKind::Obj));
+        assert_eq!(AssetKind::from_path(Path::new("b.gltf")), Some(AssetKind::Gltf));
+        assert_eq!(AssetKind::from_path(Path::new("b.shader")), None);
+        assert_eq!(AssetKind::from_path(Path::new("b")), None);
+    }
+
+    #[test]
+    fn should_strip_relative_and_absolute_roots() {
+        let root = Path::new("assets/models");
+        let canonical_root = Path::new("/game/assets/models");
+        assert_eq!(
+            to_relative_path(root, canonical_root, Path::new("/game/assets/models/tree/tree.gltf")),
+            Some(Path::new("tree/tree.gltf"))
+        );
+        assert_eq!(
+            to_relative_path(root, canonical_root, Path::new("assets/models/a.png")),
+            Some(Path::new("a.png"))
+        );
+        assert_eq!(to_relative_path(root, canonical_root, Path::new("/other/a.png")), None);
+    }
+}
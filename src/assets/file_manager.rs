@@ -1,13 +1,62 @@
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
-use std::{convert::TryFrom, hash::Hash, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    future::Future,
+    hash::Hash,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 pub type AssetCache<T> = Arc<dashmap::DashMap<PathBuf, Result<Arc<T>, Arc<AssetError>>>>;
 
+// Starts loading an asset into it's cache again, used by `AssetHandle::reload`.
+pub(crate) type AssetLoader = Arc<dyn Fn() + Send + Sync>;
+
+// The callbacks added with `AssetHandle::on_load` and `AssetHandle::on_error` for each path.
+// Managers share one map between every handle they give out, so a load notifies all handles to the path.
+pub(crate) type AssetCallbackMap<T> = Arc<Mutex<HashMap<PathBuf, AssetCallbacks<T>>>>;
+
+pub(crate) struct AssetCallbacks<T> {
+    on_load: Vec<Box<dyn Fn(Arc<T>) + Send>>,
+    on_error: Vec<Box<dyn Fn(Arc<AssetError>) + Send>>,
+}
+
+impl<T> Default for AssetCallbacks<T> {
+    fn default() -> Self {
+        Self {
+            on_load: Vec::new(),
+            on_error: Vec::new(),
+        }
+    }
+}
+
 /// A handle to a texture that will eventually resolve to Result<Arc<T>, Arc<AssetError>>
-#[derive(Debug, Clone)]
 pub struct AssetHandle<T> {
     pub(crate) handle_id: PathBuf,
     cache: AssetCache<T>,
+    loader: Option<AssetLoader>,
+    callbacks: AssetCallbackMap<T>,
+}
+
+impl<T> Clone for AssetHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            handle_id: self.handle_id.clone(),
+            cache: self.cache.clone(),
+            loader: self.loader.clone(),
+            callbacks: self.callbacks.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for AssetHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AssetHandle")
+            .field("handle_id", &self.handle_id)
+            .finish()
+    }
 }
 
 impl<T> Hash for AssetHandle<T> {
@@ -32,9 +81,105 @@ where
         Self {
             handle_id: id,
             cache,
+            loader: None,
+            callbacks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    // Shares the callbacks with every other handle created with the same map.
+    pub(crate) fn with_callbacks(mut self, callbacks: AssetCallbackMap<T>) -> Self {
+        self.callbacks = callbacks;
+        self
+    }
+
+    pub(crate) fn with_loader(mut self, loader: AssetLoader) -> Self {
+        self.loader = Some(loader);
+        self
+    }
+
+    /// Throws away the cached asset and loads it again, `get` returns `AssetError::Loading` until it's done.
+    /// Note: Assets created from an in-memory `Image` or on the GPU, like render textures, can't be reloaded and this does nothing.
+    pub fn reload(&self) {
+        if let Some(loader) = self.loader.as_ref() {
+            self.cache.remove(&self.handle_id);
+            loader();
+        }
+    }
+
+    /// Calls `f` once the asset has loaded instead of polling `get`.
+    /// The callback runs on the loader's thread pool, synchronize any access to ECS state yourself.
+    /// If the asset already loaded it's called right away on the current thread.
+    /// Callbacks are kept per path, so they run no matter which handle to the asset started the load.
+    pub fn on_load(&self, f: impl Fn(Arc<T>) + Send + 'static) {
+        // The cache is checked with the lock held so a load finishing in between can't miss the callback.
+        let mut callbacks = self.callbacks.lock().unwrap();
+        match self.cached() {
+            Some(Ok(asset)) => {
+                drop(callbacks);
+                f(asset);
+            }
+            _ => callbacks
+                .entry(self.handle_id.clone())
+                .or_default()
+                .on_load
+                .push(Box::new(f)),
+        }
+    }
+
+    /// Calls `f` if loading the asset fails, like `on_load` it runs on the loader's thread pool.
+    /// If loading already failed it's called right away on the current thread.
+    pub fn on_error(&self, f: impl Fn(Arc<AssetError>) + Send + 'static) {
+        let mut callbacks = self.callbacks.lock().unwrap();
+        match self.cached() {
+            Some(Err(error)) => {
+                drop(callbacks);
+                f(error);
+            }
+            _ => callbacks
+                .entry(self.handle_id.clone())
+                .or_default()
+                .on_error
+                .push(Box::new(f)),
+        }
+    }
+
+    // Stores the result of loading the asset and runs the matching callback.
+    pub(crate) fn finish(&self, result: Result<Arc<T>, Arc<AssetError>>) {
+        self.cache.insert(self.handle_id.clone(), result.clone());
+        self.notify(result);
+    }
+
+    // Runs the callbacks of the path for a result that's already in the cache.
+    // The callbacks are taken so they run at most once.
+    pub(crate) fn notify(&self, result: Result<Arc<T>, Arc<AssetError>>) {
+        let callbacks = self.callbacks.lock().unwrap().remove(&self.handle_id);
+        let callbacks = match callbacks {
+            Some(callbacks) => callbacks,
+            None => return,
+        };
+        match result {
+            Ok(asset) => {
+                for on_load in callbacks.on_load {
+                    on_load(asset.clone());
+                }
+            }
+            Err(error) => {
+                for on_error in callbacks.on_error {
+                    on_error(error.clone());
+                }
+            }
+        }
+    }
+
+    /// True once the asset has either loaded or failed to load.
+    pub fn is_resolved(&self) -> bool {
+        self.cache.contains_key(&self.handle_id)
+    }
+
+    fn cached(&self) -> Option<Result<Arc<T>, Arc<AssetError>>> {
+        self.cache.get(&self.handle_id).map(|result| result.clone())
+    }
+
     // Retreves some result from the cache which could be the requested asset if loaded.
     // Will return AssetError in other cases.
     // If the asset doesn't exist in the cache this will return AssetError::Loading
@@ -74,6 +219,17 @@ where
     }
 }
 
+// True if the cache entry was marked as missing because it's file was removed.
+pub(crate) fn is_missing<T>(cache: &AssetCache<T>, path: &PathBuf) -> bool {
+    match cache.get(path) {
+        Some(entry) => match entry.value() {
+            Err(error) => matches!(**error, AssetError::FileNotFound),
+            Ok(_) => false,
+        },
+        None => false,
+    }
+}
+
 #[derive(Debug)]
 pub enum AssetError {
     // Thrown when the file wasn't found
@@ -84,11 +240,35 @@ pub enum AssetError {
     Loading,
     // Thrown on some other IO error.
     OtherError(std::io::Error),
+    // Thrown when loading took longer than the manager's load timeout.
+    Timeout(Duration),
+}
+
+// Awaits an asset load, giving up with `AssetError::Timeout` once `timeout` has passed.
+// Without a timeout this waits for as long as the load takes.
+pub(crate) async fn load_with_timeout<T, F>(
+    timeout: Option<Duration>,
+    load: F,
+) -> Result<Arc<T>, Arc<AssetError>>
+where
+    F: Future<Output = Result<Arc<T>, Arc<AssetError>>>,
+{
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return load.await,
+    };
+
+    let start = Instant::now();
+    match async_std::future::timeout(timeout, load).await {
+        Ok(result) => result,
+        Err(_) => Err(Arc::new(AssetError::Timeout(start.elapsed()))),
+    }
 }
 
 pub struct FileManager<T> {
     pool: Arc<ThreadPool>,
     cache: AssetCache<T>,
+    callbacks: AssetCallbackMap<T>,
 }
 
 impl<T> FileManager<T>
@@ -99,50 +279,63 @@ where
         // TODO: One pool that we pass in is probably enough.
         let pool = Arc::new(ThreadPoolBuilder::new().pool_size(4).create().unwrap());
         let cache = Arc::new(dashmap::DashMap::new());
-        Self { pool, cache }
+        let callbacks = Arc::new(Mutex::new(HashMap::new()));
+        Self {
+            pool,
+            cache,
+            callbacks,
+        }
     }
 
     pub fn get<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<T>> {
         let path = path.into();
+        let handle = AssetHandle::new(path.clone(), self.cache.clone())
+            .with_callbacks(self.callbacks.clone());
 
-        let asset_handle = Arc::new(AssetHandle::new(path.clone(), self.cache.clone()));
+        let loader: AssetLoader = {
+            let pool = self.pool.clone();
+            // A handle without the loader so the loader doesn't keep itself alive.
+            let handle = handle.clone();
+            Arc::new(move || Self::load(&pool, handle.clone()))
+        };
 
         if !self.cache.contains_key(&path) {
-            let cache = self.cache.clone();
-
-            let asset_thread_handle = asset_handle.clone();
-
-            self.pool.spawn_ok(async move {
-                let file = async_std::fs::read(path.clone()).await;
-                let result = if file.is_ok() {
-                    // Do something
-                    let file = file.unwrap();
-                    match T::try_from((path.clone(), file)) {
-                        Ok(f) => {
-                            log::info!("{:?} loaded.", path.file_name().unwrap());
-                            Ok(Arc::new(f))
-                        },
-                        Err(_e) => Err(Arc::new(AssetError::InvalidData)),
-                    }
-                } else {
-                    let error = file.err().unwrap();
-                    match error.kind() {
-                        std::io::ErrorKind::NotFound => Err(Arc::new(AssetError::FileNotFound)),
-                        _ => Err(Arc::new(AssetError::OtherError(error))),
-                    }
-                };
-
-                cache.insert(asset_thread_handle.handle_id.clone(), result);
-            });
+            loader();
         }
 
-        asset_handle
+        Arc::new(handle.with_loader(loader))
+    }
+
+    fn load(pool: &ThreadPool, handle: AssetHandle<T>) {
+        pool.spawn_ok(async move {
+            let path = handle.handle_id.clone();
+            let file = async_std::fs::read(path.clone()).await;
+            let result = if file.is_ok() {
+                // Do something
+                let file = file.unwrap();
+                match T::try_from((path.clone(), file)) {
+                    Ok(f) => {
+                        log::info!("{:?} loaded.", path.file_name().unwrap());
+                        Ok(Arc::new(f))
+                    },
+                    Err(_e) => Err(Arc::new(AssetError::InvalidData)),
+                }
+            } else {
+                let error = file.err().unwrap();
+                match error.kind() {
+                    std::io::ErrorKind::NotFound => Err(Arc::new(AssetError::FileNotFound)),
+                    _ => Err(Arc::new(AssetError::OtherError(error))),
+                }
+            };
+
+            handle.finish(result);
+        });
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{AssetError, FileManager};
+    use super::{load_with_timeout, AssetError, AssetHandle, FileManager};
     use crate::assets::image::ImageFormat;
     use crate::assets::image::ImageRon;
     use crate::assets::material::PBRMaterialRon;
@@ -208,4 +401,95 @@ mod tests {
         let asset = asset_handle.get();
         assert!(asset.is_ok());
     }
+
+    #[test]
+    fn should_call_on_load_once_from_loader_thread() {
+        let cache = std::sync::Arc::new(dashmap::DashMap::new());
+        let handle = AssetHandle::<u32>::new("number".into(), cache);
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let callback_barrier = barrier.clone();
+        let callback_calls = calls.clone();
+        handle.on_load(move |asset| {
+            assert_eq!(*asset, 7);
+            callback_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            callback_barrier.wait();
+        });
+
+        let loader_handle = handle.clone();
+        let loader = std::thread::spawn(move || {
+            loader_handle.finish(Ok(std::sync::Arc::new(7)));
+            // Callbacks only run for the first result.
+            loader_handle.finish(Ok(std::sync::Arc::new(8)));
+        });
+        barrier.wait();
+        loader.join().unwrap();
+
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(*handle.get().unwrap(), 8);
+    }
+
+    #[test]
+    fn should_call_on_load_for_every_handle_in_flight() {
+        // Two handles to the same asset, like a second `get` while the first one is still loading.
+        let cache = std::sync::Arc::new(dashmap::DashMap::new());
+        let callbacks = std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new()));
+        let first = AssetHandle::<u32>::new("number".into(), cache.clone()).with_callbacks(callbacks.clone());
+        let second = AssetHandle::<u32>::new("number".into(), cache).with_callbacks(callbacks);
+        let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        for handle in [&first, &second].iter() {
+            let callback_barrier = barrier.clone();
+            let callback_calls = calls.clone();
+            handle.on_load(move |asset| {
+                assert_eq!(*asset, 7);
+                // Both run on the loader thread, the last one lets the test continue.
+                if callback_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 1 {
+                    callback_barrier.wait();
+                }
+            });
+        }
+
+        // Only the first handle is loading the asset.
+        let loader = std::thread::spawn(move || first.finish(Ok(std::sync::Arc::new(7))));
+        barrier.wait();
+        loader.join().unwrap();
+
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(*second.get().unwrap(), 7);
+    }
+
+    #[test]
+    fn should_call_on_error_for_missing_file() {
+        let file_manager = FileManager::<PBRMaterialRon>::new();
+        let asset_handle = file_manager.get("./assets/missing.ron");
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        asset_handle.on_error(move |error| {
+            sender
+                .send(matches!(*error, AssetError::FileNotFound))
+                .unwrap();
+        });
+
+        let timeout = std::time::Duration::from_secs(1);
+        assert!(receiver.recv_timeout(timeout).unwrap());
+    }
+
+    #[test]
+    fn should_time_out_slow_loads() {
+        let timeout = std::time::Duration::from_millis(10);
+        // Stands in for a loader stuck on a stalled file system.
+        let slow_load = async {
+            async_std::task::sleep(std::time::Duration::from_millis(500)).await;
+            Ok(std::sync::Arc::new(0))
+        };
+
+        let result = async_std::task::block_on(load_with_timeout(Some(timeout), slow_load));
+        assert!(match *result.err().unwrap() {
+            AssetError::Timeout(elapsed) => elapsed >= timeout,
+            _ => false,
+        });
+    }
 }
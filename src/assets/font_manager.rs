@@ -0,0 +1,350 @@
+use super::{
+    file_manager::{AssetError, AssetHandle},
+    texture::Texture,
+    texture_atlas::{shelf_pack, Rect},
+    texture_manager::TextureManager,
+    Image,
+};
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use dashmap::DashMap;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+// Glyphs are rasterized at this many pixels per em, the distance field keeps them sharp at other sizes.
+const SDF_FONT_SIZE: f32 = 48.0;
+// How many pixels the distance field reaches outside and inside of the glyph's outline.
+const SDF_SPREAD: usize = 6;
+// Empty pixels between glyphs in the atlas.
+const ATLAS_PADDING: u32 = 1;
+
+/// Where a glyph is in the atlas and how it's placed, measured in ems.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    pub uv_rect: Rect,
+    /// The bottom left corner of the glyph's quad relative to the pen position on the baseline, y points up.
+    pub offset: [f32; 2],
+    /// The size of the glyph's quad, zero for glyphs without an outline like spaces.
+    pub size: [f32; 2],
+    /// How far the pen moves after the glyph.
+    pub advance: f32,
+}
+
+/// A glyph of laid out text, positioned in ems with the top left of the text at the origin and y pointing up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphQuad {
+    /// The bottom left corner of the quad.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub uv_rect: Rect,
+}
+
+/// A font rasterized into a signed distance field atlas. The atlas stores the distance in alpha, 0.5 is the glyph's outline.
+pub struct SdfFont {
+    pub texture: Arc<AssetHandle<Texture>>,
+    pub glyphs: HashMap<char, Glyph>,
+    /// The height of the tallest glyphs above the baseline.
+    pub ascent: f32,
+    /// The distance between the baselines of two lines.
+    pub line_height: f32,
+}
+
+impl SdfFont {
+    /// Lays out the glyphs of each line left to right, lines are separated by `\n`.
+    /// Characters missing from the font are drawn as `?`.
+    pub fn layout(&self, text: &str) -> Vec<GlyphQuad> {
+        let mut quads = Vec::new();
+        let (mut x, mut y) = (0.0, -self.ascent);
+        for character in text.chars() {
+            if character == '\n' {
+                x = 0.0;
+                y -= self.line_height;
+                continue;
+            }
+
+            let glyph = match self
+                .glyphs
+                .get(&character)
+                .or_else(|| self.glyphs.get(&'?'))
+            {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            if glyph.size[0] > 0.0 && glyph.size[1] > 0.0 {
+                quads.push(GlyphQuad {
+                    position: [x + glyph.offset[0], y + glyph.offset[1]],
+                    size: glyph.size,
+                    uv_rect: glyph.uv_rect,
+                });
+            }
+            x += glyph.advance;
+        }
+        quads
+    }
+}
+
+/// Converts a glyph's coverage, one value from 0 to 1 per pixel, into a signed distance field.
+/// Each pixel stores the distance to the closest pixel on the other side of the outline, mapped so 128 is the outline,
+/// 255 is `spread` pixels inside and 0 is `spread` pixels outside.
+pub(crate) fn signed_distance_field(
+    coverage: &[f32],
+    width: usize,
+    height: usize,
+    spread: usize,
+) -> Vec<u8> {
+    let inside = |x: usize, y: usize| coverage[x + y * width] >= 0.5;
+    let spread = spread.max(1) as i64;
+
+    let mut field = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let is_inside = inside(x, y);
+            let mut closest = (spread + 1) as f32;
+            for search_y in (y as i64 - spread).max(0)..(y as i64 + spread + 1).min(height as i64) {
+                for search_x in
+                    (x as i64 - spread).max(0)..(x as i64 + spread + 1).min(width as i64)
+                {
+                    if inside(search_x as usize, search_y as usize) != is_inside {
+                        let (dx, dy) = ((search_x - x as i64) as f32, (search_y - y as i64) as f32);
+                        closest = closest.min((dx * dx + dy * dy).sqrt());
+                    }
+                }
+            }
+
+            // The outline lies half way between the two pixels.
+            let distance = closest - 0.5;
+            let signed = if is_inside { distance } else { -distance };
+            let value = 0.5 + signed / (2.0 * spread as f32);
+            field.push((value.max(0.0).min(1.0) * 255.0).round() as u8);
+        }
+    }
+    field
+}
+
+// The distance field of a single glyph in pixels, along with it's placement relative to the pen.
+struct RasterizedGlyph {
+    character: char,
+    field: Vec<u8>,
+    width: u32,
+    height: u32,
+    // The top left of the field relative to the pen, y pointing down.
+    left: f32,
+    top: f32,
+    advance: f32,
+}
+
+fn rasterize(font: &FontVec, character: char) -> RasterizedGlyph {
+    let scale = PxScale::from(SDF_FONT_SIZE);
+    let glyph_id = font.glyph_id(character);
+    let advance = font.as_scaled(scale).h_advance(glyph_id);
+
+    let outlined = match font.outline_glyph(glyph_id.with_scale(scale)) {
+        Some(outlined) => outlined,
+        None => {
+            return RasterizedGlyph {
+                character,
+                field: Vec::new(),
+                width: 0,
+                height: 0,
+                left: 0.0,
+                top: 0.0,
+                advance,
+            }
+        }
+    };
+
+    // The field reaches past the outline so it's padded on every side.
+    let bounds = outlined.px_bounds();
+    let width = bounds.width() as usize + SDF_SPREAD * 2;
+    let height = bounds.height() as usize + SDF_SPREAD * 2;
+    let mut coverage = vec![0.0; width * height];
+    outlined.draw(|x, y, value| {
+        let index = (x as usize + SDF_SPREAD) + (y as usize + SDF_SPREAD) * width;
+        if index < coverage.len() {
+            coverage[index] = value;
+        }
+    });
+
+    RasterizedGlyph {
+        character,
+        field: signed_distance_field(&coverage, width, height, SDF_SPREAD),
+        width: width as u32,
+        height: height as u32,
+        left: bounds.min.x - SDF_SPREAD as f32,
+        top: bounds.min.y - SDF_SPREAD as f32,
+        advance,
+    }
+}
+
+/// Loads fonts and rasterizes them into signed distance field atlases, which are cached in the `TextureManager`.
+pub struct FontManager {
+    texture_manager: Arc<TextureManager>,
+    fonts: DashMap<PathBuf, Result<Arc<SdfFont>, Arc<AssetError>>>,
+}
+
+impl FontManager {
+    pub fn new(texture_manager: Arc<TextureManager>) -> Self {
+        Self {
+            texture_manager,
+            fonts: DashMap::new(),
+        }
+    }
+
+    /// Returns the font, loading it and building it's atlas the first time it's requested which blocks.
+    /// Only printable ascii characters are rasterized.
+    pub fn get<P: Into<PathBuf>>(&self, path: P) -> Result<Arc<SdfFont>, Arc<AssetError>> {
+        let path = path.into();
+        if let Some(font) = self.fonts.get(&path) {
+            return font.clone();
+        }
+
+        let result = self.load(&path).map(Arc::new).map_err(Arc::new);
+        if let Err(error) = &result {
+            log::error!("Failed to load font {:?}: {:?}", path, error);
+        }
+        self.fonts.insert(path, result.clone());
+        result
+    }
+
+    fn load(&self, path: &PathBuf) -> Result<SdfFont, AssetError> {
+        let data = std::fs::read(path).map_err(|error| match error.kind() {
+            std::io::ErrorKind::NotFound => AssetError::FileNotFound,
+            _ => AssetError::OtherError(error),
+        })?;
+        let font = FontVec::try_from_vec(data).map_err(|_| AssetError::InvalidData)?;
+
+        let glyphs: Vec<RasterizedGlyph> = (32u8..127)
+            .map(|character| rasterize(&font, character as char))
+            .collect();
+        let sizes: Vec<(u32, u32)> = glyphs
+            .iter()
+            .map(|glyph| (glyph.width, glyph.height))
+            .collect();
+        let (positions, width, height) = shelf_pack(&sizes, ATLAS_PADDING);
+
+        // The glyphs are white so the text's color can be multiplied in, the field is stored in alpha.
+        let mut data = vec![0u8; (width * height * 4) as usize];
+        for chunk in data.chunks_mut(4) {
+            chunk[..3].copy_from_slice(&[255, 255, 255]);
+        }
+        let mut glyph_map = HashMap::new();
+        for (glyph, (x, y)) in glyphs.iter().zip(positions.iter()) {
+            for row in 0..glyph.height {
+                for column in 0..glyph.width {
+                    let source = (column + row * glyph.width) as usize;
+                    let destination = ((x + column) + (y + row) * width) as usize * 4 + 3;
+                    data[destination] = glyph.field[source];
+                }
+            }
+
+            glyph_map.insert(
+                glyph.character,
+                Glyph {
+                    uv_rect: Rect {
+                        x: *x as f32 / width as f32,
+                        y: *y as f32 / height as f32,
+                        width: glyph.width as f32 / width as f32,
+                        height: glyph.height as f32 / height as f32,
+                    },
+                    offset: [
+                        glyph.left / SDF_FONT_SIZE,
+                        -(glyph.top + glyph.height as f32) / SDF_FONT_SIZE,
+                    ],
+                    size: [
+                        glyph.width as f32 / SDF_FONT_SIZE,
+                        glyph.height as f32 / SDF_FONT_SIZE,
+                    ],
+                    advance: glyph.advance / SDF_FONT_SIZE,
+                },
+            );
+        }
+
+        let mut texture_path = path.clone();
+        texture_path.set_extension("sdf");
+        let image = Image::new_from_bytes(
+            width,
+            height,
+            wgpu::TextureFormat::Rgba8Unorm,
+            &data,
+            texture_path.to_str(),
+        )
+        .map_err(|_| AssetError::InvalidData)?;
+        let texture = self.texture_manager.insert_image(texture_path, image);
+
+        let scaled = font.as_scaled(PxScale::from(SDF_FONT_SIZE));
+        log::info!("{:?} loaded.", path);
+        Ok(SdfFont {
+            texture,
+            glyphs: glyph_map,
+            ascent: scaled.ascent() / SDF_FONT_SIZE,
+            line_height: (scaled.ascent() - scaled.descent() + scaled.line_gap()) / SDF_FONT_SIZE,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{signed_distance_field, Glyph, SdfFont};
+    use crate::assets::{texture_atlas::Rect, AssetHandle};
+    use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+    #[test]
+    fn should_store_distance_to_outline() {
+        // A filled square in the middle of a 16 by 16 image.
+        let size = 16;
+        let coverage: Vec<f32> = (0..size * size)
+            .map(|index| {
+                let (x, y) = (index % size, index / size);
+                if x >= 4 && x < 12 && y >= 4 && y < 12 {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let field = signed_distance_field(&coverage, size, size, 4);
+
+        // Pixels next to the outline are close to the middle, pixels far from it are clamped.
+        assert_eq!(field[0], 0);
+        assert_eq!(field[8 + 8 * size], 255);
+        assert!(field[4 + 8 * size] > 128 && field[4 + 8 * size] < 160);
+        assert!(field[3 + 8 * size] < 128 && field[3 + 8 * size] > 96);
+    }
+
+    #[test]
+    fn should_layout_lines() {
+        let glyph = Glyph {
+            uv_rect: Rect {
+                x: 0.0,
+                y: 0.0,
+                width: 0.5,
+                height: 0.5,
+            },
+            offset: [0.0, -0.25],
+            size: [0.5, 1.0],
+            advance: 0.5,
+        };
+        let space = Glyph {
+            size: [0.0, 0.0],
+            ..glyph
+        };
+        let mut glyphs = HashMap::new();
+        glyphs.insert('a', glyph);
+        glyphs.insert(' ', space);
+        let font = SdfFont {
+            texture: Arc::new(AssetHandle::new(
+                PathBuf::from("font.sdf"),
+                Arc::new(dashmap::DashMap::new()),
+            )),
+            glyphs,
+            ascent: 1.0,
+            line_height: 1.5,
+        };
+
+        let quads = font.layout("a a\nb");
+        assert_eq!(quads.len(), 2);
+        assert_eq!(quads[0].position, [0.0, -1.25]);
+        assert_eq!(quads[1].position, [1.0, -1.25]);
+
+        // Missing characters without a `?` glyph are skipped.
+        assert!(font.layout("b").is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use std::{convert::TryFrom, path::PathBuf};
+use std::{convert::TryFrom, path::PathBuf, sync::Arc};
 
 #[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
 pub enum ImageFormat {
@@ -18,6 +18,38 @@ impl Into<wgpu::TextureFormat> for ImageFormat {
     }
 }
 
+impl TryFrom<wgpu::TextureFormat> for ImageFormat {
+    type Error = ImageError;
+    fn try_from(format: wgpu::TextureFormat) -> Result<Self, Self::Error> {
+        match format {
+            wgpu::TextureFormat::Rgba16Float => Ok(ImageFormat::HDR16),
+            wgpu::TextureFormat::Rgba32Float => Ok(ImageFormat::HDR32),
+            wgpu::TextureFormat::Rgba8Unorm => Ok(ImageFormat::RGB),
+            wgpu::TextureFormat::Rgba8UnormSrgb => Ok(ImageFormat::SRGB),
+            _ => Err(ImageError::UnsupportedFormat(format)),
+        }
+    }
+}
+
+impl ImageFormat {
+    /// The size of a single pixel in bytes.
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            ImageFormat::HDR16 => 8,
+            ImageFormat::HDR32 => 16,
+            ImageFormat::RGB | ImageFormat::SRGB => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageError {
+    // Thrown when the format doesn't have a matching `ImageFormat`.
+    UnsupportedFormat(wgpu::TextureFormat),
+    // Thrown when the number of bytes doesn't match the size and format of the image.
+    InvalidSize { expected: usize, actual: usize },
+}
+
 // Image represents data on the CPU.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Image {
@@ -25,9 +57,40 @@ pub struct Image {
     pub data: Vec<u8>,
     pub width: u32,
     pub height: u32,
+    /// The format the texture created from this image uses.
+    pub format: ImageFormat,
     pub(crate) path: PathBuf,
 }
 
+impl Image {
+    /// Creates an image from pixels that are already in memory, such as procedurally generated data.
+    /// The bytes are tightly packed rows in `format`, register the image with `AssetManager::insert_image` to use it as a texture.
+    pub fn new_from_bytes(
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<Arc<Image>, ImageError> {
+        let format = ImageFormat::try_from(format)?;
+        let expected = width as usize * height as usize * format.bytes_per_pixel();
+        if bytes.len() != expected {
+            return Err(ImageError::InvalidSize {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+
+        Ok(Arc::new(Self {
+            data: bytes.to_vec(),
+            width,
+            height,
+            format,
+            path: PathBuf::from(label.unwrap_or("")),
+        }))
+    }
+}
+
 impl TryFrom<(Option<ImageRon>, PathBuf, Vec<u8>)> for Image {
     type Error = std::io::Error;
     fn try_from(
@@ -39,12 +102,15 @@ impl TryFrom<(Option<ImageRon>, PathBuf, Vec<u8>)> for Image {
             ImageFormat::SRGB
         };
 
+        let invalid_data =
+            |error: image::ImageError| std::io::Error::new(std::io::ErrorKind::InvalidData, error.to_string());
+
         let (image, width, height) = match format {
             ImageFormat::HDR32 | ImageFormat::HDR16 => {
                 // Load the hdr image
-                let decoder = image::hdr::HdrDecoder::new(data.as_slice()).unwrap();
+                let decoder = image::hdr::HdrDecoder::new(data.as_slice()).map_err(invalid_data)?;
                 let metadata = decoder.metadata();
-                let decoded = decoder.read_image_hdr().unwrap();
+                let decoded = decoder.read_image_hdr().map_err(invalid_data)?;
 
                 let (w, h) = (metadata.width, metadata.height);
 
@@ -63,7 +129,7 @@ impl TryFrom<(Option<ImageRon>, PathBuf, Vec<u8>)> for Image {
                 (image_bytes, w, h)
             }
             _ => {
-                let image = image::load_from_memory(&data).unwrap().to_rgba();
+                let image = image::load_from_memory(&data).map_err(invalid_data)?.to_rgba();
                 let (width, height) = image.dimensions();
 
                 (image.into_raw(), width, height)
@@ -74,6 +140,7 @@ impl TryFrom<(Option<ImageRon>, PathBuf, Vec<u8>)> for Image {
             data: image,
             width,
             height,
+            format,
             path,
         })
     }
@@ -82,6 +149,9 @@ impl TryFrom<(Option<ImageRon>, PathBuf, Vec<u8>)> for Image {
 #[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
 pub struct ImageRon {
     pub format: ImageFormat,
+    /// Generates every mip level when the texture is loaded. Only RGB, SRGB and HDR16 images are supported.
+    #[serde(default)]
+    pub generate_mipmaps: bool,
 }
 
 impl TryFrom<(PathBuf, Vec<u8>)> for ImageRon {
@@ -90,3 +160,31 @@ impl TryFrom<(PathBuf, Vec<u8>)> for ImageRon {
         ron::de::from_bytes(&v)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Image, ImageError, ImageFormat};
+
+    #[test]
+    fn should_create_image_from_bytes() {
+        let image =
+            Image::new_from_bytes(2, 2, wgpu::TextureFormat::Rgba8Unorm, &[255; 16], None).unwrap();
+        assert_eq!(image.format, ImageFormat::RGB);
+        assert_eq!((image.width, image.height), (2, 2));
+
+        let error = Image::new_from_bytes(2, 2, wgpu::TextureFormat::Rgba16Float, &[0; 16], None);
+        assert_eq!(
+            error,
+            Err(ImageError::InvalidSize {
+                expected: 32,
+                actual: 16
+            })
+        );
+
+        let error = Image::new_from_bytes(1, 1, wgpu::TextureFormat::R8Unorm, &[0], None);
+        assert_eq!(
+            error,
+            Err(ImageError::UnsupportedFormat(wgpu::TextureFormat::R8Unorm))
+        );
+    }
+}
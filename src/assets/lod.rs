@@ -0,0 +1,146 @@
+use super::mesh::{Gltf, Mesh, MeshVertexData, SubMesh};
+use nalgebra_glm::Vec3;
+use std::{collections::HashMap, path::PathBuf, sync::RwLock};
+
+#[derive(Debug)]
+pub enum LodError {
+    // Thrown when the mesh hasn't finished loading or failed to load.
+    NotLoaded(PathBuf),
+    // Thrown when the mesh is skinned, skinned meshes can't be simplified yet.
+    Skinned(PathBuf),
+    // Thrown when a level's cell size isn't a positive number.
+    InvalidLevel(f32),
+}
+
+/// The name `AssetManager::generate_mesh_lods` stores a level under.
+/// Use this with `get_mesh` or in `Mesh::lod_mesh_names`.
+pub fn lod_mesh_name(name: &str, level: usize) -> String {
+    format!("{}#lod{}", name, level)
+}
+
+/// Creates a simplified copy of the gltf by merging every vertex inside a grid cell of `cell_size`.
+pub(crate) fn generate_lod(
+    device: &wgpu::Device,
+    gltf: &Gltf,
+    cell_size: f32,
+) -> Gltf {
+    let meshes = gltf
+        .meshes
+        .iter()
+        .map(|mesh| Mesh {
+            name: mesh.name.clone(),
+            meshes: mesh
+                .meshes
+                .iter()
+                .map(|(material, sub_mesh)| {
+                    let (vertices, indices) =
+                        cluster_vertices(&sub_mesh.vertices, &sub_mesh.indices, cell_size);
+                    (material.clone(), SubMesh::new(device, vertices, indices, false))
+                })
+                .collect(),
+            bounding_sphere: mesh.bounding_sphere,
+            bounding_box: mesh.bounding_box,
+            // Lods are built without morph targets.
+            morph_weights: RwLock::new(Vec::new()),
+        })
+        .collect();
+
+    Gltf {
+        meshes,
+        nodes: gltf.nodes.clone(),
+        skins: gltf.skins.clone(),
+        animations: gltf.animations.clone(),
+        bounding_sphere: gltf.bounding_sphere,
+    }
+}
+
+// Merges vertices that fall into the same grid cell into one vertex with the average position and normal.
+// Triangles that collapse into a line or a point are removed.
+fn cluster_vertices(
+    vertices: &[MeshVertexData],
+    indices: &[u32],
+    cell_size: f32,
+) -> (Vec<MeshVertexData>, Vec<u32>) {
+    let mut cells: HashMap<(i32, i32, i32), u32> = HashMap::new();
+    let mut clustered: Vec<MeshVertexData> = Vec::new();
+    let mut counts: Vec<f32> = Vec::new();
+    let remap: Vec<u32> = vertices
+        .iter()
+        .map(|vertex| {
+            let cell = (
+                (vertex.position.x / cell_size).floor() as i32,
+                (vertex.position.y / cell_size).floor() as i32,
+                (vertex.position.z / cell_size).floor() as i32,
+            );
+            let index = *cells.entry(cell).or_insert_with(|| {
+                // The first vertex in a cell provides the uv and tangent.
+                clustered.push(MeshVertexData {
+                    position: Vec3::zeros(),
+                    normal: Vec3::zeros(),
+                    ..*vertex
+                });
+                counts.push(0.0);
+                clustered.len() as u32 - 1
+            });
+            clustered[index as usize].position += vertex.position;
+            clustered[index as usize].normal += vertex.normal;
+            counts[index as usize] += 1.0;
+            index
+        })
+        .collect();
+
+    for (vertex, count) in clustered.iter_mut().zip(counts.iter()) {
+        vertex.position /= *count;
+        if vertex.normal.magnitude() > 0.0 {
+            vertex.normal = vertex.normal.normalize();
+        }
+    }
+
+    let indices = indices
+        .chunks(3)
+        .map(|triangle| {
+            [
+                remap[triangle[0] as usize],
+                remap[triangle[1] as usize],
+                remap[triangle[2] as usize],
+            ]
+        })
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .flat_map(|triangle| triangle.to_vec())
+        .collect();
+
+    (clustered, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cluster_vertices;
+    use crate::assets::mesh::MeshVertexData;
+    use nalgebra_glm::Vec3;
+
+    fn vertex(x: f32, y: f32, z: f32) -> MeshVertexData {
+        MeshVertexData {
+            position: Vec3::new(x, y, z),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn should_merge_vertices_and_drop_degenerate_triangles() {
+        let vertices = vec![
+            vertex(0.0, 0.0, 0.0),
+            vertex(0.1, 0.0, 0.0),
+            vertex(2.0, 0.0, 0.0),
+            vertex(0.0, 0.0, 2.0),
+        ];
+        // The first triangle collapses as vertex 0 and 1 share a cell.
+        let indices = vec![0, 1, 2, 1, 2, 3];
+        let (clustered, indices) = cluster_vertices(&vertices, &indices, 1.0);
+
+        assert_eq!(clustered.len(), 3);
+        assert!((clustered[0].position.x - 0.05).abs() < 0.0001);
+        assert_eq!(clustered[0].normal, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(indices, vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{file_manager::AssetHandle, material::PBRMaterial, mesh::Gltf, texture::Texture};
+use std::{convert::TryFrom, path::PathBuf, sync::Arc};
+
+/// The assets to load ahead of time, read from a ron file by `AssetManager::preload_manifest`.
+/// Paths are relative to the asset directory, meshes can be gltf or obj files and materials are `PBRMaterialRon`s.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AssetManifest {
+    #[serde(default)]
+    pub meshes: Vec<String>,
+    #[serde(default)]
+    pub textures: Vec<String>,
+    #[serde(default)]
+    pub materials: Vec<String>,
+}
+
+impl AssetManifest {
+    pub fn len(&self) -> usize {
+        self.meshes.len() + self.textures.len() + self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl TryFrom<(PathBuf, Vec<u8>)> for AssetManifest {
+    type Error = ron::de::Error;
+    fn try_from((_p, v): (PathBuf, Vec<u8>)) -> Result<Self, Self::Error> {
+        ron::de::from_bytes(&v)
+    }
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    // Thrown when the manifest file wasn't found.
+    FileNotFound(PathBuf),
+    // Thrown when the manifest isn't valid ron.
+    ParseError(ron::de::Error),
+}
+
+// The handles of a manifest's assets, kept by the asset manager to report progress.
+#[derive(Debug, Default)]
+pub(crate) struct PreloadedManifest {
+    pub meshes: Vec<Arc<AssetHandle<Gltf>>>,
+    pub textures: Vec<Arc<AssetHandle<Texture>>>,
+    pub materials: Vec<Arc<AssetHandle<PBRMaterial>>>,
+    // Entries that failed before a handle was created, they count as resolved.
+    pub failed: usize,
+}
+
+impl PreloadedManifest {
+    // The fraction of entries that have loaded or failed to load.
+    pub fn progress(&self) -> f32 {
+        let total = self.meshes.len() + self.textures.len() + self.materials.len() + self.failed;
+        if total == 0 {
+            return 1.0;
+        }
+
+        let resolved = count_resolved(&self.meshes)
+            + count_resolved(&self.textures)
+            + count_resolved(&self.materials)
+            + self.failed;
+        resolved as f32 / total as f32
+    }
+}
+
+fn count_resolved<T>(handles: &[Arc<AssetHandle<T>>]) -> usize {
+    handles.iter().filter(|handle| handle.is_resolved()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AssetManifest, PreloadedManifest};
+    use crate::assets::file_manager::AssetHandle;
+    use std::{convert::TryFrom, path::PathBuf, sync::Arc};
+
+    #[test]
+    fn should_parse_manifest() {
+        let path = PathBuf::from("./assets/example/manifest.ron");
+        let manifest =
+            AssetManifest::try_from((path.clone(), std::fs::read(path).unwrap())).unwrap();
+        assert_eq!(manifest.meshes, vec!["example/meshes/cube/cube.gltf"]);
+        assert_eq!(manifest.textures.len(), 2);
+        assert_eq!(manifest.materials, vec!["material.ron"]);
+        assert_eq!(manifest.len(), 4);
+
+        // Missing lists are empty.
+        let manifest =
+            AssetManifest::try_from((PathBuf::new(), b"(textures: [])".to_vec())).unwrap();
+        assert!(manifest.is_empty());
+    }
+
+    #[test]
+    fn should_report_progress() {
+        let cache = Arc::new(dashmap::DashMap::new());
+        let handles: Vec<_> = (0..3)
+            .map(|index| {
+                Arc::new(AssetHandle::new(
+                    PathBuf::from(index.to_string()),
+                    cache.clone(),
+                ))
+            })
+            .collect();
+        let mut manifest = PreloadedManifest {
+            textures: handles.clone(),
+            ..Default::default()
+        };
+        assert_eq!(manifest.progress(), 0.0);
+
+        handles[0].finish(Err(Arc::new(crate::assets::AssetError::FileNotFound)));
+        manifest.failed = 1;
+        assert_eq!(manifest.progress(), 0.5);
+
+        assert_eq!(PreloadedManifest::default().progress(), 1.0);
+    }
+}
@@ -1,7 +1,10 @@
 use super::{file_manager::AssetHandle, texture::Texture};
-use crate::graphics::resources::{BindGroup, GPUResourceManager};
+use crate::graphics::{
+    pipelines::pbr::pbr_material_layout_entries,
+    resources::{BindGroup, BindGroupLayoutHash, GPUResourceManager},
+};
 use bytemuck::{Pod, Zeroable};
-use nalgebra_glm::Vec4;
+use nalgebra_glm::{Vec2, Vec4};
 use std::{convert::TryFrom, fmt::Debug, path::PathBuf, sync::Arc, borrow::Cow};
 
 #[repr(C)]
@@ -9,11 +12,52 @@ use std::{convert::TryFrom, fmt::Debug, path::PathBuf, sync::Arc, borrow::Cow};
 pub struct PBRMaterialUniform {
     pub color: Vec4,
     pub info: Vec4,
+    // (x, y, width, height) of the region of the textures to use.
+    pub uv_rect: Vec4,
+    // Multiplied with the emissive texture and added to the lit color.
+    pub emissive: Vec4,
+    // x is 1.0 when decals are drawn on the material, y is the alpha cutoff or 0.0 when the material isn't masked.
+    pub flags: Vec4,
 }
 
 unsafe impl Zeroable for PBRMaterialUniform {}
 unsafe impl Pod for PBRMaterialUniform {}
 
+/// Texture coordinates are scaled and then offset by this before sampling, see `UvAnimation`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UvAnimUniform {
+    pub offset: Vec2,
+    pub scale: Vec2,
+}
+
+impl Default for UvAnimUniform {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::zeros(),
+            scale: Vec2::new(1.0, 1.0),
+        }
+    }
+}
+
+unsafe impl Zeroable for UvAnimUniform {}
+unsafe impl Pod for UvAnimUniform {}
+
+/// How a material is combined with what's already been rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum BlendMode {
+    Opaque,
+    /// Alpha blended and drawn back to front after every opaque mesh.
+    /// Note: Only supported by forward rendering, the deferred geometry pass draws these as opaque.
+    Transparent,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Opaque
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PBRMaterialRon {
     pub main_texture: String,
@@ -24,6 +68,91 @@ pub struct PBRMaterialRon {
     pub roughness_override: f32,
     pub metallic_override: f32,
     pub color: Vec4,
+    /// Region of the textures to sample as (x, y, width, height) in uv space, used with texture atlases.
+    #[serde(default)]
+    pub uv_rect: Option<[f32; 4]>,
+    /// Light given off by the surface, defaults to white when there's an emissive texture and black otherwise.
+    #[serde(default)]
+    pub emissive_color: Option<[f32; 4]>,
+    #[serde(default)]
+    pub emissive_texture: Option<String>,
+    #[serde(default)]
+    pub blend_mode: BlendMode,
+    /// Whether `Decal`s are drawn on the material, defaults to true.
+    #[serde(default = "default_receive_decals")]
+    pub receive_decals: bool,
+    /// Pixels with less alpha than this are discarded, see `PBRMaterial::alpha_cutoff`.
+    #[serde(default)]
+    pub alpha_cutoff: Option<f32>,
+}
+
+fn default_receive_decals() -> bool {
+    true
+}
+
+impl PBRMaterialRon {
+    /// Creates an opaque material that reads its roughness and metallic values from `roughness_map`,
+    /// `roughness` and `metallic` are kept for when the map is swapped for `core/pbr_flat.png`.
+    /// Panics if any of the texture paths are empty.
+    pub fn from_pbr_params(
+        albedo: &str,
+        normal: &str,
+        roughness_map: &str,
+        roughness: f32,
+        metallic: f32,
+        color: [f32; 4],
+    ) -> Self {
+        assert!(!albedo.is_empty(), "PBR materials need an albedo texture.");
+        assert!(!normal.is_empty(), "PBR materials need a normal texture.");
+        assert!(!roughness_map.is_empty(), "PBR materials need a roughness texture.");
+
+        Self {
+            main_texture: albedo.to_string(),
+            roughness_texture: roughness_map.to_string(),
+            normal_texture: normal.to_string(),
+            roughness,
+            metallic,
+            roughness_override: 0.0,
+            metallic_override: 0.0,
+            color: Vec4::from(color),
+            uv_rect: None,
+            emissive_color: None,
+            emissive_texture: None,
+            blend_mode: BlendMode::Opaque,
+            receive_decals: default_receive_decals(),
+            alpha_cutoff: None,
+        }
+    }
+
+    /// Creates a material that ignores lighting and shows `albedo` tinted by `color`.
+    /// There's no unlit pipeline so the texture is drawn as emissive on top of a black, fully rough surface.
+    /// The material is transparent when the alpha of `color` is below 1.0. Panics if `albedo` is empty.
+    pub fn from_unlit(albedo: &str, color: [f32; 4]) -> Self {
+        assert!(!albedo.is_empty(), "Unlit materials need an albedo texture.");
+
+        let blend_mode = if color[3] < 1.0 {
+            BlendMode::Transparent
+        } else {
+            BlendMode::Opaque
+        };
+
+        Self {
+            main_texture: albedo.to_string(),
+            roughness_texture: "core/pbr_flat.png".to_string(),
+            normal_texture: "core/empty_normal.png".to_string(),
+            roughness: 1.0,
+            metallic: 0.0,
+            roughness_override: 1.0,
+            metallic_override: 1.0,
+            color: Vec4::new(0.0, 0.0, 0.0, color[3]),
+            uv_rect: None,
+            emissive_color: Some([color[0], color[1], color[2], 1.0]),
+            emissive_texture: Some(albedo.to_string()),
+            blend_mode,
+            receive_decals: default_receive_decals(),
+            alpha_cutoff: None,
+        }
+    }
 }
 
 impl TryFrom<(PathBuf, Vec<u8>)> for PBRMaterialRon {
@@ -34,11 +163,13 @@ impl TryFrom<(PathBuf, Vec<u8>)> for PBRMaterialRon {
 }
 
 pub trait Material: Clone {
-    type BindMaterialType: BindMaterial + Debug + Send + Sync;
+    type BindMaterialType: BindMaterial + Clone + Debug + Send + Sync;
 
     fn load_textures(&self) -> Vec<PathBuf>;
     fn create_material(&self, textures: Vec<Arc<AssetHandle<Texture>>>) -> Self::BindMaterialType;
     fn get_layout(gpu_resource_manager: Arc<GPUResourceManager>) -> Arc<wgpu::BindGroupLayout>;
+    /// The `BindGroupLayoutHash` of the layout returned by `get_layout`.
+    fn get_layout_hash() -> u64;
 }
 
 impl Material for PBRMaterialRon {
@@ -49,6 +180,10 @@ impl Material for PBRMaterialRon {
             self.main_texture.clone().into(),
             self.roughness_texture.clone().into(),
             self.normal_texture.clone().into(),
+            self.emissive_texture
+                .clone()
+                .unwrap_or("core/white.png".to_string())
+                .into(),
         ]
     }
 
@@ -57,12 +192,25 @@ impl Material for PBRMaterialRon {
             main_texture: textures.remove(0),
             roughness_texture: textures.remove(0),
             normal_texture: textures.remove(0),
+            emissive_texture: textures.remove(0),
             roughness: self.roughness,
             metallic: self.metallic,
             roughness_override: self.roughness_override,
             metallic_override: self.metallic_override,
             color: self.color,
+            uv_rect: self.uv_rect,
+            emissive: match (self.emissive_color, &self.emissive_texture) {
+                (Some(emissive_color), _) => Vec4::from(emissive_color),
+                (None, Some(_)) => Vec4::new(1.0, 1.0, 1.0, 1.0),
+                (None, None) => Vec4::zeros(),
+            },
+            blend_mode: self.blend_mode,
+            receive_decals: self.receive_decals,
+            alpha_cutoff: self.alpha_cutoff,
             bind_group: None,
+            uniform_buf: None,
+            uv_anim_buf: None,
+            layout_hash: 0,
         }
     }
 
@@ -72,6 +220,10 @@ impl Material for PBRMaterialRon {
             .unwrap()
             .clone()
     }
+
+    fn get_layout_hash() -> u64 {
+        BindGroupLayoutHash::new(&pbr_material_layout_entries()).0
+    }
 }
 
 #[derive(Clone)]
@@ -79,12 +231,74 @@ pub struct PBRMaterial {
     pub main_texture: Arc<AssetHandle<Texture>>,
     pub roughness_texture: Arc<AssetHandle<Texture>>,
     pub normal_texture: Arc<AssetHandle<Texture>>,
+    pub emissive_texture: Arc<AssetHandle<Texture>>,
     pub roughness: f32,
     pub metallic: f32,
     pub roughness_override: f32,
     pub metallic_override: f32,
     pub color: Vec4,
+    /// Region of the textures to sample as (x, y, width, height), `None` uses the whole texture.
+    pub uv_rect: Option<[f32; 4]>,
+    pub emissive: Vec4,
+    pub blend_mode: BlendMode,
+    /// Whether `Decal`s are drawn on the material.
+    /// Note: Only deferred rendering can tell which surfaces a decal covers, forward rendered decals ignore this.
+    pub receive_decals: bool,
+    /// Masks the material, pixels whose alpha is below the cutoff are discarded and the rest are drawn opaque.
+    /// Used for cutouts like leaves, fences and hair. Masked materials are drawn with the opaque meshes even when
+    /// their `blend_mode` is transparent, so they write depth.
+    pub alpha_cutoff: Option<f32>,
     pub(crate) bind_group: Option<Arc<BindGroup>>,
+    pub(crate) uniform_buf: Option<Arc<wgpu::Buffer>>,
+    pub(crate) uv_anim_buf: Option<Arc<wgpu::Buffer>>,
+    // The hash of the layout `bind_group` was created with.
+    pub(crate) layout_hash: u64,
+}
+
+impl PBRMaterial {
+    fn create_uniform(&self) -> PBRMaterialUniform {
+        PBRMaterialUniform {
+            color: self.color,
+            info: Vec4::new(self.metallic, self.roughness, self.metallic_override, self.roughness_override),
+            uv_rect: self.uv_rect.map_or(Vec4::new(0.0, 0.0, 1.0, 1.0), Vec4::from),
+            emissive: self.emissive,
+            flags: Vec4::new(
+                if self.receive_decals { 1.0 } else { 0.0 },
+                self.alpha_cutoff.unwrap_or(0.0),
+                0.0,
+                0.0,
+            ),
+        }
+    }
+
+    /// Whether the material is alpha blended and drawn after the opaque meshes, masked materials never are.
+    pub fn is_transparent(&self) -> bool {
+        self.blend_mode == BlendMode::Transparent && self.alpha_cutoff.is_none()
+    }
+
+    /// Whether a pixel with this alpha is discarded by the alpha test in the pbr shaders.
+    pub fn is_discarded(&self, alpha: f32) -> bool {
+        match self.alpha_cutoff {
+            Some(cutoff) if cutoff > 0.0 => alpha < cutoff,
+            _ => false,
+        }
+    }
+
+    /// Writes the current `color`, `metallic`, `roughness`, `uv_rect` and `emissive` values to the GPU.
+    /// Note: Does nothing if the bind group hasn't been created yet.
+    pub fn update_uniform(&self, queue: &wgpu::Queue) {
+        if let Some(uniform_buf) = self.uniform_buf.as_ref() {
+            queue.write_buffer(uniform_buf, 0, bytemuck::bytes_of(&self.create_uniform()));
+        }
+    }
+
+    /// Writes the uv animation, called every frame by the `tick_uv_animations` system.
+    /// Note: Does nothing if the bind group hasn't been created yet.
+    pub fn update_uv_animation(&self, queue: &wgpu::Queue, uniform: &UvAnimUniform) {
+        if let Some(uv_anim_buf) = self.uv_anim_buf.as_ref() {
+            queue.write_buffer(uv_anim_buf, 0, bytemuck::bytes_of(uniform));
+        }
+    }
 }
 
 impl std::fmt::Debug for PBRMaterial {
@@ -93,6 +307,7 @@ impl std::fmt::Debug for PBRMaterial {
             .field("main_texture", &self.main_texture)
             .field("roughness_texture", &self.roughness_texture)
             .field("normal_texture", &self.normal_texture)
+            .field("emissive_texture", &self.emissive_texture)
             .field("roughness", &self.roughness)
             .field("metallic", &self.metallic)
             .field("roughness", &self.color)
@@ -102,18 +317,35 @@ impl std::fmt::Debug for PBRMaterial {
 
 pub trait BindMaterial {
     fn create_bindgroup(&mut self, device: Arc<wgpu::Device>, layout: Arc<wgpu::BindGroupLayout>);
+    /// Records the `BindGroupLayoutHash` of the layout passed to `create_bindgroup`.
+    fn set_bind_group_layout_hash(&mut self, layout_hash: u64);
+    fn bind_group_layout_hash(&self) -> u64;
+}
+
+impl<T> AssetHandle<T>
+where
+    T: BindMaterial + Send + Sync + 'static,
+{
+    /// False if the material's bind group was created with a layout that doesn't match `layout_hash`.
+    /// Materials that haven't loaded yet are compatible, they're bound with the current layout once they load.
+    pub fn is_compatible_with_current_layout(&self, layout_hash: u64) -> bool {
+        self.get()
+            .map_or(true, |material| material.bind_group_layout_hash() == layout_hash)
+    }
 }
 
 impl BindMaterial for PBRMaterial {
     fn create_bindgroup(&mut self, device: Arc<wgpu::Device>, layout: Arc<wgpu::BindGroupLayout>) {
-        let uniform = PBRMaterialUniform {
-            color: self.color,
-            info: Vec4::new(self.metallic, self.roughness, self.metallic_override, self.roughness_override),
-        };
+        let uniform = self.create_uniform();
 
         // let material_uniform_size = std::mem::size_of::<PBRMaterialUniform>() as wgpu::BufferAddress;
         let uniform_buf = device.create_buffer_with_data(
             bytemuck::bytes_of(&uniform),
+            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::COPY_SRC,
+        );
+
+        let uv_anim_buf = device.create_buffer_with_data(
+            bytemuck::bytes_of(&UvAnimUniform::default()),
             wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
         );
 
@@ -145,6 +377,7 @@ impl BindMaterial for PBRMaterial {
         let main_texture = self.main_texture.get();
         let normal_texture = self.normal_texture.get();
         let roughness_texture = self.roughness_texture.get();
+        let emissive_texture = self.emissive_texture.get();
 
         if main_texture.is_err() {
             log::error!("Couldn't load material texture: {:?}", self.main_texture.handle_id);
@@ -158,10 +391,15 @@ impl BindMaterial for PBRMaterial {
             log::error!("Couldn't load material texture: {:?}", self.roughness_texture.handle_id);
         }
 
+        if emissive_texture.is_err() {
+            log::error!("Couldn't load material texture: {:?}", self.emissive_texture.handle_id);
+        }
+
         // By this point these should be loaded. Panicing here is probably good.
         let main_texture = main_texture.unwrap();
         let normal_texture = normal_texture.unwrap();
         let roughness_texture = roughness_texture.unwrap();
+        let emissive_texture = emissive_texture.unwrap();
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &layout,
@@ -190,10 +428,185 @@ impl BindMaterial for PBRMaterial {
                     binding: 5,
                     resource: wgpu::BindingResource::TextureView(&roughness_texture.view),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&emissive_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: wgpu::BindingResource::Buffer(uv_anim_buf.slice(..)),
+                },
             ]),
             label: None,
         });
 
         self.bind_group = Some(Arc::new(BindGroup::new(2, bind_group)));
+        self.uniform_buf = Some(Arc::new(uniform_buf));
+        self.uv_anim_buf = Some(Arc::new(uv_anim_buf));
+    }
+
+    fn set_bind_group_layout_hash(&mut self, layout_hash: u64) {
+        self.layout_hash = layout_hash;
+    }
+
+    fn bind_group_layout_hash(&self) -> u64 {
+        self.layout_hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BindMaterial, BlendMode, Material, PBRMaterialRon, PBRMaterialUniform};
+    use crate::{
+        assets::{file_manager::AssetHandle, texture_manager::TextureManager},
+        graphics::pipelines::pbr::create_pbr_bindgroup_layout,
+    };
+    use nalgebra_glm::Vec4;
+    use std::{path::PathBuf, sync::Arc};
+
+    #[test]
+    fn should_update_uniform() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(
+                    &wgpu::RequestAdapterOptions {
+                        power_preference: wgpu::PowerPreference::Default,
+                        compatible_surface: None,
+                    },
+                )
+                .await
+                .unwrap();
+
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: adapter.features(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+            (Arc::new(device), Arc::new(queue))
+        });
+
+        let texture_manager = TextureManager::new(device.clone(), queue.clone());
+        let textures = vec![
+            texture_manager.get_sync("./assets/core/white.png"),
+            texture_manager.get_sync("./assets/core/pbr_flat.png"),
+            texture_manager.get_sync("./assets/core/empty_normal.png"),
+            texture_manager.get_sync("./assets/core/white.png"),
+        ];
+
+        let material_ron = PBRMaterialRon {
+            main_texture: String::new(),
+            roughness_texture: String::new(),
+            normal_texture: String::new(),
+            roughness: 0.5,
+            metallic: 0.0,
+            roughness_override: -1.0,
+            metallic_override: -1.0,
+            color: Vec4::new(1.0, 1.0, 1.0, 1.0),
+            uv_rect: None,
+            emissive_color: None,
+            emissive_texture: None,
+            blend_mode: BlendMode::Opaque,
+            receive_decals: true,
+            alpha_cutoff: None,
+        };
+        let mut material = material_ron.create_material(textures);
+        let layout = Arc::new(create_pbr_bindgroup_layout(device.clone()));
+        material.create_bindgroup(device.clone(), layout);
+
+        material.metallic = 1.0;
+        material.update_uniform(&queue);
+
+        let size = std::mem::size_of::<PBRMaterialUniform>() as u64;
+        let readback_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(
+            material.uniform_buf.as_ref().unwrap(),
+            0,
+            &readback_buf,
+            0,
+            size,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buf.slice(..);
+        let map_future = slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(map_future).unwrap();
+
+        let data = slice.get_mapped_range();
+        let uniform: &PBRMaterialUniform = bytemuck::from_bytes(&data);
+        assert_eq!(uniform.info.x, 1.0);
+        assert_eq!(uniform.info.y, 0.5);
+    }
+
+    #[test]
+    fn should_mask_below_alpha_cutoff() {
+        let material_ron: PBRMaterialRon =
+            ron::de::from_bytes(&std::fs::read("./assets/masked_material.ron").unwrap()).unwrap();
+        assert_eq!(material_ron.alpha_cutoff, Some(0.5));
+
+        // The textures are never loaded, the alpha test only needs the cutoff.
+        let textures = (0..4)
+            .map(|_| Arc::new(AssetHandle::new(PathBuf::new(), Default::default())))
+            .collect();
+        let material = material_ron.create_material(textures);
+        assert!(material.is_discarded(0.25));
+        assert!(!material.is_discarded(0.5));
+        assert!(!material.is_discarded(1.0));
+        assert_eq!(material.create_uniform().flags.y, 0.5);
+        // Masked materials are drawn with the opaque meshes.
+        assert!(!material.is_transparent());
+    }
+
+    #[test]
+    fn should_create_from_params() {
+        let pbr = PBRMaterialRon::from_pbr_params(
+            "core/white.png",
+            "core/empty_normal.png",
+            "core/pbr_flat.png",
+            0.5,
+            1.0,
+            [1.0, 0.0, 0.0, 1.0],
+        );
+        assert_eq!(
+            pbr.load_textures(),
+            vec![
+                PathBuf::from("core/white.png"),
+                PathBuf::from("core/pbr_flat.png"),
+                PathBuf::from("core/empty_normal.png"),
+                PathBuf::from("core/white.png"),
+            ]
+        );
+        assert_eq!(pbr.color, Vec4::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(pbr.blend_mode, BlendMode::Opaque);
+
+        let unlit = PBRMaterialRon::from_unlit("core/white.png", [0.0, 1.0, 0.0, 0.5]);
+        assert_eq!(unlit.emissive_texture, Some("core/white.png".to_string()));
+        assert_eq!(unlit.emissive_color, Some([0.0, 1.0, 0.0, 1.0]));
+        // Only the alpha of the tint is kept, the color comes from the emissive term.
+        assert_eq!(unlit.color, Vec4::new(0.0, 0.0, 0.0, 0.5));
+        assert_eq!(unlit.blend_mode, BlendMode::Transparent);
+
+        let unlit = PBRMaterialRon::from_unlit("core/white.png", [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(unlit.blend_mode, BlendMode::Opaque);
+    }
+
+    #[test]
+    #[should_panic]
+    fn should_panic_without_albedo() {
+        PBRMaterialRon::from_unlit("", [1.0, 1.0, 1.0, 1.0]);
     }
 }
@@ -1,11 +1,97 @@
 use super::{
-    file_manager::{AssetCache, AssetError, AssetHandle},
+    file_manager::{
+        is_missing, load_with_timeout, AssetCache, AssetCallbackMap, AssetError, AssetHandle,
+        AssetLoader,
+    },
     material::{BindMaterial, Material},
     texture_manager::TextureManager,
 };
 use crate::graphics::resources::GPUResourceManager;
+use crossbeam::channel::Receiver;
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
-use std::{convert::TryFrom, fmt::Debug, path::PathBuf, sync::Arc};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt::Debug,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+/// Usage statistics for the bound material cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub evictions: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+// Keeps track of when each cache entry was last accessed so the least recently used one can be evicted.
+// Access times are a counter rather than a clock so two accesses never share a time.
+pub(crate) struct LruTracker {
+    capacity: usize,
+    clock: u64,
+    last_access: HashMap<PathBuf, u64>,
+}
+
+impl LruTracker {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: 0,
+            last_access: HashMap::new(),
+        }
+    }
+
+    /// Marks an entry as used. Does nothing if the entry isn't tracked.
+    pub(crate) fn touch(&mut self, key: &PathBuf) {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(last_access) = self.last_access.get_mut(key) {
+            *last_access = clock;
+        }
+    }
+
+    /// Starts tracking an entry and returns the entries that need to be evicted to stay within capacity.
+    /// Entries that are `in_use` are never evicted, if every other entry is in use the tracker goes over capacity.
+    pub(crate) fn insert(&mut self, key: PathBuf, in_use: impl Fn(&PathBuf) -> bool) -> Vec<PathBuf> {
+        self.clock += 1;
+        self.last_access.insert(key.clone(), self.clock);
+
+        let mut evicted = Vec::new();
+        while self.last_access.len() > self.capacity {
+            let oldest = self
+                .last_access
+                .iter()
+                .filter(|(candidate, _)| **candidate != key && !in_use(candidate))
+                .min_by_key(|(_, last_access)| **last_access)
+                .map(|(candidate, _)| candidate.clone());
+            match oldest {
+                Some(oldest) => {
+                    self.last_access.remove(&oldest);
+                    evicted.push(oldest);
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+}
+
+// The handles given out for each path, shared so the manager can tell which materials are still referenced.
+type HandleMap<V> = Arc<dashmap::DashMap<PathBuf, Arc<AssetHandle<V>>>>;
+
+// True if something other than the manager holds the path's handle, like a mesh that draws with it.
+fn is_referenced<V>(handles: &HandleMap<V>, key: &PathBuf) -> bool {
+    handles
+        .get(key)
+        .map_or(false, |handle| Arc::strong_count(handle.value()) > 1)
+}
 
 pub struct MaterialManager<T: Material> {
     device: Arc<wgpu::Device>,
@@ -13,9 +99,211 @@ pub struct MaterialManager<T: Material> {
     pool: Arc<ThreadPool>,
     ron_cache: AssetCache<T>,
     material_cache: AssetCache<T::BindMaterialType>,
+    material_lru: Arc<Mutex<LruTracker>>,
+    handles: HandleMap<T::BindMaterialType>,
+    callbacks: AssetCallbackMap<T::BindMaterialType>,
+    // Paths that are being loaded, they're removed once their result is in `material_cache`.
+    loading: Arc<Mutex<HashSet<PathBuf>>>,
+    ron_lru: Arc<Mutex<LruTracker>>,
+    evictions: Arc<AtomicUsize>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    texture_manager: Arc<TextureManager>,
+    gpu_resource_manager: Arc<GPUResourceManager>,
+    // The `BindGroupLayoutHash` new bind groups are created with, see `validate_materials`.
+    layout_hash: Arc<AtomicU64>,
+    asset_path: PathBuf,
+    // Kept alive so the asset directory keeps being watched.
+    _watcher: Option<Mutex<RecommendedWatcher>>,
+    reload_receiver: Option<Receiver<PathBuf>>,
+    load_timeout: Option<Duration>,
+}
+
+// Watches the asset directory on notify's background thread and sends the path of every modified ron file.
+fn watch_materials(asset_path: &PathBuf) -> Option<(RecommendedWatcher, Receiver<PathBuf>)> {
+    let (sender, receiver) = crossbeam::channel::unbounded();
+    let watcher = RecommendedWatcher::new_immediate(move |result: notify::Result<notify::Event>| {
+        if let Ok(event) = result {
+            if let EventKind::Modify(_) = event.kind {
+                for path in event.paths {
+                    if path.extension().map_or(false, |extension| extension == "ron") {
+                        sender.send(path).ok();
+                    }
+                }
+            }
+        }
+    });
+
+    let watching = watcher.and_then(|mut watcher| {
+        watcher.watch(asset_path, RecursiveMode::Recursive)?;
+        Ok(watcher)
+    });
+    match watching {
+        Ok(watcher) => Some((watcher, receiver)),
+        Err(error) => {
+            log::warn!("Material hot reloading is disabled: {:?}", error);
+            None
+        }
+    }
+}
+
+// Cache keys are built from the asset path which might be relative while the watcher reports absolute paths.
+fn is_same_file(a: &PathBuf, b: &PathBuf) -> bool {
+    if a == b {
+        return true;
+    }
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+// Inserts into a cache and evicts whatever the tracker says is least recently used.
+// Entries whose handle is still referenced are kept, evicting them would leave the handle loading forever.
+fn insert_with_eviction<V>(
+    cache: &AssetCache<V>,
+    lru: &Mutex<LruTracker>,
+    handles: Option<&HandleMap<V>>,
+    key: PathBuf,
+    value: Result<Arc<V>, Arc<AssetError>>,
+) -> usize {
+    // Hold the lock while touching the cache so eviction and insertion can't interleave across threads.
+    let mut lru = lru.lock().unwrap();
+    cache.insert(key.clone(), value);
+    let evicted = lru.insert(key, |key| {
+        handles.map_or(false, |handles| is_referenced(handles, key))
+    });
+    for key in evicted.iter() {
+        cache.remove(key);
+        if let Some(handles) = handles {
+            handles.remove(key);
+        }
+    }
+    evicted.len()
+}
+
+// Everything loading a material needs, cloned out of the manager so it can move onto the thread pool.
+// Handles keep one around so `AssetHandle::reload` can load their material again.
+struct LoadTask<T: Material> {
+    pool: Arc<ThreadPool>,
+    material_cache: AssetCache<T::BindMaterialType>,
+    ron_cache: AssetCache<T>,
     texture_manager: Arc<TextureManager>,
+    device: Arc<wgpu::Device>,
     gpu_resource_manager: Arc<GPUResourceManager>,
+    // Read when the material is bound, so reloads use the layout from `validate_materials`.
+    layout_hash: Arc<AtomicU64>,
     asset_path: PathBuf,
+    material_lru: Arc<Mutex<LruTracker>>,
+    handles: HandleMap<T::BindMaterialType>,
+    ron_lru: Arc<Mutex<LruTracker>>,
+    evictions: Arc<AtomicUsize>,
+    load_timeout: Option<Duration>,
+    loading: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl<T> LoadTask<T>
+where
+    T: TryFrom<(PathBuf, Vec<u8>)> + Debug + Material + Send + Sync + 'static,
+{
+    // Loads the handles one after another in a single task on the thread pool.
+    fn spawn(self: Arc<Self>, handles: Vec<AssetHandle<T::BindMaterialType>>) {
+        {
+            let mut loading = self.loading.lock().unwrap();
+            for handle in handles.iter() {
+                loading.insert(handle.handle_id.clone());
+            }
+        }
+
+        let pool = self.pool.clone();
+        pool.spawn_ok(async move {
+            for handle in handles {
+                self.load(handle).await;
+            }
+        });
+    }
+
+    // Loads the ron file and it's textures and stores the bound material under the handle's path.
+    async fn load(&self, handle: AssetHandle<T::BindMaterialType>) {
+        let path = handle.handle_id.clone();
+        let load = async {
+            let ron_file = async_std::fs::read(path.clone()).await;
+
+            match ron_file {
+                Ok(data) => {
+                    let material = match T::try_from((path.clone(), data)) {
+                        Ok(f) => Ok(Arc::new(f)),
+                        Err(_e) => Err(Arc::new(AssetError::InvalidData)),
+                    };
+
+                    match material {
+                        Ok(material) => {
+                            let material_arc = material.clone();
+
+                            // Store ron material in cache.
+                            insert_with_eviction(
+                                &self.ron_cache,
+                                &self.ron_lru,
+                                None,
+                                path.clone(),
+                                Ok(material),
+                            );
+
+                            let texture_paths = material_arc.load_textures();
+                            let mut textures = Vec::new();
+                            for texture_path in texture_paths {
+                                // TODO: The path here might be an issue.
+                                let texture_handle = self
+                                    .texture_manager
+                                    .get_async(&self.asset_path.join(texture_path))
+                                    .await;
+                                textures.push(texture_handle);
+                            }
+
+                            let mut material = material_arc.create_material(textures);
+                            material.create_bindgroup(
+                                self.device.clone(),
+                                T::get_layout(self.gpu_resource_manager.clone()),
+                            );
+                            material.set_bind_group_layout_hash(self.layout_hash.load(Ordering::Relaxed));
+
+                            log::info!("{:?} loaded.", path.file_name().unwrap());
+
+                            Ok(Arc::new(material))
+                        }
+                        Err(err) => {
+                            // Store ron material in cache.
+                            insert_with_eviction(
+                                &self.ron_cache,
+                                &self.ron_lru,
+                                None,
+                                path.clone(),
+                                Err(err.clone()),
+                            );
+                            Err(err)
+                        }
+                    }
+                }
+                Err(error) => match error.kind() {
+                    std::io::ErrorKind::NotFound => Err(Arc::new(AssetError::FileNotFound)),
+                    _ => Err(Arc::new(AssetError::OtherError(error))),
+                },
+            }
+        };
+        let result = load_with_timeout(self.load_timeout, load).await;
+
+        let evicted = insert_with_eviction(
+            &self.material_cache,
+            &self.material_lru,
+            Some(&self.handles),
+            path.clone(),
+            result.clone(),
+        );
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+        // Removed after the result is cached so it's always either loading or loaded.
+        self.loading.lock().unwrap().remove(&path);
+        handle.notify(result);
+    }
 }
 
 impl<T> MaterialManager<T>
@@ -28,19 +316,58 @@ where
         texture_manager: Arc<TextureManager>,
         gpu_resource_manager: Arc<GPUResourceManager>,
         asset_path: PathBuf,
+        capacity: usize,
     ) -> Self {
         let pool = Arc::new(ThreadPoolBuilder::new().pool_size(4).create().unwrap());
         let material_cache = Arc::new(dashmap::DashMap::new());
         let ron_cache = Arc::new(dashmap::DashMap::new());
+        let (watcher, reload_receiver) = match watch_materials(&asset_path) {
+            Some((watcher, receiver)) => (Some(Mutex::new(watcher)), Some(receiver)),
+            None => (None, None),
+        };
         Self {
             device,
             queue,
             pool,
             material_cache,
             ron_cache,
+            material_lru: Arc::new(Mutex::new(LruTracker::new(capacity))),
+            handles: Arc::new(dashmap::DashMap::new()),
+            callbacks: Arc::new(Mutex::new(HashMap::new())),
+            loading: Arc::new(Mutex::new(HashSet::new())),
+            ron_lru: Arc::new(Mutex::new(LruTracker::new(capacity))),
+            evictions: Arc::new(AtomicUsize::new(0)),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
             texture_manager,
             gpu_resource_manager,
+            layout_hash: Arc::new(AtomicU64::new(T::get_layout_hash())),
             asset_path,
+            _watcher: watcher,
+            reload_receiver,
+            load_timeout: None,
+        }
+    }
+
+    /// Sets how many ron materials are kept around. By default it's the same as the bound material capacity.
+    pub fn with_ron_capacity(self, capacity: usize) -> Self {
+        *self.ron_lru.lock().unwrap() = LruTracker::new(capacity);
+        self
+    }
+
+    /// Materials that take longer than `timeout` to load, textures included, fail with `AssetError::Timeout`.
+    /// By default there is no timeout.
+    pub fn with_load_timeout(mut self, timeout: Duration) -> Self {
+        self.load_timeout = Some(timeout);
+        self
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.material_cache.len(),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
         }
     }
 
@@ -53,7 +380,8 @@ where
         let path = path.join(
             uuid::Uuid::new_v4().to_string()
         );
-        let material_handle = Arc::new(AssetHandle::new(path.clone(), self.material_cache.clone()));
+        let material_handle = Arc::new(self.new_handle(path.clone()));
+        self.handles.insert(path.clone(), material_handle.clone());
         let relative_path: PathBuf = relative_path.into();
         let material_cache = self.material_cache.clone();
         let ron_cache = self.ron_cache.clone();
@@ -61,12 +389,22 @@ where
         let material_thread_handle = material_handle.clone();
         let device = self.device.clone();
         let layout = T::get_layout(self.gpu_resource_manager.clone());
+        let layout_hash = self.layout_hash.load(Ordering::Relaxed);
         let asset_path = self.asset_path.clone();
+        let material_lru = self.material_lru.clone();
+        let handles = self.handles.clone();
+        let ron_lru = self.ron_lru.clone();
+        let evictions = self.evictions.clone();
+        let loading = self.loading.clone();
+        loading.lock().unwrap().insert(path.clone());
 
         self.pool.spawn_ok(async move {
             let material_arc = Arc::new(material);
             // Store ron material in cache.
-            ron_cache.insert(
+            insert_with_eviction(
+                &ron_cache,
+                &ron_lru,
+                None,
                 material_thread_handle.handle_id.clone(),
                 Ok(material_arc.clone()),
             );
@@ -88,11 +426,20 @@ where
 
             let mut material = material_arc.create_material(textures);
             material.create_bindgroup(device.clone(), layout);
+            material.set_bind_group_layout_hash(layout_hash);
 
-            material_cache.insert(
+            let result = Ok(Arc::new(material));
+            let evicted = insert_with_eviction(
+                &material_cache,
+                &material_lru,
+                Some(&handles),
                 material_thread_handle.handle_id.clone(),
-                Ok(Arc::new(material)),
+                result.clone(),
             );
+            evictions.fetch_add(evicted, Ordering::Relaxed);
+            // Removed after the material is cached so it's always either loading or loaded.
+            loading.lock().unwrap().remove(&material_thread_handle.handle_id);
+            material_thread_handle.notify(result);
         });
 
         material_handle
@@ -100,102 +447,239 @@ where
 
     pub fn get<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<T::BindMaterialType>> {
         let path = path.into();
-        let material_handle = Arc::new(AssetHandle::new(path.clone(), self.material_cache.clone()));
-
-        if !self.material_cache.contains_key(&path) {
-            // Cross thread arcs passed to new thread.
-            let material_cache = self.material_cache.clone();
-            let ron_cache = self.ron_cache.clone();
-            let texture_manager = self.texture_manager.clone();
-            let material_thread_handle = material_handle.clone();
-            let device = self.device.clone();
-            let layout = T::get_layout(self.gpu_resource_manager.clone());
-            let asset_path = self.asset_path.clone();
-
-            self.pool.spawn_ok(async move {
-                let ron_file = async_std::fs::read(path.clone()).await;
-
-                let result = match ron_file {
-                    Ok(data) => {
-                        let material = match T::try_from((path.clone(), data)) {
-                            Ok(f) => Ok(Arc::new(f)),
-                            Err(_e) => Err(Arc::new(AssetError::InvalidData)),
-                        };
-
-                        match material {
-                            Ok(material) => {
-                                let material_arc = material.clone();
-
-                                // Store ron material in cache.
-                                ron_cache
-                                    .insert(material_thread_handle.handle_id.clone(), Ok(material));
-
-                                let texture_paths = material_arc.load_textures();
-                                let mut textures = Vec::new();
-                                for texture_path in texture_paths {
-                                    // TODO: The path here might be an issue.
-                                    let texture_handle = texture_manager
-                                        .get_async(&asset_path.clone().join(texture_path))
-                                        .await;
-                                    textures.push(texture_handle);
-                                }
-
-                                let mut material = material_arc.create_material(textures);
-                                material.create_bindgroup(device.clone(), layout);
-
-                                log::info!("{:?} loaded.", path.file_name().unwrap());
-
-                                Ok(Arc::new(material))
-                            }
-                            Err(err) => {
-                                // Store ron material in cache.
-                                ron_cache.insert(
-                                    material_thread_handle.handle_id.clone(),
-                                    Err(err.clone()),
-                                );
-                                Err(err)
-                            }
-                        }
-                    }
-                    Err(error) => match error.kind() {
-                        std::io::ErrorKind::NotFound => Err(Arc::new(AssetError::FileNotFound)),
-                        _ => Err(Arc::new(AssetError::OtherError(error))),
-                    },
-                };
+        let material_handle = self.handle(&path);
 
-                material_cache.insert(material_thread_handle.handle_id.clone(), result);
-            });
+        if self.material_cache.contains_key(&path) && !is_missing(&self.material_cache, &path) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            self.material_lru.lock().unwrap().touch(&path);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            self.load((*material_handle).clone());
         }
 
         material_handle
     }
 
+    /// Blocking version of `get`, returns once the material has finished loading or failed to load.
+    /// Note: This must not be called from inside of an async executor as it will block the executor's thread.
+    pub fn get_sync<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<T::BindMaterialType>> {
+        let material_handle = self.get(path);
+        let _ = async_std::task::block_on(material_handle.get_async());
+        material_handle
+    }
+
+    /// Same as calling `get` for every path, but the materials that aren't cached are loaded one after another
+    /// in a single task on the thread pool instead of a task each. Useful when loading a level's materials up front.
+    pub fn get_batch(&self, paths: &[PathBuf]) -> Vec<Arc<AssetHandle<T::BindMaterialType>>> {
+        let mut handles = Vec::with_capacity(paths.len());
+        let mut to_load: Vec<AssetHandle<T::BindMaterialType>> = Vec::new();
+        for path in paths.iter() {
+            let material_handle = self.handle(path);
+
+            if self.material_cache.contains_key(path) && !is_missing(&self.material_cache, path) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                self.material_lru.lock().unwrap().touch(path);
+            } else if to_load.iter().any(|handle| &handle.handle_id == path) {
+                // Asked for twice in the same batch, the first load covers both.
+                self.hits.fetch_add(1, Ordering::Relaxed);
+            } else {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                to_load.push((*material_handle).clone());
+            }
+
+            handles.push(material_handle);
+        }
+
+        if !to_load.is_empty() {
+            self.load_all(to_load);
+        }
+        handles
+    }
+
+    // Every handle shares the manager's callbacks so all handles to a material are notified when it loads.
+    fn new_handle(&self, path: PathBuf) -> AssetHandle<T::BindMaterialType> {
+        AssetHandle::new(path, self.material_cache.clone()).with_callbacks(self.callbacks.clone())
+    }
+
+    // Returns the shared handle of a path, the lru lock is held so the handle can't be evicted while it's cloned.
+    fn handle(&self, path: &PathBuf) -> Arc<AssetHandle<T::BindMaterialType>> {
+        let _lru = self.material_lru.lock().unwrap();
+        self.handles
+            .entry(path.clone())
+            .or_insert_with(|| {
+                let handle = self.new_handle(path.clone());
+                let loader: AssetLoader = {
+                    let task = self.load_task();
+                    // A handle without the loader so the loader doesn't keep itself alive.
+                    let handle = handle.clone();
+                    Arc::new(move || task.clone().spawn(vec![handle.clone()]))
+                };
+                Arc::new(handle.with_loader(loader))
+            })
+            .clone()
+    }
+
+    /// Marks materials as used so the cache evicts materials that aren't drawn first.
+    /// The renderer calls this every frame with the materials of visible meshes.
+    pub fn mark_used<'a>(&self, paths: impl Iterator<Item = &'a PathBuf>) {
+        let mut lru = self.material_lru.lock().unwrap();
+        for path in paths {
+            lru.touch(path);
+        }
+    }
+
+    // Loads the ron file and it's textures on the thread pool and stores the bound material under the handle's path.
+    fn load(&self, handle: AssetHandle<T::BindMaterialType>) {
+        self.load_all(vec![handle]);
+    }
+
+    // Loads the handles one after another in a single task on the thread pool.
+    fn load_all(&self, handles: Vec<AssetHandle<T::BindMaterialType>>) {
+        self.load_task().spawn(handles);
+    }
+
+    fn load_task(&self) -> Arc<LoadTask<T>> {
+        // Cross thread arcs passed to new thread.
+        Arc::new(LoadTask {
+            pool: self.pool.clone(),
+            material_cache: self.material_cache.clone(),
+            ron_cache: self.ron_cache.clone(),
+            texture_manager: self.texture_manager.clone(),
+            device: self.device.clone(),
+            gpu_resource_manager: self.gpu_resource_manager.clone(),
+            layout_hash: self.layout_hash.clone(),
+            asset_path: self.asset_path.clone(),
+            material_lru: self.material_lru.clone(),
+            handles: self.handles.clone(),
+            ron_lru: self.ron_lru.clone(),
+            evictions: self.evictions.clone(),
+            load_timeout: self.load_timeout,
+            loading: self.loading.clone(),
+        })
+    }
+
+    /// Reloads any ron materials that changed on disk since the last call. Call this once per frame.
+    /// Handles to a reloaded material stay valid and return the new material once it has loaded.
+    pub fn poll_reloads(&self) {
+        let receiver = match &self.reload_receiver {
+            Some(receiver) => receiver,
+            None => return,
+        };
+
+        // Editors tend to write a file more than once per save.
+        let modified: HashSet<PathBuf> = receiver.try_iter().collect();
+        for modified_path in modified {
+            let key = self
+                .material_cache
+                .iter()
+                .map(|item| item.key().clone())
+                .find(|key| is_same_file(key, &modified_path));
+
+            if let Some(key) = key {
+                log::info!("{:?} changed, reloading.", key.file_name().unwrap());
+                self.material_cache.remove(&key);
+                self.ron_cache.remove(&key);
+                self.load((*self.handle(&key)).clone());
+            }
+        }
+    }
+
+    /// Creates the bind group of a loaded material again with the current layout.
+    /// Handles to the material return the rebound material from then on.
+    pub fn recreate_bindgroup(&self, path: &PathBuf) {
+        let material = match self.material_cache.get(path).map(|item| item.value().clone()) {
+            Some(Ok(material)) => material,
+            _ => return,
+        };
+        let mut material = (*material).clone();
+        material.create_bindgroup(
+            self.device.clone(),
+            T::get_layout(self.gpu_resource_manager.clone()),
+        );
+        material.set_bind_group_layout_hash(self.layout_hash.load(Ordering::Relaxed));
+        self.material_cache.insert(path.clone(), Ok(Arc::new(material)));
+    }
+
+    /// Recreates the bind group of every loaded material that was bound with a layout other than `layout_hash`.
+    /// Materials loaded from now on are bound with `layout_hash`. Returns how many materials were rebound.
+    pub fn validate_materials(&self, layout_hash: u64) -> usize {
+        self.layout_hash.store(layout_hash, Ordering::Relaxed);
+        let stale: Vec<PathBuf> = self
+            .get_all_loaded()
+            .into_iter()
+            .filter(|(_, handle)| !handle.is_compatible_with_current_layout(layout_hash))
+            .map(|(path, _)| path)
+            .collect();
+        for path in stale.iter() {
+            log::info!("{:?} was bound with an old layout, rebinding.", path.file_name().unwrap());
+            self.recreate_bindgroup(path);
+        }
+        stale.len()
+    }
+
+    /// Marks the material as missing so handles return `AssetError::FileNotFound`.
+    pub(crate) fn mark_removed(&self, path: &PathBuf) {
+        self.ron_cache.remove(path);
+        self.material_cache
+            .insert(path.clone(), Err(Arc::new(AssetError::FileNotFound)));
+    }
+
+    pub(crate) fn texture_manager(&self) -> Arc<TextureManager> {
+        self.texture_manager.clone()
+    }
+
     pub fn get_all(&self) -> Vec<Arc<AssetHandle<T::BindMaterialType>>> {
-        let material_cache = self.material_cache.clone();
         self.material_cache
             .iter()
+            .map(|item| Arc::new(self.new_handle(item.key().clone())))
+            .collect()
+    }
+
+    /// A snapshot of every material that finished loading successfully, with the path it was loaded from.
+    pub fn get_all_loaded(&self) -> Vec<(PathBuf, Arc<AssetHandle<T::BindMaterialType>>)> {
+        self.material_cache
+            .iter()
+            .filter(|item| item.value().is_ok())
             .map(|item| {
-                Arc::new(AssetHandle::<T::BindMaterialType>::new(
-                    item.key().clone(),
-                    material_cache.clone(),
-                ))
+                let path = item.key().clone();
+                let handle = self.new_handle(path.clone());
+                (path, Arc::new(handle))
             })
             .collect()
     }
+
+    /// A snapshot of the materials that are still loading.
+    pub fn get_all_loading(&self) -> Vec<PathBuf> {
+        self.loading.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl<T: Material> Drop for MaterialManager<T> {
+    fn drop(&mut self) {
+        // The loaders of the handles keep the handle map alive, so the map has to let go of the handles.
+        self.handles.clear();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::AssetError;
-    use super::MaterialManager;
+    use super::{is_same_file, LruTracker, MaterialManager};
     use crate::{
-        assets::{material::PBRMaterialRon, texture_manager::TextureManager},
+        assets::{
+            material::{Material, PBRMaterialRon},
+            texture_manager::TextureManager,
+        },
         graphics::{pipelines::pbr::create_pbr_bindgroup_layout, resources::GPUResourceManager, shadows::ShadowQuality},
     };
+    use nalgebra_glm::Vec4;
     use std::{path::PathBuf, sync::Arc};
 
-    #[test]
-    fn should_load_material() {
+    fn create_material_manager() -> MaterialManager<PBRMaterialRon> {
+        create_material_manager_with_capacity(16)
+    }
+
+    fn create_material_manager_with_capacity(capacity: usize) -> MaterialManager<PBRMaterialRon> {
         let (_, device, queue) = async_std::task::block_on(async {
             let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
             let adapter = instance
@@ -230,21 +714,49 @@ mod tests {
             device.clone(),
             ShadowQuality::Medium
         );
-        let gpu_resource_manager = Arc::new(GPUResourceManager::new(device.clone(), &omni_manager));
+        let cascade_manager = crate::graphics::shadows::CascadeShadowManager::new(
+            device.clone(),
+            ShadowQuality::Medium
+        );
+        let gpu_resource_manager = Arc::new(GPUResourceManager::new(device.clone(), &omni_manager, &cascade_manager));
 
         let pbr_bind_group_layout = create_pbr_bindgroup_layout(device.clone());
         gpu_resource_manager.add_bind_group_layout("pbr_material_layout", pbr_bind_group_layout);
 
-        let material_manager = MaterialManager::<PBRMaterialRon>::new(
+        MaterialManager::<PBRMaterialRon>::new(
             device,
             queue,
             Arc::new(texture_manager),
             gpu_resource_manager,
             PathBuf::from("./"),
-        );
+            capacity,
+        )
+    }
+
+    #[test]
+    fn should_load_material() {
+        let material_manager = create_material_manager();
+        let material_handle = material_manager.get_sync("./assets/material.ron");
+        assert!(material_handle.get().is_ok());
+
+        // Missing materials return their error instead of blocking.
+        let material_handle = material_manager.get_sync("./assets/missing.ron");
+        assert!(match *material_handle.get().err().unwrap() {
+            AssetError::FileNotFound => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn should_reload_material() {
+        let material_manager = create_material_manager();
         let material_handle = material_manager.get("./assets/material.ron");
-        let material = material_handle.get();
-        assert!(match *material.err().unwrap() {
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        assert!(material_handle.get().is_ok());
+
+        material_handle.reload();
+        assert!(match *material_handle.get().err().unwrap() {
             AssetError::Loading => true,
             _ => false,
         });
@@ -253,5 +765,149 @@ mod tests {
 
         let material = material_handle.get();
         assert!(material.is_ok());
+        assert!(material.unwrap().color == Vec4::new(1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn should_list_loaded_materials() {
+        let material_manager = create_material_manager();
+        // Each path is cached separately, even when they point at the same file.
+        let paths = [
+            "./assets/material.ron",
+            "assets/material.ron",
+            "./assets/../assets/material.ron",
+        ];
+        for path in paths.iter() {
+            material_manager.get(*path);
+        }
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        assert!(material_manager.get_all_loading().is_empty());
+        let loaded = material_manager.get_all_loaded();
+        assert_eq!(loaded.len(), 3);
+        assert!(loaded.iter().all(|(_, handle)| handle.get().is_ok()));
+    }
+
+    #[test]
+    fn should_load_batch() {
+        let material_manager = create_material_manager();
+        let paths = vec![
+            PathBuf::from("./assets/material.ron"),
+            PathBuf::from("./assets/material.ron"),
+            PathBuf::from("./assets/missing.ron"),
+        ];
+        let handles = material_manager.get_batch(&paths);
+        assert_eq!(handles.len(), 3);
+        // The duplicate path is only loaded once.
+        let stats = material_manager.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        assert!(handles[0].get().is_ok());
+        assert!(handles[1].get().is_ok());
+        assert!(handles[2].get().is_err());
+    }
+
+    #[test]
+    fn should_batch_load_as_many_materials_as_individual_loads() {
+        // Distinct paths to the same file so nothing is shared between loads.
+        let paths: Vec<PathBuf> = (0..32)
+            .map(|i| PathBuf::from(format!("./{}assets/material.ron", "assets/../".repeat(i))))
+            .collect();
+        let count_loaded = |handles: &[Arc<super::AssetHandle<_>>]| {
+            while !handles.iter().all(|handle| handle.is_resolved()) {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            handles.iter().filter(|handle| handle.get().is_ok()).count()
+        };
+
+        let material_manager = create_material_manager_with_capacity(paths.len());
+        let handles: Vec<_> = paths.iter().map(|path| material_manager.get(path.clone())).collect();
+        let individual = count_loaded(&handles);
+
+        let material_manager = create_material_manager_with_capacity(paths.len());
+        let handles = material_manager.get_batch(&paths);
+        assert_eq!(handles.len(), paths.len());
+        assert_eq!(count_loaded(&handles), individual);
+        assert_eq!(individual, paths.len());
+    }
+
+    #[test]
+    fn should_rebind_stale_materials() {
+        let material_manager = create_material_manager();
+        let material_handle = material_manager.get("./assets/material.ron");
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        let layout_hash = PBRMaterialRon::get_layout_hash();
+        assert!(material_handle.is_compatible_with_current_layout(layout_hash));
+        assert_eq!(material_manager.validate_materials(layout_hash), 0);
+
+        // Pretend the layout changed.
+        assert!(!material_handle.is_compatible_with_current_layout(layout_hash + 1));
+        assert_eq!(material_manager.validate_materials(layout_hash + 1), 1);
+        assert!(material_handle.is_compatible_with_current_layout(layout_hash + 1));
+        assert_eq!(material_manager.validate_materials(layout_hash + 1), 0);
+    }
+
+    #[test]
+    fn lru_should_evict_least_recently_used() {
+        let mut lru = LruTracker::new(2);
+        assert!(lru.insert(PathBuf::from("a"), |_| false).is_empty());
+        assert!(lru.insert(PathBuf::from("b"), |_| false).is_empty());
+
+        // "a" is now more recent than "b".
+        lru.touch(&PathBuf::from("a"));
+
+        let evicted = lru.insert(PathBuf::from("c"), |_| false);
+        assert_eq!(evicted, vec![PathBuf::from("b")]);
+    }
+
+    #[test]
+    fn lru_should_keep_entries_in_use() {
+        let mut lru = LruTracker::new(1);
+        lru.insert(PathBuf::from("a"), |_| false);
+
+        // "a" is the oldest but still used, so nothing can be evicted.
+        let in_use = |key: &PathBuf| key == &PathBuf::from("a");
+        assert!(lru.insert(PathBuf::from("b"), in_use).is_empty());
+        assert_eq!(lru.insert(PathBuf::from("c"), in_use), vec![PathBuf::from("b")]);
+    }
+
+    #[test]
+    fn should_keep_referenced_materials() {
+        let material_manager = create_material_manager_with_capacity(1);
+        // Paths stop loading after the evictions are counted.
+        let wait = || {
+            while !material_manager.get_all_loading().is_empty() {
+                std::thread::yield_now();
+            }
+        };
+        let kept = material_manager.get("./assets/material.ron");
+        wait();
+
+        // Loading past the capacity can't evict a material someone still holds.
+        material_manager.get("./assets/masked_material.ron");
+        wait();
+        assert!(kept.get().is_ok());
+        assert_eq!(material_manager.stats().evictions, 0);
+
+        // Once it's dropped it's the first to go.
+        drop(kept);
+        material_manager.get("./assets/../assets/material.ron");
+        wait();
+        assert!(material_manager.stats().evictions > 0);
+    }
+
+    #[test]
+    fn should_match_relative_and_absolute_paths() {
+        let relative = PathBuf::from("./assets/material.ron");
+        let absolute = std::env::current_dir()
+            .unwrap()
+            .join("assets/material.ron");
+        assert!(is_same_file(&relative, &absolute));
+        assert!(!is_same_file(&relative, &PathBuf::from("./Cargo.toml")));
     }
 }
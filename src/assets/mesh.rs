@@ -1,17 +1,20 @@
 use super::{
+    animation::{AnimationChannel, AnimationClip, AnimationTrack, Interpolation},
     file_manager::AssetHandle,
-    material::{PBRMaterial, PBRMaterialRon},
+    material::{BlendMode, PBRMaterial, PBRMaterialRon},
     material_manager::MaterialManager,
+    morph::MorphTarget,
+    skeleton::{JointTransform, Skeleton},
 };
 use bytemuck::{Pod, Zeroable};
-use nalgebra_glm::{Vec2, Vec3, Vec4};
+use nalgebra_glm::{Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
 use std::{
     collections::HashMap,
     ffi::OsStr,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
-use crate::core::BoundingSphere;
+use crate::core::{Aabb, BoundingSphere};
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -37,14 +40,90 @@ impl Default for MeshVertexData {
 unsafe impl Zeroable for MeshVertexData {}
 unsafe impl Pod for MeshVertexData {}
 
+/// Vertex data used as the input of the skinning compute shader.
+/// The shader writes the deformed vertices out as `MeshVertexData`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SkinnedMeshVertexData {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+    pub tangent: Vec4,
+    pub joint_indices: [u8; 4],
+    pub joint_weights: [f32; 4],
+}
+
+unsafe impl Zeroable for SkinnedMeshVertexData {}
+unsafe impl Pod for SkinnedMeshVertexData {}
+
 pub struct SubMesh {
     pub vertices: Vec<MeshVertexData>,
-    indices: Vec<u32>,
+    pub(crate) indices: Vec<u32>,
     pub(crate) index_count: usize,
     mode: wgpu::PrimitiveTopology,
     pub(crate) vertex_buffer: Option<Arc<wgpu::Buffer>>,
     pub(crate) index_buffer: Arc<wgpu::Buffer>,
     pub bounding_sphere: BoundingSphere,
+    pub bounding_box: Aabb,
+    /// Joint indices and weights for each vertex if the mesh is skinned.
+    pub(crate) skin: Option<Vec<([u8; 4], [f32; 4])>>,
+    /// Input for the skinning compute shader. When skinned `vertex_buffer` holds the deformed vertices.
+    pub(crate) skinned_vertex_buffer: Option<Arc<wgpu::Buffer>>,
+    /// Blend shapes loaded from the gltf file. Skinned sub meshes don't load their morph targets.
+    pub morph_targets: Vec<MorphTarget>,
+    /// Every target's deltas one after the other, read by the morph compute shader.
+    pub(crate) morph_buffer: Option<Arc<wgpu::Buffer>>,
+    /// The undeformed vertices. When morphed `vertex_buffer` holds the blended vertices.
+    pub(crate) base_vertex_buffer: Option<Arc<wgpu::Buffer>>,
+}
+
+impl SubMesh {
+    /// Creates a triangle list sub mesh and uploads it's buffers to the GPU.
+    /// Tangents are generated from the uv coordinates when `generate_tangents` is true.
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        vertices: Vec<MeshVertexData>,
+        indices: Vec<u32>,
+        generate_tangents: bool,
+    ) -> Self {
+        let index_buffer = Arc::new(device.create_buffer_with_data(
+            &bytemuck::cast_slice(&indices),
+            wgpu::BufferUsage::INDEX,
+        ));
+        let index_count = indices.len();
+        let bounding_sphere =
+            BoundingSphere::from_points(vertices.iter().map(|x| x.position).collect());
+        let bounding_box =
+            Aabb::from_points(&vertices.iter().map(|x| x.position).collect::<Vec<_>>());
+
+        let mut sub_mesh = SubMesh {
+            vertices,
+            indices,
+            index_count,
+            mode: wgpu::PrimitiveTopology::TriangleList,
+            vertex_buffer: None,
+            index_buffer,
+            bounding_sphere,
+            bounding_box,
+            skin: None,
+            skinned_vertex_buffer: None,
+            morph_targets: Vec::new(),
+            morph_buffer: None,
+            base_vertex_buffer: None,
+        };
+
+        if generate_tangents {
+            mikktspace::generate_tangents(&mut sub_mesh);
+        }
+
+        let vertex_buffer = device.create_buffer_with_data(
+            &bytemuck::cast_slice(&sub_mesh.vertices),
+            wgpu::BufferUsage::VERTEX,
+        );
+        sub_mesh.vertex_buffer = Some(Arc::new(vertex_buffer));
+
+        sub_mesh
+    }
 }
 
 impl std::fmt::Debug for SubMesh {
@@ -63,11 +142,59 @@ pub struct Mesh {
     pub name: String,
     pub meshes: HashMap<Arc<AssetHandle<PBRMaterial>>, SubMesh>,
     pub bounding_sphere: BoundingSphere,
+    pub(crate) bounding_box: Aabb,
+    /// One weight per morph target, set with `AssetManager::set_morph_weight`.
+    pub(crate) morph_weights: RwLock<Vec<f32>>,
+}
+
+impl Mesh {
+    /// The box around every sub mesh in the mesh's local space.
+    pub fn bounding_box(&self) -> &Aabb {
+        &self.bounding_box
+    }
+
+    /// The names of the mesh's morph targets, every sub mesh of a gltf mesh has the same targets.
+    pub fn morph_target_names(&self) -> Vec<&str> {
+        self.meshes
+            .values()
+            .map(|sub_mesh| &sub_mesh.morph_targets)
+            .max_by_key(|morph_targets| morph_targets.len())
+            .map_or(Vec::new(), |morph_targets| {
+                morph_targets
+                    .iter()
+                    .map(|target| target.name.as_str())
+                    .collect()
+            })
+    }
+
+    /// The current weight of each morph target.
+    pub fn morph_weights(&self) -> Vec<f32> {
+        self.morph_weights.read().unwrap().clone()
+    }
+}
+
+/// A node from the gltf scene graph.
+/// The transform is flattened into world space so it can be used directly with a `Transform` component.
+#[derive(Debug, Clone)]
+pub struct GltfNode {
+    pub name: String,
+    /// Index into `Gltf::meshes` if the node has a mesh attached.
+    pub mesh_index: Option<usize>,
+    /// The gltf material indices used by the mesh's primitives, in primitive order without duplicates.
+    pub material_indices: Vec<usize>,
+    /// Index into `Gltf::skins` if the node's mesh is skinned.
+    pub skin_index: Option<usize>,
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
 }
 
 #[derive(Debug)]
 pub struct Gltf {
     pub meshes: Vec<Mesh>,
+    pub nodes: Vec<GltfNode>,
+    pub skins: Vec<Skeleton>,
+    pub animations: Vec<AnimationClip>,
     pub bounding_sphere: BoundingSphere,
 }
 
@@ -91,11 +218,51 @@ impl Gltf {
 
         let mut buffer_data = Vec::new();
         for file in files {
-            let buffer_path = path.clone().parent().unwrap().join(file);
-            let file = async_std::fs::read(buffer_path).await.unwrap();
+            let file = match Self::decode_data_uri(&file) {
+                Some(data) => data,
+                None => {
+                    let buffer_path = path.clone().parent().unwrap().join(file);
+                    async_std::fs::read(buffer_path).await.unwrap()
+                }
+            };
             buffer_data.push(gltf::buffer::Data(file));
         }
 
+        // Resolve image paths, embedded images are decoded and inserted into the texture manager.
+        let file_stem = path.file_stem().and_then(OsStr::to_str).unwrap_or("gltf").to_string();
+        let texture_manager = material_manager.texture_manager();
+        let image_paths: Vec<Option<String>> = document
+            .images()
+            .map(|image| {
+                let (data, mime_type) = match image.source() {
+                    gltf::image::Source::Uri { uri, mime_type } => {
+                        match Self::decode_data_uri(uri) {
+                            Some(data) => (data, mime_type.or(Self::data_uri_mime_type(uri))),
+                            None => return Some(Path::new(&uri).to_str().unwrap().to_string()),
+                        }
+                    }
+                    gltf::image::Source::View { view, mime_type } => {
+                        let buffer = buffer_data.get(view.buffer().index());
+                        if buffer.is_none() {
+                            log::error!("Couldn't find buffer for embedded image: {}", image.index());
+                            return None;
+                        }
+                        let start = view.offset();
+                        let end = start + view.length();
+                        (buffer.unwrap().0[start..end].to_vec(), Some(mime_type))
+                    }
+                };
+
+                let ext = match mime_type {
+                    Some("image/jpeg") => "jpg",
+                    _ => "png",
+                };
+                let image_name = format!("{}_embedded_{}.{}", file_stem, image.index(), ext);
+                texture_manager.insert(path.parent().unwrap().join(&image_name), data);
+                Some(image_name)
+            })
+            .collect();
+
         let get_buffer_data =
             |buffer: gltf::Buffer<'_>| buffer_data.get(buffer.index()).map(|x| &*x.0);
 
@@ -107,12 +274,12 @@ impl Gltf {
             let name = gltf_mesh.name().unwrap_or("mesh").to_string();
             let primitives = gltf_mesh.primitives();
 
-            let images: Vec<gltf::Image<'_>> = document.images().collect();
-
             let mut mesh = Mesh {
                 name,
                 meshes: HashMap::new(),
                 bounding_sphere: BoundingSphere::new(),
+                bounding_box: Aabb::new(),
+                morph_weights: RwLock::new(Vec::new()),
             };
 
             for primitive in primitives {
@@ -131,10 +298,12 @@ impl Gltf {
                     })
                     .collect();
 
+                let mut had_normals = false;
                 if let Some(normals) = reader.read_normals() {
                     for (i, normal) in normals.enumerate() {
                         vertices[i].normal = Vec3::from(normal.clone());
                     }
+                    had_normals = true;
                 }
                 if let Some(uvs) = reader.read_tex_coords(0) {
                     for (i, uv) in uvs.into_f32().enumerate() {
@@ -158,6 +327,12 @@ impl Gltf {
                     panic!("model doesn't have indices");
                 };
 
+                // Normals are needed for lighting and tangent generation.
+                if !had_normals && primitive.mode() == gltf::mesh::Mode::Triangles {
+                    log::info!("No normals found generating normals instead!");
+                    compute_normals(&mut vertices, &indices);
+                }
+
                 let gltf_material: gltf::Material<'_> = primitive.material();
                 let pbr = gltf_material.pbr_metallic_roughness();
 
@@ -170,32 +345,20 @@ impl Gltf {
                 );
 
                 let main_info = pbr.base_color_texture();
-                let mut normal_texture = None;
-                let normals_texture = gltf_material.normal_texture();
-                if normals_texture.is_some() {
-                    let normal_source = normals_texture.unwrap().texture().source().source();
-                    match normal_source {
-                        gltf::image::Source::Uri { uri, .. } => {
-                            let texture_file_name = Some(
-                                Path::new(&uri)
-                                    .file_name()
-                                    .and_then(OsStr::to_str)
-                                    .unwrap()
-                                    .to_string(),
-                            );
-                            if texture_file_name.is_some() {
-                                normal_texture = Some(texture_file_name.unwrap());
-                            }
-                        }
-                        _ => (),
-                    }
-                }
+                let normal_texture = gltf_material
+                    .normal_texture()
+                    .and_then(|info| image_paths[info.texture().source().index()].clone());
                 let roughness_info = pbr.metallic_roughness_texture();
                 let roughness = pbr.roughness_factor();
                 let metallic = pbr.metallic_factor();
 
-                let main_texture = Self::get_texture_url(&main_info, &images);
-                let roughness_texture = Self::get_texture_url(&roughness_info, &images);
+                let emissive_texture = gltf_material
+                    .emissive_texture()
+                    .and_then(|info| image_paths[info.texture().source().index()].clone());
+                let emissive_factor = gltf_material.emissive_factor();
+
+                let main_texture = Self::get_texture_url(&main_info, &image_paths);
+                let roughness_texture = Self::get_texture_url(&roughness_info, &image_paths);
 
                 let has_pbr_texture = roughness_texture.is_some();
 
@@ -208,6 +371,23 @@ impl Gltf {
                     roughness_override: if has_pbr_texture { 0.0 } else { 1.0 },
                     metallic_override: if has_pbr_texture { 0.0 } else { 1.0 },
                     color,
+                    uv_rect: None,
+                    emissive_color: Some([
+                        emissive_factor[0],
+                        emissive_factor[1],
+                        emissive_factor[2],
+                        1.0,
+                    ]),
+                    emissive_texture,
+                    blend_mode: match gltf_material.alpha_mode() {
+                        gltf::material::AlphaMode::Blend => BlendMode::Transparent,
+                        _ => BlendMode::Opaque,
+                    },
+                    receive_decals: true,
+                    alpha_cutoff: match gltf_material.alpha_mode() {
+                        gltf::material::AlphaMode::Mask => Some(gltf_material.alpha_cutoff()),
+                        _ => None,
+                    },
                 };
                 let material_handle = material_manager.insert(material, path.clone());
                 
@@ -220,6 +400,7 @@ impl Gltf {
                 let index_count = indices.len();
 
                 let bounding_sphere = BoundingSphere::from_points(vertices.iter().map(|x| x.position).collect());
+                let bounding_box = Aabb::from_points(&vertices.iter().map(|x| x.position).collect::<Vec<_>>());
 
                 let mut sub_mesh = SubMesh {
                     vertices,
@@ -229,6 +410,12 @@ impl Gltf {
                     vertex_buffer: None,
                     index_buffer,
                     bounding_sphere,
+                    bounding_box,
+                    skin: None,
+                    skinned_vertex_buffer: None,
+                    morph_targets: Vec::new(),
+                    morph_buffer: None,
+                    base_vertex_buffer: None,
                 };
 
                 if !had_tangents {
@@ -236,23 +423,336 @@ impl Gltf {
                     mikktspace::generate_tangents(&mut sub_mesh);
                 }
 
+                // Load joints and weights if the primitive is skinned.
+                if let (Some(joints), Some(weights)) = (reader.read_joints(0), reader.read_weights(0)) {
+                    let skin = joints
+                        .into_u16()
+                        .zip(weights.into_f32())
+                        .map(|(joints, weights)| {
+                            let mut joint_indices = [0u8; 4];
+                            for (i, joint) in joints.iter().enumerate() {
+                                joint_indices[i] = (*joint).min(255) as u8;
+                            }
+                            (joint_indices, weights)
+                        })
+                        .collect::<Vec<_>>();
+                    sub_mesh.skin = Some(skin);
+                }
+
+                // Morph targets are only applied to meshes that aren't skinned.
+                if sub_mesh.skin.is_none() {
+                    sub_mesh.morph_targets = reader
+                        .read_morph_targets()
+                        .enumerate()
+                        .map(|(index, (positions, normals, tangents))| {
+                            let mut vertex_deltas =
+                                vec![MeshVertexData::default(); sub_mesh.vertices.len()];
+                            if let Some(positions) = positions {
+                                for (delta, position) in vertex_deltas.iter_mut().zip(positions) {
+                                    delta.position = Vec3::new(position[0], position[1], position[2]);
+                                }
+                            }
+                            if let Some(normals) = normals {
+                                for (delta, normal) in vertex_deltas.iter_mut().zip(normals) {
+                                    delta.normal = Vec3::new(normal[0], normal[1], normal[2]);
+                                }
+                            }
+                            if let Some(tangents) = tangents {
+                                for (delta, tangent) in vertex_deltas.iter_mut().zip(tangents) {
+                                    delta.tangent = Vec4::new(tangent[0], tangent[1], tangent[2], 0.0);
+                                }
+                            }
+                            MorphTarget {
+                                name: MorphTarget::default_name(index),
+                                vertex_deltas,
+                            }
+                        })
+                        .collect();
+                }
+
+                let deformed = sub_mesh.skin.is_some() || !sub_mesh.morph_targets.is_empty();
+                let vertex_usage = if deformed {
+                    // The skinning and morph compute shaders write the deformed vertices into the vertex buffer.
+                    wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::STORAGE
+                } else {
+                    wgpu::BufferUsage::VERTEX
+                };
                 let vertex_buffer = device.create_buffer_with_data(
                     &bytemuck::cast_slice(&sub_mesh.vertices),
-                    wgpu::BufferUsage::VERTEX,
+                    vertex_usage,
                 );
                 sub_mesh.vertex_buffer = Some(Arc::new(vertex_buffer));
 
+                if let Some(skin) = sub_mesh.skin.as_ref() {
+                    let skinned_vertices = sub_mesh
+                        .vertices
+                        .iter()
+                        .zip(skin.iter())
+                        .map(|(vertex, (joint_indices, joint_weights))| SkinnedMeshVertexData {
+                            position: vertex.position,
+                            normal: vertex.normal,
+                            uv: vertex.uv,
+                            tangent: vertex.tangent,
+                            joint_indices: *joint_indices,
+                            joint_weights: *joint_weights,
+                        })
+                        .collect::<Vec<_>>();
+                    let skinned_vertex_buffer = device.create_buffer_with_data(
+                        &bytemuck::cast_slice(&skinned_vertices),
+                        wgpu::BufferUsage::STORAGE,
+                    );
+                    sub_mesh.skinned_vertex_buffer = Some(Arc::new(skinned_vertex_buffer));
+                }
+
+                if !sub_mesh.morph_targets.is_empty() {
+                    let base_vertex_buffer = device.create_buffer_with_data(
+                        &bytemuck::cast_slice(&sub_mesh.vertices),
+                        wgpu::BufferUsage::STORAGE,
+                    );
+                    sub_mesh.base_vertex_buffer = Some(Arc::new(base_vertex_buffer));
+
+                    let vertex_deltas = sub_mesh
+                        .morph_targets
+                        .iter()
+                        .flat_map(|target| target.vertex_deltas.iter().cloned())
+                        .collect::<Vec<_>>();
+                    let morph_buffer = device.create_buffer_with_data(
+                        &bytemuck::cast_slice(&vertex_deltas),
+                        wgpu::BufferUsage::STORAGE,
+                    );
+                    sub_mesh.morph_buffer = Some(Arc::new(morph_buffer));
+                }
+
                 mesh.meshes.insert(material_handle, sub_mesh);
             }
 
             mesh.bounding_sphere = BoundingSphere::from_bounding_spheres(mesh.meshes.values().map(|x| &x.bounding_sphere).collect());
+            mesh.bounding_box = mesh.meshes.values().fold(Aabb::new(), |aabb, x| aabb.merge(&x.bounding_box));
+
+            // Start from the mesh's default weights, targets without one start at 0.
+            let target_count = mesh.morph_target_names().len();
+            let mut morph_weights = gltf_mesh
+                .weights()
+                .map_or(Vec::new(), |weights| weights.to_vec());
+            morph_weights.resize(target_count, 0.0);
+            mesh.morph_weights = RwLock::new(morph_weights);
 
             meshes.push(mesh);
         }
 
         let bounding_sphere = BoundingSphere::from_bounding_spheres(meshes.iter().map(|x| &x.bounding_sphere).collect());
 
-        Gltf { meshes, bounding_sphere }
+        let mut nodes = Vec::new();
+        let scene = document.default_scene().or_else(|| document.scenes().next());
+        if scene.is_some() {
+            for node in scene.unwrap().nodes() {
+                Self::collect_nodes(node, Mat4::identity(), &mut nodes);
+            }
+        }
+
+        // Joints are posed relative to their parent so skins need the node hierarchy.
+        let mut node_parents = vec![None; document.nodes().count()];
+        for node in document.nodes() {
+            for child in node.children() {
+                node_parents[child.index()] = Some(node.index());
+            }
+        }
+        let node_matrices = document
+            .nodes()
+            .map(|node| Mat4::from(node.transform().matrix()))
+            .collect::<Vec<_>>();
+        let node_world = |mut index: usize| {
+            let mut world = node_matrices[index];
+            while let Some(parent) = node_parents[index] {
+                world = node_matrices[parent] * world;
+                index = parent;
+            }
+            world
+        };
+
+        let skins = document
+            .skins()
+            .map(|skin| {
+                let reader = skin.reader(get_buffer_data);
+                let inverse_bind_matrices = reader
+                    .read_inverse_bind_matrices()
+                    .map(|matrices| matrices.map(|matrix| Mat4::from(matrix)).collect())
+                    .unwrap_or(Vec::new());
+                let joint_nodes: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+
+                let mut parents = Vec::new();
+                let mut root_matrices = Vec::new();
+                for node in joint_nodes.iter() {
+                    let parent_node = node_parents[*node];
+                    let parent = parent_node
+                        .and_then(|parent| joint_nodes.iter().position(|node| *node == parent));
+                    parents.push(parent);
+                    root_matrices.push(match (parent, parent_node) {
+                        (None, Some(parent_node)) => node_world(parent_node),
+                        _ => Mat4::identity(),
+                    });
+                }
+                let rest_pose = skin
+                    .joints()
+                    .map(|joint| {
+                        let (translation, rotation, scale) = joint.transform().decomposed();
+                        JointTransform {
+                            translation: Vec3::from(translation),
+                            rotation: nalgebra_glm::quat(
+                                rotation[0],
+                                rotation[1],
+                                rotation[2],
+                                rotation[3],
+                            ),
+                            scale: Vec3::from(scale),
+                        }
+                    })
+                    .collect();
+
+                Skeleton::new(
+                    skin.name().unwrap_or("skin").to_string(),
+                    joint_nodes,
+                    inverse_bind_matrices,
+                )
+                .with_hierarchy(parents, rest_pose, root_matrices)
+            })
+            .collect();
+
+        let animations = document
+            .animations()
+            .map(|animation| {
+                let channels = animation
+                    .channels()
+                    .filter_map(|channel| Self::load_channel(channel, get_buffer_data))
+                    .collect();
+                AnimationClip::new(
+                    animation.name().unwrap_or("animation").to_string(),
+                    channels,
+                )
+            })
+            .collect();
+
+        Gltf { meshes, nodes, skins, animations, bounding_sphere }
+    }
+
+    /// Finds an animation by name.
+    pub fn animation(&self, name: &str) -> Option<&AnimationClip> {
+        self.animations
+            .iter()
+            .find(|animation| animation.name == name)
+    }
+
+    // Reads the keyframes of a channel. Morph target weights aren't supported and return None.
+    fn load_channel<'a, 's, F>(
+        channel: gltf::animation::Channel<'a>,
+        get_buffer_data: F,
+    ) -> Option<AnimationChannel>
+    where
+        F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+    {
+        use gltf::animation::util::ReadOutputs;
+
+        let reader = channel.reader(get_buffer_data);
+        let times: Vec<f32> = reader.read_inputs()?.collect();
+        let track = match reader.read_outputs()? {
+            ReadOutputs::Translations(values) => {
+                AnimationTrack::Translation(values.map(Vec3::from).collect())
+            }
+            ReadOutputs::Rotations(values) => AnimationTrack::Rotation(
+                values
+                    .into_f32()
+                    .map(|rotation| {
+                        nalgebra_glm::quat(rotation[0], rotation[1], rotation[2], rotation[3])
+                    })
+                    .collect(),
+            ),
+            ReadOutputs::Scales(values) => AnimationTrack::Scale(values.map(Vec3::from).collect()),
+            ReadOutputs::MorphTargetWeights(_) => return None,
+        };
+
+        let (interpolation, track) = match channel.sampler().interpolation() {
+            gltf::animation::Interpolation::Step => (Interpolation::Step, track),
+            gltf::animation::Interpolation::Linear => (Interpolation::Linear, track),
+            gltf::animation::Interpolation::CubicSpline => {
+                let track = match track {
+                    AnimationTrack::Translation(values) => {
+                        AnimationTrack::Translation(spline_values(values))
+                    }
+                    AnimationTrack::Rotation(values) => {
+                        AnimationTrack::Rotation(spline_values(values))
+                    }
+                    AnimationTrack::Scale(values) => AnimationTrack::Scale(spline_values(values)),
+                };
+                (Interpolation::Linear, track)
+            }
+        };
+
+        Some(AnimationChannel {
+            node: channel.target().node().index(),
+            interpolation,
+            times,
+            track,
+        })
+    }
+
+    // Walks the node hierarchy flattening each node's transform into world space.
+    fn collect_nodes(node: gltf::Node<'_>, parent: Mat4, nodes: &mut Vec<GltfNode>) {
+        let world = parent * Mat4::from(node.transform().matrix());
+
+        let position = Vec3::new(world[(0, 3)], world[(1, 3)], world[(2, 3)]);
+        let x_axis = Vec3::new(world[(0, 0)], world[(1, 0)], world[(2, 0)]);
+        let y_axis = Vec3::new(world[(0, 1)], world[(1, 1)], world[(2, 1)]);
+        let z_axis = Vec3::new(world[(0, 2)], world[(1, 2)], world[(2, 2)]);
+        let scale = Vec3::new(x_axis.magnitude(), y_axis.magnitude(), z_axis.magnitude());
+        let rotation_matrix = Mat3::from_columns(&[
+            x_axis / scale.x,
+            y_axis / scale.y,
+            z_axis / scale.z,
+        ]);
+
+        nodes.push(GltfNode {
+            name: node.name().unwrap_or("node").to_string(),
+            mesh_index: node.mesh().map(|mesh| mesh.index()),
+            material_indices: node.mesh().map_or(Vec::new(), |mesh| {
+                let mut indices = Vec::new();
+                for index in mesh.primitives().filter_map(|primitive| primitive.material().index()) {
+                    if !indices.contains(&index) {
+                        indices.push(index);
+                    }
+                }
+                indices
+            }),
+            skin_index: node.skin().map(|skin| skin.index()),
+            position,
+            rotation: nalgebra_glm::mat3_to_quat(&rotation_matrix),
+            scale,
+        });
+
+        for child in node.children() {
+            Self::collect_nodes(child, world, nodes);
+        }
+    }
+
+    // Returns the decoded bytes of a base64 data uri. Returns None if the uri isn't a data uri.
+    fn decode_data_uri(uri: &str) -> Option<Vec<u8>> {
+        if !uri.starts_with("data:") {
+            return None;
+        }
+        let data = uri.splitn(2, ",").nth(1)?;
+        match base64::decode(data) {
+            Ok(data) => Some(data),
+            Err(error) => {
+                log::error!("Couldn't decode gltf data uri: {:?}", error);
+                None
+            }
+        }
+    }
+
+    fn data_uri_mime_type(uri: &str) -> Option<&str> {
+        if !uri.starts_with("data:") {
+            return None;
+        }
+        uri["data:".len()..].split(";").next()
     }
 
     fn get_primitive_mode(mode: gltf::mesh::Mode) -> wgpu::PrimitiveTopology {
@@ -268,37 +768,43 @@ impl Gltf {
 
     fn get_texture_url(
         info: &Option<gltf::texture::Info<'_>>,
-        images: &Vec<gltf::Image<'_>>,
+        image_paths: &Vec<Option<String>>,
     ) -> Option<String> {
         let mut file_name = None;
         if info.is_some() {
             let info = info.as_ref().unwrap();
-            let tex = info.texture();
-
-            let image: Option<&gltf::Image<'_>> = images.get(tex.index());
-            if image.is_some() {
-                let image = image.unwrap();
-                let source = image.source();
-                match source {
-                    gltf::image::Source::Uri { uri, .. } => {
-                        let texture_file_name = Some(
-                            Path::new(&uri)
-                                .to_str()    
-                                .unwrap()
-                                .to_string(),
-                        );
-                        if texture_file_name.is_some() {
-                            file_name = Some(texture_file_name.unwrap());
-                        }
-                    }
-                    _ => (),
-                }
+            let image_index = info.texture().source().index();
+
+            let image_path = image_paths.get(image_index);
+            if image_path.is_some() {
+                file_name = image_path.unwrap().clone();
             }
         }
         file_name
     }
 }
 
+/// Generates smooth normals for a triangle list by averaging the normals of the faces each vertex is part of.
+/// Face normals aren't normalized before they're summed so larger triangles have more influence.
+/// Used by the loaders when a mesh doesn't contain normals, run it before tangents are generated.
+pub(crate) fn compute_normals(vertices: &mut [MeshVertexData], indices: &[u32]) {
+    for face in indices.chunks_exact(3) {
+        let a = vertices[face[0] as usize].position;
+        let b = vertices[face[1] as usize].position;
+        let c = vertices[face[2] as usize].position;
+        let normal = (b - a).cross(&(c - a));
+        for index in face.iter() {
+            vertices[*index as usize].normal += normal;
+        }
+    }
+
+    for vertex in vertices.iter_mut() {
+        if vertex.normal.magnitude() > 0.0 {
+            vertex.normal = vertex.normal.normalize();
+        }
+    }
+}
+
 fn vertex(sub_mesh: &SubMesh, face: usize, vert: usize) -> &MeshVertexData {
     &sub_mesh.vertices[sub_mesh.indices[face * 3 + vert] as usize]
 }
@@ -333,15 +839,45 @@ impl mikktspace::Geometry for SubMesh {
     }
 }
 
+// Cubic spline keyframes store an in tangent, the value and an out tangent, only the value is kept.
+fn spline_values<T: Copy>(values: Vec<T>) -> Vec<T> {
+    values
+        .chunks(3)
+        .filter_map(|keyframe| keyframe.get(1).copied())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Gltf;
+    use super::{compute_normals, Gltf, MeshVertexData};
     use crate::{
-        assets::{material_manager::MaterialManager, texture_manager::TextureManager},
+        assets::{material_manager::MaterialManager, texture_manager::TextureManager, AssetError},
         graphics::{pipelines::pbr::create_pbr_bindgroup_layout, resources::GPUResourceManager, shadows::ShadowQuality},
     };
+    use nalgebra_glm::Vec3;
     use std::{path::PathBuf, sync::Arc};
 
+    #[test]
+    fn should_compute_normals() {
+        let mut vertices: Vec<MeshVertexData> = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ]
+        .into_iter()
+        .map(|position| MeshVertexData {
+            position,
+            ..MeshVertexData::default()
+        })
+        .collect();
+
+        compute_normals(&mut vertices, &[0, 1, 2]);
+
+        for vertex in vertices.iter() {
+            assert_eq!(vertex.normal, Vec3::new(0.0, 0.0, 1.0));
+        }
+    }
+
     #[test]
     fn should_load_mesh() {
         futures::executor::block_on(async {
@@ -380,7 +916,11 @@ mod tests {
                 device.clone(),
                 ShadowQuality::Medium
             );
-            let gpu_resource_manager = Arc::new(GPUResourceManager::new(device.clone(), &omni_manager));
+            let cascade_manager = crate::graphics::shadows::CascadeShadowManager::new(
+                device.clone(),
+                ShadowQuality::Medium
+            );
+            let gpu_resource_manager = Arc::new(GPUResourceManager::new(device.clone(), &omni_manager, &cascade_manager));
 
             let pbr_bind_group_layout = create_pbr_bindgroup_layout(device.clone());
             gpu_resource_manager
@@ -392,14 +932,116 @@ mod tests {
                 Arc::new(texture_manager),
                 gpu_resource_manager,
                 PathBuf::from("./assets/"),
+                16,
             ));
 
-            let _mesh = Gltf::from_gltf(
+            let mesh = Gltf::from_gltf(
                 device.clone(),
                 material_manager,
                 PathBuf::from("./assets/example/meshes/cube/cube.gltf"),
             )
             .await;
+
+            assert_eq!(mesh.meshes.len(), 1);
+            assert_eq!(mesh.nodes.len(), 1);
+            assert_eq!(mesh.nodes[0].name, "Cube");
+            assert_eq!(mesh.nodes[0].mesh_index, Some(0));
+            assert_eq!(mesh.nodes[0].scale, nalgebra_glm::Vec3::new(1.0, 1.0, 1.0));
+
+            // The cube goes from -1 to 1, blender exported one of the corners slightly off.
+            let bounding_box = mesh.meshes[0].bounding_box();
+            assert_eq!(bounding_box.min, Vec3::new(-1.0, -1.0, -1.0));
+            assert!(nalgebra_glm::distance(&bounding_box.max, &Vec3::new(1.0, 1.0, 1.0)) < 0.0001);
+
+            let material = mesh.meshes[0].meshes.keys().next().unwrap();
+            let material = material.get_async().await.unwrap();
+            assert!(material.main_texture.handle_id.ends_with("Cube_BaseColor.png"));
+            assert!(material.normal_texture.handle_id.ends_with("Cube_normal.png"));
+        });
+    }
+
+    #[test]
+    fn should_load_embedded_textures() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: adapter.features(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+            (Arc::new(device), Arc::new(queue))
         });
+
+        let texture_manager = Arc::new(TextureManager::new(device.clone(), queue.clone()));
+        let omni_manager =
+            crate::graphics::shadows::OmniShadowManager::new(device.clone(), ShadowQuality::Medium);
+        let cascade_manager = crate::graphics::shadows::CascadeShadowManager::new(
+            device.clone(),
+            ShadowQuality::Medium,
+        );
+        let gpu_resource_manager = Arc::new(GPUResourceManager::new(
+            device.clone(),
+            &omni_manager,
+            &cascade_manager,
+        ));
+        gpu_resource_manager.add_bind_group_layout(
+            "pbr_material_layout",
+            create_pbr_bindgroup_layout(device.clone()),
+        );
+        let material_manager = Arc::new(MaterialManager::new(
+            device.clone(),
+            queue,
+            texture_manager.clone(),
+            gpu_resource_manager,
+            PathBuf::from("./assets/"),
+            16,
+        ));
+
+        // The buffer and both images are base64 data uris.
+        let mesh = futures::executor::block_on(Gltf::from_gltf(
+            device,
+            material_manager,
+            PathBuf::from("./assets/example/meshes/embedded/embedded.gltf"),
+        ));
+
+        // Each primitive keeps its own material.
+        assert_eq!(mesh.nodes[0].material_indices, vec![0, 1]);
+        assert_eq!(mesh.meshes[0].meshes.len(), 2);
+
+        let texture_path = "./assets/example/meshes/embedded/embedded_embedded_0.png";
+        let texture = texture_manager.get_sync(texture_path).get().unwrap();
+        assert_eq!((texture.extent.width, texture.extent.height), (2, 2));
+
+        // Images that can't be decoded are errors instead of panics.
+        let texture_path = "./assets/example/meshes/embedded/embedded_embedded_1.png";
+        let texture = texture_manager.get_sync(texture_path);
+        assert!(match *texture.get().err().unwrap() {
+            AssetError::InvalidData => true,
+            _ => false,
+        });
+
+        let main_textures: Vec<_> = mesh.meshes[0]
+            .meshes
+            .keys()
+            .map(|material| async_std::task::block_on(material.get_async()).unwrap())
+            .map(|material| material.main_texture.handle_id.clone())
+            .collect();
+        assert!(main_textures
+            .iter()
+            .any(|path| path.ends_with("embedded_embedded_0.png")));
+        assert!(main_textures.iter().any(|path| path.ends_with("white.png")));
     }
 }
@@ -1,16 +1,21 @@
 use super::{
-    file_manager::{AssetCache, AssetHandle},
+    file_manager::{is_missing, AssetCache, AssetCallbackMap, AssetError, AssetHandle},
     material::PBRMaterialRon,
     material_manager::MaterialManager,
     mesh::Gltf,
 };
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 pub struct MeshManager {
     device: Arc<wgpu::Device>,
     pool: Arc<ThreadPool>,
     cache: AssetCache<Gltf>,
+    callbacks: AssetCallbackMap<Gltf>,
     material_manager: Arc<MaterialManager<PBRMaterialRon>>,
 }
 
@@ -26,6 +31,7 @@ impl MeshManager {
             device,
             pool,
             cache,
+            callbacks: Arc::new(Mutex::new(HashMap::new())),
             material_manager,
         }
     }
@@ -33,11 +39,12 @@ impl MeshManager {
     pub fn get<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<Gltf>> {
         let path = path.into();
 
-        let asset_handle = Arc::new(AssetHandle::new(path.clone(), self.cache.clone()));
-
-        if !self.cache.contains_key(&path) {
-            let cache = self.cache.clone();
+        let asset_handle = Arc::new(
+            AssetHandle::new(path.clone(), self.cache.clone()).with_callbacks(self.callbacks.clone()),
+        );
 
+        // Missing meshes are loaded again in case the file was recreated.
+        if !self.cache.contains_key(&path) || is_missing(&self.cache, &path) {
             let asset_thread_handle = asset_handle.clone();
 
             let device = self.device.clone();
@@ -47,10 +54,21 @@ impl MeshManager {
                 let gltf = Gltf::from_gltf(device, material_manager, path.clone()).await;
 
                 log::info!("{:?} loaded.", path.file_name().unwrap());
-                cache.insert(asset_thread_handle.handle_id.clone(), Ok(Arc::new(gltf)));
+                asset_thread_handle.finish(Ok(Arc::new(gltf)));
             });
         }
 
         asset_handle
     }
+
+    /// Adds an already loaded mesh to the cache so it can be retrieved with `get`.
+    pub(crate) fn insert<P: Into<PathBuf>>(&self, path: P, gltf: Gltf) {
+        self.cache.insert(path.into(), Ok(Arc::new(gltf)));
+    }
+
+    /// Marks the mesh as missing so handles return `AssetError::FileNotFound`.
+    pub(crate) fn mark_removed(&self, path: &PathBuf) {
+        self.cache
+            .insert(path.clone(), Err(Arc::new(AssetError::FileNotFound)));
+    }
 }
@@ -0,0 +1,286 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+const MIPMAP_SHADER: &str = include_str!("../../assets/core/shaders/calculations/mipmap.comp.glsl");
+
+// Must match the local size in the mipmap compute shader.
+const WORKGROUP_SIZE: u32 = 8;
+
+struct MipmapPipeline {
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+    // The shader can't write sRGB textures so it writes this format which is then copied into the texture.
+    storage_format: wgpu::TextureFormat,
+}
+
+/// Fills in the mip levels of a texture with a compute shader.
+/// Each level is a bilinear downsample of the level before it.
+pub struct MipmapGenerator {
+    sampler: wgpu::Sampler,
+    // Compiled the first time a format is used.
+    pipelines: Mutex<HashMap<wgpu::TextureFormat, Arc<MipmapPipeline>>>,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("mipmap_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Self {
+            sampler,
+            pipelines: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rgba8 and Rgba16Float textures are supported.
+    /// Compressed formats can't be written by a shader and need their mip levels stored in the file.
+    pub fn supports(format: wgpu::TextureFormat) -> bool {
+        Self::storage_format(format).is_some()
+    }
+
+    /// The number of mip levels needed to go from `width` by `height` down to 1x1.
+    pub fn mip_count(width: u32, height: u32) -> u32 {
+        32 - width.max(height).max(1).leading_zeros()
+    }
+
+    /// Records the compute passes that generate levels 1 to `mip_count` from level 0 of the texture.
+    /// The texture needs to be created with `SAMPLED` and `COPY_DST` usage.
+    pub fn generate(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        format: wgpu::TextureFormat,
+        extent: wgpu::Extent3d,
+        mip_count: u32,
+    ) {
+        if mip_count <= 1 {
+            return;
+        }
+        let pipeline = match self.get_pipeline(device, format) {
+            Some(pipeline) => pipeline,
+            None => {
+                log::warn!("Can't generate mipmaps for {:?} textures.", format);
+                return;
+            }
+        };
+
+        // Level n of the texture is written to level n - 1 of the storage texture.
+        let storage_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("mipmap_storage"),
+            size: wgpu::Extent3d {
+                width: mip_size(extent.width, 1),
+                height: mip_size(extent.height, 1),
+                depth: 1,
+            },
+            mip_level_count: mip_count - 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: pipeline.storage_format,
+            usage: wgpu::TextureUsage::STORAGE | wgpu::TextureUsage::COPY_SRC,
+        });
+
+        for level in 1..mip_count {
+            let source_view = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                format,
+                dimension: wgpu::TextureViewDimension::D2,
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: level - 1,
+                level_count: 1,
+                base_array_layer: 0,
+                array_layer_count: 1,
+            });
+            let output_view = storage_texture.create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                format: pipeline.storage_format,
+                dimension: wgpu::TextureViewDimension::D2,
+                aspect: wgpu::TextureAspect::All,
+                base_mip_level: level - 1,
+                level_count: 1,
+                base_array_layer: 0,
+                array_layer_count: 1,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(Cow::Borrowed("mipmap")),
+                layout: &pipeline.layout,
+                entries: Cow::Borrowed(&[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&source_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&output_view),
+                    },
+                ]),
+            });
+
+            let width = mip_size(extent.width, level);
+            let height = mip_size(extent.height, level);
+            {
+                let mut pass = encoder.begin_compute_pass();
+                pass.set_pipeline(&pipeline.pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch(
+                    (width + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE,
+                    (height + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE,
+                    1,
+                );
+            }
+
+            // The next level reads this one back through the texture's own format.
+            encoder.copy_texture_to_texture(
+                wgpu::TextureCopyView {
+                    texture: &storage_texture,
+                    mip_level: level - 1,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                wgpu::TextureCopyView {
+                    texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth: 1,
+                },
+            );
+        }
+    }
+
+    // Returns the format the shader writes and the macros it's compiled with.
+    fn storage_format(
+        format: wgpu::TextureFormat,
+    ) -> Option<(wgpu::TextureFormat, &'static [&'static str])> {
+        match format {
+            wgpu::TextureFormat::Rgba8Unorm => Some((wgpu::TextureFormat::Rgba8Unorm, &[])),
+            wgpu::TextureFormat::Rgba8UnormSrgb => {
+                Some((wgpu::TextureFormat::Rgba8Unorm, &["SRGB_ENCODE"]))
+            }
+            wgpu::TextureFormat::Rgba16Float => {
+                Some((wgpu::TextureFormat::Rgba16Float, &["FORMAT_RGBA16F"]))
+            }
+            _ => None,
+        }
+    }
+
+    fn get_pipeline(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> Option<Arc<MipmapPipeline>> {
+        let (storage_format, macros) = Self::storage_format(format)?;
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let pipeline = pipelines
+            .entry(format)
+            .or_insert_with(|| Arc::new(Self::create_pipeline(device, storage_format, macros)));
+        Some(pipeline.clone())
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        storage_format: wgpu::TextureFormat,
+        macros: &[&str],
+    ) -> MipmapPipeline {
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let mut options = shaderc::CompileOptions::new().unwrap();
+        for name in macros.iter() {
+            options.add_macro_definition(name, Some("1"));
+        }
+        let spirv = compiler
+            .compile_into_spirv(
+                MIPMAP_SHADER,
+                shaderc::ShaderKind::Compute,
+                "mipmap.comp.glsl",
+                "main",
+                Some(&options),
+            )
+            .unwrap();
+        let module = device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(
+            spirv.as_binary(),
+        )));
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(Cow::Borrowed("mipmap_generator")),
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupLayoutEntry::new(
+                    0,
+                    wgpu::ShaderStage::COMPUTE,
+                    wgpu::BindingType::SampledTexture {
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                    },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    1,
+                    wgpu::ShaderStage::COMPUTE,
+                    wgpu::BindingType::Sampler { comparison: false },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    2,
+                    wgpu::ShaderStage::COMPUTE,
+                    wgpu::BindingType::StorageTexture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        format: storage_format,
+                        readonly: false,
+                    },
+                ),
+            ]),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: Cow::Borrowed(&[&layout]),
+            push_constant_ranges: Cow::Borrowed(&[]),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            layout: &pipeline_layout,
+            compute_stage: wgpu::ProgrammableStageDescriptor {
+                module: &module,
+                entry_point: Cow::Borrowed("main"),
+            },
+        });
+
+        MipmapPipeline {
+            layout,
+            pipeline,
+            storage_format,
+        }
+    }
+}
+
+// The size of a mip level, levels never get smaller than 1 pixel.
+fn mip_size(size: u32, level: u32) -> u32 {
+    (size >> level).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mip_size, MipmapGenerator};
+
+    #[test]
+    fn should_count_mip_levels() {
+        assert_eq!(MipmapGenerator::mip_count(1, 1), 1);
+        assert_eq!(MipmapGenerator::mip_count(256, 256), 9);
+        assert_eq!(MipmapGenerator::mip_count(300, 20), 9);
+        assert_eq!(mip_size(300, 8), 1);
+        assert_eq!(mip_size(20, 3), 2);
+    }
+}
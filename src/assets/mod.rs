@@ -1,20 +1,56 @@
 mod asset_manager;
 pub use asset_manager::AssetManager;
 
+mod audio;
+pub use audio::AudioClip;
+
 pub mod image;
-pub use self::image::Image;
+pub use self::image::{Image, ImageError};
 
 pub mod material;
 mod material_manager;
+pub use material_manager::CacheStats;
 
 pub mod texture;
+pub mod texture_atlas;
 mod texture_manager;
 
+mod compressed_texture;
+#[cfg(feature = "texture_compressor")]
+pub use compressed_texture::TextureCompressor;
+pub use compressed_texture::{BcFormat, CompressedImage};
+
+pub mod font_manager;
+
+mod mipmap_generator;
+pub use mipmap_generator::MipmapGenerator;
+
+mod noise_generator;
+pub(crate) use noise_generator::NoiseGenerator;
+pub use noise_generator::{NoiseTextureDesc, NoiseType};
+
+mod directory_watcher;
+
 mod file_manager;
 pub use file_manager::{AssetCache, AssetError, AssetHandle, FileManager};
 
 pub mod shader;
 mod shader_manager;
 
+pub mod animation;
 pub mod mesh;
+pub mod morph;
+pub mod skeleton;
 mod mesh_manager;
+
+mod obj;
+pub use obj::ObjLoadError;
+
+mod manifest;
+pub use manifest::{AssetManifest, ManifestError};
+
+mod terrain;
+pub(crate) mod voxel;
+
+mod lod;
+pub use lod::{lod_mesh_name, LodError};
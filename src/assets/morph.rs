@@ -0,0 +1,90 @@
+use super::mesh::MeshVertexData;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum MorphTargetError {
+    // Thrown when the mesh hasn't finished loading or failed to load.
+    NotLoaded(PathBuf),
+    // Thrown when none of the meshes in the file have a morph target with the name.
+    NotFound(String),
+}
+
+/// A blend shape of a sub mesh.
+/// Gltf files don't store target names in a standard place so targets are named `target_<index>`.
+#[derive(Debug, Clone)]
+pub struct MorphTarget {
+    pub name: String,
+    /// Added to the base vertices scaled by the target's weight, one per vertex.
+    /// The uv is unused and the tangent's w is always 0.
+    pub vertex_deltas: Vec<MeshVertexData>,
+}
+
+impl MorphTarget {
+    /// The name given to the target at `index` when loading a gltf file.
+    pub fn default_name(index: usize) -> String {
+        format!("target_{}", index)
+    }
+}
+
+/// Applies weighted morph targets to the base vertices on the CPU, matching the morph compute shader.
+pub fn apply_morph_targets(
+    vertices: &[MeshVertexData],
+    morph_targets: &[MorphTarget],
+    weights: &[f32],
+) -> Vec<MeshVertexData> {
+    vertices
+        .iter()
+        .enumerate()
+        .map(|(index, vertex)| {
+            let mut vertex = *vertex;
+            for (target, weight) in morph_targets.iter().zip(weights.iter()) {
+                let delta = &target.vertex_deltas[index];
+                vertex.position += delta.position * *weight;
+                vertex.normal += delta.normal * *weight;
+                vertex.tangent += delta.tangent * *weight;
+            }
+            if vertex.normal.norm() > 0.0 {
+                vertex.normal = vertex.normal.normalize();
+            }
+            vertex
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_morph_targets, MorphTarget};
+    use crate::assets::mesh::MeshVertexData;
+    use nalgebra_glm::Vec3;
+
+    #[test]
+    fn should_blend_targets() {
+        let vertices = vec![MeshVertexData {
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            ..Default::default()
+        }];
+        let targets = vec![
+            MorphTarget {
+                name: MorphTarget::default_name(0),
+                vertex_deltas: vec![MeshVertexData {
+                    position: Vec3::new(2.0, 0.0, 0.0),
+                    ..Default::default()
+                }],
+            },
+            MorphTarget {
+                name: MorphTarget::default_name(1),
+                vertex_deltas: vec![MeshVertexData {
+                    position: Vec3::new(0.0, 4.0, 0.0),
+                    ..Default::default()
+                }],
+            },
+        ];
+
+        let morphed = apply_morph_targets(&vertices, &targets, &[0.5, 0.25]);
+        assert_eq!(morphed[0].position, Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(morphed[0].normal, Vec3::new(0.0, 1.0, 0.0));
+
+        let morphed = apply_morph_targets(&vertices, &targets, &[]);
+        assert_eq!(morphed[0].position, Vec3::zeros());
+    }
+}
@@ -0,0 +1,235 @@
+use super::texture::Texture;
+use std::{
+    borrow::Cow,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+const NOISE_SHADER: &str = include_str!("../../assets/core/shaders/calculations/noise.comp.glsl");
+
+// Must match the local size in the noise compute shader.
+const WORKGROUP_SIZE: u32 = 8;
+
+/// The kind of noise `TextureManager::generate_noise` fills a texture with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseType {
+    /// Smooth gradient noise on a square grid.
+    Perlin,
+    /// Gradient noise on a triangular grid, with fewer directional artifacts than Perlin noise.
+    Simplex,
+    /// The distance to the closest of a set of random points, looks like cells.
+    Worley,
+}
+
+/// Describes a noise texture, see `TextureManager::generate_noise`.
+/// The noise is stored in the rgb channels of an `Rgba8Unorm` texture, alpha is always 1.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseTextureDesc {
+    pub width: u32,
+    pub height: u32,
+    pub noise_type: NoiseType,
+    /// How many layers of noise are added together, each one at twice the frequency of the last.
+    pub octaves: u32,
+    /// How many times the first octave repeats across the texture.
+    pub frequency: f32,
+    /// How much each octave's amplitude is scaled by compared to the one before it.
+    pub persistence: f32,
+}
+
+impl NoiseTextureDesc {
+    /// Four octaves of noise, each half as strong as the one before it.
+    pub fn new(width: u32, height: u32, noise_type: NoiseType) -> Self {
+        Self {
+            width,
+            height,
+            noise_type,
+            octaves: 4,
+            frequency: 4.0,
+            persistence: 0.5,
+        }
+    }
+
+    // Matches `params` in the noise compute shader.
+    fn params(&self) -> [f32; 4] {
+        let noise_type = match self.noise_type {
+            NoiseType::Perlin => 0.0,
+            NoiseType::Simplex => 1.0,
+            NoiseType::Worley => 2.0,
+        };
+        [
+            self.frequency,
+            self.persistence,
+            self.octaves.max(1) as f32,
+            noise_type,
+        ]
+    }
+}
+
+struct NoisePipeline {
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+}
+
+/// Fills textures with procedural noise using a compute shader.
+#[derive(Default)]
+pub struct NoiseGenerator {
+    // Compiled the first time a texture is generated.
+    pipeline: Mutex<Option<Arc<NoisePipeline>>>,
+}
+
+impl NoiseGenerator {
+    /// Creates the texture and submits the compute pass that writes the noise into it.
+    pub fn generate(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: PathBuf,
+        desc: &NoiseTextureDesc,
+    ) -> Texture {
+        let pipeline = self.get_pipeline(device);
+        let extent = wgpu::Extent3d {
+            width: desc.width.max(1),
+            height: desc.height.max(1),
+            depth: 1,
+        };
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        // wgpu moves the texture from storage to sampled usage between the compute pass and the first draw.
+        let inner = device.create_texture(&wgpu::TextureDescriptor {
+            label: path.to_str(),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::STORAGE
+                | wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = inner.create_default_view();
+
+        let params_buffer = device.create_buffer_with_data(
+            bytemuck::cast_slice(&desc.params()),
+            wgpu::BufferUsage::UNIFORM,
+        );
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(Cow::Borrowed("noise")),
+            layout: &pipeline.layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(params_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ]),
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("noise_texture"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass();
+            pass.set_pipeline(&pipeline.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch(
+                workgroup_count(extent.width),
+                workgroup_count(extent.height),
+                1,
+            );
+        }
+        queue.submit(Some(encoder.finish()));
+
+        Texture {
+            path,
+            inner,
+            view,
+            extent,
+            format,
+            mip_count: 1,
+        }
+    }
+
+    fn get_pipeline(&self, device: &wgpu::Device) -> Arc<NoisePipeline> {
+        let mut pipeline = self.pipeline.lock().unwrap();
+        pipeline
+            .get_or_insert_with(|| Arc::new(Self::create_pipeline(device)))
+            .clone()
+    }
+
+    fn create_pipeline(device: &wgpu::Device) -> NoisePipeline {
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let spirv = compiler
+            .compile_into_spirv(
+                NOISE_SHADER,
+                shaderc::ShaderKind::Compute,
+                "noise.comp.glsl",
+                "main",
+                None,
+            )
+            .unwrap();
+        let module = device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(
+            spirv.as_binary(),
+        )));
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(Cow::Borrowed("noise_generator")),
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupLayoutEntry::new(
+                    0,
+                    wgpu::ShaderStage::COMPUTE,
+                    wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: None,
+                    },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    1,
+                    wgpu::ShaderStage::COMPUTE,
+                    wgpu::BindingType::StorageTexture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        readonly: false,
+                    },
+                ),
+            ]),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: Cow::Borrowed(&[&layout]),
+            push_constant_ranges: Cow::Borrowed(&[]),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            layout: &pipeline_layout,
+            compute_stage: wgpu::ProgrammableStageDescriptor {
+                module: &module,
+                entry_point: Cow::Borrowed("main"),
+            },
+        });
+
+        NoisePipeline { layout, pipeline }
+    }
+}
+
+// Enough workgroups to cover `size` pixels.
+fn workgroup_count(size: u32) -> u32 {
+    (size + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{workgroup_count, NoiseTextureDesc, NoiseType};
+
+    #[test]
+    fn should_pack_params() {
+        let mut desc = NoiseTextureDesc::new(100, 50, NoiseType::Worley);
+        assert_eq!(desc.params(), [4.0, 0.5, 4.0, 2.0]);
+        // There's always at least one octave.
+        desc.octaves = 0;
+        assert_eq!(desc.params()[2], 1.0);
+
+        assert_eq!(workgroup_count(100), 13);
+        assert_eq!(workgroup_count(8), 1);
+    }
+}
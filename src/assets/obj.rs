@@ -0,0 +1,296 @@
+use super::{
+    material::{BlendMode, PBRMaterialRon},
+    material_manager::MaterialManager,
+    mesh::{compute_normals, Gltf, GltfNode, Mesh, MeshVertexData, SubMesh},
+};
+use crate::core::{Aabb, BoundingSphere};
+use nalgebra_glm::{Quat, Vec2, Vec3, Vec4};
+use std::{collections::HashMap, ffi::OsStr, path::PathBuf, sync::RwLock};
+
+#[derive(Debug)]
+pub enum ObjLoadError {
+    // Thrown when the obj file wasn't found.
+    FileNotFound(PathBuf),
+    // Thrown when tobj fails to parse the obj or mtl file.
+    ParseError(tobj::LoadError),
+    // Thrown when the mtl file references a texture that doesn't exist.
+    MissingTexture(PathBuf),
+}
+
+/// Loads an obj file into the same mesh format gltf files use.
+/// Faces are grouped into one sub mesh per material.
+pub(crate) fn load_obj(
+    device: &wgpu::Device,
+    material_manager: &MaterialManager<PBRMaterialRon>,
+    path: PathBuf,
+) -> Result<Gltf, ObjLoadError> {
+    if !path.exists() {
+        return Err(ObjLoadError::FileNotFound(path));
+    }
+
+    let (models, obj_materials) =
+        tobj::load_obj(&path, true).map_err(|error| ObjLoadError::ParseError(error))?;
+
+    // Make sure every texture exists before we queue anything up.
+    let parent = path.parent().unwrap().to_path_buf();
+    for obj_material in obj_materials.iter() {
+        for texture in [&obj_material.diffuse_texture, &obj_material.normal_texture].iter() {
+            if !texture.is_empty() && !parent.join(texture).exists() {
+                return Err(ObjLoadError::MissingTexture(parent.join(texture)));
+            }
+        }
+    }
+
+    // Collect the vertices and indices for each material keeping the order they first appear in.
+    let mut material_order: Vec<Option<usize>> = Vec::new();
+    let mut groups: HashMap<Option<usize>, (Vec<MeshVertexData>, Vec<u32>)> = HashMap::new();
+    for model in models.iter() {
+        let obj_mesh = &model.mesh;
+        if !groups.contains_key(&obj_mesh.material_id) {
+            material_order.push(obj_mesh.material_id);
+        }
+        let (vertices, indices) = groups
+            .entry(obj_mesh.material_id)
+            .or_insert((Vec::new(), Vec::new()));
+
+        let index_offset = vertices.len() as u32;
+        let vertex_count = obj_mesh.positions.len() / 3;
+        for i in 0..vertex_count {
+            let position = Vec3::new(
+                obj_mesh.positions[i * 3],
+                obj_mesh.positions[i * 3 + 1],
+                obj_mesh.positions[i * 3 + 2],
+            );
+            let normal = if obj_mesh.normals.len() >= (i + 1) * 3 {
+                Vec3::new(
+                    obj_mesh.normals[i * 3],
+                    obj_mesh.normals[i * 3 + 1],
+                    obj_mesh.normals[i * 3 + 2],
+                )
+            } else {
+                Vec3::zeros()
+            };
+            // Obj uv's start at the bottom left.
+            let uv = if obj_mesh.texcoords.len() >= (i + 1) * 2 {
+                Vec2::new(
+                    obj_mesh.texcoords[i * 2],
+                    1.0 - obj_mesh.texcoords[i * 2 + 1],
+                )
+            } else {
+                Vec2::zeros()
+            };
+            vertices.push(MeshVertexData {
+                position,
+                normal,
+                uv,
+                ..MeshVertexData::default()
+            });
+        }
+
+        let model_indices: Vec<u32> = obj_mesh.indices.iter().map(|i| i + index_offset).collect();
+        if obj_mesh.normals.is_empty() {
+            compute_normals(&mut vertices[index_offset as usize..], &obj_mesh.indices);
+        }
+        indices.extend(model_indices);
+    }
+
+    let name = path
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .unwrap_or("obj")
+        .to_string();
+    let mut mesh = Mesh {
+        name: name.clone(),
+        meshes: HashMap::new(),
+        bounding_sphere: BoundingSphere::new(),
+        bounding_box: Aabb::new(),
+        morph_weights: RwLock::new(Vec::new()),
+    };
+
+    for material_id in material_order {
+        let (vertices, indices) = groups.remove(&material_id).unwrap();
+        let material = match material_id.and_then(|id| obj_materials.get(id)) {
+            Some(obj_material) => create_material(obj_material),
+            None => create_material(&tobj::Material::default()),
+        };
+        // Textures are loaded through the texture manager relative to the obj file.
+        let material_handle = material_manager.insert(material, path.clone());
+
+        // Obj files don't store tangents so they're always generated.
+        let sub_mesh = SubMesh::new(device, vertices, indices, true);
+        mesh.meshes.insert(material_handle, sub_mesh);
+    }
+
+    mesh.bounding_sphere = BoundingSphere::from_bounding_spheres(
+        mesh.meshes.values().map(|x| &x.bounding_sphere).collect(),
+    );
+    mesh.bounding_box = mesh
+        .meshes
+        .values()
+        .fold(Aabb::new(), |aabb, x| aabb.merge(&x.bounding_box));
+    let bounding_sphere = mesh.bounding_sphere;
+
+    Ok(Gltf {
+        meshes: vec![mesh],
+        nodes: vec![GltfNode {
+            name,
+            mesh_index: Some(0),
+            material_indices: Vec::new(),
+            skin_index: None,
+            position: Vec3::zeros(),
+            rotation: Quat::identity(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }],
+        skins: Vec::new(),
+        animations: Vec::new(),
+        bounding_sphere,
+    })
+}
+
+fn create_material(obj_material: &tobj::Material) -> PBRMaterialRon {
+    let texture_or = |texture: &String, default: &str| {
+        if texture.is_empty() {
+            default.to_string()
+        } else {
+            texture.clone()
+        }
+    };
+
+    PBRMaterialRon {
+        main_texture: texture_or(&obj_material.diffuse_texture, "core/white.png"),
+        normal_texture: texture_or(&obj_material.normal_texture, "core/empty_normal.png"),
+        roughness_texture: "core/pbr_flat.png".to_string(),
+        // Converts the blinn-phong specular exponent into a roughness value.
+        roughness: (2.0 / (obj_material.shininess.max(0.0) + 2.0)).sqrt(),
+        metallic: 0.0,
+        roughness_override: 1.0,
+        metallic_override: 1.0,
+        color: Vec4::new(
+            obj_material.diffuse[0],
+            obj_material.diffuse[1],
+            obj_material.diffuse[2],
+            obj_material.dissolve,
+        ),
+        uv_rect: None,
+        // tobj doesn't parse the emissive values so read them from the unknown parameters.
+        emissive_color: obj_material.unknown_param.get("Ke").and_then(|value| {
+            let channels = value
+                .split_whitespace()
+                .map(|channel| channel.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .ok()?;
+            match channels.as_slice() {
+                [r, g, b] => Some([*r, *g, *b, 1.0]),
+                _ => None,
+            }
+        }),
+        emissive_texture: obj_material.unknown_param.get("map_Ke").cloned(),
+        // A dissolve of 0.0 is what tobj defaults to when there's no material, so it's treated as opaque.
+        blend_mode: if obj_material.dissolve > 0.0 && obj_material.dissolve < 1.0 {
+            BlendMode::Transparent
+        } else {
+            BlendMode::Opaque
+        },
+        receive_decals: true,
+        alpha_cutoff: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::load_obj;
+    use crate::{
+        assets::{
+            material::BlendMode, material_manager::MaterialManager,
+            texture_manager::TextureManager,
+        },
+        graphics::{
+            pipelines::pbr::create_pbr_bindgroup_layout, resources::GPUResourceManager,
+            shadows::ShadowQuality,
+        },
+    };
+    use nalgebra_glm::{Vec3, Vec4};
+    use std::{path::PathBuf, sync::Arc};
+
+    #[test]
+    fn should_load_obj() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: adapter.features(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+            (Arc::new(device), Arc::new(queue))
+        });
+
+        let texture_manager = Arc::new(TextureManager::new(device.clone(), queue.clone()));
+        let omni_manager =
+            crate::graphics::shadows::OmniShadowManager::new(device.clone(), ShadowQuality::Medium);
+        let cascade_manager = crate::graphics::shadows::CascadeShadowManager::new(
+            device.clone(),
+            ShadowQuality::Medium,
+        );
+        let gpu_resource_manager = Arc::new(GPUResourceManager::new(
+            device.clone(),
+            &omni_manager,
+            &cascade_manager,
+        ));
+        gpu_resource_manager.add_bind_group_layout(
+            "pbr_material_layout",
+            create_pbr_bindgroup_layout(device.clone()),
+        );
+        let material_manager = MaterialManager::new(
+            device.clone(),
+            queue,
+            texture_manager,
+            gpu_resource_manager,
+            PathBuf::from("./assets/"),
+            16,
+        );
+
+        let obj = load_obj(
+            &device,
+            &material_manager,
+            PathBuf::from("./assets/example/meshes/quad/quad.obj"),
+        )
+        .unwrap();
+        assert_eq!(obj.meshes.len(), 1);
+        assert_eq!(obj.nodes[0].mesh_index, Some(0));
+
+        // One sub mesh per material.
+        let mesh = &obj.meshes[0];
+        assert_eq!(mesh.meshes.len(), 2);
+        for (material_handle, sub_mesh) in mesh.meshes.iter() {
+            let material = async_std::task::block_on(material_handle.get_async()).unwrap();
+            let (color, blend_mode, vertex_count, index_count) = if sub_mesh.vertices.len() == 4 {
+                (Vec4::new(1.0, 0.0, 0.0, 1.0), BlendMode::Opaque, 4, 6)
+            } else {
+                (Vec4::new(0.0, 1.0, 0.0, 0.5), BlendMode::Transparent, 3, 3)
+            };
+            assert_eq!(material.color, color);
+            assert_eq!(material.blend_mode, blend_mode);
+            assert_eq!(sub_mesh.vertices.len(), vertex_count);
+            assert_eq!(sub_mesh.indices.len(), index_count);
+
+            // The file has no normals, the generated ones face the front of the quad.
+            for vertex in sub_mesh.vertices.iter() {
+                assert!(nalgebra_glm::distance(&vertex.normal, &Vec3::new(0.0, 0.0, 1.0)) < 0.0001);
+            }
+        }
+        assert_eq!(mesh.bounding_box().max, Vec3::new(2.0, 1.0, 0.0));
+    }
+}
@@ -1,6 +1,7 @@
+use crate::graphics::PipelineCache;
 use shaderc;
 use std::io::BufRead;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{borrow::Cow, sync::Arc};
 
 pub enum Shader {
@@ -17,8 +18,175 @@ pub struct ComputeShader {
     pub compute: wgpu::ShaderModule,
 }
 
+/// A constant baked into the shader when it's compiled so one shader can have multiple code paths
+/// without branching at runtime. Shaders read it as a preprocessor define, e.g. `#if ENABLE_VERTEX_COLOR`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct SpecializationConstant {
+    pub name: String,
+    pub value: u32,
+}
+
+impl SpecializationConstant {
+    pub fn new<T: Into<String>>(name: T, value: u32) -> Self {
+        Self {
+            name: name.into(),
+            value,
+        }
+    }
+
+    pub fn from_bool<T: Into<String>>(name: T, value: bool) -> Self {
+        Self::new(name, value as u32)
+    }
+}
+
+/// The code of one shader module, the format is picked from the file extension when it's loaded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShaderSource {
+    SpirV(Vec<u32>),
+    Wgsl(String),
+}
+
+// The first word of every SPIR-V module.
+const SPIRV_MAGIC_NUMBER: u32 = 0x0723_0203;
+
+// Splits a SPIR-V file into words, None if it isn't SPIR-V.
+fn spirv_words(bytes: &[u8]) -> Option<Vec<u32>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    let words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+    if words.first() == Some(&SPIRV_MAGIC_NUMBER) {
+        Some(words)
+    } else {
+        None
+    }
+}
+
+fn is_single_file_shader(path: &Path) -> bool {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("wgsl") | Some("spv") => true,
+        _ => false,
+    }
+}
+
+impl ShaderSource {
+    /// Reads a `.wgsl` or `.spv` file as it is, anything else is compiled from GLSL as a `kind` shader.
+    /// GLSL found in `cache` isn't compiled again.
+    pub(crate) fn load(
+        path: &Path,
+        kind: shaderc::ShaderKind,
+        compiler: &mut shaderc::Compiler,
+        options: &shaderc::CompileOptions,
+        cache: Option<&PipelineCache>,
+    ) -> std::io::Result<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("wgsl") => Ok(ShaderSource::Wgsl(std::fs::read_to_string(path)?)),
+            Some("spv") => {
+                let bytes = std::fs::read(path)?;
+                match spirv_words(&bytes) {
+                    Some(words) => Ok(ShaderSource::SpirV(words)),
+                    None => panic!("{:?} isn't a SPIR-V file!", path),
+                }
+            }
+            _ => {
+                let contents = std::fs::read_to_string(path)?;
+                let file_name = path.file_name().unwrap().to_str().unwrap();
+                let cache = cache.map(|cache| {
+                    // Preprocessing is cheap and resolves the includes and constants the key depends on.
+                    let preprocessed = compiler
+                        .preprocess(&contents, file_name, "main", Some(options))
+                        .unwrap();
+                    (cache, PipelineCache::key(&preprocessed.as_text(), kind))
+                });
+                if let Some(spirv) = cache.and_then(|(cache, key)| cache.get(key)) {
+                    return Ok(ShaderSource::SpirV(spirv));
+                }
+
+                let spirv = compiler
+                    .compile_into_spirv(&contents, kind, file_name, "main", Some(options))
+                    .unwrap()
+                    .as_binary()
+                    .to_vec();
+                if let Some((cache, key)) = cache {
+                    cache.insert(key, spirv.clone());
+                }
+                Ok(ShaderSource::SpirV(spirv))
+            }
+        }
+    }
+
+    pub fn create_module(&self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        match self {
+            ShaderSource::SpirV(words) => {
+                device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(words)))
+            }
+            ShaderSource::Wgsl(code) => {
+                device.create_shader_module(wgpu::ShaderModuleSource::Wgsl(Cow::Borrowed(code)))
+            }
+        }
+    }
+}
+
 impl Shader {
     pub fn new<T: Into<PathBuf>>(device: Arc<wgpu::Device>, path: T) -> Arc<Self> {
+        Self::new_specialized(device, path, &[])
+    }
+
+    /// Creates a render shader from already compiled SPIR-V, both stages use `main` as their entry point.
+    pub fn from_spirv(device: &wgpu::Device, vertex: &[u32], fragment: &[u32]) -> Self {
+        Shader::Core(CoreShader {
+            vertex: device
+                .create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(vertex))),
+            fragment: device
+                .create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(fragment))),
+        })
+    }
+
+    /// Creates a compute shader from already compiled SPIR-V with `main` as the entry point.
+    pub fn from_spirv_compute(device: &wgpu::Device, compute: &[u32]) -> Self {
+        Shader::Compute(ComputeShader {
+            compute: device
+                .create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(compute))),
+        })
+    }
+
+    /// Creates every stage from one module. Files with "comp" in their name are compute shaders,
+    /// anything else is a render shader whose vertex and fragment entry points are both called `main`.
+    pub fn from_source(device: &wgpu::Device, path: &Path, source: &ShaderSource) -> Self {
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        if file_name.contains("comp") {
+            Shader::Compute(ComputeShader {
+                compute: source.create_module(device),
+            })
+        } else {
+            Shader::Core(CoreShader {
+                vertex: source.create_module(device),
+                fragment: source.create_module(device),
+            })
+        }
+    }
+
+    /// Compiles the shader with the given specialization constants defined.
+    /// `path` is either a `.shader` file listing a GLSL, WGSL or SPIR-V file per stage, or a single `.wgsl` or `.spv` file.
+    pub fn new_specialized<T: Into<PathBuf>>(
+        device: Arc<wgpu::Device>,
+        path: T,
+        constants: &[SpecializationConstant],
+    ) -> Arc<Self> {
+        Self::new_cached(device, path, constants, None)
+    }
+
+    /// Same as `new_specialized` but GLSL stages are looked up in `cache` before they're compiled,
+    /// stages that had to be compiled are added to it.
+    pub fn new_cached<T: Into<PathBuf>>(
+        device: Arc<wgpu::Device>,
+        path: T,
+        constants: &[SpecializationConstant],
+        cache: Option<&PipelineCache>,
+    ) -> Arc<Self> {
         let path = path.into();
         let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
         let path = path.parent().unwrap();
@@ -36,7 +204,11 @@ impl Shader {
             options.set_optimization_level(shaderc::OptimizationLevel::Zero);
         }
 
+        // Only GLSL stages see the constants, WGSL and SPIR-V are used as they are.
         options.add_macro_definition("EP", Some("main"));
+        for constant in constants.iter() {
+            options.add_macro_definition(&constant.name, Some(&constant.value.to_string()));
+        }
         options.set_include_callback(|file_path, _include_type, _, _| {
             let shader_path = path.clone().join(file_path);
             // let mut contents: String = "".into();
@@ -47,7 +219,20 @@ impl Shader {
             })
         });
 
+        // WGSL and SPIR-V files hold every stage themselves, so they're loaded without a `.shader` file.
         let shader_path = path.join(file_name);
+        if is_single_file_shader(&shader_path) {
+            let source = ShaderSource::load(
+                &shader_path,
+                shaderc::ShaderKind::Vertex,
+                &mut compiler,
+                &options,
+                cache,
+            )
+            .unwrap();
+            return Arc::new(Self::from_source(&device, &shader_path, &source));
+        }
+
         let file = std::fs::File::open(&shader_path).unwrap();
 
         let shader_file = std::io::BufReader::new(&file);
@@ -67,58 +252,18 @@ impl Shader {
             }
         }
 
-        // Pixel
-        let shader_path = path.join(frag_file_name.clone());
-        let frag_contents = std::fs::read_to_string(&shader_path);
-
-        // Vertex
-        let shader_path = path.join(vert_file_name.clone());
-        let vert_contents = std::fs::read_to_string(&shader_path);
-
-        // Vertex
-        let shader_path = path.join(comp_file_name.clone());
-        let comp_contents = std::fs::read_to_string(&shader_path);
-
-        options.add_macro_definition("EP", Some("main"));
-
-        let vertex = if vert_contents.is_ok() {
-            let spirv = compiler
-                .compile_into_spirv(
-                    &vert_contents.unwrap(),
-                    shaderc::ShaderKind::Vertex,
-                    "vertex.glsl",
-                    "main",
-                    Some(&options),
-                )
-                .unwrap();
-            Some(device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(spirv.as_binary()))))
-        } else { None };
-
-        let fragment = if frag_contents.is_ok() {
-            let spirv = compiler
-                .compile_into_spirv(
-                    &frag_contents.unwrap(),
-                    shaderc::ShaderKind::Fragment,
-                    "pixel.glsl",
-                    "main",
-                    Some(&options),
-                )
-                .unwrap();
-            Some(device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(spirv.as_binary()))))
-        } else { None };
-
-        let compute = if comp_contents.is_ok() {
-            let spirv = compiler
-                .compile_into_spirv(
-                    &comp_contents.unwrap(),
-                    shaderc::ShaderKind::Compute,
-                    "compute.glsl",
-                    "main",
-                    Some(&options),
-                )
-                .unwrap();
-            Some(device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(spirv.as_binary()))))
-        } else { None };
+        // Stages that aren't listed are skipped.
+        let mut load_stage = |file_name: &str, kind: shaderc::ShaderKind| {
+            if file_name.is_empty() {
+                return None;
+            }
+            ShaderSource::load(&path.join(file_name), kind, &mut compiler, &options, cache)
+                .ok()
+                .map(|source| source.create_module(&device))
+        };
+        let vertex = load_stage(&vert_file_name, shaderc::ShaderKind::Vertex);
+        let fragment = load_stage(&frag_file_name, shaderc::ShaderKind::Fragment);
+        let compute = load_stage(&comp_file_name, shaderc::ShaderKind::Compute);
 
         if fragment.is_some() && vertex.is_some() {
             return Arc::new(Shader::Core(CoreShader {
@@ -137,8 +282,8 @@ impl Shader {
 
 #[cfg(test)]
 mod tests {
-    use super::Shader;
-    use std::sync::Arc;
+    use super::{spirv_words, Shader, ShaderSource, SPIRV_MAGIC_NUMBER};
+    use std::{path::Path, sync::Arc};
 
     #[test]
     fn should_load_shader() {
@@ -169,7 +314,46 @@ mod tests {
 
             let device = Arc::new(device);
 
-            Shader::new(device, "./assets/core/shaders/pbr.shader");
+            Shader::new(device.clone(), "./assets/core/shaders/pbr.shader");
+            match *Shader::new(device, "./assets/core/shaders/unlit.wgsl") {
+                Shader::Core(_) => {}
+                Shader::Compute(_) => panic!("Expected a render shader"),
+            }
         });
     }
+
+    #[test]
+    fn should_pick_source_from_extension() {
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let options = shaderc::CompileOptions::new().unwrap();
+        let load = |path: &str, compiler: &mut shaderc::Compiler| {
+            ShaderSource::load(
+                Path::new(path),
+                shaderc::ShaderKind::Vertex,
+                compiler,
+                &options,
+                None,
+            )
+            .unwrap()
+        };
+
+        match load("./assets/core/shaders/unlit.wgsl", &mut compiler) {
+            ShaderSource::Wgsl(code) => assert!(code.contains("entry_point")),
+            ShaderSource::SpirV(_) => panic!("Expected WGSL"),
+        }
+        match load("./assets/core/shaders/unlit.vert.glsl", &mut compiler) {
+            ShaderSource::SpirV(words) => assert_eq!(words[0], SPIRV_MAGIC_NUMBER),
+            ShaderSource::Wgsl(_) => panic!("Expected SPIR-V"),
+        }
+    }
+
+    #[test]
+    fn should_reject_files_that_arent_spirv() {
+        let module = [SPIRV_MAGIC_NUMBER, 0x0001_0000];
+        let bytes: Vec<u8> = module.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect();
+        assert_eq!(spirv_words(&bytes), Some(module.to_vec()));
+        assert_eq!(spirv_words(&bytes[..7]), None);
+        // Four bytes of WGSL.
+        assert_eq!(spirv_words(b"var "), None);
+    }
 }
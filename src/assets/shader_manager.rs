@@ -1,14 +1,19 @@
 use super::{
     file_manager::{AssetCache, AssetHandle},
-    shader::Shader,
+    shader::{Shader, SpecializationConstant},
 };
+use crate::graphics::PipelineCache;
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
 
 pub struct ShaderManager {
     pool: Arc<ThreadPool>,
     cache: AssetCache<Shader>,
     device: Arc<wgpu::Device>,
+    pipeline_cache: RwLock<Option<Arc<PipelineCache>>>,
 }
 
 impl ShaderManager {
@@ -20,17 +25,31 @@ impl ShaderManager {
             pool,
             cache,
             device,
+            pipeline_cache: RwLock::new(None),
         }
     }
 
+    /// Shaders loaded from now on look their GLSL stages up in `pipeline_cache` before compiling them.
+    pub fn set_pipeline_cache(&self, pipeline_cache: Arc<PipelineCache>) {
+        *self.pipeline_cache.write().unwrap() = Some(pipeline_cache);
+    }
+
     pub fn get<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<Shader>> {
-        let path = path.into();
+        self.get_specialized(path, &[])
+    }
 
-        let asset_handle = Arc::new(AssetHandle::new(path.clone(), self.cache.clone()));
+    /// Each set of constants compiles into it's own shader which is cached separately.
+    pub fn get_specialized<P: Into<PathBuf>>(
+        &self,
+        path: P,
+        constants: &[SpecializationConstant],
+    ) -> Arc<AssetHandle<Shader>> {
+        let path = path.into();
+        let key = specialized_key(&path, constants);
 
-        if !self.cache.contains_key(&path) {
-            let cache = self.cache.clone();
+        let asset_handle = Arc::new(AssetHandle::new(key.clone(), self.cache.clone()));
 
+        if !self.cache.contains_key(&key) {
             let asset_thread_handle = asset_handle.clone();
             let device = self.device.clone();
 
@@ -38,10 +57,12 @@ impl ShaderManager {
             // TODO: Just fix this when naga comes out..
             // self.pool.spawn_ok(async move {
             // TODO: Make sure we return errors!!
-            let shader = Shader::new(device, path.clone());
+            let pipeline_cache = self.pipeline_cache.read().unwrap();
+            let shader =
+                Shader::new_cached(device, path.clone(), constants, pipeline_cache.as_deref());
             
-            log::info!("{:?} loaded.", path.file_name().unwrap());
-            cache.insert(asset_thread_handle.handle_id.clone(), Ok(shader));
+            log::info!("{:?} loaded.", key.file_name().unwrap());
+            asset_thread_handle.finish(Ok(shader));
             // });
         }
 
@@ -49,10 +70,24 @@ impl ShaderManager {
     }
 }
 
+// Appends the constants to the file name so every variant gets it's own cache entry.
+fn specialized_key(path: &PathBuf, constants: &[SpecializationConstant]) -> PathBuf {
+    if constants.is_empty() {
+        return path.clone();
+    }
+
+    let mut file_name = path.file_name().unwrap().to_str().unwrap().to_string();
+    for constant in constants.iter() {
+        file_name.push_str(&format!("#{}={}", constant.name, constant.value));
+    }
+    path.with_file_name(file_name)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::ShaderManager;
-    use std::sync::Arc;
+    use super::{specialized_key, ShaderManager};
+    use crate::assets::shader::SpecializationConstant;
+    use std::{path::PathBuf, sync::Arc};
 
     #[test]
     fn should_load_shader() {
@@ -91,4 +126,17 @@ mod tests {
         let shader = handle.get();
         assert!(shader.is_ok());
     }
+
+    #[test]
+    fn specialized_shaders_get_their_own_key() {
+        let path = PathBuf::from("./assets/core/shaders/unlit.shader");
+        assert_eq!(specialized_key(&path, &[]), path);
+        assert_eq!(
+            specialized_key(
+                &path,
+                &[SpecializationConstant::from_bool("ENABLE_VERTEX_COLOR", true)]
+            ),
+            PathBuf::from("./assets/core/shaders/unlit.shader#ENABLE_VERTEX_COLOR=1")
+        );
+    }
 }
@@ -0,0 +1,186 @@
+use nalgebra_glm::{Mat4, Quat, Vec3};
+
+/// The most joints a skeleton can have. Must match the skinning compute shader.
+pub const MAX_JOINTS: usize = 256;
+
+/// The local transform of a joint relative to its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JointTransform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for JointTransform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::zeros(),
+            rotation: Quat::identity(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl JointTransform {
+    pub fn matrix(&self) -> Mat4 {
+        nalgebra_glm::translation(&self.translation)
+            * nalgebra_glm::quat_to_mat4(&self.rotation)
+            * nalgebra_glm::scaling(&self.scale)
+    }
+
+    /// Blends towards `other`, `t` of 0.0 returns self and 1.0 returns `other`.
+    pub fn lerp(&self, other: &JointTransform, t: f32) -> JointTransform {
+        JointTransform {
+            translation: nalgebra_glm::lerp(&self.translation, &other.translation, t),
+            rotation: nalgebra_glm::quat_slerp(&self.rotation, &other.rotation, t),
+            scale: nalgebra_glm::lerp(&self.scale, &other.scale, t),
+        }
+    }
+}
+
+/// A skeleton loaded from a gltf skin.
+/// `joints` holds the final joint matrices that get uploaded to the GPU for skinning.
+#[derive(Debug, Clone)]
+pub struct Skeleton {
+    pub name: String,
+    /// The gltf node index for each joint.
+    pub joint_nodes: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub joints: Vec<[[f32; 4]; 4]>,
+    /// The index of each joint's parent joint, None for root joints.
+    pub parents: Vec<Option<usize>>,
+    /// The local transform of each joint when nothing is animating it.
+    pub rest_pose: Vec<JointTransform>,
+    // World matrix of the nodes above each root joint that aren't joints themselves.
+    pub(crate) root_matrices: Vec<Mat4>,
+}
+
+impl Skeleton {
+    pub fn new(name: String, joint_nodes: Vec<usize>, inverse_bind_matrices: Vec<Mat4>) -> Self {
+        if joint_nodes.len() > MAX_JOINTS {
+            log::warn!(
+                "Skeleton {} has {} joints only the first {} will be used.",
+                name,
+                joint_nodes.len(),
+                MAX_JOINTS
+            );
+        }
+        let joint_count = joint_nodes.len().min(MAX_JOINTS);
+        let joints = vec![Mat4::identity().into(); joint_count];
+        Self {
+            name,
+            joint_nodes,
+            inverse_bind_matrices,
+            joints,
+            parents: vec![None; joint_count],
+            rest_pose: vec![JointTransform::default(); joint_count],
+            root_matrices: vec![Mat4::identity(); joint_count],
+        }
+    }
+
+    /// Sets the hierarchy and rest pose used by `apply_pose`.
+    pub(crate) fn with_hierarchy(
+        mut self,
+        parents: Vec<Option<usize>>,
+        rest_pose: Vec<JointTransform>,
+        root_matrices: Vec<Mat4>,
+    ) -> Self {
+        self.parents = parents;
+        self.rest_pose = rest_pose;
+        self.root_matrices = root_matrices;
+        self
+    }
+
+    /// Sets every joint from local transforms, one per joint in the same order as `joint_nodes`.
+    /// Usually `rest_pose` with an `AnimationClip` sampled over it.
+    pub fn apply_pose(&mut self, pose: &[JointTransform]) {
+        let mut world = vec![None; self.joints.len()];
+        for index in 0..self.joints.len() {
+            self.joint_world(index, pose, &mut world);
+        }
+        for (index, world) in world.into_iter().enumerate() {
+            self.set_joint(index, world.unwrap());
+        }
+    }
+
+    // Returns the world matrix of a joint, calculating its parents first.
+    fn joint_world(
+        &self,
+        index: usize,
+        pose: &[JointTransform],
+        world: &mut [Option<Mat4>],
+    ) -> Mat4 {
+        if let Some(matrix) = world[index] {
+            return matrix;
+        }
+
+        let local = pose.get(index).cloned().unwrap_or_default().matrix();
+        let parent = match self.parents.get(index).cloned().flatten() {
+            Some(parent) if parent < world.len() => self.joint_world(parent, pose, world),
+            _ => self
+                .root_matrices
+                .get(index)
+                .cloned()
+                .unwrap_or_else(Mat4::identity),
+        };
+        let matrix = parent * local;
+        world[index] = Some(matrix);
+        matrix
+    }
+
+    /// Sets the world space matrix of a joint. The inverse bind matrix is applied for you.
+    pub fn set_joint(&mut self, index: usize, world: Mat4) {
+        if index >= self.joints.len() {
+            return;
+        }
+        let inverse_bind = self
+            .inverse_bind_matrices
+            .get(index)
+            .cloned()
+            .unwrap_or(Mat4::identity());
+        self.joints[index] = (world * inverse_bind).into();
+    }
+
+    /// Returns the joints padded with identity matrices up to `MAX_JOINTS`.
+    pub(crate) fn palette(&self) -> Vec<[[f32; 4]; 4]> {
+        let mut palette = self.joints.clone();
+        palette.resize(MAX_JOINTS, Mat4::identity().into());
+        palette
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JointTransform, Skeleton, MAX_JOINTS};
+    use nalgebra_glm::{Mat4, Vec3};
+
+    #[test]
+    fn should_apply_inverse_bind_matrix() {
+        let inverse_bind = nalgebra_glm::translation(&Vec3::new(0.0, -1.0, 0.0));
+        let mut skeleton = Skeleton::new("test".to_string(), vec![0], vec![inverse_bind]);
+        skeleton.set_joint(0, nalgebra_glm::translation(&Vec3::new(0.0, 1.0, 0.0)));
+
+        let identity: [[f32; 4]; 4] = Mat4::identity().into();
+        assert_eq!(skeleton.joints[0], identity);
+
+        let palette = skeleton.palette();
+        assert_eq!(palette.len(), MAX_JOINTS);
+        assert_eq!(palette[MAX_JOINTS - 1], identity);
+    }
+
+    #[test]
+    fn should_apply_pose_through_parents() {
+        let mut skeleton = Skeleton::new("test".to_string(), vec![4, 7], vec![]).with_hierarchy(
+            vec![None, Some(0)],
+            vec![JointTransform::default(); 2],
+            vec![Mat4::identity(); 2],
+        );
+        let mut pose = skeleton.rest_pose.clone();
+        pose[0].translation = Vec3::new(0.0, 1.0, 0.0);
+        pose[1].translation = Vec3::new(2.0, 0.0, 0.0);
+        skeleton.apply_pose(&pose);
+
+        let child: [[f32; 4]; 4] = nalgebra_glm::translation(&Vec3::new(2.0, 1.0, 0.0)).into();
+        assert_eq!(skeleton.joints[1], child);
+    }
+}
@@ -0,0 +1,125 @@
+use super::{
+    file_manager::AssetHandle,
+    material::PBRMaterial,
+    mesh::{Gltf, GltfNode, Mesh, MeshVertexData, SubMesh},
+};
+use crate::{core::BoundingSphere, scene::components::Terrain};
+use nalgebra_glm::{Quat, Vec2, Vec3};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// Builds one mesh per terrain chunk, ordered row by row along x.
+/// Vertices sit on the height map texels so neighbouring chunks share their edge vertices.
+pub(crate) fn generate_terrain(
+    device: &wgpu::Device,
+    terrain: &Terrain,
+    material: Arc<AssetHandle<PBRMaterial>>,
+) -> Gltf {
+    let (width, height) = (terrain.heightmap.width, terrain.heightmap.height);
+    let (chunks_x, chunks_z) = terrain.chunk_count();
+
+    let mut meshes = Vec::new();
+    let mut nodes = Vec::new();
+    for chunk_z in 0..chunks_z {
+        for chunk_x in 0..chunks_x {
+            let start_x = chunk_x * terrain.chunk_size;
+            let start_z = chunk_z * terrain.chunk_size;
+            let end_x = (start_x + terrain.chunk_size).min(width - 1);
+            let end_z = (start_z + terrain.chunk_size).min(height - 1);
+
+            let mut vertices = Vec::new();
+            for z in start_z..=end_z {
+                for x in start_x..=end_x {
+                    vertices.push(terrain_vertex(terrain, x, z));
+                }
+            }
+
+            let row = end_x - start_x + 1;
+            let mut indices = Vec::new();
+            for z in 0..end_z - start_z {
+                for x in 0..end_x - start_x {
+                    let top_left = z * row + x;
+                    let bottom_left = top_left + row;
+                    indices.extend_from_slice(&[
+                        top_left,
+                        bottom_left,
+                        top_left + 1,
+                        top_left + 1,
+                        bottom_left,
+                        bottom_left + 1,
+                    ]);
+                }
+            }
+
+            let name = format!("chunk_{}_{}", chunk_x, chunk_z);
+            let sub_mesh = SubMesh::new(device, vertices, indices, true);
+            let bounding_sphere = sub_mesh.bounding_sphere;
+            let bounding_box = sub_mesh.bounding_box;
+            let mut chunk_meshes = HashMap::new();
+            chunk_meshes.insert(material.clone(), sub_mesh);
+
+            nodes.push(GltfNode {
+                name: name.clone(),
+                mesh_index: Some(meshes.len()),
+                material_indices: Vec::new(),
+                skin_index: None,
+                position: Vec3::zeros(),
+                rotation: Quat::identity(),
+                scale: Vec3::new(1.0, 1.0, 1.0),
+            });
+            meshes.push(Mesh {
+                name,
+                meshes: chunk_meshes,
+                bounding_sphere,
+                bounding_box,
+                morph_weights: RwLock::new(Vec::new()),
+            });
+        }
+    }
+
+    let bounding_sphere =
+        BoundingSphere::from_bounding_spheres(meshes.iter().map(|x| &x.bounding_sphere).collect());
+    Gltf {
+        meshes,
+        nodes,
+        skins: Vec::new(),
+        animations: Vec::new(),
+        bounding_sphere,
+    }
+}
+
+// The normal is taken from the slope between the neighbouring texels so it matches across chunks.
+fn terrain_vertex(terrain: &Terrain, x: u32, z: u32) -> MeshVertexData {
+    let (width, height) = (terrain.heightmap.width, terrain.heightmap.height);
+    let spacing = Vec2::new(
+        terrain.size.x / (width - 1) as f32,
+        terrain.size.y / (height - 1) as f32,
+    );
+
+    let left = terrain.texel_height(x.saturating_sub(1), z);
+    let right = terrain.texel_height((x + 1).min(width - 1), z);
+    let back = terrain.texel_height(x, z.saturating_sub(1));
+    let front = terrain.texel_height(x, (z + 1).min(height - 1));
+    let normal = Vec3::new(
+        (left - right) / (2.0 * spacing.x),
+        1.0,
+        (back - front) / (2.0 * spacing.y),
+    )
+    .normalize();
+
+    MeshVertexData {
+        position: Vec3::new(
+            x as f32 * spacing.x,
+            terrain.texel_height(x, z),
+            z as f32 * spacing.y,
+        ),
+        normal,
+        uv: Vec2::new(
+            x as f32 / (width - 1) as f32,
+            z as f32 / (height - 1) as f32,
+        ),
+        ..MeshVertexData::default()
+    }
+}
@@ -1,4 +1,4 @@
-use super::{image::ImageRon, Image};
+use super::{image::ImageRon, Image, MipmapGenerator};
 use std::{path::PathBuf, sync::Arc};
 
 // Texture represents data on the GPU.
@@ -7,6 +7,8 @@ pub struct Texture {
     pub inner: wgpu::Texture,
     pub view: wgpu::TextureView,
     pub extent: wgpu::Extent3d,
+    pub format: wgpu::TextureFormat,
+    pub mip_count: u32,
 }
 
 impl std::fmt::Debug for Texture {
@@ -14,17 +16,78 @@ impl std::fmt::Debug for Texture {
         f.debug_struct("Texture")
             .field("path", &self.path)
             .field("extent", &self.extent)
+            .field("format", &self.format)
+            .field("mip_count", &self.mip_count)
             .finish()
     }
 }
 
+/// Describes a texture that's only rendered to on the GPU, see `TextureManager::get_render_texture`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderTextureDesc {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub usage: wgpu::TextureUsage,
+    pub sample_count: u32,
+    /// Resized to the window's size whenever the window is resized.
+    pub follows_window: bool,
+}
+
+impl RenderTextureDesc {
+    /// A texture that can be rendered to and sampled, with one sample per pixel.
+    pub fn new(width: u32, height: u32, format: wgpu::TextureFormat) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+            sample_count: 1,
+            follows_window: false,
+        }
+    }
+}
+
 impl Texture {
+    /// Creates an empty texture to render to.
+    pub fn new_render_texture(
+        device: &wgpu::Device,
+        path: PathBuf,
+        desc: &RenderTextureDesc,
+    ) -> Self {
+        let extent = wgpu::Extent3d {
+            width: desc.width.max(1),
+            height: desc.height.max(1),
+            depth: 1,
+        };
+        let inner = device.create_texture(&wgpu::TextureDescriptor {
+            size: extent,
+            mip_level_count: 1,
+            sample_count: desc.sample_count.max(1),
+            dimension: wgpu::TextureDimension::D2,
+            format: desc.format,
+            usage: desc.usage,
+            label: path.to_str(),
+        });
+        let view = inner.create_default_view();
+
+        Texture {
+            path,
+            inner,
+            view,
+            extent,
+            format: desc.format,
+            mip_count: 1,
+        }
+    }
+
     pub fn new(
         device: Arc<wgpu::Device>,
         queue: Arc<wgpu::Queue>,
         image: Arc<Image>,
         image_ron: Option<ImageRon>,
         path: PathBuf,
+        mipmap_generator: &MipmapGenerator,
     ) -> Self {
         let extent = wgpu::Extent3d {
             width: image.width,
@@ -32,20 +95,33 @@ impl Texture {
             depth: 1,
         };
 
-        let format = if image_ron.is_some() {
-            image_ron.unwrap().format.into()
+        let format = image.format.into();
+
+        let mut generate_mipmaps = image_ron.map_or(false, |image_ron| image_ron.generate_mipmaps);
+        if generate_mipmaps && !MipmapGenerator::supports(format) {
+            log::warn!(
+                "Can't generate mipmaps for {:?}, {:?} isn't supported.",
+                path,
+                format
+            );
+            generate_mipmaps = false;
+        }
+        let mip_count = if generate_mipmaps {
+            MipmapGenerator::mip_count(extent.width, extent.height)
         } else {
-            // Default to Rgba8UnormSrgb
-            wgpu::TextureFormat::Rgba8UnormSrgb
+            1
         };
 
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             size: extent,
-            mip_level_count: 1,
+            mip_level_count: mip_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format,
-            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            // COPY_SRC lets us copy textures into other textures such as skybox cube maps.
+            usage: wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_DST
+                | wgpu::TextureUsage::COPY_SRC,
             label: None,
         });
         queue.write_texture(
@@ -65,11 +141,129 @@ impl Texture {
 
         let view = texture.create_default_view();
 
+        let texture = Texture {
+            path,
+            inner: texture,
+            view,
+            extent,
+            format,
+            mip_count,
+        };
+
+        if texture.mip_count > 1 {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("texture_mipmaps"),
+            });
+            texture.generate_mipmaps(&device, &mut encoder, mipmap_generator);
+            queue.submit(Some(encoder.finish()));
+        }
+
+        texture
+    }
+
+    /// Creates a texture from data that already holds every mip level, such as a DDS file.
+    /// Each level is a tightly packed row of 4x4 blocks for block compressed formats, or of pixels for anything else.
+    pub fn from_levels(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: PathBuf,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        levels: &[Vec<u8>],
+    ) -> Self {
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        };
+        let mip_count = levels.len().max(1) as u32;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: extent,
+            mip_level_count: mip_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED
+                | wgpu::TextureUsage::COPY_DST
+                | wgpu::TextureUsage::COPY_SRC,
+            label: path.to_str(),
+        });
+
+        let block_dimension = if is_block_compressed(format) { 4 } else { 1 };
+        for (level, data) in levels.iter().enumerate() {
+            // Copies of compressed levels cover whole blocks, even past the edge of the smallest levels.
+            let level_extent = wgpu::Extent3d {
+                width: round_up((width >> level).max(1), block_dimension),
+                height: round_up((height >> level).max(1), block_dimension),
+                depth: 1,
+            };
+            let block_rows = level_extent.height / block_dimension;
+            queue.write_texture(
+                wgpu::TextureCopyView {
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                &data[..],
+                wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: data.len() as u32 / block_rows,
+                    rows_per_image: level_extent.height,
+                },
+                level_extent,
+            );
+        }
+
+        let view = texture.create_default_view();
         Texture {
             path,
             inner: texture,
             view,
             extent,
+            format,
+            mip_count,
         }
     }
+
+    /// Fills in levels 1 to `mip_count` from the first level using a compute shader.
+    pub fn generate_mipmaps(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        mipmap_generator: &MipmapGenerator,
+    ) {
+        mipmap_generator.generate(
+            device,
+            encoder,
+            &self.inner,
+            self.format,
+            self.extent,
+            self.mip_count,
+        );
+    }
+}
+
+fn is_block_compressed(format: wgpu::TextureFormat) -> bool {
+    match format {
+        wgpu::TextureFormat::Bc1RgbaUnorm
+        | wgpu::TextureFormat::Bc1RgbaUnormSrgb
+        | wgpu::TextureFormat::Bc2RgbaUnorm
+        | wgpu::TextureFormat::Bc2RgbaUnormSrgb
+        | wgpu::TextureFormat::Bc3RgbaUnorm
+        | wgpu::TextureFormat::Bc3RgbaUnormSrgb
+        | wgpu::TextureFormat::Bc4RUnorm
+        | wgpu::TextureFormat::Bc4RSnorm
+        | wgpu::TextureFormat::Bc5RgUnorm
+        | wgpu::TextureFormat::Bc5RgSnorm
+        | wgpu::TextureFormat::Bc6hRgbUfloat
+        | wgpu::TextureFormat::Bc6hRgbSfloat
+        | wgpu::TextureFormat::Bc7RgbaUnorm
+        | wgpu::TextureFormat::Bc7RgbaUnormSrgb => true,
+        _ => false,
+    }
+}
+
+fn round_up(value: u32, multiple: u32) -> u32 {
+    (value + multiple - 1) / multiple * multiple
 }
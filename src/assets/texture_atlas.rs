@@ -0,0 +1,186 @@
+use super::texture::Texture;
+use image::{DynamicImage, GenericImageView};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+/// A region of a texture atlas in uv space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    /// Returns the rect as (x, y, width, height) which is the layout materials expect for `uv_rect`.
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.x, self.y, self.width, self.height]
+    }
+}
+
+/// Many small images packed into a single texture.
+#[derive(Debug)]
+pub struct TextureAtlas {
+    pub texture: Texture,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A built atlas along with the uv rect of every image in it.
+#[derive(Debug, Clone)]
+pub struct TextureAtlasHandle {
+    pub atlas: Arc<TextureAtlas>,
+    pub rects: Arc<HashMap<String, Rect>>,
+}
+
+impl TextureAtlasHandle {
+    pub fn rect(&self, name: &str) -> Option<Rect> {
+        self.rects.get(name).cloned()
+    }
+}
+
+impl TextureAtlas {
+    /// Packs the images into a single rgba texture using shelf packing.
+    /// `padding` is the number of empty pixels kept around each image to stop filtering from bleeding.
+    pub fn build(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: Vec<(String, DynamicImage)>,
+        padding: u32,
+    ) -> (TextureAtlas, HashMap<String, Rect>) {
+        let sizes: Vec<(u32, u32)> = images.iter().map(|(_, image)| image.dimensions()).collect();
+        let (positions, width, height) = shelf_pack(&sizes, padding);
+
+        let mut data = vec![0u8; (width * height * 4) as usize];
+        let mut rects = HashMap::new();
+        for ((name, image), (x, y)) in images.iter().zip(positions.iter()) {
+            let image = image.to_rgba();
+            let (image_width, image_height) = image.dimensions();
+            let raw = image.into_raw();
+            for row in 0..image_height {
+                let source = (row * image_width * 4) as usize;
+                let destination = (((y + row) * width + x) * 4) as usize;
+                let row_size = (image_width * 4) as usize;
+                data[destination..destination + row_size]
+                    .copy_from_slice(&raw[source..source + row_size]);
+            }
+
+            rects.insert(
+                name.clone(),
+                Rect {
+                    x: *x as f32 / width as f32,
+                    y: *y as f32 / height as f32,
+                    width: image_width as f32 / width as f32,
+                    height: image_height as f32 / height as f32,
+                },
+            );
+        }
+
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        };
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            label: Some("texture_atlas"),
+        });
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &data[..],
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: width * 4,
+                rows_per_image: height,
+            },
+            extent,
+        );
+        let view = texture.create_default_view();
+
+        let atlas = TextureAtlas {
+            texture: Texture {
+                path: PathBuf::from("texture_atlas"),
+                inner: texture,
+                view,
+                extent,
+                format,
+            },
+            width,
+            height,
+        };
+
+        (atlas, rects)
+    }
+}
+
+// Places the tallest images first on shelves that span the width of the atlas.
+// Returns the top left position of each image in the order they were passed in along with the atlas size.
+// The width is picked so the atlas ends up roughly square and both sides are a power of two.
+pub(crate) fn shelf_pack(sizes: &[(u32, u32)], padding: u32) -> (Vec<(u32, u32)>, u32, u32) {
+    let padded: Vec<(u32, u32)> = sizes
+        .iter()
+        .map(|(width, height)| (width + padding * 2, height + padding * 2))
+        .collect();
+    let area: u32 = padded.iter().map(|(width, height)| width * height).sum();
+    let widest = padded.iter().map(|(width, _)| *width).max().unwrap_or(1);
+    let width = ((area as f32).sqrt().ceil() as u32)
+        .max(widest)
+        .max(1)
+        .next_power_of_two();
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| padded[*b].1.cmp(&padded[*a].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+    for index in order {
+        let (image_width, image_height) = padded[index];
+        if x + image_width > width {
+            y += shelf_height;
+            x = 0;
+            shelf_height = 0;
+        }
+        positions[index] = (x + padding, y + padding);
+        x += image_width;
+        shelf_height = shelf_height.max(image_height);
+    }
+
+    let height = (y + shelf_height).max(1).next_power_of_two();
+    (positions, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shelf_pack;
+
+    #[test]
+    fn should_pack_without_overlap() {
+        let sizes = vec![(16, 16), (32, 8), (8, 32), (16, 16)];
+        let padding = 1;
+        let (positions, width, height) = shelf_pack(&sizes, padding);
+
+        assert!(width.is_power_of_two() && height.is_power_of_two());
+        for (i, ((x, y), (w, h))) in positions.iter().zip(sizes.iter()).enumerate() {
+            assert!(x + w + padding <= width && y + h + padding <= height);
+            for ((other_x, other_y), (other_w, other_h)) in
+                positions.iter().zip(sizes.iter()).skip(i + 1)
+            {
+                let separate = x + w + padding <= *other_x
+                    || other_x + other_w + padding <= *x
+                    || y + h + padding <= *other_y
+                    || other_y + other_h + padding <= *y;
+                assert!(separate);
+            }
+        }
+    }
+}
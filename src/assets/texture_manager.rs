@@ -1,12 +1,25 @@
 use super::{
-    file_manager::{AssetCache, AssetError, AssetHandle},
+    compressed_texture::CompressedImage,
+    file_manager::{
+        load_with_timeout, AssetCache, AssetCallbackMap, AssetError, AssetHandle, AssetLoader,
+    },
     image::ImageRon,
-    texture::Texture,
-    Image,
+    texture::{RenderTextureDesc, Texture},
+    texture_atlas::{TextureAtlas, TextureAtlasHandle},
+    Image, MipmapGenerator, NoiseGenerator, NoiseTextureDesc,
 };
 use futures::executor::{ThreadPool, ThreadPoolBuilder};
-use std::{convert::TryFrom, path::PathBuf, sync::Arc};
-use dashmap::DashSet;
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use dashmap::{DashMap, DashSet};
+
+// Empty pixels around each image in an atlas so linear filtering doesn't pick up the neighbours.
+const ATLAS_PADDING: u32 = 2;
 
 pub struct TextureManager {
     device: Arc<wgpu::Device>,
@@ -15,49 +28,50 @@ pub struct TextureManager {
     image_cache: AssetCache<Image>,
     ron_cache: AssetCache<ImageRon>,
     texture_cache: AssetCache<Texture>,
+    callbacks: AssetCallbackMap<Texture>,
+    atlas_cache: DashMap<Vec<String>, TextureAtlasHandle>,
     loaded: DashSet<PathBuf>,
+    render_textures: DashMap<PathBuf, RenderTextureDesc>,
+    load_timeout: Option<Duration>,
+    mipmap_generator: Arc<MipmapGenerator>,
+    noise_generator: NoiseGenerator,
 }
 
-impl TextureManager {
-    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> Self {
-        let pool = Arc::new(ThreadPoolBuilder::new().pool_size(4).create().unwrap());
-        let image_cache = Arc::new(dashmap::DashMap::new());
-        let ron_cache = Arc::new(dashmap::DashMap::new());
-        let texture_cache = Arc::new(dashmap::DashMap::new());
-        Self {
-            device,
-            queue,
-            pool,
-            image_cache,
-            ron_cache,
-            texture_cache,
-            loaded: DashSet::new(),
-        }
-    }
+// Everything loading a texture needs, cloned out of the manager so handles can load their texture again.
+#[derive(Clone)]
+struct LoadTask {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    pool: Arc<ThreadPool>,
+    image_cache: AssetCache<Image>,
+    ron_cache: AssetCache<ImageRon>,
+    texture_cache: AssetCache<Texture>,
+    load_timeout: Option<Duration>,
+    mipmap_generator: Arc<MipmapGenerator>,
+}
 
-    pub fn get<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<Texture>> {
-        let path = path.into();
-        let texture_handle = Arc::new(AssetHandle::new(path.clone(), self.texture_cache.clone()));
+impl LoadTask {
+    // Loads the image and it's optional ron file on the thread pool and stores the texture under the handle's path.
+    fn load(&self, texture_handle: AssetHandle<Texture>) {
+        let path = texture_handle.handle_id.clone();
+        let ext = path.extension().unwrap().to_str().unwrap().to_string();
 
-        if !self.loaded.contains(&path) {
-            let ext = path.extension().unwrap().to_str().unwrap().to_string(); 
-            self.loaded.insert(path.clone());
-        
-            // Cross thread arcs passed to new thread.
-            let image_cache = self.image_cache.clone();
-            let ron_cache = self.ron_cache.clone();
-            let texture_cache = self.texture_cache.clone();
-            let texture_thread_handle = texture_handle.clone();
-            let device = self.device.clone();
-            let queue = self.queue.clone();
+        // Cross thread arcs passed to new thread.
+        let image_cache = self.image_cache.clone();
+        let ron_cache = self.ron_cache.clone();
+        let device = self.device.clone();
+        let queue = self.queue.clone();
+        let mipmap_generator = self.mipmap_generator.clone();
+        let load_timeout = self.load_timeout;
 
-            self.pool.spawn_ok(async move {
+        self.pool.spawn_ok(async move {
+            let load = async move {
                 let mut ron_path = path.clone();
                 ron_path.set_extension(format!("{}{}", ext, ".ron"));
                 let image_file = async_std::fs::read(path.clone()).await;
                 let ron_file = async_std::fs::read(ron_path).await;
 
-                let result = match image_file {
+                match image_file {
                     Ok(image_data) => {
                         // Attempt to load ron file..
                         let image_ron = if ron_file.is_ok() {
@@ -70,8 +84,7 @@ impl TextureManager {
                             Image::try_from((image_ron, path.clone(), image_data)).unwrap(),
                         );
                         // Store image in cache.
-                        image_cache
-                            .insert(texture_thread_handle.handle_id.clone(), Ok(image.clone()));
+                        image_cache.insert(path.clone(), Ok(image.clone()));
 
                         let result = Ok(Arc::new(Texture::new(
                             device.clone(),
@@ -79,6 +92,7 @@ impl TextureManager {
                             image,
                             image_ron,
                             path.clone(),
+                            &mipmap_generator,
                         )));
 
                         let image_ron = match image_ron {
@@ -86,11 +100,8 @@ impl TextureManager {
                             None => Err(Arc::new(AssetError::FileNotFound)),
                         };
 
-                        ron_cache.insert(texture_thread_handle.handle_id.clone(), image_ron);
+                        ron_cache.insert(path.clone(), image_ron);
 
-                        // queue.submit(None);
-                        // device.poll(wgpu::Maintain::Wait);
-                        
                         log::info!("{:?} loaded.", path);
                         result
                     }
@@ -98,19 +109,198 @@ impl TextureManager {
                         std::io::ErrorKind::NotFound => Err(Arc::new(AssetError::FileNotFound)),
                         _ => Err(Arc::new(AssetError::OtherError(error))),
                     },
+                }
+            };
+            let result = load_with_timeout(load_timeout, load).await;
+
+            texture_handle.finish(result);
+        });
+    }
+
+    // Loads a DDS file on the thread pool, see `TextureManager::load_dds`.
+    fn load_dds(&self, texture_handle: AssetHandle<Texture>) {
+        let path = texture_handle.handle_id.clone();
+        let device = self.device.clone();
+        let queue = self.queue.clone();
+        let load_timeout = self.load_timeout;
+
+        self.pool.spawn_ok(async move {
+            let load = async move {
+                let data = match async_std::fs::read(path.clone()).await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        return match error.kind() {
+                            std::io::ErrorKind::NotFound => Err(Arc::new(AssetError::FileNotFound)),
+                            _ => Err(Arc::new(AssetError::OtherError(error))),
+                        }
+                    }
+                };
+                let image = match CompressedImage::from_dds(&data) {
+                    Some(image) => image,
+                    None => {
+                        log::warn!("{:?} isn't a BC1, BC3, BC5 or BC7 DDS file.", path);
+                        return Err(Arc::new(AssetError::InvalidData));
+                    }
                 };
 
-                texture_cache.insert(texture_thread_handle.handle_id.clone(), result);
-            });
+                let supports_bc = device
+                    .features()
+                    .contains(wgpu::Features::TEXTURE_COMPRESSION_BC);
+                let (format, levels) = if supports_bc {
+                    (image.format.texture_format(), image.levels.clone())
+                } else {
+                    (wgpu::TextureFormat::Rgba8Unorm, image.decompress())
+                };
+                let texture = Texture::from_levels(
+                    &device,
+                    &queue,
+                    path.clone(),
+                    image.width,
+                    image.height,
+                    format,
+                    &levels,
+                );
+
+                log::info!("{:?} loaded.", path);
+                Ok(Arc::new(texture))
+            };
+            let result = load_with_timeout(load_timeout, load).await;
+
+            texture_handle.finish(result);
+        });
+    }
+
+    // Decodes raw image bytes into a texture, see `TextureManager::insert`.
+    fn insert(&self, path: &PathBuf, data: &[u8]) {
+        match Image::try_from((None, path.clone(), data.to_vec())) {
+            Ok(image) => self.insert_image(path, Arc::new(image)),
+            Err(_) => {
+                log::error!("Couldn't decode image {:?}", path);
+                let error = Arc::new(AssetError::InvalidData);
+                self.image_cache.insert(path.clone(), Err(error.clone()));
+                self.texture_cache.insert(path.clone(), Err(error));
+            }
         }
+    }
 
-        texture_handle
+    fn insert_image(&self, path: &PathBuf, image: Arc<Image>) {
+        self.image_cache.insert(path.clone(), Ok(image.clone()));
+        self.ron_cache
+            .insert(path.clone(), Err(Arc::new(AssetError::FileNotFound)));
+
+        let texture = Texture::new(
+            self.device.clone(),
+            self.queue.clone(),
+            image,
+            None,
+            path.clone(),
+            &self.mipmap_generator,
+        );
+        self.texture_cache.insert(path.clone(), Ok(Arc::new(texture)));
+
+        log::info!("{:?} inserted.", path);
     }
+}
 
-    // Assures the asset is loaded before returning the asset handle.
+impl TextureManager {
+    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> Self {
+        let pool = Arc::new(ThreadPoolBuilder::new().pool_size(4).create().unwrap());
+        let image_cache = Arc::new(dashmap::DashMap::new());
+        let ron_cache = Arc::new(dashmap::DashMap::new());
+        let texture_cache = Arc::new(dashmap::DashMap::new());
+        let mipmap_generator = Arc::new(MipmapGenerator::new(&device));
+        Self {
+            device,
+            queue,
+            pool,
+            image_cache,
+            ron_cache,
+            texture_cache,
+            callbacks: Arc::new(Mutex::new(HashMap::new())),
+            atlas_cache: DashMap::new(),
+            loaded: DashSet::new(),
+            render_textures: DashMap::new(),
+            load_timeout: None,
+            mipmap_generator,
+            noise_generator: NoiseGenerator::default(),
+        }
+    }
+
+    /// Textures loaded with `get` that take longer than `timeout` fail with `AssetError::Timeout`.
+    /// By default there is no timeout.
+    pub fn with_load_timeout(mut self, timeout: Duration) -> Self {
+        self.load_timeout = Some(timeout);
+        self
+    }
+
+    pub fn get<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<Texture>> {
+        let path = path.into();
+        if path.extension().map_or(false, |ext| ext == "dds") {
+            return self.load_dds(path);
+        }
+        let texture_handle = self.new_handle(path.clone());
+        let loader: AssetLoader = {
+            let task = self.load_task();
+            // A handle without the loader so the loader doesn't keep itself alive.
+            let handle = texture_handle.clone();
+            Arc::new(move || task.load(handle.clone()))
+        };
+
+        if !self.loaded.contains(&path) {
+            self.loaded.insert(path.clone());
+            loader();
+        }
+
+        Arc::new(texture_handle.with_loader(loader))
+    }
+
+    /// Loads a BC1, BC3, BC5 or BC7 DDS file with its mip levels, `get` calls this for `.dds` files.
+    /// Devices without `TEXTURE_COMPRESSION_BC` get the texture decompressed to RGBA8 on the CPU instead.
+    pub fn load_dds<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<Texture>> {
+        let path = path.into();
+        let texture_handle = self.new_handle(path.clone());
+        let loader: AssetLoader = {
+            let task = self.load_task();
+            let handle = texture_handle.clone();
+            Arc::new(move || task.load_dds(handle.clone()))
+        };
+
+        if !self.loaded.contains(&path) {
+            self.loaded.insert(path.clone());
+            loader();
+        }
+
+        Arc::new(texture_handle.with_loader(loader))
+    }
+
+    // Every handle shares the manager's callbacks so all handles to a texture are notified when it loads.
+    fn new_handle(&self, path: PathBuf) -> AssetHandle<Texture> {
+        AssetHandle::new(path, self.texture_cache.clone()).with_callbacks(self.callbacks.clone())
+    }
+
+    fn load_task(&self) -> LoadTask {
+        LoadTask {
+            device: self.device.clone(),
+            queue: self.queue.clone(),
+            pool: self.pool.clone(),
+            image_cache: self.image_cache.clone(),
+            ron_cache: self.ron_cache.clone(),
+            texture_cache: self.texture_cache.clone(),
+            load_timeout: self.load_timeout,
+            mipmap_generator: self.mipmap_generator.clone(),
+        }
+    }
+
+    // Assures the asset is loaded, or failed to load, before returning the asset handle.
     pub async fn get_async<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<Texture>> {
         let path = path.into();
-        let texture_handle = Arc::new(AssetHandle::new(path.clone(), self.texture_cache.clone()));
+        let texture_handle = self.new_handle(path.clone());
+        let loader: AssetLoader = {
+            let task = self.load_task();
+            let handle = texture_handle.clone();
+            Arc::new(move || task.load(handle.clone()))
+        };
+        let texture_handle = Arc::new(texture_handle.with_loader(loader));
 
         if !self.loaded.contains(&path) {
             let ext = path.extension().unwrap().to_str().unwrap().to_string(); 
@@ -119,10 +309,10 @@ impl TextureManager {
             // Cross thread arcs passed to new thread.
             let image_cache = self.image_cache.clone();
             let ron_cache = self.ron_cache.clone();
-            let texture_cache = self.texture_cache.clone();
-            let texture_thread_handle = texture_handle.clone();
+                        let texture_thread_handle = texture_handle.clone();
             let device = self.device.clone();
             let queue = self.queue.clone();
+            let mipmap_generator = self.mipmap_generator.clone();
 
             let mut ron_path = path.clone();
             ron_path.set_extension(format!("{}{}", ext, ".ron"));
@@ -149,6 +339,7 @@ impl TextureManager {
                         image,
                         image_ron,
                         path.clone(),
+                        &mipmap_generator,
                     )));
 
                     let image_ron = match image_ron {
@@ -170,24 +361,200 @@ impl TextureManager {
                 },
             };
 
-            texture_cache.insert(texture_thread_handle.handle_id.clone(), result);
+            texture_thread_handle.finish(result);
         } else {
-            loop {
-                let result = texture_handle.get();
-                if result.is_ok() {
-                    break;
-                }
+            // Another call is loading it, a failed load is returned as the handle's error.
+            while !texture_handle.is_resolved() {
+                async_std::task::yield_now().await;
             }
         }
 
         texture_handle
     }
+
+    // Inserts a texture from raw image bytes(png, jpg, etc) instead of loading it from disk.
+    // Useful for textures that are embedded inside of other files like gltf.
+    // If the bytes can't be decoded the handle returns `AssetError::InvalidData`.
+    // Reloading the handle decodes the bytes again.
+    pub fn insert<P: Into<PathBuf>>(&self, path: P, data: Vec<u8>) -> Arc<AssetHandle<Texture>> {
+        let path = path.into();
+        let task = self.load_task();
+        let data = Arc::new(data);
+        task.insert(&path, &data);
+        self.loaded.insert(path.clone());
+
+        let texture_handle = self.new_handle(path.clone());
+        let loader: AssetLoader = Arc::new(move || {
+            let task = task.clone();
+            let path = path.clone();
+            let data = data.clone();
+            task.pool.clone().spawn_ok(async move { task.insert(&path, &data) });
+        });
+        Arc::new(texture_handle.with_loader(loader))
+    }
+
+    // Inserts a texture created from an image that's already in memory.
+    pub fn insert_image<P: Into<PathBuf>>(
+        &self,
+        path: P,
+        image: Arc<Image>,
+    ) -> Arc<AssetHandle<Texture>> {
+        let path = path.into();
+        self.load_task().insert_image(&path, image);
+        self.loaded.insert(path.clone());
+        Arc::new(self.new_handle(path))
+    }
+
+    /// Creates an empty texture that's only rendered to on the GPU, the handle is ready straight away.
+    /// Asking for the same name again returns the existing texture, unless `desc` changed.
+    pub fn get_render_texture<P: Into<PathBuf>>(
+        &self,
+        name: P,
+        desc: RenderTextureDesc,
+    ) -> Arc<AssetHandle<Texture>> {
+        let path = name.into();
+        let texture_handle = Arc::new(self.new_handle(path.clone()));
+        if self
+            .render_textures
+            .get(&path)
+            .map_or(false, |existing| *existing == desc)
+        {
+            return texture_handle;
+        }
+
+        let texture = Texture::new_render_texture(&self.device, path.clone(), &desc);
+        self.texture_cache.insert(path.clone(), Ok(Arc::new(texture)));
+        self.render_textures.insert(path.clone(), desc);
+        self.loaded.insert(path);
+        texture_handle
+    }
+
+    /// Fills a new texture with noise on the GPU and registers it under `name`, the handle is ready straight away.
+    /// The texture can be used by materials like any loaded texture. Generating the same name again replaces it.
+    pub fn generate_noise<P: Into<PathBuf>>(
+        &self,
+        name: P,
+        desc: NoiseTextureDesc,
+    ) -> Arc<AssetHandle<Texture>> {
+        let path = name.into();
+        let texture_handle = Arc::new(self.new_handle(path.clone()));
+        let texture = self
+            .noise_generator
+            .generate(&self.device, &self.queue, path.clone(), &desc);
+        self.texture_cache.insert(path.clone(), Ok(Arc::new(texture)));
+        self.loaded.insert(path);
+        texture_handle
+    }
+
+    /// True if the texture was created with `get_render_texture` instead of being loaded.
+    pub fn is_render_texture<P: Into<PathBuf>>(&self, name: P) -> bool {
+        self.render_textures.contains_key(&name.into())
+    }
+
+    /// Recreates a render texture at a new size, handles to it return the new texture afterwards.
+    /// Anything holding on to the old texture or a bind group made from it needs to get it again.
+    pub fn resize_render_texture<P: Into<PathBuf>>(
+        &self,
+        name: P,
+        width: u32,
+        height: u32,
+        device: &wgpu::Device,
+    ) {
+        let path = name.into();
+        let desc = match self.render_textures.get_mut(&path) {
+            Some(mut desc) => {
+                desc.width = width;
+                desc.height = height;
+                *desc
+            }
+            None => {
+                log::warn!("{:?} isn't a render texture and can't be resized.", path);
+                return;
+            }
+        };
+        let texture = Texture::new_render_texture(device, path.clone(), &desc);
+        self.texture_cache.insert(path, Ok(Arc::new(texture)));
+    }
+
+    /// Resizes every render texture with `follows_window` set.
+    pub(crate) fn resize_window_render_textures(&self, width: u32, height: u32) {
+        let window_sized: Vec<PathBuf> = self
+            .render_textures
+            .iter()
+            .filter(|desc| desc.value().follows_window)
+            .map(|desc| desc.key().clone())
+            .collect();
+        for path in window_sized {
+            self.resize_render_texture(path, width, height, &self.device);
+        }
+    }
+
+    /// Marks the texture as missing so handles return `AssetError::FileNotFound`.
+    /// The texture is loaded again if it's requested after the file is recreated.
+    pub(crate) fn mark_removed(&self, path: &PathBuf) {
+        self.loaded.remove(path);
+        self.texture_cache
+            .insert(path.clone(), Err(Arc::new(AssetError::FileNotFound)));
+    }
+
+    // Blocking version of `get_async`, returns once the texture has finished loading or failed to load.
+    // Note: This must not be called from inside of an async executor as it will block the executor's thread.
+    pub fn get_sync<P: Into<PathBuf>>(&self, path: P) -> Arc<AssetHandle<Texture>> {
+        async_std::task::block_on(self.get_async(path))
+    }
+
+    /// Returns the CPU copy of a loaded texture's pixels, blocks until the texture is loaded.
+    pub fn get_image<P: Into<PathBuf>>(&self, path: P) -> Option<Arc<Image>> {
+        let path = path.into();
+        self.get_sync(&path);
+        self.image_cache
+            .get(&path)
+            .and_then(|image| image.as_ref().ok().cloned())
+    }
+
+    /// Packs the images at the given paths into a single texture. Blocks until every image has loaded.
+    /// Atlases are cached so asking for the same names again returns the same atlas.
+    /// Note: Only 8 bit images can be packed, anything else is skipped.
+    pub fn get_atlas(&self, names: &[&str]) -> TextureAtlasHandle {
+        let key: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        if let Some(handle) = self.atlas_cache.get(&key) {
+            return handle.clone();
+        }
+
+        let mut images = Vec::new();
+        for name in names.iter() {
+            let path = PathBuf::from(name);
+            let texture = self.get_sync(&path);
+            if texture.get().is_err() {
+                log::warn!("Couldn't load {:?} for the texture atlas.", path);
+                continue;
+            }
+
+            let image = self.get_image(&path);
+            let rgba = image.and_then(|image| {
+                image::RgbaImage::from_raw(image.width, image.height, image.data.clone())
+            });
+            match rgba {
+                Some(rgba) => images.push((name.to_string(), image::DynamicImage::ImageRgba8(rgba))),
+                None => log::warn!("{:?} isn't an 8 bit image and can't be added to an atlas.", path),
+            }
+        }
+
+        let (atlas, rects) = TextureAtlas::build(&self.device, &self.queue, images, ATLAS_PADDING);
+        let handle = TextureAtlasHandle {
+            atlas: Arc::new(atlas),
+            rects: Arc::new(rects),
+        };
+        self.atlas_cache.insert(key, handle.clone());
+        handle
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::AssetError;
-    use super::TextureManager;
+    use super::{RenderTextureDesc, TextureManager};
+    use crate::assets::{NoiseTextureDesc, NoiseType};
     use std::sync::Arc;
 
     #[test]
@@ -235,4 +602,165 @@ mod tests {
         let asset = handle.get();
         assert!(asset.is_ok());
     }
+    #[test]
+    fn should_load_texture_sync() {
+        let (_, device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(
+                    &wgpu::RequestAdapterOptions {
+                        power_preference: wgpu::PowerPreference::Default,
+                        compatible_surface: None,
+                    },
+                )
+                .await
+                .unwrap();
+
+            let adapter_features = adapter.features();
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: adapter_features,
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+            let arc_device = Arc::new(device);
+            let arc_queue = Arc::new(queue);
+            (adapter, arc_device, arc_queue)
+        });
+
+        let texture_manager = TextureManager::new(device, queue);
+
+        // No need to wait here as get_sync blocks until the texture is loaded.
+        let handle = texture_manager.get_sync("./assets/core/white.png");
+        let asset = handle.get();
+        assert!(asset.is_ok());
+
+        // Asking for a texture that failed to load again returns the error instead of blocking.
+        let handle = texture_manager.get_sync("./assets/core/missing.png");
+        assert!(handle.get().is_err());
+        let handle = texture_manager.get_sync("./assets/core/missing.png");
+        assert!(handle.get().is_err());
+    }
+
+    #[test]
+    fn should_reload_texture() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+            (Arc::new(device), Arc::new(queue))
+        });
+
+        let texture_manager = TextureManager::new(device, queue);
+        let handle = texture_manager.get_sync("./assets/core/white.png");
+        assert!(handle.get().is_ok());
+
+        // Handles from `get` load the file again, like after it was swapped on disk.
+        let handle = texture_manager.get("./assets/core/white.png");
+        handle.reload();
+        assert!(match *handle.get().err().unwrap() {
+            AssetError::Loading => true,
+            _ => false,
+        });
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        assert!(handle.get().is_ok());
+    }
+
+    #[test]
+    fn should_create_render_texture() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+            (Arc::new(device), Arc::new(queue))
+        });
+
+        let texture_manager = TextureManager::new(device.clone(), queue);
+        let desc = RenderTextureDesc::new(64, 32, wgpu::TextureFormat::Rgba8Unorm);
+
+        // Render textures don't load so they're ready straight away.
+        let handle = texture_manager.get_render_texture("bloom_target", desc);
+        assert_eq!(handle.get().unwrap().extent.width, 64);
+        assert!(texture_manager.is_render_texture("bloom_target"));
+        assert!(!texture_manager.is_render_texture("./assets/core/white.png"));
+
+        texture_manager.resize_render_texture("bloom_target", 128, 16, &device);
+        let texture = handle.get().unwrap();
+        assert_eq!((texture.extent.width, texture.extent.height), (128, 16));
+    }
+
+    #[test]
+    fn should_generate_noise() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+            (Arc::new(device), Arc::new(queue))
+        });
+
+        let texture_manager = TextureManager::new(device, queue);
+        let desc = NoiseTextureDesc::new(64, 32, NoiseType::Simplex);
+
+        // Generated textures are registered like loaded ones.
+        let handle = texture_manager.generate_noise("clouds", desc);
+        let texture = handle.get().unwrap();
+        assert_eq!((texture.extent.width, texture.extent.height), (64, 32));
+        assert_eq!(texture.format, wgpu::TextureFormat::Rgba8Unorm);
+        assert!(texture_manager.get("clouds").get().is_ok());
+    }
 }
@@ -0,0 +1,249 @@
+use super::{
+    file_manager::AssetHandle,
+    material::PBRMaterial,
+    mesh::{Gltf, GltfNode, Mesh, MeshVertexData, SubMesh},
+};
+use crate::{
+    core::{Aabb, BoundingSphere},
+    scene::{VoxelId, EMPTY_VOXEL},
+};
+use nalgebra_glm::{Quat, Vec2, Vec3};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// The vertices and indices of a chunk's faces, grouped by the voxel they belong to.
+pub(crate) type VoxelFaces = HashMap<VoxelId, (Vec<MeshVertexData>, Vec<u32>)>;
+
+/// Builds the visible faces of a chunk, merging neighbouring faces of the same voxel into one quad.
+/// `voxels` holds the chunk plus a one voxel border of it's neighbours, x first, so faces against solid neighbours are skipped.
+/// Positions are offset by `origin`, uvs are measured in voxels so textures repeat once per voxel.
+pub(crate) fn greedy_mesh(voxels: &[VoxelId], chunk_size: u32, origin: Vec3) -> VoxelFaces {
+    let size = chunk_size as i32;
+    let padded_size = size + 2;
+    let voxel = |position: [i32; 3]| {
+        voxels[((position[0] + 1)
+            + (position[1] + 1) * padded_size
+            + (position[2] + 1) * padded_size * padded_size) as usize]
+    };
+
+    let mut faces = VoxelFaces::new();
+    let mut mask = vec![EMPTY_VOXEL; (size * size) as usize];
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        for &direction in &[1, -1] {
+            for slice in 0..size {
+                // A face is visible where the voxel's neighbour along the direction is empty.
+                for j in 0..size {
+                    for i in 0..size {
+                        let mut position = [0; 3];
+                        position[axis] = slice;
+                        position[u] = i;
+                        position[v] = j;
+                        let mut neighbour = position;
+                        neighbour[axis] += direction;
+
+                        let id = voxel(position);
+                        mask[(i + j * size) as usize] =
+                            if id != EMPTY_VOXEL && voxel(neighbour) == EMPTY_VOXEL {
+                                id
+                            } else {
+                                EMPTY_VOXEL
+                            };
+                    }
+                }
+
+                // Grow each face as wide as possible, then as tall as the whole row allows.
+                for j in 0..size {
+                    let mut i = 0;
+                    while i < size {
+                        let id = mask[(i + j * size) as usize];
+                        if id == EMPTY_VOXEL {
+                            i += 1;
+                            continue;
+                        }
+
+                        let mut width = 1;
+                        while i + width < size && mask[(i + width + j * size) as usize] == id {
+                            width += 1;
+                        }
+                        let mut height = 1;
+                        while j + height < size
+                            && (i..i + width)
+                                .all(|x| mask[(x + (j + height) * size) as usize] == id)
+                        {
+                            height += 1;
+                        }
+                        for y in j..j + height {
+                            for x in i..i + width {
+                                mask[(x + y * size) as usize] = EMPTY_VOXEL;
+                            }
+                        }
+
+                        let (vertices, indices) = faces.entry(id).or_default();
+                        let plane = if direction > 0 { slice + 1 } else { slice };
+                        push_quad(
+                            vertices,
+                            indices,
+                            (axis, u, v),
+                            direction,
+                            plane,
+                            [i, j, width, height],
+                            origin,
+                        );
+                        i += width;
+                    }
+                }
+            }
+        }
+    }
+    faces
+}
+
+// The quad starts at `i`, `j` along `u` and `v` and spans `width` by `height` voxels.
+fn push_quad(
+    vertices: &mut Vec<MeshVertexData>,
+    indices: &mut Vec<u32>,
+    (axis, u, v): (usize, usize, usize),
+    direction: i32,
+    plane: i32,
+    [i, j, width, height]: [i32; 4],
+    origin: Vec3,
+) {
+    let first = vertices.len() as u32;
+    let mut normal = Vec3::zeros();
+    normal[axis] = direction as f32;
+    for &(corner_u, corner_v) in &[(0, 0), (width, 0), (width, height), (0, height)] {
+        let mut position = origin;
+        position[axis] += plane as f32;
+        position[u] += (i + corner_u) as f32;
+        position[v] += (j + corner_v) as f32;
+        vertices.push(MeshVertexData {
+            position,
+            normal,
+            uv: Vec2::new(corner_u as f32, corner_v as f32),
+            ..MeshVertexData::default()
+        });
+    }
+
+    // u, v and the axis are ordered so the corners wind counter clockwise seen from the positive side.
+    let order: [u32; 6] = if direction > 0 {
+        [0, 1, 2, 0, 2, 3]
+    } else {
+        [0, 2, 1, 0, 3, 2]
+    };
+    indices.extend(order.iter().map(|index| first + index));
+}
+
+/// Builds a single mesh for a voxel chunk with one sub mesh per material.
+pub(crate) fn generate_voxel_chunk(
+    device: &wgpu::Device,
+    name: &str,
+    faces: Vec<(Arc<AssetHandle<PBRMaterial>>, Vec<MeshVertexData>, Vec<u32>)>,
+) -> Gltf {
+    // Voxels sharing a material are drawn together.
+    let mut grouped: HashMap<Arc<AssetHandle<PBRMaterial>>, (Vec<MeshVertexData>, Vec<u32>)> =
+        HashMap::new();
+    for (material, vertices, indices) in faces {
+        let (group_vertices, group_indices) = grouped.entry(material).or_default();
+        let first = group_vertices.len() as u32;
+        group_vertices.extend(vertices);
+        group_indices.extend(indices.iter().map(|index| first + index));
+    }
+
+    let meshes: HashMap<_, _> = grouped
+        .into_iter()
+        .map(|(material, (vertices, indices))| {
+            (material, SubMesh::new(device, vertices, indices, true))
+        })
+        .collect();
+    let bounding_sphere = BoundingSphere::from_bounding_spheres(
+        meshes.values().map(|x| &x.bounding_sphere).collect(),
+    );
+    let bounding_box = meshes
+        .values()
+        .fold(Aabb::new(), |aabb, x| aabb.merge(&x.bounding_box));
+
+    Gltf {
+        meshes: vec![Mesh {
+            name: name.to_string(),
+            meshes,
+            bounding_sphere,
+            bounding_box,
+            morph_weights: RwLock::new(Vec::new()),
+        }],
+        nodes: vec![GltfNode {
+            name: name.to_string(),
+            mesh_index: Some(0),
+            material_indices: Vec::new(),
+            skin_index: None,
+            position: Vec3::zeros(),
+            rotation: Quat::identity(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }],
+        skins: Vec::new(),
+        animations: Vec::new(),
+        bounding_sphere,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::greedy_mesh;
+    use crate::scene::{VoxelId, EMPTY_VOXEL};
+    use nalgebra_glm::Vec3;
+
+    // A chunk with its border, holding `ids` at the given chunk positions.
+    fn padded_chunk(chunk_size: u32, ids: &[([u32; 3], VoxelId)]) -> Vec<VoxelId> {
+        let padded_size = chunk_size + 2;
+        let mut voxels = vec![EMPTY_VOXEL; (padded_size * padded_size * padded_size) as usize];
+        for ([x, y, z], id) in ids {
+            voxels[((x + 1) + (y + 1) * padded_size + (z + 1) * padded_size * padded_size)
+                as usize] = *id;
+        }
+        voxels
+    }
+
+    #[test]
+    fn should_merge_faces_of_same_voxel() {
+        let voxels = padded_chunk(4, &[([0, 0, 0], 1), ([1, 0, 0], 1)]);
+        let faces = greedy_mesh(&voxels, 4, Vec3::zeros());
+
+        // The shared face is hidden and the four long sides are one quad each.
+        let (vertices, indices) = &faces[&1];
+        assert_eq!(vertices.len(), 6 * 4);
+        assert_eq!(indices.len(), 6 * 6);
+
+        let top = vertices
+            .chunks(4)
+            .find(|quad| quad[0].normal == Vec3::new(0.0, 1.0, 0.0))
+            .unwrap();
+        assert!(top.iter().all(|vertex| vertex.position.y == 1.0));
+        assert!(top.iter().any(|vertex| vertex.position.x == 2.0));
+    }
+
+    #[test]
+    fn should_keep_different_voxels_apart() {
+        let voxels = padded_chunk(4, &[([0, 0, 0], 1), ([1, 0, 0], 2)]);
+        let faces = greedy_mesh(&voxels, 4, Vec3::new(4.0, 0.0, 0.0));
+
+        assert_eq!(faces[&1].0.len(), 5 * 4);
+        assert_eq!(faces[&2].0.len(), 5 * 4);
+        assert!(faces[&1].0.iter().all(|vertex| vertex.position.x >= 4.0));
+    }
+
+    #[test]
+    fn should_skip_faces_against_neighbouring_chunks() {
+        // The border voxel left of (0, 0, 0) belongs to the chunk on the left.
+        let mut voxels = padded_chunk(2, &[([0, 0, 0], 1)]);
+        voxels[4 + 16] = 1;
+        let faces = greedy_mesh(&voxels, 2, Vec3::zeros());
+
+        assert_eq!(faces[&1].0.len(), 5 * 4);
+        assert!(faces[&1]
+            .0
+            .iter()
+            .all(|vertex| vertex.normal != Vec3::new(-1.0, 0.0, 0.0)));
+    }
+}
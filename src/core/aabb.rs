@@ -0,0 +1,86 @@
+use nalgebra_glm::Vec3;
+
+/// An axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// An empty box, merging anything into it returns the other box.
+    pub fn new() -> Self {
+        Self {
+            min: Vec3::new(std::f32::MAX, std::f32::MAX, std::f32::MAX),
+            max: Vec3::new(std::f32::MIN, std::f32::MIN, std::f32::MIN),
+        }
+    }
+
+    /// Creates the smallest box that contains every point.
+    pub fn from_points(points: &[Vec3]) -> Self {
+        points.iter().fold(Self::new(), |aabb, point| Self {
+            min: nalgebra_glm::min2(&aabb.min, point),
+            max: nalgebra_glm::max2(&aabb.max, point),
+        })
+    }
+
+    /// Creates the smallest box that contains both boxes.
+    pub fn merge(&self, other: &Aabb) -> Self {
+        Self {
+            min: nalgebra_glm::min2(&self.min, &other.min),
+            max: nalgebra_glm::max2(&self.max, &other.max),
+        }
+    }
+
+    /// True if nothing has been added to the box.
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Half of the size of the box along each axis.
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Aabb;
+    use nalgebra_glm::Vec3;
+
+    #[test]
+    fn should_contain_points() {
+        let aabb = Aabb::from_points(&[
+            Vec3::new(1.0, -2.0, 0.5),
+            Vec3::new(-1.0, 3.0, 0.0),
+            Vec3::new(0.0, 0.0, -4.0),
+        ]);
+        assert_eq!(aabb.min, Vec3::new(-1.0, -2.0, -4.0));
+        assert_eq!(aabb.max, Vec3::new(1.0, 3.0, 0.5));
+        assert_eq!(aabb.center(), Vec3::new(0.0, 0.5, -1.75));
+    }
+
+    #[test]
+    fn should_merge() {
+        let empty = Aabb::new();
+        assert!(empty.is_empty());
+
+        let a = Aabb::from_points(&[Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0)]);
+        let b = Aabb::from_points(&[Vec3::new(-1.0, 0.5, 0.5), Vec3::new(0.5, 2.0, 0.5)]);
+        assert_eq!(empty.merge(&a), a);
+
+        let merged = a.merge(&b);
+        assert_eq!(merged.min, Vec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(merged.max, Vec3::new(1.0, 2.0, 1.0));
+    }
+}
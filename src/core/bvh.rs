@@ -0,0 +1,274 @@
+use super::Aabb;
+use nalgebra_glm::Vec3;
+
+// How many buckets primitives are sorted into along each axis when searching for the cheapest split.
+const SAH_BINS: usize = 12;
+// Nodes with this many primitives or less are never split.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// A node of a `FlatBvh`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BvhNode {
+    pub bounds: Aabb,
+    // Leaves: The first primitive in `FlatBvh::primitives`. Interior nodes: The left child, the right child follows it.
+    first: u32,
+    // How many primitives a leaf holds, 0 for interior nodes.
+    count: u32,
+}
+
+impl BvhNode {
+    pub fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// A bounding volume hierarchy stored as one contiguous list of nodes, the root is the first node.
+/// Built top down with the surface area heuristic over the bounding boxes of any kind of primitive.
+#[derive(Debug, Clone, Default)]
+pub struct FlatBvh {
+    nodes: Vec<BvhNode>,
+    // Primitive indices ordered so each leaf's primitives are next to each other.
+    primitives: Vec<u32>,
+}
+
+fn surface_area(aabb: &Aabb) -> f32 {
+    if aabb.is_empty() {
+        return 0.0;
+    }
+    let size = aabb.max - aabb.min;
+    2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+}
+
+// The distance along the ray to where it enters the box, or 0 if it starts inside.
+fn ray_aabb_distance(aabb: &Aabb, origin: &Vec3, inverse_direction: &Vec3) -> Option<f32> {
+    let t1 = (aabb.min - origin).component_mul(inverse_direction);
+    let t2 = (aabb.max - origin).component_mul(inverse_direction);
+    let near = nalgebra_glm::comp_max(&nalgebra_glm::min2(&t1, &t2)).max(0.0);
+    let far = nalgebra_glm::comp_min(&nalgebra_glm::max2(&t1, &t2));
+    if far >= near {
+        Some(near)
+    } else {
+        None
+    }
+}
+
+impl FlatBvh {
+    /// Builds the tree, primitive `i` is the one bounded by `bounds[i]`.
+    pub fn build(bounds: &[Aabb]) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(bounds.len() * 2),
+            primitives: (0..bounds.len() as u32).collect(),
+        };
+        if bounds.is_empty() {
+            return bvh;
+        }
+
+        let centers: Vec<Vec3> = bounds.iter().map(|aabb| aabb.center()).collect();
+        bvh.nodes.push(BvhNode {
+            bounds: Aabb::new(),
+            first: 0,
+            count: bounds.len() as u32,
+        });
+        bvh.subdivide(0, bounds, &centers);
+        bvh
+    }
+
+    pub fn nodes(&self) -> &[BvhNode] {
+        &self.nodes
+    }
+
+    fn subdivide(&mut self, node_index: usize, bounds: &[Aabb], centers: &[Vec3]) {
+        let first = self.nodes[node_index].first as usize;
+        let count = self.nodes[node_index].count as usize;
+        let primitives = &self.primitives[first..first + count];
+
+        let node_bounds = primitives.iter().fold(Aabb::new(), |aabb, &primitive| {
+            aabb.merge(&bounds[primitive as usize])
+        });
+        self.nodes[node_index].bounds = node_bounds;
+        if count <= MAX_LEAF_SIZE {
+            return;
+        }
+
+        let split = match Self::find_split(primitives, bounds, centers) {
+            Some(split) => split,
+            None => return,
+        };
+        let (axis, position, cost) = split;
+        // Splitting is only worth it if it's cheaper than testing every primitive in this node.
+        if cost >= count as f32 * surface_area(&node_bounds) {
+            return;
+        }
+
+        let (mut left, mut right) = (first, first + count);
+        while left < right {
+            if centers[self.primitives[left] as usize][axis] < position {
+                left += 1;
+            } else {
+                right -= 1;
+                self.primitives.swap(left, right);
+            }
+        }
+        let left_count = left - first;
+        if left_count == 0 || left_count == count {
+            return;
+        }
+
+        let left_child = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds: Aabb::new(),
+            first: first as u32,
+            count: left_count as u32,
+        });
+        self.nodes.push(BvhNode {
+            bounds: Aabb::new(),
+            first: left as u32,
+            count: (count - left_count) as u32,
+        });
+        self.nodes[node_index].first = left_child as u32;
+        self.nodes[node_index].count = 0;
+
+        self.subdivide(left_child, bounds, centers);
+        self.subdivide(left_child + 1, bounds, centers);
+    }
+
+    // Returns the axis, position and cost of the cheapest split between the bins.
+    fn find_split(
+        primitives: &[u32],
+        bounds: &[Aabb],
+        centers: &[Vec3],
+    ) -> Option<(usize, f32, f32)> {
+        let center_bounds = Aabb::from_points(
+            &primitives
+                .iter()
+                .map(|&primitive| centers[primitive as usize])
+                .collect::<Vec<_>>(),
+        );
+
+        let mut best: Option<(usize, f32, f32)> = None;
+        for axis in 0..3 {
+            let (min, max) = (center_bounds.min[axis], center_bounds.max[axis]);
+            if max <= min {
+                continue;
+            }
+
+            let scale = SAH_BINS as f32 / (max - min);
+            let mut bins = [(Aabb::new(), 0usize); SAH_BINS];
+            for &primitive in primitives {
+                let bin = (((centers[primitive as usize][axis] - min) * scale) as usize)
+                    .min(SAH_BINS - 1);
+                bins[bin].0 = bins[bin].0.merge(&bounds[primitive as usize]);
+                bins[bin].1 += 1;
+            }
+
+            let merge_bins = |bins: &[(Aabb, usize)]| {
+                bins.iter()
+                    .fold((Aabb::new(), 0), |(aabb, count), (bin, bin_count)| {
+                        (aabb.merge(bin), count + bin_count)
+                    })
+            };
+            for split in 1..SAH_BINS {
+                let (left, left_count) = merge_bins(&bins[..split]);
+                let (right, right_count) = merge_bins(&bins[split..]);
+                if left_count == 0 || right_count == 0 {
+                    continue;
+                }
+
+                let cost = left_count as f32 * surface_area(&left)
+                    + right_count as f32 * surface_area(&right);
+                if best.map_or(true, |(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, min + split as f32 / scale, cost));
+                }
+            }
+        }
+        best
+    }
+
+    /// Walks the tree along the ray and returns the closest primitive and it's distance.
+    /// `intersect` is called for primitives whose leaf the ray passes through and returns the distance to the primitive if it's hit.
+    pub fn cast_ray(
+        &self,
+        origin: &Vec3,
+        direction: &Vec3,
+        mut intersect: impl FnMut(u32) -> Option<f32>,
+    ) -> Option<(u32, f32)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inverse_direction = Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        let mut closest: Option<(u32, f32)> = None;
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let max_distance = closest.map_or(std::f32::MAX, |(_, distance)| distance);
+            match ray_aabb_distance(&node.bounds, origin, &inverse_direction) {
+                Some(distance) if distance <= max_distance => {}
+                _ => continue,
+            }
+
+            let first = node.first as usize;
+            if node.is_leaf() {
+                for &primitive in &self.primitives[first..first + node.count as usize] {
+                    if let Some(distance) = intersect(primitive) {
+                        if closest.map_or(true, |(_, closest)| distance < closest) {
+                            closest = Some((primitive, distance));
+                        }
+                    }
+                }
+            } else {
+                stack.push(first);
+                stack.push(first + 1);
+            }
+        }
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Aabb, FlatBvh};
+    use nalgebra_glm::Vec3;
+
+    // Unit boxes along x, box `i` starts at x = 2 * i.
+    fn boxes(count: usize) -> Vec<Aabb> {
+        (0..count)
+            .map(|i| {
+                let min = Vec3::new(i as f32 * 2.0, 0.0, 0.0);
+                Aabb::from_points(&[min, min + Vec3::new(1.0, 1.0, 1.0)])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_split_large_nodes() {
+        let bvh = FlatBvh::build(&boxes(16));
+        assert!(!bvh.nodes()[0].is_leaf());
+        assert_eq!(bvh.nodes()[0].bounds, boxes(16)[0].merge(&boxes(16)[15]));
+
+        let mut primitives: Vec<u32> = bvh.primitives.clone();
+        primitives.sort();
+        assert_eq!(primitives, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn should_find_closest_primitive() {
+        let bounds = boxes(16);
+        let bvh = FlatBvh::build(&bounds);
+        let origin = Vec3::new(-5.0, 0.5, 0.5);
+        let direction = Vec3::new(1.0, 0.0, 0.0);
+
+        // Treat each box as the primitive, the ray hits the box's left face.
+        let hit = bvh.cast_ray(&origin, &direction, |primitive| {
+            Some(bounds[primitive as usize].min.x - origin.x)
+        });
+        assert_eq!(hit, Some((0, 5.0)));
+
+        let miss = bvh.cast_ray(&origin, &Vec3::new(0.0, 1.0, 0.0), |_| Some(1.0));
+        assert_eq!(miss, None);
+        assert_eq!(
+            FlatBvh::build(&[]).cast_ray(&origin, &direction, |_| Some(1.0)),
+            None
+        );
+    }
+}
@@ -6,12 +6,21 @@ pub use font::Font;
 mod theme;
 pub use theme::Theme;
 
+mod aabb;
+mod bvh;
 mod bounding_sphere;
 mod plane;
 mod frustum;
+mod sparse_octree;
 pub use frustum::{Frustum, GpuFrustum};
 pub use plane::{Plane, GpuPlane};
+pub use aabb::Aabb;
+pub use bvh::{BvhNode, FlatBvh};
 pub use bounding_sphere::BoundingSphere;
+pub use sparse_octree::SparseOctree;
 
 mod performance_metrics;
-pub use performance_metrics::PerformanceMetrics;
\ No newline at end of file
+pub use performance_metrics::PerformanceMetrics;
+
+mod profiler;
+pub use profiler::{ProfiledSystem, Profiler, PROFILER_FRAMES};
\ No newline at end of file
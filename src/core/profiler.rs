@@ -0,0 +1,194 @@
+use dashmap::DashMap;
+use legion::{
+    borrow::{Exclusive, RefMut},
+    command::CommandBuffer,
+    storage::ComponentTypeId,
+    systems::{
+        resource::{ResourceTypeId, Resources},
+        schedule::{ArchetypeAccess, Runnable, Schedulable},
+        SystemId,
+    },
+    world::{World, WorldId},
+};
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// How many frames of samples are kept for each system.
+pub const PROFILER_FRAMES: usize = 120;
+
+// How often the summary is printed when `HARMONY_PROFILE=1` is set.
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+type Samples = Arc<DashMap<String, VecDeque<Duration>>>;
+
+/// Times how long each system wrapped with `Profiler::wrap` takes to run.
+/// The engine's render systems are wrapped automatically. Set `HARMONY_PROFILE=1` to print a summary every 5 seconds.
+pub struct Profiler {
+    samples: Samples,
+    print_summary: bool,
+    last_report: Instant,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            samples: Arc::new(DashMap::new()),
+            print_summary: std::env::var("HARMONY_PROFILE").map_or(false, |value| value == "1"),
+            last_report: Instant::now(),
+        }
+    }
+
+    /// Wraps a system so each of it's runs is recorded under the system's name.
+    pub fn wrap<S: Schedulable + ?Sized + 'static>(&self, system: Box<S>) -> Box<dyn Schedulable> {
+        Box::new(ProfiledSystem {
+            name: system.name().to_string(),
+            system,
+            samples: self.samples.clone(),
+        })
+    }
+
+    /// Adds a sample for a system, the oldest sample is dropped once there are `PROFILER_FRAMES` of them.
+    pub fn record(&self, system_name: &str, duration: Duration) {
+        record(&self.samples, system_name, duration);
+    }
+
+    /// The average time the system took over the last `PROFILER_FRAMES` runs, 0 if it hasn't run.
+    pub fn average_ms(&self, system_name: &str) -> f32 {
+        self.samples
+            .get(system_name)
+            .map_or(0.0, |samples| average_ms(&samples))
+    }
+
+    /// The longest time the system took over the last `PROFILER_FRAMES` runs, 0 if it hasn't run.
+    pub fn worst_ms(&self, system_name: &str) -> f32 {
+        self.samples
+            .get(system_name)
+            .map_or(0.0, |samples| worst_ms(&samples))
+    }
+
+    /// A table of every recorded system sorted from slowest to fastest on average.
+    pub fn summary(&self) -> String {
+        let mut rows: Vec<(String, f32, f32)> = self
+            .samples
+            .iter()
+            .map(|entry| {
+                (
+                    entry.key().clone(),
+                    average_ms(entry.value()),
+                    worst_ms(entry.value()),
+                )
+            })
+            .collect();
+        rows.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        let mut summary = format!("{:<32} {:>10} {:>10}\n", "system", "avg ms", "worst ms");
+        for (name, average, worst) in rows {
+            summary.push_str(&format!("{:<32} {:>10.3} {:>10.3}\n", name, average, worst));
+        }
+        summary
+    }
+
+    /// Prints the summary if it's enabled and enough time has passed. Called once per frame.
+    pub(crate) fn update(&mut self) {
+        if !self.print_summary || self.last_report.elapsed() < REPORT_INTERVAL {
+            return;
+        }
+        self.last_report = Instant::now();
+        log::info!("System timings:\n{}", self.summary());
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn record(samples: &Samples, system_name: &str, duration: Duration) {
+    let mut system_samples = samples
+        .entry(system_name.to_string())
+        .or_insert_with(|| VecDeque::with_capacity(PROFILER_FRAMES));
+    if system_samples.len() == PROFILER_FRAMES {
+        system_samples.pop_front();
+    }
+    system_samples.push_back(duration);
+}
+
+fn average_ms(samples: &VecDeque<Duration>) -> f32 {
+    let total: Duration = samples.iter().sum();
+    as_ms(total) / samples.len().max(1) as f32
+}
+
+fn worst_ms(samples: &VecDeque<Duration>) -> f32 {
+    samples.iter().max().map_or(0.0, |worst| as_ms(*worst))
+}
+
+// Durations are kept in microseconds so short systems don't round down to 0.
+fn as_ms(duration: Duration) -> f32 {
+    duration.as_micros() as f32 / 1000.0
+}
+
+/// Runs the inner system and records how long it took, created by `Profiler::wrap`.
+pub struct ProfiledSystem<S: Schedulable + ?Sized> {
+    name: String,
+    samples: Samples,
+    system: Box<S>,
+}
+
+impl<S: Schedulable + ?Sized> Runnable for ProfiledSystem<S> {
+    fn name(&self) -> &SystemId {
+        self.system.name()
+    }
+
+    fn reads(&self) -> (&[ResourceTypeId], &[ComponentTypeId]) {
+        self.system.reads()
+    }
+
+    fn writes(&self) -> (&[ResourceTypeId], &[ComponentTypeId]) {
+        self.system.writes()
+    }
+
+    fn prepare(&mut self, world: &World) {
+        self.system.prepare(world);
+    }
+
+    fn accesses_archetypes(&self) -> &ArchetypeAccess {
+        self.system.accesses_archetypes()
+    }
+
+    unsafe fn run_unsafe(&mut self, world: &World, resources: &Resources) {
+        let start = Instant::now();
+        self.system.run_unsafe(world, resources);
+        record(&self.samples, &self.name, start.elapsed());
+    }
+
+    fn command_buffer_mut(&self, world: WorldId) -> Option<RefMut<Exclusive, CommandBuffer>> {
+        self.system.command_buffer_mut(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Profiler, PROFILER_FRAMES};
+    use std::time::Duration;
+
+    #[test]
+    fn should_average_recent_samples() {
+        let profiler = Profiler::new();
+        profiler.record("mesh", Duration::from_micros(500));
+        profiler.record("mesh", Duration::from_micros(1500));
+        assert_eq!(profiler.average_ms("mesh"), 1.0);
+        assert_eq!(profiler.worst_ms("mesh"), 1.5);
+        assert_eq!(profiler.average_ms("missing"), 0.0);
+
+        // Only the last frames are kept so the slow samples are pushed out.
+        for _ in 0..PROFILER_FRAMES {
+            profiler.record("mesh", Duration::from_micros(250));
+        }
+        assert_eq!(profiler.average_ms("mesh"), 0.25);
+        assert_eq!(profiler.worst_ms("mesh"), 0.25);
+    }
+}
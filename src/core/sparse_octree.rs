@@ -0,0 +1,180 @@
+#[derive(Debug, Clone, PartialEq)]
+enum OctreeNode<T> {
+    // Every cell in the node's cube holds this value.
+    Leaf(T),
+    // The eight octants, indexed by the x, y and z bits of `SparseOctree::octant`.
+    Branch(Box<[OctreeNode<T>; 8]>),
+}
+
+/// Stores a value for every integer position in a cube `size` cells wide.
+/// Regions holding the same value collapse into one node, so mostly uniform volumes like voxel worlds stay small.
+/// Unset cells hold `T::default()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseOctree<T> {
+    root: OctreeNode<T>,
+    depth: u32,
+}
+
+impl<T: Copy + PartialEq + Default> SparseOctree<T> {
+    /// Creates an empty octree, `size` is rounded up to the next power of two.
+    pub fn new(size: u32) -> Self {
+        Self {
+            root: OctreeNode::Leaf(T::default()),
+            depth: size.max(1).next_power_of_two().trailing_zeros(),
+        }
+    }
+
+    /// How many cells the octree is wide along each axis.
+    pub fn size(&self) -> u32 {
+        1 << self.depth
+    }
+
+    pub fn contains(&self, x: u32, y: u32, z: u32) -> bool {
+        let size = self.size();
+        x < size && y < size && z < size
+    }
+
+    // Which child of a node at `level` levels above the cells the position lies in.
+    fn octant(level: u32, x: u32, y: u32, z: u32) -> usize {
+        let bit = level - 1;
+        (((x >> bit) & 1) | (((y >> bit) & 1) << 1) | (((z >> bit) & 1) << 2)) as usize
+    }
+
+    /// Returns the default value for positions outside the octree.
+    pub fn get(&self, x: u32, y: u32, z: u32) -> T {
+        if !self.contains(x, y, z) {
+            return T::default();
+        }
+
+        let mut node = &self.root;
+        let mut level = self.depth;
+        loop {
+            match node {
+                OctreeNode::Leaf(value) => return *value,
+                OctreeNode::Branch(children) => {
+                    node = &children[Self::octant(level, x, y, z)];
+                    level -= 1;
+                }
+            }
+        }
+    }
+
+    /// Returns false without changing anything if the position is outside the octree.
+    pub fn set(&mut self, x: u32, y: u32, z: u32, value: T) -> bool {
+        if !self.contains(x, y, z) {
+            return false;
+        }
+        Self::set_node(&mut self.root, self.depth, x, y, z, value);
+        true
+    }
+
+    fn set_node(node: &mut OctreeNode<T>, level: u32, x: u32, y: u32, z: u32, value: T) {
+        if level == 0 {
+            *node = OctreeNode::Leaf(value);
+            return;
+        }
+
+        if let OctreeNode::Leaf(current) = *node {
+            if current == value {
+                return;
+            }
+            let leaf = || OctreeNode::Leaf(current);
+            *node = OctreeNode::Branch(Box::new([
+                leaf(),
+                leaf(),
+                leaf(),
+                leaf(),
+                leaf(),
+                leaf(),
+                leaf(),
+                leaf(),
+            ]));
+        }
+
+        if let OctreeNode::Branch(children) = node {
+            Self::set_node(
+                &mut children[Self::octant(level, x, y, z)],
+                level - 1,
+                x,
+                y,
+                z,
+                value,
+            );
+
+            // Collapse the branch again once all of it's children hold the same value.
+            let first = match children[0] {
+                OctreeNode::Leaf(first) => first,
+                OctreeNode::Branch(_) => return,
+            };
+            if children
+                .iter()
+                .all(|child| *child == OctreeNode::Leaf(first))
+            {
+                *node = OctreeNode::Leaf(first);
+            }
+        }
+    }
+
+    /// How many nodes the octree is made of, used to check how well it's compressed.
+    pub fn node_count(&self) -> usize {
+        fn count<T>(node: &OctreeNode<T>) -> usize {
+            match node {
+                OctreeNode::Leaf(_) => 1,
+                OctreeNode::Branch(children) => 1 + children.iter().map(count).sum::<usize>(),
+            }
+        }
+        count(&self.root)
+    }
+}
+
+impl<T: Copy + PartialEq + Default> Default for SparseOctree<T> {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SparseOctree;
+
+    #[test]
+    fn should_store_values() {
+        let mut octree = SparseOctree::<u16>::new(10);
+        assert_eq!(octree.size(), 16);
+
+        assert!(octree.set(3, 15, 7, 2));
+        assert_eq!(octree.get(3, 15, 7), 2);
+        assert_eq!(octree.get(3, 15, 6), 0);
+
+        assert!(!octree.set(16, 0, 0, 1));
+        assert_eq!(octree.get(16, 0, 0), 0);
+    }
+
+    #[test]
+    fn should_collapse_uniform_regions() {
+        let mut octree = SparseOctree::<u16>::new(4);
+        assert_eq!(octree.node_count(), 1);
+
+        octree.set(0, 0, 0, 1);
+        // The root and the bottom octant are split.
+        assert_eq!(octree.node_count(), 17);
+
+        for z in 0..2 {
+            for y in 0..2 {
+                for x in 0..2 {
+                    octree.set(x, y, z, 1);
+                }
+            }
+        }
+        assert_eq!(octree.node_count(), 9);
+
+        for z in 0..2 {
+            for y in 0..2 {
+                for x in 0..2 {
+                    octree.set(x, y, z, 0);
+                }
+            }
+        }
+        assert_eq!(octree.node_count(), 1);
+    }
+}
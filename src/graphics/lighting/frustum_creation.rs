@@ -73,7 +73,7 @@ impl FrustumCreation {
         let mut pipeline_desc = ComputePipelineDesc::new("core/shaders/clustered/froxels.shader");
         pipeline_desc.layouts = vec!["froxel_layout".to_string()];
 
-        pipeline_manager.add_compute_pipeline("froxel_creation", &pipeline_desc, vec![], &device, asset_manager, gpu_resource_manager.clone());
+        pipeline_manager.add_compute_pipeline("froxel_creation", &pipeline_desc, vec![], &device, asset_manager, gpu_resource_manager.clone()).unwrap();
 
         Self {
             uniform_buffer,
@@ -50,7 +50,7 @@ impl LightCulling {
         let mut pipeline_desc = ComputePipelineDesc::new("core/shaders/clustered/light_culling.shader");
         pipeline_desc.layouts = vec!["froxel_cull_layout".to_string(), "globals".to_string()];
 
-        pipeline_manager.add_compute_pipeline("froxel_cull", &pipeline_desc, vec!["globals"], &device, asset_manager, gpu_resource_manager.clone());
+        pipeline_manager.add_compute_pipeline("froxel_cull", &pipeline_desc, vec!["globals"], &device, asset_manager, gpu_resource_manager.clone()).unwrap();
 
         Self {
             gpu_resource_manager,
@@ -1,2 +1,2 @@
 pub(crate) mod skybox;
-pub use self::skybox::Skybox;
+pub use self::skybox::{Skybox, SkyboxError};
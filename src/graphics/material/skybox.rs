@@ -1,28 +1,61 @@
 use nalgebra_glm::Vec3;
 
 use crate::{
+    assets::Texture,
     graphics::{
         resources::{GPUResourceManager, RenderTarget},
-        RenderGraph,
+        RenderGraph, RenderGraphError,
     },
     Application, AssetManager,
 };
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, path::Path, sync::Arc};
 
 pub const SPEC_CUBEMAP_MIP_LEVELS: u32 = 6;
 
+const PANORAMA_SHADER: &str =
+    include_str!("../../../assets/core/shaders/calculations/panorama_to_cube.comp.glsl");
+
+// Must match the local size in the panorama compute shader.
+const PANORAMA_WORKGROUP_SIZE: u32 = 8;
+
+/// The format of cube maps converted from HDR panoramas.
+pub const PANORAMA_CUBEMAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Returned when an HDR panorama or the faces of a cube map can't be made into a skybox.
+#[derive(Debug)]
+pub enum SkyboxError {
+    /// The file couldn't be read.
+    Io(std::io::Error),
+    /// The file isn't a valid `.hdr` image.
+    InvalidImage(image::ImageError),
+    /// The cube map didn't get six square faces that share the same size and format.
+    MismatchedFaces,
+}
+
+// A cube map converted from an HDR panorama, cached by the asset manager.
+#[derive(Clone)]
+pub(crate) struct PanoramaCubemap {
+    texture: Arc<wgpu::Texture>,
+    size: u32,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum SkyboxType {
     ClearColor,
     HdrCubemap,
+    /// A cube map created from six face images.
+    Cubemap,
     RealTime,
 }
 
+/// The suffixes of each face image in the order the faces are stored in the cube map.
+pub const CUBEMAP_FACES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
 pub struct Skybox {
     pub size: f32,
     pub skybox_type: SkyboxType,
     pub clear_color: Vec3,
-    pub(crate) color_texture: Option<wgpu::Texture>,
+    pub(crate) color_texture: Option<Arc<wgpu::Texture>>,
     pub(crate) color_view: Option<wgpu::TextureView>,
     pub(crate) cubemap_sampler: Option<wgpu::Sampler>,
     pub(crate) cubemap_bind_group: Option<wgpu::BindGroup>,
@@ -33,7 +66,13 @@ pub struct Skybox {
 // that can load in memory via async!
 
 impl Skybox {
-    pub fn new_hdr<T>(app: &mut Application, texture: T, size: f32) -> Self
+    /// Projects an equirectangular hdr image onto a cube map of `size`.
+    /// Returns an error if the render graph used for the projection can't be built.
+    pub fn new_hdr<T>(
+        app: &mut Application,
+        texture: T,
+        size: f32,
+    ) -> Result<Self, RenderGraphError>
     where
         T: Into<String>,
     {
@@ -72,6 +111,7 @@ impl Skybox {
             Some(cube_map_target),
             false,
         );
+        graph.build()?;
 
         // We need to convert our regular texture map to a cube texture map with 6 faces.
         // Should be straight forward enough if we use equirectangular projection.
@@ -84,7 +124,7 @@ impl Skybox {
             &mut app.current_scene.world,
             None,
             None,
-        );
+        )?;
         // Push to all command buffers to the queue
         let queue = app.resources.get::<Arc<wgpu::Queue>>().unwrap();
         queue.submit(vec![command_buffer]);
@@ -122,9 +162,157 @@ impl Skybox {
             ..Default::default()
         });
 
-        Self {
+        Ok(Self {
             size,
-            color_texture: Some(color.texture),
+            color_texture: Some(Arc::new(color.texture)),
+            color_view: Some(color_view),
+            cubemap_sampler: Some(cubemap_sampler),
+            cubemap_bind_group: None,
+            pbr_bind_group: None,
+            clear_color: Vec3::zeros(),
+            skybox_type: SkyboxType::HdrCubemap,
+        })
+    }
+
+    /// Creates a skybox from six face textures ordered +x, -x, +y, -y, +z, -z.
+    /// Every face needs to be square and share the same size and format, otherwise `SkyboxError::MismatchedFaces` is returned.
+    /// Like the HDR skybox the cube map is used by probes for image based lighting.
+    pub fn new_cubemap(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[Arc<Texture>],
+    ) -> Result<Self, SkyboxError> {
+        if faces.len() != 6 {
+            return Err(SkyboxError::MismatchedFaces);
+        }
+        let size = faces[0].extent.width;
+        let format = faces[0].format;
+        let matching = faces.iter().all(|face| {
+            face.extent.width == size && face.extent.height == size && face.format == format
+        });
+        if !matching {
+            return Err(SkyboxError::MismatchedFaces);
+        }
+
+        let color_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            label: Some("skybox_cubemap"),
+        });
+
+        // Copy each face into it's layer of the cube map.
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("skybox_cubemap"),
+        });
+        for (layer, face) in faces.iter().enumerate() {
+            encoder.copy_texture_to_texture(
+                wgpu::TextureCopyView {
+                    texture: &face.inner,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                wgpu::TextureCopyView {
+                    texture: &color_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth: 1,
+                },
+            );
+        }
+        queue.submit(Some(encoder.finish()));
+
+        let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: None,
+            format,
+            dimension: wgpu::TextureViewDimension::Cube,
+            aspect: wgpu::TextureAspect::default(),
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            array_layer_count: 6,
+        });
+
+        let cubemap_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            size: size as f32,
+            color_texture: Some(Arc::new(color_texture)),
+            color_view: Some(color_view),
+            cubemap_sampler: Some(cubemap_sampler),
+            cubemap_bind_group: None,
+            pbr_bind_group: None,
+            clear_color: Vec3::zeros(),
+            skybox_type: SkyboxType::Cubemap,
+        })
+    }
+
+    /// Loads an equirectangular `.hdr` image and converts it into a `PANORAMA_CUBEMAP_FORMAT` cube map with a compute shader.
+    /// Each face is a quarter of the panorama's width. Like the other cube map skyboxes it's used by probes for image based lighting.
+    /// Note: This blocks while the file is read and converted. `AssetManager::load_hdr_panorama` caches the conversion.
+    pub fn from_hdr_panorama(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &str,
+    ) -> Result<Self, SkyboxError> {
+        let cubemap = convert_hdr_panorama(device, queue, Path::new(path))?;
+        Ok(Self::from_panorama_cubemap(device, cubemap))
+    }
+
+    pub(crate) fn from_panorama_cubemap(device: &wgpu::Device, cubemap: PanoramaCubemap) -> Self {
+        let color_view = cubemap.texture.create_view(&wgpu::TextureViewDescriptor {
+            label: None,
+            format: PANORAMA_CUBEMAP_FORMAT,
+            dimension: wgpu::TextureViewDimension::Cube,
+            aspect: wgpu::TextureAspect::default(),
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            array_layer_count: 6,
+        });
+
+        let cubemap_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        Self {
+            size: cubemap.size as f32,
+            color_texture: Some(cubemap.texture),
             color_view: Some(color_view),
             cubemap_sampler: Some(cubemap_sampler),
             cubemap_bind_group: None,
@@ -247,3 +435,258 @@ impl Skybox {
     //     }
     // }
 }
+
+// Reads an equirectangular `.hdr` image and writes every face of a cube map from it on the GPU.
+pub(crate) fn convert_hdr_panorama(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    path: &Path,
+) -> Result<PanoramaCubemap, SkyboxError> {
+    let data = std::fs::read(path).map_err(SkyboxError::Io)?;
+    let decoder =
+        image::hdr::HdrDecoder::new(data.as_slice()).map_err(SkyboxError::InvalidImage)?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr().map_err(SkyboxError::InvalidImage)?;
+    let (width, height) = (metadata.width, metadata.height);
+    let image_data = pixels
+        .iter()
+        .flat_map(|pixel| vec![pixel[0], pixel[1], pixel[2], 1.0])
+        .collect::<Vec<f32>>();
+
+    let panorama_extent = wgpu::Extent3d {
+        width,
+        height,
+        depth: 1,
+    };
+    let panorama = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("hdr_panorama"),
+        size: panorama_extent,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+    });
+    queue.write_texture(
+        wgpu::TextureCopyView {
+            texture: &panorama,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        bytemuck::cast_slice(&image_data),
+        wgpu::TextureDataLayout {
+            offset: 0,
+            bytes_per_row: width * 16,
+            rows_per_image: height,
+        },
+        panorama_extent,
+    );
+    let panorama_view = panorama.create_default_view();
+
+    // The panorama covers 360 degrees horizontally, four faces wide.
+    let size = (width / 4).max(1);
+    let cubemap = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("hdr_panorama_cubemap"),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth: 6,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: PANORAMA_CUBEMAP_FORMAT,
+        usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::STORAGE,
+    });
+    let cubemap_view = cubemap.create_view(&wgpu::TextureViewDescriptor {
+        label: None,
+        format: PANORAMA_CUBEMAP_FORMAT,
+        dimension: wgpu::TextureViewDimension::D2Array,
+        aspect: wgpu::TextureAspect::All,
+        base_mip_level: 0,
+        level_count: 1,
+        base_array_layer: 0,
+        array_layer_count: 6,
+    });
+
+    let mut compiler = shaderc::Compiler::new().unwrap();
+    let spirv = compiler
+        .compile_into_spirv(
+            PANORAMA_SHADER,
+            shaderc::ShaderKind::Compute,
+            "panorama_to_cube.comp.glsl",
+            "main",
+            None,
+        )
+        .unwrap();
+    let module = device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(
+        spirv.as_binary(),
+    )));
+
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(Cow::Borrowed("panorama_to_cube")),
+        entries: Cow::Borrowed(&[
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::COMPUTE,
+                wgpu::BindingType::SampledTexture {
+                    component_type: wgpu::TextureComponentType::Float,
+                    multisampled: false,
+                    dimension: wgpu::TextureViewDimension::D2,
+                },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                1,
+                wgpu::ShaderStage::COMPUTE,
+                wgpu::BindingType::Sampler { comparison: false },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                2,
+                wgpu::ShaderStage::COMPUTE,
+                wgpu::BindingType::StorageTexture {
+                    dimension: wgpu::TextureViewDimension::D2Array,
+                    format: PANORAMA_CUBEMAP_FORMAT,
+                    readonly: false,
+                },
+            ),
+        ]),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        bind_group_layouts: Cow::Borrowed(&[&layout]),
+        push_constant_ranges: Cow::Borrowed(&[]),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        layout: &pipeline_layout,
+        compute_stage: wgpu::ProgrammableStageDescriptor {
+            module: &module,
+            entry_point: Cow::Borrowed("main"),
+        },
+    });
+
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("hdr_panorama"),
+        // Wraps horizontally so the seam at the back of the panorama blends.
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(Cow::Borrowed("panorama_to_cube")),
+        layout: &layout,
+        entries: Cow::Borrowed(&[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&panorama_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&cubemap_view),
+            },
+        ]),
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("panorama_to_cube"),
+    });
+    {
+        let mut pass = encoder.begin_compute_pass();
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        let groups = (size + PANORAMA_WORKGROUP_SIZE - 1) / PANORAMA_WORKGROUP_SIZE;
+        pass.dispatch(groups, groups, 6);
+    }
+    queue.submit(Some(encoder.finish()));
+
+    Ok(PanoramaCubemap {
+        texture: Arc::new(cubemap),
+        size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Skybox, SkyboxError, SkyboxType};
+    use crate::assets::{texture::RenderTextureDesc, Texture};
+    use std::sync::Arc;
+
+    #[test]
+    fn should_convert_hdr_panorama() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+
+            adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap()
+        });
+
+        let skybox = Skybox::from_hdr_panorama(&device, &queue, "./assets/core/mie.hdr").unwrap();
+        assert_eq!(skybox.skybox_type, SkyboxType::HdrCubemap);
+        // The image is 64 pixels wide.
+        assert_eq!(skybox.size, 16.0);
+
+        let missing = Skybox::from_hdr_panorama(&device, &queue, "./assets/core/missing.hdr");
+        assert!(matches!(missing, Err(SkyboxError::Io(_))));
+    }
+
+    #[test]
+    fn should_reject_mismatched_cubemap_faces() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+
+            adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap()
+        });
+        let face = |width: u32, height: u32| {
+            let desc = RenderTextureDesc::new(width, height, wgpu::TextureFormat::Rgba8Unorm);
+            Arc::new(Texture::new_render_texture(&device, "face".into(), &desc))
+        };
+
+        let mut faces: Vec<_> = (0..5).map(|_| face(16, 16)).collect();
+        let too_few = Skybox::new_cubemap(&device, &queue, &faces);
+        assert!(matches!(too_few, Err(SkyboxError::MismatchedFaces)));
+
+        // One face that isn't square.
+        faces.push(face(16, 8));
+        let not_square = Skybox::new_cubemap(&device, &queue, &faces);
+        assert!(matches!(not_square, Err(SkyboxError::MismatchedFaces)));
+    }
+}
@@ -4,10 +4,16 @@ pub use renderer::Renderer;
 pub mod material;
 
 mod render_graph;
-pub use render_graph::{CommandBufferQueue, CommandQueueItem, RenderGraph};
+pub use render_graph::{
+    CommandBufferQueue, CommandQueueItem, RenderGraph, RenderGraphError, RenderPriority,
+    SubmissionIndex,
+};
 
 mod pipeline;
-pub use pipeline::{BindGroupWithData, SimplePipeline, SimplePipelineDesc, VertexStateBuilder};
+pub use pipeline::{
+    BindGroupWithData, ResizeAware, SimplePipeline, SimplePipelineDesc, VertexLayoutError,
+    VertexStateBuilder,
+};
 
 pub mod pipelines;
 
@@ -16,6 +22,10 @@ pub mod resources;
 pub mod systems;
 
 pub mod pipeline_manager;
+pub use pipeline_manager::ComputeNodeDesc;
+
+pub mod pipeline_cache;
+pub use pipeline_cache::PipelineCache;
 
 pub mod shadows;
 
@@ -1,5 +1,8 @@
 use super::resources::{GPUResourceManager, RenderTarget};
-use crate::{assets::shader::Shader, AssetManager};
+use crate::{
+    assets::shader::{Shader, SpecializationConstant},
+    AssetManager,
+};
 use std::{borrow::Cow, sync::Arc};
 
 pub struct BindGroupWithData {
@@ -7,6 +10,12 @@ pub struct BindGroupWithData {
     pub(crate) bind_group: wgpu::BindGroup,
 }
 
+/// Implemented by compute nodes holding resources that depend on the size of the window.
+/// See `PipelineManager::notify_resize`.
+pub trait ResizeAware {
+    fn on_resize(&mut self, device: &wgpu::Device, width: u32, height: u32);
+}
+
 /// DEPRECIATED DO NOT USE.
 pub trait SimplePipeline: Send + Sync + 'static {
     fn prepare(
@@ -71,10 +80,13 @@ pub trait SimplePipelineDesc: std::fmt::Debug {
         let mut bind_group_layouts = self.create_layout(&device, resource_manager);
         let rasterization_state = self.rasterization_state_desc();
         let primitive_topology = self.primitive_topology();
-        let color_states = self.color_states_desc(&sc_desc);
+        let sample_count = self.create_samplers(&device);
+        let color_states = self.color_states_desc(&[sc_desc.format]);
         let depth_stencil_state = self.depth_stencil_state_desc();
         let vertex_state_builder = self.vertex_state_desc();
-        let sample_count = self.create_samplers(&device);
+        if let Err(error) = vertex_state_builder.validate_strides() {
+            panic!("Invalid vertex layout for {:?}: {:?}", self, error);
+        }
         let sample_mask = self.sampler_mask();
         let alpha_to_coverage_enabled = self.alpha_to_coverage_enabled();
 
@@ -123,6 +135,12 @@ pub trait SimplePipelineDesc: std::fmt::Debug {
         pipeline
     }
 
+    /// Constants to compile the shader with. Implementations should load their shader with
+    /// `AssetManager::get_specialized_shader` and pass these in.
+    fn specialization_constants(&self) -> Vec<SpecializationConstant> {
+        Vec::new()
+    }
+
     // TODO: Support other types of shaders like compute.
     // Also support having only a vertex shader.
     fn load_shader<'a>(
@@ -136,10 +154,8 @@ pub trait SimplePipelineDesc: std::fmt::Debug {
     ) -> Vec<Arc<wgpu::BindGroupLayout>>;
     fn rasterization_state_desc(&self) -> wgpu::RasterizationStateDescriptor;
     fn primitive_topology(&self) -> wgpu::PrimitiveTopology;
-    fn color_states_desc(
-        &self,
-        sc_desc: &wgpu::SwapChainDescriptor,
-    ) -> Vec<wgpu::ColorStateDescriptor>;
+    /// `formats[0]` is the primary target, the swap chain's format when the pipeline is added to the render graph.
+    fn color_states_desc(&self, formats: &[wgpu::TextureFormat]) -> Vec<wgpu::ColorStateDescriptor>;
     fn depth_stencil_state_desc(&self) -> Option<wgpu::DepthStencilStateDescriptor>;
     fn vertex_state_desc(&self) -> VertexStateBuilder;
     fn create_samplers(&self, _device: &wgpu::Device) -> u32 {
@@ -159,6 +175,18 @@ pub trait SimplePipelineDesc: std::fmt::Debug {
     ) -> Self::Pipeline;
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VertexLayoutError {
+    /// Thrown when an attribute reads past the end of the vertex.
+    OffsetOutOfBounds {
+        attr_index: usize,
+        offset: wgpu::BufferAddress,
+        stride: wgpu::BufferAddress,
+    },
+    /// Thrown when two attributes read the same bytes. Contains the index of both attributes.
+    Overlap { first: usize, second: usize },
+}
+
 #[derive(Debug, Hash, Clone)]
 pub struct VertexStateBuilder {
     pub(crate) index_format: wgpu::IndexFormat,
@@ -191,6 +219,49 @@ impl VertexStateBuilder {
         });
         self
     }
+
+    /// Checks that the attributes of every buffer fit inside of `T` and don't overlap.
+    /// Catches attribute offsets that no longer match the vertex struct after it changes.
+    pub fn validate<T: bytemuck::Pod>(&self) -> Result<(), VertexLayoutError> {
+        let size = std::mem::size_of::<T>() as wgpu::BufferAddress;
+        for desc in self.buffer_desc.iter() {
+            validate_attributes(&desc.attributes, size)?;
+        }
+        Ok(())
+    }
+
+    /// Same as `validate` but checks each buffer against it's own stride.
+    pub(crate) fn validate_strides(&self) -> Result<(), VertexLayoutError> {
+        for desc in self.buffer_desc.iter() {
+            validate_attributes(&desc.attributes, desc.stride)?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_attributes(
+    attributes: &[wgpu::VertexAttributeDescriptor],
+    stride: wgpu::BufferAddress,
+) -> Result<(), VertexLayoutError> {
+    for (attr_index, attribute) in attributes.iter().enumerate() {
+        if attribute.offset + attribute.format.size() > stride {
+            return Err(VertexLayoutError::OffsetOutOfBounds {
+                attr_index,
+                offset: attribute.offset,
+                stride,
+            });
+        }
+    }
+
+    for (first, a) in attributes.iter().enumerate() {
+        for (second, b) in attributes.iter().enumerate().skip(first + 1) {
+            if a.offset < b.offset + b.format.size() && b.offset < a.offset + a.format.size() {
+                return Err(VertexLayoutError::Overlap { first, second });
+            }
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Hash, Clone)]
@@ -199,3 +270,65 @@ pub struct VertexBufferDescriptor {
     pub(crate) step_mode: wgpu::InputStepMode,
     pub(crate) attributes: Vec<wgpu::VertexAttributeDescriptor>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{VertexLayoutError, VertexStateBuilder};
+    use crate::assets::mesh::MeshVertexData;
+
+    fn builder(attributes: Vec<wgpu::VertexAttributeDescriptor>) -> VertexStateBuilder {
+        let mut builder = VertexStateBuilder::new();
+        builder.new_buffer_descriptor(
+            std::mem::size_of::<MeshVertexData>() as wgpu::BufferAddress,
+            wgpu::InputStepMode::Vertex,
+            attributes,
+        );
+        builder
+    }
+
+    #[test]
+    fn mesh_vertex_layout_is_valid() {
+        let builder = builder(
+            wgpu::vertex_attr_array![0 => Float3, 1 => Float3, 2 => Float2, 3 => Float4].to_vec(),
+        );
+        assert_eq!(builder.validate::<MeshVertexData>(), Ok(()));
+    }
+
+    #[test]
+    fn should_catch_out_of_bounds_and_overlap() {
+        let stride = std::mem::size_of::<MeshVertexData>() as wgpu::BufferAddress;
+        let out_of_bounds = builder(vec![wgpu::VertexAttributeDescriptor {
+            format: wgpu::VertexFormat::Float4,
+            offset: stride - 4,
+            shader_location: 0,
+        }]);
+        assert_eq!(
+            out_of_bounds.validate::<MeshVertexData>(),
+            Err(VertexLayoutError::OffsetOutOfBounds {
+                attr_index: 0,
+                offset: stride - 4,
+                stride,
+            })
+        );
+
+        let overlap = builder(vec![
+            wgpu::VertexAttributeDescriptor {
+                format: wgpu::VertexFormat::Float3,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttributeDescriptor {
+                format: wgpu::VertexFormat::Float3,
+                offset: 8,
+                shader_location: 1,
+            },
+        ]);
+        assert_eq!(
+            overlap.validate::<MeshVertexData>(),
+            Err(VertexLayoutError::Overlap {
+                first: 0,
+                second: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    convert::TryInto,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+// Written at the start of every cache file, bump the version when the layout changes.
+const CACHE_MAGIC: &[u8; 4] = b"HRPC";
+const CACHE_VERSION: u32 = 2;
+// The shaderc crate doesn't expose its version at runtime, keep this in sync with Cargo.toml.
+const SHADERC_VERSION: &str = "0.6";
+
+// 64 bit FNV-1a, used instead of `DefaultHasher` because keys are saved to disk and have to stay the
+// same across Rust releases.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // Strings are length prefixed so "ab" + "c" and "a" + "bc" hash differently.
+    fn write_str(&mut self, value: &str) {
+        self.write_u64(value.len() as u64);
+        self.write(value.as_bytes());
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Compiled SPIR-V for GLSL shader stages, saved to disk so later runs skip compiling them.
+/// wgpu doesn't expose driver pipeline caches yet, so the cache stores the output of shaderc, which is most of the cold start cost.
+/// Entries are keyed by the preprocessed source, which includes the specialization constants and included files.
+/// See `PipelineManager::set_cache`.
+pub struct PipelineCache {
+    path: PathBuf,
+    entries: dashmap::DashMap<u64, Vec<u32>>,
+    // Hash of the features and limits of the device the entries were compiled for.
+    hardware_key: AtomicU64,
+    dirty: AtomicBool,
+}
+
+impl PipelineCache {
+    /// Loads the cache file at `path`, a missing or unreadable file starts an empty cache.
+    pub fn new(path: &str) -> PipelineCache {
+        let path = PathBuf::from(path);
+        let cache = PipelineCache {
+            path,
+            entries: dashmap::DashMap::new(),
+            hardware_key: AtomicU64::new(0),
+            dirty: AtomicBool::new(false),
+        };
+
+        if let Ok(bytes) = std::fs::read(&cache.path) {
+            match decode(&bytes) {
+                Some((hardware_key, entries)) => {
+                    cache.hardware_key.store(hardware_key, Ordering::Relaxed);
+                    for (key, spirv) in entries {
+                        cache.entries.insert(key, spirv);
+                    }
+                }
+                None => log::warn!("Ignoring invalid pipeline cache {:?}.", cache.path),
+            }
+        }
+
+        cache
+    }
+
+    /// Returns the key of a shader stage from its preprocessed source.
+    /// The key also covers the cache and shaderc versions, so upgrading either misses old entries.
+    pub fn key(preprocessed_source: &str, kind: shaderc::ShaderKind) -> u64 {
+        let mut hasher = StableHasher::new();
+        hasher.write_u32(CACHE_VERSION);
+        hasher.write_str(SHADERC_VERSION);
+        let (spirv_version, spirv_revision) = shaderc::get_spirv_version();
+        hasher.write_u32(spirv_version);
+        hasher.write_u32(spirv_revision);
+        hasher.write_str(preprocessed_source);
+        hasher.write_u32(kind as u32);
+        // Debug builds compile without optimizations.
+        hasher.write(&[cfg!(debug_assertions) as u8]);
+        hasher.finish()
+    }
+
+    pub fn get(&self, key: u64) -> Option<Vec<u32>> {
+        self.entries.get(&key).map(|spirv| spirv.clone())
+    }
+
+    pub fn insert(&self, key: u64, spirv: Vec<u32>) {
+        self.entries.insert(key, spirv);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Clears every entry if they were compiled for a device with different features or limits,
+    /// for example after a driver update or when running on other hardware.
+    pub fn invalidate(&self, device: &wgpu::Device) {
+        let limits = device.limits();
+        let mut hasher = StableHasher::new();
+        hasher.write_u64(device.features().bits());
+        for limit in [
+            limits.max_bind_groups,
+            limits.max_dynamic_uniform_buffers_per_pipeline_layout,
+            limits.max_dynamic_storage_buffers_per_pipeline_layout,
+            limits.max_sampled_textures_per_shader_stage,
+            limits.max_samplers_per_shader_stage,
+            limits.max_storage_buffers_per_shader_stage,
+            limits.max_storage_textures_per_shader_stage,
+            limits.max_uniform_buffers_per_shader_stage,
+            limits.max_uniform_buffer_binding_size,
+            limits.max_push_constant_size,
+        ]
+        .iter()
+        {
+            hasher.write_u32(*limit);
+        }
+        self.invalidate_key(hasher.finish());
+    }
+
+    fn invalidate_key(&self, hardware_key: u64) {
+        if self.hardware_key.swap(hardware_key, Ordering::Relaxed) != hardware_key {
+            self.entries.clear();
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Writes the cache to its file if anything changed since it was loaded.
+    pub fn save(&self) -> std::io::Result<()> {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(CACHE_MAGIC);
+        bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.hardware_key.load(Ordering::Relaxed).to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in self.entries.iter() {
+            bytes.extend_from_slice(&entry.key().to_le_bytes());
+            bytes.extend_from_slice(&(entry.value().len() as u32).to_le_bytes());
+            for word in entry.value().iter() {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        std::fs::write(&self.path, bytes)
+    }
+}
+
+// Reads the hardware key and entries of a cache file, None if it's corrupt or from another version.
+fn decode(bytes: &[u8]) -> Option<(u64, Vec<(u64, Vec<u32>)>)> {
+    let mut offset = 0;
+    let mut take = |count: usize| {
+        let slice = bytes.get(offset..offset + count)?;
+        offset += count;
+        Some(slice)
+    };
+
+    if take(4)? != CACHE_MAGIC {
+        return None;
+    }
+    if u32::from_le_bytes(take(4)?.try_into().ok()?) != CACHE_VERSION {
+        return None;
+    }
+    let hardware_key = u64::from_le_bytes(take(8)?.try_into().ok()?);
+    let count = u32::from_le_bytes(take(4)?.try_into().ok()?);
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let key = u64::from_le_bytes(take(8)?.try_into().ok()?);
+        let word_count = u32::from_le_bytes(take(4)?.try_into().ok()?) as usize;
+        let spirv = take(word_count * 4)?
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect();
+        entries.push((key, spirv));
+    }
+
+    Some((hardware_key, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PipelineCache, StableHasher};
+
+    #[test]
+    fn should_save_and_load() {
+        let path = std::env::temp_dir().join("harmony_pipeline_cache_test.bin");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let cache = PipelineCache::new(path);
+        assert!(cache.is_empty());
+        let key = PipelineCache::key("void main() {}", shaderc::ShaderKind::Vertex);
+        assert_ne!(
+            key,
+            PipelineCache::key("void main() {}", shaderc::ShaderKind::Fragment)
+        );
+        cache.invalidate_key(7);
+        cache.insert(key, vec![0x0723_0203, 1, 2]);
+        cache.save().unwrap();
+
+        let cache = PipelineCache::new(path);
+        assert_eq!(cache.get(key), Some(vec![0x0723_0203, 1, 2]));
+
+        // Entries from other hardware are thrown away.
+        cache.invalidate_key(7);
+        assert_eq!(cache.len(), 1);
+        cache.invalidate_key(8);
+        assert_eq!(cache.len(), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn should_hash_with_fnv1a() {
+        // Reference values for 64 bit FNV-1a, cache files depend on these never changing.
+        let hash = |bytes: &[u8]| {
+            let mut hasher = StableHasher::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn should_ignore_corrupt_files() {
+        let path = std::env::temp_dir().join("harmony_pipeline_cache_corrupt.bin");
+        std::fs::write(&path, b"HRPC\x02\x00\x00\x00").unwrap();
+
+        let cache = PipelineCache::new(path.to_str().unwrap());
+        assert_eq!(cache.len(), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,15 +1,25 @@
 use ordered_float::OrderedFloat;
-use std::collections::{hash_map::DefaultHasher, HashMap};
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
+use futures::executor::{ThreadPool, ThreadPoolBuilder};
 use std::{
     hash::{Hash, Hasher},
-    sync::Arc, borrow::Cow,
+    sync::{Arc, OnceLock}, borrow::Cow,
 };
 
 use super::{
-    renderer::FRAME_FORMAT, resources::GPUResourceManager, CommandBufferQueue, VertexStateBuilder,
+    render_graph::topological_sort,
+    renderer::FRAME_FORMAT,
+    resources::{GPUResourceManager, GpuProfiler},
+    CommandBufferQueue, CommandQueueItem, PipelineCache, RenderGraphError, RenderPriority,
+    ResizeAware, VertexStateBuilder,
+};
+use crate::{
+    assets::{
+        shader::{Shader, SpecializationConstant},
+        AssetError, AssetHandle,
+    },
+    AssetManager,
 };
-use crate::{assets::shader::Shader, AssetManager};
-use solvent::DepGraph;
 
 /// A description of a render pipeline.
 /// Note: You can call `default()` to get a base implementation.
@@ -27,10 +37,14 @@ pub struct PipelineDesc {
     pub layouts: Vec<String>,
     pub front_face: wgpu::FrontFace,
     pub cull_mode: wgpu::CullMode,
+    /// Anything but `Fill` needs `wgpu::Features::NON_FILL_POLYGON_MODE`.
+    pub polygon_mode: wgpu::PolygonMode,
     pub depth_bias: i32,
     pub depth_bias_slope_scale: OrderedFloat<f32>, // Use OrderedFloat because of hash.
     pub depth_bias_clamp: OrderedFloat<f32>,
     pub push_constant_ranges: Vec<wgpu::PushConstantRange>,
+    /// Baked into the shader when it's compiled, see `SpecializationConstant`.
+    pub specialization_constants: Vec<SpecializationConstant>,
 }
 
 impl Default for PipelineDesc {
@@ -52,10 +66,12 @@ impl Default for PipelineDesc {
             layouts: Vec::new(),
             front_face: wgpu::FrontFace::Cw,
             cull_mode: wgpu::CullMode::Back,
+            polygon_mode: wgpu::PolygonMode::Fill,
             depth_bias: 0,
             depth_bias_slope_scale: 0.0.into(),
             depth_bias_clamp: 0.0.into(),
             push_constant_ranges: Vec::new(),
+            specialization_constants: Vec::new(),
         }
     }
 }
@@ -75,8 +91,31 @@ impl PipelineDesc {
         device: &wgpu::Device,
         gpu_resource_manager: &GPUResourceManager,
     ) -> Pipeline {
-        let shader_handle = asset_manager.get_shader(self.shader.clone());
+        let shader_handle = self.shader_handle(asset_manager);
         let shader = futures::executor::block_on(shader_handle.get_async()).unwrap();
+        self.build_with_shader(&shader, device, gpu_resource_manager)
+    }
+
+    /// Returns a copy of the description that renders into `formats`, `formats[0]` being the primary target.
+    /// Formats past the last color state are ignored.
+    pub fn with_color_formats(&self, formats: &[wgpu::TextureFormat]) -> PipelineDesc {
+        let mut desc = self.clone();
+        for (color_state, format) in desc.color_states.iter_mut().zip(formats.iter()) {
+            color_state.format = *format;
+        }
+        desc
+    }
+
+    fn shader_handle(&self, asset_manager: &AssetManager) -> Arc<AssetHandle<Shader>> {
+        asset_manager.get_specialized_shader(self.shader.clone(), &self.specialization_constants)
+    }
+
+    fn build_with_shader(
+        &self,
+        shader: &Shader,
+        device: &wgpu::Device,
+        gpu_resource_manager: &GPUResourceManager,
+    ) -> Pipeline {
         let shader = match *shader {
             Shader::Core(ref shader) => shader,
             _ => panic!("Pipeline/shader mismatch!"),
@@ -103,6 +142,7 @@ impl PipelineDesc {
         let rasterization_state = wgpu::RasterizationStateDescriptor {
             front_face: self.front_face,
             cull_mode: self.cull_mode,
+            polygon_mode: self.polygon_mode,
             depth_bias: self.depth_bias,
             depth_bias_slope_scale: self.depth_bias_slope_scale.into(),
             depth_bias_clamp: self.depth_bias_clamp.into(),
@@ -112,6 +152,9 @@ impl PipelineDesc {
         let color_states = self.color_states.clone();
         let depth_stencil_state = self.depth_state.clone();
         let vertex_state_builder = self.vertex_state.clone();
+        if let Err(error) = vertex_state_builder.validate_strides() {
+            panic!("Invalid vertex layout for {}: {:?}", self.shader, error);
+        }
         let sample_count = self.sample_count;
         let sample_mask = self.sampler_mask;
         let alpha_to_coverage_enabled = self.alpha_to_coverage_enabled;
@@ -190,8 +233,21 @@ impl ComputePipelineDesc {
         device: &wgpu::Device,
         gpu_resource_manager: &GPUResourceManager,
     ) -> ComputePipeline {
-        let shader_handle = asset_manager.get_shader(self.shader.clone());
+        let shader_handle = self.shader_handle(asset_manager);
         let shader = futures::executor::block_on(shader_handle.get_async()).unwrap();
+        self.build_with_shader(&shader, device, gpu_resource_manager)
+    }
+
+    fn shader_handle(&self, asset_manager: &AssetManager) -> Arc<AssetHandle<Shader>> {
+        asset_manager.get_shader(self.shader.clone())
+    }
+
+    fn build_with_shader(
+        &self,
+        shader: &Shader,
+        device: &wgpu::Device,
+        gpu_resource_manager: &GPUResourceManager,
+    ) -> ComputePipeline {
         let shader = match *shader {
             Shader::Compute(ref shader) => shader,
             _ => panic!("Pipeline/shader mismatch!"),
@@ -246,40 +302,195 @@ pub struct ComputePipeline {
     pub compute_pipeline: wgpu::ComputePipeline,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineError {
+    /// Thrown when there is no pipeline with the name.
+    NotFound(String),
+    /// Thrown when the name belongs to a node, which has no shader to replace.
+    NotAPipeline(String),
+    /// Thrown when a compute shader is given for a render pipeline or the other way around.
+    ShaderMismatch(String),
+    /// Thrown when a precompiled pipeline couldn't be built, for example because its shader failed to load.
+    BuildFailed(String),
+}
+
 /// The type of pipeline.
 pub enum PipelineType {
     Pipeline(Pipeline),
     ComputePipeline(ComputePipeline),
     /// Node is used for things that run on the GPU without a pipeline. Such as globals.
     Node,
+    /// A pipeline from `PipelineManager::precompile_all` that is still being built on the thread pool.
+    /// The lock holds the shader's error instead if the pipeline couldn't be built.
+    Compiling(Arc<OnceLock<Result<PipelineType, Arc<AssetError>>>>),
+    /// A pipeline from `PipelineManager::get_or_insert_with` waiting for its shader to load.
+    /// It's built by the next call to `get_or_insert_with` once the shader is ready, `get` returns `None` until then.
+    Pending(Box<dyn PipelineDescErased>, Arc<AssetHandle<Shader>>),
     // TODO: Add group type.
 }
 
+impl PipelineType {
+    /// Blocks until a compiling pipeline has finished, otherwise returns itself.
+    /// Returns `None` if the pipeline failed to build.
+    fn wait(&self) -> Option<&PipelineType> {
+        match self {
+            PipelineType::Compiling(pipeline) => pipeline.wait().as_ref().ok(),
+            PipelineType::Pending(..) => None,
+            _ => Some(self),
+        }
+    }
+
+    /// True once the pipeline has been built successfully.
+    fn is_ready(&self) -> bool {
+        match self {
+            PipelineType::Compiling(pipeline) => matches!(pipeline.get(), Some(Ok(_))),
+            PipelineType::Pending(..) => false,
+            _ => true,
+        }
+    }
+
+    fn is_compiling(&self) -> bool {
+        match self {
+            PipelineType::Compiling(pipeline) => pipeline.get().is_none(),
+            PipelineType::Pending(..) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A pipeline or compute pipeline description that can be built without knowing which one it is.
+/// Used to hand a mixed list of pipelines to `PipelineManager::precompile_all`.
+pub trait PipelineDescErased: Send + Sync {
+    fn create_hash(&self) -> u64;
+    fn shader_handle(&self, asset_manager: &AssetManager) -> Arc<AssetHandle<Shader>>;
+    fn build_erased(
+        &self,
+        shader: &Shader,
+        device: &wgpu::Device,
+        gpu_resource_manager: &GPUResourceManager,
+    ) -> PipelineType;
+}
+
+impl PipelineDescErased for PipelineDesc {
+    fn create_hash(&self) -> u64 {
+        PipelineDesc::create_hash(self)
+    }
+
+    fn shader_handle(&self, asset_manager: &AssetManager) -> Arc<AssetHandle<Shader>> {
+        PipelineDesc::shader_handle(self, asset_manager)
+    }
+
+    fn build_erased(
+        &self,
+        shader: &Shader,
+        device: &wgpu::Device,
+        gpu_resource_manager: &GPUResourceManager,
+    ) -> PipelineType {
+        PipelineType::Pipeline(self.build_with_shader(shader, device, gpu_resource_manager))
+    }
+}
+
+impl PipelineDescErased for ComputePipelineDesc {
+    fn create_hash(&self) -> u64 {
+        ComputePipelineDesc::create_hash(self)
+    }
+
+    fn shader_handle(&self, asset_manager: &AssetManager) -> Arc<AssetHandle<Shader>> {
+        ComputePipelineDesc::shader_handle(self, asset_manager)
+    }
+
+    fn build_erased(
+        &self,
+        shader: &Shader,
+        device: &wgpu::Device,
+        gpu_resource_manager: &GPUResourceManager,
+    ) -> PipelineType {
+        PipelineType::ComputePipeline(self.build_with_shader(shader, device, gpu_resource_manager))
+    }
+}
+
+/// Records compute work for a node added with `PipelineManager::add_compute_node`.
+/// Compute nodes are ordered by their dependencies just like pipelines and nodes.
+pub trait ComputeNodeDesc: Send + Sync + 'static {
+    fn dispatch(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        resource_manager: &GPUResourceManager,
+        world: &mut legion::world::World,
+    );
+
+    /// The priority the node's command buffer is submitted with, see `RenderPriority`.
+    fn priority(&self) -> i32 {
+        RenderPriority::OPAQUE
+    }
+
+    /// Return `Some(self)` if the node implements `ResizeAware`.
+    fn as_resize_aware(&mut self) -> Option<&mut dyn ResizeAware> {
+        None
+    }
+}
+
 /// This is essentially a render graph with additional features.
 /// It can also manage duplicate pipelines.
 pub struct PipelineManager {
     pipelines: HashMap<String, HashMap<u64, PipelineType>>,
+    compute_nodes: HashMap<String, Box<dyn ComputeNodeDesc>>,
     pub(crate) current_pipelines: HashMap<String, u64>,
-    dep_graph: DepGraph<String>,
+    // Node names in the order they were added, used to keep ordering stable for nodes without dependencies.
+    nodes: Vec<String>,
+    // (from, to) where `from` must execute before `to`.
+    edges: Vec<(String, String)>,
+    accesses: HashMap<String, ResourceAccess>,
     order: Vec<String>,
+    disabled: HashSet<String>,
+    pool: Arc<ThreadPool>,
+    cache: Option<Arc<PipelineCache>>,
 }
 
 impl PipelineManager {
     /// Creates a new pipeline manager.
     pub fn new() -> Self {
-        let mut dep_graph = DepGraph::new();
-        dep_graph.register_node("root".to_string());
         Self {
             pipelines: HashMap::new(),
-            dep_graph,
+            compute_nodes: HashMap::new(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            accesses: HashMap::new(),
             order: Vec::new(),
+            disabled: HashSet::new(),
             current_pipelines: HashMap::new(),
+            pool: Arc::new(ThreadPoolBuilder::new().pool_size(4).create().unwrap()),
+            cache: None,
+        }
+    }
+
+    /// Uses `cache` for the shaders of pipelines added from now on, so stages compiled by an earlier run are reused.
+    /// Entries compiled for another device are cleared first, see `PipelineCache::invalidate`.
+    /// Call `save_cache` once the pipelines are created to write new entries to disk.
+    pub fn set_cache(
+        &mut self,
+        cache: PipelineCache,
+        device: &wgpu::Device,
+        asset_manager: &AssetManager,
+    ) {
+        cache.invalidate(device);
+        let cache = Arc::new(cache);
+        asset_manager.set_pipeline_cache(cache.clone());
+        self.cache = Some(cache);
+    }
+
+    /// Writes the cache set with `set_cache` to disk, does nothing without one.
+    pub fn save_cache(&self) -> std::io::Result<()> {
+        match &self.cache {
+            Some(cache) => cache.save(),
+            None => Ok(()),
         }
     }
 
     /// This lets you add new pipelines. Note: You can have multiple pipelines for the same shader. It's recommended that you store
     /// PipelineDesc and pass it in when retrieving the pipeline.
     /// Note: Pipeline's are considered a fairly costly operation, try not to create a new one every frame.
+    /// Returns `RenderGraphError::Cycle` if the dependencies would form a cycle, nothing is added in that case.
     pub fn add_pipeline<T: Into<String>>(
         &mut self,
         name: T,
@@ -288,10 +499,18 @@ impl PipelineManager {
         device: &wgpu::Device,
         asset_manager: &AssetManager,
         gpu_resource_manager: Arc<GPUResourceManager>, // TODO: This is an arc so just throw it in via new
-    ) {
+    ) -> Result<(), RenderGraphError> {
         let hash = pipeline_desc.create_hash();
         let name = name.into();
 
+        if self.contains(&name, hash) {
+            // Already exists do nothing in this case.
+            return Ok(());
+        }
+
+        // Add to our graph first so nothing is built if the dependencies form a cycle.
+        self.add_dependencies(&name, &dependency)?;
+
         if !self.pipelines.contains_key(&name) {
             let pipeline_hashmap = HashMap::new();
             self.pipelines.insert(name.clone(), pipeline_hashmap);
@@ -301,33 +520,17 @@ impl PipelineManager {
         }
 
         let pipeline_hashmap = self.pipelines.get_mut(&name).unwrap();
-        if pipeline_hashmap.contains_key(&hash) {
-            // Already exists do nothing in this case.
-            return;
-        }
-
         let pipeline = pipeline_desc.build(&asset_manager, &device, &gpu_resource_manager);
         pipeline_hashmap.insert(hash, PipelineType::Pipeline(pipeline));
 
-        // Add to our graph
-        self.dep_graph.register_node(name.clone());
-
-        if dependency.len() > 0 {
-            let dependency = dependency
-                .iter()
-                .map(|name| name.to_string())
-                .collect::<Vec<String>>();
-            self.dep_graph
-                .register_dependencies(name.clone(), dependency);
-        }
-
         // Recalculate order.
-        self.get_order();
+        self.get_order()
     }
 
     /// This lets you add new compute pipelines. Note: You can have multiple pipelines for the same shader. It's recommended that you store
     /// PipelineDesc and pass it in when retrieving the pipeline.
     /// Note: Pipeline's are considered a fairly costly operation, try not to create a new one every frame.
+    /// Returns `RenderGraphError::Cycle` if the dependencies would form a cycle, nothing is added in that case.
     pub fn add_compute_pipeline<T: Into<String>>(
         &mut self,
         name: T,
@@ -336,10 +539,18 @@ impl PipelineManager {
         device: &wgpu::Device,
         asset_manager: &AssetManager,
         gpu_resource_manager: Arc<GPUResourceManager>,
-    ) {
+    ) -> Result<(), RenderGraphError> {
         let hash = pipeline_desc.create_hash();
         let name = name.into();
 
+        if self.contains(&name, hash) {
+            // Already exists do nothing in this case.
+            return Ok(());
+        }
+
+        // Add to our graph first so nothing is built if the dependencies form a cycle.
+        self.add_dependencies(&name, &dependency)?;
+
         if !self.pipelines.contains_key(&name) {
             let pipeline_hashmap = HashMap::new();
             self.pipelines.insert(name.clone(), pipeline_hashmap);
@@ -349,38 +560,214 @@ impl PipelineManager {
         }
 
         let pipeline_hashmap = self.pipelines.get_mut(&name).unwrap();
-        if pipeline_hashmap.contains_key(&hash) {
-            // Already exists do nothing in this case.
-            return;
-        }
-
         let pipeline = pipeline_desc.build(&asset_manager, &device, &gpu_resource_manager);
         pipeline_hashmap.insert(hash, PipelineType::ComputePipeline(pipeline));
 
-        // Add to our graph
-        self.dep_graph.register_node(name.clone());
+        // Recalculate order.
+        self.get_order()
+    }
 
-        if dependency.len() > 0 {
-            let dependency = dependency
-                .iter()
-                .map(|name| name.to_string())
-                .collect::<Vec<String>>();
-            self.dep_graph
-                .register_dependencies(name.clone(), dependency);
+
+    /// Builds every pipeline on the thread pool so they're ready before they're first used.
+    /// Each entry is the pipeline's name, its description and the pipelines it depends on, the same as `add_pipeline`.
+    /// Note: `get` blocks until a pipeline has finished compiling, use `is_ready` or `all_ready` to avoid that.
+    /// If a pipeline's shader fails to load the error is logged and the pipeline is never ready.
+    /// Render systems skip their passes until the pipelines they use are ready.
+    /// Returns `RenderGraphError::Cycle` if the dependencies would form a cycle, none of the pipelines are added in that case.
+    pub fn precompile_all(
+        &mut self,
+        device: Arc<wgpu::Device>,
+        asset_manager: &AssetManager,
+        gpu_resource_manager: Arc<GPUResourceManager>,
+        descs: Vec<(&str, Box<dyn PipelineDescErased>, Vec<&str>)>,
+    ) -> Result<(), RenderGraphError> {
+        // Every dependency is added first so nothing is compiled if they form a cycle.
+        let (nodes, edges) = (self.nodes.clone(), self.edges.clone());
+        for (name, _, dependency) in descs.iter() {
+            if let Err(error) = self.add_dependencies(name, dependency) {
+                self.nodes = nodes;
+                self.edges = edges;
+                return Err(error);
+            }
+        }
+
+        for (name, pipeline_desc, _) in descs {
+            let hash = pipeline_desc.create_hash();
+            let name = name.to_string();
+
+            if !self.pipelines.contains_key(&name) {
+                self.pipelines.insert(name.clone(), HashMap::new());
+                self.current_pipelines.insert(name.clone(), hash);
+            }
+
+            let pipeline_hashmap = self.pipelines.get_mut(&name).unwrap();
+            if pipeline_hashmap.contains_key(&hash) {
+                continue;
+            }
+
+            // Shaders are loaded on the asset manager's own pool, so only the pipeline itself is built here.
+            let shader_handle = pipeline_desc.shader_handle(asset_manager);
+            let pipeline = Arc::new(OnceLock::new());
+            pipeline_hashmap.insert(hash, PipelineType::Compiling(pipeline.clone()));
+
+            let device = device.clone();
+            let gpu_resource_manager = gpu_resource_manager.clone();
+            self.pool.spawn_ok(async move {
+                let result = shader_handle.get_async().await.map(|shader| {
+                    pipeline_desc.build_erased(&shader, &device, &gpu_resource_manager)
+                });
+                if let Err(error) = &result {
+                    log::error!("Couldn't build pipeline {:?}: {:?}", shader_handle.handle_id, error);
+                }
+                let _ = pipeline.set(result);
+            });
         }
 
         // Recalculate order.
-        self.get_order();
+        self.get_order()
+    }
+
+    /// Gets a pipeline, registering it with the description from `f` the first time it's asked for.
+    /// Useful for pipelines that aren't known before the game starts, for example materials from downloaded content.
+    /// The pipeline is built on the calling thread once its shader has loaded, until then this returns `None` instead of blocking.
+    /// `f` is only called the first time. Returns `None` if the shader failed to load or the dependencies would form a cycle,
+    /// the error is logged.
+    pub fn get_or_insert_with<F>(
+        &mut self,
+        name: &str,
+        dependency: Vec<&str>,
+        device: &wgpu::Device,
+        asset_manager: &AssetManager,
+        gpu_resource_manager: &GPUResourceManager,
+        f: F,
+    ) -> Option<&Pipeline>
+    where
+        F: FnOnce() -> Box<dyn PipelineDescErased>,
+    {
+        if !self.pipelines.contains_key(name) {
+            if let Err(error) = self.add_dependencies(name, &dependency) {
+                log::error!("Couldn't add pipeline {}: {:?}", name, error);
+                return None;
+            }
+
+            let pipeline_desc = f();
+            let hash = pipeline_desc.create_hash();
+            let shader_handle = pipeline_desc.shader_handle(asset_manager);
+            let mut pipeline_hashmap = HashMap::new();
+            pipeline_hashmap.insert(hash, PipelineType::Pending(pipeline_desc, shader_handle));
+            self.pipelines.insert(name.to_string(), pipeline_hashmap);
+            self.current_pipelines.insert(name.to_string(), hash);
+
+            // Recalculate order.
+            self.get_order().ok()?;
+        }
+
+        let hash = *self.current_pipelines.get(name)?;
+        let pipeline_type = self.pipelines.get_mut(name)?.get_mut(&hash)?;
+        if let PipelineType::Pending(pipeline_desc, shader_handle) = pipeline_type {
+            if !shader_handle.is_resolved() {
+                return None;
+            }
+            *pipeline_type = match shader_handle.get() {
+                Ok(shader) => pipeline_desc.build_erased(&shader, device, gpu_resource_manager),
+                Err(error) => {
+                    log::error!("Couldn't build pipeline {}: {:?}", name, error);
+                    PipelineType::Compiling(Arc::new(OnceLock::from(Err(error))))
+                }
+            };
+        }
+
+        match pipeline_type.wait()? {
+            PipelineType::Pipeline(pipeline) => Some(pipeline),
+            _ => None,
+        }
+    }
+
+    /// Rebuilds the current pipeline for `name` with a new shader, keeping the same layouts and state.
+    /// The rebuilt pipeline is stored under the same hash so `get` with the original description still finds it.
+    /// This needs the manager mutably so nothing can still be borrowing the old pipeline, command buffers
+    /// that were already recorded with it stay valid until the GPU is done with them.
+    /// Other variants of the pipeline keep their old shader, use `set_current_pipeline_hash` to reload those.
+    pub fn reload_shader(
+        &mut self,
+        name: &str,
+        shader: &Shader,
+        device: &wgpu::Device,
+        gpu_resource_manager: &GPUResourceManager,
+    ) -> Result<(), PipelineError> {
+        let not_found = || PipelineError::NotFound(name.to_string());
+        let hash = *self.current_pipelines.get(name).ok_or_else(not_found)?;
+        let pipeline_type = self
+            .pipelines
+            .get_mut(name)
+            .and_then(|pipeline_hashmap| pipeline_hashmap.get_mut(&hash))
+            .ok_or_else(not_found)?;
+
+        let pipeline_type_ready = pipeline_type
+            .wait()
+            .ok_or_else(|| PipelineError::BuildFailed(name.to_string()))?;
+        let pipeline = match (pipeline_type_ready, shader) {
+            (PipelineType::Pipeline(pipeline), Shader::Core(_)) => PipelineType::Pipeline(
+                pipeline
+                    .desc
+                    .build_with_shader(shader, device, gpu_resource_manager),
+            ),
+            (PipelineType::ComputePipeline(pipeline), Shader::Compute(_)) => {
+                PipelineType::ComputePipeline(pipeline.desc.build_with_shader(
+                    shader,
+                    device,
+                    gpu_resource_manager,
+                ))
+            }
+            (PipelineType::Node, _) => return Err(PipelineError::NotAPipeline(name.to_string())),
+            _ => return Err(PipelineError::ShaderMismatch(name.to_string())),
+        };
+        *pipeline_type = pipeline;
+
+        Ok(())
+    }
+
+    /// Returns true once the current pipeline for `name` has finished compiling.
+    /// Pipelines that don't exist or failed to build are never ready.
+    pub fn is_ready(&self, name: &str) -> bool {
+        let hash = match self.current_pipelines.get(name) {
+            Some(hash) => hash,
+            None => return false,
+        };
+        self.pipelines
+            .get(name)
+            .and_then(|pipeline_hashmap| pipeline_hashmap.get(hash))
+            .map_or(false, |pipeline_type| pipeline_type.is_ready())
     }
 
+    /// Returns true when no pipelines are still compiling.
+    pub fn all_ready(&self) -> bool {
+        self.pipelines
+            .values()
+            .flat_map(|pipeline_hashmap| pipeline_hashmap.values())
+            .all(|pipeline_type| !pipeline_type.is_compiling())
+    }
 
     /// A node is an encoder you want to run at some step inside of the pipeline workflow.
-    pub fn add_node<T: Into<String>>(&mut self, name: T, dependency: Vec<&str>) {
+    /// Returns `RenderGraphError::Cycle` if the dependencies would form a cycle, the node isn't added in that case.
+    pub fn add_node<T: Into<String>>(
+        &mut self,
+        name: T,
+        dependency: Vec<&str>,
+    ) -> Result<(), RenderGraphError> {
         let name = name.into();
         let mut hasher = DefaultHasher::new();
         name.hash(&mut hasher);
         let hash = hasher.finish();
 
+        if self.contains(&name, hash) {
+            // Already exists do nothing in this case. Perhaps error?
+            return Ok(());
+        }
+
+        // Add to our graph
+        self.add_dependencies(&name, &dependency)?;
+
         if !self.pipelines.contains_key(&name) {
             let pipeline_hashmap = HashMap::new();
             self.pipelines.insert(name.clone(), pipeline_hashmap);
@@ -389,54 +776,159 @@ impl PipelineManager {
             self.current_pipelines.insert(name.clone(), hash);
         }
 
-        let pipeline_hashmap = self.pipelines.get_mut(&name).unwrap();
-        if pipeline_hashmap.contains_key(&hash) {
-            // Already exists do nothing in this case. Perhaps error?
-            return;
+        // Recalculate order.
+        self.get_order()
+    }
+
+    /// Adds a node that records compute work with `ComputeNodeDesc::dispatch` instead of a render system.
+    /// It's recorded into its own command buffer right before the frame is submitted,
+    /// and submitted in dependency order with the other nodes that have the same priority.
+    /// Returns `RenderGraphError::Cycle` if the dependencies would form a cycle, the node isn't added in that case.
+    pub fn add_compute_node<T: Into<String>>(
+        &mut self,
+        name: T,
+        desc: Box<dyn ComputeNodeDesc>,
+        dependency: Vec<&str>,
+    ) -> Result<(), RenderGraphError> {
+        let name = name.into();
+        self.add_node(name.clone(), dependency)?;
+        self.compute_nodes.insert(name, desc);
+        Ok(())
+    }
+
+    /// Removes a pipeline or node and every variant of it so it no longer runs.
+    /// Note: The dependency graph keeps the node so anything that depends on it still orders itself after it.
+    pub fn remove_pipeline<T: Into<String>>(&mut self, name: T) {
+        let name = name.into();
+        self.pipelines.remove(&name);
+        self.compute_nodes.remove(&name);
+        self.current_pipelines.remove(&name);
+
+        // Recalculate order.
+        self.get_order()
+            .expect("Removing a pipeline can't form a cycle");
+    }
+
+    /// Stops a node's command buffers from being submitted until it's enabled again.
+    /// The render systems check `is_enabled` and skip recording a disabled node's pass altogether.
+    /// Note: The node stays in the dependency graph so the order of everything else doesn't change.
+    /// Textures written by a disabled node keep whatever they held when it last ran.
+    pub fn disable_node<T: Into<String>>(&mut self, name: T) {
+        self.disabled.insert(name.into());
+    }
+
+    /// Re-enables a node that was turned off with `disable_node`.
+    pub fn enable_node<T: Into<String>>(&mut self, name: T) {
+        self.disabled.remove(&name.into());
+    }
+
+    /// Returns false if the node was turned off with `disable_node`.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        !self.disabled.contains(name)
+    }
+
+    /// Declares the textures and buffers a node reads and writes by name.
+    /// A node reading a resource written by a node it isn't ordered with runs after the writer,
+    /// wgpu then transitions the resource between the passes.
+    /// Nodes that already depend on the writer keep their order, so they can still read last frame's contents.
+    /// Returns `RenderGraphError::WriteConflict` if two nodes write the same resource without a dependency between them
+    /// and `RenderGraphError::Cycle` if the new ordering forms a cycle, the declaration isn't kept in either case.
+    pub fn declare_access<T: Into<String>>(
+        &mut self,
+        name: T,
+        reads: Vec<&str>,
+        writes: Vec<&str>,
+    ) -> Result<(), RenderGraphError> {
+        let name = name.into();
+        if !self.nodes.contains(&name) {
+            self.nodes.push(name.clone());
         }
 
-        // Add to our graph
-        self.dep_graph.register_node(name.clone());
+        let previous = self
+            .accesses
+            .insert(name.clone(), ResourceAccess::new(reads, writes));
+        if let Err(error) = self.get_order() {
+            match previous {
+                Some(access) => self.accesses.insert(name, access),
+                None => self.accesses.remove(&name),
+            };
+            return Err(error);
+        }
+        Ok(())
+    }
 
-        if dependency.len() > 0 {
-            let dependency = dependency
-                .iter()
-                .map(|name| name.to_string())
-                .collect::<Vec<String>>();
-            self.dep_graph
-                .register_dependencies(name.clone(), dependency);
+    fn contains(&self, name: &str, hash: u64) -> bool {
+        self.pipelines.get(name).map_or(false, |pipeline_hashmap| {
+            pipeline_hashmap.contains_key(&hash)
+        })
+    }
+
+    // Adds `name` to the graph after its dependencies, dependencies that weren't added yet are registered as nodes too.
+    // The graph is left unchanged if the new dependencies would form a cycle.
+    fn add_dependencies(
+        &mut self,
+        name: &str,
+        dependency: &[&str],
+    ) -> Result<(), RenderGraphError> {
+        let (nodes, edges) = (self.nodes.clone(), self.edges.clone());
+        for node in std::iter::once(&name).chain(dependency.iter()) {
+            if !self.nodes.iter().any(|other| other == node) {
+                self.nodes.push(node.to_string());
+            }
+        }
+        for dependency in dependency {
+            let edge = (dependency.to_string(), name.to_string());
+            if !self.edges.contains(&edge) {
+                self.edges.push(edge);
+            }
         }
 
-        // Recalculate order.
-        self.get_order();
-    }
-
-    fn get_order(&mut self) {
-        let mut order = Vec::new();
-        for (name, _) in self.pipelines.iter() {
-            let dependencies = self.dep_graph.dependencies_of(&name);
-            if dependencies.is_ok() {
-                for node in dependencies.unwrap() {
-                    match node {
-                        Ok(n) => {
-                            if !order.contains(n) {
-                                order.push(n.clone());
-                            }
-                        }
-                        Err(e) => panic!("Solvent error detected: {:?}", e),
-                    }
+        if let Err(error) = self.sort() {
+            self.nodes = nodes;
+            self.edges = edges;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    // Sorts the graph with Kahn's algorithm, nodes without dependencies keep the order they were added in.
+    // Readers of a resource are ordered after its writers, see `declare_access`.
+    fn sort(&self) -> Result<Vec<String>, RenderGraphError> {
+        let mut edges = self.edges.clone();
+        edges.extend(resolve_hazards(&self.nodes, &self.edges, &self.accesses)?);
+        topological_sort(&self.nodes, &edges)
+    }
+
+    // Only pipelines and the nodes they depend on are kept, so removed pipelines stop running.
+    fn get_order(&mut self) -> Result<(), RenderGraphError> {
+        let mut order = self.sort()?;
+
+        let mut required: Vec<&String> = self
+            .nodes
+            .iter()
+            .filter(|name| self.pipelines.contains_key(*name))
+            .collect();
+        let mut index = 0;
+        while index < required.len() {
+            for (from, _) in self.edges.iter().filter(|(_, to)| to == required[index]) {
+                if !required.contains(&from) {
+                    required.push(from);
                 }
             }
+            index += 1;
         }
+        order.retain(|name| required.contains(&name));
 
         // UI always comes last.
         order.push("UI".to_string());
 
         self.order = order;
+        Ok(())
     }
 
     /// Let's you retrieve a reference to a pipeline from the manager.
     /// Note if you don't pass in a pipeline description it defaults to whatever the current pipeline is.
+    /// If the pipeline is still being precompiled this blocks until it's finished, if it failed to build this returns `None`.
     pub fn get<T: Into<String>>(
         &self,
         name: T,
@@ -458,7 +950,31 @@ impl PipelineManager {
         if pipeline_type.is_none() {
             return None;
         }
-        match pipeline_type.as_ref().unwrap() {
+        match pipeline_type.unwrap().wait()? {
+            PipelineType::Pipeline(pipeline) => Some(pipeline),
+            _ => None,
+        }
+    }
+
+    /// Gets a variant of the current pipeline for `name` that renders into `formats`, for example HDR render targets.
+    /// The variant is built the first time it's asked for and stored next to the current pipeline, which stays current.
+    pub fn get_variant(
+        &mut self,
+        name: &str,
+        formats: &[wgpu::TextureFormat],
+        device: &wgpu::Device,
+        asset_manager: &AssetManager,
+        gpu_resource_manager: &GPUResourceManager,
+    ) -> Option<&Pipeline> {
+        let desc = self.get(name, None)?.desc.with_color_formats(formats);
+        let hash = desc.create_hash();
+        let pipeline_hashmap = self.pipelines.get_mut(name)?;
+        if !pipeline_hashmap.contains_key(&hash) {
+            let pipeline = desc.build(asset_manager, device, gpu_resource_manager);
+            pipeline_hashmap.insert(hash, PipelineType::Pipeline(pipeline));
+        }
+
+        match pipeline_hashmap.get(&hash)?.wait()? {
             PipelineType::Pipeline(pipeline) => Some(pipeline),
             _ => None,
         }
@@ -466,6 +982,7 @@ impl PipelineManager {
 
     /// Let's you retrieve a reference to a pipeline from the manager.
     /// Note if you don't pass in a pipeline description it defaults to whatever the current pipeline is.
+    /// If the pipeline is still being precompiled this blocks until it's finished, if it failed to build this returns `None`.
     pub fn get_compute<T: Into<String>>(
         &self,
         name: T,
@@ -487,7 +1004,7 @@ impl PipelineManager {
         if pipeline_type.is_none() {
             return None;
         }
-        match pipeline_type.as_ref().unwrap() {
+        match pipeline_type.unwrap().wait()? {
             PipelineType::ComputePipeline(pipeline) => Some(pipeline),
             _ => None,
         }
@@ -505,27 +1022,702 @@ impl PipelineManager {
         self.current_pipelines.insert(name, hash);
     }
 
-    /// Collects command buffers for submission.
+    /// Calls `ResizeAware::on_resize` on every compute node that implements it.
+    /// Called after the swap chain is recreated for the new window size.
+    pub fn notify_resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        for compute_node in self.compute_nodes.values_mut() {
+            if let Some(compute_node) = compute_node.as_resize_aware() {
+                compute_node.on_resize(device, width, height);
+            }
+        }
+    }
+
+    // Records every enabled compute node into its own command buffer and queues it with the render systems' work.
+    pub(crate) fn record_compute_nodes(
+        &mut self,
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        world: &mut legion::world::World,
+        command_queue: &CommandBufferQueue,
+    ) {
+        for (name, compute_node) in self.compute_nodes.iter_mut() {
+            if self.disabled.contains(name) {
+                continue;
+            }
+
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some(name.as_str()),
+            });
+            compute_node.dispatch(&mut encoder, resource_manager, world);
+            command_queue
+                .push(CommandQueueItem {
+                    name: name.clone(),
+                    buffer: encoder.finish(),
+                    priority: compute_node.priority(),
+                })
+                .unwrap();
+        }
+    }
+
+    /// Collects command buffers for submission, ordered by `RenderPriority` and then by the dependency graph.
+    /// When a profiler is passed in each node's command buffers are wrapped in a timestamp scope.
     pub(crate) fn collect_buffers(
         &self,
         command_queue: &mut CommandBufferQueue,
+        mut profiler: Option<(&wgpu::Device, &mut GpuProfiler)>,
     ) -> Vec<wgpu::CommandBuffer> {
         let mut command_buffers = Vec::new();
-        let mut queue_items = Vec::new();
-        while let Ok(command) = command_queue.pop() {
-            queue_items.push(command);
+
+        for (order, node_buffers) in self.collect_nodes(command_queue) {
+            match profiler.as_mut() {
+                Some((device, profiler)) => {
+                    let mut begin_encoder =
+                        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("gpu_profiler_begin"),
+                        });
+                    profiler.begin_scope(order, &mut begin_encoder);
+                    command_buffers.push(begin_encoder.finish());
+
+                    command_buffers.extend(node_buffers);
+
+                    let mut end_encoder =
+                        device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("gpu_profiler_end"),
+                        });
+                    profiler.end_scope(&mut end_encoder);
+                    command_buffers.push(end_encoder.finish());
+                }
+                None => command_buffers.extend(node_buffers),
+            }
         }
 
-        for order in self.order.iter() {
-            while let Some(queue_item_index) = queue_items
-                .iter()
-                .position(|queue_item| &queue_item.name == order)
+        command_buffers
+    }
+
+    // Drains the queue into the command buffers of each node in the order `collect_buffers` submits them.
+    fn collect_nodes(
+        &self,
+        command_queue: &mut CommandBufferQueue,
+    ) -> Vec<(&str, Vec<wgpu::CommandBuffer>)> {
+        // Sorted by priority and then by node order, items for nodes that aren't in the graph or are disabled are dropped.
+        let mut queue_items = command_queue
+            .drain()
+            .into_iter()
+            .filter(|queue_item| self.is_enabled(&queue_item.name))
+            .filter_map(|queue_item| {
+                let index = self.order.iter().position(|order| order == &queue_item.name)?;
+                Some((queue_item.priority, index, queue_item))
+            })
+            .collect::<Vec<_>>();
+        queue_items.sort_by_key(|(priority, index, _)| (*priority, *index));
+
+        let mut nodes = Vec::new();
+        let mut queue_items = queue_items.into_iter().peekable();
+        while let Some((priority, index, queue_item)) = queue_items.next() {
+            let mut node_buffers = vec![queue_item.buffer];
+            while let Some((_, _, queue_item)) =
+                queue_items.next_if(|(p, i, _)| *p == priority && *i == index)
             {
-                let queue_item = queue_items.remove(queue_item_index);
-                command_buffers.push(queue_item.buffer);
+                node_buffers.push(queue_item.buffer);
             }
+            nodes.push((self.order[index].as_str(), node_buffers));
         }
 
-        command_buffers
+        nodes
+    }
+}
+
+// The resources a node declared with `declare_access`.
+#[derive(Debug, Default, Clone)]
+struct ResourceAccess {
+    reads: Vec<String>,
+    writes: Vec<String>,
+}
+
+impl ResourceAccess {
+    fn new(reads: Vec<&str>, writes: Vec<&str>) -> Self {
+        Self {
+            reads: reads.into_iter().map(|name| name.to_string()).collect(),
+            writes: writes.into_iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+// Returns the edges needed so every reader of a resource runs after the nodes writing it.
+// Readers already ordered with a writer are left alone, so reading last frame's contents still works.
+fn resolve_hazards(
+    nodes: &[String],
+    edges: &[(String, String)],
+    accesses: &HashMap<String, ResourceAccess>,
+) -> Result<Vec<(String, String)>, RenderGraphError> {
+    let reads = |name: &String| {
+        accesses
+            .get(name)
+            .map_or(&[] as &[String], |access| &access.reads[..])
+    };
+    let writes = |name: &String| {
+        accesses
+            .get(name)
+            .map_or(&[] as &[String], |access| &access.writes[..])
+    };
+    let ordered = |a: &String, b: &String| depends_on(edges, a, b) || depends_on(edges, b, a);
+
+    for (index, node_a) in nodes.iter().enumerate() {
+        for node_b in nodes[index + 1..].iter() {
+            let conflict = writes(node_a)
+                .iter()
+                .find(|resource| writes(node_b).contains(*resource));
+            if let Some(resource) = conflict {
+                if !ordered(node_a, node_b) {
+                    return Err(RenderGraphError::WriteConflict {
+                        node_a: node_a.clone(),
+                        node_b: node_b.clone(),
+                        resource: resource.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let mut hazard_edges = Vec::new();
+    for writer in nodes.iter() {
+        for reader in nodes.iter().filter(|reader| *reader != writer) {
+            let reads_written = writes(writer)
+                .iter()
+                .any(|resource| reads(reader).contains(resource));
+            if reads_written && !ordered(writer, reader) {
+                hazard_edges.push((writer.clone(), reader.clone()));
+            }
+        }
+    }
+    Ok(hazard_edges)
+}
+
+// Whether `to` can only execute after `from`.
+fn depends_on(edges: &[(String, String)], from: &String, to: &String) -> bool {
+    let mut stack = vec![from];
+    let mut visited = Vec::new();
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+        if visited.contains(&node) {
+            continue;
+        }
+        visited.push(node);
+        stack.extend(
+            edges
+                .iter()
+                .filter(|(edge_from, _)| edge_from == node)
+                .map(|(_, edge_to)| edge_to),
+        );
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComputeNodeDesc, PipelineDesc, PipelineDescErased, PipelineManager, PipelineType};
+    use crate::{
+        assets::{
+            shader::{CoreShader, Shader},
+            AssetCache, AssetError, AssetHandle,
+        },
+        graphics::{
+            resources::GPUResourceManager,
+            shadows::{CascadeShadowManager, OmniShadowManager, ShadowQuality},
+            CommandBufferQueue, CommandQueueItem, RenderGraphError, RenderPriority, ResizeAware,
+        },
+        AssetManager,
+    };
+    use legion::prelude::Universe;
+    use std::{
+        borrow::Cow,
+        collections::HashMap,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex, OnceLock,
+        },
+        thread::{self, ThreadId},
+    };
+
+    fn create_device() -> wgpu::Device {
+        create_device_and_queue().0
+    }
+
+    fn create_device_and_queue() -> (wgpu::Device, wgpu::Queue) {
+        async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+            adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap()
+        })
+    }
+
+    // An empty command buffer standing in for a node's recorded work.
+    fn queue_item(device: &wgpu::Device, name: &str, priority: i32) -> CommandQueueItem {
+        let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some(name),
+        });
+        CommandQueueItem {
+            buffer: encoder.finish(),
+            name: name.to_string(),
+            priority,
+        }
+    }
+
+    struct EmptyComputeNode(i32);
+
+    impl ComputeNodeDesc for EmptyComputeNode {
+        fn dispatch(
+            &mut self,
+            _encoder: &mut wgpu::CommandEncoder,
+            _resource_manager: &GPUResourceManager,
+            _world: &mut legion::world::World,
+        ) {
+        }
+
+        fn priority(&self) -> i32 {
+            self.0
+        }
+    }
+
+    // Stores the last size it was resized to.
+    struct ResizeComputeNode(Arc<Mutex<(u32, u32)>>);
+
+    impl ComputeNodeDesc for ResizeComputeNode {
+        fn dispatch(
+            &mut self,
+            _encoder: &mut wgpu::CommandEncoder,
+            _resource_manager: &GPUResourceManager,
+            _world: &mut legion::world::World,
+        ) {
+        }
+
+        fn as_resize_aware(&mut self) -> Option<&mut dyn ResizeAware> {
+            Some(self)
+        }
+    }
+
+    impl ResizeAware for ResizeComputeNode {
+        fn on_resize(&mut self, _device: &wgpu::Device, width: u32, height: u32) {
+            *self.0.lock().unwrap() = (width, height);
+        }
+    }
+
+    #[test]
+    fn should_notify_compute_nodes_of_resizes() {
+        let device = create_device();
+        let size = Arc::new(Mutex::new((0, 0)));
+        let mut pipeline_manager = PipelineManager::new();
+        pipeline_manager
+            .add_compute_node("froxels", Box::new(ResizeComputeNode(size.clone())), vec![])
+            .unwrap();
+        pipeline_manager
+            .add_compute_node("simulate", Box::new(EmptyComputeNode(0)), vec![])
+            .unwrap();
+
+        pipeline_manager.notify_resize(&device, 1280, 720);
+        assert_eq!(*size.lock().unwrap(), (1280, 720));
+    }
+
+    #[test]
+    fn should_record_compute_nodes_in_order() {
+        let device = Arc::new(create_device());
+        let omni_manager = OmniShadowManager::new(device.clone(), ShadowQuality::Medium);
+        let cascade_manager = CascadeShadowManager::new(device.clone(), ShadowQuality::Medium);
+        let resource_manager =
+            GPUResourceManager::new(device.clone(), &omni_manager, &cascade_manager);
+        let mut world = Universe::new().create_world();
+
+        let mut pipeline_manager = PipelineManager::new();
+        let opaque = || Box::new(EmptyComputeNode(RenderPriority::OPAQUE));
+        let shadow = Box::new(EmptyComputeNode(RenderPriority::SHADOW));
+        pipeline_manager
+            .add_compute_node("sort", opaque(), vec!["simulate"])
+            .unwrap();
+        pipeline_manager
+            .add_compute_node("simulate", opaque(), vec![])
+            .unwrap();
+        pipeline_manager
+            .add_compute_node("cull", shadow, vec![])
+            .unwrap();
+        pipeline_manager.add_node("lighting", vec!["sort"]).unwrap();
+
+        let mut command_queue = CommandBufferQueue::new(8);
+        let mut collect = |pipeline_manager: &mut PipelineManager| {
+            pipeline_manager.record_compute_nodes(
+                &device,
+                &resource_manager,
+                &mut world,
+                &command_queue,
+            );
+            command_queue
+                .push(queue_item(&device, "lighting", RenderPriority::OPAQUE))
+                .unwrap();
+            pipeline_manager
+                .collect_nodes(&mut command_queue)
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            collect(&mut pipeline_manager),
+            vec!["cull", "simulate", "sort", "lighting"]
+        );
+
+        pipeline_manager.disable_node("sort");
+        assert_eq!(
+            collect(&mut pipeline_manager),
+            vec!["cull", "simulate", "lighting"]
+        );
+    }
+
+    #[test]
+    fn should_collect_in_priority_order() {
+        let device = create_device();
+        let mut pipeline_manager = PipelineManager::new();
+        for name in &["shadow", "pbr", "glass", "water"] {
+            pipeline_manager.add_node(*name, vec![]).unwrap();
+        }
+
+        let mut command_queue = CommandBufferQueue::new(8);
+        let items = [
+            ("UI", RenderPriority::UI),
+            ("water", RenderPriority::TRANSPARENT),
+            ("pbr", RenderPriority::OPAQUE),
+            ("glass", RenderPriority::TRANSPARENT),
+            ("shadow", RenderPriority::SHADOW),
+        ];
+        for (name, priority) in items.iter() {
+            command_queue
+                .push(queue_item(&device, name, *priority))
+                .unwrap();
+        }
+
+        // Items with the same priority are submitted in the graph's order, not the order they were pushed in.
+        let names: Vec<&str> = pipeline_manager
+            .collect_nodes(&mut command_queue)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["shadow", "pbr", "glass", "water", "UI"]);
+        assert!(command_queue.drain().is_empty());
+    }
+
+    #[test]
+    fn should_toggle_nodes() {
+        let device = create_device();
+        let mut pipeline_manager = PipelineManager::new();
+        pipeline_manager.add_node("ssao", vec![]).unwrap();
+        pipeline_manager.add_node("lighting", vec!["ssao"]).unwrap();
+        assert!(pipeline_manager.is_enabled("ssao"));
+
+        pipeline_manager.disable_node("ssao");
+        assert!(!pipeline_manager.is_enabled("ssao"));
+        assert!(pipeline_manager.is_enabled("lighting"));
+
+        // Disabled nodes still take part in ordering.
+        let position = |node: &str| pipeline_manager.order.iter().position(|name| name == node);
+        let (ssao, lighting) = (position("ssao"), position("lighting"));
+        assert!(ssao.is_some());
+        assert!(ssao < lighting);
+
+        // Only the enabled node's work is submitted.
+        let mut command_queue = CommandBufferQueue::new(8);
+        let push = |command_queue: &CommandBufferQueue| {
+            command_queue.push(queue_item(&device, "ssao", RenderPriority::OPAQUE)).unwrap();
+            command_queue.push(queue_item(&device, "ssao", RenderPriority::OPAQUE)).unwrap();
+            command_queue.push(queue_item(&device, "lighting", RenderPriority::OPAQUE)).unwrap();
+        };
+        push(&command_queue);
+        assert_eq!(pipeline_manager.collect_buffers(&mut command_queue, None).len(), 1);
+
+        pipeline_manager.enable_node("ssao");
+        assert!(pipeline_manager.is_enabled("ssao"));
+        push(&command_queue);
+        assert_eq!(pipeline_manager.collect_buffers(&mut command_queue, None).len(), 3);
+    }
+
+    #[test]
+    fn should_reject_cycles() {
+        let mut pipeline_manager = PipelineManager::new();
+        pipeline_manager.add_node("lighting", vec!["ssao"]).unwrap();
+        assert_eq!(
+            pipeline_manager.add_node("ssao", vec!["lighting"]),
+            Err(RenderGraphError::Cycle(vec![
+                "lighting".to_string(),
+                "ssao".to_string()
+            ]))
+        );
+
+        // Nothing is added when the dependencies would form a cycle.
+        assert!(!pipeline_manager.pipelines.contains_key("ssao"));
+        pipeline_manager.add_node("ssao", vec![]).unwrap();
+        assert_eq!(pipeline_manager.order, vec!["ssao", "lighting", "UI"]);
+    }
+
+    #[test]
+    fn should_order_readers_after_writers() {
+        let mut pipeline_manager = PipelineManager::new();
+        pipeline_manager.add_node("blur", vec![]).unwrap();
+        pipeline_manager.add_node("history", vec![]).unwrap();
+        // Reads last frame's contents on purpose.
+        pipeline_manager
+            .add_node("lighting", vec!["history"])
+            .unwrap();
+        pipeline_manager
+            .declare_access("blur", vec!["hdr"], vec!["blurred"])
+            .unwrap();
+        pipeline_manager
+            .declare_access("lighting", vec![], vec!["hdr"])
+            .unwrap();
+        pipeline_manager
+            .declare_access("history", vec!["hdr"], vec![])
+            .unwrap();
+
+        assert_eq!(
+            pipeline_manager.order,
+            vec!["history", "lighting", "blur", "UI"]
+        );
+    }
+
+    #[test]
+    fn should_detect_write_conflicts() {
+        let mut pipeline_manager = PipelineManager::new();
+        pipeline_manager.add_node("sky", vec![]).unwrap();
+        pipeline_manager.add_node("lighting", vec![]).unwrap();
+        pipeline_manager.add_node("fog", vec!["sky"]).unwrap();
+        pipeline_manager
+            .declare_access("sky", vec![], vec!["hdr"])
+            .unwrap();
+        assert_eq!(
+            pipeline_manager.declare_access("lighting", vec![], vec!["hdr"]),
+            Err(RenderGraphError::WriteConflict {
+                node_a: "sky".to_string(),
+                node_b: "lighting".to_string(),
+                resource: "hdr".to_string(),
+            })
+        );
+        assert!(!pipeline_manager.accesses.contains_key("lighting"));
+
+        pipeline_manager
+            .declare_access("fog", vec![], vec!["hdr"])
+            .unwrap();
+        assert_eq!(pipeline_manager.order, vec!["sky", "lighting", "fog", "UI"]);
+    }
+
+    #[test]
+    fn should_skip_removed_pipelines() {
+        let mut pipeline_manager = PipelineManager::new();
+        pipeline_manager.add_node("lighting", vec![]).unwrap();
+        pipeline_manager.add_node("fxaa", vec!["lighting"]).unwrap();
+        pipeline_manager.add_node("ui_blur", vec!["lighting"]).unwrap();
+
+        pipeline_manager.remove_pipeline("fxaa");
+        assert_eq!(pipeline_manager.order, vec!["lighting", "ui_blur", "UI"]);
+
+        // Removed nodes other pipelines depend on still run first.
+        pipeline_manager.remove_pipeline("lighting");
+        assert_eq!(pipeline_manager.order, vec!["lighting", "ui_blur", "UI"]);
+    }
+
+    #[test]
+    fn should_override_color_formats() {
+        let desc = PipelineDesc::default();
+        let hdr_desc = desc.with_color_formats(&[
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureFormat::Rgba8Unorm,
+        ]);
+        assert_eq!(hdr_desc.color_states.len(), 1);
+        assert_eq!(hdr_desc.color_states[0].format, wgpu::TextureFormat::Rgba16Float);
+        // Variants are stored under their own hash.
+        assert_ne!(desc.create_hash(), hdr_desc.create_hash());
+    }
+
+    const VERTEX_SHADER: &str = "#version 450
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}";
+
+    const FRAGMENT_SHADER: &str = "#version 450
+layout(location = 0) out vec4 color;
+void main() {
+    color = vec4(1.0);
+}";
+
+    // A default pipeline whose shader only resolves once the test puts it in the cache.
+    // Records the thread every build happens on.
+    struct TestPipelineDesc {
+        shader_cache: AssetCache<Shader>,
+        builds: Arc<Mutex<Vec<ThreadId>>>,
+    }
+
+    impl PipelineDescErased for TestPipelineDesc {
+        fn create_hash(&self) -> u64 {
+            0
+        }
+
+        fn shader_handle(&self, _asset_manager: &AssetManager) -> Arc<AssetHandle<Shader>> {
+            let path = PathBuf::from("test.shader");
+            Arc::new(AssetHandle::new(path, self.shader_cache.clone()))
+        }
+
+        fn build_erased(
+            &self,
+            shader: &Shader,
+            device: &wgpu::Device,
+            gpu_resource_manager: &GPUResourceManager,
+        ) -> PipelineType {
+            self.builds.lock().unwrap().push(thread::current().id());
+            PipelineDesc::default().build_erased(shader, device, gpu_resource_manager)
+        }
+    }
+
+    fn create_managers() -> (Arc<wgpu::Device>, AssetManager, Arc<GPUResourceManager>) {
+        let (device, queue) = create_device_and_queue();
+        let device = Arc::new(device);
+        let omni_manager = OmniShadowManager::new(device.clone(), ShadowQuality::Medium);
+        let cascade_manager = CascadeShadowManager::new(device.clone(), ShadowQuality::Medium);
+        let gpu_resource_manager = Arc::new(GPUResourceManager::new(
+            device.clone(),
+            &omni_manager,
+            &cascade_manager,
+        ));
+        let asset_manager = AssetManager::new(
+            PathBuf::from(""),
+            device.clone(),
+            Arc::new(queue),
+            gpu_resource_manager.clone(),
+        );
+        (device, asset_manager, gpu_resource_manager)
+    }
+
+    #[test]
+    fn should_build_inserted_pipelines_on_first_use() {
+        let (device, asset_manager, gpu_resource_manager) = create_managers();
+        let shader_cache: AssetCache<Shader> = Arc::new(dashmap::DashMap::new());
+        let builds = Arc::new(Mutex::new(Vec::new()));
+        let inserts = AtomicUsize::new(0);
+        let mut pipeline_manager = PipelineManager::new();
+        let get_or_insert = |pipeline_manager: &mut PipelineManager| {
+            pipeline_manager
+                .get_or_insert_with(
+                    "dlc_material",
+                    vec![],
+                    &device,
+                    &asset_manager,
+                    &gpu_resource_manager,
+                    || {
+                        inserts.fetch_add(1, Ordering::SeqCst);
+                        Box::new(TestPipelineDesc {
+                            shader_cache: shader_cache.clone(),
+                            builds: builds.clone(),
+                        })
+                    },
+                )
+                .is_some()
+        };
+
+        // The first call registers the pipeline, it isn't built until its shader has loaded.
+        assert!(!get_or_insert(&mut pipeline_manager));
+        assert!(!get_or_insert(&mut pipeline_manager));
+        assert_eq!(inserts.load(Ordering::SeqCst), 1);
+        assert!(builds.lock().unwrap().is_empty());
+        assert!(!pipeline_manager.is_ready("dlc_material"));
+        assert!(!pipeline_manager.all_ready());
+        assert!(pipeline_manager.get("dlc_material", None).is_none());
+
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let mut compile = |source: &str, kind: shaderc::ShaderKind| {
+            let spirv = compiler
+                .compile_into_spirv(source, kind, "dlc_material.glsl", "main", None)
+                .unwrap();
+            device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Owned(
+                spirv.as_binary().to_vec(),
+            )))
+        };
+        let shader = Shader::Core(CoreShader {
+            vertex: compile(VERTEX_SHADER, shaderc::ShaderKind::Vertex),
+            fragment: compile(FRAGMENT_SHADER, shaderc::ShaderKind::Fragment),
+        });
+        shader_cache.insert(PathBuf::from("test.shader"), Ok(Arc::new(shader)));
+
+        // Once the shader has loaded the next call builds it on this thread, later calls reuse it.
+        assert!(get_or_insert(&mut pipeline_manager));
+        assert!(get_or_insert(&mut pipeline_manager));
+        assert_eq!(inserts.load(Ordering::SeqCst), 1);
+        assert_eq!(*builds.lock().unwrap(), vec![thread::current().id()]);
+        assert!(pipeline_manager.is_ready("dlc_material"));
+        assert!(pipeline_manager.get("dlc_material", None).is_some());
+    }
+
+    #[test]
+    fn should_not_build_inserted_pipelines_without_a_shader() {
+        let (device, asset_manager, gpu_resource_manager) = create_managers();
+        let shader_cache: AssetCache<Shader> = Arc::new(dashmap::DashMap::new());
+        let builds = Arc::new(Mutex::new(Vec::new()));
+        shader_cache.insert(
+            PathBuf::from("test.shader"),
+            Err(Arc::new(AssetError::FileNotFound)),
+        );
+
+        let mut pipeline_manager = PipelineManager::new();
+        for _ in 0..2 {
+            let pipeline = pipeline_manager.get_or_insert_with(
+                "dlc_material",
+                vec![],
+                &device,
+                &asset_manager,
+                &gpu_resource_manager,
+                || {
+                    Box::new(TestPipelineDesc {
+                        shader_cache: shader_cache.clone(),
+                        builds: builds.clone(),
+                    })
+                },
+            );
+            assert!(pipeline.is_none());
+        }
+
+        // A shader that failed to load is never built or retried.
+        assert!(builds.lock().unwrap().is_empty());
+        assert!(!pipeline_manager.is_ready("dlc_material"));
+        assert!(pipeline_manager.all_ready());
+    }
+
+    #[test]
+    fn should_not_block_on_failed_pipelines() {
+        let mut pipeline_manager = PipelineManager::new();
+        let pipeline = Arc::new(OnceLock::new());
+        let mut pipeline_hashmap = HashMap::new();
+        pipeline_hashmap.insert(0, PipelineType::Compiling(pipeline.clone()));
+        pipeline_manager.pipelines.insert("pbr".to_string(), pipeline_hashmap);
+        pipeline_manager.current_pipelines.insert("pbr".to_string(), 0);
+        assert!(!pipeline_manager.is_ready("pbr"));
+        assert!(!pipeline_manager.all_ready());
+
+        let _ = pipeline.set(Err(Arc::new(AssetError::FileNotFound)));
+        assert!(!pipeline_manager.is_ready("pbr"));
+        // Failed pipelines aren't compiling anymore.
+        assert!(pipeline_manager.all_ready());
+        assert!(pipeline_manager.get("pbr", None).is_none());
     }
 }
@@ -0,0 +1,393 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{ComputePipelineDesc, PipelineDesc, PipelineManager},
+        renderer::HDR_FORMAT,
+        resources::{GPUResourceManager, HdrFramebuffer},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+// Must match the local size in the bloom compute shaders.
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Runtime settings for bloom. Insert this as a resource to change them.
+#[derive(Debug, Clone, Copy)]
+pub struct BloomConfig {
+    /// Pixels with a luminance above this value will bloom.
+    pub threshold: f32,
+    /// How much of the blurred result is added back onto the hdr framebuffer.
+    pub intensity: f32,
+    /// How many times the horizontal and vertical blur passes run.
+    pub iterations: u32,
+    /// The radius in pixels of the gaussian blur kernel.
+    pub radius: u32,
+}
+
+impl Default for BloomConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.5,
+            iterations: 2,
+            radius: 4,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct BloomUniform {
+    threshold: f32,
+    radius: u32,
+    direction: [u32; 2],
+}
+
+unsafe impl Zeroable for BloomUniform {}
+unsafe impl Pod for BloomUniform {}
+
+/// Extracts the bright parts of the hdr framebuffer, blurs them and adds them back on top.
+/// The bloom textures are half the size of the hdr framebuffer.
+pub struct BloomPass {
+    pub bright: wgpu::Texture,
+    pub bright_view: wgpu::TextureView,
+    pub blur: wgpu::Texture,
+    pub blur_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+
+    // Used to sample the bloom textures.
+    bright_bind_group: wgpu::BindGroup,
+    blur_bind_group: wgpu::BindGroup,
+
+    // Used to write into the bloom textures.
+    threshold_bind_group: wgpu::BindGroup,
+    horizontal_bind_group: wgpu::BindGroup,
+    vertical_bind_group: wgpu::BindGroup,
+
+    threshold_buffer: wgpu::Buffer,
+    horizontal_buffer: wgpu::Buffer,
+    vertical_buffer: wgpu::Buffer,
+
+    pub width: u32,
+    pub height: u32,
+}
+
+impl BloomPass {
+    pub fn new(
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let width = (width / 2).max(1);
+        let height = (height / 2).max(1);
+
+        let sample_layout = resource_manager
+            .get_bind_group_layout("hdr_texture_layout")
+            .unwrap();
+        let storage_layout = resource_manager
+            .get_bind_group_layout("bloom_storage_layout")
+            .unwrap();
+
+        let (bright, bright_view) = Self::create_texture(device, width, height);
+        let (blur, blur_view) = Self::create_texture(device, width, height);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        let create_uniform_buffer = || {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: std::mem::size_of::<BloomUniform>() as u64,
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let threshold_buffer = create_uniform_buffer();
+        let horizontal_buffer = create_uniform_buffer();
+        let vertical_buffer = create_uniform_buffer();
+
+        let create_sample_bind_group = |view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &sample_layout,
+                entries: Cow::Borrowed(&[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                ]),
+                label: None,
+            })
+        };
+        let bright_bind_group = create_sample_bind_group(&bright_view);
+        let blur_bind_group = create_sample_bind_group(&blur_view);
+
+        let create_storage_bind_group = |view: &wgpu::TextureView, buffer: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &storage_layout,
+                entries: Cow::Borrowed(&[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+                    },
+                ]),
+                label: None,
+            })
+        };
+        let threshold_bind_group = create_storage_bind_group(&bright_view, &threshold_buffer);
+        let horizontal_bind_group = create_storage_bind_group(&blur_view, &horizontal_buffer);
+        let vertical_bind_group = create_storage_bind_group(&bright_view, &vertical_buffer);
+
+        Self {
+            bright,
+            bright_view,
+            blur,
+            blur_view,
+            sampler,
+            bright_bind_group,
+            blur_bind_group,
+            threshold_bind_group,
+            horizontal_bind_group,
+            vertical_bind_group,
+            threshold_buffer,
+            horizontal_buffer,
+            vertical_buffer,
+            width,
+            height,
+        }
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsage::STORAGE | wgpu::TextureUsage::SAMPLED,
+            label: Some("bloom"),
+        });
+        let view = texture.create_default_view();
+        (texture, view)
+    }
+
+    /// Runs the threshold and blur compute passes. The blurred result ends up in `bright`.
+    pub fn compute(
+        &self,
+        queue: &wgpu::Queue,
+        config: &BloomConfig,
+        hdr_framebuffer: &HdrFramebuffer,
+        pipeline_manager: &PipelineManager,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let uniform = |direction: [u32; 2]| BloomUniform {
+            threshold: config.threshold,
+            radius: config.radius,
+            direction,
+        };
+        queue.write_buffer(&self.threshold_buffer, 0, bytemuck::bytes_of(&uniform([0, 0])));
+        queue.write_buffer(&self.horizontal_buffer, 0, bytemuck::bytes_of(&uniform([1, 0])));
+        queue.write_buffer(&self.vertical_buffer, 0, bytemuck::bytes_of(&uniform([0, 1])));
+
+        let threshold_pipeline = pipeline_manager.get_compute("bloom_threshold", None).unwrap();
+        let blur_pipeline = pipeline_manager.get_compute("bloom_blur", None).unwrap();
+        let dispatch_x = (self.width + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+        let dispatch_y = (self.height + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+
+        // Each pass gets it's own compute pass as the textures swap between being read and written.
+        {
+            let mut pass = encoder.begin_compute_pass();
+            pass.set_pipeline(&threshold_pipeline.compute_pipeline);
+            pass.set_bind_group(0, &hdr_framebuffer.bind_group, &[]);
+            pass.set_bind_group(1, &self.threshold_bind_group, &[]);
+            pass.dispatch(dispatch_x, dispatch_y, 1);
+        }
+
+        for _ in 0..config.iterations {
+            {
+                let mut pass = encoder.begin_compute_pass();
+                pass.set_pipeline(&blur_pipeline.compute_pipeline);
+                pass.set_bind_group(0, &self.bright_bind_group, &[]);
+                pass.set_bind_group(1, &self.horizontal_bind_group, &[]);
+                pass.dispatch(dispatch_x, dispatch_y, 1);
+            }
+            {
+                let mut pass = encoder.begin_compute_pass();
+                pass.set_pipeline(&blur_pipeline.compute_pipeline);
+                pass.set_bind_group(0, &self.blur_bind_group, &[]);
+                pass.set_bind_group(1, &self.vertical_bind_group, &[]);
+                pass.dispatch(dispatch_x, dispatch_y, 1);
+            }
+        }
+    }
+
+    /// Additively blends the blurred result onto the hdr framebuffer.
+    pub fn composite(
+        &self,
+        config: &BloomConfig,
+        hdr_framebuffer: &HdrFramebuffer,
+        pipeline_manager: &PipelineManager,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: &hdr_framebuffer.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }]),
+            depth_stencil_attachment: None,
+        });
+
+        let composite_pipeline = pipeline_manager.get("bloom_composite", None).unwrap();
+        render_pass.set_pipeline(&composite_pipeline.render_pipeline);
+        render_pass.set_bind_group(0, &self.bright_bind_group, &[]);
+        // The blend color scales the bloom before it's added.
+        let intensity = config.intensity as f64;
+        render_pass.set_blend_color(wgpu::Color {
+            r: intensity,
+            g: intensity,
+            b: intensity,
+            a: 1.0,
+        });
+        render_pass.draw(0..3 as u32, 0..1);
+    }
+}
+
+/// Creates the bloom pipelines and inserts the `BloomPass` and `BloomConfig` resources.
+/// Note: This needs to be called after the hdr framebuffer is created.
+pub fn create(resources: &mut Resources) {
+    let bloom_pass = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let hdr_framebuffer = resources.get::<HdrFramebuffer>().unwrap();
+
+        let storage_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupLayoutEntry::new(
+                    0,
+                    wgpu::ShaderStage::COMPUTE,
+                    wgpu::BindingType::StorageTexture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        format: HDR_FORMAT,
+                        readonly: false,
+                    },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    1,
+                    wgpu::ShaderStage::COMPUTE,
+                    wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<BloomUniform>() as _,
+                        ),
+                    },
+                ),
+            ]),
+            label: Some(Cow::Borrowed("bloom_storage_layout")),
+        });
+        resource_manager.add_bind_group_layout("bloom_storage_layout", storage_layout);
+
+        let mut threshold_desc =
+            ComputePipelineDesc::new("core/shaders/bloom/bloom_threshold.shader");
+        threshold_desc.layouts = vec![
+            "hdr_texture_layout".to_string(),
+            "bloom_storage_layout".to_string(),
+        ];
+        pipeline_manager
+            .add_compute_pipeline(
+                "bloom_threshold",
+                &threshold_desc,
+                vec!["pbr", "deferred_lighting", "debug_draw", "taa"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        let mut blur_desc = ComputePipelineDesc::new("core/shaders/bloom/bloom_blur.shader");
+        blur_desc.layouts = threshold_desc.layouts.clone();
+        pipeline_manager
+            .add_compute_pipeline(
+                "bloom_blur",
+                &blur_desc,
+                vec!["bloom_threshold"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        let mut composite_desc = PipelineDesc::default();
+        composite_desc.shader = "core/shaders/bloom/bloom_composite.shader".to_string();
+        composite_desc.color_states[0] = wgpu::ColorStateDescriptor {
+            format: HDR_FORMAT,
+            color_blend: wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::BlendColor,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha_blend: wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            write_mask: wgpu::ColorWrite::ALL,
+        };
+        composite_desc.layouts = vec!["hdr_texture_layout".to_string()];
+        composite_desc.cull_mode = wgpu::CullMode::None;
+        pipeline_manager
+            .add_pipeline(
+                "bloom_composite",
+                &composite_desc,
+                vec!["bloom_blur"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        BloomPass::new(
+            &device,
+            &resource_manager,
+            hdr_framebuffer.width,
+            hdr_framebuffer.height,
+        )
+    };
+
+    resources.insert(bloom_pass);
+    resources.insert(BloomConfig::default());
+}
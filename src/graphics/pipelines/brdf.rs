@@ -27,14 +27,16 @@ pub fn create(resources: &Resources, output: &RenderTarget, format: wgpu::Textur
         mipmap_desc.shader = "core/shaders/calculations/specular_brdf.shader".to_string();
         mipmap_desc.color_states[0].format = format;
         mipmap_desc.cull_mode = wgpu::CullMode::None;
-        pipeline_manager.add_pipeline(
-            "brdf",
-            &mipmap_desc,
-            vec![],
-            &device,
-            &asset_manager,
-            resource_manager.clone(),
-        );
+        pipeline_manager
+            .add_pipeline(
+                "brdf",
+                &mipmap_desc,
+                vec![],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
         pipeline = pipeline_manager.get("brdf", None);
     }
 
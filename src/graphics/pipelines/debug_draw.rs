@@ -0,0 +1,152 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+use nalgebra_glm::Vec3;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::GPUResourceManager,
+    },
+    AssetManager,
+};
+use std::sync::Arc;
+
+// How many line segments make up each circle of a debug sphere.
+const SPHERE_SEGMENTS: usize = 32;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+unsafe impl Zeroable for DebugVertex {}
+unsafe impl Pod for DebugVertex {}
+
+/// Immediate mode lines, boxes and spheres drawn on top of the scene.
+/// Everything queued is drawn by the `render_debug` system and then cleared, so call these every frame.
+#[derive(Debug, Default)]
+pub struct DebugDraw {
+    pub(crate) vertices: Vec<DebugVertex>,
+}
+
+impl DebugDraw {
+    pub fn line(&mut self, from: Vec3, to: Vec3, color: [f32; 4]) {
+        self.vertices.push(DebugVertex {
+            position: [from.x, from.y, from.z],
+            color,
+        });
+        self.vertices.push(DebugVertex {
+            position: [to.x, to.y, to.z],
+            color,
+        });
+    }
+
+    /// Draws the 12 edges of an axis aligned box.
+    pub fn aabb(&mut self, min: Vec3, max: Vec3, color: [f32; 4]) {
+        let corner = |x: bool, y: bool, z: bool| {
+            Vec3::new(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+
+        for &a in [false, true].iter() {
+            for &b in [false, true].iter() {
+                self.line(corner(false, a, b), corner(true, a, b), color);
+                self.line(corner(a, false, b), corner(a, true, b), color);
+                self.line(corner(a, b, false), corner(a, b, true), color);
+            }
+        }
+    }
+
+    /// Draws a circle around each axis.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: [f32; 4]) {
+        let step = std::f32::consts::PI * 2.0 / SPHERE_SEGMENTS as f32;
+        for i in 0..SPHERE_SEGMENTS {
+            let (sin_a, cos_a) = (i as f32 * step).sin_cos();
+            let (sin_b, cos_b) = ((i + 1) as f32 * step).sin_cos();
+            let (a, b) = (sin_a * radius, cos_a * radius);
+            let (c, d) = (sin_b * radius, cos_b * radius);
+
+            self.line(
+                center + Vec3::new(a, b, 0.0),
+                center + Vec3::new(c, d, 0.0),
+                color,
+            );
+            self.line(
+                center + Vec3::new(a, 0.0, b),
+                center + Vec3::new(c, 0.0, d),
+                color,
+            );
+            self.line(
+                center + Vec3::new(0.0, a, b),
+                center + Vec3::new(0.0, c, d),
+                color,
+            );
+        }
+    }
+}
+
+/// Creates the debug line pipeline and inserts the `DebugDraw` resource.
+pub fn create(resources: &mut Resources) {
+    {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+        let mut debug_desc = PipelineDesc::default();
+        debug_desc.shader = "core/shaders/debug_draw.shader".to_string();
+        debug_desc.primitive_topology = wgpu::PrimitiveTopology::LineList;
+        debug_desc.color_states[0].format = HDR_FORMAT;
+        debug_desc.color_states[0].color_blend = wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+        // Debug shapes are always drawn over the scene and don't hide each other.
+        debug_desc.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        });
+        debug_desc.layouts = vec!["globals".to_string()];
+        debug_desc.cull_mode = wgpu::CullMode::None;
+        debug_desc.vertex_state.new_buffer_descriptor(
+            std::mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+            wgpu::InputStepMode::Vertex,
+            wgpu::vertex_attr_array![0 => Float3, 1 => Float4].to_vec(),
+        );
+
+        pipeline_manager
+            .add_pipeline(
+                "debug_draw",
+                &debug_desc,
+                vec!["pbr", "pbr_transparent", "deferred_lighting"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+    }
+
+    resources.insert(DebugDraw::default());
+
+    // Empty physics sets so the physics debug system can run before a game inserts it's own.
+    #[cfg(feature = "physics_debug")]
+    {
+        resources.insert(super::physics_debug::PhysicsDebugDraw::default());
+        resources.insert(rapier3d::dynamics::RigidBodySet::new());
+        resources.insert(rapier3d::geometry::ColliderSet::new());
+        resources.insert(rapier3d::dynamics::ImpulseJointSet::new());
+        resources.insert(rapier3d::dynamics::MultibodyJointSet::new());
+    }
+}
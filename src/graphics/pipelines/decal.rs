@@ -0,0 +1,387 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+use nalgebra_glm::{Mat4, Vec3};
+
+use crate::{
+    assets::texture::Texture,
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::{GPUResourceManager, GBUFFER_ALBEDO_FORMAT, GBUFFER_NORMAL_FORMAT},
+    },
+    scene::components::Decal,
+    AssetManager,
+};
+use std::{borrow::Cow, collections::HashMap, ops::Range, sync::Arc};
+
+/// The decal box is drawn as a unit cube made of 12 triangles.
+pub const VERTICES_PER_DECAL: u32 = 36;
+/// Forward rendered decals are drawn as a quad through the middle of the box.
+pub const VERTICES_PER_FORWARD_DECAL: u32 = 6;
+
+/// Used when a decal has no normal texture.
+pub const FLAT_NORMAL_TEXTURE: &str = "core/empty_normal.png";
+
+/// The per instance data of a decal.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecalInstance {
+    /// Turns the unit cube into the decal's box.
+    pub model: Mat4,
+    /// Turns world positions into positions in the unit cube.
+    pub inverse_model: Mat4,
+    /// (fade distance, 1.0 if the decal has a normal texture, 0, 0)
+    pub info: [f32; 4],
+}
+
+unsafe impl Zeroable for DecalInstance {}
+unsafe impl Pod for DecalInstance {}
+
+impl DecalInstance {
+    pub fn new(decal: &Decal, matrix: &Mat4) -> Self {
+        // The cube is one unit wide, centered on the origin.
+        let model = nalgebra_glm::scale(matrix, &decal.size);
+        Self {
+            model,
+            inverse_model: nalgebra_glm::inverse(&model),
+            info: [
+                decal.fade_distance,
+                if decal.normal_texture.is_some() {
+                    1.0
+                } else {
+                    0.0
+                },
+                0.0,
+                0.0,
+            ],
+        }
+    }
+
+    /// The center of the decal's box in world space.
+    pub fn position(&self) -> Vec3 {
+        self.model.column(3).xyz()
+    }
+}
+
+/// The textures a decal is drawn with, decals sharing them are drawn with one draw call.
+pub(crate) fn decal_textures(decal: &Decal) -> (&str, &str) {
+    (
+        decal.texture.as_str(),
+        decal
+            .normal_texture
+            .as_ref()
+            .map_or(FLAT_NORMAL_TEXTURE, |texture| texture.as_str()),
+    )
+}
+
+/// Groups consecutive decals with the same textures into one draw, returning the textures and instance range of each.
+pub(crate) fn decal_batches<'a>(
+    textures: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Vec<((&'a str, &'a str), Range<u32>)> {
+    let mut batches: Vec<((&str, &str), Range<u32>)> = Vec::new();
+    for (index, key) in textures.enumerate() {
+        let index = index as u32;
+        match batches.last_mut() {
+            Some((last, range)) if *last == key => range.end = index + 1,
+            _ => batches.push((key, index..index + 1)),
+        }
+    }
+    batches
+}
+
+/// The GPU resources shared by every decal. Inserted as a resource by `create`.
+pub struct DecalPipeline {
+    instance_buffer: Option<wgpu::Buffer>,
+    // How many instances fit in `instance_buffer`.
+    capacity: usize,
+    sampler: wgpu::Sampler,
+    // Keyed by the texture paths, the textures are kept to notice when they're reloaded.
+    bind_groups: HashMap<(String, String), (Arc<Texture>, Arc<Texture>, wgpu::BindGroup)>,
+}
+
+impl DecalPipeline {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("decal_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            instance_buffer: None,
+            capacity: 0,
+            sampler,
+            bind_groups: HashMap::new(),
+        }
+    }
+
+    /// Makes sure the instance buffer holds at least `instance_count` decals, it grows to the next power of two when it's too small.
+    pub(crate) fn reserve(&mut self, device: &wgpu::Device, instance_count: usize) {
+        if self.instance_buffer.is_none() || self.capacity < instance_count {
+            self.capacity = instance_count.next_power_of_two();
+            self.instance_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("decal_instances"),
+                size: (self.capacity * std::mem::size_of::<DecalInstance>()) as u64,
+                usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+    }
+
+    /// Panics if `reserve` hasn't been called yet.
+    pub(crate) fn instance_buffer(&self) -> &wgpu::Buffer {
+        self.instance_buffer.as_ref().unwrap()
+    }
+
+    /// Creates the bind group for a pair of textures, or recreates it if either was reloaded.
+    pub(crate) fn prepare_textures(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        (texture_path, normal_path): (&str, &str),
+        texture: Arc<Texture>,
+        normal_texture: Arc<Texture>,
+    ) {
+        let key = (texture_path.to_string(), normal_path.to_string());
+        if let Some((cached, cached_normal, _)) = self.bind_groups.get(&key) {
+            if Arc::ptr_eq(cached, &texture) && Arc::ptr_eq(cached_normal, &normal_texture) {
+                return;
+            }
+        }
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&normal_texture.view),
+                },
+            ]),
+            label: Some(Cow::Borrowed("decal_bind_group")),
+        });
+        self.bind_groups
+            .insert(key, (texture, normal_texture, bind_group));
+    }
+
+    pub(crate) fn bind_group(
+        &self,
+        (texture, normal_texture): (&str, &str),
+    ) -> Option<&wgpu::BindGroup> {
+        self.bind_groups
+            .get(&(texture.to_string(), normal_texture.to_string()))
+            .map(|(_, _, bind_group)| bind_group)
+    }
+}
+
+fn create_texture_layout(
+    device: &wgpu::Device,
+    label: &'static str,
+    textures: u32,
+) -> wgpu::BindGroupLayout {
+    let mut entries = vec![wgpu::BindGroupLayoutEntry::new(
+        0,
+        wgpu::ShaderStage::FRAGMENT,
+        wgpu::BindingType::Sampler { comparison: false },
+    )];
+    for binding in 1..=textures {
+        entries.push(wgpu::BindGroupLayoutEntry::new(
+            binding,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                component_type: wgpu::TextureComponentType::Float,
+                dimension: wgpu::TextureViewDimension::D2,
+            },
+        ));
+    }
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(entries),
+        label: Some(Cow::Borrowed(label)),
+    })
+}
+
+/// Describes both of the pipelines decals are drawn with.
+/// deferred: Draws the back faces of each decal box and blends the texture into the gbuffer where the gbuffer's depth lies inside the box.
+/// forward: Adds the texture to the frame on a quad through the middle of each box.
+pub struct DecalPipelineDesc {
+    pub deferred: PipelineDesc,
+    pub forward: PipelineDesc,
+}
+
+impl DecalPipelineDesc {
+    pub fn new() -> Self {
+        let instance_attributes = wgpu::vertex_attr_array![
+            0 => Float4, 1 => Float4, 2 => Float4, 3 => Float4,
+            4 => Float4, 5 => Float4, 6 => Float4, 7 => Float4,
+            8 => Float4
+        ]
+        .to_vec();
+        let alpha_blend = wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        let mut deferred = PipelineDesc::default();
+        deferred.shader = "core/shaders/decal.shader".to_string();
+        // The gbuffer's alpha channels are left alone.
+        deferred.color_states = vec![GBUFFER_ALBEDO_FORMAT, GBUFFER_NORMAL_FORMAT]
+            .into_iter()
+            .map(|format| wgpu::ColorStateDescriptor {
+                format,
+                color_blend: alpha_blend.clone(),
+                alpha_blend: wgpu::BlendDescriptor {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                write_mask: wgpu::ColorWrite::ALL,
+            })
+            .collect();
+        // The gbuffer's depth is sampled instead of tested, so the back faces are drawn to cover the box
+        // even when the camera is inside it.
+        deferred.cull_mode = wgpu::CullMode::Front;
+        deferred.layouts = vec![
+            "decal_gbuffer_layout".to_string(),
+            "globals".to_string(),
+            "decal_texture_layout".to_string(),
+        ];
+        deferred.vertex_state.new_buffer_descriptor(
+            std::mem::size_of::<DecalInstance>() as wgpu::BufferAddress,
+            wgpu::InputStepMode::Instance,
+            instance_attributes.clone(),
+        );
+
+        let mut forward = PipelineDesc::default();
+        forward.shader = "core/shaders/decal_forward.shader".to_string();
+        forward.color_states[0].format = HDR_FORMAT;
+        let additive = wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        forward.color_states[0].color_blend = additive.clone();
+        forward.color_states[0].alpha_blend = additive;
+        // The quad lies on the surface it's projected on, so it's pulled towards the camera.
+        forward.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        });
+        forward.depth_bias = -2;
+        forward.depth_bias_slope_scale = (-1.0).into();
+        forward.cull_mode = wgpu::CullMode::None;
+        forward.layouts = vec!["globals".to_string(), "decal_texture_layout".to_string()];
+        forward.vertex_state.new_buffer_descriptor(
+            std::mem::size_of::<DecalInstance>() as wgpu::BufferAddress,
+            wgpu::InputStepMode::Instance,
+            instance_attributes,
+        );
+
+        Self { deferred, forward }
+    }
+}
+
+/// Creates the decal pipelines and inserts the `DecalPipeline` resource.
+/// Note: This needs to be called after the deferred pipelines are created as it draws into the gbuffer.
+pub fn create(resources: &mut Resources) {
+    let decal_pipeline = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+        // The gbuffer's depth and material targets, the albedo and normal targets are drawn into.
+        resource_manager.add_bind_group_layout(
+            "decal_gbuffer_layout",
+            create_texture_layout(&device, "decal_gbuffer_layout", 2),
+        );
+        resource_manager.add_bind_group_layout(
+            "decal_texture_layout",
+            create_texture_layout(&device, "decal_texture_layout", 2),
+        );
+
+        let decal_desc = DecalPipelineDesc::new();
+        pipeline_manager
+            .add_pipeline(
+                "decals",
+                &decal_desc.deferred,
+                vec!["deferred_geometry"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+        pipeline_manager
+            .add_pipeline(
+                "decal_forward",
+                &decal_desc.forward,
+                vec!["pbr", "pbr_transparent", "deferred_lighting"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        DecalPipeline::new(&device)
+    };
+
+    resources.insert(decal_pipeline);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decal_batches, decal_textures, DecalInstance, FLAT_NORMAL_TEXTURE};
+    use crate::scene::components::Decal;
+    use nalgebra_glm::{Vec3, Vec4};
+
+    #[test]
+    fn should_map_box_to_unit_cube() {
+        let decal = Decal::new("decal.png", Vec3::new(2.0, 4.0, 1.0));
+        let matrix = nalgebra_glm::translation(&Vec3::new(10.0, 0.0, 0.0));
+        let instance = DecalInstance::new(&decal, &matrix);
+
+        assert_eq!(instance.position(), Vec3::new(10.0, 0.0, 0.0));
+        // A corner of the box lands on a corner of the unit cube.
+        let corner = instance.inverse_model * Vec4::new(11.0, 2.0, 0.5, 1.0);
+        assert!((corner - Vec4::new(0.5, 0.5, 0.5, 1.0)).norm() < 0.0001);
+        assert_eq!(instance.info, [0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn should_batch_by_textures() {
+        let mut decals = vec![
+            Decal::new("a.png", Vec3::new(1.0, 1.0, 1.0)),
+            Decal::new("a.png", Vec3::new(1.0, 1.0, 1.0)),
+            Decal::new("a.png", Vec3::new(1.0, 1.0, 1.0)),
+        ];
+        decals[2].normal_texture = Some("a_normal.png".to_string());
+
+        let batches = decal_batches(decals.iter().map(decal_textures));
+        assert_eq!(
+            batches,
+            vec![
+                (("a.png", FLAT_NORMAL_TEXTURE), 0..2),
+                (("a.png", "a_normal.png"), 2..3)
+            ]
+        );
+    }
+}
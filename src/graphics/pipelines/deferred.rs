@@ -0,0 +1,174 @@
+use legion::prelude::Resources;
+
+use crate::assets::mesh::MeshVertexData;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::{
+            GBuffer, GPUResourceManager, GBUFFER_ALBEDO_FORMAT, GBUFFER_MATERIAL_FORMAT,
+            GBUFFER_NORMAL_FORMAT,
+        },
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// Turns deferred rendering on or off. When on the geometry and lighting passes render meshes
+/// instead of the forward pbr pipeline.
+pub struct DeferredRendering(pub bool);
+
+/// Describes both of the pipelines the deferred renderer uses.
+/// geometry: Renders meshes into the gbuffer using MRT.
+/// lighting: Reads the gbuffer with a full screen triangle and outputs the lit result.
+pub struct DeferredPipelineDesc {
+    pub geometry: PipelineDesc,
+    pub lighting: PipelineDesc,
+}
+
+impl DeferredPipelineDesc {
+    pub fn new(output_format: wgpu::TextureFormat) -> Self {
+        let mut geometry = PipelineDesc::default();
+        geometry.shader = "core/shaders/deferred_geometry.shader".to_string();
+        geometry.color_states = vec![
+            GBUFFER_ALBEDO_FORMAT,
+            GBUFFER_NORMAL_FORMAT,
+            GBUFFER_MATERIAL_FORMAT,
+        ]
+        .into_iter()
+        .map(|format| wgpu::ColorStateDescriptor {
+            format,
+            color_blend: wgpu::BlendDescriptor::REPLACE,
+            alpha_blend: wgpu::BlendDescriptor::REPLACE,
+            write_mask: wgpu::ColorWrite::ALL,
+        })
+        .collect();
+        geometry.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        });
+        // The material bind group is the same one the pbr pipeline uses.
+        geometry.layouts = vec![
+            "locals".to_string(),
+            "globals".to_string(),
+            "pbr_material_layout".to_string(),
+        ];
+        geometry.cull_mode = wgpu::CullMode::Back;
+        let vertex_size = std::mem::size_of::<MeshVertexData>();
+        geometry
+            .vertex_state
+            .set_index_format(wgpu::IndexFormat::Uint32)
+            .new_buffer_descriptor(
+                vertex_size as wgpu::BufferAddress,
+                wgpu::InputStepMode::Vertex,
+                wgpu::vertex_attr_array![0 => Float3, 1 => Float3, 2 => Float2, 3 => Float4]
+                    .to_vec(),
+            );
+
+        let mut lighting = PipelineDesc::default();
+        lighting.shader = "core/shaders/deferred_lighting.shader".to_string();
+        lighting.color_states[0].format = output_format;
+        lighting.layouts = vec![
+            "gbuffer_layout".to_string(),
+            "globals".to_string(),
+            "probe_material_layout".to_string(),
+            "ssao_texture_layout".to_string(),
+        ];
+        lighting.cull_mode = wgpu::CullMode::None;
+
+        Self { geometry, lighting }
+    }
+}
+
+pub fn create_gbuffer_bindgroup_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let texture_entry = |binding| {
+        wgpu::BindGroupLayoutEntry::new(
+            binding,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                component_type: wgpu::TextureComponentType::Float,
+                dimension: wgpu::TextureViewDimension::D2,
+            },
+        )
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::Sampler { comparison: false },
+            ),
+            texture_entry(1),
+            texture_entry(2),
+            texture_entry(3),
+            texture_entry(4),
+        ]),
+        label: Some(Cow::Borrowed("gbuffer_layout")),
+    })
+}
+
+/// Creates the deferred pipelines, the gbuffer and the ssao pass.
+/// Note: This needs to be called after the pbr pipeline is created as it shares it's bind group layouts.
+pub fn create(resources: &mut Resources) {
+    let gbuffer = {
+        let asset_manager = resources.get_mut::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let sc_desc = resources.get::<wgpu::SwapChainDescriptor>().unwrap();
+
+        let gbuffer_layout = create_gbuffer_bindgroup_layout(&device);
+        let gbuffer = GBuffer::new(&device, &gbuffer_layout, sc_desc.width, sc_desc.height);
+        resource_manager.add_bind_group_layout("gbuffer_layout", gbuffer_layout);
+
+        let deferred_desc = DeferredPipelineDesc::new(HDR_FORMAT);
+
+        pipeline_manager
+            .add_pipeline(
+                "deferred_geometry",
+                &deferred_desc.geometry,
+                vec!["globals", "skybox", "froxel_cull", "skinning"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        gbuffer
+    };
+    resources.insert(gbuffer);
+
+    // Ssao reads the gbuffer and is read by the lighting pass.
+    super::ssao::create(resources);
+
+    {
+        let asset_manager = resources.get_mut::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+        let deferred_desc = DeferredPipelineDesc::new(HDR_FORMAT);
+
+        // The lighting pass reads what the geometry, decal and ssao passes wrote.
+        pipeline_manager
+            .add_pipeline(
+                "deferred_lighting",
+                &deferred_desc.lighting,
+                vec!["deferred_geometry", "decals", "ssao_blur"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+    }
+
+    resources.insert(DeferredRendering(false));
+}
@@ -0,0 +1,77 @@
+use crate::{
+    assets::mesh::MeshVertexData,
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        resources::GPUResourceManager,
+    },
+    AssetManager,
+};
+use std::sync::Arc;
+
+/// Renders only the depth of opaque meshes, see `RenderSettings::use_depth_prepass`.
+/// Uses the same vertex layout and depth bias as the pbr pipeline so both write the exact same depth.
+pub struct DepthPrepassPipelineDesc {
+    pub prepass: PipelineDesc,
+}
+
+impl DepthPrepassPipelineDesc {
+    pub fn new(pbr_desc: &PipelineDesc) -> Self {
+        let mut prepass = PipelineDesc::default();
+        prepass.shader = "core/shaders/depth_prepass.shader".to_string();
+        prepass.color_states = Vec::new();
+        prepass.depth_state = pbr_desc.depth_state.clone();
+        prepass.depth_bias = pbr_desc.depth_bias;
+        prepass.depth_bias_slope_scale = pbr_desc.depth_bias_slope_scale;
+        prepass.depth_bias_clamp = pbr_desc.depth_bias_clamp;
+        prepass.cull_mode = pbr_desc.cull_mode;
+        prepass.layouts = vec!["locals".to_string(), "globals".to_string()];
+        prepass
+            .vertex_state
+            .set_index_format(wgpu::IndexFormat::Uint32)
+            .new_buffer_descriptor(
+                std::mem::size_of::<MeshVertexData>() as wgpu::BufferAddress,
+                wgpu::InputStepMode::Vertex,
+                wgpu::vertex_attr_array![0 => Float3, 1 => Float3, 2 => Float2, 3 => Float4]
+                    .to_vec(),
+            );
+
+        Self { prepass }
+    }
+}
+
+/// Creates the "depth_prepass" pipeline and "pbr_depth_equal", a pbr pipeline that only shades
+/// fragments matching the depth written by the prepass.
+pub(crate) fn create(
+    device: &wgpu::Device,
+    asset_manager: &AssetManager,
+    pipeline_manager: &mut PipelineManager,
+    resource_manager: &Arc<GPUResourceManager>,
+    pbr_desc: &PipelineDesc,
+) {
+    let prepass_desc = DepthPrepassPipelineDesc::new(pbr_desc);
+    pipeline_manager
+        .add_pipeline(
+            "depth_prepass",
+            &prepass_desc.prepass,
+            vec!["pbr"],
+            device,
+            asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+
+    let mut depth_equal_desc = pbr_desc.clone();
+    let depth_state = depth_equal_desc.depth_state.as_mut().unwrap();
+    depth_state.depth_compare = wgpu::CompareFunction::Equal;
+    depth_state.depth_write_enabled = false;
+    pipeline_manager
+        .add_pipeline(
+            "pbr_depth_equal",
+            &depth_equal_desc,
+            vec!["depth_prepass"],
+            device,
+            asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+}
@@ -202,10 +202,8 @@ impl SimplePipelineDesc for CubeProjectionPipelineDesc {
     fn primitive_topology(&self) -> wgpu::PrimitiveTopology {
         wgpu::PrimitiveTopology::TriangleList
     }
-    fn color_states_desc(
-        &self,
-        _sc_desc: &wgpu::SwapChainDescriptor,
-    ) -> Vec<wgpu::ColorStateDescriptor> {
+    fn color_states_desc(&self, _formats: &[wgpu::TextureFormat]) -> Vec<wgpu::ColorStateDescriptor> {
+        // Always renders into the cube map's float texture.
         vec![wgpu::ColorStateDescriptor {
             format: wgpu::TextureFormat::Rgba32Float,
             color_blend: wgpu::BlendDescriptor::REPLACE,
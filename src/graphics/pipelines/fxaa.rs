@@ -0,0 +1,220 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        resources::{GPUResourceManager, RenderTarget},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// Runtime settings for fxaa. Insert this as a resource to change them.
+/// To turn fxaa off remove the "fxaa" pipeline with `PipelineManager::remove_pipeline`.
+#[derive(Debug, Clone, Copy)]
+pub struct FxaaConfig {
+    /// How much sub-pixel aliasing is removed. 0.0 is off and 1.0 is the softest.
+    pub subpixel_quality: f32,
+    /// The minimum contrast between neighbouring pixels needed to be treated as an edge.
+    pub edge_threshold: f32,
+    /// Skips dark areas where the contrast is below this value.
+    pub edge_threshold_min: f32,
+}
+
+impl Default for FxaaConfig {
+    fn default() -> Self {
+        Self {
+            subpixel_quality: 0.75,
+            edge_threshold: 0.166,
+            edge_threshold_min: 0.0833,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct FxaaUniform {
+    subpixel_quality: f32,
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    _padding: f32,
+}
+
+unsafe impl Zeroable for FxaaUniform {}
+unsafe impl Pod for FxaaUniform {}
+
+/// Reads the tonemapped frame with a full screen triangle and writes the anti-aliased result.
+pub struct FxaaPipelineDesc {
+    pub pipeline: PipelineDesc,
+}
+
+impl FxaaPipelineDesc {
+    pub fn new(output_format: wgpu::TextureFormat) -> Self {
+        let mut pipeline = PipelineDesc::default();
+        pipeline.shader = "core/shaders/fxaa.shader".to_string();
+        pipeline.color_states[0].format = output_format;
+        pipeline.layouts = vec!["fxaa_layout".to_string()];
+        pipeline.cull_mode = wgpu::CullMode::None;
+        Self { pipeline }
+    }
+}
+
+/// The intermediate target the hdr blit writes to when fxaa is on.
+pub struct FxaaPass {
+    pub target: RenderTarget,
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+}
+
+impl FxaaPass {
+    pub fn new(
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let layout = resource_manager.get_bind_group_layout("fxaa_layout").unwrap();
+        let target = RenderTarget::new(
+            device,
+            width as f32,
+            height as f32,
+            1,
+            1,
+            format,
+            wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        );
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<FxaaUniform>() as u64,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&target.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&target.texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+                },
+            ]),
+            label: None,
+        });
+
+        Self {
+            target,
+            bind_group,
+            uniform_buffer,
+        }
+    }
+
+    /// Anti-aliases `target` into `output`.
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        config: &FxaaConfig,
+        output: &wgpu::TextureView,
+        pipeline_manager: &PipelineManager,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let uniform = FxaaUniform {
+            subpixel_quality: config.subpixel_quality,
+            edge_threshold: config.edge_threshold,
+            edge_threshold_min: config.edge_threshold_min,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }]),
+            depth_stencil_attachment: None,
+        });
+
+        let fxaa_pipeline = pipeline_manager.get("fxaa", None).unwrap();
+        render_pass.set_pipeline(&fxaa_pipeline.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3 as u32, 0..1);
+    }
+}
+
+/// Creates the fxaa pipeline and inserts the `FxaaPass` and `FxaaConfig` resources.
+/// Note: This needs to be called after the hdr pipeline is created as fxaa runs after the hdr blit.
+pub fn create(resources: &mut Resources) {
+    let fxaa_pass = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let sc_desc = resources.get::<wgpu::SwapChainDescriptor>().unwrap();
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupLayoutEntry::new(
+                    0,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::Sampler { comparison: false },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    1,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        component_type: wgpu::TextureComponentType::Float,
+                        dimension: wgpu::TextureViewDimension::D2,
+                    },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    2,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<FxaaUniform>() as _,
+                        ),
+                    },
+                ),
+            ]),
+            label: Some(Cow::Borrowed("fxaa_layout")),
+        });
+        resource_manager.add_bind_group_layout("fxaa_layout", layout);
+
+        let fxaa_desc = FxaaPipelineDesc::new(sc_desc.format);
+        pipeline_manager
+            .add_pipeline(
+                "fxaa",
+                &fxaa_desc.pipeline,
+                vec!["hdr_blit"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        FxaaPass::new(
+            &device,
+            &resource_manager,
+            sc_desc.format,
+            sc_desc.width,
+            sc_desc.height,
+        )
+    };
+
+    resources.insert(fxaa_pass);
+    resources.insert(FxaaConfig::default());
+}
@@ -0,0 +1,83 @@
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{ComputePipelineDesc, PipelineManager},
+        resources::{GPUResourceManager, GpuDrivenRenderer},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+// Must match the local size in the gpu cull compute shader.
+pub(crate) const GPU_CULL_WORKGROUP_SIZE: u32 = 64;
+
+// How many draws the buffers hold before they grow.
+const INITIAL_DRAW_CAPACITY: u32 = 1024;
+
+pub fn create_gpu_cull_bindgroup_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let storage_entry = |binding, readonly| {
+        wgpu::BindGroupLayoutEntry::new(
+            binding,
+            wgpu::ShaderStage::COMPUTE,
+            wgpu::BindingType::StorageBuffer {
+                dynamic: false,
+                readonly,
+                min_binding_size: None,
+            },
+        )
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            // Frustum planes and draw count
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::COMPUTE,
+                wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: None,
+                },
+            ),
+            // Draws
+            storage_entry(1, true),
+            // Indirect draw arguments
+            storage_entry(2, false),
+        ]),
+        label: Some(Cow::Borrowed("gpu_cull_layout")),
+    })
+}
+
+/// Creates the compute pipeline that culls draws on the GPU and the `GpuDrivenRenderer` resource.
+pub fn create(resources: &mut Resources) {
+    let gpu_driven_renderer = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+        resource_manager
+            .add_bind_group_layout("gpu_cull_layout", create_gpu_cull_bindgroup_layout(&device));
+        let layout = resource_manager
+            .get_bind_group_layout("gpu_cull_layout")
+            .unwrap();
+        let gpu_driven_renderer = GpuDrivenRenderer::new(&device, layout, INITIAL_DRAW_CAPACITY);
+
+        let mut cull_desc = ComputePipelineDesc::new("core/shaders/culling/gpu_cull.shader");
+        cull_desc.layouts = vec!["gpu_cull_layout".to_string()];
+        pipeline_manager
+            .add_compute_pipeline(
+                "gpu_cull",
+                &cull_desc,
+                vec![],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        gpu_driven_renderer
+    };
+
+    resources.insert(gpu_driven_renderer);
+}
@@ -0,0 +1,207 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        resources::{GPUResourceManager, HdrFramebuffer},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// The curve used to map hdr colors into the 0 to 1 range of the swap chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapMode {
+    Aces,
+    Reinhard,
+    Uncharted2,
+    /// Colors above 1.0 are clipped.
+    Passthrough,
+}
+
+/// Runtime settings for tone mapping. Insert this as a resource to change them.
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMapConfig {
+    pub mode: ToneMapMode,
+    /// The hdr color is multiplied by this before the curve is applied.
+    pub exposure: f32,
+}
+
+impl Default for ToneMapConfig {
+    fn default() -> Self {
+        Self {
+            mode: ToneMapMode::Aces,
+            exposure: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ToneMapUniform {
+    mode: u32,
+    exposure: f32,
+    srgb_encode: u32,
+    _padding: u32,
+}
+
+unsafe impl Zeroable for ToneMapUniform {}
+unsafe impl Pod for ToneMapUniform {}
+
+/// Tone maps the hdr framebuffer with a full screen triangle and writes it to the swap chain.
+/// Gamma correction is done here as well, by the hardware for sRGB outputs and in the shader otherwise.
+pub struct ToneMapPipelineDesc {
+    pub pipeline: PipelineDesc,
+}
+
+impl ToneMapPipelineDesc {
+    pub fn new(output_format: wgpu::TextureFormat) -> Self {
+        let mut pipeline = PipelineDesc::default();
+        pipeline.shader = "core/shaders/hdr_blit.shader".to_string();
+        pipeline.color_states[0].format = output_format;
+        pipeline.layouts = vec![
+            "hdr_texture_layout".to_string(),
+            "tone_map_layout".to_string(),
+        ];
+        pipeline.cull_mode = wgpu::CullMode::None;
+        Self { pipeline }
+    }
+}
+
+/// The uniform used by the hdr blit to tone map.
+pub struct ToneMapPass {
+    pub(crate) bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    srgb_encode: bool,
+}
+
+impl ToneMapPass {
+    pub fn new(
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        output_format: wgpu::TextureFormat,
+    ) -> Self {
+        let layout = resource_manager
+            .get_bind_group_layout("tone_map_layout")
+            .unwrap();
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<ToneMapUniform>() as u64,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: Cow::Borrowed(&[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+            }]),
+            label: None,
+        });
+
+        // sRGB textures convert from linear when they're written to.
+        let srgb_encode = match output_format {
+            wgpu::TextureFormat::Bgra8UnormSrgb | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            _ => true,
+        };
+
+        Self {
+            bind_group,
+            uniform_buffer,
+            srgb_encode,
+        }
+    }
+
+    /// Uploads the config, call this before the hdr blit is drawn.
+    pub fn update(&self, queue: &wgpu::Queue, config: &ToneMapConfig) {
+        let mode = match config.mode {
+            ToneMapMode::Aces => 0,
+            ToneMapMode::Reinhard => 1,
+            ToneMapMode::Uncharted2 => 2,
+            ToneMapMode::Passthrough => 3,
+        };
+        let uniform = ToneMapUniform {
+            mode,
+            exposure: config.exposure,
+            srgb_encode: self.srgb_encode as u32,
+            _padding: 0,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+}
+
+/// A sampler and a 2D float texture, used to read hdr textures from fragment or compute shaders.
+pub fn create_hdr_texture_bindgroup_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Borrowed(&[
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::FRAGMENT | wgpu::ShaderStage::COMPUTE,
+                wgpu::BindingType::Sampler { comparison: false },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                1,
+                wgpu::ShaderStage::FRAGMENT | wgpu::ShaderStage::COMPUTE,
+                wgpu::BindingType::SampledTexture {
+                    multisampled: false,
+                    component_type: wgpu::TextureComponentType::Float,
+                    dimension: wgpu::TextureViewDimension::D2,
+                },
+            ),
+        ]),
+        label: Some(Cow::Borrowed("hdr_texture_layout")),
+    })
+}
+
+/// Creates the hdr framebuffer and the pipeline that tone maps it to the swap chain.
+/// Inserts the `HdrFramebuffer`, `ToneMapPass` and `ToneMapConfig` resources.
+pub fn create(resources: &mut Resources) {
+    let (hdr_framebuffer, tone_map_pass) = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let sc_desc = resources.get::<wgpu::SwapChainDescriptor>().unwrap();
+
+        let hdr_texture_layout = create_hdr_texture_bindgroup_layout(&device);
+        let hdr_framebuffer =
+            HdrFramebuffer::new(&device, &hdr_texture_layout, sc_desc.width, sc_desc.height);
+        resource_manager.add_bind_group_layout("hdr_texture_layout", hdr_texture_layout);
+
+        let tone_map_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Borrowed(&[wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<ToneMapUniform>() as _
+                    ),
+                },
+            )]),
+            label: Some(Cow::Borrowed("tone_map_layout")),
+        });
+        resource_manager.add_bind_group_layout("tone_map_layout", tone_map_layout);
+
+        // Post processing passes in hdr need to run before this, fxaa is the only one that runs after.
+        let blit_desc = ToneMapPipelineDesc::new(sc_desc.format);
+        pipeline_manager
+            .add_pipeline(
+                "hdr_blit",
+                &blit_desc.pipeline,
+                vec!["pbr", "deferred_lighting", "debug_draw", "taa", "bloom_composite"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        let tone_map_pass = ToneMapPass::new(&device, &resource_manager, sc_desc.format);
+        (hdr_framebuffer, tone_map_pass)
+    };
+
+    resources.insert(hdr_framebuffer);
+    resources.insert(tone_map_pass);
+    resources.insert(ToneMapConfig::default());
+}
@@ -0,0 +1,151 @@
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{ComputePipelineDesc, PipelineManager},
+        resources::{GPUResourceManager, HiZCulling, HI_Z_FORMAT},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+fn hi_z_image_entry(binding: u32, readonly: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry::new(
+        binding,
+        wgpu::ShaderStage::COMPUTE,
+        wgpu::BindingType::StorageTexture {
+            dimension: wgpu::TextureViewDimension::D2,
+            format: HI_Z_FORMAT,
+            readonly,
+        },
+    )
+}
+
+fn sampled_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry::new(
+        binding,
+        wgpu::ShaderStage::COMPUTE,
+        wgpu::BindingType::SampledTexture {
+            multisampled: false,
+            component_type: wgpu::TextureComponentType::Float,
+            dimension: wgpu::TextureViewDimension::D2,
+        },
+    )
+}
+
+fn sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry::new(
+        binding,
+        wgpu::ShaderStage::COMPUTE,
+        wgpu::BindingType::Sampler { comparison: false },
+    )
+}
+
+/// Returns the layouts of the pass that copies the depth buffer into the hi-z buffer,
+/// the pass that builds each mip and the cull pass.
+pub fn create_hi_z_bindgroup_layouts(
+    device: &wgpu::Device,
+) -> (
+    wgpu::BindGroupLayout,
+    wgpu::BindGroupLayout,
+    wgpu::BindGroupLayout,
+) {
+    let copy_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            // Depth buffer
+            sampled_entry(0),
+            sampler_entry(1),
+            // First hi-z mip
+            hi_z_image_entry(2, false),
+        ]),
+        label: Some(Cow::Borrowed("hi_z_copy_layout")),
+    });
+
+    let downsample_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![hi_z_image_entry(0, true), hi_z_image_entry(1, false)]),
+        label: Some(Cow::Borrowed("hi_z_downsample_layout")),
+    });
+
+    let storage_entry = |binding, readonly| {
+        wgpu::BindGroupLayoutEntry::new(
+            binding,
+            wgpu::ShaderStage::COMPUTE,
+            wgpu::BindingType::StorageBuffer {
+                dynamic: false,
+                readonly,
+                min_binding_size: None,
+            },
+        )
+    };
+    let cull_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            // View projection and counts
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::COMPUTE,
+                wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: None,
+                },
+            ),
+            // Spheres
+            storage_entry(1, true),
+            // Visibility bits
+            storage_entry(2, false),
+            // Every hi-z mip
+            sampled_entry(3),
+            sampler_entry(4),
+        ]),
+        label: Some(Cow::Borrowed("hi_z_cull_layout")),
+    });
+
+    (copy_layout, downsample_layout, cull_layout)
+}
+
+/// Creates the compute pipelines that build the hi-z buffer and cull against it, and the `HiZCulling` resource.
+pub fn create(resources: &mut Resources) {
+    let hi_z_culling = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+        let (copy_layout, downsample_layout, cull_layout) = create_hi_z_bindgroup_layouts(&device);
+        resource_manager.add_bind_group_layout("hi_z_copy_layout", copy_layout);
+        resource_manager.add_bind_group_layout("hi_z_downsample_layout", downsample_layout);
+        resource_manager.add_bind_group_layout("hi_z_cull_layout", cull_layout);
+
+        let pipelines = [
+            ("hi_z_copy", "hi_z_copy_layout"),
+            ("hi_z_downsample", "hi_z_downsample_layout"),
+            ("hi_z_cull", "hi_z_cull_layout"),
+        ];
+        for (index, (name, layout)) in pipelines.iter().enumerate() {
+            let mut desc =
+                ComputePipelineDesc::new(format!("core/shaders/culling/{}.shader", name));
+            desc.layouts = vec![layout.to_string()];
+            let dependencies = if index > 0 {
+                vec![pipelines[index - 1].0]
+            } else {
+                vec![]
+            };
+            pipeline_manager
+                .add_compute_pipeline(
+                    name,
+                    &desc,
+                    dependencies,
+                    &device,
+                    &asset_manager,
+                    resource_manager.clone(),
+                )
+                .unwrap();
+        }
+
+        let cull_layout = resource_manager
+            .get_bind_group_layout("hi_z_cull_layout")
+            .unwrap();
+        HiZCulling::new(&device, cull_layout)
+    };
+
+    resources.insert(hi_z_culling);
+}
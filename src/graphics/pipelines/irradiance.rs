@@ -65,12 +65,14 @@ pub fn create(resources: &Resources, format: wgpu::TextureFormat) {
         .vertex_state
         .set_index_format(wgpu::IndexFormat::Uint16);
 
-    pipeline_manager.add_pipeline(
-        "irradiance",
-        &irradiance_desc,
-        vec![],
-        &device,
-        &asset_manager,
-        resource_manager.clone(),
-    );
+    pipeline_manager
+        .add_pipeline(
+            "irradiance",
+            &irradiance_desc,
+            vec![],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
 }
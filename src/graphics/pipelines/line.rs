@@ -41,12 +41,9 @@ impl SimplePipelineDesc for LinePipelineDesc {
     fn primitive_topology(&self) -> wgpu::PrimitiveTopology {
         wgpu::PrimitiveTopology::LineList
     }
-    fn color_states_desc(
-        &self,
-        sc_desc: &wgpu::SwapChainDescriptor,
-    ) -> Vec<wgpu::ColorStateDescriptor> {
+    fn color_states_desc(&self, formats: &[wgpu::TextureFormat]) -> Vec<wgpu::ColorStateDescriptor> {
         vec![wgpu::ColorStateDescriptor {
-            format: sc_desc.format,
+            format: formats[0],
             color_blend: wgpu::BlendDescriptor::REPLACE,
             alpha_blend: wgpu::BlendDescriptor::REPLACE,
             write_mask: wgpu::ColorWrite::ALL,
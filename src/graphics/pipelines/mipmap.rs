@@ -82,14 +82,16 @@ pub fn create(
         mipmap_desc.color_states[0].format = format;
         mipmap_desc.cull_mode = wgpu::CullMode::None;
         mipmap_desc.layouts = vec!["mipmap".to_string()];
-        pipeline_manager.add_pipeline(
-            "mipmap",
-            &mipmap_desc,
-            vec![],
-            &device,
-            &asset_manager,
-            resource_manager.clone(),
-        );
+        pipeline_manager
+            .add_pipeline(
+                "mipmap",
+                &mipmap_desc,
+                vec![],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
         pipeline = pipeline_manager.get("mipmap", None);
     }
 
@@ -6,6 +6,46 @@ use nalgebra_glm::{Mat4, Vec4};
 
 pub mod pbr;
 
+pub mod depth_prepass;
+
+pub mod deferred;
+
+pub mod decal;
+
+pub mod ssao;
+
+pub mod motion_vectors;
+
+pub mod hdr;
+
+pub mod bloom;
+
+pub mod fxaa;
+
+pub mod taa;
+
+pub mod debug_draw;
+
+#[cfg(feature = "physics_debug")]
+pub mod physics_debug;
+
+pub mod wireframe;
+
+pub mod skinning;
+
+pub mod morph;
+
+pub mod gpu_cull;
+
+pub mod hi_z;
+
+pub mod particles;
+
+pub mod water;
+
+pub mod sprite;
+pub mod text;
+
 // mod line;
 // pub(crate) use line::LinePipelineDesc;
 
@@ -29,6 +69,19 @@ pub struct GlobalUniform {
     pub camera_pos: Vec4,
     pub view: Mat4,
     pub projection: Mat4,
+    /// Seconds since the application started, for animated shaders. Written by the `tick_global_uniforms` system.
+    pub time: f32,
+    pub delta_time: f32,
+    pub _pad: [f32; 2],
+    /// Written by the `tick_fog` system.
+    pub fog: FogUniform,
+}
+
+impl GlobalUniform {
+    /// Where `fog` starts in the buffer.
+    pub const FOG_OFFSET: u64 = (std::mem::size_of::<GlobalUniform>() - std::mem::size_of::<FogUniform>()) as u64;
+    /// Where `time` starts in the buffer, everything before it is written by the camera.
+    pub const TIME_OFFSET: u64 = Self::FOG_OFFSET - 4 * std::mem::size_of::<f32>() as u64;
 }
 
 impl Default for GlobalUniform {
@@ -38,6 +91,10 @@ impl Default for GlobalUniform {
             camera_pos: Vec4::zeros(),
             view: Mat4::identity(),
             projection: Mat4::identity(),
+            time: 0.0,
+            delta_time: 0.0,
+            _pad: [0.0; 2],
+            fog: FogParams::default().uniform(),
         }
     }
 }
@@ -45,6 +102,79 @@ impl Default for GlobalUniform {
 unsafe impl Zeroable for GlobalUniform {}
 unsafe impl Pod for GlobalUniform {}
 
+/// How fog thickens with the distance from the camera, see `FogParams`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FogMode {
+    /// Fades in between `start` and `end`.
+    Linear,
+    /// `1 - e^(-density * depth)` of the color is fog.
+    Exponential,
+    /// `1 - e^(-(density * depth)^2)` of the color is fog, clear near the camera and thicker further away.
+    ExponentialSquared,
+}
+
+/// Atmospheric fog blended over forward rendered meshes by their camera space depth.
+/// Insert this as a resource to change it, the default has no density so nothing is fogged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FogParams {
+    /// The rgb color fogged pixels fade towards, alpha scales the amount of fog.
+    pub color: Vec4,
+    /// Used by the exponential modes.
+    pub density: f32,
+    /// Used by `FogMode::Linear`.
+    pub start: f32,
+    pub end: f32,
+    pub mode: FogMode,
+}
+
+impl Default for FogParams {
+    fn default() -> Self {
+        Self {
+            color: Vec4::new(0.5, 0.6, 0.7, 1.0),
+            density: 0.0,
+            start: 0.0,
+            end: 0.0,
+            mode: FogMode::Exponential,
+        }
+    }
+}
+
+impl FogParams {
+    /// How much of a pixel `depth` away from the camera is fog, from 0.0 to 1.0.
+    /// Matches `fog_amount` in `library/fog.glsl`.
+    pub fn amount(&self, depth: f32) -> f32 {
+        let amount = match self.mode {
+            FogMode::Linear => (depth - self.start) / (self.end - self.start).max(0.0001),
+            FogMode::Exponential => 1.0 - (-self.density * depth).exp(),
+            FogMode::ExponentialSquared => 1.0 - (-(self.density * depth).powi(2)).exp(),
+        };
+        amount.max(0.0).min(1.0) * self.color.w
+    }
+
+    pub fn uniform(&self) -> FogUniform {
+        let mode = match self.mode {
+            FogMode::Linear => 0.0,
+            FogMode::Exponential => 1.0,
+            FogMode::ExponentialSquared => 2.0,
+        };
+        FogUniform {
+            color: self.color,
+            params: Vec4::new(self.density, self.start, self.end, mode),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FogUniform {
+    pub color: Vec4,
+    // (density, start, end, mode)
+    pub params: Vec4,
+}
+
+unsafe impl Zeroable for FogUniform {}
+unsafe impl Pod for FogUniform {}
+
 
 // TODO: We can support more lights, but a uniform buffer probably isn't the best.
 // We likely want to use wgpu's belt buffer.
@@ -114,3 +244,89 @@ impl Default for LightingUniform {
 
 unsafe impl Zeroable for LightingUniform {}
 unsafe impl Pod for LightingUniform {}
+
+pub const MAX_SPOT_LIGHTS: usize = 16;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+    // w is the range.
+    pub position: Vec4,
+    pub direction: Vec4,
+    // w is the intensity.
+    pub color: Vec4,
+    // x is the cosine of the inner angle and y is the cosine of the outer angle.
+    pub cone: Vec4,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        Self {
+            position: Vec4::zeros(),
+            direction: Vec4::zeros(),
+            color: Vec4::zeros(),
+            cone: Vec4::zeros(),
+        }
+    }
+}
+
+/// Every light in the scene, written to the "lights" buffer by the `upload_lights` system.
+/// Each light struct is a multiple of 16 bytes so the layout matches std430 in the shaders.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LightUniformBuffer {
+    // Number of (directional, point, spot) lights.
+    pub light_count: [u32; 4],
+    pub directional_lights: [DirectionalLight; 4],
+    pub point_lights: [PointLight; MAX_LIGHTS],
+    pub spot_lights: [SpotLight; MAX_SPOT_LIGHTS],
+}
+
+impl Default for LightUniformBuffer {
+    fn default() -> Self {
+        Self {
+            light_count: [0; 4],
+            directional_lights: [DirectionalLight::default(); 4],
+            point_lights: [PointLight::default(); MAX_LIGHTS],
+            spot_lights: [SpotLight::default(); MAX_SPOT_LIGHTS],
+        }
+    }
+}
+
+unsafe impl Zeroable for LightUniformBuffer {}
+unsafe impl Pod for LightUniformBuffer {}
+
+#[cfg(test)]
+mod tests {
+    use super::{FogMode, FogParams, GlobalUniform};
+
+    #[test]
+    fn should_pack_fog_after_time() {
+        // The camera matrices and position come before the time.
+        assert_eq!(GlobalUniform::TIME_OFFSET, 208);
+        assert_eq!(GlobalUniform::FOG_OFFSET, 224);
+        assert_eq!(std::mem::size_of::<GlobalUniform>() % 16, 0);
+    }
+
+    #[test]
+    fn should_thicken_fog_with_depth() {
+        let mut fog = FogParams {
+            start: 10.0,
+            end: 20.0,
+            mode: FogMode::Linear,
+            ..FogParams::default()
+        };
+        assert_eq!(fog.amount(5.0), 0.0);
+        assert_eq!(fog.amount(15.0), 0.5);
+        assert_eq!(fog.amount(30.0), 1.0);
+
+        fog.mode = FogMode::Exponential;
+        assert_eq!(fog.amount(100.0), 0.0);
+        fog.density = 0.1;
+        assert!((fog.amount(10.0) - (1.0 - (-1.0f32).exp())).abs() < 1e-6);
+
+        // Squared fog is thinner than exponential fog closer than 1 / density.
+        fog.mode = FogMode::ExponentialSquared;
+        assert!(fog.amount(5.0) < 1.0 - (-0.5f32).exp());
+    }
+}
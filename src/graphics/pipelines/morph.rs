@@ -0,0 +1,65 @@
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{ComputePipelineDesc, PipelineManager},
+        resources::GPUResourceManager,
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+// Must match the local size in the morph compute shader.
+pub(crate) const MORPH_WORKGROUP_SIZE: u32 = 64;
+
+pub fn create_morph_bindgroup_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let storage_entry = |binding, readonly| {
+        wgpu::BindGroupLayoutEntry::new(
+            binding,
+            wgpu::ShaderStage::COMPUTE,
+            wgpu::BindingType::StorageBuffer {
+                dynamic: false,
+                readonly,
+                min_binding_size: None,
+            },
+        )
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            // Weights
+            storage_entry(0, true),
+            // Base vertices
+            storage_entry(1, true),
+            // Morph target deltas
+            storage_entry(2, true),
+            // Blended vertices
+            storage_entry(3, false),
+        ]),
+        label: Some(Cow::Borrowed("morph_layout")),
+    })
+}
+
+/// Creates the compute pipeline that blends morph targets.
+/// Note: Like skinning this needs to be created before the pipelines that draw meshes.
+pub fn create(resources: &Resources) {
+    let asset_manager = resources.get::<AssetManager>().unwrap();
+    let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+    let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+    let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+    resource_manager.add_bind_group_layout("morph_layout", create_morph_bindgroup_layout(&device));
+
+    let mut morph_desc = ComputePipelineDesc::new("core/shaders/morph/morph.shader");
+    morph_desc.layouts = vec!["morph_layout".to_string()];
+    pipeline_manager
+        .add_compute_pipeline(
+            "morph",
+            &morph_desc,
+            vec![],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+}
@@ -0,0 +1,361 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+use nalgebra_glm::{Mat4, Vec2, Vec4};
+
+use crate::{
+    assets::mesh::MeshVertexData,
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        renderer::DEPTH_FORMAT,
+        resources::GPUResourceManager,
+    },
+    scene::components::transform::LocalUniform,
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// Motion vectors store how far each pixel moved since the last frame in uv units.
+pub const MOTION_VECTOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+
+/// Turns the motion vector pass on or off, it's off by default.
+/// Turn it on for effects that read `MotionVectorPass::bind_group`, like temporal anti aliasing or motion blur.
+pub struct MotionVectorRendering(pub bool);
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct MotionVectorGlobals {
+    view_projection: Mat4,
+    previous_view_projection: Mat4,
+}
+
+unsafe impl Zeroable for MotionVectorGlobals {}
+unsafe impl Pod for MotionVectorGlobals {}
+
+/// An entity's transform this frame and last frame, one is stored per draw and indexed by the instance index.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MotionVectorLocals {
+    pub current: LocalUniform,
+    pub previous: LocalUniform,
+}
+
+unsafe impl Zeroable for MotionVectorLocals {}
+unsafe impl Pod for MotionVectorLocals {}
+
+/// How far a point moved across the screen in uv units, given it's clip space position this frame and last frame.
+/// Must match motion_vectors.frag.glsl.
+pub fn velocity(current: &Vec4, previous: &Vec4) -> Vec2 {
+    let current = current.xy() / current.w;
+    let previous = previous.xy() / previous.w;
+    // Uvs start at the top left so y is flipped.
+    (current - previous).component_mul(&Vec2::new(0.5, -0.5))
+}
+
+/// A sampler and the motion vector texture, used by passes that read the motion vectors.
+pub fn create_motion_vector_texture_bindgroup_layout(
+    device: &wgpu::Device,
+) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Borrowed(&[
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::Sampler { comparison: false },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                1,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::SampledTexture {
+                    multisampled: false,
+                    component_type: wgpu::TextureComponentType::Float,
+                    dimension: wgpu::TextureViewDimension::D2,
+                },
+            ),
+        ]),
+        label: Some(Cow::Borrowed("motion_vector_texture_layout")),
+    })
+}
+
+/// Renders the screen space velocity of every mesh into an `RG16Float` texture.
+/// Pixels no mesh covers are left at zero.
+pub struct MotionVectorPass {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    // The pass depth tests against it's own depth buffer as the main one may be multisampled.
+    depth_texture: wgpu::Texture,
+    pub(crate) depth_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    /// Binds the motion vectors with the "motion_vector_texture_layout".
+    pub bind_group: wgpu::BindGroup,
+
+    globals_buffer: wgpu::Buffer,
+    pub(crate) globals_bind_group: wgpu::BindGroup,
+    locals_buffer: Option<wgpu::Buffer>,
+    locals_bind_group: Option<wgpu::BindGroup>,
+    // How many `MotionVectorLocals` fit in `locals_buffer`.
+    capacity: usize,
+    // None until the first frame has been rendered.
+    previous_view_projection: Option<Mat4>,
+
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MotionVectorPass {
+    pub fn new(
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: MOTION_VECTOR_FORMAT,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+            label: Some("motion_vectors"),
+        });
+        let view = texture.create_default_view();
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            label: Some("motion_vector_depth"),
+        });
+        let depth_view = depth_texture.create_default_view();
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+        let texture_layout = resource_manager
+            .get_bind_group_layout("motion_vector_texture_layout")
+            .unwrap();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &texture_layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ]),
+            label: None,
+        });
+
+        let globals_layout = resource_manager
+            .get_bind_group_layout("motion_vector_globals")
+            .unwrap();
+        let globals_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("motion_vector_globals"),
+            size: std::mem::size_of::<MotionVectorGlobals>() as u64,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let globals_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &globals_layout,
+            entries: Cow::Borrowed(&[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(globals_buffer.slice(..)),
+            }]),
+            label: None,
+        });
+
+        Self {
+            texture,
+            view,
+            depth_texture,
+            depth_view,
+            sampler,
+            bind_group,
+            globals_buffer,
+            globals_bind_group,
+            locals_buffer: None,
+            locals_bind_group: None,
+            capacity: 0,
+            previous_view_projection: None,
+            width,
+            height,
+        }
+    }
+
+    /// Makes sure the locals buffer holds at least `count` transforms, it grows to the next power of two when it's too small.
+    pub(crate) fn reserve(
+        &mut self,
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        count: usize,
+    ) {
+        if self.locals_buffer.is_some() && self.capacity >= count {
+            return;
+        }
+
+        self.capacity = count.max(1).next_power_of_two();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("motion_vector_locals"),
+            size: (self.capacity * std::mem::size_of::<MotionVectorLocals>()) as u64,
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = resource_manager
+            .get_bind_group_layout("motion_vector_locals")
+            .unwrap();
+        self.locals_bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: Cow::Borrowed(&[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+            }]),
+            label: None,
+        }));
+        self.locals_buffer = Some(buffer);
+    }
+
+    /// Uploads the camera and the transforms of this frame's draws.
+    /// The view projection is remembered for the next frame, on the first frame it's also used as the previous one.
+    /// Panics if `reserve` hasn't been called with at least `locals.len()`.
+    pub(crate) fn write(
+        &mut self,
+        queue: &wgpu::Queue,
+        view_projection: Mat4,
+        locals: &[MotionVectorLocals],
+    ) {
+        let globals = MotionVectorGlobals {
+            view_projection,
+            previous_view_projection: self.previous_view_projection.unwrap_or(view_projection),
+        };
+        queue.write_buffer(&self.globals_buffer, 0, bytemuck::bytes_of(&globals));
+        if !locals.is_empty() {
+            queue.write_buffer(
+                self.locals_buffer.as_ref().unwrap(),
+                0,
+                bytemuck::cast_slice(locals),
+            );
+        }
+        self.previous_view_projection = Some(view_projection);
+    }
+
+    /// Panics if `reserve` hasn't been called yet.
+    pub(crate) fn locals_bind_group(&self) -> &wgpu::BindGroup {
+        self.locals_bind_group.as_ref().unwrap()
+    }
+}
+
+/// Creates the motion vector pipeline and inserts the `MotionVectorPass` and `MotionVectorRendering` resources.
+pub fn create(resources: &mut Resources) {
+    let motion_vector_pass = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let sc_desc = resources.get::<wgpu::SwapChainDescriptor>().unwrap();
+
+        let globals_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Borrowed(&[wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::VERTEX,
+                wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<MotionVectorGlobals>() as _,
+                    ),
+                },
+            )]),
+            label: Some(Cow::Borrowed("motion_vector_globals")),
+        });
+        resource_manager.add_bind_group_layout("motion_vector_globals", globals_layout);
+        resource_manager.add_storage_bind_group_layout(
+            &device,
+            "motion_vector_locals",
+            wgpu::ShaderStage::VERTEX,
+            &[(0, true)],
+        );
+        resource_manager.add_bind_group_layout(
+            "motion_vector_texture_layout",
+            create_motion_vector_texture_bindgroup_layout(&device),
+        );
+
+        let mut motion_vector_desc = PipelineDesc::default();
+        motion_vector_desc.shader = "core/shaders/motion_vectors.shader".to_string();
+        motion_vector_desc.color_states[0].format = MOTION_VECTOR_FORMAT;
+        motion_vector_desc.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        });
+        motion_vector_desc.layouts = vec![
+            "motion_vector_globals".to_string(),
+            "motion_vector_locals".to_string(),
+        ];
+        motion_vector_desc.cull_mode = wgpu::CullMode::Back;
+        // Only the positions of the mesh vertices are read.
+        motion_vector_desc
+            .vertex_state
+            .set_index_format(wgpu::IndexFormat::Uint32)
+            .new_buffer_descriptor(
+                std::mem::size_of::<MeshVertexData>() as wgpu::BufferAddress,
+                wgpu::InputStepMode::Vertex,
+                wgpu::vertex_attr_array![0 => Float3].to_vec(),
+            );
+
+        pipeline_manager
+            .add_pipeline(
+                "motion_vectors",
+                &motion_vector_desc,
+                vec![],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        MotionVectorPass::new(&device, &resource_manager, sc_desc.width, sc_desc.height)
+    };
+
+    resources.insert(motion_vector_pass);
+    resources.insert(MotionVectorRendering(false));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::velocity;
+    use nalgebra_glm::{Vec2, Vec4};
+
+    #[test]
+    fn should_measure_velocity_in_uv_units() {
+        // Moving right by half the screen and up by a quarter of it.
+        let previous = Vec4::new(0.0, 0.0, 0.5, 1.0);
+        let current = Vec4::new(1.0, 0.5, 0.5, 1.0);
+        assert_eq!(velocity(&current, &previous), Vec2::new(0.5, -0.25));
+
+        // The perspective divide is applied before comparing.
+        let current = Vec4::new(2.0, 1.0, 1.0, 2.0);
+        assert_eq!(velocity(&current, &previous), Vec2::new(0.5, -0.25));
+        assert_eq!(velocity(&previous, &previous), Vec2::zeros());
+    }
+}
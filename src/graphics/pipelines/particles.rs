@@ -0,0 +1,229 @@
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{ComputePipelineDesc, PipelineDesc, PipelineManager},
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::{GPUResourceManager, VelocityFields, MAX_VELOCITY_FIELDS},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+// Must match the local size in the particle compute shaders.
+pub(crate) const PARTICLE_WORKGROUP_SIZE: u32 = 64;
+
+fn storage_entry(
+    binding: u32,
+    stages: wgpu::ShaderStage,
+    readonly: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry::new(
+        binding,
+        stages,
+        wgpu::BindingType::StorageBuffer {
+            dynamic: false,
+            readonly,
+            min_binding_size: None,
+        },
+    )
+}
+
+fn uniform_entry(
+    binding: u32,
+    stages: wgpu::ShaderStage,
+    dynamic: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry::new(
+        binding,
+        stages,
+        wgpu::BindingType::UniformBuffer {
+            dynamic,
+            min_binding_size: None,
+        },
+    )
+}
+
+/// The layout of the `VelocityFields` bound to the simulation, a uniform, a sampler and a 3D texture per field.
+pub fn create_velocity_field_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let mut entries = vec![
+        uniform_entry(0, wgpu::ShaderStage::COMPUTE, false),
+        wgpu::BindGroupLayoutEntry::new(
+            1,
+            wgpu::ShaderStage::COMPUTE,
+            wgpu::BindingType::Sampler { comparison: false },
+        ),
+    ];
+    for i in 0..MAX_VELOCITY_FIELDS as u32 {
+        entries.push(wgpu::BindGroupLayoutEntry::new(
+            2 + i,
+            wgpu::ShaderStage::COMPUTE,
+            wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                component_type: wgpu::TextureComponentType::Float,
+                dimension: wgpu::TextureViewDimension::D3,
+            },
+        ));
+    }
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(entries),
+        label: Some(Cow::Borrowed("particle_velocity_layout")),
+    })
+}
+
+pub fn create_particle_bindgroup_layouts(
+    device: &wgpu::Device,
+) -> (
+    wgpu::BindGroupLayout,
+    wgpu::BindGroupLayout,
+    wgpu::BindGroupLayout,
+) {
+    let simulate_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            // Particle settings
+            uniform_entry(0, wgpu::ShaderStage::COMPUTE, false),
+            // Particles
+            storage_entry(1, wgpu::ShaderStage::COMPUTE, false),
+            // Sort keys
+            storage_entry(2, wgpu::ShaderStage::COMPUTE, false),
+            // Indirect draw arguments
+            storage_entry(3, wgpu::ShaderStage::COMPUTE, false),
+        ]),
+        label: Some(Cow::Borrowed("particle_simulate_layout")),
+    });
+
+    let sort_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            // The sort step, offset for each pass
+            uniform_entry(0, wgpu::ShaderStage::COMPUTE, true),
+            // Sort keys
+            storage_entry(1, wgpu::ShaderStage::COMPUTE, false),
+        ]),
+        label: Some(Cow::Borrowed("particle_sort_layout")),
+    });
+
+    let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            // Particle settings
+            uniform_entry(0, wgpu::ShaderStage::VERTEX, false),
+            // Particles
+            storage_entry(1, wgpu::ShaderStage::VERTEX, true),
+            // Sorted particle indices
+            storage_entry(2, wgpu::ShaderStage::VERTEX, true),
+        ]),
+        label: Some(Cow::Borrowed("particle_render_layout")),
+    });
+
+    (simulate_layout, sort_layout, render_layout)
+}
+
+/// Creates the compute pipelines that simulate and sort particles and the pipeline that draws them.
+pub fn create(resources: &mut Resources) {
+    let velocity_fields = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let queue = resources.get::<Arc<wgpu::Queue>>().unwrap();
+
+        let (simulate_layout, sort_layout, render_layout) =
+            create_particle_bindgroup_layouts(&device);
+        resource_manager.add_bind_group_layout("particle_simulate_layout", simulate_layout);
+        resource_manager.add_bind_group_layout("particle_sort_layout", sort_layout);
+        resource_manager.add_bind_group_layout("particle_render_layout", render_layout);
+        resource_manager.add_bind_group_layout(
+            "particle_velocity_layout",
+            create_velocity_field_layout(&device),
+        );
+
+        create_pipelines(
+            &device,
+            &asset_manager,
+            &mut pipeline_manager,
+            &resource_manager,
+        );
+
+        let velocity_layout = resource_manager
+            .get_bind_group_layout("particle_velocity_layout")
+            .unwrap();
+        VelocityFields::new(&device, &queue, &velocity_layout)
+    };
+
+    resources.insert(velocity_fields);
+}
+
+fn create_pipelines(
+    device: &wgpu::Device,
+    asset_manager: &AssetManager,
+    pipeline_manager: &mut PipelineManager,
+    resource_manager: &Arc<GPUResourceManager>,
+) {
+    let mut simulate_desc = ComputePipelineDesc::new("core/shaders/particles/simulate.shader");
+    simulate_desc.layouts = vec![
+        "particle_simulate_layout".to_string(),
+        "particle_velocity_layout".to_string(),
+    ];
+    pipeline_manager
+        .add_compute_pipeline(
+            "particles_simulate",
+            &simulate_desc,
+            vec![],
+            device,
+            asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+
+    let mut sort_desc = ComputePipelineDesc::new("core/shaders/particles/sort.shader");
+    sort_desc.layouts = vec!["particle_sort_layout".to_string()];
+    pipeline_manager
+        .add_compute_pipeline(
+            "particles_sort",
+            &sort_desc,
+            vec!["particles_simulate"],
+            device,
+            asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+
+    let alpha_blend = wgpu::BlendDescriptor {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+        operation: wgpu::BlendOperation::Add,
+    };
+    let mut render_desc = PipelineDesc::default();
+    render_desc.shader = "core/shaders/particles/particles.shader".to_string();
+    render_desc.color_states[0].format = HDR_FORMAT;
+    render_desc.color_states[0].color_blend = alpha_blend.clone();
+    render_desc.color_states[0].alpha_blend = alpha_blend;
+    // Particles are hidden by the scene but don't hide each other, they're drawn back to front instead.
+    render_desc.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Less,
+        stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+        stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+        stencil_read_mask: 0,
+        stencil_write_mask: 0,
+    });
+    render_desc.layouts = vec!["globals".to_string(), "particle_render_layout".to_string()];
+    render_desc.cull_mode = wgpu::CullMode::None;
+
+    pipeline_manager
+        .add_pipeline(
+            "particles",
+            &render_desc,
+            vec![
+                "pbr",
+                "pbr_transparent",
+                "deferred_lighting",
+                "particles_sort",
+            ],
+            device,
+            asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+}
@@ -1,82 +1,155 @@
 use legion::prelude::Resources;
 
-use crate::assets::{material::PBRMaterialUniform, mesh::MeshVertexData};
+use super::{depth_prepass, LightUniformBuffer};
+use crate::assets::{material::{PBRMaterialUniform, UvAnimUniform}, mesh::MeshVertexData, shader::SpecializationConstant};
 
 use crate::{
     graphics::{
         pipeline_manager::{PipelineDesc, PipelineManager},
-        renderer::DEPTH_FORMAT,
-        resources::GPUResourceManager,
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::{
+            BindGroup, BindGroupLayoutHash, GPUResourceManager, PushConstantTransformStrategy,
+            TransformUploadStrategy,
+        },
     },
     AssetManager,
 };
 use std::{borrow::Cow, sync::Arc};
 
+/// The entries of the "pbr_material_layout", they're also hashed into the `BindGroupLayoutHash` resource.
+pub fn pbr_material_layout_entries() -> Vec<wgpu::BindGroupLayoutEntry> {
+    vec![
+        wgpu::BindGroupLayoutEntry::new(
+            0,
+            wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::UniformBuffer {
+                dynamic: false,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<PBRMaterialUniform>() as _,
+                ),
+            },
+        ),
+        wgpu::BindGroupLayoutEntry::new(
+            1,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::Sampler { comparison: false },
+        ),
+        wgpu::BindGroupLayoutEntry::new(
+            2,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::Sampler { comparison: false },
+        ),
+        wgpu::BindGroupLayoutEntry::new(
+            3,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                component_type: wgpu::TextureComponentType::Float,
+                dimension: wgpu::TextureViewDimension::D2,
+            },
+        ),
+        wgpu::BindGroupLayoutEntry::new(
+            4,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                component_type: wgpu::TextureComponentType::Float,
+                dimension: wgpu::TextureViewDimension::D2,
+            },
+        ),
+        wgpu::BindGroupLayoutEntry::new(
+            5,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                component_type: wgpu::TextureComponentType::Float,
+                dimension: wgpu::TextureViewDimension::D2,
+            },
+        ),
+        wgpu::BindGroupLayoutEntry::new(
+            6,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                component_type: wgpu::TextureComponentType::Float,
+                dimension: wgpu::TextureViewDimension::D2,
+            },
+        ),
+        wgpu::BindGroupLayoutEntry::new(
+            7,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::UniformBuffer {
+                dynamic: false,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<UvAnimUniform>() as _,
+                ),
+            },
+        ),
+    ]
+}
+
 pub fn create_pbr_bindgroup_layout(device: Arc<wgpu::Device>) -> wgpu::BindGroupLayout {
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        entries: Cow::Borrowed(&[
-            wgpu::BindGroupLayoutEntry::new(
-                0,
-                wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
-                wgpu::BindingType::UniformBuffer {
-                    dynamic: false,
-                    min_binding_size: wgpu::BufferSize::new(
-                        std::mem::size_of::<PBRMaterialUniform>() as _,
-                    ),
-                },
-            ),
-            wgpu::BindGroupLayoutEntry::new(
-                1,
-                wgpu::ShaderStage::FRAGMENT,
-                wgpu::BindingType::Sampler { comparison: false },
-            ),
-            wgpu::BindGroupLayoutEntry::new(
-                2,
-                wgpu::ShaderStage::FRAGMENT,
-                wgpu::BindingType::Sampler { comparison: false },
-            ),
-            wgpu::BindGroupLayoutEntry::new(
-                3,
-                wgpu::ShaderStage::FRAGMENT,
-                wgpu::BindingType::SampledTexture {
-                    multisampled: false,
-                    component_type: wgpu::TextureComponentType::Float,
-                    dimension: wgpu::TextureViewDimension::D2,
-                },
-            ),
-            wgpu::BindGroupLayoutEntry::new(
-                4,
-                wgpu::ShaderStage::FRAGMENT,
-                wgpu::BindingType::SampledTexture {
-                    multisampled: false,
-                    component_type: wgpu::TextureComponentType::Float,
-                    dimension: wgpu::TextureViewDimension::D2,
-                },
-            ),
-            wgpu::BindGroupLayoutEntry::new(
-                5,
-                wgpu::ShaderStage::FRAGMENT,
-                wgpu::BindingType::SampledTexture {
-                    multisampled: false,
-                    component_type: wgpu::TextureComponentType::Float,
-                    dimension: wgpu::TextureViewDimension::D2,
-                },
-            ),
-        ]),
+        entries: Cow::Owned(pbr_material_layout_entries()),
         label: Some(Cow::Borrowed("pbr_material_layout")),
     })
 }
 
-pub fn create(resources: &Resources) {
+/// Creates the "lights" buffer written by the `upload_lights` system and it's bind group.
+fn create_lights(device: &wgpu::Device, resource_manager: &GPUResourceManager) {
+    let lights_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Borrowed(&[wgpu::BindGroupLayoutEntry::new(
+            0,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::StorageBuffer {
+                dynamic: false,
+                readonly: true,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<LightUniformBuffer>() as _,
+                ),
+            },
+        )]),
+        label: Some(Cow::Borrowed("lights_layout")),
+    });
+
+    let lights_buffer = device.create_buffer_with_data(
+        bytemuck::bytes_of(&LightUniformBuffer::default()),
+        wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+    );
+
+    let bind_group = BindGroup::new(
+        4,
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(Cow::Borrowed("lights")),
+            layout: &lights_layout,
+            entries: Cow::Borrowed(&[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(lights_buffer.slice(..)),
+            }]),
+        }),
+    );
+
+    resource_manager.add_bind_group_layout("lights_layout", lights_layout);
+    resource_manager.add_buffer("lights", lights_buffer);
+    resource_manager.add_single_bind_group("lights", bind_group);
+}
+
+/// Creates the pbr pipelines and inserts the `BindGroupLayoutHash` of their material layout.
+pub fn create(resources: &mut Resources) {
+    create_pipelines(resources);
+    resources.insert(BindGroupLayoutHash::new(&pbr_material_layout_entries()));
+}
+
+fn create_pipelines(resources: &Resources) {
     let asset_manager = resources.get_mut::<AssetManager>().unwrap();
     let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
     let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
     let device = resources.get::<Arc<wgpu::Device>>().unwrap();
-    let sc_desc = resources.get::<wgpu::SwapChainDescriptor>().unwrap();
+    let transform_upload = resources.get::<TransformUploadStrategy>().unwrap();
 
     let mut pbr_desc = PipelineDesc::default();
     pbr_desc.shader = "core/shaders/pbr.shader".to_string();
-    pbr_desc.color_states[0].format = sc_desc.format;
+    pbr_desc.color_states[0].format = HDR_FORMAT;
     pbr_desc.depth_state = Some(wgpu::DepthStencilStateDescriptor {
         format: DEPTH_FORMAT,
         depth_write_enabled: true,
@@ -130,11 +203,14 @@ pub fn create(resources: &Resources) {
 
     resource_manager.add_bind_group_layout("probe_material_layout", probe_material_layout);
 
+    create_lights(&device, &resource_manager);
+
     pbr_desc.layouts = vec![
         "locals".to_string(),
         "globals".to_string(),
         "pbr_material_layout".to_string(),
         "probe_material_layout".to_string(),
+        "lights_layout".to_string(),
     ];
     pbr_desc.cull_mode = wgpu::CullMode::Back;
     let vertex_size = std::mem::size_of::<MeshVertexData>();
@@ -147,12 +223,107 @@ pub fn create(resources: &Resources) {
             wgpu::vertex_attr_array![0 => Float3, 1 => Float3, 2 => Float2, 3 => Float4].to_vec(),
         );
 
-    pipeline_manager.add_pipeline(
-        "pbr",
-        &pbr_desc,
-        vec!["globals", "skybox", "froxel_cull"],
+    pipeline_manager
+        .add_pipeline(
+            "pbr",
+            &pbr_desc,
+            vec!["globals", "skybox", "froxel_cull", "skinning"],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+
+    // Used by `BlendMode::Transparent` materials, these are drawn after every opaque mesh.
+    let alpha_blend = wgpu::BlendDescriptor {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+        operation: wgpu::BlendOperation::Add,
+    };
+    let mut transparent_desc = pbr_desc.clone();
+    transparent_desc.color_states[0].color_blend = alpha_blend.clone();
+    transparent_desc.color_states[0].alpha_blend = alpha_blend;
+    transparent_desc.depth_state.as_mut().unwrap().depth_write_enabled = false;
+
+    pipeline_manager
+        .add_pipeline(
+            "pbr_transparent",
+            &transparent_desc,
+            vec!["pbr"],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+
+    depth_prepass::create(
         &device,
         &asset_manager,
-        resource_manager.clone(),
+        &mut pipeline_manager,
+        &resource_manager,
+        &pbr_desc,
     );
+
+    if *transform_upload == TransformUploadStrategy::PushConstants {
+        create_push_constant_pipelines(
+            &device,
+            &asset_manager,
+            &mut pipeline_manager,
+            &resource_manager,
+            &pbr_desc,
+            &transparent_desc,
+        );
+    }
+}
+
+// Variants of the pbr pipelines that read the world matrix from push constants, set 0 is left empty.
+fn create_push_constant_pipelines(
+    device: &wgpu::Device,
+    asset_manager: &AssetManager,
+    pipeline_manager: &mut PipelineManager,
+    resource_manager: &Arc<GPUResourceManager>,
+    pbr_desc: &PipelineDesc,
+    transparent_desc: &PipelineDesc,
+) {
+    let empty_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Borrowed(&[]),
+        label: Some(Cow::Borrowed("empty_layout")),
+    });
+    let empty_bind_group = BindGroup::new(
+        0,
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(Cow::Borrowed("empty")),
+            layout: &empty_layout,
+            entries: Cow::Borrowed(&[]),
+        }),
+    );
+    resource_manager.add_bind_group_layout("empty_layout", empty_layout);
+    resource_manager.add_single_bind_group("empty", empty_bind_group);
+
+    for (name, desc, dependency) in [
+        ("pbr_push_constants", pbr_desc, "pbr"),
+        (
+            "pbr_transparent_push_constants",
+            transparent_desc,
+            "pbr_transparent",
+        ),
+    ]
+    .iter()
+    {
+        let mut desc = (*desc).clone();
+        desc.layouts[0] = "empty_layout".to_string();
+        desc.push_constant_ranges = vec![PushConstantTransformStrategy::range()];
+        desc.specialization_constants
+            .push(SpecializationConstant::from_bool("PUSH_CONSTANT_TRANSFORM", true));
+        pipeline_manager
+            .add_pipeline(
+                *name,
+                &desc,
+                vec![*dependency],
+                device,
+                asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+    }
 }
@@ -0,0 +1,63 @@
+use super::debug_draw::DebugDraw;
+use nalgebra_glm::Vec3;
+use rapier3d::{
+    math::{Point, Real},
+    pipeline::{DebugRenderBackend, DebugRenderObject},
+};
+
+/// Draws the collision shapes, joints and body axes of a rapier physics world with `DebugDraw` lines.
+/// The `render_physics_debug` system draws the `RigidBodySet`, `ColliderSet`, `ImpulseJointSet` and
+/// `MultibodyJointSet` resources every frame while `enabled` is true.
+#[derive(Debug, Default)]
+pub struct PhysicsDebugDraw {
+    pub enabled: bool,
+    // Lines from the current frame, moved into `DebugDraw` once the physics world has been drawn.
+    pub(crate) lines: DebugDraw,
+}
+
+impl DebugRenderBackend for PhysicsDebugDraw {
+    fn draw_line(
+        &mut self,
+        _object: DebugRenderObject,
+        a: Point<Real>,
+        b: Point<Real>,
+        color: [f32; 4],
+    ) {
+        self.lines.line(
+            Vec3::new(a.x, a.y, a.z),
+            Vec3::new(b.x, b.y, b.z),
+            hsla_to_rgba(color),
+        );
+    }
+}
+
+/// Rapier colors are hue in degrees, saturation, lightness and alpha.
+pub(crate) fn hsla_to_rgba(color: [f32; 4]) -> [f32; 4] {
+    let [hue, saturation, lightness, alpha] = color;
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = (hue.rem_euclid(360.0)) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r + m, g + m, b + m, alpha]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hsla_to_rgba;
+
+    #[test]
+    fn should_convert_hsla() {
+        assert_eq!(hsla_to_rgba([0.0, 1.0, 0.5, 1.0]), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(hsla_to_rgba([120.0, 1.0, 0.5, 0.5]), [0.0, 1.0, 0.0, 0.5]);
+        assert_eq!(hsla_to_rgba([240.0, 1.0, 0.5, 1.0]), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(hsla_to_rgba([0.0, 0.0, 1.0, 1.0]), [1.0, 1.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,64 @@
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{ComputePipelineDesc, PipelineManager},
+        resources::GPUResourceManager,
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+// Must match the local size in the skinning compute shader.
+pub(crate) const SKINNING_WORKGROUP_SIZE: u32 = 64;
+
+pub fn create_skinning_bindgroup_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let storage_entry = |binding, readonly| {
+        wgpu::BindGroupLayoutEntry::new(
+            binding,
+            wgpu::ShaderStage::COMPUTE,
+            wgpu::BindingType::StorageBuffer {
+                dynamic: false,
+                readonly,
+                min_binding_size: None,
+            },
+        )
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(vec![
+            // Joint matrices
+            storage_entry(0, true),
+            // Skinned vertices
+            storage_entry(1, true),
+            // Deformed vertices
+            storage_entry(2, false),
+        ]),
+        label: Some(Cow::Borrowed("skinning_layout")),
+    })
+}
+
+/// Creates the compute pipeline that deforms skinned meshes.
+/// Note: This needs to be called before the pbr pipeline as the pbr pipeline reads the deformed vertices.
+pub fn create(resources: &Resources) {
+    let asset_manager = resources.get::<AssetManager>().unwrap();
+    let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+    let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+    let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+    resource_manager
+        .add_bind_group_layout("skinning_layout", create_skinning_bindgroup_layout(&device));
+
+    let mut skinning_desc = ComputePipelineDesc::new("core/shaders/skinning/skinning.shader");
+    skinning_desc.layouts = vec!["skinning_layout".to_string()];
+    pipeline_manager
+        .add_compute_pipeline(
+            "skinning",
+            &skinning_desc,
+            vec![],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+}
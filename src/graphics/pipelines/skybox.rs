@@ -3,7 +3,7 @@ use legion::prelude::Resources;
 use crate::{
     graphics::{
         pipeline_manager::{PipelineDesc, PipelineManager},
-        renderer::DEPTH_FORMAT,
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
         resources::GPUResourceManager,
     },
     AssetManager,
@@ -15,11 +15,10 @@ pub fn create(resources: &Resources) {
     let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
     let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
     let device = resources.get::<Arc<wgpu::Device>>().unwrap();
-    let sc_desc = resources.get::<wgpu::SwapChainDescriptor>().unwrap();
 
     let mut skybox_desc = PipelineDesc::default();
     skybox_desc.shader = "core/shaders/skybox.shader".to_string();
-    skybox_desc.color_states[0].format = sc_desc.format;
+    skybox_desc.color_states[0].format = HDR_FORMAT;
     skybox_desc.depth_state = Some(wgpu::DepthStencilStateDescriptor {
         format: DEPTH_FORMAT,
         depth_write_enabled: false,
@@ -57,12 +56,14 @@ pub fn create(resources: &Resources) {
         .vertex_state
         .set_index_format(wgpu::IndexFormat::Uint16);
 
-    pipeline_manager.add_pipeline(
-        "skybox",
-        &skybox_desc,
-        vec!["globals"],
-        &device,
-        &asset_manager,
-        resource_manager.clone(),
-    );
+    pipeline_manager
+        .add_pipeline(
+            "skybox",
+            &skybox_desc,
+            vec!["globals"],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
 }
@@ -71,12 +71,14 @@ pub fn create(resources: &Resources, format: wgpu::TextureFormat) {
     );
     resource_manager.add_buffer("specular", specular_globals_buffer);
 
-    pipeline_manager.add_pipeline(
-        "specular",
-        &skybox_desc,
-        vec![],
-        &device,
-        &asset_manager,
-        resource_manager.clone(),
-    );
+    pipeline_manager
+        .add_pipeline(
+            "specular",
+            &skybox_desc,
+            vec![],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
 }
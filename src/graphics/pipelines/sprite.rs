@@ -0,0 +1,296 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+use nalgebra_glm::{Mat4, Vec4};
+
+use crate::{
+    assets::texture::Texture,
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::GPUResourceManager,
+    },
+    scene::components::Sprite,
+    AssetManager,
+};
+use std::{borrow::Cow, collections::HashMap, ops::Range, sync::Arc};
+
+/// Each sprite is drawn as two triangles.
+pub const VERTICES_PER_SPRITE: usize = 6;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteVertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+unsafe impl Zeroable for SpriteVertex {}
+unsafe impl Pod for SpriteVertex {}
+
+// The corners of the unit quad in the order they're emitted, as fractions of the quad's size.
+const QUAD_CORNERS: [(f32, f32); VERTICES_PER_SPRITE] = [
+    (0.0, 0.0),
+    (1.0, 0.0),
+    (1.0, 1.0),
+    (0.0, 0.0),
+    (1.0, 1.0),
+    (0.0, 1.0),
+];
+
+fn corner_position(sprite: &Sprite, matrix: &Mat4, (x, y): (f32, f32)) -> Vec4 {
+    matrix * Vec4::new(x - sprite.pivot[0], y - sprite.pivot[1], 0.0, 1.0)
+}
+
+/// Writes the two triangles of a sprite into `vertices`, which must hold `VERTICES_PER_SPRITE` vertices.
+pub(crate) fn write_sprite_vertices(sprite: &Sprite, matrix: &Mat4, vertices: &mut [SpriteVertex]) {
+    let [u, v, width, height] = sprite.rect;
+    for (vertex, &corner) in vertices.iter_mut().zip(QUAD_CORNERS.iter()) {
+        let position = corner_position(sprite, matrix, corner);
+        *vertex = SpriteVertex {
+            position: [position.x, position.y, position.z],
+            // Textures start at the top left, the quad at the bottom left.
+            uv: [u + corner.0 * width, v + (1.0 - corner.1) * height],
+            color: sprite.color,
+        };
+    }
+}
+
+/// Whether any part of the sprite is inside the camera's view.
+pub(crate) fn is_sprite_visible(sprite: &Sprite, matrix: &Mat4, view_projection: &Mat4) -> bool {
+    let corners: Vec<Vec4> = QUAD_CORNERS
+        .iter()
+        .map(|&corner| view_projection * corner_position(sprite, matrix, corner))
+        .collect();
+    // The sprite is hidden if all of it's corners are outside the same clip plane.
+    (0..3).all(|axis| {
+        !corners.iter().all(|corner| corner[axis] < -corner.w)
+            && !corners.iter().all(|corner| corner[axis] > corner.w)
+    })
+}
+
+/// Groups consecutive sprites with the same texture into one draw, returning the texture and vertex range of each.
+pub(crate) fn sprite_batches<'a>(
+    textures: impl Iterator<Item = &'a str>,
+) -> Vec<(&'a str, Range<u32>)> {
+    let mut batches: Vec<(&str, Range<u32>)> = Vec::new();
+    for (index, texture) in textures.enumerate() {
+        let end = ((index + 1) * VERTICES_PER_SPRITE) as u32;
+        match batches.last_mut() {
+            Some((last, range)) if *last == texture => range.end = end,
+            _ => batches.push((texture, end - VERTICES_PER_SPRITE as u32..end)),
+        }
+    }
+    batches
+}
+
+/// The GPU resources shared by every sprite. Inserted as a resource by `create`.
+pub struct SpritePipeline {
+    vertex_buffer: Option<wgpu::Buffer>,
+    // How many vertices fit in `vertex_buffer`.
+    capacity: usize,
+    sampler: wgpu::Sampler,
+    // Keyed by texture path, the texture is kept to notice when it's reloaded.
+    bind_groups: HashMap<String, (Arc<Texture>, wgpu::BindGroup)>,
+}
+
+impl SpritePipeline {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sprite_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            vertex_buffer: None,
+            capacity: 0,
+            sampler,
+            bind_groups: HashMap::new(),
+        }
+    }
+
+    /// Makes sure the vertex buffer holds at least `vertex_count` vertices, it grows to the next power of two when it's too small.
+    pub(crate) fn reserve(&mut self, device: &wgpu::Device, vertex_count: usize) {
+        if self.vertex_buffer.is_none() || self.capacity < vertex_count {
+            self.capacity = vertex_count.next_power_of_two();
+            self.vertex_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("sprite_vertices"),
+                size: (self.capacity * std::mem::size_of::<SpriteVertex>()) as u64,
+                usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+    }
+
+    /// Panics if `reserve` hasn't been called yet.
+    pub(crate) fn vertex_buffer(&self) -> &wgpu::Buffer {
+        self.vertex_buffer.as_ref().unwrap()
+    }
+
+    /// Creates the bind group for a texture, or recreates it if the texture was reloaded.
+    pub(crate) fn prepare_texture(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        path: &str,
+        texture: Arc<Texture>,
+    ) {
+        if let Some((cached, _)) = self.bind_groups.get(path) {
+            if Arc::ptr_eq(cached, &texture) {
+                return;
+            }
+        }
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ]),
+            label: Some(Cow::Borrowed("sprite_bind_group")),
+        });
+        self.bind_groups
+            .insert(path.to_string(), (texture, bind_group));
+    }
+
+    pub(crate) fn bind_group(&self, path: &str) -> Option<&wgpu::BindGroup> {
+        self.bind_groups.get(path).map(|(_, bind_group)| bind_group)
+    }
+}
+
+/// Creates the sprite pipeline and inserts the `SpritePipeline` resource.
+pub fn create(resources: &mut Resources) {
+    let sprite_pipeline = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+        let sprite_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupLayoutEntry::new(
+                    0,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        component_type: wgpu::TextureComponentType::Float,
+                        dimension: wgpu::TextureViewDimension::D2,
+                    },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    1,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::Sampler { comparison: false },
+                ),
+            ]),
+            label: Some(Cow::Borrowed("sprite_layout")),
+        });
+        resource_manager.add_bind_group_layout("sprite_layout", sprite_layout);
+
+        let mut sprite_desc = PipelineDesc::default();
+        sprite_desc.shader = "core/shaders/sprite.shader".to_string();
+        sprite_desc.color_states[0].format = HDR_FORMAT;
+        sprite_desc.color_states[0].color_blend = wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+        // Sprites are batched by texture instead of drawn back to front, so their transform's z orders them.
+        // Fully transparent pixels are discarded so they don't hide the sprites behind them.
+        sprite_desc.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        });
+        sprite_desc.layouts = vec!["globals".to_string(), "sprite_layout".to_string()];
+        sprite_desc.cull_mode = wgpu::CullMode::None;
+        sprite_desc.vertex_state.new_buffer_descriptor(
+            std::mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
+            wgpu::InputStepMode::Vertex,
+            wgpu::vertex_attr_array![0 => Float3, 1 => Float2, 2 => Float4].to_vec(),
+        );
+
+        pipeline_manager
+            .add_pipeline(
+                "sprite",
+                &sprite_desc,
+                vec!["pbr", "pbr_transparent", "deferred_lighting"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        SpritePipeline::new(&device)
+    };
+
+    resources.insert(sprite_pipeline);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_sprite_visible, sprite_batches, write_sprite_vertices, SpriteVertex, VERTICES_PER_SPRITE,
+    };
+    use crate::scene::components::Sprite;
+    use nalgebra_glm::{Mat4, Vec3};
+
+    #[test]
+    fn should_place_pivot_at_origin() {
+        let mut sprite = Sprite::new("sprite.png");
+        sprite.pivot = [0.0, 0.0];
+        sprite.rect = [0.5, 0.0, 0.5, 0.5];
+        let matrix = nalgebra_glm::scale(&Mat4::identity(), &Vec3::new(2.0, 4.0, 1.0));
+
+        let mut vertices = [SpriteVertex {
+            position: [0.0; 3],
+            uv: [0.0; 2],
+            color: [0.0; 4],
+        }; VERTICES_PER_SPRITE];
+        write_sprite_vertices(&sprite, &matrix, &mut vertices);
+
+        // Bottom left, bottom right and top right.
+        assert_eq!(vertices[0].position, [0.0, 0.0, 0.0]);
+        assert_eq!(vertices[0].uv, [0.5, 0.5]);
+        assert_eq!(vertices[1].position, [2.0, 0.0, 0.0]);
+        assert_eq!(vertices[2].position, [2.0, 4.0, 0.0]);
+        assert_eq!(vertices[2].uv, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn should_cull_sprites_outside_view() {
+        let sprite = Sprite::new("sprite.png");
+        let view_projection = nalgebra_glm::ortho_lh_no(-5.0, 5.0, -5.0, 5.0, -1.0, 1.0);
+        let inside = nalgebra_glm::translation(&Vec3::new(4.9, 0.0, 0.0));
+        let outside = nalgebra_glm::translation(&Vec3::new(6.0, 0.0, 0.0));
+
+        assert!(is_sprite_visible(&sprite, &inside, &view_projection));
+        assert!(!is_sprite_visible(&sprite, &outside, &view_projection));
+    }
+
+    #[test]
+    fn should_batch_by_texture() {
+        let textures = vec!["a.png", "a.png", "b.png", "a.png"];
+        let batches = sprite_batches(textures.into_iter());
+        assert_eq!(
+            batches,
+            vec![("a.png", 0..12), ("b.png", 12..18), ("a.png", 18..24)]
+        );
+    }
+}
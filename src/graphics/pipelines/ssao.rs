@@ -0,0 +1,383 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+
+use crate::{
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        resources::{GBuffer, GPUResourceManager},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// The ssao textures only store a single occlusion value.
+pub const SSAO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+/// Must match the size of the samples array in ssao.frag.glsl.
+pub const MAX_SSAO_KERNEL_SIZE: usize = 64;
+
+/// Runtime settings for screen space ambient occlusion. Insert this as a resource to change them.
+#[derive(Debug, Clone, Copy)]
+pub struct SsaoConfig {
+    /// How many hemisphere samples are taken per pixel. Zero turns ssao off.
+    pub kernel_size: u32,
+    /// The radius of the sample hemisphere in view space units.
+    pub radius: f32,
+    /// Stops flat surfaces from occluding themselves.
+    pub bias: f32,
+}
+
+impl Default for SsaoConfig {
+    fn default() -> Self {
+        Self {
+            kernel_size: 32,
+            radius: 0.5,
+            bias: 0.025,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct SsaoUniform {
+    samples: [[f32; 4]; MAX_SSAO_KERNEL_SIZE],
+    // x: kernel size, y: radius, z: bias
+    settings: [f32; 4],
+}
+
+unsafe impl Zeroable for SsaoUniform {}
+unsafe impl Pod for SsaoUniform {}
+
+// Returns the index'th value of the halton sequence for the given base.
+pub(crate) fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Generates the sample kernel for a tangent space hemisphere facing +z.
+/// Samples are spread using halton sequences so the kernel is the same every run and are
+/// scaled so more of them end up close to the center.
+pub fn generate_kernel(kernel_size: u32) -> Vec<[f32; 4]> {
+    let kernel_size = kernel_size.min(MAX_SSAO_KERNEL_SIZE as u32);
+    (0..kernel_size)
+        .map(|i| {
+            let direction = nalgebra_glm::normalize(&nalgebra_glm::vec3(
+                halton(i + 1, 2) * 2.0 - 1.0,
+                halton(i + 1, 3) * 2.0 - 1.0,
+                halton(i + 1, 5),
+            ));
+            let t = i as f32 / kernel_size as f32;
+            let scale = nalgebra_glm::lerp_scalar(0.1, 1.0, t * t);
+            let sample = direction * halton(i + 1, 7) * scale;
+            [sample.x, sample.y, sample.z, 0.0]
+        })
+        .collect()
+}
+
+/// A sampler and a single 2D float texture used to read the ssao textures.
+pub fn create_ssao_texture_bindgroup_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Borrowed(&[
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::Sampler { comparison: false },
+            ),
+            wgpu::BindGroupLayoutEntry::new(
+                1,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::SampledTexture {
+                    multisampled: false,
+                    component_type: wgpu::TextureComponentType::Float,
+                    dimension: wgpu::TextureViewDimension::D2,
+                },
+            ),
+        ]),
+        label: Some(Cow::Borrowed("ssao_texture_layout")),
+    })
+}
+
+/// Renders ambient occlusion from the gbuffer depth and normals then blurs it.
+/// The blurred result is bound by the deferred lighting pass to darken the ambient term.
+pub struct SsaoPass {
+    pub ao: wgpu::Texture,
+    pub ao_view: wgpu::TextureView,
+    pub blur: wgpu::Texture,
+    pub blur_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+
+    // Used by the blur pass to read the raw occlusion.
+    ao_bind_group: wgpu::BindGroup,
+    /// Used by the lighting pass to read the blurred occlusion.
+    pub bind_group: wgpu::BindGroup,
+
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SsaoPass {
+    pub fn new(
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texture_layout = resource_manager
+            .get_bind_group_layout("ssao_texture_layout")
+            .unwrap();
+        let uniform_layout = resource_manager.get_bind_group_layout("ssao_layout").unwrap();
+
+        let (ao, ao_view) = Self::create_texture(device, width, height);
+        let (blur, blur_view) = Self::create_texture(device, width, height);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        let create_texture_bind_group = |view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &texture_layout,
+                entries: Cow::Borrowed(&[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                ]),
+                label: None,
+            })
+        };
+        let ao_bind_group = create_texture_bind_group(&ao_view);
+        let bind_group = create_texture_bind_group(&blur_view);
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<SsaoUniform>() as u64,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &uniform_layout,
+            entries: Cow::Borrowed(&[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+            }]),
+            label: None,
+        });
+
+        Self {
+            ao,
+            ao_view,
+            blur,
+            blur_view,
+            sampler,
+            ao_bind_group,
+            bind_group,
+            uniform_buffer,
+            uniform_bind_group,
+            width,
+            height,
+        }
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SSAO_FORMAT,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+            label: Some("ssao"),
+        });
+        let view = texture.create_default_view();
+        (texture, view)
+    }
+
+    /// Renders the occlusion and blurs it into `blur`.
+    /// If the kernel size is zero `blur` is cleared to white so nothing is occluded.
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        config: &SsaoConfig,
+        gbuffer: &GBuffer,
+        resource_manager: &GPUResourceManager,
+        pipeline_manager: &PipelineManager,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let kernel = generate_kernel(config.kernel_size);
+        if kernel.is_empty() {
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &self.blur_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                        store: true,
+                    },
+                }]),
+                depth_stencil_attachment: None,
+            });
+            return;
+        }
+
+        let mut uniform = SsaoUniform {
+            samples: [[0.0; 4]; MAX_SSAO_KERNEL_SIZE],
+            settings: [kernel.len() as f32, config.radius, config.bias, 0.0],
+        };
+        uniform.samples[..kernel.len()].copy_from_slice(&kernel);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let color_attachment = |view| wgpu::RenderPassColorAttachmentDescriptor {
+            attachment: view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                store: true,
+            },
+        };
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: Cow::Borrowed(&[color_attachment(&self.ao_view)]),
+                depth_stencil_attachment: None,
+            });
+            let ssao_pipeline = pipeline_manager.get("ssao", None).unwrap();
+            render_pass.set_pipeline(&ssao_pipeline.render_pipeline);
+            render_pass.set_bind_group(0, &gbuffer.bind_group, &[]);
+            render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+            render_pass.set_bind_group(2, &self.uniform_bind_group, &[]);
+            render_pass.draw(0..3 as u32, 0..1);
+        }
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: Cow::Borrowed(&[color_attachment(&self.blur_view)]),
+                depth_stencil_attachment: None,
+            });
+            let blur_pipeline = pipeline_manager.get("ssao_blur", None).unwrap();
+            render_pass.set_pipeline(&blur_pipeline.render_pipeline);
+            render_pass.set_bind_group(0, &self.ao_bind_group, &[]);
+            render_pass.draw(0..3 as u32, 0..1);
+        }
+    }
+}
+
+/// Creates the ssao pipelines and inserts the `SsaoPass` and `SsaoConfig` resources.
+/// Note: This is called by the deferred pipeline after the gbuffer is created and before the lighting pipeline.
+pub fn create(resources: &mut Resources) {
+    let ssao_pass = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let gbuffer = resources.get::<GBuffer>().unwrap();
+
+        let uniform_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Borrowed(&[wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<SsaoUniform>() as _,
+                    ),
+                },
+            )]),
+            label: Some(Cow::Borrowed("ssao_layout")),
+        });
+        resource_manager.add_bind_group_layout("ssao_layout", uniform_layout);
+        resource_manager.add_bind_group_layout(
+            "ssao_texture_layout",
+            create_ssao_texture_bindgroup_layout(&device),
+        );
+
+        let mut ssao_desc = PipelineDesc::default();
+        ssao_desc.shader = "core/shaders/ssao/ssao.shader".to_string();
+        ssao_desc.color_states[0].format = SSAO_FORMAT;
+        ssao_desc.layouts = vec![
+            "gbuffer_layout".to_string(),
+            "globals".to_string(),
+            "ssao_layout".to_string(),
+        ];
+        ssao_desc.cull_mode = wgpu::CullMode::None;
+        pipeline_manager
+            .add_pipeline(
+                "ssao",
+                &ssao_desc,
+                vec!["deferred_geometry"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        let mut blur_desc = PipelineDesc::default();
+        blur_desc.shader = "core/shaders/ssao/ssao_blur.shader".to_string();
+        blur_desc.color_states[0].format = SSAO_FORMAT;
+        blur_desc.layouts = vec!["ssao_texture_layout".to_string()];
+        blur_desc.cull_mode = wgpu::CullMode::None;
+        pipeline_manager
+            .add_pipeline(
+                "ssao_blur",
+                &blur_desc,
+                vec!["ssao"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        SsaoPass::new(&device, &resource_manager, gbuffer.width, gbuffer.height)
+    };
+
+    resources.insert(ssao_pass);
+    resources.insert(SsaoConfig::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_kernel, MAX_SSAO_KERNEL_SIZE};
+
+    #[test]
+    fn kernel_should_fit_in_hemisphere() {
+        let kernel = generate_kernel(32);
+        assert_eq!(kernel.len(), 32);
+        for sample in kernel.iter() {
+            let length = (sample[0] * sample[0] + sample[1] * sample[1] + sample[2] * sample[2]).sqrt();
+            assert!(length <= 1.0);
+            assert!(sample[2] >= 0.0);
+        }
+        assert_eq!(generate_kernel(32), kernel);
+        assert_eq!(generate_kernel(1000).len(), MAX_SSAO_KERNEL_SIZE);
+    }
+}
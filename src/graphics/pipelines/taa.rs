@@ -0,0 +1,370 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+use nalgebra_glm::{Mat4, Vec2, Vec3};
+
+use super::{motion_vectors::MotionVectorPass, ssao::halton};
+use crate::{
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        renderer::HDR_FORMAT,
+        resources::{GPUResourceManager, HdrFramebuffer, RenderTarget},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// Runtime settings for temporal anti-aliasing. Insert this as a resource to change them.
+/// While TAA is on the motion vectors are rendered even if `MotionVectorRendering` is off.
+#[derive(Debug, Clone, Copy)]
+pub struct TaaConfig {
+    pub enabled: bool,
+    /// How much of the current frame is blended into the history. Lower values are smoother but ghost more.
+    pub blend_alpha: f32,
+}
+
+impl Default for TaaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            blend_alpha: 0.1,
+        }
+    }
+}
+
+/// Sub-pixel offsets the projection is moved by each frame while TAA is on, so the history gathers samples from across each pixel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaaJitter {
+    /// Offsets in pixels, between -0.5 and 0.5.
+    pub sequence: Vec<Vec2>,
+    /// The offset used this frame.
+    pub index: usize,
+}
+
+impl Default for TaaJitter {
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl TaaJitter {
+    /// Creates `count` offsets from the halton (2, 3) sequence.
+    pub fn new(count: usize) -> Self {
+        let sequence = (1..=count.max(1) as u32)
+            .map(|i| Vec2::new(halton(i, 2) - 0.5, halton(i, 3) - 0.5))
+            .collect();
+        Self { sequence, index: 0 }
+    }
+
+    pub fn current(&self) -> Vec2 {
+        self.sequence[self.index % self.sequence.len()]
+    }
+
+    /// Moves on to the next offset, wrapping around at the end of the sequence.
+    pub fn advance(&mut self) {
+        self.index = (self.index + 1) % self.sequence.len();
+    }
+
+    /// Offsets the projection by the current jitter of a `width` by `height` pixel target.
+    /// The offset is applied after the perspective divide so it's the same at every depth.
+    pub fn apply(&self, projection: &Mat4, width: u32, height: u32) -> Mat4 {
+        let jitter = self.current();
+        let offset = Vec3::new(
+            jitter.x * 2.0 / width as f32,
+            jitter.y * 2.0 / height as f32,
+            0.0,
+        );
+        nalgebra_glm::translation(&offset) * projection
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct TaaUniform {
+    // x: blend alpha, y and z: the size of a texel, w: 1.0 when the history holds a previous frame.
+    settings: [f32; 4],
+}
+
+unsafe impl Zeroable for TaaUniform {}
+unsafe impl Pod for TaaUniform {}
+
+/// Blends the hdr framebuffer with the previous frames reprojected using the motion vectors.
+pub struct TaaPipelineDesc {
+    pub pipeline: PipelineDesc,
+}
+
+impl TaaPipelineDesc {
+    pub fn new() -> Self {
+        let mut pipeline = PipelineDesc::default();
+        pipeline.shader = "core/shaders/taa.shader".to_string();
+        pipeline.color_states[0].format = HDR_FORMAT;
+        pipeline.layouts = vec!["taa_layout".to_string()];
+        pipeline.cull_mode = wgpu::CullMode::None;
+        Self { pipeline }
+    }
+}
+
+/// The history of previous frames, kept in two targets that take turns being read and written.
+/// The result is copied back into the hdr framebuffer for the rest of post processing.
+pub struct TaaPass {
+    history: [RenderTarget; 2],
+    // bind_groups[i] reads history[i] along with the hdr framebuffer and the motion vectors.
+    bind_groups: [wgpu::BindGroup; 2],
+    uniform_buffer: wgpu::Buffer,
+    // Which history target holds the last frame.
+    current: usize,
+    history_valid: bool,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TaaPass {
+    pub fn new(
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        hdr_framebuffer: &HdrFramebuffer,
+        motion_vector_pass: &MotionVectorPass,
+    ) -> Self {
+        let layout = resource_manager
+            .get_bind_group_layout("taa_layout")
+            .unwrap();
+        let (width, height) = (hdr_framebuffer.width, hdr_framebuffer.height);
+        let create_history = || {
+            RenderTarget::new(
+                device,
+                width as f32,
+                height as f32,
+                1,
+                1,
+                HDR_FORMAT,
+                wgpu::TextureUsage::OUTPUT_ATTACHMENT
+                    | wgpu::TextureUsage::SAMPLED
+                    | wgpu::TextureUsage::COPY_SRC,
+            )
+        };
+        let history = [create_history(), create_history()];
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<TaaUniform>() as u64,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let create_bind_group = |history: &RenderTarget| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &layout,
+                entries: Cow::Borrowed(&[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Sampler(&hdr_framebuffer.sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&hdr_framebuffer.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&history.texture_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(&motion_vector_pass.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+                    },
+                ]),
+                label: None,
+            })
+        };
+        let bind_groups = [
+            create_bind_group(&history[0]),
+            create_bind_group(&history[1]),
+        ];
+
+        Self {
+            history,
+            bind_groups,
+            uniform_buffer,
+            current: 0,
+            history_valid: false,
+            width,
+            height,
+        }
+    }
+
+    /// Forgets the previous frames, the next frame is used as is.
+    pub fn reset(&mut self) {
+        self.history_valid = false;
+    }
+
+    /// Resolves the hdr framebuffer against the history and writes the result back into the framebuffer.
+    pub fn render(
+        &mut self,
+        queue: &wgpu::Queue,
+        config: &TaaConfig,
+        hdr_framebuffer: &HdrFramebuffer,
+        pipeline_manager: &PipelineManager,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let uniform = TaaUniform {
+            settings: [
+                config.blend_alpha,
+                1.0 / self.width as f32,
+                1.0 / self.height as f32,
+                if self.history_valid { 1.0 } else { 0.0 },
+            ],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let output = 1 - self.current;
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &self.history[output].texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }]),
+                depth_stencil_attachment: None,
+            });
+
+            let taa_pipeline = pipeline_manager.get("taa", None).unwrap();
+            render_pass.set_pipeline(&taa_pipeline.render_pipeline);
+            render_pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+            render_pass.draw(0..3 as u32, 0..1);
+        }
+
+        encoder.copy_texture_to_texture(
+            wgpu::TextureCopyView {
+                texture: &self.history[output].texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::TextureCopyView {
+                texture: &hdr_framebuffer.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth: 1,
+            },
+        );
+
+        self.current = output;
+        self.history_valid = true;
+    }
+}
+
+/// Creates the taa pipeline and inserts the `TaaPass`, `TaaConfig` and `TaaJitter` resources.
+/// Note: This needs to be called after the hdr framebuffer and the motion vector pass are created.
+pub fn create(resources: &mut Resources) {
+    let taa_pass = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let hdr_framebuffer = resources.get::<HdrFramebuffer>().unwrap();
+        let motion_vector_pass = resources.get::<MotionVectorPass>().unwrap();
+
+        let texture_entry = |binding| {
+            wgpu::BindGroupLayoutEntry::new(
+                binding,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::SampledTexture {
+                    multisampled: false,
+                    component_type: wgpu::TextureComponentType::Float,
+                    dimension: wgpu::TextureViewDimension::D2,
+                },
+            )
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Owned(vec![
+                wgpu::BindGroupLayoutEntry::new(
+                    0,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::Sampler { comparison: false },
+                ),
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                wgpu::BindGroupLayoutEntry::new(
+                    4,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<TaaUniform>() as _
+                        ),
+                    },
+                ),
+            ]),
+            label: Some(Cow::Borrowed("taa_layout")),
+        });
+        resource_manager.add_bind_group_layout("taa_layout", layout);
+
+        let taa_desc = TaaPipelineDesc::new();
+        pipeline_manager
+            .add_pipeline(
+                "taa",
+                &taa_desc.pipeline,
+                vec!["pbr", "deferred_lighting", "debug_draw", "motion_vectors"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        TaaPass::new(
+            &device,
+            &resource_manager,
+            &hdr_framebuffer,
+            &motion_vector_pass,
+        )
+    };
+
+    resources.insert(taa_pass);
+    resources.insert(TaaConfig::default());
+    resources.insert(TaaJitter::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TaaJitter;
+    use nalgebra_glm::{Vec2, Vec4};
+
+    #[test]
+    fn should_cycle_through_sub_pixel_offsets() {
+        let mut jitter = TaaJitter::new(4);
+        assert_eq!(jitter.current(), Vec2::new(0.0, 1.0 / 3.0 - 0.5));
+        for offset in jitter.sequence.iter() {
+            assert!(offset.x.abs() <= 0.5 && offset.y.abs() <= 0.5);
+        }
+
+        for _ in 0..4 {
+            jitter.advance();
+        }
+        assert_eq!(jitter.index, 0);
+    }
+
+    #[test]
+    fn should_offset_projection_in_pixels() {
+        let mut jitter = TaaJitter::new(1);
+        jitter.sequence[0] = Vec2::new(0.5, -0.25);
+        let projection = nalgebra_glm::perspective_fov_lh_no(1.0, 100.0, 50.0, 0.1, 100.0);
+        let jittered = jitter.apply(&projection, 100, 50);
+
+        // The offset is the same in normalized device coordinates at any depth.
+        for depth in [1.0, 10.0].iter() {
+            let point = Vec4::new(0.0, 0.0, *depth, 1.0);
+            let clip = projection * point;
+            let jittered_clip = jittered * point;
+            let offset = jittered_clip.xy() / jittered_clip.w - clip.xy() / clip.w;
+            assert!((offset - Vec2::new(0.01, -0.01)).norm() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use bytemuck::{Pod, Zeroable};
+use legion::prelude::Resources;
+use nalgebra_glm::Vec4;
+
+use crate::{
+    assets::{font_manager::GlyphQuad, texture::Texture},
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::GPUResourceManager,
+    },
+    scene::components::{TextRenderer, TextSpace, Transform},
+    AssetManager,
+};
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+/// Each glyph is drawn as two triangles.
+pub const VERTICES_PER_GLYPH: usize = 6;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextVertex {
+    /// In world space, or in normalized device coordinates for screen space text.
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+    /// 1.0 for screen space text which skips the camera's view projection.
+    pub screen_space: f32,
+}
+
+unsafe impl Zeroable for TextVertex {}
+unsafe impl Pod for TextVertex {}
+
+// The corners of a glyph in the order they're emitted, as fractions of the glyph's size.
+const QUAD_CORNERS: [(f32, f32); VERTICES_PER_GLYPH] = [
+    (0.0, 0.0),
+    (1.0, 0.0),
+    (1.0, 1.0),
+    (0.0, 0.0),
+    (1.0, 1.0),
+    (0.0, 1.0),
+];
+
+/// Appends the triangles of the laid out glyphs to `vertices`.
+/// `screen_size` is the size of the framebuffer in pixels, used to place screen space text.
+pub(crate) fn write_text_vertices(
+    text: &TextRenderer,
+    transform: &Transform,
+    quads: &[GlyphQuad],
+    screen_size: (u32, u32),
+    vertices: &mut Vec<TextVertex>,
+) {
+    let to_position = |x: f32, y: f32| -> [f32; 3] {
+        match text.space {
+            TextSpace::World => {
+                let position = transform.matrix * Vec4::new(x, y, 0.0, 1.0);
+                [position.x, position.y, position.z]
+            }
+            // Pixels from the top left, y pointing down, into normalized device coordinates.
+            TextSpace::Screen => {
+                let pixel_x = transform.position.x + x;
+                let pixel_y = transform.position.y - y;
+                [
+                    pixel_x / screen_size.0 as f32 * 2.0 - 1.0,
+                    1.0 - pixel_y / screen_size.1 as f32 * 2.0,
+                    0.0,
+                ]
+            }
+        }
+    };
+    let screen_space = if text.space == TextSpace::Screen {
+        1.0
+    } else {
+        0.0
+    };
+
+    for quad in quads {
+        let rect = quad.uv_rect;
+        for &(corner_x, corner_y) in QUAD_CORNERS.iter() {
+            vertices.push(TextVertex {
+                position: to_position(
+                    (quad.position[0] + corner_x * quad.size[0]) * text.size,
+                    (quad.position[1] + corner_y * quad.size[1]) * text.size,
+                ),
+                // The atlas starts at the top left, the quad at the bottom left.
+                uv: [
+                    rect.x + corner_x * rect.width,
+                    rect.y + (1.0 - corner_y) * rect.height,
+                ],
+                color: text.color,
+                screen_space,
+            });
+        }
+    }
+}
+
+/// The GPU resources shared by all text. Inserted as a resource by `create`.
+pub struct TextPipeline {
+    vertex_buffer: Option<wgpu::Buffer>,
+    // How many vertices fit in `vertex_buffer`.
+    capacity: usize,
+    sampler: wgpu::Sampler,
+    // Keyed by font path, the atlas is kept to notice when it's replaced.
+    bind_groups: HashMap<String, (Arc<Texture>, wgpu::BindGroup)>,
+}
+
+impl TextPipeline {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("text_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            vertex_buffer: None,
+            capacity: 0,
+            sampler,
+            bind_groups: HashMap::new(),
+        }
+    }
+
+    /// Makes sure the vertex buffer holds at least `vertex_count` vertices, it grows to the next power of two when it's too small.
+    pub(crate) fn reserve(&mut self, device: &wgpu::Device, vertex_count: usize) {
+        if self.vertex_buffer.is_none() || self.capacity < vertex_count {
+            self.capacity = vertex_count.next_power_of_two();
+            self.vertex_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("text_vertices"),
+                size: (self.capacity * std::mem::size_of::<TextVertex>()) as u64,
+                usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+    }
+
+    /// Panics if `reserve` hasn't been called yet.
+    pub(crate) fn vertex_buffer(&self) -> &wgpu::Buffer {
+        self.vertex_buffer.as_ref().unwrap()
+    }
+
+    /// Creates the bind group for a font's atlas, or recreates it if the atlas was replaced.
+    pub(crate) fn prepare_font(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        font: &str,
+        atlas: Arc<Texture>,
+    ) {
+        if let Some((cached, _)) = self.bind_groups.get(font) {
+            if Arc::ptr_eq(cached, &atlas) {
+                return;
+            }
+        }
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ]),
+            label: Some(Cow::Borrowed("text_bind_group")),
+        });
+        self.bind_groups
+            .insert(font.to_string(), (atlas, bind_group));
+    }
+
+    pub(crate) fn bind_group(&self, font: &str) -> Option<&wgpu::BindGroup> {
+        self.bind_groups.get(font).map(|(_, bind_group)| bind_group)
+    }
+}
+
+/// Creates the signed distance field text pipeline and inserts the `TextPipeline` resource.
+pub fn create(resources: &mut Resources) {
+    let text_pipeline = {
+        let asset_manager = resources.get::<AssetManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+        let text_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupLayoutEntry::new(
+                    0,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        component_type: wgpu::TextureComponentType::Float,
+                        dimension: wgpu::TextureViewDimension::D2,
+                    },
+                ),
+                wgpu::BindGroupLayoutEntry::new(
+                    1,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::Sampler { comparison: false },
+                ),
+            ]),
+            label: Some(Cow::Borrowed("text_layout")),
+        });
+        resource_manager.add_bind_group_layout("text_layout", text_layout);
+
+        let mut text_desc = PipelineDesc::default();
+        text_desc.shader = "core/shaders/sdf_text.shader".to_string();
+        text_desc.color_states[0].format = HDR_FORMAT;
+        text_desc.color_states[0].color_blend = wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        };
+        // World space text is hidden behind the scene but doesn't write depth, so the soft edges of glyphs blend with each other.
+        // Screen space text sits on the near plane so it's always in front.
+        text_desc.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        });
+        text_desc.layouts = vec!["globals".to_string(), "text_layout".to_string()];
+        text_desc.cull_mode = wgpu::CullMode::None;
+        text_desc.vertex_state.new_buffer_descriptor(
+            std::mem::size_of::<TextVertex>() as wgpu::BufferAddress,
+            wgpu::InputStepMode::Vertex,
+            wgpu::vertex_attr_array![0 => Float3, 1 => Float2, 2 => Float4, 3 => Float].to_vec(),
+        );
+
+        pipeline_manager
+            .add_pipeline(
+                "sdf_text",
+                &text_desc,
+                vec!["pbr", "pbr_transparent", "deferred_lighting", "sprite"],
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+
+        TextPipeline::new(&device)
+    };
+
+    resources.insert(text_pipeline);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_text_vertices, VERTICES_PER_GLYPH};
+    use crate::{
+        assets::{font_manager::GlyphQuad, texture_atlas::Rect},
+        scene::components::{TextRenderer, Transform},
+    };
+    use nalgebra_glm::{Mat4, Quat, Vec3};
+
+    fn create_transform(position: Vec3, matrix: Mat4) -> Transform {
+        Transform {
+            index: 0,
+            position,
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            rotation: Quat::identity(),
+            matrix,
+            cull: false,
+        }
+    }
+
+    fn quad() -> GlyphQuad {
+        GlyphQuad {
+            position: [0.0, -1.0],
+            size: [0.5, 1.0],
+            uv_rect: Rect {
+                x: 0.25,
+                y: 0.0,
+                width: 0.25,
+                height: 0.5,
+            },
+        }
+    }
+
+    #[test]
+    fn should_place_world_text_with_transform() {
+        let mut text = TextRenderer::new("a", "font.ttf");
+        text.size = 2.0;
+        let transform = create_transform(
+            Vec3::zeros(),
+            nalgebra_glm::translation(&Vec3::new(1.0, 0.0, 3.0)),
+        );
+
+        let mut vertices = Vec::new();
+        write_text_vertices(&text, &transform, &[quad()], (100, 100), &mut vertices);
+        assert_eq!(vertices.len(), VERTICES_PER_GLYPH);
+
+        // Bottom left and top right.
+        assert_eq!(vertices[0].position, [1.0, -2.0, 3.0]);
+        assert_eq!(vertices[0].uv, [0.25, 0.5]);
+        assert_eq!(vertices[2].position, [2.0, 0.0, 3.0]);
+        assert_eq!(vertices[2].uv, [0.5, 0.0]);
+        assert_eq!(vertices[0].screen_space, 0.0);
+    }
+
+    #[test]
+    fn should_place_screen_text_in_pixels() {
+        let text = TextRenderer::screen("a", "font.ttf", 50.0);
+        let transform = create_transform(Vec3::new(100.0, 0.0, 0.0), Mat4::identity());
+
+        let mut vertices = Vec::new();
+        write_text_vertices(&text, &transform, &[quad()], (200, 100), &mut vertices);
+
+        // The glyph spans pixels 100 to 125 across and the top half of the screen.
+        assert_eq!(vertices[0].position, [0.0, 0.0, 0.0]);
+        assert_eq!(vertices[2].position, [0.25, 1.0, 0.0]);
+        assert_eq!(vertices[0].screen_space, 1.0);
+    }
+}
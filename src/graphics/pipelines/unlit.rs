@@ -1,6 +1,7 @@
-use std::mem;
+use std::{mem, sync::Arc};
 
 use crate::{
+    assets::shader::SpecializationConstant,
     graphics::{
         mesh::MeshVertexData,
         pipeline::VertexStateBuilder,
@@ -63,16 +64,34 @@ impl SimplePipeline for UnlitPipeline {
 }
 
 #[derive(Debug, Default)]
-pub struct UnlitPipelineDesc;
+pub struct UnlitPipelineDesc {
+    /// Multiplies the texture with a per vertex color read from a second vertex buffer.
+    pub enable_vertex_color: bool,
+}
 
 impl SimplePipelineDesc for UnlitPipelineDesc {
     type Pipeline = UnlitPipeline;
 
+    fn specialization_constants(&self) -> Vec<SpecializationConstant> {
+        vec![SpecializationConstant::from_bool(
+            "ENABLE_VERTEX_COLOR",
+            self.enable_vertex_color,
+        )]
+    }
+
     fn load_shader<'a>(
         &self,
         asset_manager: &'a crate::AssetManager,
-    ) -> &'a crate::graphics::material::Shader {
-        asset_manager.get_shader("core/shaders/unlit.shader")
+    ) -> Arc<crate::assets::shader::Shader> {
+        futures::executor::block_on(
+            asset_manager
+                .get_specialized_shader(
+                    "core/shaders/unlit.shader",
+                    &self.specialization_constants(),
+                )
+                .get_async(),
+        )
+        .unwrap()
     }
 
     fn create_layout<'a>(
@@ -127,12 +146,9 @@ impl SimplePipelineDesc for UnlitPipelineDesc {
     fn primitive_topology(&self) -> wgpu::PrimitiveTopology {
         wgpu::PrimitiveTopology::TriangleList
     }
-    fn color_states_desc(
-        &self,
-        sc_desc: &wgpu::SwapChainDescriptor,
-    ) -> Vec<wgpu::ColorStateDescriptor> {
+    fn color_states_desc(&self, formats: &[wgpu::TextureFormat]) -> Vec<wgpu::ColorStateDescriptor> {
         vec![wgpu::ColorStateDescriptor {
-            format: sc_desc.format,
+            format: formats[0],
             color_blend: wgpu::BlendDescriptor::REPLACE,
             alpha_blend: wgpu::BlendDescriptor::REPLACE,
             write_mask: wgpu::ColorWrite::ALL,
@@ -185,6 +201,18 @@ impl SimplePipelineDesc for UnlitPipelineDesc {
                 ],
             );
 
+        if self.enable_vertex_color {
+            vertex_state_builder.new_buffer_descriptor(
+                mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                wgpu::InputStepMode::Vertex,
+                vec![wgpu::VertexAttributeDescriptor {
+                    format: wgpu::VertexFormat::Float4,
+                    offset: 0,
+                    shader_location: 4,
+                }],
+            );
+        }
+
         vertex_state_builder
     }
 
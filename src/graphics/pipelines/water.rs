@@ -0,0 +1,234 @@
+use legion::prelude::Resources;
+
+use crate::{
+    assets::mesh::MeshVertexData,
+    graphics::{
+        pipeline_manager::{ComputePipelineDesc, PipelineDesc, PipelineManager},
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::{GPUResourceManager, OCEAN_MAP_FORMAT},
+    },
+    AssetManager,
+};
+use std::{borrow::Cow, sync::Arc};
+
+fn uniform_entry(binding: u32, dynamic: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry::new(
+        binding,
+        wgpu::ShaderStage::COMPUTE,
+        wgpu::BindingType::UniformBuffer {
+            dynamic,
+            min_binding_size: None,
+        },
+    )
+}
+
+fn image_entry(
+    binding: u32,
+    format: wgpu::TextureFormat,
+    readonly: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry::new(
+        binding,
+        wgpu::ShaderStage::COMPUTE,
+        wgpu::BindingType::StorageTexture {
+            dimension: wgpu::TextureViewDimension::D2,
+            format,
+            readonly,
+        },
+    )
+}
+
+fn create_layout(
+    device: &wgpu::Device,
+    label: &'static str,
+    entries: Vec<wgpu::BindGroupLayoutEntry>,
+) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: Cow::Owned(entries),
+        label: Some(Cow::Borrowed(label)),
+    })
+}
+
+/// Creates the layouts of the ocean compute passes and the layout the water is drawn with.
+pub fn create_ocean_bindgroup_layouts(
+    device: &wgpu::Device,
+    resource_manager: &GPUResourceManager,
+) {
+    let spectrum = wgpu::TextureFormat::Rgba32Float;
+
+    // Ocean settings, the initial spectrum and the grid's vertices.
+    let init_layout = create_layout(
+        device,
+        "ocean_init_layout",
+        vec![
+            uniform_entry(0, false),
+            image_entry(1, spectrum, false),
+            wgpu::BindGroupLayoutEntry::new(
+                2,
+                wgpu::ShaderStage::COMPUTE,
+                wgpu::BindingType::StorageBuffer {
+                    dynamic: false,
+                    readonly: false,
+                    min_binding_size: None,
+                },
+            ),
+        ],
+    );
+    // Ocean settings, the initial spectrum and this frame's spectrum.
+    let time_layout = create_layout(
+        device,
+        "ocean_time_layout",
+        vec![
+            uniform_entry(0, false),
+            image_entry(1, spectrum, true),
+            image_entry(2, spectrum, false),
+        ],
+    );
+    // The settings of each FFT pass at a dynamic offset, and the textures it reads and writes.
+    let fft_layout = create_layout(
+        device,
+        "ocean_fft_layout",
+        vec![
+            uniform_entry(0, true),
+            image_entry(1, spectrum, true),
+            image_entry(2, spectrum, false),
+        ],
+    );
+    // Ocean settings, the transformed spectrum and the height and normal maps.
+    let maps_layout = create_layout(
+        device,
+        "ocean_maps_layout",
+        vec![
+            uniform_entry(0, false),
+            image_entry(1, spectrum, true),
+            image_entry(2, OCEAN_MAP_FORMAT, false),
+            image_entry(3, OCEAN_MAP_FORMAT, false),
+        ],
+    );
+
+    let map_entry = |binding| {
+        wgpu::BindGroupLayoutEntry::new(
+            binding,
+            wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                component_type: wgpu::TextureComponentType::Float,
+                dimension: wgpu::TextureViewDimension::D2,
+            },
+        )
+    };
+    let render_layout = create_layout(
+        device,
+        "ocean_render_layout",
+        vec![
+            wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::Sampler { comparison: false },
+            ),
+            // Height map
+            map_entry(1),
+            // Normal map
+            map_entry(2),
+        ],
+    );
+
+    resource_manager.add_bind_group_layout("ocean_init_layout", init_layout);
+    resource_manager.add_bind_group_layout("ocean_time_layout", time_layout);
+    resource_manager.add_bind_group_layout("ocean_fft_layout", fft_layout);
+    resource_manager.add_bind_group_layout("ocean_maps_layout", maps_layout);
+    resource_manager.add_bind_group_layout("ocean_render_layout", render_layout);
+}
+
+/// Draws an `OceanSurface`'s grid, displaced by the height map in the vertex shader.
+/// The fragment shader reads the normal map and blends the probe's reflection in with a Fresnel term.
+pub struct WaterPipelineDesc {
+    pub surface: PipelineDesc,
+}
+
+impl WaterPipelineDesc {
+    pub fn new() -> Self {
+        let mut surface = PipelineDesc::default();
+        surface.shader = "core/shaders/water/water.shader".to_string();
+        surface.color_states[0].format = HDR_FORMAT;
+        surface.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        });
+        surface.layouts = vec![
+            "locals".to_string(),
+            "globals".to_string(),
+            "ocean_render_layout".to_string(),
+            "probe_material_layout".to_string(),
+        ];
+        // The surface can be seen from below.
+        surface.cull_mode = wgpu::CullMode::None;
+        surface
+            .vertex_state
+            .set_index_format(wgpu::IndexFormat::Uint32)
+            .new_buffer_descriptor(
+                std::mem::size_of::<MeshVertexData>() as wgpu::BufferAddress,
+                wgpu::InputStepMode::Vertex,
+                wgpu::vertex_attr_array![0 => Float3, 1 => Float3, 2 => Float2, 3 => Float4]
+                    .to_vec(),
+            );
+
+        Self { surface }
+    }
+}
+
+/// Creates the compute pipelines that simulate oceans and the pipeline that draws them.
+/// Note: This needs to be called after the pbr pipelines are created as it uses their probe layout.
+pub fn create(resources: &mut Resources) {
+    let asset_manager = resources.get::<AssetManager>().unwrap();
+    let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+    let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+    let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+    create_ocean_bindgroup_layouts(&device, &resource_manager);
+
+    let compute_pipelines = [
+        ("ocean_spectrum", "ocean_init_layout"),
+        ("ocean_grid", "ocean_init_layout"),
+        ("ocean_time", "ocean_time_layout"),
+        ("ocean_fft", "ocean_fft_layout"),
+        ("ocean_maps", "ocean_maps_layout"),
+    ];
+    for (index, (name, layout)) in compute_pipelines.iter().enumerate() {
+        let mut desc = ComputePipelineDesc::new(format!("core/shaders/water/{}.shader", name));
+        desc.layouts = vec![layout.to_string()];
+        // Each pass runs after the one before it.
+        let dependencies = if index > 0 {
+            vec![compute_pipelines[index - 1].0]
+        } else {
+            vec![]
+        };
+        pipeline_manager
+            .add_compute_pipeline(
+                name,
+                &desc,
+                dependencies,
+                &device,
+                &asset_manager,
+                resource_manager.clone(),
+            )
+            .unwrap();
+    }
+
+    let water_desc = WaterPipelineDesc::new();
+    pipeline_manager
+        .add_pipeline(
+            "water",
+            &water_desc.surface,
+            vec!["pbr", "pbr_transparent", "deferred_lighting", "ocean_maps"],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+}
@@ -0,0 +1,97 @@
+use legion::prelude::Resources;
+
+use crate::{
+    assets::mesh::MeshVertexData,
+    graphics::{
+        pipeline_manager::{PipelineDesc, PipelineManager},
+        renderer::{DEPTH_FORMAT, HDR_FORMAT},
+        resources::GPUResourceManager,
+    },
+    AssetManager,
+};
+use std::sync::Arc;
+
+/// The color wireframes are drawn with, pushed to the fragment shader as a push constant.
+pub const WIREFRAME_COLOR: [f32; 4] = [0.2, 1.0, 0.1, 1.0];
+// Without line mode the triangles are filled, so they're blended over the mesh instead of hiding it.
+const FILLED_WIREFRAME_COLOR: [f32; 4] = [0.2, 1.0, 0.1, 0.25];
+
+/// The range the wireframe color is pushed to.
+pub fn push_constant_range() -> wgpu::PushConstantRange {
+    wgpu::PushConstantRange {
+        stages: wgpu::ShaderStage::FRAGMENT,
+        range: 0..std::mem::size_of::<[f32; 4]>() as u32,
+    }
+}
+
+/// Returns the color to push for the wireframe pipeline.
+/// Backends without `NON_FILL_POLYGON_MODE` draw filled triangles, which get a transparent color.
+pub fn color(features: wgpu::Features) -> [f32; 4] {
+    if features.contains(wgpu::Features::NON_FILL_POLYGON_MODE) {
+        WIREFRAME_COLOR
+    } else {
+        FILLED_WIREFRAME_COLOR
+    }
+}
+
+/// Creates the pipeline used to draw `DrawWireframe` entities.
+/// It needs `PUSH_CONSTANTS`, without them the pipeline isn't created and those entities are only drawn with their material.
+pub fn create(resources: &Resources) {
+    let asset_manager = resources.get::<AssetManager>().unwrap();
+    let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
+    let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+    let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+
+    let features = device.features();
+    if !features.contains(wgpu::Features::PUSH_CONSTANTS) {
+        log::warn!("Wireframes need push constants which aren't supported by this backend.");
+        return;
+    }
+
+    let mut wireframe_desc = PipelineDesc::default();
+    wireframe_desc.shader = "core/shaders/wireframe.shader".to_string();
+    wireframe_desc.primitive_topology = wgpu::PrimitiveTopology::TriangleList;
+    if features.contains(wgpu::Features::NON_FILL_POLYGON_MODE) {
+        wireframe_desc.polygon_mode = wgpu::PolygonMode::Line;
+    } else {
+        log::warn!("Line polygon mode isn't supported, wireframes are drawn as filled triangles.");
+    }
+    wireframe_desc.color_states[0].format = HDR_FORMAT;
+    wireframe_desc.color_states[0].color_blend = wgpu::BlendDescriptor {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+        operation: wgpu::BlendOperation::Add,
+    };
+    // The lines lie on the mesh's own surface so equal depths have to pass.
+    wireframe_desc.depth_state = Some(wgpu::DepthStencilStateDescriptor {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::LessEqual,
+        stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+        stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+        stencil_read_mask: 0,
+        stencil_write_mask: 0,
+    });
+    wireframe_desc.layouts = vec!["locals".to_string(), "globals".to_string()];
+    wireframe_desc.push_constant_ranges = vec![push_constant_range()];
+    // Meshes share the pbr vertex buffers, only the position is read.
+    wireframe_desc
+        .vertex_state
+        .set_index_format(wgpu::IndexFormat::Uint32)
+        .new_buffer_descriptor(
+            std::mem::size_of::<MeshVertexData>() as wgpu::BufferAddress,
+            wgpu::InputStepMode::Vertex,
+            wgpu::vertex_attr_array![0 => Float3].to_vec(),
+        );
+
+    pipeline_manager
+        .add_pipeline(
+            "wireframe",
+            &wireframe_desc,
+            vec!["pbr", "deferred_lighting"],
+            &device,
+            &asset_manager,
+            resource_manager.clone(),
+        )
+        .unwrap();
+}
@@ -3,18 +3,123 @@ use super::{
     SimplePipeline, SimplePipelineDesc,
 };
 use crate::AssetManager;
+use futures::FutureExt;
 use legion::systems::resource::Resources;
-use solvent::DepGraph;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crossbeam::queue::{ArrayQueue, PushError};
 
-use crossbeam::queue::ArrayQueue;
+/// Priorities for `CommandQueueItem`, lower priorities are submitted first.
+/// Items with the same priority are submitted in the pipeline manager's dependency order.
+pub struct RenderPriority;
+
+impl RenderPriority {
+    pub const SHADOW: i32 = -100;
+    pub const OPAQUE: i32 = 0;
+    pub const TRANSPARENT: i32 = 100;
+    pub const POST_PROCESS: i32 = 150;
+    pub const UI: i32 = 200;
+}
 
 pub struct CommandQueueItem {
     pub name: String,
     pub buffer: wgpu::CommandBuffer,
+    /// See `RenderPriority`.
+    pub priority: i32,
+}
+
+/// Returned by `CommandBufferQueue::flush_sorted`, pass it to `CommandBufferQueue::wait_for_flush`.
+pub struct SubmissionIndex {
+    // Copied into by the last command buffer of the submission, so mapping it only finishes once the whole submission has.
+    _fence: wgpu::Buffer,
+    mapped: Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>,
+}
+
+/// Command buffers recorded by the render systems this frame waiting to be submitted.
+pub struct CommandBufferQueue {
+    items: ArrayQueue<CommandQueueItem>,
+}
+
+impl CommandBufferQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: ArrayQueue::new(capacity),
+        }
+    }
+
+    pub fn push(&self, item: CommandQueueItem) -> Result<(), PushError<CommandQueueItem>> {
+        self.items.push(item)
+    }
+
+    /// Removes every queued item, sorted by priority ascending.
+    /// Items with the same priority keep the order they were pushed in.
+    pub fn drain(&self) -> Vec<CommandQueueItem> {
+        drain_sorted(&self.items, |item| item.priority)
+    }
+
+    /// Submits every queued item in priority order and returns a handle to wait on the submission.
+    /// Meant for work outside of the frame, like readbacks. The frame is submitted in the pipeline manager's order instead.
+    pub fn flush_sorted(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> SubmissionIndex {
+        let mut command_buffers: Vec<wgpu::CommandBuffer> =
+            self.drain().into_iter().map(|item| item.buffer).collect();
+
+        let fence = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("flush_fence"),
+            size: 4,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let fence_source = device.create_buffer_with_data(&[0; 4], wgpu::BufferUsage::COPY_SRC);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("flush_fence"),
+        });
+        encoder.copy_buffer_to_buffer(&fence_source, 0, &fence, 0, 4);
+        command_buffers.push(encoder.finish());
+        queue.submit(command_buffers);
+
+        let mapped = Box::pin(fence.slice(..).map_async(wgpu::MapMode::Read));
+        SubmissionIndex {
+            _fence: fence,
+            mapped,
+        }
+    }
+
+    /// Polls the device until the submission has finished on the GPU.
+    /// Returns false if it didn't finish within `timeout` or the device was lost.
+    pub fn wait_for_flush(
+        device: &wgpu::Device,
+        mut submission: SubmissionIndex,
+        timeout: Duration,
+    ) -> bool {
+        let start = Instant::now();
+        loop {
+            device.poll(wgpu::Maintain::Poll);
+            if let Some(result) = (&mut submission.mapped).now_or_never() {
+                return result.is_ok();
+            }
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            std::thread::yield_now();
+        }
+    }
 }
 
-pub type CommandBufferQueue = ArrayQueue<CommandQueueItem>;
+fn drain_sorted<T>(queue: &ArrayQueue<T>, priority: impl Fn(&T) -> i32) -> Vec<T> {
+    let mut items = Vec::with_capacity(queue.len());
+    while let Ok(item) = queue.pop() {
+        items.push(item);
+    }
+    // Stable so items with the same priority aren't reordered.
+    items.sort_by_key(|item| priority(item));
+    items
+}
 
 pub struct RenderGraphNode {
     pub name: String,
@@ -23,19 +128,38 @@ pub struct RenderGraphNode {
     pub use_output_from_dependency: bool,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderGraphError {
+    /// Thrown when the dependencies between nodes form a cycle.
+    /// Contains the nodes that are part of the cycle in execution order, nodes that only depend on it aren't included.
+    Cycle(Vec<String>),
+    /// Thrown when two nodes write the same resource but neither depends on the other, so the final contents are undefined.
+    /// See `PipelineManager::declare_access`.
+    WriteConflict {
+        node_a: String,
+        node_b: String,
+        resource: String,
+    },
+}
+
 pub struct RenderGraph {
     pub(crate) nodes: HashMap<String, RenderGraphNode>,
     pub(crate) outputs: HashMap<String, Option<RenderTarget>>,
-    dep_graph: DepGraph<String>,
+    // Node names in the order they were added, used to keep ordering stable for nodes without dependencies.
+    insertion_order: Vec<String>,
+    // (from, to) where `from` must execute before `to`.
+    edges: Vec<(String, String)>,
+    // Cached result of `build`, cleared whenever a node or dependency is added.
+    order: Option<Vec<String>>,
+    /// Culls opaque pbr meshes with a compute shader and draws them with indirect draws instead of culling them on the CPU.
+    /// See `GpuDrivenRenderer`. Off by default.
+    pub use_gpu_driven: bool,
 }
 
 /// DEPRECIATED DO NOT USE.
 impl RenderGraph {
     /// DEPRECIATED DO NOT USE.
     pub(crate) fn new(resources: &mut Resources, create_command_queue: bool) -> Self {
-        let mut dep_graph = DepGraph::new();
-        dep_graph.register_node("root".to_string());
-
         if create_command_queue {
             let command_queue = CommandBufferQueue::new(50);
             resources.insert(command_queue);
@@ -44,7 +168,10 @@ impl RenderGraph {
         RenderGraph {
             nodes: HashMap::new(),
             outputs: HashMap::new(),
-            dep_graph,
+            insertion_order: Vec::new(),
+            edges: Vec::new(),
+            order: None,
+            use_gpu_driven: false,
         }
     }
 
@@ -80,17 +207,37 @@ impl RenderGraph {
             simple_pipeline: built_pipeline,
             use_output_from_dependency,
         };
+        if !self.insertion_order.contains(&name) {
+            self.insertion_order.push(name.clone());
+        }
         self.nodes.insert(name.clone(), node);
         self.outputs.insert(name.clone(), output);
-        self.dep_graph.register_node(name.clone());
-        if dependency.len() > 0 {
-            let dependency = dependency
-                .iter()
-                .map(|name| name.to_string())
-                .collect::<Vec<String>>();
-            self.dep_graph
-                .register_dependencies(name.clone(), dependency);
+        for dependency in dependency {
+            self.add_dependency(dependency, &name);
+        }
+        self.order = None;
+    }
+
+    /// Declares that the node `from` must execute before the node `to`.
+    /// Nodes without any dependencies execute in the order they were added.
+    pub fn add_dependency(&mut self, from: &str, to: &str) {
+        let edge = (from.to_string(), to.to_string());
+        if !self.edges.contains(&edge) {
+            self.edges.push(edge);
         }
+        self.order = None;
+    }
+
+    /// Sorts the nodes based off of their dependencies.
+    /// Returns `RenderGraphError::Cycle` if the dependencies contain a cycle.
+    pub fn build(&mut self) -> Result<(), RenderGraphError> {
+        let order = self.sort()?;
+        self.order = Some(order);
+        Ok(())
+    }
+
+    fn sort(&self) -> Result<Vec<String>, RenderGraphError> {
+        topological_sort(&self.insertion_order, &self.edges)
     }
 
     /// Allows you to take the output render target for a given node.
@@ -122,27 +269,14 @@ impl RenderGraph {
     }
 
     /// DEPRECIATED DO NOT USE.
-    fn get_order(&self) -> Vec<String> {
-        let mut order = Vec::new();
-        for (name, _) in self.nodes.iter() {
-            let dependencies = self.dep_graph.dependencies_of(&name);
-            if dependencies.is_ok() {
-                for node in dependencies.unwrap() {
-                    match node {
-                        Ok(n) => {
-                            if !order.contains(n) {
-                                order.push(n.clone());
-                            }
-                        }
-                        Err(e) => panic!("Solvent error detected: {:?}", e),
-                    }
-                }
-            }
+    fn get_order(&self) -> Result<Vec<String>, RenderGraphError> {
+        match &self.order {
+            Some(order) => Ok(order.clone()),
+            None => self.sort(),
         }
-
-        order
     }
 
+    /// Returns the error from `build` if the graph wasn't built first and can't be sorted.
     /// DEPRECIATED DO NOT USE.
     pub(crate) fn render_one_time(
         &mut self,
@@ -152,25 +286,20 @@ impl RenderGraph {
         world: &mut legion::world::World,
         frame: Option<&wgpu::SwapChainTexture>,
         forward_depth: Option<&wgpu::TextureView>,
-    ) -> wgpu::CommandBuffer {
+    ) -> Result<wgpu::CommandBuffer, RenderGraphError> {
+        let order = self.get_order()?;
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("main"),
         });
 
-        let order = self.get_order();
-
         for name in order {
             let node = self.nodes.get_mut(&name).unwrap();
             let mut input = None;
             if node.use_output_from_dependency {
-                let dependencies = self.dep_graph.dependencies_of(&name);
-                if dependencies.is_ok() {
-                    let mut dependencies = dependencies.unwrap();
-                    let dependency = dependencies.next().unwrap();
-                    if dependency.is_ok() {
-                        let dependency = dependency.unwrap().to_string();
-                        input = self.outputs.get(&dependency).unwrap().as_ref();
-                    }
+                let dependency = self.edges.iter().find(|(_, to)| to == &name);
+                if dependency.is_some() {
+                    let (dependency, _) = dependency.unwrap();
+                    input = self.outputs.get(dependency).unwrap().as_ref();
                 }
             }
             let output = self.outputs.get(&name).unwrap().as_ref();
@@ -200,32 +329,148 @@ impl RenderGraph {
             }
         }
 
-        encoder.finish()
+        Ok(encoder.finish())
     }
 
     /// DEPRECIATED DO NOT USE.
     pub fn collect_buffers(
         &self,
         command_queue: &mut CommandBufferQueue,
-    ) -> Vec<wgpu::CommandBuffer> {
-        let mut command_buffers = Vec::new();
-        let mut queue_items = Vec::new();
-        while let Ok(command) = command_queue.pop() {
-            queue_items.push(command);
+    ) -> Result<Vec<wgpu::CommandBuffer>, RenderGraphError> {
+        let ordering = self.get_order()?;
+
+        let mut queue_items = command_queue.drain();
+        queue_items.retain(|queue_item| ordering.contains(&queue_item.name));
+        queue_items.sort_by_key(|queue_item| {
+            let index = ordering
+                .iter()
+                .position(|order| order == &queue_item.name)
+                .unwrap();
+            (queue_item.priority, index)
+        });
+
+        Ok(queue_items
+            .into_iter()
+            .map(|queue_item| queue_item.buffer)
+            .collect())
+    }
+}
+
+// Kahn's algorithm, ties are broken by insertion order so nodes without dependencies keep the order they were added in.
+// Edges that reference unknown nodes are ignored.
+pub(crate) fn topological_sort(
+    nodes: &[String],
+    edges: &[(String, String)],
+) -> Result<Vec<String>, RenderGraphError> {
+    let edges: Vec<&(String, String)> = edges
+        .iter()
+        .filter(|(from, to)| nodes.contains(from) && nodes.contains(to))
+        .collect();
+
+    let mut in_degree: HashMap<&String, usize> = nodes.iter().map(|name| (name, 0)).collect();
+    for (_, to) in edges.iter() {
+        *in_degree.get_mut(to).unwrap() += 1;
+    }
+
+    let mut remaining: Vec<&String> = nodes.iter().collect();
+    let mut order = Vec::new();
+    while let Some(index) = remaining.iter().position(|name| in_degree[name] == 0) {
+        let name = remaining.remove(index);
+        for (_, to) in edges.iter().filter(|(from, _)| from == name) {
+            *in_degree.get_mut(to).unwrap() -= 1;
         }
+        order.push(name.clone());
+    }
+
+    if remaining.len() > 0 {
+        return Err(RenderGraphError::Cycle(find_cycle(&remaining, &edges)));
+    }
+
+    Ok(order)
+}
 
-        let ordering = self.get_order();
+// Finds a cycle among the nodes `topological_sort` couldn't order, some of them might only depend on a cycle.
+// Every one of them has a predecessor that's also left, so walking back through those has to repeat a node.
+// The cycle is returned in execution order starting with the node that was added first.
+fn find_cycle(remaining: &[&String], edges: &[&(String, String)]) -> Vec<String> {
+    let mut path = vec![remaining[0]];
+    loop {
+        let node = path[path.len() - 1];
+        let predecessor = edges
+            .iter()
+            .find(|(from, to)| to == node && remaining.contains(&from))
+            .map(|(from, _)| from)
+            .unwrap();
 
-        for order in ordering {
-            while let Some(queue_item_index) = queue_items
+        if let Some(start) = path.iter().position(|name| *name == predecessor) {
+            let mut cycle: Vec<&String> = path.split_off(start);
+            cycle.reverse();
+            let first = cycle
                 .iter()
-                .position(|queue_item| queue_item.name == order)
-            {
-                let queue_item = queue_items.remove(queue_item_index);
-                command_buffers.push(queue_item.buffer);
-            }
+                .enumerate()
+                .min_by_key(|(_, name)| remaining.iter().position(|other| other == *name))
+                .map(|(index, _)| index)
+                .unwrap();
+            cycle.rotate_left(first);
+            return cycle.into_iter().cloned().collect();
         }
+        path.push(predecessor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{topological_sort, RenderGraphError};
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn edges(edges: &[(&str, &str)]) -> Vec<(String, String)> {
+        edges
+            .iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn should_keep_insertion_order_without_edges() {
+        let nodes = names(&["c", "a", "b"]);
+        let order = topological_sort(&nodes, &[]).unwrap();
+        assert_eq!(order, nodes);
+    }
+
+    #[test]
+    fn should_sort_dependencies_first() {
+        let nodes = names(&["lighting", "geometry", "ui"]);
+        let order =
+            topological_sort(&nodes, &edges(&[("geometry", "lighting"), ("lighting", "ui")]))
+                .unwrap();
+        assert_eq!(order, names(&["geometry", "lighting", "ui"]));
+    }
 
-        command_buffers
+    #[test]
+    fn should_ignore_unknown_nodes() {
+        let nodes = names(&["a", "b"]);
+        let order = topological_sort(&nodes, &edges(&[("root", "b"), ("b", "a")])).unwrap();
+        assert_eq!(order, names(&["b", "a"]));
+    }
+
+    #[test]
+    fn should_detect_cycles() {
+        let nodes = names(&["a", "b", "c"]);
+        let result = topological_sort(&nodes, &edges(&[("a", "b"), ("b", "a")]));
+        assert_eq!(result, Err(RenderGraphError::Cycle(names(&["a", "b"]))));
+    }
+
+    #[test]
+    fn should_only_return_nodes_in_the_cycle() {
+        // "d" can't be ordered because it depends on the cycle, but it isn't part of it.
+        let nodes = names(&["d", "c", "a", "b"]);
+        let result = topological_sort(
+            &nodes,
+            &edges(&[("a", "b"), ("b", "c"), ("c", "a"), ("b", "d")]),
+        );
+        assert_eq!(result, Err(RenderGraphError::Cycle(names(&["c", "a", "b"]))));
     }
 }
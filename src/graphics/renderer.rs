@@ -1,12 +1,113 @@
-use super::{resources::GPUResourceManager, pipeline_manager::PipelineManager, shadows::ShadowQuality};
+use super::{
+    pipeline_manager::PipelineManager,
+    pipelines::FogParams,
+    resources::{
+        CommandEncoderPool, GPUResourceManager, RenderTarget, RenderTargetPool,
+        TransformUploadStrategy, BINDLESS_FEATURES,
+    },
+    shadows::{CascadeShadowManager, CsmConfig, ShadowQuality},
+};
 use legion::systems::resource::Resources;
 use std::sync::Arc;
 
 pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 pub const FRAME_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
+/// The format of the hdr framebuffer the scene is rendered into.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// The most render graph nodes the gpu profiler will time in a frame.
+pub(crate) const MAX_PROFILER_SCOPES: u32 = 64;
+// How many unused render targets of the same description are kept around.
+const MAX_POOLED_RENDER_TARGETS: usize = 4;
+// How many finished command encoder wrappers are kept around, about one per render system.
+const MAX_POOLED_ENCODERS: usize = 32;
 
 pub struct DepthTexture(pub wgpu::TextureView);
 
+/// Multisample anti-aliasing settings. Changing the sample count at runtime recreates
+/// the render targets and pipelines that depend on it.
+#[derive(Debug, Clone, Copy)]
+pub struct MsaaConfig {
+    pub sample_count: u32,
+}
+
+impl Default for MsaaConfig {
+    fn default() -> Self {
+        Self { sample_count: 1 }
+    }
+}
+
+/// Renderer settings that can be changed at runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderSettings {
+    /// Renders the depth of opaque meshes before shading them so the pbr shader only runs once per pixel.
+    /// Helps scenes with a lot of overdraw, the extra pass costs more than it saves in simple scenes.
+    /// Note: Only used by forward rendering without gpu driven draws.
+    pub use_depth_prepass: bool,
+    /// Measures how long each node takes on the GPU, read the timings from the `GpuProfiler` resource.
+    /// Timings are read back a couple of frames late so the CPU doesn't wait on the GPU.
+    /// Note: Ignored when the adapter doesn't support `wgpu::Features::TIMESTAMP_QUERY`.
+    pub gpu_profiling: bool,
+}
+
+/// Pipelines that render into the msaa framebuffer and need to match it's sample count.
+pub(crate) const MSAA_PIPELINES: [&str; 15] = [
+    "pbr",
+    "pbr_transparent",
+    "pbr_depth_equal",
+    "depth_prepass",
+    "pbr_push_constants",
+    "pbr_transparent_push_constants",
+    "skybox",
+    "realtime_skybox",
+    "debug_draw",
+    "wireframe",
+    "particles",
+    "sprite",
+    "sdf_text",
+    "decal_forward",
+    "water",
+];
+
+/// When msaa is on the scene renders into this and is resolved into the hdr framebuffer.
+pub struct MsaaFramebuffer(pub Option<RenderTarget>);
+
+impl MsaaFramebuffer {
+    /// Returns the view to render into and the view to resolve to.
+    pub fn attachments<'a>(
+        &'a self,
+        view: &'a wgpu::TextureView,
+    ) -> (&'a wgpu::TextureView, Option<&'a wgpu::TextureView>) {
+        match &self.0 {
+            Some(target) => (&target.texture_view, Some(view)),
+            None => (view, None),
+        }
+    }
+}
+
+pub(crate) fn create_depth_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> DepthTexture {
+    let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        // Sampled when `HiZCulling` builds it's hi-z buffer from the previous frame's depth.
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        label: None,
+    });
+    DepthTexture(depth_texture.create_default_view())
+}
+
 pub struct Renderer {
     pub(crate) surface: wgpu::Surface,
     pub size: winit::dpi::PhysicalSize<u32>,
@@ -39,9 +140,17 @@ impl Renderer {
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    features: adapter_features & wgpu::Features::PUSH_CONSTANTS,
+                    features: adapter_features
+                        & (wgpu::Features::PUSH_CONSTANTS
+                            | wgpu::Features::TIMESTAMP_QUERY
+                            | wgpu::Features::NON_FILL_POLYGON_MODE
+                            // Lets DDS textures stay compressed on the GPU, see `TextureManager::load_dds`.
+                            | wgpu::Features::TEXTURE_COMPRESSION_BC
+                            | BINDLESS_FEATURES),
                     limits:  wgpu::Limits {
                         max_push_constant_size: 128,
+                        // The pbr pipeline binds the lights buffer at set 4.
+                        max_bind_groups: 5,
                         ..wgpu::Limits::default()
                     },
                     shader_validation: true,
@@ -60,20 +169,12 @@ impl Renderer {
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
-        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
-            size: wgpu::Extent3d {
-                width: sc_desc.width,
-                height: sc_desc.height,
-                depth: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: DEPTH_FORMAT,
-            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-            label: None,
-        });
+        let depth_texture = create_depth_texture(&device, sc_desc.width, sc_desc.height, 1);
         let device = Arc::new(device);
+        log::info!(
+            "Bindless texture arrays {}.",
+            if GPUResourceManager::supports_bindless(&device) { "are supported" } else { "aren't supported" }
+        );
 
         // Omni Shadow manager
         // TODO: Expose this as configurable to the user.
@@ -81,17 +182,28 @@ impl Renderer {
             device.clone(),
             ShadowQuality::Medium
         );
+        let cascade_manager = CascadeShadowManager::new(device.clone(), ShadowQuality::Medium);
         
-        let gpu_resource_manager = Arc::new(GPUResourceManager::new(device.clone(), &omni_manager));
+        let gpu_resource_manager = Arc::new(GPUResourceManager::new(device.clone(), &omni_manager, &cascade_manager));
         let pipeline_manager = PipelineManager::new();
 
         resources.insert(omni_manager);
+        resources.insert(cascade_manager);
+        resources.insert(CsmConfig::default());
         resources.insert(pipeline_manager);
         resources.insert(gpu_resource_manager);
         resources.insert(sc_desc);
-        resources.insert(Arc::new(queue));
+        let queue = Arc::new(queue);
+        resources.insert(queue.clone());
         resources.insert(device.clone());
-        resources.insert(DepthTexture(depth_texture.create_default_view()));
+        resources.insert(depth_texture);
+        resources.insert(MsaaConfig::default());
+        resources.insert(RenderSettings::default());
+        resources.insert(FogParams::default());
+        resources.insert(MsaaFramebuffer(None));
+        resources.insert(RenderTargetPool::new(device.clone(), MAX_POOLED_RENDER_TARGETS));
+        resources.insert(CommandEncoderPool::new(device.clone(), MAX_POOLED_ENCODERS));
+        resources.insert(TransformUploadStrategy::select(device.features(), &device.limits()));
         
         Self {
             surface,
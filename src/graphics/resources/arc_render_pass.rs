@@ -60,7 +60,17 @@ impl<'a> ArcRenderPass<'a> {
             .draw_indexed(indices, base_vertex, instances);
     }
 
+    /// Draws using the arguments stored at `offset` in `buffer`, see `DrawIndexedIndirectArgs`.
+    pub fn draw_indexed_indirect(&mut self, buffer: Arc<wgpu::Buffer>, offset: wgpu::BufferAddress) {
+        let buffer = self.buffer_arena.alloc(buffer);
+        self.render_pass.draw_indexed_indirect(buffer, offset);
+    }
+
     pub fn set_viewport(&mut self, x: f32, y: f32, w: f32, h: f32, min_depth: f32, max_depth: f32) {
         self.render_pass.set_viewport(x, y, w, h, min_depth, max_depth);
     }
+
+    pub fn set_scissor_rect(&mut self, x: u32, y: u32, w: u32, h: u32) {
+        self.render_pass.set_scissor_rect(x, y, w, h);
+    }
 }
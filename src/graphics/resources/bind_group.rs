@@ -12,3 +12,39 @@ impl BindGroup {
         }
     }
 }
+
+/// A hash of the entries a bind group layout was created from.
+/// Bind groups created with a layout that has a different hash can't be used with the current pipelines.
+/// The hash of the "pbr_material_layout" is inserted as a resource by the pbr pipeline, see the `validate_materials` system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindGroupLayoutHash(pub u64);
+
+impl BindGroupLayoutHash {
+    pub fn new(entries: &[wgpu::BindGroupLayoutEntry]) -> Self {
+        use std::hash::{Hash, Hasher};
+        // Hashed through the debug output so every field of an entry is covered.
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        format!("{:?}", entries).hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BindGroupLayoutHash;
+
+    #[test]
+    fn should_hash_layout_entries() {
+        let entry = |binding| {
+            wgpu::BindGroupLayoutEntry::new(
+                binding,
+                wgpu::ShaderStage::FRAGMENT,
+                wgpu::BindingType::Sampler { comparison: false },
+            )
+        };
+        let hash = BindGroupLayoutHash::new(&[entry(0), entry(1)]);
+        assert_eq!(hash, BindGroupLayoutHash::new(&[entry(0), entry(1)]));
+        assert_ne!(hash, BindGroupLayoutHash::new(&[entry(0), entry(2)]));
+        assert_ne!(hash, BindGroupLayoutHash::new(&[entry(0)]));
+    }
+}
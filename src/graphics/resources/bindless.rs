@@ -0,0 +1,163 @@
+use std::{
+    borrow::Cow,
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+};
+
+// The binding of the texture array and the sampler in a bindless bind group.
+const TEXTURES_BINDING: u32 = 0;
+const SAMPLER_BINDING: u32 = 1;
+
+/// The features a device needs for `GPUResourceManager::create_bindless_texture_array`.
+pub const BINDLESS_FEATURES: wgpu::Features = wgpu::Features::from_bits_truncate(
+    wgpu::Features::SAMPLED_TEXTURE_BINDING_ARRAY.bits()
+        | wgpu::Features::SAMPLED_TEXTURE_ARRAY_DYNAMIC_INDEXING.bits(),
+);
+
+struct BindlessSlots {
+    views: Vec<wgpu::TextureView>,
+    len: u32,
+    // Rebuilt the next time it's requested after a view is inserted.
+    bind_group: Option<Arc<wgpu::BindGroup>>,
+}
+
+/// An array of textures bound once and indexed in shaders, so draws using different textures don't rebind anything.
+/// Bound as `layout(set = N, binding = 0) uniform texture2D textures[capacity];` with a sampler at binding 1,
+/// the material passes the slot returned by `insert` to the shader as an index.
+/// Create one with `GPUResourceManager::create_bindless_texture_array`.
+pub struct BindlessArrayHandle {
+    device: Arc<wgpu::Device>,
+    layout: Arc<wgpu::BindGroupLayout>,
+    sampler: wgpu::Sampler,
+    // Empty slots hold views of this so the array is always full, wgpu requires a view for every element.
+    placeholder: wgpu::Texture,
+    capacity: u32,
+    slots: Mutex<BindlessSlots>,
+}
+
+impl BindlessArrayHandle {
+    pub(crate) fn new(device: Arc<wgpu::Device>, name: &str, capacity: u32) -> Self {
+        let mut textures = wgpu::BindGroupLayoutEntry::new(
+            TEXTURES_BINDING,
+            wgpu::ShaderStage::FRAGMENT,
+            wgpu::BindingType::SampledTexture {
+                dimension: wgpu::TextureViewDimension::D2,
+                component_type: wgpu::TextureComponentType::Float,
+                multisampled: false,
+            },
+        );
+        textures.count = NonZeroU32::new(capacity);
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Owned(vec![
+                textures,
+                wgpu::BindGroupLayoutEntry::new(
+                    SAMPLER_BINDING,
+                    wgpu::ShaderStage::FRAGMENT,
+                    wgpu::BindingType::Sampler { comparison: false },
+                ),
+            ]),
+            label: Some(Cow::Owned(name.to_string())),
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(name),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let placeholder = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::SAMPLED,
+            label: Some("bindless_placeholder"),
+        });
+        let views = (0..capacity)
+            .map(|_| placeholder.create_default_view())
+            .collect();
+
+        Self {
+            device,
+            layout: Arc::new(layout),
+            sampler,
+            placeholder,
+            capacity,
+            slots: Mutex::new(BindlessSlots {
+                views,
+                len: 0,
+                bind_group: None,
+            }),
+        }
+    }
+
+    /// Adds a texture to the array and returns the slot shaders index it with.
+    /// Takes the view because wgpu binds arrays of owned views.
+    /// Panics if the array is full.
+    pub fn insert(&self, view: wgpu::TextureView) -> u32 {
+        let mut slots = self.slots.lock().unwrap();
+        let slot = slots.len;
+        if slot >= self.capacity {
+            panic!(
+                "Bindless texture array is full, it holds {} textures.",
+                self.capacity
+            );
+        }
+        slots.views[slot as usize] = view;
+        slots.len += 1;
+        slots.bind_group = None;
+        slot
+    }
+
+    /// How many textures were inserted.
+    pub fn len(&self) -> u32 {
+        self.slots.lock().unwrap().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn layout(&self) -> Arc<wgpu::BindGroupLayout> {
+        self.layout.clone()
+    }
+
+    /// The bind group holding every texture, recreated after textures are inserted.
+    pub fn bind_group(&self) -> Arc<wgpu::BindGroup> {
+        let mut slots = self.slots.lock().unwrap();
+        if let Some(bind_group) = &slots.bind_group {
+            return bind_group.clone();
+        }
+
+        let bind_group = Arc::new(self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: TEXTURES_BINDING,
+                    resource: wgpu::BindingResource::TextureViewArray(&slots.views),
+                },
+                wgpu::BindGroupEntry {
+                    binding: SAMPLER_BINDING,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ]),
+            label: Some(Cow::Borrowed("bindless_textures")),
+        }));
+        slots.bind_group = Some(bind_group.clone());
+        bind_group
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+// The labels of finished encoders, kept so their allocations are reused.
+struct EncoderFreeList {
+    labels: Vec<String>,
+    capacity: usize,
+}
+
+impl EncoderFreeList {
+    fn take(&mut self, label: &str) -> String {
+        let mut pooled = self.labels.pop().unwrap_or_default();
+        pooled.clear();
+        pooled.push_str(label);
+        pooled
+    }
+
+    // Drops the label instead if the pool is already full.
+    fn give_back(&mut self, label: String) {
+        if self.labels.len() < self.capacity {
+            self.labels.push(label);
+        }
+    }
+}
+
+/// Hands out command encoders for the render systems and counts how many are created each frame.
+/// wgpu encoders are consumed by `finish` and can't be reset, so only the `PooledEncoder`s
+/// wrapping them are recycled. The count ends up in `RenderStats::command_encoders`.
+pub struct CommandEncoderPool {
+    device: Arc<wgpu::Device>,
+    free_list: Arc<Mutex<EncoderFreeList>>,
+    created: Arc<AtomicU32>,
+}
+
+impl CommandEncoderPool {
+    pub fn new(device: Arc<wgpu::Device>, capacity: usize) -> Self {
+        Self {
+            device,
+            free_list: Arc::new(Mutex::new(EncoderFreeList {
+                labels: Vec::with_capacity(capacity),
+                capacity,
+            })),
+            created: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Creates a new encoder, finish it with `PooledEncoder::finish` so it's wrapper goes back into the pool.
+    pub fn begin(&self, label: &str) -> PooledEncoder {
+        let label = self.free_list.lock().unwrap().take(label);
+        let encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some(&label),
+            });
+        self.created.fetch_add(1, Ordering::Relaxed);
+
+        PooledEncoder {
+            label,
+            encoder: Some(encoder),
+            free_list: self.free_list.clone(),
+        }
+    }
+
+    /// How many encoders were created since the last call.
+    pub fn take_created(&self) -> u32 {
+        self.created.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// A command encoder from a `CommandEncoderPool`.
+pub struct PooledEncoder {
+    label: String,
+    encoder: Option<wgpu::CommandEncoder>,
+    free_list: Arc<Mutex<EncoderFreeList>>,
+}
+
+impl PooledEncoder {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Finishes recording and returns the wrapper to the pool.
+    pub fn finish(mut self) -> wgpu::CommandBuffer {
+        self.encoder.take().unwrap().finish()
+    }
+}
+
+impl Deref for PooledEncoder {
+    type Target = wgpu::CommandEncoder;
+
+    fn deref(&self) -> &wgpu::CommandEncoder {
+        self.encoder.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledEncoder {
+    fn deref_mut(&mut self) -> &mut wgpu::CommandEncoder {
+        self.encoder.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledEncoder {
+    fn drop(&mut self) {
+        let label = std::mem::take(&mut self.label);
+        self.free_list.lock().unwrap().give_back(label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EncoderFreeList;
+
+    #[test]
+    fn free_list_reuses_and_caps_labels() {
+        let mut free_list = EncoderFreeList {
+            labels: Vec::new(),
+            capacity: 1,
+        };
+        let label = free_list.take("mesh");
+        assert_eq!(label, "mesh");
+        let capacity = label.capacity();
+
+        free_list.give_back(label);
+        free_list.give_back("skybox".to_string());
+        assert_eq!(free_list.labels.len(), 1);
+
+        let label = free_list.take("ui");
+        assert_eq!(label, "ui");
+        assert_eq!(label.capacity(), capacity);
+        assert!(free_list.labels.is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use super::{BindGroup, SlabHandle};
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// How many copies of a framed buffer are created by default.
+pub const DEFAULT_FRAME_COUNT: usize = 3;
+
+/// A uniform buffer with a copy for each frame in flight.
+/// Writes go to the copy for the current frame so the GPU can keep reading the copies from previous frames.
+/// The frame index is shared with the `GPUResourceManager` and advanced by the begin frame system.
+pub struct FramedBuffer<T> {
+    // Each copy is a buffer and the offset it starts at, copies created from a slab share it's buffer.
+    buffers: Vec<(Arc<wgpu::Buffer>, wgpu::BufferAddress)>,
+    bind_groups: Vec<Arc<BindGroup>>,
+    frame_index: Arc<AtomicUsize>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> FramedBuffer<T> {
+    /// Creates `count` uniform buffers each with a bind group that binds it at binding 0.
+    /// `bind_slot` is the set the bind groups are bound to.
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        bind_slot: u32,
+        frame_index: Arc<AtomicUsize>,
+        count: usize,
+        data: &T,
+    ) -> Self {
+        let buffers = (0..count.max(1))
+            .map(|_| {
+                let buffer = device.create_buffer_with_data(
+                    bytemuck::bytes_of(data),
+                    wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                );
+                (Arc::new(buffer), 0)
+            })
+            .collect();
+
+        Self::from_buffers(device, layout, bind_slot, frame_index, buffers)
+    }
+
+    /// Creates `count` copies using the slab elements starting at `first_index` instead of new buffers.
+    /// The slab needs `UNIFORM` and `COPY_DST` usage and it's elements must be the size of `T`.
+    /// Note: Slab elements start out zeroed so write to the buffer before it's drawn with.
+    pub fn from_slab(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        bind_slot: u32,
+        frame_index: Arc<AtomicUsize>,
+        slab: &SlabHandle,
+        first_index: u32,
+        count: usize,
+    ) -> Self {
+        let buffers = (0..count.max(1) as u32)
+            .map(|i| (slab.shared_buffer(), slab.offset(first_index + i)))
+            .collect();
+
+        Self::from_buffers(device, layout, bind_slot, frame_index, buffers)
+    }
+
+    fn from_buffers(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        bind_slot: u32,
+        frame_index: Arc<AtomicUsize>,
+        buffers: Vec<(Arc<wgpu::Buffer>, wgpu::BufferAddress)>,
+    ) -> Self {
+        let size = std::mem::size_of::<T>() as wgpu::BufferAddress;
+        let bind_groups = buffers
+            .iter()
+            .map(|(buffer, offset)| {
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout,
+                    entries: Cow::Borrowed(&[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(
+                            buffer.slice(*offset..*offset + size),
+                        ),
+                    }]),
+                    label: None,
+                });
+                Arc::new(BindGroup::new(bind_slot, bind_group))
+            })
+            .collect();
+
+        Self {
+            buffers,
+            bind_groups,
+            frame_index,
+            _marker: PhantomData,
+        }
+    }
+
+    fn current_index(&self) -> usize {
+        self.frame_index.load(Ordering::Relaxed) % self.buffers.len()
+    }
+
+    /// Writes to the copy used by the current frame.
+    pub fn write(&self, queue: &wgpu::Queue, data: &T) {
+        let (buffer, offset) = &self.buffers[self.current_index()];
+        queue.write_buffer(buffer, *offset, bytemuck::bytes_of(data));
+    }
+
+    /// The bind group for the copy used by the current frame.
+    pub fn current_binding(&self) -> Arc<BindGroup> {
+        self.bind_groups[self.current_index()].clone()
+    }
+
+    /// The buffer used by the current frame and the offset it's copy starts at.
+    pub fn current_buffer(&self) -> (&wgpu::Buffer, wgpu::BufferAddress) {
+        let (buffer, offset) = &self.buffers[self.current_index()];
+        (buffer, *offset)
+    }
+}
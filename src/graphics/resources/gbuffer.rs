@@ -0,0 +1,125 @@
+use crate::graphics::renderer::DEPTH_FORMAT;
+use std::borrow::Cow;
+
+pub const GBUFFER_ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+pub const GBUFFER_NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgb10a2Unorm;
+pub const GBUFFER_MATERIAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// The render targets used by the deferred renderer.
+/// albedo: rgb = albedo, a = alpha
+/// normal: rgb = world space normal packed into 0-1
+/// material: r = metallic, g = roughness, b = ambient occlusion, a = receives decals
+pub struct GBuffer {
+    pub albedo: wgpu::Texture,
+    pub albedo_view: wgpu::TextureView,
+    pub normal: wgpu::Texture,
+    pub normal_view: wgpu::TextureView,
+    pub material: wgpu::Texture,
+    pub material_view: wgpu::TextureView,
+
+    // The gbuffer has it's own depth texture as the lighting pass needs to sample it
+    // in order to reconstruct the world position.
+    pub depth: wgpu::Texture,
+    pub depth_view: wgpu::TextureView,
+
+    pub sampler: wgpu::Sampler,
+    pub bind_group: wgpu::BindGroup,
+
+    pub width: u32,
+    pub height: u32,
+}
+
+impl GBuffer {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let (albedo, albedo_view) =
+            Self::create_target(device, width, height, GBUFFER_ALBEDO_FORMAT);
+        let (normal, normal_view) =
+            Self::create_target(device, width, height, GBUFFER_NORMAL_FORMAT);
+        let (material, material_view) =
+            Self::create_target(device, width, height, GBUFFER_MATERIAL_FORMAT);
+        let (depth, depth_view) = Self::create_target(device, width, height, DEPTH_FORMAT);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: None,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&albedo_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&normal_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&material_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&depth_view),
+                },
+            ]),
+            label: Some(Cow::Borrowed("gbuffer")),
+        });
+
+        Self {
+            albedo,
+            albedo_view,
+            normal,
+            normal_view,
+            material,
+            material_view,
+            depth,
+            depth_view,
+            sampler,
+            bind_group,
+            width,
+            height,
+        }
+    }
+
+    fn create_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+            label: None,
+        });
+        let view = texture.create_default_view();
+        (texture, view)
+    }
+}
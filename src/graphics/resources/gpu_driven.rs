@@ -0,0 +1,196 @@
+use crate::{
+    core::{BoundingSphere, Frustum},
+    graphics::pipelines::gpu_cull::GPU_CULL_WORKGROUP_SIZE,
+};
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm::Mat4;
+use std::{borrow::Cow, sync::Arc};
+
+/// The arguments `draw_indexed_indirect` reads from the indirect buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DrawIndexedIndirectArgs {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub first_instance: u32,
+}
+
+unsafe impl Zeroable for DrawIndexedIndirectArgs {}
+unsafe impl Pod for DrawIndexedIndirectArgs {}
+
+/// A single draw the cull shader decides the visibility of.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GpuDraw {
+    world: [[f32; 4]; 4],
+    bounding_sphere: [f32; 4],
+    index_count: u32,
+    _padding: [u32; 3],
+}
+
+unsafe impl Zeroable for GpuDraw {}
+unsafe impl Pod for GpuDraw {}
+
+impl GpuDraw {
+    pub(crate) fn new(world: Mat4, bounding_sphere: BoundingSphere, index_count: u32) -> Self {
+        let center = bounding_sphere.center;
+        Self {
+            world: world.into(),
+            bounding_sphere: [center.x, center.y, center.z, bounding_sphere.radius],
+            index_count,
+            _padding: [0; 3],
+        }
+    }
+
+    pub(crate) fn index_count(&self) -> u32 {
+        self.index_count
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct CullUniform {
+    planes: [[f32; 4]; 5],
+    draw_count: u32,
+    _padding: [u32; 3],
+}
+
+unsafe impl Zeroable for CullUniform {}
+unsafe impl Pod for CullUniform {}
+
+/// Culls opaque draws on the GPU and writes an indirect draw for each one.
+/// Used by the mesh system when `RenderGraph::use_gpu_driven` is set, draw `i` is read from `args_offset(i)`.
+/// Culled draws are written with an instance count of 0 so the CPU doesn't need to know which ones are visible.
+pub struct GpuDrivenRenderer {
+    layout: Arc<wgpu::BindGroupLayout>,
+    capacity: u32,
+    uniform_buffer: wgpu::Buffer,
+    draw_buffer: wgpu::Buffer,
+    indirect_buffer: Arc<wgpu::Buffer>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GpuDrivenRenderer {
+    pub fn new(device: &wgpu::Device, layout: Arc<wgpu::BindGroupLayout>, capacity: u32) -> Self {
+        let capacity = capacity.max(1);
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            size: std::mem::size_of::<CullUniform>() as u64,
+            mapped_at_creation: false,
+            label: Some("gpu_cull_uniform"),
+        });
+        let draw_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+            size: capacity as u64 * std::mem::size_of::<GpuDraw>() as u64,
+            mapped_at_creation: false,
+            label: Some("gpu_cull_draws"),
+        });
+        let indirect_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::INDIRECT,
+            size: capacity as u64 * std::mem::size_of::<DrawIndexedIndirectArgs>() as u64,
+            mapped_at_creation: false,
+            label: Some("gpu_cull_indirect"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(draw_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(indirect_buffer.slice(..)),
+                },
+            ]),
+            label: Some(Cow::Borrowed("gpu_cull")),
+        });
+
+        Self {
+            layout,
+            capacity,
+            uniform_buffer,
+            draw_buffer,
+            indirect_buffer: Arc::new(indirect_buffer),
+            bind_group,
+        }
+    }
+
+    /// How many draws fit in the buffers before they have to grow.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// The buffer `draw_indexed_indirect` reads from.
+    pub fn indirect_buffer(&self) -> Arc<wgpu::Buffer> {
+        self.indirect_buffer.clone()
+    }
+
+    /// Where the arguments of draw `index` start in the indirect buffer.
+    pub fn args_offset(index: u32) -> wgpu::BufferAddress {
+        index as u64 * std::mem::size_of::<DrawIndexedIndirectArgs>() as u64
+    }
+
+    /// Uploads the draws and frustum and records the compute pass that fills the indirect buffer.
+    /// The buffers are recreated if there are more draws than fit, so fetch `indirect_buffer` after calling this.
+    pub(crate) fn cull(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        frustum: &Frustum,
+        draws: &[GpuDraw],
+    ) {
+        if draws.is_empty() {
+            return;
+        }
+        if draws.len() as u32 > self.capacity {
+            let capacity = (draws.len() as u32).next_power_of_two();
+            *self = Self::new(device, self.layout.clone(), capacity);
+        }
+
+        let mut planes = [[0.0; 4]; 5];
+        for (plane, frustum_plane) in planes.iter_mut().zip(frustum.planes.iter()) {
+            let normal = frustum_plane.normal;
+            *plane = [normal.x, normal.y, normal.z, frustum_plane.distance];
+        }
+        let uniform = CullUniform {
+            planes,
+            draw_count: draws.len() as u32,
+            _padding: [0; 3],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        queue.write_buffer(&self.draw_buffer, 0, bytemuck::cast_slice(draws));
+
+        let mut pass = encoder.begin_compute_pass();
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.dispatch(
+            (draws.len() as u32 + GPU_CULL_WORKGROUP_SIZE - 1) / GPU_CULL_WORKGROUP_SIZE,
+            1,
+            1,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrawIndexedIndirectArgs, GpuDraw, GpuDrivenRenderer};
+
+    #[test]
+    fn should_match_shader_layout() {
+        // draw_indexed_indirect reads 5 tightly packed 32 bit values.
+        assert_eq!(std::mem::size_of::<DrawIndexedIndirectArgs>(), 20);
+        assert_eq!(GpuDrivenRenderer::args_offset(3), 60);
+        // mat4, vec4 and 4 uints in std430.
+        assert_eq!(std::mem::size_of::<GpuDraw>(), 96);
+    }
+}
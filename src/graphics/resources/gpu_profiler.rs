@@ -0,0 +1,193 @@
+use futures::FutureExt;
+use std::{future::Future, pin::Pin, sync::Mutex};
+
+/// How many frames can be waiting to be read back at once.
+/// Timings are usually read back one or two frames after they were measured.
+const READBACK_FRAMES: usize = 3;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+// A buffer the timestamps of one frame are resolved into.
+struct Readback {
+    buffer: wgpu::Buffer,
+    // Labels for the scopes resolved into the buffer.
+    scopes: Vec<String>,
+    // Set while the buffer is being mapped, in a mutex so the profiler can be stored as a resource.
+    mapping: Option<Mutex<MapFuture>>,
+}
+
+/// How long a single scope took on the GPU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuTimestamp {
+    pub label: String,
+    pub duration_ns: u64,
+}
+
+/// Measures how long render passes take on the GPU using timestamp queries.
+/// Each scope writes a timestamp at the beginning and end so a scope uses two queries.
+/// Created by the renderer when `RenderSettings::gpu_profiling` is turned on.
+/// Note: Requires the adapter to support `wgpu::Features::TIMESTAMP_QUERY`.
+pub struct GpuProfiler {
+    query_set: wgpu::QuerySet,
+    readbacks: Vec<Readback>,
+    // Index of the readback the next frame is resolved into.
+    current: usize,
+    max_scopes: u32,
+    // Labels for the scopes written this frame in order.
+    scopes: Vec<String>,
+    // Label of the scope that has begun but not ended yet.
+    open_scope: Option<String>,
+    // Nanoseconds per timestamp tick.
+    timestamp_period: f32,
+    timings: Vec<GpuTimestamp>,
+}
+
+impl GpuProfiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, max_scopes: u32) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            ty: wgpu::QueryType::Timestamp,
+            count: max_scopes * 2,
+        });
+
+        let readbacks = (0..READBACK_FRAMES)
+            .map(|_| Readback {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("gpu_profiler"),
+                    size: (max_scopes * 2) as u64 * std::mem::size_of::<u64>() as u64,
+                    usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                scopes: Vec::new(),
+                mapping: None,
+            })
+            .collect();
+
+        Self {
+            query_set,
+            readbacks,
+            current: 0,
+            max_scopes,
+            scopes: Vec::new(),
+            open_scope: None,
+            timestamp_period: queue.get_timestamp_period(),
+            timings: Vec::new(),
+        }
+    }
+
+    /// Starts timing a scope. Scopes can't be nested and anything past `max_scopes` is ignored.
+    pub fn begin_scope(&mut self, label: &str, encoder: &mut wgpu::CommandEncoder) {
+        if self.open_scope.is_some() || self.scopes.len() as u32 >= self.max_scopes {
+            return;
+        }
+
+        let index = self.scopes.len() as u32 * 2;
+        encoder.write_timestamp(&self.query_set, index);
+        self.open_scope = Some(label.to_string());
+    }
+
+    /// Ends the scope started with `begin_scope`.
+    pub fn end_scope(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(label) = self.open_scope.take() {
+            let index = self.scopes.len() as u32 * 2 + 1;
+            encoder.write_timestamp(&self.query_set, index);
+            self.scopes.push(label);
+        }
+    }
+
+    /// Resolves the timestamps written this frame and reads back earlier frames that the GPU has finished.
+    /// Call this once after the frame was submitted, it never waits on the GPU.
+    /// If every readback buffer is still in use this frame's timings are dropped.
+    pub fn resolve(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.open_scope = None;
+        device.poll(wgpu::Maintain::Poll);
+
+        // Oldest first so the newest finished frame ends up in `timings`.
+        for offset in 0..self.readbacks.len() {
+            let index = (self.current + offset) % self.readbacks.len();
+            let readback = &mut self.readbacks[index];
+            let result = match readback.mapping.as_mut() {
+                Some(mapping) => match mapping.get_mut().unwrap().as_mut().now_or_never() {
+                    Some(result) => result,
+                    None => continue,
+                },
+                None => continue,
+            };
+            readback.mapping = None;
+
+            if result.is_ok() {
+                let size = readback.scopes.len() as u64 * 2 * std::mem::size_of::<u64>() as u64;
+                let ticks: Vec<u64> = bytemuck::cast_slice(
+                    &readback.buffer.slice(0..size).get_mapped_range(),
+                )
+                .to_vec();
+                readback.buffer.unmap();
+                self.timings = calculate_timings(&readback.scopes, &ticks, self.timestamp_period);
+            } else {
+                log::error!("Failed to read back gpu timestamps.");
+            }
+            readback.scopes.clear();
+        }
+
+        let scopes = std::mem::take(&mut self.scopes);
+        let readback = &mut self.readbacks[self.current];
+        if scopes.is_empty() || readback.mapping.is_some() {
+            return;
+        }
+
+        let query_count = scopes.len() as u32 * 2;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("gpu_profiler_resolve"),
+        });
+        encoder.resolve_query_set(&self.query_set, 0..query_count, &readback.buffer, 0);
+        queue.submit(Some(encoder.finish()));
+
+        let size = query_count as u64 * std::mem::size_of::<u64>() as u64;
+        let map_future = readback.buffer.slice(0..size).map_async(wgpu::MapMode::Read);
+        readback.mapping = Some(Mutex::new(Box::pin(map_future)));
+        readback.scopes = scopes;
+        self.current = (self.current + 1) % self.readbacks.len();
+    }
+
+    /// The timings of the newest frame that has been read back.
+    pub fn last_frame_timings(&self) -> &Vec<GpuTimestamp> {
+        &self.timings
+    }
+}
+
+// Turns pairs of begin and end ticks into durations.
+fn calculate_timings(labels: &[String], ticks: &[u64], timestamp_period: f32) -> Vec<GpuTimestamp> {
+    labels
+        .iter()
+        .zip(ticks.chunks_exact(2))
+        .map(|(label, range)| GpuTimestamp {
+            label: label.clone(),
+            duration_ns: (range[1].saturating_sub(range[0]) as f64 * timestamp_period as f64)
+                as u64,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_calculate_timings_from_pairs() {
+        let labels = vec!["pbr".to_string(), "skybox".to_string()];
+        let ticks = vec![10, 20, 30, 25];
+        let timings = calculate_timings(&labels, &ticks, 2.0);
+        assert_eq!(
+            timings,
+            vec![
+                GpuTimestamp {
+                    label: "pbr".to_string(),
+                    duration_ns: 20,
+                },
+                GpuTimestamp {
+                    label: "skybox".to_string(),
+                    duration_ns: 0,
+                },
+            ]
+        );
+    }
+}
@@ -1,12 +1,31 @@
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
-use super::{ArcRenderPass, BindGroup};
+use super::{
+    bindless::BINDLESS_FEATURES, ArcRenderPass, BindGroup, BindlessArrayHandle, FramedBuffer, ReadbackBuffer, SlabHandle, DEFAULT_FRAME_COUNT,
+};
 use crate::{
-    graphics::{lighting::cluster::{LIGHT_LIST_BUFFER_SIZE, FRUSTUM_BUFFER_SIZE}, pipelines::{GlobalUniform, LightingUniform}, shadows::OmniShadowManager},
+    graphics::{lighting::cluster::{LIGHT_LIST_BUFFER_SIZE, FRUSTUM_BUFFER_SIZE}, pipelines::{GlobalUniform, LightingUniform}, shadows::{CascadeShadowManager, CascadeUniform, OmniShadowManager}},
     scene::components::transform::LocalUniform,
 };
 use dashmap::DashMap;
 
+/// The multi-buffer key joint buffers are stored under.
+pub const JOINT_BUFFER: &str = "joints";
+const JOINT_BUFFER_SIZE: u64 =
+    (crate::assets::skeleton::MAX_JOINTS * std::mem::size_of::<[[f32; 4]; 4]>()) as u64;
+
+/// The slab key transform uniforms are stored under.
+pub const TRANSFORM_SLAB: &str = "transforms";
+/// How many transforms fit in the transform slab before it's first resized.
+/// Transforms created past this get their own buffers until the render mesh system grows the slab.
+pub const MAX_TRANSFORMS: u32 = 16384;
+
 /// Stores bind groups for consumption by pipelines.
 /// Also can store buffers, but it's not required.
 pub struct GPUResourceManager {
@@ -16,6 +35,13 @@ pub struct GPUResourceManager {
     multi_bind_groups: DashMap<String, DashMap<u32, DashMap<u32, Arc<BindGroup>>>>,
     multi_buffer: DashMap<String, DashMap<u32, Arc<wgpu::Buffer>>>,
     buffers: DashMap<String, Arc<wgpu::Buffer>>,
+    storage_buffers: DashMap<String, Arc<wgpu::Buffer>>,
+    slabs: DashMap<String, Arc<SlabHandle>>,
+    readback_buffers: DashMap<String, Arc<ReadbackBuffer>>,
+    bindless_arrays: DashMap<String, Arc<BindlessArrayHandle>>,
+    transform_buffers: DashMap<u32, Arc<FramedBuffer<LocalUniform>>>,
+    // Shared with every framed buffer so they all cycle together.
+    frame_index: Arc<AtomicUsize>,
 
     pub global_uniform_buffer: wgpu::Buffer,
     pub global_lighting_buffer: wgpu::Buffer,
@@ -26,7 +52,11 @@ pub struct GPUResourceManager {
 }
 
 impl GPUResourceManager {
-    pub fn new(device: Arc<wgpu::Device>, omni_manager: &OmniShadowManager) -> Self {
+    pub fn new(
+        device: Arc<wgpu::Device>,
+        omni_manager: &OmniShadowManager,
+        cascade_manager: &CascadeShadowManager,
+    ) -> Self {
         let bind_group_layouts = DashMap::new();
 
         // Create our global uniforms buffers, layouts, and bindgroups here.
@@ -150,6 +180,27 @@ impl GPUResourceManager {
                             multisampled: false,
                         }
                     ),
+                    wgpu::BindGroupLayoutEntry::new(
+                        // Cascade shadow maps
+                        9,
+                        wgpu::ShaderStage::FRAGMENT,
+                        wgpu::BindingType::SampledTexture {
+                            dimension: wgpu::TextureViewDimension::D2Array,
+                            component_type: wgpu::TextureComponentType::Float,
+                            multisampled: false,
+                        }
+                    ),
+                    wgpu::BindGroupLayoutEntry::new(
+                        // Cascade matrices and splits
+                        10,
+                        wgpu::ShaderStage::FRAGMENT,
+                        wgpu::BindingType::UniformBuffer {
+                            dynamic: false,
+                            min_binding_size: wgpu::BufferSize::new(
+                                std::mem::size_of::<CascadeUniform>() as _,
+                            ),
+                        },
+                    ),
                 ]),
                 label: Some(Cow::Borrowed("Globals")),
             });
@@ -193,6 +244,14 @@ impl GPUResourceManager {
                     binding: 8,
                     resource: wgpu::BindingResource::TextureView(&omni_manager.quad_textures[3].view),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 9,
+                    resource: wgpu::BindingResource::TextureView(&cascade_manager.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 10,
+                    resource: wgpu::BindingResource::Buffer(cascade_manager.uniform_buffer.slice(..)),
+                },
             ]),
             label: Some(Cow::Borrowed("Globals")),
         });
@@ -216,30 +275,55 @@ impl GPUResourceManager {
             });
         bind_group_layouts.insert("locals".to_string(), Arc::new(local_bind_group_layout));
 
-        Self {
+        let manager = Self {
             bind_group_layouts,
             buffers: DashMap::new(),
+            storage_buffers: DashMap::new(),
+            slabs: DashMap::new(),
+            readback_buffers: DashMap::new(),
+            bindless_arrays: DashMap::new(),
             single_bind_groups: DashMap::new(),
             multi_bind_groups: DashMap::new(),
             multi_buffer: DashMap::new(),
+            transform_buffers: DashMap::new(),
+            frame_index: Arc::new(AtomicUsize::new(0)),
             global_bind_group,
             global_lighting_buffer,
             global_uniform_buffer,
             frustum_buffer,
             light_list_buffer,
-        }
+        };
+
+        // Every frame's copy of a transform uniform is an element of the slab.
+        manager.init_multi_buffer(
+            &device,
+            TRANSFORM_SLAB,
+            std::mem::size_of::<LocalUniform>() as u64,
+            MAX_TRANSFORMS * DEFAULT_FRAME_COUNT as u32,
+        );
+
+        manager
     }
 
     /// Adds a single bind group with a given key.
     pub fn add_single_bind_group<T: Into<String>>(&self, key: T, bind_group: BindGroup) {
+        self.add_shared_single_bind_group(key, Arc::new(bind_group));
+    }
+
+    /// Adds a single bind group that's also kept somewhere else, like a probe's bind group.
+    pub fn add_shared_single_bind_group<T: Into<String>>(
+        &self,
+        key: T,
+        bind_group: Arc<BindGroup>,
+    ) {
         let key = key.into();
         let bind_group_index = bind_group.index;
         if self.single_bind_groups.contains_key(&key) {
             let bind_groups = self.single_bind_groups.get_mut(&key).unwrap();
-            bind_groups.insert(bind_group_index, Arc::new(bind_group));
+            bind_groups.insert(bind_group_index, bind_group);
         } else {
             let hash_map = DashMap::new();
-            hash_map.insert(bind_group_index, Arc::new(bind_group));
+            hash_map.insert(bind_group_index, bind_group);
             self.single_bind_groups.insert(key.clone(), hash_map);
         }
     }
@@ -288,6 +372,212 @@ impl GPUResourceManager {
         }
     }
 
+    /// Allocates a single buffer split into `capacity` elements of `element_size` bytes and stores it with a given key.
+    /// Elements are aligned so they can be bound as uniform buffers.
+    pub fn allocate_slab<T: Into<String>>(
+        &self,
+        device: &wgpu::Device,
+        key: T,
+        element_size: u64,
+        capacity: u32,
+        usage: wgpu::BufferUsage,
+    ) -> Arc<SlabHandle> {
+        let key = key.into();
+        if self.slabs.contains_key(&key) {
+            panic!("Slab already exists use `get_slab` or use a different key.");
+        }
+        let slab = Arc::new(SlabHandle::new(device, &key, element_size, capacity, usage));
+        self.slabs.insert(key, slab.clone());
+        slab
+    }
+
+    /// Gets a slab allocated with `allocate_slab`.
+    pub fn get_slab<T: Into<String>>(&self, key: T) -> Option<Arc<SlabHandle>> {
+        self.slabs.get(&key.into()).map(|slab| slab.value().clone())
+    }
+
+    /// Allocates a uniform slab with room for `capacity` elements that can be grown with `resize_multi_buffer`.
+    /// `stride` is the size of an element, it's rounded up to `SLAB_ALIGNMENT`.
+    pub fn init_multi_buffer<T: Into<String>>(
+        &self,
+        device: &wgpu::Device,
+        key: T,
+        stride: u64,
+        capacity: u32,
+    ) -> Arc<SlabHandle> {
+        self.allocate_slab(
+            device,
+            key,
+            stride,
+            capacity,
+            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::COPY_SRC,
+        )
+    }
+
+    /// How many elements the slab stored under `key` can hold.
+    pub fn get_multi_buffer_count<T: Into<String>>(&self, key: T) -> Option<u32> {
+        self.get_slab(key).map(|slab| slab.capacity())
+    }
+
+    /// Swaps the slab stored under `key` for one with room for `new_capacity` elements.
+    /// The old contents are copied over by `encoder`, so it has to be submitted after any writes to the old slab.
+    /// Does nothing if the slab is already big enough.
+    /// Transform buffers that lived in the transform slab are recreated to point at the new one.
+    pub fn resize_multi_buffer<T: Into<String>>(
+        &self,
+        device: &wgpu::Device,
+        key: T,
+        new_capacity: u32,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let key = key.into();
+        let old_slab = self
+            .get_slab(key.as_str())
+            .expect("Slab doesn't exist use `init_multi_buffer` first.");
+        if new_capacity <= old_slab.capacity() {
+            return;
+        }
+        let slab = Arc::new(old_slab.grow(device, &key, new_capacity, encoder));
+        self.slabs.insert(key.clone(), slab.clone());
+
+        if key == TRANSFORM_SLAB {
+            let layout = self.get_bind_group_layout("locals").unwrap();
+            let frame_count = DEFAULT_FRAME_COUNT as u32;
+            for mut item in self.transform_buffers.iter_mut() {
+                let first_index = *item.key() * frame_count;
+                // Transforms that didn't fit keep their own buffers.
+                if first_index + frame_count > old_slab.capacity() {
+                    continue;
+                }
+                *item.value_mut() = Arc::new(FramedBuffer::from_slab(
+                    device,
+                    &layout,
+                    0,
+                    self.frame_index(),
+                    &slab,
+                    first_index,
+                    DEFAULT_FRAME_COUNT,
+                ));
+            }
+        }
+    }
+
+    /// Creates a buffer `size` bytes long that GPU buffers can be copied into and read on the CPU.
+    /// See `ReadbackBuffer::request_read` and `ReadbackBuffer::map_blocking`.
+    pub fn create_readback_buffer<T: Into<String>>(
+        &self,
+        device: &wgpu::Device,
+        name: T,
+        size: u64,
+    ) -> Arc<ReadbackBuffer> {
+        let name = name.into();
+        if self.readback_buffers.contains_key(&name) {
+            panic!("Readback buffer already exists use `get_readback_buffer` or use a different key.");
+        }
+        let buffer = Arc::new(ReadbackBuffer::new(device, &name, size));
+        self.readback_buffers.insert(name, buffer.clone());
+        buffer
+    }
+
+    /// Gets a buffer created with `create_readback_buffer`.
+    pub fn get_readback_buffer<T: Into<String>>(&self, name: T) -> Option<Arc<ReadbackBuffer>> {
+        self.readback_buffers
+            .get(&name.into())
+            .map(|buffer| buffer.value().clone())
+    }
+
+    /// Whether the device can create bindless texture arrays, see `BINDLESS_FEATURES`.
+    pub fn supports_bindless(device: &wgpu::Device) -> bool {
+        device.features().contains(BINDLESS_FEATURES)
+    }
+
+    /// Creates an array of `capacity` textures that shaders index directly, see `BindlessArrayHandle`.
+    /// It's layout is added under `name` so pipelines can use it.
+    /// Returns None if the device doesn't support `BINDLESS_FEATURES`.
+    /// The capacity is clamped to the device's `max_sampled_textures_per_shader_stage`.
+    pub fn create_bindless_texture_array<T: Into<String>>(
+        &self,
+        device: Arc<wgpu::Device>,
+        name: T,
+        capacity: u32,
+    ) -> Option<Arc<BindlessArrayHandle>> {
+        let name = name.into();
+        if !Self::supports_bindless(&device) {
+            log::warn!("Can't create bindless texture array {}, the device doesn't support it.", name);
+            return None;
+        }
+        if self.bind_group_layouts.contains_key(&name) {
+            panic!("Bind group layout already exists use `get_bindless_texture_array` or use a different key.");
+        }
+
+        let max_textures = device.limits().max_sampled_textures_per_shader_stage;
+        if capacity > max_textures {
+            log::warn!("Bindless texture array {} clamped to {} textures.", name, max_textures);
+        }
+        let array = Arc::new(BindlessArrayHandle::new(device, &name, capacity.min(max_textures)));
+        self.bind_group_layouts.insert(name.clone(), array.layout());
+        self.bindless_arrays.insert(name, array.clone());
+        Some(array)
+    }
+
+    /// Gets an array created with `create_bindless_texture_array`.
+    pub fn get_bindless_texture_array<T: Into<String>>(
+        &self,
+        name: T,
+    ) -> Option<Arc<BindlessArrayHandle>> {
+        self.bindless_arrays
+            .get(&name.into())
+            .map(|array| array.value().clone())
+    }
+
+    /// Advances the frame index so framed buffers write to their next copy.
+    /// Called by the begin frame system at the start of every frame.
+    pub fn begin_frame(&self) {
+        self.frame_index.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The frame index counter used to create a `FramedBuffer`.
+    pub fn frame_index(&self) -> Arc<AtomicUsize> {
+        self.frame_index.clone()
+    }
+
+    /// Adds the framed uniform buffer for a transform using the transform's index.
+    pub fn add_transform_buffer(&self, buffer: FramedBuffer<LocalUniform>, item_index: u32) {
+        self.transform_buffers.insert(item_index, Arc::new(buffer));
+    }
+
+    /// Let's you retrieve the framed uniform buffer for a transform.
+    pub fn get_transform_buffer(&self, item_index: u32) -> Arc<FramedBuffer<LocalUniform>> {
+        self.transform_buffers.get(&item_index).unwrap().value().clone()
+    }
+
+    /// Sets the transform bind group for the current frame.
+    pub fn set_transform_bind_group<'a>(
+        &'a self,
+        render_pass: &mut ArcRenderPass<'a>,
+        item_index: u32,
+    ) {
+        let bind_group = self.get_transform_buffer(item_index).current_binding();
+        render_pass.set_bind_group_internal(bind_group);
+    }
+
+    /// Creates the joint buffer for a skinned mesh. Joint buffers are stored like the transform buffers
+    /// using the skinned mesh's index.
+    pub fn add_joint_buffer(&self, device: &wgpu::Device, item_index: u32) {
+        let joint_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::STORAGE,
+            size: JOINT_BUFFER_SIZE,
+            mapped_at_creation: false,
+            label: Some("joint buffer"),
+        });
+        self.add_multi_buffer(JOINT_BUFFER, joint_buffer, item_index);
+    }
+
+    /// Let's you retrieve the joint buffer for a skinned mesh.
+    pub fn get_joint_buffer(&self, item_index: u32) -> Arc<wgpu::Buffer> {
+        self.get_multi_buffer(JOINT_BUFFER, item_index)
+    }
+
     /// Let's you retrieve a multi-buffer.
     pub fn get_multi_buffer<T: Into<String>>(&self, key: T, item_index: u32) -> Arc<wgpu::Buffer> {
         self.multi_buffer
@@ -401,4 +691,75 @@ impl GPUResourceManager {
     pub fn get_buffer<T: Into<String>>(&self, name: T) -> Arc<wgpu::Buffer> {
         self.buffers.get(&name.into()).unwrap().value().clone()
     }
+
+    /// Creates a zeroed storage buffer `size` bytes long for compute shaders, fill it with `queue.write_buffer`.
+    pub fn add_storage_buffer<T: Into<String>>(&self, device: &wgpu::Device, name: T, size: u64) {
+        let name = name.into();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+            size,
+            mapped_at_creation: false,
+            label: Some(&name),
+        });
+        self.insert_storage_buffer(name, buffer);
+    }
+
+    /// Creates a storage buffer holding `data`.
+    pub fn add_storage_buffer_with_data<T: Into<String>, D: bytemuck::Pod>(
+        &self,
+        device: &wgpu::Device,
+        name: T,
+        data: &[D],
+    ) {
+        let buffer = device.create_buffer_with_data(
+            bytemuck::cast_slice(data),
+            wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+        );
+        self.insert_storage_buffer(name.into(), buffer);
+    }
+
+    fn insert_storage_buffer(&self, name: String, buffer: wgpu::Buffer) {
+        if self.storage_buffers.contains_key(&name) {
+            panic!("Storage buffer already exists use `get_storage_buffer` or use a different key.");
+        }
+        self.storage_buffers.insert(name, Arc::new(buffer));
+    }
+
+    /// Gets a storage buffer.
+    pub fn get_storage_buffer<T: Into<String>>(&self, name: T) -> Option<Arc<wgpu::Buffer>> {
+        self.storage_buffers
+            .get(&name.into())
+            .map(|buffer| buffer.value().clone())
+    }
+
+    /// Creates a bind group layout of storage buffers, fetch it with `get_bind_group_layout`.
+    /// `bindings` holds the binding index of each buffer and whether shaders only read from it.
+    pub fn add_storage_bind_group_layout<T: Into<String>>(
+        &self,
+        device: &wgpu::Device,
+        name: T,
+        visibility: wgpu::ShaderStage,
+        bindings: &[(u32, bool)],
+    ) {
+        let name = name.into();
+        let entries: Vec<wgpu::BindGroupLayoutEntry> = bindings
+            .iter()
+            .map(|(binding, readonly)| {
+                wgpu::BindGroupLayoutEntry::new(
+                    *binding,
+                    visibility,
+                    wgpu::BindingType::StorageBuffer {
+                        dynamic: false,
+                        readonly: *readonly,
+                        min_binding_size: None,
+                    },
+                )
+            })
+            .collect();
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Owned(entries),
+            label: Some(Cow::Owned(name.clone())),
+        });
+        self.add_bind_group_layout(name, layout);
+    }
 }
@@ -0,0 +1,458 @@
+use super::ReadbackBuffer;
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm::{Mat4, Vec3};
+use std::{borrow::Cow, sync::Arc};
+
+// Must match the local sizes in the hi-z compute shaders.
+const HI_Z_WORKGROUP_SIZE: u32 = 8;
+const HI_Z_CULL_WORKGROUP_SIZE: u32 = 64;
+
+// How many spheres the cull buffers hold before they grow.
+const INITIAL_SPHERE_CAPACITY: u32 = 1024;
+
+/// The format of the hi-z buffer, each texel is the furthest depth of the block it covers.
+pub const HI_Z_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+/// How many mips a hi-z buffer for a `width` by `height` depth buffer has, the last one is a single texel.
+pub fn hi_z_mip_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// A bounding sphere the cull pass tests against the hi-z buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HiZSphere {
+    sphere: [f32; 4],
+    index: u32,
+    _padding: [u32; 3],
+}
+
+unsafe impl Zeroable for HiZSphere {}
+unsafe impl Pod for HiZSphere {}
+
+impl HiZSphere {
+    /// `center` and `radius` are in world space, the result is stored in the bit for `transform_index`.
+    pub(crate) fn new(center: Vec3, radius: f32, transform_index: u32) -> Self {
+        Self {
+            sphere: [center.x, center.y, center.z, radius],
+            index: transform_index,
+            _padding: [0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct HiZCullUniform {
+    view_projection: [[f32; 4]; 4],
+    // Sphere count, mip count and the size of the first mip.
+    counts: [u32; 4],
+}
+
+unsafe impl Zeroable for HiZCullUniform {}
+unsafe impl Pod for HiZCullUniform {}
+
+pub(crate) fn set_bit(bits: &mut Vec<u32>, index: u32) {
+    let word = (index / 32) as usize;
+    if word >= bits.len() {
+        bits.resize(word + 1, 0);
+    }
+    bits[word] |= 1 << (index % 32);
+}
+
+pub(crate) fn is_bit_set(bits: &[u32], index: u32) -> bool {
+    bits.get((index / 32) as usize)
+        .map_or(false, |word| word & (1 << (index % 32)) != 0)
+}
+
+/// Builds a hierarchical z-buffer from a depth buffer. The first mip is a copy of the depth buffer
+/// and every other mip keeps the furthest depth of each 2x2 block of the mip before it.
+pub struct HiZPass {
+    width: u32,
+    height: u32,
+    // The view projection the depth buffer in the hi-z buffer was rendered with.
+    view_projection: Mat4,
+    copy_layout: Arc<wgpu::BindGroupLayout>,
+    // Every mip, sampled by the cull pass.
+    view: wgpu::TextureView,
+    mip_views: Vec<wgpu::TextureView>,
+    // Reads mip `i` and writes mip `i + 1`.
+    downsample_bind_groups: Vec<wgpu::BindGroup>,
+    sampler: wgpu::Sampler,
+}
+
+impl HiZPass {
+    pub fn new(
+        device: &wgpu::Device,
+        copy_layout: Arc<wgpu::BindGroupLayout>,
+        downsample_layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mip_count = hi_z_mip_count(width, height);
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: mip_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HI_Z_FORMAT,
+            usage: wgpu::TextureUsage::STORAGE | wgpu::TextureUsage::SAMPLED,
+            label: Some("hi_z"),
+        });
+        let view = texture.create_default_view();
+        let mip_views: Vec<_> = (0..mip_count)
+            .map(|mip| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: None,
+                    format: HI_Z_FORMAT,
+                    dimension: wgpu::TextureViewDimension::D2,
+                    aspect: wgpu::TextureAspect::default(),
+                    base_mip_level: mip,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    array_layer_count: 1,
+                })
+            })
+            .collect();
+
+        let downsample_bind_groups = mip_views
+            .windows(2)
+            .map(|views| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: downsample_layout,
+                    entries: Cow::Borrowed(&[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&views[0]),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&views[1]),
+                        },
+                    ]),
+                    label: Some(Cow::Borrowed("hi_z_downsample")),
+                })
+            })
+            .collect();
+
+        // Texels are always fetched, never filtered.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("hi_z"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            width,
+            height,
+            view_projection: Mat4::identity(),
+            copy_layout,
+            view,
+            mip_views,
+            downsample_bind_groups,
+            sampler,
+        }
+    }
+
+    /// The size of the depth buffer the hi-z buffer is built from.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn mip_count(&self) -> u32 {
+        self.mip_views.len() as u32
+    }
+
+    /// Records the passes that copy `depth` into the first mip and then build the rest of the mips.
+    /// `depth` has to be a single sampled depth buffer the size of the hi-z buffer that was rendered with `view_projection`.
+    pub(crate) fn build(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        depth: &wgpu::TextureView,
+        view_projection: Mat4,
+        copy_pipeline: &wgpu::ComputePipeline,
+        downsample_pipeline: &wgpu::ComputePipeline,
+    ) {
+        self.view_projection = view_projection;
+        // The depth buffer is recreated when the window is resized so it's bound every frame.
+        let copy_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.copy_layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&self.mip_views[0]),
+                },
+            ]),
+            label: Some(Cow::Borrowed("hi_z_copy")),
+        });
+
+        let workgroups = |size: u32| (size + HI_Z_WORKGROUP_SIZE - 1) / HI_Z_WORKGROUP_SIZE;
+        let mut pass = encoder.begin_compute_pass();
+        pass.set_pipeline(copy_pipeline);
+        pass.set_bind_group(0, &copy_bind_group, &[]);
+        pass.dispatch(workgroups(self.width), workgroups(self.height), 1);
+
+        // Each mip reads the one before it so they're separate dispatches.
+        pass.set_pipeline(downsample_pipeline);
+        for (index, bind_group) in self.downsample_bind_groups.iter().enumerate() {
+            let mip = index as u32 + 1;
+            let width = (self.width >> mip).max(1);
+            let height = (self.height >> mip).max(1);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch(workgroups(width), workgroups(height), 1);
+        }
+    }
+}
+
+/// Tests bounding spheres against a `HiZPass` and writes a visibility bit for each of their transforms.
+/// The bits are copied into a readback buffer and read on the CPU by `read_results` the next frame,
+/// so results are always a frame old. Transforms that weren't tested are visible.
+pub struct HiZCullPass {
+    layout: Arc<wgpu::BindGroupLayout>,
+    sphere_capacity: u32,
+    word_capacity: u32,
+    uniform_buffer: wgpu::Buffer,
+    sphere_buffer: wgpu::Buffer,
+    visibility_buffer: wgpu::Buffer,
+    readback: ReadbackBuffer,
+    // The transforms tested by the cull waiting in the readback buffer.
+    pending: Option<Vec<u32>>,
+    // The transforms tested by the last cull that was read back and which of them are visible.
+    tested: Vec<u32>,
+    visible: Vec<u32>,
+}
+
+impl HiZCullPass {
+    pub fn new(device: &wgpu::Device, layout: Arc<wgpu::BindGroupLayout>) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            size: std::mem::size_of::<HiZCullUniform>() as u64,
+            mapped_at_creation: false,
+            label: Some("hi_z_cull_uniform"),
+        });
+        let (sphere_buffer, visibility_buffer, readback) = Self::create_buffers(
+            device,
+            INITIAL_SPHERE_CAPACITY,
+            INITIAL_SPHERE_CAPACITY / 32,
+        );
+
+        Self {
+            layout,
+            sphere_capacity: INITIAL_SPHERE_CAPACITY,
+            word_capacity: INITIAL_SPHERE_CAPACITY / 32,
+            uniform_buffer,
+            sphere_buffer,
+            visibility_buffer,
+            readback,
+            pending: None,
+            tested: Vec::new(),
+            visible: Vec::new(),
+        }
+    }
+
+    fn create_buffers(
+        device: &wgpu::Device,
+        sphere_capacity: u32,
+        word_capacity: u32,
+    ) -> (wgpu::Buffer, wgpu::Buffer, ReadbackBuffer) {
+        let sphere_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+            size: sphere_capacity as u64 * std::mem::size_of::<HiZSphere>() as u64,
+            mapped_at_creation: false,
+            label: Some("hi_z_cull_spheres"),
+        });
+        let visibility_size = word_capacity as u64 * 4;
+        let visibility_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::STORAGE
+                | wgpu::BufferUsage::COPY_SRC
+                | wgpu::BufferUsage::COPY_DST,
+            size: visibility_size,
+            mapped_at_creation: false,
+            label: Some("hi_z_cull_visibility"),
+        });
+        let readback = ReadbackBuffer::new(device, "hi_z_cull_readback", visibility_size);
+        (sphere_buffer, visibility_buffer, readback)
+    }
+
+    /// True if the transform wasn't hidden the last time it was tested.
+    pub fn is_visible(&self, transform_index: u32) -> bool {
+        !is_bit_set(&self.tested, transform_index) || is_bit_set(&self.visible, transform_index)
+    }
+
+    /// Reads the visibility bits of the last recorded cull, call this after it was submitted.
+    pub(crate) fn read_results(&mut self, device: &wgpu::Device) {
+        let tested = match self.pending.take() {
+            Some(tested) => tested,
+            None => return,
+        };
+        let guard = self.readback.map_blocking(device);
+        self.visible = bytemuck::cast_slice::<u8, u32>(&guard).to_vec();
+        self.tested = tested;
+    }
+
+    /// Forgets every result so everything is visible.
+    pub(crate) fn clear_results(&mut self) {
+        self.pending = None;
+        self.tested.clear();
+        self.visible.clear();
+    }
+
+    /// Records the cull of `spheres` against `hi_z`, the hi-z buffer has to be built first.
+    pub(crate) fn record(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        hi_z: &HiZPass,
+        spheres: &[HiZSphere],
+    ) {
+        let mut tested = Vec::new();
+        for sphere in spheres.iter() {
+            set_bit(&mut tested, sphere.index);
+        }
+
+        let sphere_count = spheres.len() as u32;
+        let word_count = tested.len() as u32;
+        if sphere_count > self.sphere_capacity || word_count > self.word_capacity {
+            self.sphere_capacity = self.sphere_capacity.max(sphere_count.next_power_of_two());
+            self.word_capacity = self.word_capacity.max(word_count.next_power_of_two());
+            let (sphere_buffer, visibility_buffer, readback) =
+                Self::create_buffers(device, self.sphere_capacity, self.word_capacity);
+            self.sphere_buffer = sphere_buffer;
+            self.visibility_buffer = visibility_buffer;
+            self.readback = readback;
+        }
+
+        let (width, height) = hi_z.size();
+        let uniform = HiZCullUniform {
+            view_projection: hi_z.view_projection.into(),
+            counts: [sphere_count, hi_z.mip_count(), width, height],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        queue.write_buffer(&self.sphere_buffer, 0, bytemuck::cast_slice(spheres));
+        // Queue writes happen before the cull runs, the shader only ever sets bits.
+        let cleared = vec![0u32; self.word_capacity as usize];
+        queue.write_buffer(&self.visibility_buffer, 0, bytemuck::cast_slice(&cleared));
+
+        // The hi-z view can change when the window is resized so this is created every frame.
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(self.uniform_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(self.sphere_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(self.visibility_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&hi_z.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&hi_z.sampler),
+                },
+            ]),
+            label: Some(Cow::Borrowed("hi_z_cull")),
+        });
+
+        {
+            let mut pass = encoder.begin_compute_pass();
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch(
+                (sphere_count + HI_Z_CULL_WORKGROUP_SIZE - 1) / HI_Z_CULL_WORKGROUP_SIZE,
+                1,
+                1,
+            );
+        }
+        self.readback.request_read(encoder, &self.visibility_buffer);
+        self.pending = Some(tested);
+    }
+}
+
+/// Occlusion culling with a hierarchical z-buffer built from the previous frame's depth buffer.
+/// Meshes hidden behind what was drawn last frame are skipped by the mesh system.
+/// Off by default, set `enabled` to use it. It's skipped while msaa is on or a camera renders into a render target.
+/// Note: The visibility the mesh system sees is a frame old, meshes that come out from behind something
+/// can show up a frame late.
+pub struct HiZCulling {
+    pub enabled: bool,
+    pub(crate) hi_z: Option<HiZPass>,
+    pub(crate) cull: HiZCullPass,
+    // The view projection the depth buffer was rendered with last frame.
+    pub(crate) previous_view_projection: Option<Mat4>,
+}
+
+impl HiZCulling {
+    pub fn new(device: &wgpu::Device, cull_layout: Arc<wgpu::BindGroupLayout>) -> Self {
+        Self {
+            enabled: false,
+            hi_z: None,
+            cull: HiZCullPass::new(device, cull_layout),
+            previous_view_projection: None,
+        }
+    }
+
+    /// False if the transform's mesh was hidden the last time it was tested.
+    pub fn is_visible(&self, transform_index: u32) -> bool {
+        !self.enabled || self.cull.is_visible(transform_index)
+    }
+
+    /// Forgets the last results and the previous frame's depth, used when culling can't run this frame.
+    pub(crate) fn reset(&mut self) {
+        self.cull.clear_results();
+        self.previous_view_projection = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hi_z_mip_count, is_bit_set, set_bit};
+
+    #[test]
+    fn should_count_hi_z_mips() {
+        assert_eq!(hi_z_mip_count(1, 1), 1);
+        assert_eq!(hi_z_mip_count(2, 1), 2);
+        assert_eq!(hi_z_mip_count(1920, 1080), 11);
+        assert_eq!(hi_z_mip_count(1024, 1024), 11);
+        assert_eq!(hi_z_mip_count(0, 0), 1);
+    }
+
+    #[test]
+    fn should_set_visibility_bits() {
+        let mut bits = Vec::new();
+        set_bit(&mut bits, 3);
+        set_bit(&mut bits, 40);
+        assert_eq!(bits, vec![1 << 3, 1 << 8]);
+        assert!(is_bit_set(&bits, 3));
+        assert!(is_bit_set(&bits, 40));
+        assert!(!is_bit_set(&bits, 4));
+        assert!(!is_bit_set(&bits, 1000));
+    }
+}
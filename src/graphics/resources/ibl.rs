@@ -0,0 +1,49 @@
+use nalgebra_glm::Vec3;
+use std::sync::Arc;
+
+use super::{ProbeFormat, ProbeQuality, RenderTarget};
+use crate::{
+    graphics::{material::Skybox, RenderGraphError},
+    Application,
+};
+
+/// The image based lighting textures the pbr shader uses for its ambient term.
+/// Every probe produces these, the most recently created set is also stored as a resource.
+#[derive(Clone)]
+pub struct IblData {
+    /// Diffuse irradiance, convolved over the hemisphere around each direction.
+    pub irradiance_cubemap: Arc<RenderTarget>,
+    /// Specular radiance convolved with GGX, rougher surfaces read from lower mip levels.
+    pub prefiltered_env_cubemap: Arc<RenderTarget>,
+    /// The split sum BRDF integration lookup indexed by NdotV and roughness.
+    pub brdf_lut: Arc<RenderTarget>,
+}
+
+impl IblData {
+    /// Lights the scene with an equirectangular hdr image.
+    /// The image is projected onto a skybox of `size` and a probe at the origin convolves it
+    /// into the irradiance and prefiltered maps on the next frame.
+    /// Returns an error if the skybox couldn't be created, see `Skybox::new_hdr`.
+    pub fn from_equirectangular<T: Into<String>>(
+        app: &mut Application,
+        hdr_image_path: T,
+        size: f32,
+        quality: ProbeQuality,
+    ) -> Result<Self, RenderGraphError> {
+        let skybox = Skybox::new_hdr(app, hdr_image_path, size)?;
+        app.current_scene.world.insert((), vec![(skybox,)]);
+
+        let probe_entity =
+            crate::scene::entities::probe::create(app, Vec3::zeros(), quality, ProbeFormat::RGBA16);
+        let probe_id = app
+            .current_scene
+            .world
+            .get_component::<crate::scene::components::Probe>(probe_entity)
+            .unwrap()
+            .id;
+
+        let ibl_data = app.probe_manager.get(probe_id).unwrap().ibl_data();
+        app.resources.insert(ibl_data.clone());
+        Ok(ibl_data)
+    }
+}
@@ -1,18 +1,60 @@
 mod bind_group;
+mod bindless;
+mod encoder_pool;
+mod framed_buffer;
+mod gbuffer;
+mod hdr_framebuffer;
+mod ibl;
+mod gpu_driven;
+mod gpu_profiler;
 mod gpu_resource_manager;
+mod hi_z;
+mod ocean;
+mod particles;
 mod probe;
 mod probe_manager;
+mod readback;
 mod render_target;
+mod render_target_pool;
+mod render_stats;
+mod slab;
+mod transform_upload;
 
-pub use bind_group::BindGroup;
-pub use gpu_resource_manager::GPUResourceManager;
-pub use render_target::RenderTarget;
+pub use bind_group::{BindGroup, BindGroupLayoutHash};
+pub use bindless::{BindlessArrayHandle, BINDLESS_FEATURES};
+pub use encoder_pool::{CommandEncoderPool, PooledEncoder};
+pub use framed_buffer::{FramedBuffer, DEFAULT_FRAME_COUNT};
+pub use gbuffer::{
+    GBuffer, GBUFFER_ALBEDO_FORMAT, GBUFFER_MATERIAL_FORMAT, GBUFFER_NORMAL_FORMAT,
+};
+pub use gpu_driven::{DrawIndexedIndirectArgs, GpuDrivenRenderer};
+pub(crate) use gpu_driven::GpuDraw;
+pub use gpu_profiler::{GpuProfiler, GpuTimestamp};
+pub use gpu_resource_manager::{GPUResourceManager, MAX_TRANSFORMS, TRANSFORM_SLAB};
+pub use hdr_framebuffer::HdrFramebuffer;
+pub use hi_z::{hi_z_mip_count, HiZCullPass, HiZCulling, HiZPass, HI_Z_FORMAT};
+pub(crate) use hi_z::HiZSphere;
+pub use readback::{ReadbackBuffer, ReadbackGuard};
+pub use ocean::{OceanBuffers, OCEAN_MAP_FORMAT};
+pub(crate) use ocean::OceanPipelines;
+pub use particles::{ParticleBuffers, VelocityFields, GRADIENT_SAMPLES, MAX_VELOCITY_FIELDS};
+pub use ibl::IblData;
+pub use render_stats::RenderStats;
+pub use render_target::{RenderTarget, RenderTargetHandle};
+pub use slab::{SlabHandle, SLAB_ALIGNMENT};
+pub use transform_upload::{
+    PushConstantTransformStrategy, TransformUploadStrategy, PUSH_CONSTANT_ENTITY_LIMIT,
+};
+pub use render_target_pool::{
+    PooledRenderTarget, RenderTargetDesc, RenderTargetPool, RenderTargetPoolStats,
+};
 
 pub(crate) use probe::CurrentRenderTarget;
 
 pub use probe::{Probe, ProbeFormat, ProbeQuality, ProbeUniform};
 
-pub(crate) use probe_manager::ProbeManager;
+pub(crate) use probe_manager::{ProbeManager, ReflectionProbes};
+pub use probe_manager::MAX_REFLECTION_PROBES;
 
 mod arc_render_pass;
 pub use arc_render_pass::ArcRenderPass;
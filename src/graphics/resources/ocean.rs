@@ -0,0 +1,398 @@
+use super::GPUResourceManager;
+use crate::{assets::mesh::MeshVertexData, scene::components::OceanSurface};
+use bytemuck::{Pod, Zeroable};
+use std::{
+    borrow::Cow,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+// Must match the local size in the ocean compute shaders.
+const OCEAN_WORKGROUP_SIZE: u32 = 8;
+const GRID_WORKGROUP_SIZE: u32 = 64;
+
+// Dynamic uniform offsets have to be aligned to 256 bytes.
+const FFT_PASS_STRIDE: u64 = 256;
+
+/// The format of the spectrum textures the FFT runs on.
+const SPECTRUM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+/// The format of the height and normal maps the water is drawn with.
+pub const OCEAN_MAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct OceanUniform {
+    // (patch size, amplitude, time, 0)
+    settings: [f32; 4],
+    // xy is the wind direction, z is the wind speed.
+    wind: [f32; 4],
+    // (fft size, grid vertices per side)
+    counts: [u32; 4],
+}
+
+unsafe impl Zeroable for OceanUniform {}
+unsafe impl Pod for OceanUniform {}
+
+/// The butterfly span and direction of each pass of a 2D FFT of `size` texels, rows first and then columns.
+/// `size` must be a power of two.
+pub(crate) fn fft_passes(size: u32) -> Vec<[u32; 4]> {
+    let mut passes = Vec::new();
+    for vertical in 0..2 {
+        let mut span = 1;
+        while span < size {
+            passes.push([span, vertical, size, 0]);
+            span *= 2;
+        }
+    }
+    passes
+}
+
+/// Two counter clockwise triangles for each quad of a grid with `quads` quads along each side.
+pub(crate) fn grid_indices(quads: u32) -> Vec<u32> {
+    let side = quads + 1;
+    let mut indices = Vec::with_capacity((quads * quads * 6) as usize);
+    for z in 0..quads {
+        for x in 0..quads {
+            let i = z * side + x;
+            indices.extend_from_slice(&[i, i + side, i + 1, i + 1, i + side, i + side + 1]);
+        }
+    }
+    indices
+}
+
+/// The compute pipelines that simulate an ocean, in the order they run.
+pub(crate) struct OceanPipelines<'a> {
+    pub spectrum: &'a wgpu::ComputePipeline,
+    pub grid: &'a wgpu::ComputePipeline,
+    pub time: &'a wgpu::ComputePipeline,
+    pub fft: &'a wgpu::ComputePipeline,
+    pub maps: &'a wgpu::ComputePipeline,
+}
+
+/// The GPU state of a single `OceanSurface`.
+/// The Phillips spectrum and the grid are created on the GPU the first time the ocean is simulated, after that
+/// each frame advances the spectrum and runs an inverse FFT to get the height and normal maps.
+pub struct OceanBuffers {
+    fft_size: u32,
+    size: f32,
+    amplitude: f32,
+    wind_direction: nalgebra_glm::Vec2,
+    uniform_buffer: wgpu::Buffer,
+    _pass_buffer: wgpu::Buffer,
+    pass_count: usize,
+    _textures: Vec<wgpu::Texture>,
+    init_bind_group: wgpu::BindGroup,
+    time_bind_group: wgpu::BindGroup,
+    // Reads the first spectrum texture and writes the second, then the other way around.
+    fft_bind_groups: [wgpu::BindGroup; 2],
+    maps_bind_group: wgpu::BindGroup,
+    pub(crate) render_bind_group: wgpu::BindGroup,
+    pub(crate) vertex_buffer: wgpu::Buffer,
+    pub(crate) index_buffer: wgpu::Buffer,
+    pub(crate) index_count: u32,
+    initialized: AtomicBool,
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    label: &str,
+    size: u32,
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsage,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage,
+    });
+    let view = texture.create_default_view();
+    (texture, view)
+}
+
+fn texture_entry(binding: u32, view: &wgpu::TextureView) -> wgpu::BindGroupEntry<'_> {
+    wgpu::BindGroupEntry {
+        binding,
+        resource: wgpu::BindingResource::TextureView(view),
+    }
+}
+
+impl OceanBuffers {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        resource_manager: &GPUResourceManager,
+        surface: &OceanSurface,
+    ) -> Self {
+        let layout = |name: &str| resource_manager.get_bind_group_layout(name).unwrap();
+        let fft_size = surface.fft_size();
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            size: std::mem::size_of::<OceanUniform>() as u64,
+            mapped_at_creation: false,
+            label: Some("ocean_uniform"),
+        });
+
+        let passes = fft_passes(fft_size);
+        let mut pass_data = vec![0u8; passes.len() * FFT_PASS_STRIDE as usize];
+        for (pass, data) in passes
+            .iter()
+            .zip(pass_data.chunks_mut(FFT_PASS_STRIDE as usize))
+        {
+            data[..16].copy_from_slice(bytemuck::cast_slice(pass));
+        }
+        let pass_buffer = device.create_buffer_with_data(&pass_data, wgpu::BufferUsage::UNIFORM);
+
+        let storage = wgpu::TextureUsage::STORAGE;
+        let sampled = wgpu::TextureUsage::STORAGE | wgpu::TextureUsage::SAMPLED;
+        let (initial_spectrum, initial_spectrum_view) = create_texture(
+            device,
+            "ocean_initial_spectrum",
+            fft_size,
+            SPECTRUM_FORMAT,
+            storage,
+        );
+        let (spectrum_a, spectrum_a_view) = create_texture(
+            device,
+            "ocean_spectrum_a",
+            fft_size,
+            SPECTRUM_FORMAT,
+            storage,
+        );
+        let (spectrum_b, spectrum_b_view) = create_texture(
+            device,
+            "ocean_spectrum_b",
+            fft_size,
+            SPECTRUM_FORMAT,
+            storage,
+        );
+        let (height_map, height_map_view) = create_texture(
+            device,
+            "ocean_height_map",
+            fft_size,
+            OCEAN_MAP_FORMAT,
+            sampled,
+        );
+        let (normal_map, normal_map_view) = create_texture(
+            device,
+            "ocean_normal_map",
+            fft_size,
+            OCEAN_MAP_FORMAT,
+            sampled,
+        );
+
+        let side = fft_size + 1;
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::STORAGE,
+            size: (side * side) as u64 * std::mem::size_of::<MeshVertexData>() as u64,
+            mapped_at_creation: false,
+            label: Some("ocean_grid"),
+        });
+        let indices = grid_indices(fft_size);
+        let index_buffer = device
+            .create_buffer_with_data(bytemuck::cast_slice(&indices), wgpu::BufferUsage::INDEX);
+
+        let uniform_entry = || wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+        };
+        let init_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout("ocean_init_layout"),
+            entries: Cow::Borrowed(&[
+                uniform_entry(),
+                texture_entry(1, &initial_spectrum_view),
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(vertex_buffer.slice(..)),
+                },
+            ]),
+            label: Some(Cow::Borrowed("ocean_init")),
+        });
+        let time_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout("ocean_time_layout"),
+            entries: Cow::Borrowed(&[
+                uniform_entry(),
+                texture_entry(1, &initial_spectrum_view),
+                texture_entry(2, &spectrum_a_view),
+            ]),
+            label: Some(Cow::Borrowed("ocean_time")),
+        });
+        let fft_layout = layout("ocean_fft_layout");
+        let fft_bind_group = |source, destination| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &fft_layout,
+                entries: Cow::Borrowed(&[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(pass_buffer.slice(0..16)),
+                    },
+                    texture_entry(1, source),
+                    texture_entry(2, destination),
+                ]),
+                label: Some(Cow::Borrowed("ocean_fft")),
+            })
+        };
+        let fft_bind_groups = [
+            fft_bind_group(&spectrum_a_view, &spectrum_b_view),
+            fft_bind_group(&spectrum_b_view, &spectrum_a_view),
+        ];
+        // There's always an even number of passes so the result ends up back in the first texture.
+        let maps_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout("ocean_maps_layout"),
+            entries: Cow::Borrowed(&[
+                uniform_entry(),
+                texture_entry(1, &spectrum_a_view),
+                texture_entry(2, &height_map_view),
+                texture_entry(3, &normal_map_view),
+            ]),
+            label: Some(Cow::Borrowed("ocean_maps")),
+        });
+
+        // The maps tile, so the sampler repeats.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ocean_sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout("ocean_render_layout"),
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                texture_entry(1, &height_map_view),
+                texture_entry(2, &normal_map_view),
+            ]),
+            label: Some(Cow::Borrowed("ocean_render")),
+        });
+
+        Self {
+            fft_size,
+            size: surface.size,
+            amplitude: surface.amplitude,
+            wind_direction: surface.wind_direction,
+            uniform_buffer,
+            _pass_buffer: pass_buffer,
+            pass_count: passes.len(),
+            _textures: vec![
+                initial_spectrum,
+                spectrum_a,
+                spectrum_b,
+                height_map,
+                normal_map,
+            ],
+            init_bind_group,
+            time_bind_group,
+            fft_bind_groups,
+            maps_bind_group,
+            render_bind_group,
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            initialized: AtomicBool::new(false),
+        }
+    }
+
+    /// False if the surface's settings changed since the buffers were created.
+    pub fn matches(&self, surface: &OceanSurface) -> bool {
+        self.fft_size == surface.fft_size()
+            && self.size == surface.size
+            && self.amplitude == surface.amplitude
+            && self.wind_direction == surface.wind_direction
+    }
+
+    /// Uploads the surface's settings for this frame.
+    pub(crate) fn update(&self, queue: &wgpu::Queue, surface: &OceanSurface) {
+        let wind_speed = nalgebra_glm::length(&surface.wind_direction);
+        let direction = if wind_speed > 0.0 {
+            surface.wind_direction / wind_speed
+        } else {
+            nalgebra_glm::vec2(1.0, 0.0)
+        };
+        let uniform = OceanUniform {
+            settings: [surface.size, surface.amplitude, surface.time, 0.0],
+            wind: [direction.x, direction.y, wind_speed, 0.0],
+            counts: [self.fft_size, self.fft_size + 1, 0, 0],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// Records the passes that turn the spectrum into this frame's height and normal maps.
+    /// The first time this is called the initial spectrum and the grid are created as well.
+    pub(crate) fn simulate(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipelines: &OceanPipelines<'_>,
+    ) {
+        let workgroups = (self.fft_size + OCEAN_WORKGROUP_SIZE - 1) / OCEAN_WORKGROUP_SIZE;
+        let mut pass = encoder.begin_compute_pass();
+
+        if !self.initialized.swap(true, Ordering::Relaxed) {
+            let side = self.fft_size + 1;
+            pass.set_pipeline(pipelines.spectrum);
+            pass.set_bind_group(0, &self.init_bind_group, &[]);
+            pass.dispatch(workgroups, workgroups, 1);
+            pass.set_pipeline(pipelines.grid);
+            pass.dispatch(
+                (side * side + GRID_WORKGROUP_SIZE - 1) / GRID_WORKGROUP_SIZE,
+                1,
+                1,
+            );
+        }
+
+        pass.set_pipeline(pipelines.time);
+        pass.set_bind_group(0, &self.time_bind_group, &[]);
+        pass.dispatch(workgroups, workgroups, 1);
+
+        // Each pass has to see the results of the previous one, so they're separate dispatches.
+        // Every invocation handles a pair of texels.
+        let half_workgroups = (self.fft_size / 2 + OCEAN_WORKGROUP_SIZE - 1) / OCEAN_WORKGROUP_SIZE;
+        pass.set_pipeline(pipelines.fft);
+        for index in 0..self.pass_count {
+            let offset = (index as u64 * FFT_PASS_STRIDE) as wgpu::DynamicOffset;
+            pass.set_bind_group(0, &self.fft_bind_groups[index % 2], &[offset]);
+            pass.dispatch(half_workgroups, workgroups, 1);
+        }
+
+        pass.set_pipeline(pipelines.maps);
+        pass.set_bind_group(0, &self.maps_bind_group, &[]);
+        pass.dispatch(workgroups, workgroups, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fft_passes, grid_indices, OceanUniform};
+
+    #[test]
+    fn should_build_fft_passes() {
+        let passes: Vec<(u32, u32)> = fft_passes(8)
+            .iter()
+            .map(|pass| (pass[0], pass[1]))
+            .collect();
+        assert_eq!(passes, vec![(1, 0), (2, 0), (4, 0), (1, 1), (2, 1), (4, 1)]);
+
+        // Matches the std140 layout in ocean.glsl.
+        assert_eq!(std::mem::size_of::<OceanUniform>(), 48);
+    }
+
+    #[test]
+    fn should_build_grid_indices() {
+        let indices = grid_indices(2);
+        assert_eq!(indices.len(), 2 * 2 * 6);
+        assert_eq!(&indices[..6], &[0, 3, 1, 1, 3, 4]);
+        assert_eq!(*indices.iter().max().unwrap(), 8);
+    }
+}
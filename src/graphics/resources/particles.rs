@@ -0,0 +1,498 @@
+use super::DrawIndexedIndirectArgs;
+use crate::{
+    graphics::pipelines::particles::PARTICLE_WORKGROUP_SIZE,
+    scene::components::{EmitterShape, ParticleSystem, VelocityField, VELOCITY_FIELD_FORMAT},
+};
+use bytemuck::{Pod, Zeroable};
+use nalgebra_glm::{Mat4, Vec3};
+use std::{borrow::Cow, sync::Arc};
+
+/// How many colors of `ParticleSystem::color_over_lifetime` are sent to the GPU, the shader blends between them.
+pub const GRADIENT_SAMPLES: usize = 8;
+
+/// How many `VelocityField`s affect particles at once, fields past this are ignored.
+pub const MAX_VELOCITY_FIELDS: usize = 4;
+
+// Dynamic uniform offsets have to be aligned to 256 bytes.
+const SORT_STEP_STRIDE: u64 = 256;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    // w is the particle's age in seconds, negative until it's first emitted.
+    position: [f32; 4],
+    velocity: [f32; 4],
+}
+
+unsafe impl Zeroable for Particle {}
+unsafe impl Pod for Particle {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SortKey {
+    // Distance to the camera, -1 for dead particles so they sort to the end.
+    depth: f32,
+    index: u32,
+}
+
+unsafe impl Zeroable for SortKey {}
+unsafe impl Pod for SortKey {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ParticleUniform {
+    emitter: [[f32; 4]; 4],
+    camera_position: [f32; 4],
+    // w is the drag.
+    gravity: [f32; 4],
+    // x is the shape, yzw are it's parameters.
+    shape: [f32; 4],
+    // (time, delta time, lifetime, start speed)
+    timing: [f32; 4],
+    // (start size, end size)
+    sizes: [f32; 4],
+    // (max particles, sort count)
+    counts: [u32; 4],
+    colors: [[f32; 4]; GRADIENT_SAMPLES],
+}
+
+unsafe impl Zeroable for ParticleUniform {}
+unsafe impl Pod for ParticleUniform {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct VelocityFieldUniform {
+    // w is the strength.
+    bounds_min: [[f32; 4]; MAX_VELOCITY_FIELDS],
+    bounds_max: [[f32; 4]; MAX_VELOCITY_FIELDS],
+    // x is how many fields are bound.
+    count: [u32; 4],
+}
+
+unsafe impl Zeroable for VelocityFieldUniform {}
+unsafe impl Pod for VelocityFieldUniform {}
+
+/// The compare and swap distances of each bitonic sort pass for `count` keys, `count` must be a power of two.
+pub(crate) fn sort_steps(count: u32) -> Vec<[u32; 4]> {
+    let mut steps = Vec::new();
+    let mut k = 2;
+    while k <= count {
+        let mut j = k / 2;
+        while j > 0 {
+            steps.push([k, j, count, 0]);
+            j /= 2;
+        }
+        k *= 2;
+    }
+    steps
+}
+
+/// The GPU state of a single `ParticleSystem`.
+/// Particles are simulated and sorted back to front on the GPU, the living particle count is written straight
+/// into the indirect draw arguments so it's never read back.
+pub struct ParticleBuffers {
+    max_particles: u32,
+    sort_count: u32,
+    _particle_buffer: wgpu::Buffer,
+    _sort_buffer: wgpu::Buffer,
+    _step_buffer: wgpu::Buffer,
+    uniform_buffer: wgpu::Buffer,
+    indirect_buffer: wgpu::Buffer,
+    // Copied over the indirect arguments each frame to reset the instance count.
+    reset_buffer: wgpu::Buffer,
+    pub(crate) index_buffer: wgpu::Buffer,
+    sort_steps: Vec<[u32; 4]>,
+    simulate_bind_group: wgpu::BindGroup,
+    sort_bind_group: wgpu::BindGroup,
+    pub(crate) render_bind_group: wgpu::BindGroup,
+}
+
+impl ParticleBuffers {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        simulate_layout: &wgpu::BindGroupLayout,
+        sort_layout: &wgpu::BindGroupLayout,
+        render_layout: &wgpu::BindGroupLayout,
+        system: &ParticleSystem,
+    ) -> Self {
+        let max_particles = system.max_particles.max(1);
+        let sort_count = max_particles.next_power_of_two();
+
+        // Particles start spread out over one lifetime so they're emitted at a steady rate.
+        let particles: Vec<Particle> = (0..max_particles)
+            .map(|i| Particle {
+                position: [
+                    0.0,
+                    0.0,
+                    0.0,
+                    -system.lifetime * i as f32 / max_particles as f32,
+                ],
+                velocity: [0.0; 4],
+            })
+            .collect();
+        let particle_buffer = device
+            .create_buffer_with_data(bytemuck::cast_slice(&particles), wgpu::BufferUsage::STORAGE);
+        let sort_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::STORAGE,
+            size: sort_count as u64 * std::mem::size_of::<SortKey>() as u64,
+            mapped_at_creation: false,
+            label: Some("particle_sort_keys"),
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            size: std::mem::size_of::<ParticleUniform>() as u64,
+            mapped_at_creation: false,
+            label: Some("particle_uniform"),
+        });
+
+        let args = DrawIndexedIndirectArgs {
+            index_count: 6,
+            ..Default::default()
+        };
+        let reset_buffer =
+            device.create_buffer_with_data(bytemuck::bytes_of(&args), wgpu::BufferUsage::COPY_SRC);
+        let indirect_buffer = device.create_buffer_with_data(
+            bytemuck::bytes_of(&args),
+            wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::INDIRECT | wgpu::BufferUsage::COPY_DST,
+        );
+        let index_buffer = device.create_buffer_with_data(
+            bytemuck::cast_slice(&[0u32, 1, 2, 2, 3, 0]),
+            wgpu::BufferUsage::INDEX,
+        );
+
+        let sort_steps = sort_steps(sort_count);
+        let mut step_data = vec![0u8; sort_steps.len().max(1) * SORT_STEP_STRIDE as usize];
+        for (step, data) in sort_steps
+            .iter()
+            .zip(step_data.chunks_mut(SORT_STEP_STRIDE as usize))
+        {
+            data[..16].copy_from_slice(bytemuck::cast_slice(step));
+        }
+        let step_buffer = device.create_buffer_with_data(&step_data, wgpu::BufferUsage::UNIFORM);
+
+        let simulate_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: simulate_layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(particle_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(sort_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(indirect_buffer.slice(..)),
+                },
+            ]),
+            label: Some(Cow::Borrowed("particle_simulate")),
+        });
+        let sort_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: sort_layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(step_buffer.slice(0..16)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(sort_buffer.slice(..)),
+                },
+            ]),
+            label: Some(Cow::Borrowed("particle_sort")),
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: render_layout,
+            entries: Cow::Borrowed(&[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(particle_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(sort_buffer.slice(..)),
+                },
+            ]),
+            label: Some(Cow::Borrowed("particle_render")),
+        });
+
+        Self {
+            max_particles,
+            sort_count,
+            _particle_buffer: particle_buffer,
+            _sort_buffer: sort_buffer,
+            _step_buffer: step_buffer,
+            uniform_buffer,
+            indirect_buffer,
+            reset_buffer,
+            index_buffer,
+            sort_steps,
+            simulate_bind_group,
+            sort_bind_group,
+            render_bind_group,
+        }
+    }
+
+    /// The `max_particles` the buffers were created for.
+    pub fn max_particles(&self) -> u32 {
+        self.max_particles
+    }
+
+    /// The buffer `draw_indexed_indirect` reads the quad count from.
+    pub fn indirect_buffer(&self) -> &wgpu::Buffer {
+        &self.indirect_buffer
+    }
+
+    /// Uploads the system's settings for this frame.
+    pub(crate) fn update(
+        &self,
+        queue: &wgpu::Queue,
+        system: &ParticleSystem,
+        emitter: Mat4,
+        camera_position: Vec3,
+        delta_time: f32,
+    ) {
+        let shape = match system.emitter {
+            EmitterShape::Point => [0.0; 4],
+            EmitterShape::Sphere { radius } => [1.0, radius, 0.0, 0.0],
+            EmitterShape::Box { half_extents } => {
+                [2.0, half_extents.x, half_extents.y, half_extents.z]
+            }
+            EmitterShape::Cone { angle, radius } => [3.0, angle.to_radians(), radius, 0.0],
+        };
+        let mut colors = [[0.0; 4]; GRADIENT_SAMPLES];
+        for (i, color) in colors.iter_mut().enumerate() {
+            *color = system
+                .color_over_lifetime
+                .sample(i as f32 / (GRADIENT_SAMPLES - 1) as f32)
+                .into();
+        }
+
+        let uniform = ParticleUniform {
+            emitter: emitter.into(),
+            camera_position: [camera_position.x, camera_position.y, camera_position.z, 1.0],
+            gravity: [
+                system.gravity.x,
+                system.gravity.y,
+                system.gravity.z,
+                system.drag,
+            ],
+            shape,
+            timing: [
+                system.time,
+                delta_time,
+                system.lifetime.max(0.001),
+                system.start_speed,
+            ],
+            sizes: [system.start_size, system.end_size, 0.0, 0.0],
+            counts: [self.max_particles, self.sort_count, 0, 0],
+            colors,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// Records the simulation pass followed by the bitonic sort passes.
+    pub(crate) fn simulate(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        simulate_pipeline: &wgpu::ComputePipeline,
+        sort_pipeline: &wgpu::ComputePipeline,
+        velocity_bind_group: &wgpu::BindGroup,
+    ) {
+        encoder.copy_buffer_to_buffer(
+            &self.reset_buffer,
+            0,
+            &self.indirect_buffer,
+            0,
+            std::mem::size_of::<DrawIndexedIndirectArgs>() as u64,
+        );
+
+        let workgroups = (self.sort_count + PARTICLE_WORKGROUP_SIZE - 1) / PARTICLE_WORKGROUP_SIZE;
+        let mut pass = encoder.begin_compute_pass();
+        pass.set_pipeline(simulate_pipeline);
+        pass.set_bind_group(0, &self.simulate_bind_group, &[]);
+        pass.set_bind_group(1, velocity_bind_group, &[]);
+        pass.dispatch(workgroups, 1, 1);
+
+        // Each sort step has to see the results of the previous one, so they're separate dispatches.
+        pass.set_pipeline(sort_pipeline);
+        for step in 0..self.sort_steps.len() {
+            let offset = (step as u64 * SORT_STEP_STRIDE) as wgpu::DynamicOffset;
+            pass.set_bind_group(0, &self.sort_bind_group, &[offset]);
+            pass.dispatch(workgroups, 1, 1);
+        }
+    }
+}
+
+/// The `VelocityField`s bound to the particle simulation, updated by the `bind_velocity_fields` system.
+/// Unused texture slots are bound to a single texel with no velocity.
+pub struct VelocityFields {
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    empty_view: Arc<wgpu::TextureView>,
+    // The views the bind group was created with, it's only recreated when they change.
+    views: Vec<Arc<wgpu::TextureView>>,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+impl VelocityFields {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_with_data(
+            bytemuck::bytes_of(&VelocityFieldUniform::zeroed()),
+            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        );
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("velocity_field_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let extent = wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth: 1,
+        };
+        let empty_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("empty_velocity_field"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: VELOCITY_FIELD_FORMAT,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &empty_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &[0; 4],
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4,
+                rows_per_image: 1,
+            },
+            extent,
+        );
+        let empty_view = Arc::new(empty_texture.create_default_view());
+
+        let views = vec![empty_view.clone(); MAX_VELOCITY_FIELDS];
+        let bind_group = Self::create_bind_group(device, layout, &uniform_buffer, &sampler, &views);
+
+        Self {
+            uniform_buffer,
+            sampler,
+            empty_view,
+            views,
+            bind_group,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        sampler: &wgpu::Sampler,
+        views: &[Arc<wgpu::TextureView>],
+    ) -> wgpu::BindGroup {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ];
+        for (i, view) in views.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 + i as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+        }
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: Cow::Owned(entries),
+            label: Some(Cow::Borrowed("particle_velocity_fields")),
+        })
+    }
+
+    /// Uploads the first `MAX_VELOCITY_FIELDS` fields.
+    pub(crate) fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        fields: &[&VelocityField],
+    ) {
+        let fields = &fields[..fields.len().min(MAX_VELOCITY_FIELDS)];
+        let mut uniform = VelocityFieldUniform::zeroed();
+        let mut views = vec![self.empty_view.clone(); MAX_VELOCITY_FIELDS];
+        for (i, field) in fields.iter().enumerate() {
+            let (min, max) = (field.bounds.min, field.bounds.max);
+            uniform.bounds_min[i] = [min.x, min.y, min.z, field.strength];
+            uniform.bounds_max[i] = [max.x, max.y, max.z, 0.0];
+            views[i] = field.view.clone();
+        }
+        uniform.count[0] = fields.len() as u32;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let changed = self
+            .views
+            .iter()
+            .zip(views.iter())
+            .any(|(a, b)| !Arc::ptr_eq(a, b));
+        if changed {
+            self.bind_group = Self::create_bind_group(
+                device,
+                layout,
+                &self.uniform_buffer,
+                &self.sampler,
+                &views,
+            );
+            self.views = views;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_steps, ParticleUniform, SortKey, VelocityFieldUniform};
+
+    #[test]
+    fn should_build_bitonic_steps() {
+        let steps = sort_steps(8);
+        let distances: Vec<(u32, u32)> = steps.iter().map(|step| (step[0], step[1])).collect();
+        assert_eq!(
+            distances,
+            vec![(2, 1), (4, 2), (4, 1), (8, 4), (8, 2), (8, 1)]
+        );
+        assert!(sort_steps(1).is_empty());
+
+        // Matches the std140 and std430 layouts in the particle shaders.
+        assert_eq!(std::mem::size_of::<SortKey>(), 8);
+        assert_eq!(std::mem::size_of::<ParticleUniform>(), 288);
+        assert_eq!(std::mem::size_of::<VelocityFieldUniform>(), 144);
+    }
+}
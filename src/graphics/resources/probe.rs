@@ -3,7 +3,9 @@ use legion::prelude::*;
 use nalgebra_glm::{Vec3, Vec4};
 use std::{borrow::Cow, sync::Arc};
 
-use super::{BindGroup, GPUResourceManager, RenderTarget};
+use super::{
+    BindGroup, GPUResourceManager, IblData, RenderTarget, RenderTargetDesc, RenderTargetPool,
+};
 use crate::{
     graphics::pipeline_manager::PipelineManager, scene::components::CameraData, AssetManager,
 };
@@ -26,7 +28,7 @@ impl Into<wgpu::TextureFormat> for ProbeFormat {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ProbeQuality {
     Low,
     Medium,
@@ -34,6 +36,17 @@ pub enum ProbeQuality {
 }
 
 impl ProbeQuality {
+    /// The quality whose probe resolution is closest to `resolution`.
+    pub fn from_resolution(resolution: u32) -> Self {
+        let qualities = [ProbeQuality::Low, ProbeQuality::Medium, ProbeQuality::High];
+        *qualities
+            .iter()
+            .min_by_key(|quality| {
+                (quality.get_probe_resoultion() as i64 - resolution as i64).abs()
+            })
+            .unwrap()
+    }
+
     pub(crate) fn get_irradiance_resoultion(&self) -> u32 {
         match self {
             ProbeQuality::Low => 64,
@@ -80,9 +93,10 @@ pub struct Probe {
     irradiance_resoultion: u32,
     specular_resoultion: u32,
     probe_cube: Arc<RenderTarget>,
-    irradiance_target: RenderTarget,
-    specular_target: RenderTarget,
-    brdf_texture: RenderTarget,
+    irradiance_target: Arc<RenderTarget>,
+    specular_target: Arc<RenderTarget>,
+    brdf_texture: Arc<RenderTarget>,
+    bind_group: Arc<BindGroup>,
     pub(crate) has_rendered: bool,
 }
 
@@ -184,7 +198,6 @@ impl Probe {
                 ]),
             }),
         );
-        resource_manager.add_single_bind_group("probe_material", bind_group);
 
         Self {
             id,
@@ -192,7 +205,7 @@ impl Probe {
             format,
             has_rendered: false,
             irradiance_resoultion,
-            irradiance_target,
+            irradiance_target: Arc::new(irradiance_target),
             probe_cube: Arc::new(probe_cube),
             quality,
             sample_count,
@@ -201,18 +214,35 @@ impl Probe {
             samples_remaining: 0,
             scale,
             specular_resoultion,
-            specular_target,
-            brdf_texture,
+            specular_target: Arc::new(specular_target),
+            brdf_texture: Arc::new(brdf_texture),
+            bind_group: Arc::new(bind_group),
+        }
+    }
+
+    /// The bind group meshes use to sample this probe, at slot 3.
+    pub(crate) fn bind_group(&self) -> Arc<BindGroup> {
+        self.bind_group.clone()
+    }
+
+    /// The textures the pbr shader samples for ambient lighting. They're filled in once the probe has rendered.
+    pub fn ibl_data(&self) -> IblData {
+        IblData {
+            irradiance_cubemap: self.irradiance_target.clone(),
+            prefiltered_env_cubemap: self.specular_target.clone(),
+            brdf_lut: self.brdf_texture.clone(),
         }
     }
 
     // Render's scene to the cube
     // This is considered a very "HEAVY" operation, and shouldn't be treated lightly
     // TODO: If wgpu ever adds multi-view's use that instead..
+    // Environment probes only capture the sky, `include_meshes` also draws the scene's meshes for reflection probes.
     pub(crate) fn render_scene(
         &mut self,
         resources: &mut Resources,
         scene: &mut crate::scene::Scene,
+        include_meshes: bool,
     ) {
         // If we already rendered don't do it again.
         if self.has_rendered {
@@ -222,15 +252,38 @@ impl Probe {
         self.samples_remaining = self.sample_count;
         self.sample_offset = 0;
 
+        // The cube is swapped for a mip mapped copy without depth after rendering, so a recapture needs a new one.
+        if self.probe_cube.depth_texture_view.is_none() {
+            let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+            let probe_resoultion = self.quality.get_probe_resoultion();
+            let mut probe_cube = RenderTarget::new(
+                &device,
+                probe_resoultion as f32,
+                probe_resoultion as f32,
+                6,
+                1,
+                self.format.into(),
+                wgpu::TextureUsage::SAMPLED
+                    | wgpu::TextureUsage::COPY_SRC
+                    | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            );
+            probe_cube.with_depth(&device);
+            self.probe_cube = Arc::new(probe_cube);
+        }
+
         // Create new render schedule has to be different from normal as we want to not queue items up right away.
         // TODO: Have more systems support our CurrentRenderTarget.
-        let mut render_schedule =
-            Schedule::builder() //create_render_schedule_builder()
-                .add_system(crate::graphics::systems::globals::create())
-                .add_system(crate::graphics::systems::skybox::create())
-                .flush()
-                .add_thread_local_fn(crate::graphics::systems::render::create())
-                .build();
+        let mut render_schedule_builder = Schedule::builder() //create_render_schedule_builder()
+            .add_system(crate::graphics::systems::globals::create())
+            .add_system(crate::graphics::systems::skybox::create());
+        if include_meshes {
+            render_schedule_builder =
+                render_schedule_builder.add_system(crate::graphics::systems::mesh::create());
+        }
+        let mut render_schedule = render_schedule_builder
+            .flush()
+            .add_thread_local_fn(crate::graphics::systems::render::create())
+            .build();
 
         // First we need to create new pipelines using the correct texture format
         let current_pipelines;
@@ -240,32 +293,36 @@ impl Probe {
             let device = resources.get::<Arc<wgpu::Device>>().unwrap();
             let asset_manager = resources.get::<AssetManager>().unwrap();
             let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
-            let skybox_pipeline = pipeline_manager.get("skybox", None).unwrap();
-            let realtime_skybox_pipeline = pipeline_manager.get("realtime_skybox", None).unwrap();
-            let mut new_skybox_desc = skybox_pipeline.desc.clone();
-            let mut new_realtime_skybox_desc = realtime_skybox_pipeline.desc.clone();
-            new_skybox_desc.color_states[0].format = self.format.into();
-            new_realtime_skybox_desc.color_states[0].format = self.format.into();
-            let hash = new_skybox_desc.create_hash();
-            let realtime_hash = new_realtime_skybox_desc.create_hash();
-            pipeline_manager.add_pipeline(
-                "skybox",
-                &new_skybox_desc,
-                vec![],
-                &device,
-                &asset_manager,
-                resource_manager.clone(),
-            );
-            pipeline_manager.add_pipeline(
-                "realtime_skybox",
-                &new_realtime_skybox_desc,
-                vec![],
-                &device,
-                &asset_manager,
-                resource_manager.clone(),
-            );
-            pipeline_manager.set_current_pipeline_hash("skybox", hash);
-            pipeline_manager.set_current_pipeline_hash("realtime_skybox", realtime_hash);
+            let mut pipelines = vec!["skybox", "realtime_skybox"];
+            if include_meshes {
+                pipelines.extend(&[
+                    "pbr",
+                    "pbr_transparent",
+                    "pbr_push_constants",
+                    "pbr_transparent_push_constants",
+                ]);
+            }
+            for name in pipelines {
+                let mut new_desc = match pipeline_manager.get(name, None) {
+                    Some(pipeline) => pipeline.desc.clone(),
+                    None => continue,
+                };
+                new_desc.color_states[0].format = self.format.into();
+                // Probes render into a single sampled cube map.
+                new_desc.sample_count = 1;
+                let hash = new_desc.create_hash();
+                pipeline_manager
+                    .add_pipeline(
+                        name,
+                        &new_desc,
+                        vec![],
+                        &device,
+                        &asset_manager,
+                        resource_manager.clone(),
+                    )
+                    .unwrap();
+                pipeline_manager.set_current_pipeline_hash(name, hash);
+            }
         }
 
         {
@@ -423,15 +480,12 @@ impl Probe {
             .get_bind_group_layout("irradiance")
             .unwrap();
 
-        let output = RenderTarget::new(
-            &device,
-            self.irradiance_resoultion as f32,
-            self.irradiance_resoultion as f32 * 6.0,
-            1,
-            1,
+        let output = resources.get::<RenderTargetPool>().unwrap().acquire(RenderTargetDesc::new(
+            self.irradiance_resoultion,
+            self.irradiance_resoultion * 6,
             self.format.into(),
             wgpu::TextureUsage::COPY_SRC | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-        );
+        ));
 
         let uniform = ProbeUniform {
             data: Vec4::new(
@@ -544,15 +598,15 @@ impl Probe {
             .get_bind_group_layout("specular_globals")
             .unwrap();
 
-        let output = RenderTarget::new(
-            &device,
-            self.specular_resoultion as f32,
-            self.specular_resoultion as f32 * 6.0,
-            1,
-            mip_levels,
-            self.format.into(),
-            wgpu::TextureUsage::COPY_SRC | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
-        );
+        let output = resources.get::<RenderTargetPool>().unwrap().acquire(RenderTargetDesc {
+            mip_count: mip_levels,
+            ..RenderTargetDesc::new(
+                self.specular_resoultion,
+                self.specular_resoultion * 6,
+                self.format.into(),
+                wgpu::TextureUsage::COPY_SRC | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            )
+        });
         let buffer_size = std::mem::size_of::<ProbeUniform>() as u64;
 
         let buffer = resource_manager.get_buffer("specular");
@@ -1,19 +1,27 @@
 use legion::prelude::*;
 use nalgebra_glm::Vec3;
+use std::sync::Arc;
 
-use super::{Probe, ProbeFormat, ProbeQuality};
+use super::{BindGroup, GPUResourceManager, Probe, ProbeFormat, ProbeQuality};
 use crate::scene::components;
 
+/// The most `ReflectionProbe`s that are captured, further probes are ignored.
+pub const MAX_REFLECTION_PROBES: usize = 8;
+
 /// Keeps track of probes matches them up with entities for updates.
 /// TODO: Calculate probes based off of distance to camera. Prioritized baised off of distance.
 /// TODO: Some how stream probes in an out depending on distance. We likely shouldn't keep them in memory.
 pub struct ProbeManager {
     probes: Vec<Probe>,
+    reflection_probes: Vec<(Entity, Probe)>,
 }
 
 impl ProbeManager {
     pub fn new() -> Self {
-        Self { probes: Vec::new() }
+        Self {
+            probes: Vec::new(),
+            reflection_probes: Vec::new(),
+        }
     }
 
     pub fn create(
@@ -24,11 +32,18 @@ impl ProbeManager {
         format: ProbeFormat,
     ) -> u32 {
         let id = self.probes.len() as u32;
-        self.probes
-            .push(Probe::new(id, position, resources, quality, format));
+        let probe = Probe::new(id, position, resources, quality, format);
+        // The last probe created lights everything that isn't near a reflection probe.
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        resource_manager.add_shared_single_bind_group("probe_material", probe.bind_group());
+        self.probes.push(probe);
         id
     }
 
+    pub fn get(&self, id: u32) -> Option<&Probe> {
+        self.probes.get(id as usize)
+    }
+
     pub(crate) fn render(&mut self, resources: &mut Resources, scene: &mut crate::scene::Scene) {
         //TODO: Fix this as it's not very well optimized. Perhaps a oct tree would work better?
 
@@ -41,9 +56,147 @@ impl ProbeManager {
         for (probe_id, position) in probe_ids {
             let probe = &mut self.probes[probe_id as usize];
             probe.position = position;
-            probe.render_scene(resources, scene);
+            probe.render_scene(resources, scene, false);
             probe.render_brdf(resources, scene);
             probe.has_rendered = true;
         }
     }
+
+    /// Captures the scene into every dirty `ReflectionProbe` and convolves it for the pbr shader,
+    /// then updates the `ReflectionProbes` resource the mesh system picks probes from.
+    pub(crate) fn capture_probes(
+        &mut self,
+        resources: &mut Resources,
+        scene: &mut crate::scene::Scene,
+    ) {
+        let query = <(Write<components::ReflectionProbe>,)>::query();
+        let mut entities = Vec::new();
+        let mut captures = Vec::new();
+        for (entity, (mut reflection_probe,)) in query.iter_entities_mut(&mut scene.world) {
+            if entities.len() == MAX_REFLECTION_PROBES {
+                if reflection_probe.dirty {
+                    log::warn!(
+                        "Only {} reflection probes are supported, ignoring {:?}.",
+                        MAX_REFLECTION_PROBES,
+                        entity
+                    );
+                    reflection_probe.dirty = false;
+                }
+                continue;
+            }
+            entities.push((entity, reflection_probe.position, reflection_probe.radius));
+            if reflection_probe.dirty {
+                captures.push((entity, reflection_probe.clone()));
+                reflection_probe.dirty = false;
+            }
+        }
+
+        // Drop the probes of removed entities.
+        self.reflection_probes
+            .retain(|(entity, _)| entities.iter().any(|(e, _, _)| e == entity));
+
+        // Captures are lit by the global probe so they don't sample a probe that hasn't rendered yet.
+        if !captures.is_empty() {
+            resources.get_mut::<ReflectionProbes>().unwrap().0.clear();
+        }
+
+        for (entity, reflection_probe) in captures {
+            let quality = ProbeQuality::from_resolution(reflection_probe.resolution);
+            let index = self
+                .reflection_probes
+                .iter()
+                .position(|(e, probe)| *e == entity && probe.quality == quality);
+            let index = match index {
+                Some(index) => index,
+                None => {
+                    self.reflection_probes.retain(|(e, _)| *e != entity);
+                    let id = self.reflection_probes.len() as u32;
+                    let probe = Probe::new(
+                        id,
+                        reflection_probe.position,
+                        resources,
+                        quality,
+                        ProbeFormat::RGBA16,
+                    );
+                    self.reflection_probes.push((entity, probe));
+                    self.reflection_probes.len() - 1
+                }
+            };
+
+            let probe = &mut self.reflection_probes[index].1;
+            probe.position = reflection_probe.position;
+            probe.has_rendered = false;
+            probe.render_scene(resources, scene, true);
+            probe.render_brdf(resources, scene);
+            probe.has_rendered = true;
+        }
+
+        let mut probes = resources.get_mut::<ReflectionProbes>().unwrap();
+        probes.0 = entities
+            .into_iter()
+            .filter_map(|(entity, position, radius)| {
+                self.reflection_probes
+                    .iter()
+                    .find(|(e, _)| *e == entity)
+                    .map(|(_, probe)| (position, radius, probe.bind_group()))
+            })
+            .collect();
+    }
+}
+
+/// The captured `ReflectionProbe`s as (position, radius, bind group).
+#[derive(Default)]
+pub struct ReflectionProbes(pub(crate) Vec<(Vec3, f32, Arc<BindGroup>)>);
+
+impl ReflectionProbes {
+    /// The bind group of the closest probe `position` is inside of.
+    pub(crate) fn get(&self, position: &Vec3) -> Option<Arc<BindGroup>> {
+        closest_probe(self.0.iter().map(|(p, radius, _)| (*p, *radius)), position)
+            .map(|index| self.0[index].2.clone())
+    }
+}
+
+/// The index of the closest probe whose radius contains `position`.
+fn closest_probe(probes: impl Iterator<Item = (Vec3, f32)>, position: &Vec3) -> Option<usize> {
+    probes
+        .enumerate()
+        .map(|(index, (probe_position, radius))| {
+            (
+                index,
+                nalgebra_glm::distance(&probe_position, position),
+                radius,
+            )
+        })
+        .filter(|(_, distance, radius)| distance <= radius)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(index, _, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::closest_probe;
+    use nalgebra_glm::Vec3;
+
+    #[test]
+    fn should_pick_closest_probe_in_range() {
+        let probes = vec![
+            (Vec3::new(0.0, 0.0, 0.0), 10.0),
+            (Vec3::new(4.0, 0.0, 0.0), 2.0),
+            (Vec3::new(3.0, 0.0, 0.0), 0.1),
+        ];
+
+        // The third probe is closer but too small.
+        let position = Vec3::new(3.3, 0.0, 0.0);
+        assert_eq!(
+            closest_probe(probes.clone().into_iter(), &position),
+            Some(1)
+        );
+        let position = Vec3::new(-5.0, 0.0, 0.0);
+        assert_eq!(
+            closest_probe(probes.clone().into_iter(), &position),
+            Some(0)
+        );
+        let position = Vec3::new(20.0, 0.0, 0.0);
+        assert_eq!(closest_probe(probes.into_iter(), &position), None);
+    }
 }
@@ -0,0 +1,117 @@
+use std::ops::Deref;
+
+/// A buffer the GPU copies into so the results of compute shaders and other GPU work can be read on the CPU.
+/// Create one with `GPUResourceManager::create_readback_buffer`.
+pub struct ReadbackBuffer {
+    buffer: wgpu::Buffer,
+    size: u64,
+}
+
+impl ReadbackBuffer {
+    pub(crate) fn new(device: &wgpu::Device, name: &str, size: u64) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(name),
+            size,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self { buffer, size }
+    }
+
+    /// The size of the buffer in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Copies the start of `src` into the readback buffer once the encoder is submitted.
+    /// `src` needs `wgpu::BufferUsage::COPY_SRC` and must be at least as large as the readback buffer.
+    pub fn request_read(&self, encoder: &mut wgpu::CommandEncoder, src: &wgpu::Buffer) {
+        encoder.copy_buffer_to_buffer(src, 0, &self.buffer, 0, self.size);
+    }
+
+    /// Waits on the GPU until the buffer is mapped, call this after the copy was submitted.
+    /// The buffer is unmapped again when the guard is dropped.
+    pub fn map_blocking(&self, device: &wgpu::Device) -> ReadbackGuard<'_> {
+        let slice = self.buffer.slice(..);
+        let map_future = slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(map_future).expect("Failed to map readback buffer.");
+
+        ReadbackGuard {
+            buffer: &self.buffer,
+            view: Some(slice.get_mapped_range()),
+        }
+    }
+}
+
+/// The mapped contents of a `ReadbackBuffer`.
+pub struct ReadbackGuard<'a> {
+    buffer: &'a wgpu::Buffer,
+    // Only None while dropping, the view has to be gone before the buffer is unmapped.
+    view: Option<wgpu::BufferView<'a>>,
+}
+
+impl<'a> Deref for ReadbackGuard<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.view.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for ReadbackGuard<'a> {
+    fn drop(&mut self) {
+        self.view.take();
+        self.buffer.unmap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadbackBuffer;
+
+    #[test]
+    fn should_read_buffer_back() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+
+            adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap()
+        });
+
+        let data: Vec<u32> = (0..16).collect();
+        let src = device.create_buffer_with_data(
+            bytemuck::cast_slice(&data),
+            wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_SRC,
+        );
+        let readback = ReadbackBuffer::new(&device, "readback", 16 * 4);
+
+        // Read twice to check the buffer is unmapped again.
+        for _ in 0..2 {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            readback.request_read(&mut encoder, &src);
+            queue.submit(Some(encoder.finish()));
+
+            let guard = readback.map_blocking(&device);
+            let result: &[u32] = bytemuck::cast_slice(&guard);
+            assert_eq!(result, &data[..]);
+        }
+    }
+}
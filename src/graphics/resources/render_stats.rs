@@ -0,0 +1,83 @@
+use imgui::{im_str, Condition};
+use std::time::Instant;
+
+/// Counters describing the last rendered frame.
+/// Reset by the `begin_frame` system and finished once the frame's command buffers are submitted.
+#[derive(Debug, Clone, Default)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub triangle_count: u64,
+    /// Meshes that passed frustum culling. Written by the culling system so they aren't reset each frame.
+    pub visible_entities: u32,
+    /// Meshes skipped by frustum culling.
+    pub culled_entities: u32,
+    /// Command encoders created through the `CommandEncoderPool`.
+    pub command_encoders: u32,
+    /// CPU time spent recording and submitting the frame.
+    pub frame_time_ms: f32,
+    frame_start: Option<Instant>,
+}
+
+impl RenderStats {
+    /// Clears the per frame counters and starts timing a new frame.
+    pub fn reset(&mut self) {
+        self.draw_calls = 0;
+        self.triangle_count = 0;
+        self.frame_start = Some(Instant::now());
+    }
+
+    /// Counts an indexed triangle list draw of `index_count` indices.
+    pub fn record_draw(&mut self, index_count: u32) {
+        self.draw_calls += 1;
+        self.triangle_count += index_count as u64 / 3;
+    }
+
+    pub(crate) fn end_frame(&mut self) {
+        if let Some(frame_start) = self.frame_start.take() {
+            self.frame_time_ms = frame_start.elapsed().as_secs_f32() * 1000.0;
+        }
+    }
+
+    pub(crate) fn display(&self, ui: &imgui::Ui<'_>) {
+        let window = imgui::Window::new(im_str!("Render Stats"));
+        window
+            .resizable(false)
+            .size([300.0, 130.0], Condition::Always)
+            .position([0.0, 150.0], Condition::Always)
+            .build(&ui, || {
+                ui.text(im_str!("draw calls: {}", self.draw_calls));
+                ui.text(im_str!("triangles: {}", self.triangle_count));
+                ui.text(im_str!(
+                    "entities: {} visible, {} culled",
+                    self.visible_entities,
+                    self.culled_entities
+                ));
+                ui.text(im_str!("command encoders: {}", self.command_encoders));
+                ui.text(im_str!("frame time: {:.2}ms", self.frame_time_ms));
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenderStats;
+
+    #[test]
+    fn should_count_draws() {
+        let mut stats = RenderStats::default();
+        stats.visible_entities = 4;
+        stats.reset();
+        stats.record_draw(36);
+        stats.record_draw(6);
+        assert_eq!(stats.draw_calls, 2);
+        assert_eq!(stats.triangle_count, 14);
+
+        stats.end_frame();
+        assert!(stats.frame_time_ms >= 0.0);
+
+        stats.reset();
+        assert_eq!(stats.draw_calls, 0);
+        assert_eq!(stats.triangle_count, 0);
+        assert_eq!(stats.visible_entities, 4);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::graphics::renderer::DEPTH_FORMAT;
+use crate::graphics::renderer::{DEPTH_FORMAT, HDR_FORMAT};
+use std::sync::Arc;
 
 /// Used for rendering to a texture instead of to the frame buffer.
 /// Supports 2D and 3D textures or cube maps.
@@ -73,6 +74,39 @@ impl RenderTarget {
         }
     }
 
+    /// Creates a multisampled render target. These can't be sampled and are meant to be resolved into another texture.
+    pub fn new_multisampled(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            label: None,
+        });
+        let texture_view = texture.create_default_view();
+        Self {
+            texture,
+            texture_view,
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor::default()),
+            depth_texture: None,
+            depth_texture_view: None,
+            width,
+            height,
+        }
+    }
+
     pub fn with_depth(&mut self, device: &wgpu::Device) {
         self.depth_texture = Some(device.create_texture(&wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
@@ -95,3 +129,51 @@ impl RenderTarget {
         (self.texture, self.texture_view, self.sampler)
     }
 }
+
+/// A shared 2D render target a `Camera` can render into instead of the screen.
+/// The color texture uses `HDR_FORMAT` and isn't tone mapped, sample it like any other hdr texture.
+#[derive(Clone)]
+pub struct RenderTargetHandle(pub(crate) Arc<RenderTarget>);
+
+impl RenderTargetHandle {
+    /// Creates a color texture with a matching depth texture.
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let mut render_target = RenderTarget::new(
+            device,
+            width as f32,
+            height as f32,
+            1,
+            1,
+            HDR_FORMAT,
+            wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        );
+        render_target.with_depth(device);
+        Self(Arc::new(render_target))
+    }
+
+    /// The view of the color texture, used to bind it as a texture in another pass.
+    pub fn as_texture_view(&self) -> &wgpu::TextureView {
+        &self.0.texture_view
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.0.sampler
+    }
+
+    pub fn width(&self) -> u32 {
+        self.0.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.0.height
+    }
+}
+
+impl std::fmt::Debug for RenderTargetHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderTargetHandle")
+            .field("width", &self.0.width)
+            .field("height", &self.0.height)
+            .finish()
+    }
+}
@@ -0,0 +1,174 @@
+use super::RenderTarget;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+/// Describes a pooled render target. Targets are only reused for an identical description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderTargetDesc {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    pub sample_count: u32,
+    pub mip_count: u32,
+    pub usage: wgpu::TextureUsage,
+}
+
+impl RenderTargetDesc {
+    pub fn new(width: u32, height: u32, format: wgpu::TextureFormat, usage: wgpu::TextureUsage) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            sample_count: 1,
+            mip_count: 1,
+            usage,
+        }
+    }
+}
+
+/// How often `acquire` was able to reuse a render target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderTargetPoolStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+// Unused values grouped by key, holding at most `max_per_key` values per key.
+struct FreeList<K, V> {
+    free: HashMap<K, Vec<V>>,
+    max_per_key: usize,
+    hits: usize,
+    misses: usize,
+}
+
+impl<K: Eq + Hash, V> FreeList<K, V> {
+    fn new(max_per_key: usize) -> Self {
+        Self {
+            free: HashMap::new(),
+            max_per_key,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn take(&mut self, key: &K) -> Option<V> {
+        let value = self.free.get_mut(key).and_then(|values| values.pop());
+        if value.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        value
+    }
+
+    // Drops the value instead if the key is already full.
+    fn give_back(&mut self, key: K, value: V) {
+        let values = self.free.entry(key).or_insert_with(Vec::new);
+        if values.len() < self.max_per_key {
+            values.push(value);
+        }
+    }
+}
+
+/// Keeps render targets that are only needed for part of a frame around so they can be reused
+/// instead of being created every frame.
+pub struct RenderTargetPool {
+    device: Arc<wgpu::Device>,
+    free_list: Arc<Mutex<FreeList<RenderTargetDesc, RenderTarget>>>,
+}
+
+impl RenderTargetPool {
+    pub fn new(device: Arc<wgpu::Device>, max_per_key: usize) -> Self {
+        Self {
+            device,
+            free_list: Arc::new(Mutex::new(FreeList::new(max_per_key))),
+        }
+    }
+
+    /// Returns an unused render target matching `desc` or creates a new one.
+    /// The render target goes back into the pool when the returned guard is dropped.
+    pub fn acquire(&self, desc: RenderTargetDesc) -> PooledRenderTarget {
+        let pooled = self.free_list.lock().unwrap().take(&desc);
+        let target = pooled.unwrap_or_else(|| {
+            if desc.sample_count > 1 {
+                RenderTarget::new_multisampled(
+                    &self.device,
+                    desc.width,
+                    desc.height,
+                    desc.format,
+                    desc.sample_count,
+                )
+            } else {
+                RenderTarget::new(
+                    &self.device,
+                    desc.width as f32,
+                    desc.height as f32,
+                    1,
+                    desc.mip_count,
+                    desc.format,
+                    desc.usage,
+                )
+            }
+        });
+
+        PooledRenderTarget {
+            desc,
+            target: Some(target),
+            free_list: self.free_list.clone(),
+        }
+    }
+
+    pub fn stats(&self) -> RenderTargetPoolStats {
+        let free_list = self.free_list.lock().unwrap();
+        RenderTargetPoolStats {
+            hits: free_list.hits,
+            misses: free_list.misses,
+        }
+    }
+}
+
+/// A render target borrowed from a `RenderTargetPool`.
+pub struct PooledRenderTarget {
+    desc: RenderTargetDesc,
+    target: Option<RenderTarget>,
+    free_list: Arc<Mutex<FreeList<RenderTargetDesc, RenderTarget>>>,
+}
+
+impl Deref for PooledRenderTarget {
+    type Target = RenderTarget;
+
+    fn deref(&self) -> &RenderTarget {
+        self.target.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledRenderTarget {
+    fn drop(&mut self) {
+        if let Some(target) = self.target.take() {
+            self.free_list.lock().unwrap().give_back(self.desc, target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FreeList;
+
+    #[test]
+    fn free_list_reuses_and_caps_values() {
+        let mut free_list = FreeList::new(1);
+        assert_eq!(free_list.take(&"a"), None);
+
+        free_list.give_back("a", 1);
+        free_list.give_back("a", 2);
+        assert_eq!(free_list.take(&"a"), Some(1));
+        assert_eq!(free_list.take(&"a"), None);
+        assert_eq!(free_list.take(&"b"), None);
+
+        assert_eq!((free_list.hits, free_list.misses), (1, 3));
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+/// Uniform buffer bindings have to start at a multiple of this.
+pub const SLAB_ALIGNMENT: u64 = 256;
+
+/// A single large buffer split into `capacity` fixed size elements.
+/// Used instead of creating a buffer per item so thousands of items only need one GPU allocation.
+/// Each element starts at a multiple of `SLAB_ALIGNMENT` so it can be bound as a uniform buffer.
+#[derive(Debug, Clone)]
+pub struct SlabHandle {
+    buffer: Arc<wgpu::Buffer>,
+    element_size: u64,
+    stride: u64,
+    capacity: u32,
+    usage: wgpu::BufferUsage,
+}
+
+impl SlabHandle {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        label: &str,
+        element_size: u64,
+        capacity: u32,
+        usage: wgpu::BufferUsage,
+    ) -> Self {
+        let stride = Self::aligned_stride(element_size);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            usage,
+            size: stride * capacity as u64,
+            mapped_at_creation: false,
+            label: Some(label),
+        });
+
+        Self {
+            buffer: Arc::new(buffer),
+            element_size,
+            stride,
+            capacity,
+            usage,
+        }
+    }
+
+    /// Creates a slab with room for `new_capacity` elements and records a copy of every element into it.
+    /// The slab needs `COPY_SRC` usage, the new slab is created with the same usage.
+    pub(crate) fn grow(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        new_capacity: u32,
+        encoder: &mut wgpu::CommandEncoder,
+    ) -> Self {
+        assert!(
+            new_capacity >= self.capacity,
+            "Slabs can only grow, the capacity is {} but {} was requested.",
+            self.capacity,
+            new_capacity
+        );
+        let slab = Self::new(device, label, self.element_size, new_capacity, self.usage);
+        encoder.copy_buffer_to_buffer(
+            &self.buffer,
+            0,
+            &slab.buffer,
+            0,
+            self.stride * self.capacity as u64,
+        );
+        slab
+    }
+
+    fn aligned_stride(element_size: u64) -> u64 {
+        (element_size + SLAB_ALIGNMENT - 1) / SLAB_ALIGNMENT * SLAB_ALIGNMENT
+    }
+
+    /// The buffer every element lives in.
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub(crate) fn shared_buffer(&self) -> Arc<wgpu::Buffer> {
+        self.buffer.clone()
+    }
+
+    /// The size of each element in bytes before alignment.
+    pub fn element_size(&self) -> u64 {
+        self.element_size
+    }
+
+    /// How many elements the slab can hold.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Where the element at `index` starts inside of the buffer.
+    pub fn offset(&self, index: u32) -> wgpu::BufferAddress {
+        assert!(
+            index < self.capacity,
+            "Slab index {} is out of range, the capacity is {}.",
+            index,
+            self.capacity
+        );
+        index as u64 * self.stride
+    }
+
+    /// The part of the buffer that holds the element at `index`.
+    pub fn slice(&self, index: u32) -> wgpu::BufferSlice<'_> {
+        let offset = self.offset(index);
+        self.buffer.slice(offset..offset + self.element_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SlabHandle, SLAB_ALIGNMENT};
+
+    #[test]
+    fn should_align_stride() {
+        assert_eq!(SlabHandle::aligned_stride(1), SLAB_ALIGNMENT);
+        assert_eq!(SlabHandle::aligned_stride(64), SLAB_ALIGNMENT);
+        assert_eq!(SlabHandle::aligned_stride(256), 256);
+        assert_eq!(SlabHandle::aligned_stride(257), 512);
+    }
+}
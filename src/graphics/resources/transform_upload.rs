@@ -0,0 +1,363 @@
+use super::ArcRenderPass;
+use crate::scene::components::transform::LocalUniform;
+
+/// Scenes drawing fewer meshes than this push each transform instead of binding it's buffer.
+pub const PUSH_CONSTANT_ENTITY_LIMIT: usize = 32;
+
+/// How the `render_mesh` system hands each mesh's `LocalUniform` to the pbr pipelines.
+/// Picked once when the renderer starts and inserted as a resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformUploadStrategy {
+    /// Binds the transform's uniform buffer at set 0 before every draw.
+    Buffer,
+    /// Pushes the matrix with `PushConstantTransformStrategy` while fewer than `PUSH_CONSTANT_ENTITY_LIMIT` meshes are drawn.
+    PushConstants,
+}
+
+impl TransformUploadStrategy {
+    /// Uses push constants when the device has them and they can hold a `LocalUniform`.
+    pub fn select(features: wgpu::Features, limits: &wgpu::Limits) -> Self {
+        if features.contains(wgpu::Features::PUSH_CONSTANTS)
+            && limits.max_push_constant_size as usize >= std::mem::size_of::<LocalUniform>()
+        {
+            TransformUploadStrategy::PushConstants
+        } else {
+            TransformUploadStrategy::Buffer
+        }
+    }
+
+    /// Whether a frame drawing `mesh_count` meshes pushes it's transforms.
+    pub fn use_push_constants(&self, mesh_count: usize) -> bool {
+        *self == TransformUploadStrategy::PushConstants && mesh_count < PUSH_CONSTANT_ENTITY_LIMIT
+    }
+}
+
+/// Uploads transforms straight into the render pass, used by the "pbr_push_constants" pipelines
+/// whose shaders read `world` from push constants instead of a uniform buffer.
+pub struct PushConstantTransformStrategy;
+
+impl PushConstantTransformStrategy {
+    pub const STAGES: wgpu::ShaderStage = wgpu::ShaderStage::VERTEX;
+
+    /// The range to add to the pipeline's `push_constant_ranges`.
+    pub fn range() -> wgpu::PushConstantRange {
+        wgpu::PushConstantRange {
+            stages: Self::STAGES,
+            range: 0..std::mem::size_of::<LocalUniform>() as u32,
+        }
+    }
+
+    /// The words to push for a transform.
+    pub fn data(uniform: &LocalUniform) -> &[u32] {
+        bytemuck::cast_slice(bytemuck::bytes_of(uniform))
+    }
+
+    pub fn set_transform(render_pass: &mut ArcRenderPass<'_>, uniform: &LocalUniform) {
+        render_pass.set_push_constants(Self::STAGES, 0, Self::data(uniform));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PushConstantTransformStrategy, TransformUploadStrategy};
+    use crate::scene::components::transform::LocalUniform;
+    use nalgebra_glm::{Mat4, Vec3};
+    use std::borrow::Cow;
+
+    const SIZE: u32 = 8;
+    // Rows of a texture copy have to be aligned to 256 bytes.
+    const BYTES_PER_ROW: u32 = 256;
+
+    const VERTEX_SHADER: &str = r#"
+        #version 450
+        #if PUSH_CONSTANT_TRANSFORM
+        layout(push_constant) uniform Locals {
+            mat4 world;
+        };
+        #else
+        layout(set = 0, binding = 0) uniform Locals {
+            mat4 world;
+        };
+        #endif
+
+        const vec2 positions[3] = vec2[3](vec2(-0.5, -0.5), vec2(0.5, -0.5), vec2(-0.5, 0.5));
+
+        void main() {
+            gl_Position = world * vec4(positions[gl_VertexIndex], 0.0, 1.0);
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &str = r#"
+        #version 450
+        layout(location = 0) out vec4 outColor;
+
+        void main() {
+            outColor = vec4(1.0, 0.0, 1.0, 1.0);
+        }
+    "#;
+
+    fn compile(
+        device: &wgpu::Device,
+        source: &str,
+        kind: shaderc::ShaderKind,
+        push_constants: bool,
+    ) -> wgpu::ShaderModule {
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let mut options = shaderc::CompileOptions::new().unwrap();
+        options.add_macro_definition(
+            "PUSH_CONSTANT_TRANSFORM",
+            Some(&(push_constants as u32).to_string()),
+        );
+        let spirv = compiler
+            .compile_into_spirv(source, kind, "transform.glsl", "main", Some(&options))
+            .unwrap();
+        device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Borrowed(
+            spirv.as_binary(),
+        )))
+    }
+
+    // Draws a triangle moved by the world matrix and returns the rendered rows.
+    fn render(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        strategy: TransformUploadStrategy,
+    ) -> Vec<u8> {
+        let push_constants = strategy == TransformUploadStrategy::PushConstants;
+        let uniform = LocalUniform {
+            world: nalgebra_glm::translate(&Mat4::identity(), &Vec3::new(0.25, 0.25, 0.0)),
+        };
+
+        // The push constant pipeline keeps an empty set 0 like the pbr pipelines do.
+        let entries = if push_constants {
+            Vec::new()
+        } else {
+            vec![wgpu::BindGroupLayoutEntry::new(
+                0,
+                wgpu::ShaderStage::VERTEX,
+                wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: None,
+                },
+            )]
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: Cow::Borrowed(&entries),
+            label: None,
+        });
+        let uniform_buffer = device
+            .create_buffer_with_data(bytemuck::bytes_of(&uniform), wgpu::BufferUsage::UNIFORM);
+        let bind_group_entries = if push_constants {
+            Vec::new()
+        } else {
+            vec![wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(uniform_buffer.slice(..)),
+            }]
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: Cow::Borrowed(&bind_group_entries),
+        });
+
+        let push_constant_ranges = if push_constants {
+            vec![PushConstantTransformStrategy::range()]
+        } else {
+            Vec::new()
+        };
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: Cow::Borrowed(&[&layout]),
+            push_constant_ranges: Cow::Owned(push_constant_ranges),
+        });
+        let vertex = compile(
+            device,
+            VERTEX_SHADER,
+            shaderc::ShaderKind::Vertex,
+            push_constants,
+        );
+        let fragment = compile(
+            device,
+            FRAGMENT_SHADER,
+            shaderc::ShaderKind::Fragment,
+            push_constants,
+        );
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: &pipeline_layout,
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vertex,
+                entry_point: Cow::Borrowed("main"),
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fragment,
+                entry_point: Cow::Borrowed("main"),
+            }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: wgpu::CullMode::None,
+                ..Default::default()
+            }),
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            color_states: Cow::Borrowed(&[wgpu::ColorStateDescriptor {
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                color_blend: wgpu::BlendDescriptor::REPLACE,
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }]),
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint32,
+                vertex_buffers: Cow::Borrowed(&[]),
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (BYTES_PER_ROW * SIZE) as u64,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }]),
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(&pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            if push_constants {
+                render_pass.set_push_constants(
+                    PushConstantTransformStrategy::STAGES,
+                    0,
+                    PushConstantTransformStrategy::data(&uniform),
+                );
+            }
+            render_pass.draw(0..3, 0..1);
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &readback_buffer,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: BYTES_PER_ROW,
+                    rows_per_image: SIZE,
+                },
+            },
+            wgpu::Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        let map_future = slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(map_future).unwrap();
+        let data = slice.get_mapped_range();
+        data.chunks(BYTES_PER_ROW as usize)
+            .flat_map(|row| row[..(SIZE * 4) as usize].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn should_select_push_constants_when_supported() {
+        let limits = wgpu::Limits {
+            max_push_constant_size: 128,
+            ..wgpu::Limits::default()
+        };
+        assert_eq!(
+            TransformUploadStrategy::select(wgpu::Features::PUSH_CONSTANTS, &limits),
+            TransformUploadStrategy::PushConstants
+        );
+        assert_eq!(
+            TransformUploadStrategy::select(wgpu::Features::empty(), &limits),
+            TransformUploadStrategy::Buffer
+        );
+        assert_eq!(
+            TransformUploadStrategy::select(
+                wgpu::Features::PUSH_CONSTANTS,
+                &wgpu::Limits::default()
+            ),
+            TransformUploadStrategy::Buffer
+        );
+
+        assert!(TransformUploadStrategy::PushConstants.use_push_constants(31));
+        assert!(!TransformUploadStrategy::PushConstants.use_push_constants(32));
+        assert!(!TransformUploadStrategy::Buffer.use_push_constants(1));
+    }
+
+    #[test]
+    fn should_render_same_pixels_with_both_strategies() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: adapter.features() & wgpu::Features::PUSH_CONSTANTS,
+                        limits: wgpu::Limits {
+                            max_push_constant_size: 128,
+                            ..wgpu::Limits::default()
+                        },
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+            (device, queue)
+        });
+        if TransformUploadStrategy::select(device.features(), &device.limits())
+            == TransformUploadStrategy::Buffer
+        {
+            // Nothing to compare against without push constants.
+            return;
+        }
+
+        let buffer = render(&device, &queue, TransformUploadStrategy::Buffer);
+        let push_constants = render(&device, &queue, TransformUploadStrategy::PushConstants);
+        assert_eq!(buffer, push_constants);
+        // The triangle covers some pixels but not all of them.
+        assert!(buffer.chunks(4).any(|pixel| pixel == [255, 0, 255, 255]));
+        assert!(buffer.chunks(4).any(|pixel| pixel == [0, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,390 @@
+use super::{omni_manager::ShadowPush, ShadowQuality};
+use crate::{
+    core::Frustum,
+    graphics::{
+        pipeline_manager::PipelineManager,
+        resources::{ArcRenderPass, GPUResourceManager},
+    },
+    scene::components::{self, camera_data::ProjectionData, CameraData},
+};
+use bytemuck::{Pod, Zeroable};
+use legion::{
+    filter::{And, ComponentFilter, EntityFilterTuple, Passthrough},
+    prelude::*,
+    systems::{SubWorld, SystemQuery},
+};
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use std::{borrow::Cow, sync::Arc};
+
+/// The most cascades a directional light can be split into, the shadow texture always has this many layers.
+pub const MAX_CASCADES: usize = 4;
+
+/// Controls how the camera frustum is split for the directional light's cascaded shadow maps.
+#[derive(Debug, Clone)]
+pub struct CsmConfig {
+    /// How many shadow maps the camera frustum is split into, clamped to `MAX_CASCADES`.
+    pub num_cascades: usize,
+    /// Blends between uniform (0.0) and logarithmic (1.0) split distances.
+    pub lambda: f32,
+    /// Shadows end at this distance from the camera, or at the camera's far plane if it's closer.
+    pub max_distance: f32,
+}
+
+impl Default for CsmConfig {
+    fn default() -> Self {
+        Self {
+            num_cascades: MAX_CASCADES,
+            lambda: 0.75,
+            max_distance: 100.0,
+        }
+    }
+}
+
+impl CsmConfig {
+    /// The view space depth where each cascade ends, using the practical split scheme.
+    pub fn cascade_splits(&self, near: f32, far: f32) -> Vec<f32> {
+        // The logarithmic split is undefined for a near plane at 0.
+        let near = near.max(0.001);
+        let far = far.min(self.max_distance).max(near);
+        let count = self.num_cascades.min(MAX_CASCADES).max(1);
+        (1..=count)
+            .map(|i| {
+                let fraction = i as f32 / count as f32;
+                let log = near * (far / near).powf(fraction);
+                let uniform = near + (far - near) * fraction;
+                self.lambda * log + (1.0 - self.lambda) * uniform
+            })
+            .collect()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CascadeUniform {
+    matrices: [Mat4; MAX_CASCADES],
+    splits: Vec4,
+    // x is the number of cascades, 0 if no directional light casts shadows.
+    info: Vec4,
+}
+
+unsafe impl Zeroable for CascadeUniform {}
+unsafe impl Pod for CascadeUniform {}
+
+impl Default for CascadeUniform {
+    fn default() -> Self {
+        Self {
+            matrices: [Mat4::identity(); MAX_CASCADES],
+            splits: Vec4::zeros(),
+            info: Vec4::zeros(),
+        }
+    }
+}
+
+/// Renders the shadow casting directional light into one shadow map per cascade.
+/// The maps are layers of a single texture bound to the globals with the cascade matrices and splits.
+pub struct CascadeShadowManager {
+    _texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+    cascade_views: Vec<wgpu::TextureView>,
+    pub(crate) uniform_buffer: wgpu::Buffer,
+    uniform: CascadeUniform,
+    size: u32,
+}
+
+impl CascadeShadowManager {
+    pub fn new(device: Arc<wgpu::Device>, quality: ShadowQuality) -> Self {
+        let size = match quality {
+            ShadowQuality::Low => 1024,
+            ShadowQuality::Medium => 2048,
+            ShadowQuality::High => 4096,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("cascade_shadow_map"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth: MAX_CASCADES as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("cascade_shadow_map"),
+            format: wgpu::TextureFormat::Depth32Float,
+            dimension: wgpu::TextureViewDimension::D2Array,
+            aspect: wgpu::TextureAspect::All,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            array_layer_count: MAX_CASCADES as u32,
+        });
+
+        let cascade_views = (0..MAX_CASCADES as u32)
+            .map(|cascade| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some(&format!("cascade_shadow_map_{}", cascade)),
+                    format: wgpu::TextureFormat::Depth32Float,
+                    dimension: wgpu::TextureViewDimension::D2,
+                    aspect: wgpu::TextureAspect::All,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: cascade,
+                    array_layer_count: 1,
+                })
+            })
+            .collect();
+
+        let uniform = CascadeUniform::default();
+        let uniform_buffer = device.create_buffer_with_data(
+            bytemuck::bytes_of(&uniform),
+            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        );
+
+        Self {
+            _texture: texture,
+            view,
+            cascade_views,
+            uniform_buffer,
+            uniform,
+            size,
+        }
+    }
+
+    /// Fits a light matrix around each cascade of the camera's frustum and uploads them with the split depths.
+    /// Pass `None` as the direction when no directional light casts shadows, the shader then skips the lookup.
+    pub(crate) fn update(
+        &mut self,
+        config: &CsmConfig,
+        camera: &CameraData,
+        direction: Option<Vec3>,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        self.uniform = CascadeUniform::default();
+        if let Some(direction) = direction {
+            let (near, far) = match camera.projection_data() {
+                ProjectionData::Perspective { z_near, z_far, .. } => (*z_near, *z_far),
+                ProjectionData::Orthographic { z_near, z_far, .. } => (*z_near, *z_far),
+            };
+            let splits = config.cascade_splits(near, far);
+            let mut cascade_near = near;
+            for (i, split) in splits.iter().enumerate() {
+                let corners = cascade_corners(camera, cascade_near, *split);
+                self.uniform.matrices[i] =
+                    light_matrix(&corners, direction, config.max_distance, self.size);
+                self.uniform.splits[i] = *split;
+                cascade_near = *split;
+            }
+            self.uniform.info.x = splits.len() as f32;
+        }
+
+        let uniform_buffer = device.create_buffer_with_data(
+            bytemuck::bytes_of(&self.uniform),
+            wgpu::BufferUsage::COPY_SRC,
+        );
+        encoder.copy_buffer_to_buffer(
+            &uniform_buffer,
+            0,
+            &self.uniform_buffer,
+            0,
+            std::mem::size_of::<CascadeUniform>() as u64,
+        );
+    }
+
+    /// Draws every mesh inside a cascade's light frustum into that cascade's layer.
+    pub(crate) fn render(
+        &self,
+        pipeline_manager: &PipelineManager,
+        resource_manager: Arc<GPUResourceManager>,
+        encoder: &mut wgpu::CommandEncoder,
+        mesh_query: &mut SystemQuery<
+            (Read<components::Mesh>, Read<components::Transform>),
+            EntityFilterTuple<
+                And<(
+                    ComponentFilter<components::Mesh>,
+                    ComponentFilter<components::Transform>,
+                )>,
+                And<(Passthrough, Passthrough)>,
+                And<(Passthrough, Passthrough)>,
+            >,
+        >,
+        world: &mut SubWorld,
+    ) {
+        let pipeline = pipeline_manager.get("shadow", None).unwrap();
+        let cascade_count = self.uniform.info.x as usize;
+
+        for cascade in 0..cascade_count {
+            let matrix = self.uniform.matrices[cascade];
+            // Meshes outside of the camera's view still cast shadows into it, so only the light's frustum is used.
+            let frustum = Frustum::from_matrix(matrix);
+            let meshes = mesh_query
+                .iter(world)
+                .filter(|(mesh, transform)| match mesh.mesh_handle.get() {
+                    Ok(gltf) => {
+                        let mut bounding_sphere = mesh.get_bounding_sphere(&gltf);
+                        let center = bounding_sphere.center;
+                        bounding_sphere.center =
+                            (transform.matrix * Vec4::new(center.x, center.y, center.z, 1.0)).xyz();
+                        frustum.contains_sphere(bounding_sphere)
+                    }
+                    Err(_) => false,
+                })
+                .map(|(mesh, transform)| {
+                    (
+                        mesh.mesh_handle.get().unwrap(),
+                        mesh.mesh_index,
+                        transform.index,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: Cow::Borrowed(&[]),
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                    attachment: &self.cascade_views[cascade],
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            let arena1 = typed_arena::Arena::new();
+            let arena2 = typed_arena::Arena::new();
+
+            let mut render_pass = ArcRenderPass::new(&arena1, &arena2, render_pass);
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_push_constants(
+                wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                0,
+                bytemuck::cast_slice(&[ShadowPush {
+                    matrix,
+                    light_pos: Vec4::zeros(),
+                }]),
+            );
+
+            for (gltf, mesh_index, transform_index) in meshes.iter() {
+                resource_manager.set_transform_bind_group(&mut render_pass, *transform_index);
+
+                let asset_meshes = match mesh_index {
+                    Some(index) => &gltf.meshes[*index..*index + 1],
+                    None => &gltf.meshes[..],
+                };
+
+                for mesh in asset_meshes.iter() {
+                    for (_, sub_mesh) in mesh.meshes.iter() {
+                        render_pass.set_index_buffer(sub_mesh.index_buffer.clone());
+                        render_pass
+                            .set_vertex_buffer(0, sub_mesh.vertex_buffer.as_ref().unwrap().clone());
+                        render_pass.draw_indexed(0..sub_mesh.index_count as u32, 0, 0..1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The world space corners of the camera frustum between two view space depths.
+fn cascade_corners(camera: &CameraData, near: f32, far: f32) -> [Vec3; 8] {
+    let aspect = camera.width / camera.height.max(1.0);
+    let half_height = |depth: f32| match camera.projection_data() {
+        ProjectionData::Perspective { fov, .. } => depth * (fov.to_radians() * 0.5).tan(),
+        ProjectionData::Orthographic { world_height, .. } => world_height * 0.5,
+    };
+    let inverse_view = camera.view.try_inverse().unwrap_or_else(Mat4::identity);
+
+    let mut corners = [Vec3::zeros(); 8];
+    for (i, depth) in [near, far].iter().enumerate() {
+        let y = half_height(*depth);
+        let x = y * aspect;
+        let view_corners = [
+            Vec3::new(-x, -y, *depth),
+            Vec3::new(x, -y, *depth),
+            Vec3::new(x, y, *depth),
+            Vec3::new(-x, y, *depth),
+        ];
+        for (j, corner) in view_corners.iter().enumerate() {
+            corners[i * 4 + j] =
+                (inverse_view * Vec4::new(corner.x, corner.y, corner.z, 1.0)).xyz();
+        }
+    }
+    corners
+}
+
+// An orthographic light matrix around the bounding sphere of the corners.
+// The light is pulled back by `caster_distance` so casters between it and the cascade are still drawn.
+fn light_matrix(corners: &[Vec3; 8], direction: Vec3, caster_distance: f32, size: u32) -> Mat4 {
+    let center = corners
+        .iter()
+        .fold(Vec3::zeros(), |sum, corner| sum + corner)
+        / 8.0;
+    let radius = corners.iter().fold(0.0, |radius: f32, corner| {
+        radius.max(nalgebra_glm::distance(corner, &center))
+    });
+    // Rounding the radius keeps the projection the same size as the camera rotates.
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let light_direction = direction.normalize();
+    let up = if light_direction.y.abs() > 0.99 {
+        Vec3::new(0.0, 0.0, 1.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+    let eye = center + light_direction * (radius + caster_distance);
+    let view = nalgebra_glm::look_at_lh(&eye, &center, &up);
+    let mut projection = nalgebra_glm::ortho_lh_zo(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        0.0,
+        radius * 2.0 + caster_distance,
+    );
+
+    // Snap the origin to a texel so the shadow edges don't shimmer as the camera moves.
+    let half_size = size as f32 * 0.5;
+    let origin = projection * view * Vec4::new(0.0, 0.0, 0.0, 1.0);
+    let offset_x = ((origin.x * half_size).round() - origin.x * half_size) / half_size;
+    let offset_y = ((origin.y * half_size).round() - origin.y * half_size) / half_size;
+    projection[(0, 3)] += offset_x;
+    projection[(1, 3)] += offset_y;
+
+    projection * view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CsmConfig, MAX_CASCADES};
+
+    #[test]
+    fn should_split_cascades() {
+        let config = CsmConfig {
+            num_cascades: 4,
+            lambda: 0.0,
+            max_distance: 100.0,
+        };
+        // Without the logarithmic term the splits are evenly spaced up to the max distance.
+        let splits = config.cascade_splits(1.0, 1000.0);
+        assert_eq!(splits, vec![25.75, 50.5, 75.25, 100.0]);
+
+        let config = CsmConfig {
+            lambda: 1.0,
+            ..config
+        };
+        let splits = config.cascade_splits(1.0, 16.0);
+        for (split, expected) in splits.iter().zip([2.0, 4.0, 8.0, 16.0].iter()) {
+            assert!((split - expected).abs() < 0.001);
+        }
+
+        let config = CsmConfig {
+            num_cascades: 8,
+            ..config
+        };
+        assert_eq!(config.cascade_splits(1.0, 16.0).len(), MAX_CASCADES);
+    }
+}
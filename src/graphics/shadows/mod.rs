@@ -1,2 +1,5 @@
+mod cascade_manager;
 mod omni_manager;
-pub use omni_manager::{OmniShadowManager, ShadowQuality, ShadowCamera};
\ No newline at end of file
+pub use cascade_manager::{CascadeShadowManager, CsmConfig, MAX_CASCADES};
+pub(crate) use cascade_manager::CascadeUniform;
+pub use omni_manager::{OmniShadowManager, ShadowQuality, ShadowCamera, MAX_SHADOW_CASTERS_PER_FRAME};
@@ -11,6 +11,11 @@ pub struct ShadowPush {
     pub light_pos: Vec4,
 }
 
+/// How many point lights have their cube shadow maps rendered each frame.
+/// Casters are ranked by `attenuation / distance² * (age + 1)`, where age counts the frames since
+/// their last update. The rest keep their last shadow map until they are picked again.
+pub const MAX_SHADOW_CASTERS_PER_FRAME: u32 = 4;
+
 unsafe impl Pod for ShadowPush { }
 unsafe impl Zeroable for ShadowPush { }
 
@@ -135,7 +140,7 @@ impl OmniShadowManager {
             quality,
             quad_textures,
             sampler,
-            max_casters_per_frame: 5,
+            max_casters_per_frame: MAX_SHADOW_CASTERS_PER_FRAME,
         }
     }
 
@@ -173,7 +178,8 @@ impl OmniShadowManager {
                 wgpu::vertex_attr_array![0 => Float3, 1 => Float3, 2 => Float2, 3 => Float4].to_vec(),
             );
 
-        pipeline_manager.add_pipeline("shadow", &pipeline_desc, vec![], &device, asset_manager, gpu_resource_manager);
+        // Skinned meshes are deformed by the skinning pass before they are drawn into the shadow maps.
+        pipeline_manager.add_pipeline("shadow", &pipeline_desc, vec!["skinning"], &device, asset_manager, gpu_resource_manager).unwrap();
     }
 
     pub fn update(&mut self,
@@ -215,16 +221,16 @@ impl OmniShadowManager {
                         return false;
                     }
                     
-                    let mesh = mesh_data.unwrap();
-                    let mut bounding_sphere = mesh.bounding_sphere.clone();
+                    let gltf = mesh_data.unwrap();
+                    let mut bounding_sphere = mesh.get_bounding_sphere(&gltf);
                     bounding_sphere.center = (transform.matrix * Vec4::new(bounding_sphere.center.x, bounding_sphere.center.y, bounding_sphere.center.z, 1.0)).xyz();
                     return bounding_sphere.intersects_sphere(&light_bounds);
                 })
                 .map(|(mesh, transform)| {
-                    let mesh = mesh.mesh_handle.get().unwrap();
+                    let gltf = mesh.mesh_handle.get().unwrap();
                     // Arc<Mesh> hard transform on clone.
                     // TODO: Figure out performance impacts of cloning here..
-                    (mesh.clone(), transform.clone())
+                    (gltf.clone(), mesh.mesh_index, transform.clone())
                 })
                 .collect::<Vec<_>>();
             
@@ -258,15 +264,15 @@ impl OmniShadowManager {
                 }]));
 
                 // Step 2: Render shadow maps to that space.
-                for (asset_mesh, transform) in meshes.iter() {
-                    resource_manager.set_multi_bind_group(
-                        &mut render_pass,
-                        "transform",
-                        0,
-                        transform.index,
-                    );
-
-                    for mesh in asset_mesh.meshes.iter() {
+                for (asset_mesh, mesh_index, transform) in meshes.iter() {
+                    resource_manager.set_transform_bind_group(&mut render_pass, transform.index);
+
+                    let asset_meshes = match mesh_index {
+                        Some(index) => &asset_mesh.meshes[*index..*index + 1],
+                        None => &asset_mesh.meshes[..],
+                    };
+
+                    for mesh in asset_meshes.iter() {
                         for (_, sub_mesh) in mesh.meshes.iter() {
                             render_pass
                                 .set_index_buffer(sub_mesh.index_buffer.clone());
@@ -0,0 +1,67 @@
+use crate::graphics::{
+    pipeline_manager::PipelineManager,
+    pipelines::bloom::{BloomConfig, BloomPass},
+    resources::HdrFramebuffer,
+    CommandBufferQueue, CommandQueueItem, RenderPriority,
+};
+use legion::prelude::*;
+use std::sync::Arc;
+
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_bloom")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<BloomPass>()
+        .read_resource::<BloomConfig>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<PipelineManager>()
+        .build(
+            |_,
+             _,
+             (
+                command_buffer_queue,
+                device,
+                queue,
+                bloom_pass,
+                bloom_config,
+                hdr_framebuffer,
+                pipeline_manager,
+            ),
+             _| {
+                // Nothing would be added to the framebuffer so skip the work.
+                if bloom_config.intensity <= 0.0 {
+                    return;
+                }
+
+                // Skip the pass until its pipelines have finished compiling, or while it's disabled.
+                let pipelines = ["bloom_threshold", "bloom_blur", "bloom_composite"];
+                if !pipelines.iter().all(|name| pipeline_manager.is_ready(name))
+                    || !pipeline_manager.is_enabled("bloom_composite")
+                {
+                    return;
+                }
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("bloom"),
+                });
+
+                bloom_pass.compute(
+                    &queue,
+                    &bloom_config,
+                    &hdr_framebuffer,
+                    &pipeline_manager,
+                    &mut encoder,
+                );
+                bloom_pass.composite(&bloom_config, &hdr_framebuffer, &pipeline_manager, &mut encoder);
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "bloom_composite".to_string(),
+                        priority: RenderPriority::POST_PROCESS,
+                    })
+                    .unwrap();
+            },
+        )
+}
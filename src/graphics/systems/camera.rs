@@ -0,0 +1,92 @@
+use legion::prelude::*;
+use std::sync::Arc;
+
+use crate::{
+    graphics::{
+        pipelines::taa::{TaaConfig, TaaJitter},
+        resources::{CurrentRenderTarget, GPUResourceManager, HdrFramebuffer},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::{components, resources::ActiveCamera},
+};
+
+/// Updates the active camera's view from it's transform and writes the camera matrices to the global uniforms.
+/// Also points `CurrentRenderTarget` at the camera's render target so the frame is drawn into it.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("update_camera")
+        .write_resource::<CommandBufferQueue>()
+        .write_resource::<CurrentRenderTarget>()
+        .write_resource::<TaaJitter>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<ActiveCamera>()
+        .read_resource::<TaaConfig>()
+        .read_resource::<HdrFramebuffer>()
+        .write_component::<components::Camera>()
+        .read_component::<components::Transform>()
+        .build(
+            |_,
+             world,
+             (
+                command_buffer_queue,
+                current_render_target,
+                taa_jitter,
+                resource_manager,
+                device,
+                active_camera,
+                taa_config,
+                hdr_framebuffer,
+            ),
+             _| {
+                if active_camera.0.is_none() {
+                    current_render_target.0 = None;
+                    return;
+                }
+                let entity = active_camera.0.unwrap();
+
+                let transform = world
+                    .get_component::<components::Transform>(entity)
+                    .map(|transform| transform.clone());
+                let camera = world.get_component_mut::<components::Camera>(entity);
+                if transform.is_none() || camera.is_none() {
+                    log::error!("Active camera entity needs a Camera and Transform component.");
+                    return;
+                }
+                let mut camera = camera.unwrap();
+                camera.update_view(&transform.unwrap());
+
+                current_render_target.0 = camera.render_target.as_ref().map(|render_target| {
+                    let view = render_target.0.texture.create_default_view();
+                    (render_target.0.clone(), view)
+                });
+
+                // Taa moves the projection by a fraction of a pixel each frame.
+                let mut projection = camera.projection_matrix();
+                if taa_config.enabled {
+                    taa_jitter.advance();
+                    projection =
+                        taa_jitter.apply(&projection, hdr_framebuffer.width, hdr_framebuffer.height);
+                }
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("camera"),
+                });
+                super::globals::write_globals(
+                    camera.view,
+                    projection,
+                    camera.position,
+                    &mut encoder,
+                    &device,
+                    &resource_manager,
+                );
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "globals".to_string(),
+                        priority: RenderPriority::OPAQUE,
+                    })
+                    .unwrap();
+            },
+        )
+}
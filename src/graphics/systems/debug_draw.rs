@@ -0,0 +1,94 @@
+use crate::graphics::{
+    pipeline_manager::PipelineManager,
+    pipelines::debug_draw::DebugDraw,
+    renderer::{DepthTexture, MsaaFramebuffer},
+    resources::{GPUResourceManager, HdrFramebuffer},
+    CommandBufferQueue, CommandQueueItem, RenderPriority,
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Draws everything queued in `DebugDraw` this frame and clears it for the next one.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_debug")
+        .write_resource::<DebugDraw>()
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<DepthTexture>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<PipelineManager>()
+        .build(
+            |_,
+             _,
+             (
+                debug_draw,
+                command_buffer_queue,
+                device,
+                resource_manager,
+                hdr_framebuffer,
+                depth_texture,
+                msaa_framebuffer,
+                pipeline_manager,
+            ),
+             _| {
+                if debug_draw.vertices.is_empty()
+                    || !pipeline_manager.is_ready("debug_draw")
+                    || !pipeline_manager.is_enabled("debug_draw")
+                {
+                    return;
+                }
+
+                let vertex_buffer = device.create_buffer_with_data(
+                    bytemuck::cast_slice(&debug_draw.vertices),
+                    wgpu::BufferUsage::VERTEX,
+                );
+                let vertex_count = debug_draw.vertices.len() as u32;
+                debug_draw.vertices.clear();
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("debug_draw"),
+                });
+
+                {
+                    let (attachment, resolve_target) =
+                        msaa_framebuffer.attachments(&hdr_framebuffer.view);
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment,
+                            resolve_target,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: true,
+                            },
+                        }]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &depth_texture.0,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+
+                    let debug_node = pipeline_manager.get("debug_draw", None).unwrap();
+                    render_pass.set_pipeline(&debug_node.render_pipeline);
+                    render_pass.set_bind_group(0, &resource_manager.global_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    render_pass.draw(0..vertex_count, 0..1);
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "debug_draw".to_string(),
+                        priority: RenderPriority::TRANSPARENT,
+                    })
+                    .unwrap();
+            },
+        )
+}
@@ -0,0 +1,220 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager,
+        pipelines::{
+            decal::{
+                decal_batches, decal_textures, DecalInstance, DecalPipeline, VERTICES_PER_DECAL,
+                VERTICES_PER_FORWARD_DECAL,
+            },
+            deferred::DeferredRendering,
+        },
+        renderer::{DepthTexture, MsaaFramebuffer},
+        resources::{GBuffer, GPUResourceManager, HdrFramebuffer},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::components,
+    AssetManager,
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Draws every `Decal`.
+/// With deferred rendering the decals are blended into the gbuffer's albedo and normals before the lighting pass,
+/// otherwise they're added to the frame as quads after the transparent meshes.
+/// Decals are sorted by texture and drawn instanced with one draw call per texture.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_decals")
+        .write_resource::<DecalPipeline>()
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<AssetManager>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<GBuffer>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<DepthTexture>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<DeferredRendering>()
+        .read_resource::<PipelineManager>()
+        .with_query(<(Read<components::Decal>, Read<components::Transform>)>::query())
+        .build(
+            |_,
+             world,
+             (
+                decal_pipeline,
+                command_buffer_queue,
+                asset_manager,
+                device,
+                queue,
+                resource_manager,
+                gbuffer,
+                hdr_framebuffer,
+                depth_texture,
+                msaa_framebuffer,
+                deferred_rendering,
+                pipeline_manager,
+            ),
+             decal_query| {
+                let mut decals: Vec<(components::Decal, DecalInstance)> = decal_query
+                    .iter(&world)
+                    .map(|(decal, transform)| {
+                        (decal.clone(), DecalInstance::new(&decal, &transform.matrix))
+                    })
+                    .collect();
+
+                // Decals whose textures are still loading are skipped until they're ready.
+                let layout = resource_manager
+                    .get_bind_group_layout("decal_texture_layout")
+                    .unwrap();
+                decals.retain(|(decal, _)| {
+                    let textures = decal_textures(decal);
+                    let texture = asset_manager.get_texture(textures.0).get();
+                    let normal_texture = asset_manager.get_texture(textures.1).get();
+                    match (texture, normal_texture) {
+                        (Ok(texture), Ok(normal_texture)) => {
+                            decal_pipeline.prepare_textures(
+                                &device,
+                                &layout,
+                                textures,
+                                texture,
+                                normal_texture,
+                            );
+                            true
+                        }
+                        _ => false,
+                    }
+                });
+                if decals.is_empty() {
+                    return;
+                }
+                // Skip the pass until its pipeline has finished compiling, or while it's disabled.
+                let node_name = if deferred_rendering.0 { "decals" } else { "decal_forward" };
+                if !pipeline_manager.is_ready(node_name) || !pipeline_manager.is_enabled(node_name) {
+                    return;
+                }
+                decals.sort_by(|(a, _), (b, _)| decal_textures(a).cmp(&decal_textures(b)));
+
+                decal_pipeline.reserve(&device, decals.len());
+                let instances: Vec<DecalInstance> =
+                    decals.iter().map(|(_, instance)| *instance).collect();
+                let instance_buffer = decal_pipeline.instance_buffer();
+                queue.write_buffer(instance_buffer, 0, bytemuck::cast_slice(&instances));
+                let size = (instances.len() * std::mem::size_of::<DecalInstance>()) as u64;
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("decals"),
+                });
+                let batches = decal_batches(decals.iter().map(|(decal, _)| decal_textures(decal)));
+
+                let (name, priority) = if deferred_rendering.0 {
+                    // Created every frame as the gbuffer is recreated when the window is resized.
+                    let gbuffer_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        layout: &resource_manager
+                            .get_bind_group_layout("decal_gbuffer_layout")
+                            .unwrap(),
+                        entries: Cow::Borrowed(&[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::Sampler(&gbuffer.sampler),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::TextureView(&gbuffer.depth_view),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 2,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &gbuffer.material_view,
+                                ),
+                            },
+                        ]),
+                        label: Some(Cow::Borrowed("decal_gbuffer")),
+                    });
+
+                    let load_ops = wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    };
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment: &gbuffer.albedo_view,
+                                resolve_target: None,
+                                ops: load_ops,
+                            },
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment: &gbuffer.normal_view,
+                                resolve_target: None,
+                                ops: load_ops,
+                            },
+                        ]),
+                        depth_stencil_attachment: None,
+                    });
+
+                    let decal_node = pipeline_manager.get("decals", None).unwrap();
+                    render_pass.set_pipeline(&decal_node.render_pipeline);
+                    render_pass.set_bind_group(0, &gbuffer_bind_group, &[]);
+                    render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, instance_buffer.slice(..size));
+                    for (textures, instances) in batches {
+                        render_pass.set_bind_group(
+                            2,
+                            decal_pipeline.bind_group(textures).unwrap(),
+                            &[],
+                        );
+                        render_pass.draw(0..VERTICES_PER_DECAL, instances);
+                    }
+
+                    ("decals", RenderPriority::OPAQUE)
+                } else {
+                    let (attachment, resolve_target) =
+                        msaa_framebuffer.attachments(&hdr_framebuffer.view);
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment,
+                                resolve_target,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                },
+                            },
+                        ]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &depth_texture.0,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+
+                    let decal_node = pipeline_manager.get("decal_forward", None).unwrap();
+                    render_pass.set_pipeline(&decal_node.render_pipeline);
+                    render_pass.set_bind_group(0, &resource_manager.global_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, instance_buffer.slice(..size));
+                    for (textures, instances) in batches {
+                        render_pass.set_bind_group(
+                            1,
+                            decal_pipeline.bind_group(textures).unwrap(),
+                            &[],
+                        );
+                        render_pass.draw(0..VERTICES_PER_FORWARD_DECAL, instances);
+                    }
+
+                    ("decal_forward", RenderPriority::TRANSPARENT)
+                };
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: name.to_string(),
+                        priority,
+                    })
+                    .unwrap();
+            },
+        )
+}
@@ -0,0 +1,242 @@
+use crate::{
+    assets::{
+        material::{PBRMaterial, PBRMaterialRon},
+        AssetHandle,
+    },
+    graphics::{
+        pipeline_manager::PipelineManager,
+        pipelines::{deferred::DeferredRendering, ssao::SsaoPass},
+        resources::{ArcRenderPass, GBuffer, GPUResourceManager, HdrFramebuffer, RenderStats},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::components,
+    AssetManager,
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Renders meshes into the gbuffer.
+/// Note: Transforms are uploaded to the GPU by the mesh system so we don't do that here.
+pub fn create_geometry_pass() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_deferred_geometry")
+        .write_resource::<crate::core::PerformanceMetrics>()
+        .write_resource::<AssetManager>()
+        .write_resource::<CommandBufferQueue>()
+        .write_resource::<RenderStats>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<GBuffer>()
+        .read_resource::<DeferredRendering>()
+        .read_resource::<PipelineManager>()
+        .with_query(<(Read<components::Mesh>, Read<components::Transform>)>::query())
+        .build(
+            |_,
+             world,
+             (
+                perf_metrics,
+                asset_manager,
+                command_buffer_queue,
+                render_stats,
+                device,
+                resource_manager,
+                gbuffer,
+                deferred_rendering,
+                pipeline_manager,
+            ),
+             mesh_query| {
+                if !deferred_rendering.0 || !pipeline_manager.is_enabled("deferred_geometry") {
+                    return;
+                }
+
+                let geometry_render_time = std::time::Instant::now();
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("deferred_geometry"),
+                });
+
+                let clear_ops = wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                };
+
+                let asset_materials: Vec<Arc<AssetHandle<PBRMaterial>>> =
+                    asset_manager.get_all_materials::<PBRMaterialRon>();
+                {
+                    let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment: &gbuffer.albedo_view,
+                                resolve_target: None,
+                                ops: clear_ops,
+                            },
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment: &gbuffer.normal_view,
+                                resolve_target: None,
+                                ops: clear_ops,
+                            },
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment: &gbuffer.material_view,
+                                resolve_target: None,
+                                ops: clear_ops,
+                            },
+                        ]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &gbuffer.depth_view,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(1.0),
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+                    let arena1 = typed_arena::Arena::new();
+                    let arena2 = typed_arena::Arena::new();
+
+                    let mut render_pass = ArcRenderPass::new(&arena1, &arena2, render_pass);
+
+                    // The gbuffer is still cleared while the pipeline is compiling.
+                    if mesh_query.iter(&world).count() > 0
+                        && pipeline_manager.is_ready("deferred_geometry")
+                    {
+                        let geometry_node =
+                            pipeline_manager.get("deferred_geometry", None).unwrap();
+                        render_pass.set_pipeline(geometry_node);
+                        render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+                        for material_handle in asset_materials {
+                            let material = material_handle.get();
+                            if material.is_err() {
+                                continue;
+                            }
+                            let material = material.unwrap();
+
+                            // Material bind groups are shared with the forward pbr pipeline.
+                            render_pass.set_bind_group_internal(
+                                material.bind_group.as_ref().unwrap().clone(),
+                            );
+
+                            for (mesh_component, transform) in mesh_query.iter(&world) {
+                                if transform.cull {
+                                    continue;
+                                }
+
+                                resource_manager
+                                    .set_transform_bind_group(&mut render_pass, transform.index);
+
+                                let asset_mesh_handle = mesh_component.mesh_handle.get();
+                                if asset_mesh_handle.is_err() {
+                                    continue;
+                                }
+                                let asset_mesh = asset_mesh_handle.unwrap().clone();
+
+                                for mesh in mesh_component.get_meshes(&asset_mesh).iter() {
+                                    let material_mesh = mesh.meshes.get(&material_handle);
+                                    if material_mesh.is_some() {
+                                        let material_mesh = material_mesh.unwrap();
+                                        render_pass
+                                            .set_index_buffer(material_mesh.index_buffer.clone());
+                                        render_pass.set_vertex_buffer(
+                                            0,
+                                            material_mesh.vertex_buffer.as_ref().unwrap().clone(),
+                                        );
+                                        render_pass.draw_indexed(
+                                            0..material_mesh.index_count as u32,
+                                            0,
+                                            0..1,
+                                        );
+                                        render_stats.record_draw(material_mesh.index_count as u32);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "deferred_geometry".to_string(),
+                        priority: RenderPriority::OPAQUE,
+                    })
+                    .unwrap();
+                perf_metrics.insert(
+                    "deferred geometry render",
+                    std::time::Instant::now().duration_since(geometry_render_time),
+                );
+            },
+        )
+}
+
+/// Reads the gbuffer and renders the lit result to the frame using a full screen triangle.
+pub fn create_lighting_pass() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_deferred_lighting")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<GBuffer>()
+        .read_resource::<SsaoPass>()
+        .read_resource::<DeferredRendering>()
+        .read_resource::<PipelineManager>()
+        .build(
+            |_,
+             _,
+             (
+                command_buffer_queue,
+                device,
+                hdr_framebuffer,
+                resource_manager,
+                gbuffer,
+                ssao_pass,
+                deferred_rendering,
+                pipeline_manager,
+            ),
+             _| {
+                if !deferred_rendering.0
+                    || !pipeline_manager.is_ready("deferred_lighting")
+                    || !pipeline_manager.is_enabled("deferred_lighting")
+                {
+                    return;
+                }
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("deferred_lighting"),
+                });
+
+                let probe_material = resource_manager
+                    .get_bind_group("probe_material", 3)
+                    .unwrap();
+
+                {
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: &hdr_framebuffer.view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: true,
+                            },
+                        }]),
+                        depth_stencil_attachment: None,
+                    });
+
+                    let lighting_node = pipeline_manager.get("deferred_lighting", None).unwrap();
+                    render_pass.set_pipeline(&lighting_node.render_pipeline);
+                    render_pass.set_bind_group(0, &gbuffer.bind_group, &[]);
+                    render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+                    // The probe bind group is stored at slot 3 for the pbr pipeline, the lighting pass uses slot 2.
+                    render_pass.set_bind_group(2, &probe_material.group, &[]);
+                    render_pass.set_bind_group(3, &ssao_pass.bind_group, &[]);
+                    render_pass.draw(0..3 as u32, 0..1);
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "deferred_lighting".to_string(),
+                        priority: RenderPriority::OPAQUE,
+                    })
+                    .unwrap();
+            },
+        )
+}
@@ -0,0 +1,14 @@
+use crate::graphics::resources::{GPUResourceManager, RenderStats};
+use legion::prelude::*;
+use std::sync::Arc;
+
+/// Advances the frame index so this frame's uniform writes don't touch buffers the GPU may still be reading.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("begin_frame")
+        .read_resource::<Arc<GPUResourceManager>>()
+        .write_resource::<RenderStats>()
+        .build(|_, _, (resource_manager, render_stats), _| {
+            resource_manager.begin_frame();
+            render_stats.reset();
+        })
+}
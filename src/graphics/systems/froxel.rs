@@ -2,7 +2,7 @@ use crate::{
     graphics::{
         pipeline_manager::{PipelineManager},
         CommandBufferQueue,
-        CommandQueueItem,
+        CommandQueueItem, RenderPriority,
         lighting::cluster::Clustering,
     },
     scene::components, core::Frustum
@@ -26,6 +26,14 @@ pub fn create() -> Box<dyn Schedulable> {
                 device,
             ),
              camera_query| {
+                // Skip the pass until its pipelines have finished compiling.
+                if !pipeline_manager.is_ready("froxel_creation")
+                    || !pipeline_manager.is_ready("froxel_cull")
+                    || !pipeline_manager.is_enabled("froxel_cull")
+                {
+                    return;
+                }
+
                 let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("froxel_command_buffer"),
                 });
@@ -54,6 +62,7 @@ pub fn create() -> Box<dyn Schedulable> {
                     .push(CommandQueueItem {
                         buffer: encoder.finish(),
                         name: "froxel_cull".to_string(),
+                        priority: RenderPriority::OPAQUE,
                     })
                     .unwrap();
             },
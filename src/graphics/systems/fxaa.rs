@@ -0,0 +1,59 @@
+use crate::graphics::{
+    pipeline_manager::PipelineManager,
+    pipelines::fxaa::{FxaaConfig, FxaaPass},
+    CommandBufferQueue, CommandQueueItem, RenderPriority,
+};
+use legion::prelude::*;
+use std::sync::Arc;
+
+/// Anti-aliases the output of the hdr blit into the swap chain.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_fxaa")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<wgpu::SwapChainTexture>>()
+        .read_resource::<FxaaPass>()
+        .read_resource::<FxaaConfig>()
+        .read_resource::<PipelineManager>()
+        .build(
+            |_,
+             _,
+             (
+                command_buffer_queue,
+                device,
+                queue,
+                output,
+                fxaa_pass,
+                fxaa_config,
+                pipeline_manager,
+            ),
+             _| {
+                // The node was removed or disabled so the hdr blit writes straight to the swap chain.
+                // Also skipped until the pipeline has finished compiling.
+                if !pipeline_manager.is_ready("fxaa") || !pipeline_manager.is_enabled("fxaa") {
+                    return;
+                }
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("fxaa"),
+                });
+
+                fxaa_pass.render(
+                    &queue,
+                    &fxaa_config,
+                    &output.view,
+                    &pipeline_manager,
+                    &mut encoder,
+                );
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "fxaa".to_string(),
+                        priority: RenderPriority::POST_PROCESS,
+                    })
+                    .unwrap();
+            },
+        )
+}
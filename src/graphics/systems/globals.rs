@@ -1,34 +1,34 @@
 use legion::prelude::*;
-use nalgebra_glm::{Mat4, Vec4};
+use nalgebra_glm::{Mat4, Vec3, Vec4};
 use std::{convert::TryInto, sync::Arc};
 
 use crate::{
     graphics::{
-        pipelines::{DirectionalLight, GlobalUniform, LightingUniform, PointLight, MAX_LIGHTS},
-        resources::GPUResourceManager,
-        CommandBufferQueue, CommandQueueItem, lighting::cluster::{FROXELS_Y, FROXELS_X, FROXELS_Z, FAR_PLANE_DISTANCE},
+        pipelines::{DirectionalLight, FogParams, GlobalUniform, LightingUniform, PointLight, MAX_LIGHTS, taa::{TaaConfig, TaaJitter}},
+        resources::{GPUResourceManager, HdrFramebuffer},
+        CommandBufferQueue, CommandQueueItem, RenderPriority, lighting::cluster::{FROXELS_Y, FROXELS_X, FROXELS_Z, FAR_PLANE_DISTANCE},
     },
-    scene::components,
+    scene::{components, resources::{ActiveCamera, DeltaTime, EngineTime}},
 };
 
 // ******************************************************************************
 // This section is meant to prepare our global uniforms and pass them to the GPU.
 // ******************************************************************************
 pub fn update_globals<'a>(camera_data: &components::CameraData, encoder: &'a mut wgpu::CommandEncoder, device: Arc<wgpu::Device>, resource_manager: Arc<GPUResourceManager>) -> Mat4 {
-    let camera_matrix = camera_data.get_matrix();
+    write_globals(camera_data.view, camera_data.projection, camera_data.position, encoder, &device, &resource_manager);
 
-    let camera_view = camera_data.view;
+    return camera_data.view;
+}
 
+/// Copies the camera matrices into the global uniform buffer.
+/// The time is left alone, see `create_tick`.
+pub fn write_globals(view: Mat4, projection: Mat4, position: Vec3, encoder: &mut wgpu::CommandEncoder, device: &wgpu::Device, resource_manager: &GPUResourceManager) {
     let uniforms = GlobalUniform {
-        view_projection: camera_matrix,
-        camera_pos: Vec4::new(
-            camera_data.position.x,
-            camera_data.position.y,
-            camera_data.position.z,
-            0.0,
-        ),
-        view: camera_data.view,
-        projection: camera_data.projection,
+        view_projection: projection * view,
+        camera_pos: Vec4::new(position.x, position.y, position.z, 0.0),
+        view,
+        projection,
+        ..GlobalUniform::default()
     };
 
     let constants_buffer = device.create_buffer_with_data(
@@ -41,18 +41,52 @@ pub fn update_globals<'a>(camera_data: &components::CameraData, encoder: &'a mut
         0,
         &resource_manager.global_uniform_buffer,
         0,
-        std::mem::size_of::<GlobalUniform>() as u64,
+        GlobalUniform::TIME_OFFSET,
     );
+}
+
+/// Writes the time into the global uniform buffer every frame for animated shaders.
+pub fn create_tick() -> Box<dyn Schedulable> {
+    SystemBuilder::new("tick_global_uniforms")
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<EngineTime>()
+        .read_resource::<DeltaTime>()
+        .build(|_, _, (queue, resource_manager, engine_time, delta_time), _| {
+            let time: [f32; 4] = [engine_time.elapsed(), delta_time.0, 0.0, 0.0];
+            queue.write_buffer(
+                &resource_manager.global_uniform_buffer,
+                GlobalUniform::TIME_OFFSET,
+                bytemuck::cast_slice(&time),
+            );
+        })
+}
 
-    return camera_view;
+/// Writes the `FogParams` resource into the global uniform buffer every frame.
+pub fn create_tick_fog() -> Box<dyn Schedulable> {
+    SystemBuilder::new("tick_fog")
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<FogParams>()
+        .build(|_, _, (queue, resource_manager, fog_params), _| {
+            queue.write_buffer(
+                &resource_manager.global_uniform_buffer,
+                GlobalUniform::FOG_OFFSET,
+                bytemuck::bytes_of(&fog_params.uniform()),
+            );
+        })
 }
 
 pub fn create() -> Box<dyn Schedulable> {
     SystemBuilder::new("encoder_globals")
         .write_resource::<crate::core::PerformanceMetrics>()
         .write_resource::<CommandBufferQueue>()
+        .write_resource::<TaaJitter>()
         .read_resource::<Arc<GPUResourceManager>>()
         .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<ActiveCamera>()
+        .read_resource::<TaaConfig>()
+        .read_resource::<HdrFramebuffer>()
         .with_query(<(Read<components::CameraData>,)>::query())
         .with_query(<(Read<components::DirectionalLightData>,)>::query())
         .with_query(<(
@@ -62,8 +96,13 @@ pub fn create() -> Box<dyn Schedulable> {
         .build(
             |_,
              world,
-             (perf_metrics, command_buffer_queue, resource_manager, device),
+             (perf_metrics, command_buffer_queue, taa_jitter, resource_manager, device, active_camera, taa_config, hdr_framebuffer),
              (camera_query, directional_lights, point_lights)| {
+                // The update_camera system writes the globals when a `Camera` entity is active.
+                if active_camera.0.is_some() {
+                    return;
+                }
+
                 let global_time = std::time::Instant::now();
                 let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("globals"),
@@ -83,13 +122,21 @@ pub fn create() -> Box<dyn Schedulable> {
                 }
                 let camera_data = &camera_data.as_ref().unwrap().0;
 
-                let camera_view: Mat4 = update_globals(camera_data, &mut encoder, device.clone(), resource_manager.clone());
+                // Taa moves the projection by a fraction of a pixel each frame.
+                if taa_config.enabled {
+                    taa_jitter.advance();
+                    let projection = taa_jitter.apply(&camera_data.projection, hdr_framebuffer.width, hdr_framebuffer.height);
+                    write_globals(camera_data.view, projection, camera_data.position, &mut encoder, &device, &resource_manager);
+                } else {
+                    update_globals(camera_data, &mut encoder, device.clone(), resource_manager.clone());
+                }
 
 
                 command_buffer_queue
                     .push(CommandQueueItem {
                         buffer: encoder.finish(),
                         name: "globals".to_string(),
+                        priority: RenderPriority::OPAQUE,
                     })
                     .unwrap();
                 perf_metrics.insert("transform calculations", std::time::Instant::now().duration_since(global_time));
@@ -0,0 +1,86 @@
+use crate::graphics::{
+    pipeline_manager::PipelineManager,
+    pipelines::{
+        fxaa::FxaaPass,
+        hdr::{ToneMapConfig, ToneMapPass},
+    },
+    resources::HdrFramebuffer,
+    CommandBufferQueue, CommandQueueItem, RenderPriority,
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Tone maps the hdr framebuffer into the swap chain, or into the fxaa target when the "fxaa" pipeline is ready.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_hdr_blit")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<wgpu::SwapChainTexture>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<FxaaPass>()
+        .read_resource::<ToneMapPass>()
+        .read_resource::<ToneMapConfig>()
+        .read_resource::<PipelineManager>()
+        .build(
+            |_,
+             _,
+             (
+                command_buffer_queue,
+                device,
+                queue,
+                output,
+                hdr_framebuffer,
+                fxaa_pass,
+                tone_map_pass,
+                tone_map_config,
+                pipeline_manager,
+            ),
+             _| {
+                tone_map_pass.update(&queue, &tone_map_config);
+
+                // Skip the pass until its pipeline has finished compiling.
+                if !pipeline_manager.is_ready("hdr_blit") || !pipeline_manager.is_enabled("hdr_blit") {
+                    return;
+                }
+
+                let attachment = if pipeline_manager.is_ready("fxaa") && pipeline_manager.is_enabled("fxaa") {
+                    &fxaa_pass.target.texture_view
+                } else {
+                    &output.view
+                };
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("hdr_blit"),
+                });
+
+                {
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: true,
+                            },
+                        }]),
+                        depth_stencil_attachment: None,
+                    });
+
+                    let blit_node = pipeline_manager.get("hdr_blit", None).unwrap();
+                    render_pass.set_pipeline(&blit_node.render_pipeline);
+                    render_pass.set_bind_group(0, &hdr_framebuffer.bind_group, &[]);
+                    render_pass.set_bind_group(1, &tone_map_pass.bind_group, &[]);
+                    render_pass.draw(0..3 as u32, 0..1);
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "hdr_blit".to_string(),
+                        priority: RenderPriority::POST_PROCESS,
+                    })
+                    .unwrap();
+            },
+        )
+}
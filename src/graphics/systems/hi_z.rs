@@ -0,0 +1,170 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager,
+        renderer::{DepthTexture, MsaaFramebuffer},
+        resources::{
+            CurrentRenderTarget, GPUResourceManager, HdrFramebuffer, HiZCulling, HiZPass, HiZSphere,
+        },
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::components,
+};
+use legion::prelude::*;
+use nalgebra_glm::Vec4;
+use std::sync::Arc;
+
+/// Builds the hi-z buffer from the previous frame's depth buffer and tests every mesh that passed
+/// frustum culling against it, see `HiZCulling`.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("hi_z_cull")
+        .write_resource::<HiZCulling>()
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<PipelineManager>()
+        .read_resource::<DepthTexture>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<CurrentRenderTarget>()
+        .with_query(<(Read<components::Mesh>, Read<components::Transform>)>::query())
+        .with_query(<Read<components::CameraData>>::query())
+        .build(
+            |_,
+             world,
+             (
+                hi_z_culling,
+                command_buffer_queue,
+                device,
+                queue,
+                resource_manager,
+                pipeline_manager,
+                depth_texture,
+                hdr_framebuffer,
+                msaa_framebuffer,
+                current_render_target,
+            ),
+             (mesh_query, camera_query)| {
+                let hi_z_culling: &mut HiZCulling = &mut *hi_z_culling;
+                if !hi_z_culling.enabled {
+                    hi_z_culling.reset();
+                    return;
+                }
+                // Last frame's cull was submitted with last frame.
+                hi_z_culling.cull.read_results(&device);
+
+                let view_projection = match camera_query.iter(&world).find(|camera| camera.active) {
+                    Some(camera) => camera.get_matrix(),
+                    None => {
+                        hi_z_culling.reset();
+                        return;
+                    }
+                };
+                // Only a single sampled depth buffer the size of the hdr framebuffer can be read.
+                if msaa_framebuffer.0.is_some() || current_render_target.0.is_some() {
+                    hi_z_culling.reset();
+                    return;
+                }
+
+                // The depth buffer still holds last frame's depth until the skybox clears it.
+                let previous_view_projection = match hi_z_culling
+                    .previous_view_projection
+                    .replace(view_projection)
+                {
+                    Some(previous_view_projection) => previous_view_projection,
+                    None => return,
+                };
+
+                let (width, height) = (hdr_framebuffer.width, hdr_framebuffer.height);
+                if hi_z_culling
+                    .hi_z
+                    .as_ref()
+                    .map_or(true, |hi_z| hi_z.size() != (width, height))
+                {
+                    let copy_layout = resource_manager
+                        .get_bind_group_layout("hi_z_copy_layout")
+                        .unwrap();
+                    let downsample_layout = resource_manager
+                        .get_bind_group_layout("hi_z_downsample_layout")
+                        .unwrap();
+                    hi_z_culling.hi_z = Some(HiZPass::new(
+                        &device,
+                        copy_layout,
+                        &downsample_layout,
+                        width,
+                        height,
+                    ));
+                    // A resized depth buffer hasn't been drawn into yet.
+                    hi_z_culling.cull.clear_results();
+                    return;
+                }
+
+                let spheres: Vec<HiZSphere> = mesh_query
+                    .iter(&world)
+                    .filter(|(_, transform)| !transform.cull)
+                    .filter_map(|(mesh_component, transform)| {
+                        let mesh = mesh_component.mesh_handle.get().ok()?;
+                        let bounding_sphere = mesh_component.get_bounding_sphere(&mesh);
+                        let center = bounding_sphere.center;
+                        let center =
+                            (transform.matrix * Vec4::new(center.x, center.y, center.z, 1.0)).xyz();
+                        // The sphere is in model space so it grows with the transform's largest scale.
+                        let scale = transform.scale.abs().max();
+                        Some(HiZSphere::new(
+                            center,
+                            bounding_sphere.radius * scale,
+                            transform.index,
+                        ))
+                    })
+                    .collect();
+                if spheres.is_empty() {
+                    return;
+                }
+
+                // Skip the pass until its pipelines have finished compiling.
+                let pipelines = ["hi_z_copy", "hi_z_downsample", "hi_z_cull"];
+                if !pipelines.iter().all(|name| pipeline_manager.is_ready(name))
+                    || !pipeline_manager.is_enabled("hi_z_cull")
+                {
+                    return;
+                }
+
+                let copy_pipeline = pipeline_manager.get_compute("hi_z_copy", None).unwrap();
+                let downsample_pipeline = pipeline_manager
+                    .get_compute("hi_z_downsample", None)
+                    .unwrap();
+                let cull_pipeline = pipeline_manager.get_compute("hi_z_cull", None).unwrap();
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("hi_z_cull"),
+                });
+                let HiZCulling { hi_z, cull, .. } = hi_z_culling;
+                let hi_z = hi_z.as_mut().unwrap();
+                hi_z.build(
+                    &device,
+                    &mut encoder,
+                    &depth_texture.0,
+                    previous_view_projection,
+                    &copy_pipeline.compute_pipeline,
+                    &downsample_pipeline.compute_pipeline,
+                );
+                cull.record(
+                    &device,
+                    &queue,
+                    &mut encoder,
+                    &cull_pipeline.compute_pipeline,
+                    hi_z,
+                    &spheres,
+                );
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "hi_z_cull".to_string(),
+                        // Before the skybox clears the depth buffer.
+                        priority: RenderPriority::SHADOW,
+                    })
+                    .unwrap();
+            },
+        )
+}
@@ -0,0 +1,144 @@
+use legion::prelude::*;
+use nalgebra_glm::{Vec3, Vec4};
+use std::sync::Arc;
+
+use crate::{
+    graphics::{
+        pipelines::{
+            DirectionalLight, LightUniformBuffer, PointLight, SpotLight, MAX_LIGHTS,
+            MAX_SPOT_LIGHTS,
+        },
+        resources::GPUResourceManager,
+    },
+    scene::components::{DirectionalLightData, PointLightData, SpotLightData, Transform},
+};
+
+/// Collects every light in the scene and writes them to the "lights" buffer.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("upload_lights")
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .with_query(<Read<DirectionalLightData>>::query())
+        .with_query(<(Read<PointLightData>, Read<Transform>)>::query())
+        .with_query(<(Read<SpotLightData>, Read<Transform>)>::query())
+        .build(
+            |_,
+             world,
+             (queue, resource_manager),
+             (directional_light_query, point_light_query, spot_light_query)| {
+                let directional_lights: Vec<DirectionalLight> = directional_light_query
+                    .iter(&world)
+                    .map(|data| DirectionalLight {
+                        direction: Vec4::new(data.direction.x, data.direction.y, data.direction.z, 0.0),
+                        color: Vec4::new(data.color.x, data.color.y, data.color.z, data.intensity),
+                    })
+                    .collect();
+                let point_lights: Vec<PointLight> = point_light_query
+                    .iter(&world)
+                    .map(|(data, transform)| PointLight {
+                        position: Vec4::new(
+                            transform.position.x,
+                            transform.position.y,
+                            transform.position.z,
+                            1.0,
+                        ),
+                        color: Vec4::new(data.color.x, data.color.y, data.color.z, data.intensity),
+                        attenuation: Vec4::new(data.attenuation, 0.0, 0.0, 0.0),
+                        ..Default::default()
+                    })
+                    .collect();
+                let spot_lights: Vec<SpotLight> = spot_light_query
+                    .iter(&world)
+                    .map(|(data, transform)| pack_spot_light(&data, transform.position))
+                    .collect();
+
+                let light_buffer =
+                    build_light_buffer(&directional_lights, &point_lights, &spot_lights);
+
+                queue.write_buffer(
+                    &resource_manager.get_buffer("lights"),
+                    0,
+                    bytemuck::bytes_of(&light_buffer),
+                );
+            },
+        )
+}
+
+fn pack_spot_light(data: &SpotLightData, position: Vec3) -> SpotLight {
+    let direction = data.direction.normalize();
+    SpotLight {
+        position: Vec4::new(position.x, position.y, position.z, data.range),
+        direction: Vec4::new(direction.x, direction.y, direction.z, 0.0),
+        color: Vec4::new(data.color.x, data.color.y, data.color.z, data.intensity),
+        cone: Vec4::new(
+            data.inner_angle.to_radians().cos(),
+            data.outer_angle.to_radians().cos(),
+            0.0,
+            0.0,
+        ),
+    }
+}
+
+// Copies the lights into the gpu layout. Lights past the max for each type are ignored.
+fn build_light_buffer(
+    directional_lights: &[DirectionalLight],
+    point_lights: &[PointLight],
+    spot_lights: &[SpotLight],
+) -> LightUniformBuffer {
+    let mut light_buffer = LightUniformBuffer::default();
+    let directional_count = directional_lights.len().min(4);
+    let point_count = point_lights.len().min(MAX_LIGHTS);
+    let spot_count = spot_lights.len().min(MAX_SPOT_LIGHTS);
+
+    light_buffer.directional_lights[..directional_count]
+        .copy_from_slice(&directional_lights[..directional_count]);
+    light_buffer.point_lights[..point_count].copy_from_slice(&point_lights[..point_count]);
+    light_buffer.spot_lights[..spot_count].copy_from_slice(&spot_lights[..spot_count]);
+    light_buffer.light_count = [
+        directional_count as u32,
+        point_count as u32,
+        spot_count as u32,
+        0,
+    ];
+
+    light_buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_light_buffer, pack_spot_light};
+    use crate::{
+        graphics::pipelines::{DirectionalLight, PointLight, SpotLight, MAX_SPOT_LIGHTS},
+        scene::components::SpotLightData,
+    };
+    use nalgebra_glm::Vec3;
+
+    #[test]
+    fn light_structs_are_16_byte_aligned() {
+        assert_eq!(std::mem::size_of::<DirectionalLight>() % 16, 0);
+        assert_eq!(std::mem::size_of::<PointLight>() % 16, 0);
+        assert_eq!(std::mem::size_of::<SpotLight>() % 16, 0);
+    }
+
+    #[test]
+    fn should_pack_spot_lights() {
+        let spot_light = SpotLightData::new(
+            Vec3::new(0.0, -2.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            5.0,
+            0.0,
+            90.0,
+            20.0,
+        );
+        let packed = pack_spot_light(&spot_light, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(packed.position.w, 20.0);
+        assert_eq!(packed.direction.y, -1.0);
+        assert_eq!(packed.color.w, 5.0);
+        assert_eq!(packed.cone.x, 1.0);
+        assert!(packed.cone.y.abs() < 0.0001);
+
+        let spot_lights = vec![packed; MAX_SPOT_LIGHTS + 2];
+        let light_buffer = build_light_buffer(&[], &[], &spot_lights);
+        assert_eq!(light_buffer.light_count, [0, 0, MAX_SPOT_LIGHTS as u32, 0]);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     graphics::{
         pipelines::GlobalUniform, render_graph::RenderGraphNode, renderer::DepthTexture,
-        resources::GPUResourceManager, CommandBufferQueue, CommandQueueItem, RenderGraph,
+        resources::GPUResourceManager, CommandBufferQueue, CommandQueueItem, RenderPriority, RenderGraph,
     },
     scene::components,
     AssetManager,
@@ -130,6 +130,7 @@ pub fn create() -> Box<dyn Schedulable> {
                     .push(CommandQueueItem {
                         buffer: encoder.finish(),
                         name: "line".to_string(),
+                        priority: RenderPriority::OPAQUE,
                     })
                     .unwrap();
             },
@@ -0,0 +1,15 @@
+use crate::{
+    assets::material::PBRMaterialRon, graphics::resources::BindGroupLayoutHash, AssetManager,
+};
+use legion::prelude::*;
+
+/// Rebinds pbr materials whose bind group was created with a layout other than the current `BindGroupLayoutHash`,
+/// like after the pbr pipelines are recreated.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("validate_materials")
+        .read_resource::<AssetManager>()
+        .read_resource::<BindGroupLayoutHash>()
+        .build(|_, _, (asset_manager, layout_hash), _| {
+            asset_manager.validate_materials::<PBRMaterialRon>(layout_hash.0);
+        })
+}
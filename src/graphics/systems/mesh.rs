@@ -3,17 +3,28 @@ use crate::{
         material::{PBRMaterial, PBRMaterialRon},
         AssetHandle,
     },
+    core::Frustum,
     graphics::{
         pipeline_manager::PipelineManager,
-        renderer::DepthTexture,
-        resources::{ArcRenderPass, GPUResourceManager},
-        CommandBufferQueue, CommandQueueItem,
+        pipelines::deferred::DeferredRendering,
+        renderer::{DepthTexture, MsaaFramebuffer, RenderSettings},
+        resources::{
+            ArcRenderPass, CommandEncoderPool, CurrentRenderTarget, GPUResourceManager, GpuDraw,
+            GpuDrivenRenderer,
+            HdrFramebuffer, HiZCulling, PushConstantTransformStrategy, ReflectionProbes, RenderStats,
+            TransformUploadStrategy, DEFAULT_FRAME_COUNT, TRANSFORM_SLAB,
+        },
+        CommandBufferQueue, CommandQueueItem, RenderGraph, RenderPriority,
     },
-    scene::components,
-    AssetManager,
+    scene::{
+        components,
+        resources::{ActiveCamera, BvhDirty},
+    },
+    AssetManager, TransformCount,
 };
 use components::transform::LocalUniform;
 use legion::prelude::*;
+use nalgebra_glm::Vec3;
 use std::{borrow::Cow, sync::Arc};
 
 pub fn create() -> Box<dyn Schedulable> {
@@ -21,14 +32,30 @@ pub fn create() -> Box<dyn Schedulable> {
         .write_resource::<crate::core::PerformanceMetrics>()
         .write_resource::<AssetManager>()
         .write_resource::<CommandBufferQueue>()
+        .write_resource::<RenderStats>()
+        .write_resource::<GpuDrivenRenderer>()
         .read_resource::<Arc<wgpu::Device>>()
         .read_resource::<Arc<wgpu::Queue>>()
-        .read_resource::<Arc<wgpu::SwapChainTexture>>()
+        .read_resource::<CommandEncoderPool>()
+        .read_resource::<HdrFramebuffer>()
         .read_resource::<Arc<GPUResourceManager>>()
         .read_resource::<DepthTexture>()
         .read_resource::<PipelineManager>()
+        .read_resource::<DeferredRendering>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<CurrentRenderTarget>()
+        .read_resource::<RenderGraph>()
+        .read_resource::<TransformUploadStrategy>()
+        .read_resource::<ReflectionProbes>()
+        .read_resource::<ActiveCamera>()
+        .read_resource::<TransformCount>()
+        .read_resource::<HiZCulling>()
+        .read_resource::<RenderSettings>()
+        .write_resource::<BvhDirty>()
+        .read_component::<components::ScissorRect>()
         .with_query(<(Write<components::Transform>,)>::query())
         .with_query(<(Read<components::Mesh>, Read<components::Transform>)>::query())
+        .with_query(<Read<components::CameraData>>::query())
         .build(
             |_,
              mut world,
@@ -36,19 +63,32 @@ pub fn create() -> Box<dyn Schedulable> {
                 perf_metrics,
                 asset_manager,
                 command_buffer_queue,
+                render_stats,
+                gpu_driven_renderer,
                 device,
                 queue,
-                output,
+                encoder_pool,
+                hdr_framebuffer,
                 resource_manager,
                 depth_texture,
                 pipeline_manager,
+                deferred_rendering,
+                msaa_framebuffer,
+                current_render_target,
+                render_graph,
+                transform_upload,
+                reflection_probes,
+                active_camera,
+                transform_count,
+                hi_z_culling,
+                render_settings,
+                bvh_dirty,
             ),
-             (transform_query, mesh_query)| {
+             (transform_query, mesh_query, camera_query)| {
                 // Create mesh encoder
                 let mesh_render_time = std::time::Instant::now();
-                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("mesh"),
-                });
+                let mut encoder = encoder_pool.begin("mesh");
+                let mut transparent_encoder = encoder_pool.begin("mesh_transparent");
 
                 // ******************************************************************************
                 // This section is where we upload our transforms to the GPU
@@ -67,29 +107,186 @@ pub fn create() -> Box<dyn Schedulable> {
                         if transform.cull {
                             continue;
                         }
+                        let matrix = transform.matrix;
                         transform.update();
-                        let transform_buffer =
-                            resource_manager.get_multi_buffer("transform", transform.index);
-                        queue.write_buffer(
-                            &transform_buffer,
-                            0,
-                            bytemuck::bytes_of(&LocalUniform {
+                        if transform.matrix != matrix {
+                            bvh_dirty.0 = true;
+                        }
+                        resource_manager
+                            .get_transform_buffer(transform.index)
+                            .write(&queue, &LocalUniform {
                                 world: transform.matrix,
-                            }),
-                        );
+                            });
                     }
                 }
 
+                // Grow the transform slab once there are more transforms than it can hold.
+                // This has to happen after the writes above since the copy runs after them on the GPU.
+                let needed = transform_count.0.saturating_mul(DEFAULT_FRAME_COUNT as u32);
+                let capacity = resource_manager
+                    .get_multi_buffer_count(TRANSFORM_SLAB)
+                    .unwrap_or(0);
+                if needed > capacity {
+                    resource_manager.resize_multi_buffer(
+                        &device,
+                        TRANSFORM_SLAB,
+                        needed.next_power_of_two(),
+                        &mut encoder,
+                    );
+                }
+
                 // ******************************************************************************
                 // This section is where we actually render our meshes.
                 // ******************************************************************************
+                // Materials of visible meshes count as used so the material cache evicts unused ones first.
+                let mut used_materials = std::collections::HashSet::new();
+                for (mesh_component, transform) in mesh_query.iter(&world) {
+                    if transform.cull {
+                        continue;
+                    }
+                    if let Ok(asset_mesh) = mesh_component.mesh_handle.get() {
+                        for mesh in mesh_component.get_meshes(&asset_mesh).iter() {
+                            used_materials
+                                .extend(mesh.meshes.keys().map(|handle| handle.handle_id.clone()));
+                        }
+                    }
+                }
+                asset_manager.mark_materials_used::<PBRMaterialRon>(used_materials.iter());
+
                 // Collect materials in to their groups.
                 let asset_materials: Vec<Arc<AssetHandle<PBRMaterial>>> = asset_manager.get_all_materials::<PBRMaterialRon>();
-                {
+                // Used to pick which lod to render.
+                let camera_position = camera_query
+                    .iter(&world)
+                    .find(|camera| camera.active)
+                    .map(|camera| camera.position)
+                    .unwrap_or_else(Vec3::zeros);
+                // Indirect draws are recorded once for every mesh so they always bind the transform buffers.
+                // The buffers are still written above since the shadow passes read them.
+                let mesh_count = mesh_query
+                    .iter(&world)
+                    .filter(|(_, transform)| !transform.cull)
+                    .count();
+                // Indirect draws can't be split between the prepass and the pbr pipelines, so they skip it.
+                // It's also skipped until both of its pipelines have finished compiling.
+                let depth_prepass = render_settings.use_depth_prepass
+                    && !render_graph.use_gpu_driven
+                    && pipeline_manager.is_ready("depth_prepass")
+                    && pipeline_manager.is_ready("pbr_depth_equal");
+                // The prepass binds transform buffers, the pbr pass has to use the same transforms to match its depth.
+                let push_constants = transform_upload.use_push_constants(mesh_count)
+                    && !render_graph.use_gpu_driven
+                    && !depth_prepass;
+                let (pbr_pipeline, transparent_pipeline) = if push_constants {
+                    ("pbr_push_constants", "pbr_transparent_push_constants")
+                } else {
+                    ("pbr", "pbr_transparent")
+                };
+
+                // When deferred rendering is on the geometry pass renders the meshes instead.
+                // We skip the pass entirely so a msaa resolve doesn't overwrite the deferred output.
+                if !deferred_rendering.0 {
+                    // A camera with a render target draws into it instead of the hdr framebuffer.
+                    let (attachment, resolve_target, depth_attachment) =
+                        match &current_render_target.0 {
+                            Some((render_target, view)) => (
+                                view,
+                                None,
+                                render_target.depth_texture_view.as_ref().unwrap(),
+                            ),
+                            None => {
+                                let (attachment, resolve_target) =
+                                    msaa_framebuffer.attachments(&hdr_framebuffer.view);
+                                (attachment, resolve_target, &depth_texture.0)
+                            }
+                        };
+                    let (target_width, target_height) = match &current_render_target.0 {
+                        Some((render_target, _)) => (render_target.width, render_target.height),
+                        None => (hdr_framebuffer.width, hdr_framebuffer.height),
+                    };
+                    // The active camera's `ScissorRect` clipped to the target, `Some(None)` if none of it is visible.
+                    let scissor = active_camera
+                        .0
+                        .and_then(|entity| {
+                            world
+                                .get_component::<components::ScissorRect>(entity)
+                                .map(|rect| *rect)
+                        })
+                        .map(|rect| rect.clamp(target_width, target_height));
+
+                    if depth_prepass && scissor != Some(None) {
+                        let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                            color_attachments: Cow::Borrowed(&[]),
+                            depth_stencil_attachment: Some(
+                                wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                    attachment: depth_attachment,
+                                    depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: true,
+                                    }),
+                                    stencil_ops: None,
+                                },
+                            ),
+                        });
+                        let arena1 = typed_arena::Arena::new();
+                        let arena2 = typed_arena::Arena::new();
+                        let mut render_pass = ArcRenderPass::new(&arena1, &arena2, render_pass);
+                        if let Some(Some((x, y, width, height))) = scissor {
+                            render_pass.set_scissor_rect(x, y, width, height);
+                        }
+
+                        let prepass_node = pipeline_manager.get("depth_prepass", None).unwrap();
+                        render_pass.set_pipeline(prepass_node);
+                        render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+                        for material_handle in asset_materials.iter() {
+                            // Masked materials discard pixels so their depth is only known while shading them.
+                            match material_handle.get() {
+                                Ok(material)
+                                    if !material.is_transparent()
+                                        && material.alpha_cutoff.is_none() => {}
+                                _ => continue,
+                            }
+                            for (mesh_component, transform) in mesh_query.iter(&world) {
+                                if transform.cull || !hi_z_culling.is_visible(transform.index) {
+                                    continue;
+                                }
+                                let distance =
+                                    nalgebra_glm::distance(&transform.position, &camera_position);
+                                let mesh_handle = match mesh_component.lod_mesh_name(distance) {
+                                    Some(name) => asset_manager.get_mesh(name),
+                                    None => mesh_component.mesh_handle.clone(),
+                                };
+                                let asset_mesh = match mesh_handle.get() {
+                                    Ok(asset_mesh) => asset_mesh,
+                                    Err(_) => continue,
+                                };
+
+                                resource_manager
+                                    .set_transform_bind_group(&mut render_pass, transform.index);
+                                for mesh in mesh_component.get_meshes(&asset_mesh).iter() {
+                                    if let Some(material_mesh) = mesh.meshes.get(material_handle) {
+                                        render_pass
+                                            .set_index_buffer(material_mesh.index_buffer.clone());
+                                        render_pass.set_vertex_buffer(
+                                            0,
+                                            material_mesh.vertex_buffer.as_ref().unwrap().clone(),
+                                        );
+                                        render_pass.draw_indexed(
+                                            0..material_mesh.index_count as u32,
+                                            0,
+                                            0..1,
+                                        );
+                                        render_stats.record_draw(material_mesh.index_count as u32);
+                                    }
+                                }
+                            }
+                        }
+                    }
+
                     let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
-                            attachment: &output.view,
-                            resolve_target: None,
+                            attachment,
+                            resolve_target,
                             ops: wgpu::Operations {
                                 load: wgpu::LoadOp::Load,
                                 store: true,
@@ -97,7 +294,7 @@ pub fn create() -> Box<dyn Schedulable> {
                         }]),
                         depth_stencil_attachment: Some(
                             wgpu::RenderPassDepthStencilAttachmentDescriptor {
-                                attachment: &depth_texture.0,
+                                attachment: depth_attachment,
                                 depth_ops: Some(wgpu::Operations {
                                     load: wgpu::LoadOp::Load,
                                     store: true,
@@ -110,49 +307,132 @@ pub fn create() -> Box<dyn Schedulable> {
                     let arena2 = typed_arena::Arena::new();
 
                     let mut render_pass = ArcRenderPass::new(&arena1, &arena2, render_pass);
+                    if let Some(Some((x, y, width, height))) = scissor {
+                        render_pass.set_scissor_rect(x, y, width, height);
+                    }
+
+                    // (distance to the camera, material, index buffer, vertex buffer, index count, transform index, world matrix, probe)
+                    let mut transparent_draws = Vec::new();
+                    // (material, index buffer, vertex buffer, transform index, probe) in the same order as `gpu_draws`.
+                    let mut indirect_draws = Vec::new();
+                    let mut gpu_draws = Vec::new();
 
-                    if mesh_query.iter(&world).count() > 0 {
-                        let pbr_node = pipeline_manager.get("pbr", None).unwrap();
+                    // The opaque meshes are skipped until the pbr pipeline has finished compiling.
+                    if mesh_query.iter(&world).count() > 0
+                        && scissor != Some(None)
+                        && pipeline_manager.is_ready(pbr_pipeline)
+                        && pipeline_manager.is_enabled("pbr")
+                    {
+                        let pbr_node = pipeline_manager.get(pbr_pipeline, None).unwrap();
                         render_pass.set_pipeline(pbr_node);
+                        if push_constants {
+                            let empty = resource_manager.get_bind_group("empty", 0).unwrap();
+                            render_pass.set_bind_group_internal(empty);
+                        }
                         render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+                        // Meshes near a reflection probe use it instead of the global probe.
                         let probe_material = resource_manager
                             .get_bind_group("probe_material", 3)
                             .unwrap();
-                        render_pass.set_bind_group_internal(probe_material);
+                        let lights = resource_manager.get_bind_group("lights", 4).unwrap();
+                        render_pass.set_bind_group_internal(lights);
+                        let depth_equal_node = if depth_prepass {
+                            pipeline_manager.get("pbr_depth_equal", None)
+                        } else {
+                            None
+                        };
                         for material_handle in asset_materials {
                             let material = material_handle.get();
                             if material.is_err() {
                                 continue;
                             }
                             let material = material.unwrap();
+                            let transparent = material.is_transparent();
+
+                            // Meshes in the prepass are only shaded where their depth won.
+                            if depth_prepass && !transparent {
+                                if material.alpha_cutoff.is_none() {
+                                    render_pass.set_pipeline(depth_equal_node.unwrap());
+                                } else {
+                                    render_pass.set_pipeline(pbr_node);
+                                }
+                            }
 
                             // Setup bind group for material.
-                            render_pass.set_bind_group_internal(
-                                material.bind_group.as_ref().unwrap().clone(),
-                            );
+                            if !transparent {
+                                render_pass.set_bind_group_internal(
+                                    material.bind_group.as_ref().unwrap().clone(),
+                                );
+                            }
 
                             for (mesh_component, transform) in mesh_query.iter(&world) {
-                                if transform.cull {
+                                // Meshes hidden behind last frame's depth are skipped as well.
+                                if transform.cull || !hi_z_culling.is_visible(transform.index) {
                                     continue;
                                 }
 
-                                resource_manager.set_multi_bind_group(
-                                    &mut render_pass,
-                                    "transform",
-                                    0,
-                                    transform.index,
-                                );
+                                if !transparent && push_constants {
+                                    PushConstantTransformStrategy::set_transform(
+                                        &mut render_pass,
+                                        &LocalUniform {
+                                            world: transform.matrix,
+                                        },
+                                    );
+                                } else if !transparent {
+                                    resource_manager
+                                        .set_transform_bind_group(&mut render_pass, transform.index);
+                                }
+
+                                let probe = reflection_probes
+                                    .get(&transform.position)
+                                    .unwrap_or_else(|| probe_material.clone());
+                                if !transparent {
+                                    render_pass.set_bind_group_internal(probe.clone());
+                                }
+
+                                let distance = nalgebra_glm::distance(&transform.position, &camera_position);
+                                let mesh_handle = match mesh_component.lod_mesh_name(distance) {
+                                    Some(name) => asset_manager.get_mesh(name),
+                                    None => mesh_component.mesh_handle.clone(),
+                                };
 
                                 // If mesh is ready render it!
-                                let asset_mesh_handle = mesh_component.mesh_handle.get();
+                                let asset_mesh_handle = mesh_handle.get();
                                 if asset_mesh_handle.is_err() {
                                     continue;
                                 }
                                 let asset_mesh = asset_mesh_handle.unwrap().clone();
 
-                                for mesh in asset_mesh.meshes.iter() {
+                                for mesh in mesh_component.get_meshes(&asset_mesh).iter() {
                                     let material_mesh = mesh.meshes.get(&material_handle);
-                                    if material_mesh.is_some() {
+                                    if material_mesh.is_some() && transparent {
+                                        let material_mesh = material_mesh.unwrap();
+                                        transparent_draws.push((
+                                            distance,
+                                            material.clone(),
+                                            material_mesh.index_buffer.clone(),
+                                            material_mesh.vertex_buffer.as_ref().unwrap().clone(),
+                                            material_mesh.index_count as u32,
+                                            transform.index,
+                                            transform.matrix,
+                                            probe.clone(),
+                                        ));
+                                    } else if material_mesh.is_some() && render_graph.use_gpu_driven
+                                    {
+                                        let material_mesh = material_mesh.unwrap();
+                                        indirect_draws.push((
+                                            material.clone(),
+                                            material_mesh.index_buffer.clone(),
+                                            material_mesh.vertex_buffer.as_ref().unwrap().clone(),
+                                            transform.index,
+                                            probe.clone(),
+                                        ));
+                                        gpu_draws.push(GpuDraw::new(
+                                            transform.matrix,
+                                            material_mesh.bounding_sphere,
+                                            material_mesh.index_count as u32,
+                                        ));
+                                    } else if material_mesh.is_some() {
                                         let material_mesh = material_mesh.unwrap();
                                         render_pass
                                             .set_index_buffer(material_mesh.index_buffer.clone());
@@ -166,6 +446,7 @@ pub fn create() -> Box<dyn Schedulable> {
                                             0,
                                             0..1,
                                         );
+                                        render_stats.record_draw(material_mesh.index_count as u32);
                                     }
                                 }
                             }
@@ -275,12 +556,143 @@ pub fn create() -> Box<dyn Schedulable> {
                         //     }
                         // }
                     }
+
+                    // The draws are culled by a compute shader which writes the indirect arguments read here.
+                    if !gpu_draws.is_empty()
+                        && pipeline_manager.is_ready("gpu_cull")
+                        && pipeline_manager.is_enabled("gpu_cull")
+                    {
+                        let frustum = camera_query
+                            .iter(&world)
+                            .find(|camera| camera.cull)
+                            .map(|camera| camera.frustum.clone())
+                            // Without a culling camera the planes are empty and everything is drawn.
+                            .unwrap_or_else(Frustum::new);
+                        let cull_pipeline = pipeline_manager.get_compute("gpu_cull", None).unwrap();
+                        let mut cull_encoder =
+                            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                                label: Some("gpu_cull"),
+                            });
+                        gpu_driven_renderer.cull(
+                            &device,
+                            &queue,
+                            &mut cull_encoder,
+                            &cull_pipeline.compute_pipeline,
+                            &frustum,
+                            &gpu_draws,
+                        );
+                        // Submitted with the other compute work so it's done before the opaque pass.
+                        command_buffer_queue
+                            .push(CommandQueueItem {
+                                buffer: cull_encoder.finish(),
+                                name: "gpu_cull".to_string(),
+                                priority: RenderPriority::SHADOW,
+                            })
+                            .unwrap();
+
+                        let indirect_buffer = gpu_driven_renderer.indirect_buffer();
+                        for (index, (material, index_buffer, vertex_buffer, transform_index, probe)) in
+                            indirect_draws.into_iter().enumerate()
+                        {
+                            render_pass.set_bind_group_internal(
+                                material.bind_group.as_ref().unwrap().clone(),
+                            );
+                            render_pass.set_bind_group_internal(probe);
+                            resource_manager
+                                .set_transform_bind_group(&mut render_pass, transform_index);
+                            render_pass.set_index_buffer(index_buffer);
+                            render_pass.set_vertex_buffer(0, vertex_buffer);
+                            render_pass.draw_indexed_indirect(
+                                indirect_buffer.clone(),
+                                GpuDrivenRenderer::args_offset(index as u32),
+                            );
+                            render_stats.record_draw(gpu_draws[index].index_count());
+                        }
+                    }
+
+                    drop(render_pass);
+
+                    if !transparent_draws.is_empty()
+                        && pipeline_manager.is_ready(transparent_pipeline)
+                        && pipeline_manager.is_enabled("pbr_transparent")
+                    {
+                        // Back to front so each mesh blends over the ones behind it.
+                        transparent_draws.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+                        let render_pass =
+                            transparent_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                                color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                                    attachment,
+                                    resolve_target,
+                                    ops: wgpu::Operations {
+                                        load: wgpu::LoadOp::Load,
+                                        store: true,
+                                    },
+                                }]),
+                                depth_stencil_attachment: Some(
+                                    wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                        attachment: depth_attachment,
+                                        depth_ops: Some(wgpu::Operations {
+                                            load: wgpu::LoadOp::Load,
+                                            store: true,
+                                        }),
+                                        stencil_ops: None,
+                                    },
+                                ),
+                            });
+                        let arena1 = typed_arena::Arena::new();
+                        let arena2 = typed_arena::Arena::new();
+                        let mut render_pass = ArcRenderPass::new(&arena1, &arena2, render_pass);
+                        if let Some(Some((x, y, width, height))) = scissor {
+                            render_pass.set_scissor_rect(x, y, width, height);
+                        }
+
+                        let transparent_node = pipeline_manager.get(transparent_pipeline, None).unwrap();
+                        render_pass.set_pipeline(transparent_node);
+                        if push_constants {
+                            let empty = resource_manager.get_bind_group("empty", 0).unwrap();
+                            render_pass.set_bind_group_internal(empty);
+                        }
+                        render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+                        let lights = resource_manager.get_bind_group("lights", 4).unwrap();
+                        render_pass.set_bind_group_internal(lights);
+
+                        for (_, material, index_buffer, vertex_buffer, index_count, transform_index, matrix, probe) in
+                            transparent_draws
+                        {
+                            render_pass.set_bind_group_internal(
+                                material.bind_group.as_ref().unwrap().clone(),
+                            );
+                            render_pass.set_bind_group_internal(probe);
+                            if push_constants {
+                                PushConstantTransformStrategy::set_transform(
+                                    &mut render_pass,
+                                    &LocalUniform { world: matrix },
+                                );
+                            } else {
+                                resource_manager
+                                    .set_transform_bind_group(&mut render_pass, transform_index);
+                            }
+                            render_pass.set_index_buffer(index_buffer);
+                            render_pass.set_vertex_buffer(0, vertex_buffer);
+                            render_pass.draw_indexed(0..index_count, 0, 0..1);
+                            render_stats.record_draw(index_count);
+                        }
+                    }
                 }
 
                 command_buffer_queue
                     .push(CommandQueueItem {
                         buffer: encoder.finish(),
                         name: "pbr".to_string(),
+                        priority: RenderPriority::OPAQUE,
+                    })
+                    .unwrap();
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: transparent_encoder.finish(),
+                        name: "pbr_transparent".to_string(),
+                        priority: RenderPriority::TRANSPARENT,
                     })
                     .unwrap();
                 perf_metrics.insert("mesh render", std::time::Instant::now().duration_since(mesh_render_time));
@@ -1,18 +1,56 @@
+pub mod camera;
+pub mod frame;
 pub mod globals;
+pub mod hi_z;
+pub mod lights;
+pub mod material;
 // pub mod line;
 pub mod mesh;
 pub mod render;
 pub mod skybox;
 pub mod froxel;
 pub mod shadow;
+pub mod skinning;
+pub mod morph;
+pub mod deferred;
+pub mod decal;
+pub mod ssao;
+pub mod motion_vectors;
+pub mod bloom;
+pub mod hdr;
+pub mod fxaa;
+pub mod taa;
+pub mod debug_draw;
+#[cfg(feature = "physics_debug")]
+pub mod physics_debug;
+pub mod wireframe;
+pub mod particles;
+pub mod water;
+pub mod sprite;
+pub mod text;
 
+use crate::core::Profiler;
 use legion::prelude::*;
 use legion::systems::schedule::Builder;
-pub fn create_render_schedule_builder() -> Builder {
-    Schedule::builder()
-        .add_system(crate::graphics::systems::froxel::create())
-        .add_system(crate::graphics::systems::globals::create())
-        .add_system(skybox::create())
+pub fn create_render_schedule_builder(profiler: &Profiler) -> Builder {
+    let builder = Schedule::builder()
+        // Runs on it's own so every other system sees the new frame index.
+        .add_system(profiler.wrap(frame::create()))
+        .flush()
+        .add_system(profiler.wrap(crate::graphics::systems::froxel::create()))
+        .add_system(profiler.wrap(crate::graphics::systems::globals::create()))
+        .add_system(profiler.wrap(crate::graphics::systems::globals::create_tick()))
+        .add_system(profiler.wrap(crate::graphics::systems::globals::create_tick_fog()))
+        .add_system(profiler.wrap(camera::create()))
+        .add_system(profiler.wrap(skybox::create()))
+        // Stale materials are rebound before anything draws them.
+        .add_system(profiler.wrap(material::create()));
     // .add_system(line::create())
     // .add_system(mesh::create())
+
+    // Queues it's lines before `render_debug` draws them.
+    #[cfg(feature = "physics_debug")]
+    let builder = builder.add_system(profiler.wrap(physics_debug::create()));
+
+    builder
 }
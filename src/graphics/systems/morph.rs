@@ -0,0 +1,127 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager, pipelines::morph::MORPH_WORKGROUP_SIZE,
+        resources::GPUResourceManager, CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::components,
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Blends the morph targets of every mesh that has them into the mesh's vertex buffer on the GPU.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("morph")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<PipelineManager>()
+        .read_component::<components::MorphTargetWeights>()
+        .with_query(<Read<components::Mesh>>::query())
+        .build(
+            |_,
+             world,
+             (command_buffer_queue, device, resource_manager, pipeline_manager),
+             mesh_query| {
+                // Skip the pass until its pipeline has finished compiling, or while it's disabled.
+                if !pipeline_manager.is_ready("morph") || !pipeline_manager.is_enabled("morph") {
+                    return;
+                }
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("morph"),
+                });
+
+                let morph_pipeline = pipeline_manager.get_compute("morph", None).unwrap();
+                let layout = resource_manager
+                    .get_bind_group_layout("morph_layout")
+                    .unwrap();
+
+                let mut dispatched = false;
+                for (entity, mesh_component) in mesh_query.iter_entities(&world) {
+                    let asset_mesh = mesh_component.mesh_handle.get();
+                    if asset_mesh.is_err() {
+                        continue;
+                    }
+                    let asset_mesh = asset_mesh.unwrap();
+
+                    let entity_weights = world
+                        .get_component::<components::MorphTargetWeights>(entity)
+                        .map(|weights| weights.0.clone());
+
+                    for mesh in mesh_component.get_meshes(&asset_mesh).iter() {
+                        let mut weights = match &entity_weights {
+                            Some(weights) => weights.clone(),
+                            None => mesh.morph_weights(),
+                        };
+
+                        for sub_mesh in mesh.meshes.values() {
+                            if sub_mesh.morph_buffer.is_none() {
+                                continue;
+                            }
+
+                            // The shader reads one weight per target.
+                            weights.resize(sub_mesh.morph_targets.len(), 0.0);
+                            let weight_buffer = device.create_buffer_with_data(
+                                bytemuck::cast_slice(&weights),
+                                wgpu::BufferUsage::STORAGE,
+                            );
+
+                            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                layout: &layout,
+                                entries: Cow::Borrowed(&[
+                                    wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: wgpu::BindingResource::Buffer(
+                                            weight_buffer.slice(..),
+                                        ),
+                                    },
+                                    wgpu::BindGroupEntry {
+                                        binding: 1,
+                                        resource: wgpu::BindingResource::Buffer(
+                                            sub_mesh.base_vertex_buffer.as_ref().unwrap().slice(..),
+                                        ),
+                                    },
+                                    wgpu::BindGroupEntry {
+                                        binding: 2,
+                                        resource: wgpu::BindingResource::Buffer(
+                                            sub_mesh.morph_buffer.as_ref().unwrap().slice(..),
+                                        ),
+                                    },
+                                    wgpu::BindGroupEntry {
+                                        binding: 3,
+                                        resource: wgpu::BindingResource::Buffer(
+                                            sub_mesh.vertex_buffer.as_ref().unwrap().slice(..),
+                                        ),
+                                    },
+                                ]),
+                                label: Some(Cow::Borrowed("morph")),
+                            });
+
+                            let vertex_count = sub_mesh.vertices.len() as u32;
+                            let mut pass = encoder.begin_compute_pass();
+                            pass.set_pipeline(&morph_pipeline.compute_pipeline);
+                            pass.set_bind_group(0, &bind_group, &[]);
+                            pass.dispatch(
+                                (vertex_count + MORPH_WORKGROUP_SIZE - 1) / MORPH_WORKGROUP_SIZE,
+                                1,
+                                1,
+                            );
+                            dispatched = true;
+                        }
+                    }
+                }
+
+                if !dispatched {
+                    return;
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "morph".to_string(),
+                        priority: RenderPriority::SHADOW,
+                    })
+                    .unwrap();
+            },
+        )
+}
@@ -0,0 +1,188 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager,
+        pipelines::{
+            motion_vectors::{MotionVectorLocals, MotionVectorPass, MotionVectorRendering},
+            taa::TaaConfig,
+        },
+        resources::{ArcRenderPass, GPUResourceManager},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::{
+        components::{self, transform::LocalUniform},
+        resources::ActiveCamera,
+    },
+};
+use legion::prelude::*;
+use nalgebra_glm::Mat4;
+use std::{borrow::Cow, sync::Arc};
+
+/// Renders the screen space velocity of every mesh from it's `Transform` and `PreviousTransform`.
+/// Runs when `MotionVectorRendering` is on or when taa needs the motion vectors.
+/// Note: Transforms are updated by the mesh system so it has to run before this.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_motion_vectors")
+        .write_resource::<MotionVectorPass>()
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<MotionVectorRendering>()
+        .read_resource::<TaaConfig>()
+        .read_resource::<PipelineManager>()
+        .read_resource::<ActiveCamera>()
+        .read_component::<components::Camera>()
+        .with_query(<(Read<components::CameraData>,)>::query())
+        .with_query(<(
+            Read<components::Mesh>,
+            Read<components::Transform>,
+            Read<components::PreviousTransform>,
+        )>::query())
+        .build(
+            |_,
+             world,
+             (
+                motion_vector_pass,
+                command_buffer_queue,
+                device,
+                queue,
+                resource_manager,
+                motion_vector_rendering,
+                taa_config,
+                pipeline_manager,
+                active_camera,
+            ),
+             (camera_query, mesh_query)| {
+                if !motion_vector_rendering.0 && !taa_config.enabled {
+                    return;
+                }
+
+                // The same camera the globals are written from.
+                let view_projection: Mat4 = match active_camera
+                    .0
+                    .and_then(|entity| world.get_component::<components::Camera>(entity))
+                {
+                    Some(camera) => camera.view_projection(),
+                    None => match camera_query.iter(&world).find(|(camera,)| camera.active) {
+                        Some((camera,)) => camera.projection * camera.view,
+                        None => return,
+                    },
+                };
+
+                // Each draw reads it's transforms from the locals buffer using the instance index.
+                let mut locals = Vec::new();
+                let mut draws = Vec::new();
+                for (mesh_component, transform, previous_transform) in mesh_query.iter(&world) {
+                    if transform.cull {
+                        continue;
+                    }
+                    let asset_mesh = match mesh_component.mesh_handle.get() {
+                        Ok(asset_mesh) => asset_mesh,
+                        Err(_) => continue,
+                    };
+
+                    let instance = locals.len() as u32;
+                    locals.push(MotionVectorLocals {
+                        current: LocalUniform {
+                            world: transform.matrix,
+                        },
+                        previous: LocalUniform {
+                            world: previous_transform.matrix,
+                        },
+                    });
+                    for mesh in mesh_component.get_meshes(&asset_mesh).iter() {
+                        for sub_mesh in mesh.meshes.values() {
+                            if let Some(vertex_buffer) = sub_mesh.vertex_buffer.as_ref() {
+                                draws.push((
+                                    sub_mesh.index_buffer.clone(),
+                                    vertex_buffer.clone(),
+                                    sub_mesh.index_count as u32,
+                                    instance,
+                                ));
+                            }
+                        }
+                    }
+                }
+
+                motion_vector_pass.reserve(&device, &resource_manager, locals.len());
+                motion_vector_pass.write(&queue, view_projection, &locals);
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("motion_vectors"),
+                });
+                {
+                    let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment: &motion_vector_pass.view,
+                                resolve_target: None,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                    store: true,
+                                },
+                            },
+                        ]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &motion_vector_pass.depth_view,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Clear(1.0),
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+                    let arena1 = typed_arena::Arena::new();
+                    let arena2 = typed_arena::Arena::new();
+                    let mut render_pass = ArcRenderPass::new(&arena1, &arena2, render_pass);
+
+                    if !draws.is_empty()
+                        && pipeline_manager.is_ready("motion_vectors")
+                        && pipeline_manager.is_enabled("motion_vectors")
+                    {
+                        let motion_vector_node =
+                            pipeline_manager.get("motion_vectors", None).unwrap();
+                        render_pass.set_pipeline(motion_vector_node);
+                        render_pass.set_bind_group(0, &motion_vector_pass.globals_bind_group, &[]);
+                        render_pass.set_bind_group(1, motion_vector_pass.locals_bind_group(), &[]);
+                        for (index_buffer, vertex_buffer, index_count, instance) in draws {
+                            render_pass.set_index_buffer(index_buffer);
+                            render_pass.set_vertex_buffer(0, vertex_buffer);
+                            render_pass.draw_indexed(0..index_count, 0, instance..instance + 1);
+                        }
+                    }
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "motion_vectors".to_string(),
+                        priority: RenderPriority::OPAQUE,
+                    })
+                    .unwrap();
+            },
+        )
+}
+
+/// Copies each mesh's `Transform::matrix` into it's `PreviousTransform` once the frame has been rendered,
+/// adding the component to meshes that don't have one yet.
+pub fn create_previous_transform_update() -> Box<dyn Schedulable> {
+    SystemBuilder::new("update_previous_transforms")
+        .write_component::<components::PreviousTransform>()
+        .with_query(<(Read<components::Mesh>, Read<components::Transform>)>::query())
+        .build(|command_buffer, mut world, _, mesh_query| {
+            let transforms: Vec<(Entity, components::Transform)> = mesh_query
+                .iter_entities(&world)
+                .map(|(entity, (_, transform))| (entity, (*transform).clone()))
+                .collect();
+
+            for (entity, transform) in transforms {
+                match world.get_component_mut::<components::PreviousTransform>(entity) {
+                    Some(mut previous_transform) => previous_transform.matrix = transform.matrix,
+                    None => command_buffer
+                        .add_component(entity, components::PreviousTransform::new(&transform)),
+                }
+            }
+        })
+}
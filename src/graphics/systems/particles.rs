@@ -0,0 +1,229 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager,
+        renderer::{DepthTexture, MsaaFramebuffer},
+        resources::{GPUResourceManager, HdrFramebuffer, ParticleBuffers, VelocityFields},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::{components, resources::DeltaTime},
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Uploads the first `MAX_VELOCITY_FIELDS` `VelocityField`s for the particle simulation to read.
+pub fn create_velocity_fields() -> Box<dyn Schedulable> {
+    SystemBuilder::new("bind_velocity_fields")
+        .write_resource::<VelocityFields>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .with_query(<Read<components::VelocityField>>::query())
+        .build(
+            |_, world, (velocity_fields, device, queue, resource_manager), field_query| {
+                let layout = resource_manager
+                    .get_bind_group_layout("particle_velocity_layout")
+                    .unwrap();
+                // The component borrows have to outlive the references passed to `update`.
+                let borrows: Vec<_> = field_query.iter(&world).collect();
+                let fields: Vec<&components::VelocityField> =
+                    borrows.iter().map(|field| &**field).collect();
+                velocity_fields.update(&device, &queue, &layout, &fields);
+            },
+        )
+}
+
+/// Simulates and sorts the particles of every `ParticleSystem` on the GPU.
+pub fn create_simulation() -> Box<dyn Schedulable> {
+    SystemBuilder::new("simulate_particles")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<PipelineManager>()
+        .read_resource::<DeltaTime>()
+        .read_resource::<VelocityFields>()
+        .with_query(<(
+            Write<components::ParticleSystem>,
+            Read<components::Transform>,
+        )>::query())
+        .with_query(<Read<components::CameraData>>::query())
+        .build(
+            |_,
+             mut world,
+             (
+                command_buffer_queue,
+                device,
+                queue,
+                resource_manager,
+                pipeline_manager,
+                delta_time,
+                velocity_fields,
+            ),
+             (particle_query, camera_query)| {
+                if particle_query.iter_mut(&mut world).next().is_none() {
+                    return;
+                }
+                // Skip the pass until its pipelines have finished compiling, or while it's disabled.
+                if !pipeline_manager.is_ready("particles_simulate")
+                    || !pipeline_manager.is_ready("particles_sort")
+                    || !pipeline_manager.is_enabled("particles_simulate")
+                {
+                    return;
+                }
+                // Particles are sorted by their distance to the camera.
+                let camera_position = match camera_query.iter(&world).find(|camera| camera.active) {
+                    Some(camera) => camera.position,
+                    None => return,
+                };
+
+                let simulate_layout = resource_manager
+                    .get_bind_group_layout("particle_simulate_layout")
+                    .unwrap();
+                let sort_layout = resource_manager
+                    .get_bind_group_layout("particle_sort_layout")
+                    .unwrap();
+                let render_layout = resource_manager
+                    .get_bind_group_layout("particle_render_layout")
+                    .unwrap();
+                let simulate_pipeline = pipeline_manager
+                    .get_compute("particles_simulate", None)
+                    .unwrap();
+                let sort_pipeline = pipeline_manager
+                    .get_compute("particles_sort", None)
+                    .unwrap();
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("particles_simulate"),
+                });
+
+                for (mut particle_system, transform) in particle_query.iter_mut(&mut world) {
+                    particle_system.time += delta_time.0;
+
+                    let max_particles = particle_system.max_particles.max(1);
+                    let buffers = match particle_system.buffers.clone() {
+                        Some(buffers) if buffers.max_particles() == max_particles => buffers,
+                        _ => {
+                            let buffers = Arc::new(ParticleBuffers::new(
+                                &device,
+                                &simulate_layout,
+                                &sort_layout,
+                                &render_layout,
+                                &particle_system,
+                            ));
+                            particle_system.buffers = Some(buffers.clone());
+                            buffers
+                        }
+                    };
+
+                    buffers.update(
+                        &queue,
+                        &particle_system,
+                        transform.matrix,
+                        camera_position,
+                        delta_time.0,
+                    );
+                    buffers.simulate(
+                        &mut encoder,
+                        &simulate_pipeline.compute_pipeline,
+                        &sort_pipeline.compute_pipeline,
+                        &velocity_fields.bind_group,
+                    );
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "particles_simulate".to_string(),
+                        priority: RenderPriority::SHADOW,
+                    })
+                    .unwrap();
+            },
+        )
+}
+
+/// Draws the living particles of every `ParticleSystem` with one indirect draw each.
+pub fn create_render() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_particles")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<DepthTexture>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<PipelineManager>()
+        .with_query(<Read<components::ParticleSystem>>::query())
+        .build(
+            |_,
+             world,
+             (
+                command_buffer_queue,
+                device,
+                resource_manager,
+                hdr_framebuffer,
+                depth_texture,
+                msaa_framebuffer,
+                pipeline_manager,
+            ),
+             particle_query| {
+                // Systems that haven't been simulated yet have nothing to draw.
+                let particle_buffers: Vec<Arc<ParticleBuffers>> = particle_query
+                    .iter(&world)
+                    .filter_map(|particle_system| particle_system.buffers.clone())
+                    .collect();
+                if particle_buffers.is_empty()
+                    || !pipeline_manager.is_ready("particles")
+                    || !pipeline_manager.is_enabled("particles")
+                {
+                    return;
+                }
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("particles"),
+                });
+
+                {
+                    let (attachment, resolve_target) =
+                        msaa_framebuffer.attachments(&hdr_framebuffer.view);
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment,
+                                resolve_target,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                },
+                            },
+                        ]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &depth_texture.0,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+
+                    let particle_node = pipeline_manager.get("particles", None).unwrap();
+                    render_pass.set_pipeline(&particle_node.render_pipeline);
+                    render_pass.set_bind_group(0, &resource_manager.global_bind_group, &[]);
+                    for buffers in particle_buffers.iter() {
+                        render_pass.set_bind_group(1, &buffers.render_bind_group, &[]);
+                        render_pass.set_index_buffer(buffers.index_buffer.slice(..));
+                        render_pass.draw_indexed_indirect(buffers.indirect_buffer(), 0);
+                    }
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "particles".to_string(),
+                        priority: RenderPriority::TRANSPARENT,
+                    })
+                    .unwrap();
+            },
+        )
+}
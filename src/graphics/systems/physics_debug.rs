@@ -0,0 +1,47 @@
+use crate::graphics::pipelines::{debug_draw::DebugDraw, physics_debug::PhysicsDebugDraw};
+use legion::prelude::*;
+use rapier3d::{
+    dynamics::{ImpulseJointSet, MultibodyJointSet, RigidBodySet},
+    geometry::ColliderSet,
+    pipeline::DebugRenderPipeline,
+};
+
+/// Queues the lines of the physics world on `DebugDraw` while `PhysicsDebugDraw::enabled` is true.
+/// Has to run before `render_debug` so the lines are drawn the same frame.
+pub fn create() -> Box<dyn Schedulable> {
+    let mut debug_pipeline = DebugRenderPipeline::default();
+    SystemBuilder::new("render_physics_debug")
+        .write_resource::<PhysicsDebugDraw>()
+        .write_resource::<DebugDraw>()
+        .read_resource::<RigidBodySet>()
+        .read_resource::<ColliderSet>()
+        .read_resource::<ImpulseJointSet>()
+        .read_resource::<MultibodyJointSet>()
+        .build(
+            move |_,
+                  _,
+                  (
+                physics_debug_draw,
+                debug_draw,
+                bodies,
+                colliders,
+                impulse_joints,
+                multibody_joints,
+            ),
+                  _| {
+                if !physics_debug_draw.enabled {
+                    return;
+                }
+
+                debug_pipeline.render(
+                    &mut **physics_debug_draw,
+                    &bodies,
+                    &colliders,
+                    &impulse_joints,
+                    &multibody_joints,
+                );
+                let lines = std::mem::take(&mut physics_debug_draw.lines.vertices);
+                debug_draw.vertices.extend(lines);
+            },
+        )
+}
@@ -1,19 +1,70 @@
-use crate::graphics::{pipeline_manager::PipelineManager, CommandBufferQueue};
+use crate::graphics::{
+    pipeline_manager::PipelineManager,
+    renderer::{RenderSettings, MAX_PROFILER_SCOPES},
+    resources::{CommandEncoderPool, GPUResourceManager, GpuProfiler, RenderStats},
+    CommandBufferQueue,
+};
 use legion::prelude::*;
 use std::sync::Arc;
 
 pub fn create() -> Box<dyn Fn(&mut World, &mut Resources) -> ()> {
-    let thread = Box::new(|_world: &mut World, resources: &mut Resources| {
+    let thread = Box::new(|world: &mut World, resources: &mut Resources| {
+        update_profiler(resources);
+
         let mut command_buffers = Vec::new();
 
         // Moved this out into application run loop.
         //let _swap_chain_output = resources.remove::<Arc<wgpu::SwapChainOutput>>().unwrap();
         let queue = resources.get::<Arc<wgpu::Queue>>().unwrap();
-        let pipeline_manager = resources.get::<PipelineManager>().unwrap();
+        let mut pipeline_manager = resources.get_mut::<PipelineManager>().unwrap();
         let mut command_queue = resources.get_mut::<CommandBufferQueue>().unwrap();
-        command_buffers.extend(pipeline_manager.collect_buffers(&mut command_queue));
+        let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+        let resource_manager = resources.get::<Arc<GPUResourceManager>>().unwrap();
+        pipeline_manager.record_compute_nodes(&device, &resource_manager, world, &command_queue);
+        let mut gpu_profiler = resources.get_mut::<GpuProfiler>();
+        command_buffers.extend(pipeline_manager.collect_buffers(
+            &mut command_queue,
+            gpu_profiler
+                .as_mut()
+                .map(|profiler| (device.as_ref(), &mut **profiler)),
+        ));
 
         queue.submit(command_buffers);
+
+        // Read back how long each node took in earlier frames and queue up this frame's timings.
+        if let Some(profiler) = gpu_profiler.as_mut() {
+            profiler.resolve(&device, &queue);
+        }
+
+        let mut render_stats = resources.get_mut::<RenderStats>().unwrap();
+        render_stats.command_encoders = resources
+            .get::<CommandEncoderPool>()
+            .unwrap()
+            .take_created();
+        render_stats.end_frame();
     });
     thread
 }
+
+// Creates the gpu profiler when `RenderSettings::gpu_profiling` is turned on and removes it when it's turned off.
+fn update_profiler(resources: &mut Resources) {
+    let enabled = resources
+        .get::<RenderSettings>()
+        .map_or(false, |settings| settings.gpu_profiling);
+    let exists = resources.get::<GpuProfiler>().is_some();
+
+    if enabled && !exists {
+        let profiler = {
+            let device = resources.get::<Arc<wgpu::Device>>().unwrap();
+            // Gpu timings are only available when the adapter supports timestamp queries.
+            if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+                return;
+            }
+            let queue = resources.get::<Arc<wgpu::Queue>>().unwrap();
+            GpuProfiler::new(&device, &queue, MAX_PROFILER_SCOPES)
+        };
+        resources.insert(profiler);
+    } else if !enabled && exists {
+        resources.remove::<GpuProfiler>();
+    }
+}
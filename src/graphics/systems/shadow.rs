@@ -5,8 +5,8 @@ use crate::{
     graphics::{
         pipeline_manager::PipelineManager,
         resources::GPUResourceManager,
-        shadows::{OmniShadowManager, ShadowCamera},
-        CommandBufferQueue, CommandQueueItem, pipelines::{PointLight, DirectionalLight, MAX_LIGHTS, LightingUniform}, lighting::cluster::{FROXELS_Y, FROXELS_X, FAR_PLANE_DISTANCE, FROXELS_Z},
+        shadows::{CascadeShadowManager, CsmConfig, OmniShadowManager, ShadowCamera},
+        CommandBufferQueue, CommandQueueItem, RenderPriority, pipelines::{PointLight, DirectionalLight, MAX_LIGHTS, LightingUniform}, lighting::cluster::{FROXELS_Y, FROXELS_X, FAR_PLANE_DISTANCE, FROXELS_Z},
     },
     scene::components,
 };
@@ -22,6 +22,8 @@ pub fn create() -> Box<dyn Schedulable> {
         .read_resource::<Arc<GPUResourceManager>>()
         .read_resource::<PipelineManager>()
         .write_resource::<OmniShadowManager>()
+        .read_resource::<CsmConfig>()
+        .write_resource::<CascadeShadowManager>()
         .with_query(<(Write<components::PointLightData>, Read<components::Transform>)>::query())
         .with_query(<(Read<components::Mesh>, Read<components::Transform>)>::query())
         .with_query(<(Read<components::CameraData>, )>::query())
@@ -29,11 +31,11 @@ pub fn create() -> Box<dyn Schedulable> {
         .build(
             |_,
              mut world,
-             (resource_manager, perf_metrics, device, shadow_camera, command_buffer_queue, gpu_resource_manager, pipeline_manager, omni_shadow_manager),
+             (resource_manager, perf_metrics, device, shadow_camera, command_buffer_queue, gpu_resource_manager, pipeline_manager, omni_shadow_manager, csm_config, cascade_shadow_manager),
              (point_light_query, transform_mesh_query, camera_query, directional_light_query)| {
 
                 // Get camera for update_globals function.
-                let (cam_pos, camera_view) = {
+                let camera = {
                     let filtered_camera_data: Vec<_> = camera_query
                         .iter(&world)
                         .filter(|(camera,)| camera.active)
@@ -46,9 +48,14 @@ pub fn create() -> Box<dyn Schedulable> {
                     if camera_data.is_none() {
                         return;
                     }
-                    let camera = &camera_data.as_ref().unwrap().0;
-                    (camera.position, camera.view)
+                    components::CameraData::clone(&camera_data.unwrap().0)
                 };
+                let (cam_pos, camera_view) = (camera.position, camera.view);
+
+                // Skip the pass until the shadow pipeline has finished compiling, or while it's disabled.
+                if !pipeline_manager.is_ready("shadow") || !pipeline_manager.is_enabled("shadow") {
+                    return;
+                }
 
                 // Create shadow encoder
                 let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -58,11 +65,11 @@ pub fn create() -> Box<dyn Schedulable> {
                 let shadow_sort_time = std::time::Instant::now();
                 let point_lights = {
                     let mut point_lights = point_light_query.iter_mut(world)
-                        .filter(|(light, _)| light.shadow)
+                        .filter(|(light, _)| light.cast_shadow)
                         .collect::<Vec<_>>();
 
                     
-                    // First sort point lights by range / distance.
+                    // First sort point lights by attenuation / distance² to hand out shadow map slots.
                     point_lights.sort_by(|(light_a, transform_a), (light_b, transform_b)| {
                         let distance_a = nalgebra_glm::distance2(&transform_a.position, &cam_pos);
                         let distance_b = nalgebra_glm::distance2(&transform_b.position, &cam_pos);
@@ -100,7 +107,7 @@ pub fn create() -> Box<dyn Schedulable> {
                         }
                     });
 
-                    // Sort point lights by distance and age.
+                    // Then by attenuation / distance² * (age + 1) so lights that were skipped catch up.
                     point_lights.sort_by(|(light_a, transform_a), (light_b, transform_b)| {
                         let distance_a = nalgebra_glm::distance2(&transform_a.position, &cam_pos);
                         let distance_b = nalgebra_glm::distance2(&transform_b.position, &cam_pos);
@@ -139,20 +146,42 @@ pub fn create() -> Box<dyn Schedulable> {
                 );
                 perf_metrics.insert("shadow generation", std::time::Instant::now().duration_since(shadow_time));
 
+                // Only the first shadow casting directional light gets cascaded shadow maps.
+                let cascade_time = std::time::Instant::now();
+                let shadow_direction = directional_light_query
+                    .iter(&world)
+                    .find(|(light,)| light.cast_shadow)
+                    .map(|(light,)| light.direction);
+                cascade_shadow_manager.update(csm_config, &camera, shadow_direction, device, &mut encoder);
+                cascade_shadow_manager.render(
+                    pipeline_manager,
+                    gpu_resource_manager.clone(),
+                    &mut encoder,
+                    transform_mesh_query,
+                    world,
+                );
+                perf_metrics.insert("cascade shadow generation", std::time::Instant::now().duration_since(cascade_time));
+
                 // ******************************************************************************
                 // This section is where we upload our lighting uniforms to the GPU
                 // ******************************************************************************
                 if directional_light_query.iter(&world).count() > 0 || point_light_query.iter_mut(&mut world).count() > 0  {
+                    // The shader samples the cascades for the light with a w of 1.
+                    let mut shadow_assigned = false;
                     let mut directional_light_data_vec: Vec<DirectionalLight> = directional_light_query
                         .iter(&world)
-                        .map(|(data,)| DirectionalLight {
-                            direction: Vec4::new(
-                                data.direction.x,
-                                data.direction.y,
-                                data.direction.z,
-                                0.0,
-                            ),
-                            color: Vec4::new(data.color.x, data.color.y, data.color.z, data.intensity),
+                        .map(|(data,)| {
+                            let cast_shadow = data.cast_shadow && !shadow_assigned;
+                            shadow_assigned |= cast_shadow;
+                            DirectionalLight {
+                                direction: Vec4::new(
+                                    data.direction.x,
+                                    data.direction.y,
+                                    data.direction.z,
+                                    if cast_shadow { 1.0 } else { 0.0 },
+                                ),
+                                color: Vec4::new(data.color.x, data.color.y, data.color.z, data.intensity),
+                            }
                         })
                         .collect();
 
@@ -167,7 +196,7 @@ pub fn create() -> Box<dyn Schedulable> {
                                 1.0,
                             );
                             PointLight {
-                                attenuation: Vec4::new(data.attenuation, if data.shadow { 1.0 } else { 0.0 }, data.shadow_texture_id.0 as f32, data.shadow_texture_id.1 as f32),
+                                attenuation: Vec4::new(data.attenuation, if data.cast_shadow { 1.0 } else { 0.0 }, data.shadow_texture_id.0 as f32, data.shadow_texture_id.1 as f32),
                                 color: Vec4::new(data.color.x, data.color.y, data.color.z, data.intensity),
                                 position,
                                 view_position: camera_view * position,
@@ -224,6 +253,7 @@ pub fn create() -> Box<dyn Schedulable> {
                     .push(CommandQueueItem {
                         buffer: encoder.finish(),
                         name: "shadow".to_string(),
+                        priority: RenderPriority::SHADOW,
                     })
                     .unwrap();
              },
@@ -0,0 +1,110 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager, pipelines::skinning::SKINNING_WORKGROUP_SIZE,
+        resources::GPUResourceManager, CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::components,
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Uploads the joints of every skinned mesh and deforms the mesh's vertices on the GPU.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("skinning")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<PipelineManager>()
+        .with_query(<(Read<components::Mesh>, Read<components::SkinnedMesh>)>::query())
+        .build(
+            |_,
+             world,
+             (command_buffer_queue, device, queue, resource_manager, pipeline_manager),
+             skinned_mesh_query| {
+                if skinned_mesh_query.iter(&world).count() == 0 {
+                    return;
+                }
+
+                // Skip the pass until its pipeline has finished compiling, or while it's disabled.
+                if !pipeline_manager.is_ready("skinning") || !pipeline_manager.is_enabled("skinning") {
+                    return;
+                }
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("skinning"),
+                });
+
+                let skinning_pipeline = pipeline_manager.get_compute("skinning", None).unwrap();
+                let layout = resource_manager
+                    .get_bind_group_layout("skinning_layout")
+                    .unwrap();
+
+                for (mesh_component, skinned_mesh) in skinned_mesh_query.iter(&world) {
+                    let asset_mesh = mesh_component.mesh_handle.get();
+                    if asset_mesh.is_err() {
+                        continue;
+                    }
+                    let asset_mesh = asset_mesh.unwrap();
+
+                    let joint_buffer = resource_manager.get_joint_buffer(skinned_mesh.index);
+                    queue.write_buffer(
+                        &joint_buffer,
+                        0,
+                        bytemuck::cast_slice(&skinned_mesh.skeleton.palette()),
+                    );
+
+                    for mesh in mesh_component.get_meshes(&asset_mesh).iter() {
+                        for sub_mesh in mesh.meshes.values() {
+                            if sub_mesh.skinned_vertex_buffer.is_none() {
+                                continue;
+                            }
+
+                            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                                layout: &layout,
+                                entries: Cow::Borrowed(&[
+                                    wgpu::BindGroupEntry {
+                                        binding: 0,
+                                        resource: wgpu::BindingResource::Buffer(
+                                            joint_buffer.slice(..),
+                                        ),
+                                    },
+                                    wgpu::BindGroupEntry {
+                                        binding: 1,
+                                        resource: wgpu::BindingResource::Buffer(
+                                            sub_mesh.skinned_vertex_buffer.as_ref().unwrap().slice(..),
+                                        ),
+                                    },
+                                    wgpu::BindGroupEntry {
+                                        binding: 2,
+                                        resource: wgpu::BindingResource::Buffer(
+                                            sub_mesh.vertex_buffer.as_ref().unwrap().slice(..),
+                                        ),
+                                    },
+                                ]),
+                                label: Some(Cow::Borrowed("skinning")),
+                            });
+
+                            let vertex_count = sub_mesh.vertices.len() as u32;
+                            let mut pass = encoder.begin_compute_pass();
+                            pass.set_pipeline(&skinning_pipeline.compute_pipeline);
+                            pass.set_bind_group(0, &bind_group, &[]);
+                            pass.dispatch(
+                                (vertex_count + SKINNING_WORKGROUP_SIZE - 1) / SKINNING_WORKGROUP_SIZE,
+                                1,
+                                1,
+                            );
+                        }
+                    }
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "skinning".to_string(),
+                        priority: RenderPriority::SHADOW,
+                    })
+                    .unwrap();
+            },
+        )
+}
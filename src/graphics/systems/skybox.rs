@@ -1,9 +1,9 @@
 use crate::graphics::{
     material::{skybox::SkyboxType, Skybox},
     pipeline_manager::{Pipeline, PipelineManager},
-    renderer::DepthTexture,
-    resources::{CurrentRenderTarget, GPUResourceManager},
-    CommandBufferQueue, CommandQueueItem,
+    renderer::{DepthTexture, MsaaFramebuffer},
+    resources::{CommandEncoderPool, CurrentRenderTarget, GPUResourceManager, HdrFramebuffer},
+    CommandBufferQueue, CommandQueueItem, RenderPriority,
 };
 use legion::prelude::*;
 use std::{borrow::Cow, sync::Arc};
@@ -14,9 +14,10 @@ pub fn create() -> Box<dyn Schedulable> {
         .read_resource::<CurrentRenderTarget>()
         .read_resource::<Arc<GPUResourceManager>>()
         .read_resource::<PipelineManager>()
-        .read_resource::<Arc<wgpu::Device>>()
-        .read_resource::<Arc<wgpu::SwapChainTexture>>()
+        .read_resource::<CommandEncoderPool>()
+        .read_resource::<HdrFramebuffer>()
         .read_resource::<DepthTexture>()
+        .read_resource::<MsaaFramebuffer>()
         .with_query(<(Read<Skybox>,)>::query())
         .build(
             |_,
@@ -26,19 +27,27 @@ pub fn create() -> Box<dyn Schedulable> {
                 current_render_target,
                 resource_manager,
                 pipeline_manager,
-                device,
-                output,
+                encoder_pool,
+                hdr_framebuffer,
                 depth_texture,
+                msaa_framebuffer,
             ),
              skyboxes| {
-                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                    label: Some("skybox_clear_pass"),
-                });
+                // Skip the pass until its pipelines have finished compiling, or while it's disabled.
+                if !pipeline_manager.is_ready("skybox")
+                    || !pipeline_manager.is_ready("realtime_skybox")
+                    || !pipeline_manager.is_enabled("skybox")
+                {
+                    return;
+                }
+
+                let mut encoder = encoder_pool.begin("skybox_clear_pass");
 
-                let view_attachment = if current_render_target.0.is_some() {
-                    &current_render_target.0.as_ref().unwrap().1
+                // Render targets are never multisampled, the hdr framebuffer is resolved to when msaa is on.
+                let (view_attachment, resolve_target) = if current_render_target.0.is_some() {
+                    (&current_render_target.0.as_ref().unwrap().1, None)
                 } else {
-                    &output.view
+                    msaa_framebuffer.attachments(&hdr_framebuffer.view)
                 };
 
                 let depth_attachment = if current_render_target.0.is_some() {
@@ -62,7 +71,7 @@ pub fn create() -> Box<dyn Schedulable> {
                     let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                         color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
                             attachment: view_attachment,
-                            resolve_target: None,
+                            resolve_target,
                             ops: wgpu::Operations {
                                 load: wgpu::LoadOp::Clear(wgpu::Color {
                                     r: skybox.clear_color.x as f64,
@@ -85,7 +94,9 @@ pub fn create() -> Box<dyn Schedulable> {
                         ),
                     });
 
-                    if skybox.skybox_type == SkyboxType::HdrCubemap {
+                    if skybox.skybox_type == SkyboxType::HdrCubemap
+                        || skybox.skybox_type == SkyboxType::Cubemap
+                    {
                         render_pass.set_pipeline(&pipeline.render_pipeline);
                         render_pass.set_bind_group(0, &resource_manager.global_bind_group, &[]);
 
@@ -111,6 +122,7 @@ pub fn create() -> Box<dyn Schedulable> {
                     .push(CommandQueueItem {
                         buffer: encoder.finish(),
                         name: "skybox".to_string(),
+                        priority: RenderPriority::OPAQUE,
                     })
                     .unwrap();
             },
@@ -0,0 +1,177 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager,
+        pipelines::sprite::{
+            is_sprite_visible, sprite_batches, write_sprite_vertices, SpritePipeline, SpriteVertex,
+            VERTICES_PER_SPRITE,
+        },
+        renderer::{DepthTexture, MsaaFramebuffer},
+        resources::{GPUResourceManager, HdrFramebuffer},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::{
+        components::{self, CameraMode},
+        resources::ActiveCamera,
+    },
+    AssetManager,
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Draws every `Sprite` visible to the active camera when it's a 2D camera.
+/// Sprites are sorted by texture and written into one vertex buffer, then drawn with one draw call per texture.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_sprites")
+        .write_resource::<SpritePipeline>()
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<AssetManager>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<DepthTexture>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<PipelineManager>()
+        .read_resource::<ActiveCamera>()
+        .read_component::<components::Camera>()
+        .with_query(<(Read<components::Sprite>, Read<components::Transform>)>::query())
+        .build(
+            |_,
+             world,
+             (
+                sprite_pipeline,
+                command_buffer_queue,
+                asset_manager,
+                device,
+                resource_manager,
+                hdr_framebuffer,
+                depth_texture,
+                msaa_framebuffer,
+                pipeline_manager,
+                active_camera,
+            ),
+             sprite_query| {
+                let camera = match active_camera
+                    .0
+                    .and_then(|entity| world.get_component::<components::Camera>(entity))
+                {
+                    Some(camera) => camera,
+                    None => return,
+                };
+                if camera.mode != CameraMode::Mode2D {
+                    return;
+                }
+                let view_projection = camera.view_projection();
+
+                let mut sprites: Vec<(components::Sprite, nalgebra_glm::Mat4)> = sprite_query
+                    .iter(&world)
+                    .filter(|(sprite, transform)| {
+                        is_sprite_visible(&sprite, &transform.matrix, &view_projection)
+                    })
+                    .map(|(sprite, transform)| (sprite.clone(), transform.matrix))
+                    .collect();
+
+                // Sprites whose texture is still loading are skipped until it's ready.
+                let layout = resource_manager
+                    .get_bind_group_layout("sprite_layout")
+                    .unwrap();
+                sprites.retain(|(sprite, _)| {
+                    match asset_manager.get_texture(sprite.texture.clone()).get() {
+                        Ok(texture) => {
+                            sprite_pipeline.prepare_texture(
+                                &device,
+                                &layout,
+                                &sprite.texture,
+                                texture,
+                            );
+                            true
+                        }
+                        Err(_) => false,
+                    }
+                });
+                if sprites.is_empty()
+                    || !pipeline_manager.is_ready("sprite")
+                    || !pipeline_manager.is_enabled("sprite")
+                {
+                    return;
+                }
+                sprites.sort_by(|(a, _), (b, _)| a.texture.cmp(&b.texture));
+
+                // The vertices are written straight into a mapped staging buffer and copied to the vertex buffer on the GPU.
+                let vertex_count = sprites.len() * VERTICES_PER_SPRITE;
+                let size = (vertex_count * std::mem::size_of::<SpriteVertex>()) as u64;
+                let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("sprite_staging"),
+                    size,
+                    usage: wgpu::BufferUsage::COPY_SRC,
+                    mapped_at_creation: true,
+                });
+                {
+                    let mut mapped = staging_buffer.slice(..).get_mapped_range_mut();
+                    let vertices: &mut [SpriteVertex] = bytemuck::cast_slice_mut(&mut mapped);
+                    for ((sprite, matrix), vertices) in
+                        sprites.iter().zip(vertices.chunks_mut(VERTICES_PER_SPRITE))
+                    {
+                        write_sprite_vertices(sprite, matrix, vertices);
+                    }
+                }
+                staging_buffer.unmap();
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("sprite"),
+                });
+                sprite_pipeline.reserve(&device, vertex_count);
+                let vertex_buffer = sprite_pipeline.vertex_buffer();
+                encoder.copy_buffer_to_buffer(&staging_buffer, 0, vertex_buffer, 0, size);
+
+                {
+                    let (attachment, resolve_target) =
+                        msaa_framebuffer.attachments(&hdr_framebuffer.view);
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment,
+                                resolve_target,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                },
+                            },
+                        ]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &depth_texture.0,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+
+                    let sprite_node = pipeline_manager.get("sprite", None).unwrap();
+                    render_pass.set_pipeline(&sprite_node.render_pipeline);
+                    render_pass.set_bind_group(0, &resource_manager.global_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..size));
+                    let batches =
+                        sprite_batches(sprites.iter().map(|(sprite, _)| sprite.texture.as_str()));
+                    for (texture, vertices) in batches {
+                        render_pass.set_bind_group(
+                            1,
+                            sprite_pipeline.bind_group(texture).unwrap(),
+                            &[],
+                        );
+                        render_pass.draw(vertices, 0..1);
+                    }
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "sprite".to_string(),
+                        priority: RenderPriority::TRANSPARENT,
+                    })
+                    .unwrap();
+            },
+        )
+}
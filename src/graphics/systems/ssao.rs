@@ -0,0 +1,74 @@
+use crate::graphics::{
+    pipeline_manager::PipelineManager,
+    pipelines::{
+        deferred::DeferredRendering,
+        ssao::{SsaoConfig, SsaoPass},
+    },
+    resources::{GBuffer, GPUResourceManager},
+    CommandBufferQueue, CommandQueueItem, RenderPriority,
+};
+use legion::prelude::*;
+use std::sync::Arc;
+
+/// Renders ambient occlusion from the gbuffer for the deferred lighting pass.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_ssao")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<SsaoPass>()
+        .read_resource::<SsaoConfig>()
+        .read_resource::<GBuffer>()
+        .read_resource::<DeferredRendering>()
+        .read_resource::<PipelineManager>()
+        .build(
+            |_,
+             _,
+             (
+                command_buffer_queue,
+                device,
+                queue,
+                resource_manager,
+                ssao_pass,
+                ssao_config,
+                gbuffer,
+                deferred_rendering,
+                pipeline_manager,
+            ),
+             _| {
+                if !deferred_rendering.0 {
+                    return;
+                }
+
+                // Skip the pass until its pipelines have finished compiling, or while it's disabled.
+                if !pipeline_manager.is_ready("ssao")
+                    || !pipeline_manager.is_ready("ssao_blur")
+                    || !pipeline_manager.is_enabled("ssao_blur")
+                {
+                    return;
+                }
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("ssao"),
+                });
+
+                ssao_pass.render(
+                    &queue,
+                    &ssao_config,
+                    &gbuffer,
+                    &resource_manager,
+                    &pipeline_manager,
+                    &mut encoder,
+                );
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "ssao_blur".to_string(),
+                        priority: RenderPriority::OPAQUE,
+                    })
+                    .unwrap();
+            },
+        )
+}
@@ -0,0 +1,64 @@
+use crate::graphics::{
+    pipeline_manager::PipelineManager,
+    pipelines::taa::{TaaConfig, TaaPass},
+    resources::HdrFramebuffer,
+    CommandBufferQueue, CommandQueueItem, RenderPriority,
+};
+use legion::prelude::*;
+use std::sync::Arc;
+
+/// Blends the hdr framebuffer with the history of previous frames before the rest of post processing.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_taa")
+        .write_resource::<CommandBufferQueue>()
+        .write_resource::<TaaPass>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<TaaConfig>()
+        .read_resource::<PipelineManager>()
+        .build(
+            |_,
+             _,
+             (
+                command_buffer_queue,
+                taa_pass,
+                device,
+                queue,
+                hdr_framebuffer,
+                taa_config,
+                pipeline_manager,
+            ),
+             _| {
+                // The history is dropped so turning taa back on doesn't blend in an old frame.
+                if !taa_config.enabled {
+                    taa_pass.reset();
+                    return;
+                }
+
+                // Skip the pass until its pipeline has finished compiling, or while it's disabled.
+                if !pipeline_manager.is_ready("taa") || !pipeline_manager.is_enabled("taa") {
+                    return;
+                }
+
+                let mut encoder = device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("taa") });
+
+                taa_pass.render(
+                    &queue,
+                    &taa_config,
+                    &hdr_framebuffer,
+                    &pipeline_manager,
+                    &mut encoder,
+                );
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "taa".to_string(),
+                        priority: RenderPriority::POST_PROCESS,
+                    })
+                    .unwrap();
+            },
+        )
+}
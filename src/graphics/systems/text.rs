@@ -0,0 +1,156 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager,
+        pipelines::text::{write_text_vertices, TextPipeline, TextVertex},
+        renderer::{DepthTexture, MsaaFramebuffer},
+        resources::{GPUResourceManager, HdrFramebuffer},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::components,
+    AssetManager,
+};
+use legion::prelude::*;
+use std::{borrow::Cow, ops::Range, sync::Arc};
+
+/// Draws every `TextRenderer`, both in the world and on screen.
+/// Glyphs are written into one vertex buffer sorted by font and drawn with one draw call per font's atlas.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_text")
+        .write_resource::<TextPipeline>()
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<AssetManager>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<DepthTexture>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<PipelineManager>()
+        .with_query(<(
+            Read<components::TextRenderer>,
+            Read<components::Transform>,
+        )>::query())
+        .build(
+            |_,
+             world,
+             (
+                text_pipeline,
+                command_buffer_queue,
+                asset_manager,
+                device,
+                resource_manager,
+                hdr_framebuffer,
+                depth_texture,
+                msaa_framebuffer,
+                pipeline_manager,
+            ),
+             text_query| {
+                let mut texts: Vec<(components::TextRenderer, components::Transform)> = text_query
+                    .iter(&world)
+                    .filter(|(text, _)| !text.text.is_empty())
+                    .map(|(text, transform)| (text.clone(), transform.clone()))
+                    .collect();
+                texts.sort_by(|(a, _), (b, _)| a.font.cmp(&b.font));
+
+                // Text whose font failed to load or whose atlas isn't ready yet is skipped.
+                let layout = resource_manager
+                    .get_bind_group_layout("text_layout")
+                    .unwrap();
+                let screen_size = (hdr_framebuffer.width, hdr_framebuffer.height);
+                let mut vertices: Vec<TextVertex> = Vec::new();
+                let mut batches: Vec<(String, Range<u32>)> = Vec::new();
+                for (text, transform) in texts.iter() {
+                    let font = match asset_manager.get_font(text.font.clone()) {
+                        Ok(font) => font,
+                        Err(_) => continue,
+                    };
+                    match font.texture.get() {
+                        Ok(atlas) => {
+                            text_pipeline.prepare_font(&device, &layout, &text.font, atlas)
+                        }
+                        Err(_) => continue,
+                    }
+
+                    let start = vertices.len() as u32;
+                    write_text_vertices(
+                        text,
+                        transform,
+                        &font.layout(&text.text),
+                        screen_size,
+                        &mut vertices,
+                    );
+                    let end = vertices.len() as u32;
+                    match batches.last_mut() {
+                        Some((last, range)) if *last == text.font => range.end = end,
+                        _ => batches.push((text.font.clone(), start..end)),
+                    }
+                }
+                if vertices.is_empty()
+                    || !pipeline_manager.is_ready("sdf_text")
+                    || !pipeline_manager.is_enabled("sdf_text")
+                {
+                    return;
+                }
+
+                let size = (vertices.len() * std::mem::size_of::<TextVertex>()) as u64;
+                let staging_buffer = device.create_buffer_with_data(
+                    bytemuck::cast_slice(&vertices),
+                    wgpu::BufferUsage::COPY_SRC,
+                );
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("sdf_text"),
+                });
+                text_pipeline.reserve(&device, vertices.len());
+                let vertex_buffer = text_pipeline.vertex_buffer();
+                encoder.copy_buffer_to_buffer(&staging_buffer, 0, vertex_buffer, 0, size);
+
+                {
+                    let (attachment, resolve_target) =
+                        msaa_framebuffer.attachments(&hdr_framebuffer.view);
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment,
+                                resolve_target,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                },
+                            },
+                        ]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &depth_texture.0,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+
+                    let text_node = pipeline_manager.get("sdf_text", None).unwrap();
+                    render_pass.set_pipeline(&text_node.render_pipeline);
+                    render_pass.set_bind_group(0, &resource_manager.global_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..size));
+                    for (font, vertices) in batches {
+                        render_pass.set_bind_group(
+                            1,
+                            text_pipeline.bind_group(&font).unwrap(),
+                            &[],
+                        );
+                        render_pass.draw(vertices, 0..1);
+                    }
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "sdf_text".to_string(),
+                        priority: RenderPriority::TRANSPARENT,
+                    })
+                    .unwrap();
+            },
+        )
+}
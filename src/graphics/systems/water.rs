@@ -0,0 +1,197 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager,
+        renderer::{DepthTexture, MsaaFramebuffer},
+        resources::{GPUResourceManager, HdrFramebuffer, OceanBuffers, OceanPipelines},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::{components, resources::DeltaTime},
+};
+use legion::prelude::*;
+use std::{borrow::Cow, sync::Arc};
+
+/// Advances every `OceanSurface` and turns its wave spectrum into height and normal maps on the GPU.
+pub fn create_simulation() -> Box<dyn Schedulable> {
+    SystemBuilder::new("simulate_ocean")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<PipelineManager>()
+        .read_resource::<DeltaTime>()
+        .with_query(<Write<components::OceanSurface>>::query())
+        .build(
+            |_,
+             mut world,
+             (
+                command_buffer_queue,
+                device,
+                queue,
+                resource_manager,
+                pipeline_manager,
+                delta_time,
+            ),
+             ocean_query| {
+                if ocean_query.iter_mut(&mut world).next().is_none() {
+                    return;
+                }
+
+                let names = ["ocean_spectrum", "ocean_grid", "ocean_time", "ocean_fft", "ocean_maps"];
+                // Skip the pass until its pipelines have finished compiling, or while it's disabled.
+                if !names.iter().all(|name| pipeline_manager.is_ready(name))
+                    || !pipeline_manager.is_enabled("ocean_simulate")
+                {
+                    return;
+                }
+
+                let pipeline = |name| {
+                    &pipeline_manager
+                        .get_compute(name, None)
+                        .unwrap()
+                        .compute_pipeline
+                };
+                let pipelines = OceanPipelines {
+                    spectrum: pipeline("ocean_spectrum"),
+                    grid: pipeline("ocean_grid"),
+                    time: pipeline("ocean_time"),
+                    fft: pipeline("ocean_fft"),
+                    maps: pipeline("ocean_maps"),
+                };
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("ocean_simulate"),
+                });
+
+                for mut surface in ocean_query.iter_mut(&mut world) {
+                    surface.time += delta_time.0;
+
+                    let buffers = match surface.buffers.clone() {
+                        Some(buffers) if buffers.matches(&surface) => buffers,
+                        _ => {
+                            let buffers =
+                                Arc::new(OceanBuffers::new(&device, &resource_manager, &surface));
+                            surface.buffers = Some(buffers.clone());
+                            buffers
+                        }
+                    };
+
+                    buffers.update(&queue, &surface);
+                    buffers.simulate(&mut encoder, &pipelines);
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "ocean_simulate".to_string(),
+                        priority: RenderPriority::SHADOW,
+                    })
+                    .unwrap();
+            },
+        )
+}
+
+/// Draws every simulated `OceanSurface` into the hdr framebuffer.
+pub fn create_render() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_water")
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<DepthTexture>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<PipelineManager>()
+        .with_query(<(
+            Read<components::OceanSurface>,
+            Read<components::Transform>,
+        )>::query())
+        .build(
+            |_,
+             world,
+             (
+                command_buffer_queue,
+                device,
+                resource_manager,
+                hdr_framebuffer,
+                depth_texture,
+                msaa_framebuffer,
+                pipeline_manager,
+            ),
+             ocean_query| {
+                // Surfaces that haven't been simulated yet have nothing to draw.
+                let oceans: Vec<_> = ocean_query
+                    .iter(&world)
+                    .filter(|(_, transform)| !transform.cull)
+                    .filter_map(|(surface, transform)| {
+                        let transform_binding = resource_manager
+                            .get_transform_buffer(transform.index)
+                            .current_binding();
+                        surface
+                            .buffers
+                            .clone()
+                            .map(|buffers| (buffers, transform_binding))
+                    })
+                    .collect();
+                if oceans.is_empty()
+                    || !pipeline_manager.is_ready("water")
+                    || !pipeline_manager.is_enabled("water")
+                {
+                    return;
+                }
+                let probe_material = resource_manager
+                    .get_bind_group("probe_material", 3)
+                    .unwrap();
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("water"),
+                });
+
+                {
+                    let (attachment, resolve_target) =
+                        msaa_framebuffer.attachments(&hdr_framebuffer.view);
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment,
+                                resolve_target,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                },
+                            },
+                        ]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &depth_texture.0,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+
+                    let water_node = pipeline_manager.get("water", None).unwrap();
+                    render_pass.set_pipeline(&water_node.render_pipeline);
+                    render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+                    render_pass.set_bind_group(3, &probe_material.group, &[]);
+                    for (buffers, transform_binding) in oceans.iter() {
+                        render_pass.set_bind_group(0, &transform_binding.group, &[]);
+                        render_pass.set_bind_group(2, &buffers.render_bind_group, &[]);
+                        render_pass.set_index_buffer(buffers.index_buffer.slice(..));
+                        render_pass.set_vertex_buffer(0, buffers.vertex_buffer.slice(..));
+                        render_pass.draw_indexed(0..buffers.index_count, 0, 0..1);
+                    }
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "water".to_string(),
+                        // After the opaque meshes and the deferred lighting pass, which both write the hdr framebuffer.
+                        priority: RenderPriority::TRANSPARENT,
+                    })
+                    .unwrap();
+            },
+        )
+}
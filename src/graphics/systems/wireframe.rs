@@ -0,0 +1,144 @@
+use crate::{
+    graphics::{
+        pipeline_manager::PipelineManager,
+        pipelines::wireframe,
+        renderer::{DepthTexture, MsaaFramebuffer},
+        resources::{ArcRenderPass, GPUResourceManager, HdrFramebuffer},
+        CommandBufferQueue, CommandQueueItem, RenderPriority,
+    },
+    scene::components,
+    AssetManager,
+};
+use legion::prelude::*;
+use nalgebra_glm::Vec3;
+use std::{borrow::Cow, sync::Arc};
+
+/// Draws the triangles of every `DrawWireframe` entity as lines on top of the opaque meshes.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_wireframe")
+        .read_resource::<AssetManager>()
+        .write_resource::<CommandBufferQueue>()
+        .read_resource::<Arc<wgpu::Device>>()
+        .read_resource::<Arc<GPUResourceManager>>()
+        .read_resource::<HdrFramebuffer>()
+        .read_resource::<DepthTexture>()
+        .read_resource::<MsaaFramebuffer>()
+        .read_resource::<PipelineManager>()
+        .with_query(<(
+            Read<components::Mesh>,
+            Read<components::Transform>,
+            Read<components::DrawWireframe>,
+        )>::query())
+        .with_query(<Read<components::CameraData>>::query())
+        .build(
+            |_,
+             world,
+             (
+                asset_manager,
+                command_buffer_queue,
+                device,
+                resource_manager,
+                hdr_framebuffer,
+                depth_texture,
+                msaa_framebuffer,
+                pipeline_manager,
+            ),
+             (wireframe_query, camera_query)| {
+                if wireframe_query.iter(&world).next().is_none() {
+                    return;
+                }
+                // Not created on backends without push constants, still compiling or disabled.
+                if !pipeline_manager.is_ready("wireframe") || !pipeline_manager.is_enabled("wireframe") {
+                    return;
+                }
+                let wireframe_node = pipeline_manager.get("wireframe", None).unwrap();
+                let color = wireframe::color(device.features());
+                let camera_position = camera_query
+                    .iter(&world)
+                    .find(|camera| camera.active)
+                    .map(|camera| camera.position)
+                    .unwrap_or_else(Vec3::zeros);
+
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("wireframe"),
+                });
+
+                {
+                    let (attachment, resolve_target) =
+                        msaa_framebuffer.attachments(&hdr_framebuffer.view);
+                    let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: Cow::Borrowed(&[
+                            wgpu::RenderPassColorAttachmentDescriptor {
+                                attachment,
+                                resolve_target,
+                                ops: wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                },
+                            },
+                        ]),
+                        depth_stencil_attachment: Some(
+                            wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                                attachment: &depth_texture.0,
+                                depth_ops: Some(wgpu::Operations {
+                                    load: wgpu::LoadOp::Load,
+                                    store: true,
+                                }),
+                                stencil_ops: None,
+                            },
+                        ),
+                    });
+                    let arena1 = typed_arena::Arena::new();
+                    let arena2 = typed_arena::Arena::new();
+                    let mut render_pass = ArcRenderPass::new(&arena1, &arena2, render_pass);
+
+                    render_pass.set_pipeline(wireframe_node);
+                    render_pass.set_bind_group(1, &resource_manager.global_bind_group, &[]);
+                    render_pass.set_push_constants(
+                        wgpu::ShaderStage::FRAGMENT,
+                        0,
+                        bytemuck::cast_slice(&color),
+                    );
+
+                    for (mesh_component, transform, _) in wireframe_query.iter(&world) {
+                        if transform.cull {
+                            continue;
+                        }
+
+                        // Outline the same lod the mesh system draws.
+                        let distance =
+                            nalgebra_glm::distance(&transform.position, &camera_position);
+                        let mesh_handle = match mesh_component.lod_mesh_name(distance) {
+                            Some(name) => asset_manager.get_mesh(name),
+                            None => mesh_component.mesh_handle.clone(),
+                        };
+                        let asset_mesh = match mesh_handle.get() {
+                            Ok(asset_mesh) => asset_mesh,
+                            Err(_) => continue,
+                        };
+
+                        resource_manager
+                            .set_transform_bind_group(&mut render_pass, transform.index);
+                        for mesh in mesh_component.get_meshes(&asset_mesh).iter() {
+                            for sub_mesh in mesh.meshes.values() {
+                                render_pass.set_index_buffer(sub_mesh.index_buffer.clone());
+                                render_pass.set_vertex_buffer(
+                                    0,
+                                    sub_mesh.vertex_buffer.as_ref().unwrap().clone(),
+                                );
+                                render_pass.draw_indexed(0..sub_mesh.index_count as u32, 0, 0..1);
+                            }
+                        }
+                    }
+                }
+
+                command_buffer_queue
+                    .push(CommandQueueItem {
+                        buffer: encoder.finish(),
+                        name: "wireframe".to_string(),
+                        priority: RenderPriority::OPAQUE,
+                    })
+                    .unwrap();
+            },
+        )
+}
@@ -16,3 +16,4 @@ pub use assets::AssetManager;
 pub use winit_state::WinitState;
 
 pub struct TransformCount(u32);
+pub struct SkinCount(u32);
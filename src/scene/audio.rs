@@ -0,0 +1,156 @@
+use crate::assets::AudioClip;
+use legion::prelude::Entity;
+use rodio::{source::ChannelVolume, Source};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+// The left and right gains of a playing source, stored as f32 bits so the audio thread can read them without locking.
+struct StereoGains {
+    left: AtomicU32,
+    right: AtomicU32,
+}
+
+impl StereoGains {
+    fn new() -> Self {
+        Self {
+            left: AtomicU32::new(1.0f32.to_bits()),
+            right: AtomicU32::new(1.0f32.to_bits()),
+        }
+    }
+
+    fn set(&self, left: f32, right: f32) {
+        self.left.store(left.to_bits(), Ordering::Relaxed);
+        self.right.store(right.to_bits(), Ordering::Relaxed);
+    }
+}
+
+// Scales the left and right channels of a stereo source by its `StereoGains`.
+struct Balanced<S> {
+    input: S,
+    gains: Arc<StereoGains>,
+    // 0 for the left channel, 1 for the right.
+    channel: u16,
+}
+
+impl<S: Source<Item = f32>> Iterator for Balanced<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        let gain = if self.channel == 0 {
+            &self.gains.left
+        } else {
+            &self.gains.right
+        };
+        self.channel = (self.channel + 1) % 2;
+        Some(sample * f32::from_bits(gain.load(Ordering::Relaxed)))
+    }
+}
+
+impl<S: Source<Item = f32>> Source for Balanced<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+// The sink playing an entity's `SpatialAudioSource`, None if the clip couldn't be played.
+struct SpatialSink {
+    sink: Option<rodio::Sink>,
+    gains: Arc<StereoGains>,
+}
+
+/// Plays the clips of `SpatialAudioSource`s, each entity gets its own `rodio::Sink` the first time it's ticked.
+/// Without an audio device nothing is played.
+pub struct AudioManager {
+    handle: Option<rodio::OutputStreamHandle>,
+    sinks: HashMap<Entity, SpatialSink>,
+}
+
+impl AudioManager {
+    /// The `rodio::OutputStream` the handle belongs to has to be kept alive, audio stops when it's dropped.
+    pub fn new(handle: Option<rodio::OutputStreamHandle>) -> Self {
+        Self {
+            handle,
+            sinks: HashMap::new(),
+        }
+    }
+
+    /// Returns true while the entity's clip is playing, looping clips play until the entity is removed.
+    pub fn is_playing(&self, entity: Entity) -> bool {
+        self.sinks
+            .get(&entity)
+            .and_then(|spatial_sink| spatial_sink.sink.as_ref())
+            .map_or(false, |sink| !sink.empty())
+    }
+
+    pub(crate) fn has_sink(&self, entity: Entity) -> bool {
+        self.sinks.contains_key(&entity)
+    }
+
+    // Starts playing the clip for the entity, mixed down to mono so it can be panned.
+    // Failures are logged once and the entity stays silent.
+    pub(crate) fn play(&mut self, entity: Entity, clip: Option<&AudioClip>, looping: bool) {
+        let gains = Arc::new(StereoGains::new());
+        let sink = match (&self.handle, clip) {
+            (Some(handle), Some(clip)) => match (clip.decoder(), rodio::Sink::try_new(handle)) {
+                (Ok(decoder), Ok(sink)) => {
+                    let source: Box<dyn Source<Item = f32> + Send> = if looping {
+                        Box::new(decoder.convert_samples().repeat_infinite())
+                    } else {
+                        Box::new(decoder.convert_samples())
+                    };
+                    sink.append(Balanced {
+                        input: ChannelVolume::new(source, vec![1.0, 1.0]),
+                        gains: gains.clone(),
+                        channel: 0,
+                    });
+                    Some(sink)
+                }
+                (Err(error), _) => {
+                    log::error!("Couldn't decode audio clip: {}", error);
+                    None
+                }
+                (_, Err(error)) => {
+                    log::error!("Couldn't create audio sink: {}", error);
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        self.sinks.insert(entity, SpatialSink { sink, gains });
+    }
+
+    // Sets the volume and the left and right gains of the entity's clip.
+    pub(crate) fn set_spatial(&self, entity: Entity, volume: f32, left: f32, right: f32) {
+        if let Some(spatial_sink) = self.sinks.get(&entity) {
+            if let Some(sink) = &spatial_sink.sink {
+                sink.set_volume(volume);
+            }
+            spatial_sink.gains.set(left, right);
+        }
+    }
+
+    // Stops the clips of entities that don't match `keep`, dropping a sink stops it.
+    pub(crate) fn retain<F: FnMut(&Entity) -> bool>(&mut self, mut keep: F) {
+        self.sinks.retain(|entity, _| keep(entity));
+    }
+}
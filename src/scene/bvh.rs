@@ -0,0 +1,194 @@
+use crate::{
+    assets::mesh::Mesh,
+    core::{Aabb, FlatBvh},
+};
+use legion::prelude::Entity;
+use nalgebra_glm::{Mat4, Vec2, Vec3, Vec4};
+
+/// Where a ray cast with `Bvh::cast_ray` hit a mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitResult {
+    pub entity: Entity,
+    /// The distance from the ray's origin in world units.
+    pub distance: f32,
+    /// The world space normal of the triangle that was hit, facing the ray.
+    pub normal: Vec3,
+    /// The texture coordinates at the hit point.
+    pub uv: Vec2,
+}
+
+struct BvhTriangle {
+    entity: Entity,
+    positions: [Vec3; 3],
+    uvs: [Vec2; 3],
+}
+
+impl BvhTriangle {
+    // Möller–Trumbore, returns the distance and the barycentric coordinates of the second and third vertex.
+    fn intersect(&self, origin: &Vec3, direction: &Vec3) -> Option<(f32, f32, f32)> {
+        let edge1 = self.positions[1] - self.positions[0];
+        let edge2 = self.positions[2] - self.positions[0];
+        let p = direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < std::f32::EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let s = origin - self.positions[0];
+        let u = s.dot(&p) * inverse_determinant;
+        if u < 0.0 || u > 1.0 {
+            return None;
+        }
+        let q = s.cross(&edge1);
+        let v = direction.dot(&q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(&q) * inverse_determinant;
+        if distance > 0.0 {
+            Some((distance, u, v))
+        } else {
+            None
+        }
+    }
+}
+
+/// A bounding volume hierarchy over the world space triangles of every loaded `Mesh` and `Transform` entity, used for ray casts.
+/// Rebuilt by the `update_bvh` system whenever `BvhDirty` is set or meshes are added or removed.
+#[derive(Default)]
+pub struct Bvh {
+    tree: FlatBvh,
+    triangles: Vec<BvhTriangle>,
+    // How many entities the tree was built from, used to notice added and removed meshes.
+    pub(crate) entity_count: usize,
+}
+
+impl Bvh {
+    /// Removes every triangle, call `build` afterwards.
+    pub fn clear(&mut self) {
+        self.triangles.clear();
+        self.entity_count = 0;
+    }
+
+    /// Adds the triangles of every sub mesh, transformed into world space by `matrix`.
+    pub fn add_mesh(&mut self, entity: Entity, matrix: &Mat4, mesh: &Mesh) {
+        for sub_mesh in mesh.meshes.values() {
+            let positions: Vec<Vec3> = sub_mesh
+                .vertices
+                .iter()
+                .map(|vertex| {
+                    let position = vertex.position;
+                    (matrix * Vec4::new(position.x, position.y, position.z, 1.0)).xyz()
+                })
+                .collect();
+            for indices in sub_mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [
+                    indices[0] as usize,
+                    indices[1] as usize,
+                    indices[2] as usize,
+                ];
+                self.add_triangle(
+                    entity,
+                    [positions[a], positions[b], positions[c]],
+                    [
+                        sub_mesh.vertices[a].uv,
+                        sub_mesh.vertices[b].uv,
+                        sub_mesh.vertices[c].uv,
+                    ],
+                );
+            }
+        }
+    }
+
+    fn add_triangle(&mut self, entity: Entity, positions: [Vec3; 3], uvs: [Vec2; 3]) {
+        self.triangles.push(BvhTriangle {
+            entity,
+            positions,
+            uvs,
+        });
+    }
+
+    /// Builds the tree from the triangles added since the last `clear`.
+    pub fn build(&mut self) {
+        let bounds: Vec<Aabb> = self
+            .triangles
+            .iter()
+            .map(|triangle| Aabb::from_points(&triangle.positions))
+            .collect();
+        self.tree = FlatBvh::build(&bounds);
+    }
+
+    /// Returns the closest triangle the ray hits, `direction` doesn't need to be normalized.
+    pub fn cast_ray(&self, origin: Vec3, direction: Vec3) -> Option<HitResult> {
+        let direction = direction.normalize();
+        let (index, _) = self.tree.cast_ray(&origin, &direction, |index| {
+            self.triangles[index as usize]
+                .intersect(&origin, &direction)
+                .map(|(distance, _, _)| distance)
+        })?;
+
+        let triangle = &self.triangles[index as usize];
+        let (distance, u, v) = triangle.intersect(&origin, &direction)?;
+        let mut normal = (triangle.positions[1] - triangle.positions[0])
+            .cross(&(triangle.positions[2] - triangle.positions[0]))
+            .normalize();
+        if normal.dot(&direction) > 0.0 {
+            normal = -normal;
+        }
+        let uv = triangle.uvs[0] * (1.0 - u - v) + triangle.uvs[1] * u + triangle.uvs[2] * v;
+
+        Some(HitResult {
+            entity: triangle.entity,
+            distance,
+            normal,
+            uv,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bvh;
+    use legion::prelude::*;
+    use nalgebra_glm::{Vec2, Vec3};
+
+    #[test]
+    fn should_hit_closest_triangle() {
+        let universe = Universe::new();
+        let mut world = universe.create_world();
+        let entities = world.insert((), vec![(0u32,), (1u32,)]).to_vec();
+
+        // Two triangles facing the ray, the second one is further away.
+        let mut bvh = Bvh::default();
+        for (entity, z) in entities.iter().zip([1.0, 2.0].iter()) {
+            bvh.add_triangle(
+                *entity,
+                [
+                    Vec3::new(-1.0, -1.0, *z),
+                    Vec3::new(1.0, -1.0, *z),
+                    Vec3::new(-1.0, 1.0, *z),
+                ],
+                [
+                    Vec2::new(0.0, 0.0),
+                    Vec2::new(1.0, 0.0),
+                    Vec2::new(0.0, 1.0),
+                ],
+            );
+        }
+        bvh.build();
+
+        let hit = bvh
+            .cast_ray(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 2.0))
+            .unwrap();
+        assert_eq!(hit.entity, entities[0]);
+        assert_eq!(hit.distance, 2.0);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(hit.uv, Vec2::new(0.5, 0.5));
+
+        assert!(bvh
+            .cast_ray(Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, -1.0))
+            .is_none());
+    }
+}
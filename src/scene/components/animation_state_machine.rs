@@ -0,0 +1,180 @@
+use super::Animator;
+use legion::prelude::{Entity, World};
+use std::collections::HashMap;
+
+/// A clip the state machine can play.
+#[derive(Debug, Clone)]
+pub struct AnimationState {
+    /// Name of the `AnimationClip` in the mesh's gltf file.
+    pub clip: String,
+    pub speed: f32,
+    pub looping: bool,
+}
+
+impl AnimationState {
+    pub fn new<T: Into<String>>(clip: T) -> Self {
+        Self {
+            clip: clip.into(),
+            speed: 1.0,
+            looping: true,
+        }
+    }
+}
+
+/// Moves the state machine from `from` to `to` once `condition` returns true.
+pub struct Transition {
+    pub from: String,
+    pub to: String,
+    /// Called every frame while `from` is the current state with the world and the entity that owns the state machine.
+    pub condition: Box<dyn Fn(&World, Entity) -> bool + Send + Sync>,
+    /// Seconds spent blending from the old clip to the new one, 0.0 switches instantly.
+    pub blend_duration: f32,
+}
+
+impl Transition {
+    pub fn new<T: Into<String>, T2: Into<String>, F>(
+        from: T,
+        to: T2,
+        blend_duration: f32,
+        condition: F,
+    ) -> Self
+    where
+        F: Fn(&World, Entity) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            from: from.into(),
+            to: to.into(),
+            condition: Box::new(condition),
+            blend_duration,
+        }
+    }
+}
+
+// The clip being blended out of, it keeps playing until the blend finishes.
+#[derive(Debug, Clone)]
+pub(crate) struct AnimationBlend {
+    pub(crate) from: Animator,
+    pub(crate) elapsed: f32,
+    pub(crate) duration: f32,
+}
+
+impl AnimationBlend {
+    // Advances the old clip and the blend, returns the weight of the new clip.
+    pub(crate) fn advance(&mut self, delta_time: f32, from_duration: f32) -> f32 {
+        self.from.advance(delta_time, from_duration);
+        self.elapsed += delta_time;
+        (self.elapsed / self.duration).min(1.0)
+    }
+}
+
+/// Switches the entity's `Animator` between states, blending the skeleton's pose during transitions.
+/// The `Animator` should start out playing the current state's clip.
+pub struct AnimationStateMachine {
+    pub states: HashMap<String, AnimationState>,
+    /// Name of the state that is playing.
+    pub current: String,
+    /// Checked in order, the first transition out of the current state whose condition passes is taken.
+    pub transitions: Vec<Transition>,
+    pub(crate) blend: Option<AnimationBlend>,
+}
+
+impl AnimationStateMachine {
+    pub fn new<T: Into<String>>(current: T) -> Self {
+        Self {
+            states: HashMap::new(),
+            current: current.into(),
+            transitions: Vec::new(),
+            blend: None,
+        }
+    }
+
+    pub fn add_state<T: Into<String>>(&mut self, name: T, state: AnimationState) {
+        self.states.insert(name.into(), state);
+    }
+
+    pub fn add_transition(&mut self, transition: Transition) {
+        self.transitions.push(transition);
+    }
+
+    /// Returns true while the previous state's clip is being blended out.
+    pub fn is_blending(&self) -> bool {
+        self.blend.is_some()
+    }
+
+    // Returns the index of the first transition out of the current state whose condition passes.
+    pub(crate) fn find_transition(&self, world: &World, entity: Entity) -> Option<usize> {
+        self.transitions.iter().position(|transition| {
+            transition.from == self.current && (transition.condition)(world, entity)
+        })
+    }
+
+    // Makes the transition's target the current state and starts blending out of what the animator was playing.
+    pub(crate) fn start_transition(&mut self, index: usize, animator: &mut Animator) {
+        let transition = &self.transitions[index];
+        let state = match self.states.get(&transition.to) {
+            Some(state) => state,
+            None => {
+                log::warn!(
+                    "Animation state machine has no state named {}.",
+                    transition.to
+                );
+                return;
+            }
+        };
+
+        self.blend = if transition.blend_duration > 0.0 {
+            Some(AnimationBlend {
+                from: animator.clone(),
+                elapsed: 0.0,
+                duration: transition.blend_duration,
+            })
+        } else {
+            None
+        };
+        animator.play(state.clip.clone());
+        animator.speed = state.speed;
+        animator.looping = state.looping;
+        self.current = transition.to.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimationState, AnimationStateMachine, Transition};
+    use crate::scene::components::Animator;
+    use legion::prelude::Universe;
+
+    #[test]
+    fn should_transition_and_blend() {
+        let universe = Universe::new();
+        let mut world = universe.create_world();
+        let entity = world.insert((), vec![(0u32,)])[0];
+
+        let mut state_machine = AnimationStateMachine::new("idle");
+        state_machine.add_state("idle", AnimationState::new("idle"));
+        state_machine.add_state("walk", AnimationState::new("walk"));
+        state_machine.add_transition(Transition::new("walk", "idle", 0.5, |_, _| true));
+        state_machine.add_transition(Transition::new("idle", "walk", 0.5, |world, entity| {
+            world
+                .get_component::<u32>(entity)
+                .map_or(false, |speed| *speed > 0)
+        }));
+        assert_eq!(state_machine.find_transition(&world, entity), None);
+
+        *world.get_component_mut::<u32>(entity).unwrap() = 1;
+        let index = state_machine.find_transition(&world, entity).unwrap();
+        let mut animator = Animator::new("idle");
+        animator.time = 0.25;
+        state_machine.start_transition(index, &mut animator);
+
+        assert_eq!(state_machine.current, "walk");
+        assert_eq!(animator.clip, "walk");
+        assert_eq!(animator.time, 0.0);
+
+        let blend = state_machine.blend.as_mut().unwrap();
+        assert_eq!(blend.from.clip, "idle");
+        assert_eq!(blend.advance(0.25, 1.0), 0.5);
+        assert_eq!(blend.from.time, 0.5);
+        assert_eq!(blend.advance(0.5, 1.0), 1.0);
+    }
+}
@@ -0,0 +1,57 @@
+/// Plays an animation from the entity's mesh on its `SkinnedMesh`.
+#[derive(Debug, Clone)]
+pub struct Animator {
+    /// Name of the `AnimationClip` in the mesh's gltf file.
+    pub clip: String,
+    /// Playback position in seconds.
+    pub time: f32,
+    /// Multiplies the delta time, 1.0 plays the clip at its normal speed.
+    pub speed: f32,
+    /// If false the clip holds its last frame once it reaches the end.
+    pub looping: bool,
+}
+
+impl Animator {
+    pub fn new<T: Into<String>>(clip: T) -> Self {
+        Self {
+            clip: clip.into(),
+            time: 0.0,
+            speed: 1.0,
+            looping: true,
+        }
+    }
+
+    /// Switches to another clip and starts it from the beginning.
+    pub fn play<T: Into<String>>(&mut self, clip: T) {
+        self.clip = clip.into();
+        self.time = 0.0;
+    }
+
+    // Moves the playback position forward, wrapping or clamping it to the clip's duration.
+    pub(crate) fn advance(&mut self, delta_time: f32, duration: f32) {
+        self.time += delta_time * self.speed;
+        if duration <= 0.0 {
+            self.time = 0.0;
+        } else if self.looping {
+            self.time = self.time.rem_euclid(duration);
+        } else {
+            self.time = self.time.max(0.0).min(duration);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Animator;
+
+    #[test]
+    fn should_wrap_or_clamp_time() {
+        let mut animator = Animator::new("walk");
+        animator.advance(2.5, 2.0);
+        assert_eq!(animator.time, 0.5);
+
+        animator.looping = false;
+        animator.advance(2.5, 2.0);
+        assert_eq!(animator.time, 2.0);
+    }
+}
@@ -0,0 +1,194 @@
+use super::Transform;
+use crate::graphics::resources::RenderTargetHandle;
+use nalgebra_glm::{Mat4, Vec3};
+
+/// How the camera projects the world onto the screen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Projection {
+    /// fov_y is the vertical field of view in degrees.
+    Perspective { fov_y: f32, near: f32, far: f32 },
+    /// width and height are the size of the view box in world units.
+    Orthographic {
+        width: f32,
+        height: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+/// Whether a camera renders the 3D scene or 2D sprites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    Mode3D,
+    /// Sprites are only drawn for 2D cameras, which should use an orthographic projection.
+    Mode2D,
+}
+
+impl Default for CameraMode {
+    fn default() -> Self {
+        CameraMode::Mode3D
+    }
+}
+
+/// A camera component. The view is calculated from the entity's `Transform` by the `update_camera` system.
+/// Note: Only the entity stored in the `ActiveCamera` resource is rendered from.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub projection: Projection,
+    pub mode: CameraMode,
+    /// Width divided by height of the viewport, only used by perspective projections.
+    pub aspect_ratio: f32,
+    pub view: Mat4,
+    pub position: Vec3,
+    /// When set the camera renders into this texture instead of the screen.
+    /// Only the forward path supports this and msaa has to be off, render targets aren't multisampled.
+    pub render_target: Option<RenderTargetHandle>,
+}
+
+impl Camera {
+    pub fn new(projection: Projection, width: f32, height: f32) -> Self {
+        Self {
+            projection,
+            mode: CameraMode::default(),
+            aspect_ratio: width / height,
+            view: Mat4::identity(),
+            position: Vec3::zeros(),
+            render_target: None,
+        }
+    }
+
+    /// Needs to be called when the window resizes.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.aspect_ratio = width / height;
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y, near, far } => {
+                nalgebra_glm::perspective_lh_no(self.aspect_ratio, fov_y.to_radians(), near, far)
+            }
+            Projection::Orthographic {
+                width,
+                height,
+                near,
+                far,
+            } => nalgebra_glm::ortho_lh_no(
+                -0.5 * width,
+                0.5 * width,
+                -0.5 * height,
+                0.5 * height,
+                near,
+                far,
+            ),
+        }
+    }
+
+    /// Returns the view-projection matrix.
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection_matrix() * self.view
+    }
+
+    /// Calculates the view matrix from the transform's position and rotation. Scale is ignored.
+    pub fn update_view(&mut self, transform: &Transform) {
+        let rotation = nalgebra_glm::quat_to_mat4(&nalgebra_glm::quat_conjugate(&transform.rotation));
+        self.view = rotation * nalgebra_glm::translation(&-transform.position);
+        self.position = transform.position;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Camera, Projection};
+    use crate::scene::components::Transform;
+    use nalgebra_glm::{Mat4, Quat, Vec3, Vec4};
+
+    // Projects a world space point into normalized device coordinates.
+    fn to_ndc(camera: &Camera, point: Vec3) -> Vec3 {
+        let clip = camera.view_projection() * Vec4::new(point.x, point.y, point.z, 1.0);
+        Vec3::new(clip.x, clip.y, clip.z) / clip.w
+    }
+
+    fn assert_near(actual: Vec3, expected: Vec3) {
+        assert!(
+            (actual - expected).norm() < 1e-4,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn should_project_perspective_frustum_to_ndc() {
+        let mut camera = Camera::new(
+            Projection::Perspective {
+                fov_y: 90.0,
+                near: 0.1,
+                far: 100.0,
+            },
+            800.0,
+            400.0,
+        );
+        // Left handed, the camera looks down +z.
+        assert_near(to_ndc(&camera, Vec3::new(0.0, 0.0, 0.1)), Vec3::new(0.0, 0.0, -1.0));
+        assert_near(to_ndc(&camera, Vec3::new(0.0, 0.0, 100.0)), Vec3::new(0.0, 0.0, 1.0));
+        // With a 90 degree fov the top of the frustum is as far up as it's away, the sides are twice that.
+        let corner = to_ndc(&camera, Vec3::new(20.0, 10.0, 10.0));
+        assert_near(Vec3::new(corner.x, corner.y, 0.0), Vec3::new(1.0, 1.0, 0.0));
+
+        // The view moves the near plane along with the camera.
+        camera.update_view(&Transform {
+            index: 0,
+            position: Vec3::new(0.0, 0.0, -5.0),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            rotation: Quat::identity(),
+            matrix: Mat4::identity(),
+            cull: false,
+        });
+        assert_near(to_ndc(&camera, Vec3::new(0.0, 0.0, -4.9)), Vec3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn should_project_orthographic_box_to_ndc() {
+        let camera = Camera::new(
+            Projection::Orthographic {
+                width: 10.0,
+                height: 5.0,
+                near: 0.1,
+                far: 100.0,
+            },
+            800.0,
+            600.0,
+        );
+        // The aspect ratio doesn't change the size of the view box.
+        assert_near(to_ndc(&camera, Vec3::new(5.0, 2.5, 0.1)), Vec3::new(1.0, 1.0, -1.0));
+        assert_near(to_ndc(&camera, Vec3::new(-5.0, -2.5, 100.0)), Vec3::new(-1.0, -1.0, 1.0));
+        assert_near(to_ndc(&camera, Vec3::new(0.0, 0.0, 50.05)), Vec3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_calculate_view_from_transform() {
+        let mut camera = Camera::new(
+            Projection::Perspective {
+                fov_y: 70.0,
+                near: 0.1,
+                far: 100.0,
+            },
+            800.0,
+            600.0,
+        );
+        let transform = Transform {
+            index: 0,
+            position: Vec3::new(0.0, 0.0, 5.0),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            rotation: Quat::identity(),
+            matrix: Mat4::identity(),
+            cull: false,
+        };
+        camera.update_view(&transform);
+        // The camera's position should end up at the origin in view space.
+        assert_eq!(
+            camera.view * Vec4::new(0.0, 0.0, 5.0, 1.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0)
+        );
+    }
+}
@@ -1,8 +1,8 @@
 use nalgebra_glm::{Mat4, Vec3};
 use crate::core::Frustum;
 
-#[derive(Clone)]
-enum ProjectionData {
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum ProjectionData {
     Perspective {
         fov: f32,
         z_near: f32,
@@ -173,6 +173,10 @@ impl CameraData {
         }
     }
 
+    pub(crate) fn projection_data(&self) -> &ProjectionData {
+        &self.projection_data
+    }
+
     /// resize recalculates the projection matrix. Needs to be called on window resize
     pub fn resize(&mut self, width: f32, height: f32) {
         self.projection = self.projection_data.get_projection(width, height);
@@ -0,0 +1,30 @@
+use nalgebra_glm::Vec3;
+
+/// A texture projected onto the geometry inside a box, drawn by the `render_decals` system.
+/// The box is centered on the entity's `Transform` and the texture is projected along it's negative z axis.
+/// Only surfaces whose material has `receive_decals` set are covered when deferred rendering is on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decal {
+    /// Path to the texture, relative to the asset folder.
+    pub texture: String,
+    /// Path to a tangent space normal map blended into the surface's normals, relative to the asset folder.
+    /// Note: Only used by deferred rendering.
+    pub normal_texture: Option<String>,
+    /// The width, height and depth of the box in world units, before the transform's scale.
+    pub size: Vec3,
+    /// The distance from the camera at which the decal has faded out, it starts fading at three quarters of it.
+    /// Zero never fades.
+    pub fade_distance: f32,
+}
+
+impl Decal {
+    /// Creates a decal that never fades.
+    pub fn new<T: Into<String>>(texture: T, size: Vec3) -> Self {
+        Self {
+            texture: texture.into(),
+            normal_texture: None,
+            size,
+            fade_distance: 0.0,
+        }
+    }
+}
@@ -0,0 +1,3 @@
+/// Draws the triangles of an entity's `Mesh` as lines over it's normal material, used to inspect mesh topology.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DrawWireframe;
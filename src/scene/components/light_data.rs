@@ -6,6 +6,8 @@ pub enum LightType {
     Directional(DirectionalLightData),
     /// Point Light
     Point(PointLightData),
+    /// Spot Light
+    Spot(SpotLightData),
 }
 
 /// Directional light information
@@ -16,6 +18,9 @@ pub struct DirectionalLightData {
     pub color: Vec3,
     /// Light intensity
     pub intensity: f32,
+    /// Renders cascaded shadow maps for the light, configured by the `CsmConfig` resource.
+    /// Only the first shadow casting directional light is used.
+    pub cast_shadow: bool,
 }
 
 impl Default for DirectionalLightData {
@@ -24,14 +29,13 @@ impl Default for DirectionalLightData {
             direction: Vec3::zeros(),
             color: Vec3::zeros(),
             intensity: 10.0,
+            cast_shadow: false,
         }
     }
 }
 
 /// Point light information
 /// Position is defined by the transform.
-/// Currently point lights do not render.
-/// TODO: Fix point lighting rendering.
 pub struct PointLightData {
     /// Color of the light.
     pub color: Vec3,
@@ -39,8 +43,10 @@ pub struct PointLightData {
     pub attenuation: f32,
     /// Light intensity
     pub intensity: f32,
-    // Will generate a shadow map.
-    pub shadow: bool,
+    /// Renders the scene's depth into a cube shadow map around the light, off by default.
+    /// Only the `MAX_SHADOW_CASTERS_PER_FRAME` most important casters are updated each frame,
+    /// where importance is `attenuation / distance² * (age + 1)`.
+    pub cast_shadow: bool,
     // Auto calculated by the omni shadow manager.
     pub(crate) shadow_texture_id: (u32, u32),
     // The age of the shadow map in frames.
@@ -53,7 +59,7 @@ impl Default for PointLightData {
             color: Vec3::zeros(),
             attenuation: 0.0,
             intensity: 10.0,
-            shadow: false,
+            cast_shadow: false,
             shadow_texture_id: (0, 0),
             age: 0,
         }
@@ -61,14 +67,64 @@ impl Default for PointLightData {
 }
 
 impl PointLightData {
-    pub fn new(color: Vec3, attenuation: f32, intensity: f32, shadow: bool) -> Self {
+    pub fn new(color: Vec3, attenuation: f32, intensity: f32, cast_shadow: bool) -> Self {
         Self {
             color,
             attenuation,
             intensity,
-            shadow,
+            cast_shadow,
             shadow_texture_id: (0, 0),
             age: 0,
         }
     }
 }
+
+/// Spot light information
+/// Position is defined by the transform.
+pub struct SpotLightData {
+    /// The direction the light is pointing.
+    pub direction: Vec3,
+    /// Color of the light.
+    pub color: Vec3,
+    /// Light intensity
+    pub intensity: f32,
+    /// Angle in degrees from the direction where the light starts to fade out.
+    pub inner_angle: f32,
+    /// Angle in degrees from the direction where the light is fully faded out.
+    pub outer_angle: f32,
+    /// The distance the light reaches.
+    pub range: f32,
+}
+
+impl Default for SpotLightData {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            color: Vec3::zeros(),
+            intensity: 10.0,
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+            range: 10.0,
+        }
+    }
+}
+
+impl SpotLightData {
+    pub fn new(
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+        range: f32,
+    ) -> Self {
+        Self {
+            direction,
+            color,
+            intensity,
+            inner_angle,
+            outer_angle,
+            range,
+        }
+    }
+}
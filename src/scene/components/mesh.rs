@@ -5,10 +5,81 @@ use std::sync::Arc;
 #[derive(PartialEq, Clone)]
 pub struct Mesh {
     pub mesh_handle: Arc<AssetHandle<Gltf>>,
+    /// Which mesh inside of the gltf file to render. If None every mesh in the file is rendered.
+    pub mesh_index: Option<usize>,
+    /// The camera distance up to which each lod is used. Index 0 is the closest.
+    pub lod_distances: Vec<f32>,
+    /// The mesh to render for each entry in `lod_distances`. If empty `mesh_handle` is always rendered.
+    pub lod_mesh_names: Vec<String>,
 }
 
 impl Mesh {
     pub fn new(mesh_handle: Arc<AssetHandle<Gltf>>) -> Self {
-        Self { mesh_handle }
+        Self {
+            mesh_handle,
+            mesh_index: None,
+            lod_distances: Vec::new(),
+            lod_mesh_names: Vec::new(),
+        }
+    }
+
+    /// Creates a mesh component that only renders a single mesh from the gltf file.
+    pub fn new_with_index(mesh_handle: Arc<AssetHandle<Gltf>>, mesh_index: usize) -> Self {
+        Self {
+            mesh_handle,
+            mesh_index: Some(mesh_index),
+            lod_distances: Vec::new(),
+            lod_mesh_names: Vec::new(),
+        }
+    }
+
+    /// Picks which lod mesh to render at the given distance from the camera.
+    /// Past the last distance the last lod is used. Returns None if no lods are set.
+    pub fn lod_mesh_name(&self, distance: f32) -> Option<&str> {
+        let lod_count = self.lod_distances.len().min(self.lod_mesh_names.len());
+        if lod_count == 0 {
+            return None;
+        }
+        let index = self.lod_distances[..lod_count]
+            .iter()
+            .position(|lod_distance| distance <= *lod_distance)
+            .unwrap_or(lod_count - 1);
+        Some(&self.lod_mesh_names[index])
+    }
+
+    /// Returns the meshes from the gltf that this component references.
+    pub fn get_meshes<'a>(&self, gltf: &'a Gltf) -> &'a [crate::assets::mesh::Mesh] {
+        match self.mesh_index {
+            Some(index) => &gltf.meshes[index..index + 1],
+            None => &gltf.meshes[..],
+        }
+    }
+
+    /// Returns the bounding sphere for the meshes this component references.
+    pub fn get_bounding_sphere(&self, gltf: &Gltf) -> crate::core::BoundingSphere {
+        match self.mesh_index {
+            Some(index) => gltf.meshes[index].bounding_sphere,
+            None => gltf.bounding_sphere,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mesh;
+    use crate::assets::{AssetCache, AssetHandle};
+    use std::sync::Arc;
+
+    #[test]
+    fn should_pick_lod_by_distance() {
+        let cache: AssetCache<_> = Arc::new(dashmap::DashMap::new());
+        let mut mesh = Mesh::new(Arc::new(AssetHandle::new("tree.gltf".into(), cache)));
+        assert_eq!(mesh.lod_mesh_name(10.0), None);
+
+        mesh.lod_distances = vec![10.0, 50.0];
+        mesh.lod_mesh_names = vec!["tree.gltf".to_string(), "tree.gltf#lod0".to_string()];
+        assert_eq!(mesh.lod_mesh_name(5.0), Some("tree.gltf"));
+        assert_eq!(mesh.lod_mesh_name(20.0), Some("tree.gltf#lod0"));
+        assert_eq!(mesh.lod_mesh_name(100.0), Some("tree.gltf#lod0"));
     }
 }
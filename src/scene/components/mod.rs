@@ -4,9 +4,69 @@ pub use mesh::Mesh;
 pub(crate) mod transform;
 pub use transform::Transform;
 
+pub(crate) mod previous_transform;
+pub use previous_transform::PreviousTransform;
+
 pub(crate) mod camera_data;
 pub use camera_data::CameraData;
 
+pub(crate) mod camera;
+pub use camera::{Camera, CameraMode, Projection};
+
+pub(crate) mod scissor_rect;
+pub use scissor_rect::ScissorRect;
+
+pub(crate) mod skinned_mesh;
+pub use skinned_mesh::SkinnedMesh;
+
+pub(crate) mod morph_target_weights;
+pub use morph_target_weights::MorphTargetWeights;
+
+pub(crate) mod animator;
+pub use animator::Animator;
+
+pub(crate) mod animation_state_machine;
+pub use animation_state_machine::{AnimationState, AnimationStateMachine, Transition};
+
+pub(crate) mod terrain;
+pub use terrain::Terrain;
+
+pub(crate) mod particle_system;
+pub use particle_system::{EmitterShape, Gradient, ParticleSystem};
+
+pub(crate) mod ocean_surface;
+pub use ocean_surface::{OceanSurface, MAX_OCEAN_RESOLUTION, MIN_OCEAN_RESOLUTION};
+
+pub(crate) mod velocity_field;
+pub use velocity_field::{VelocityField, VELOCITY_FIELD_FORMAT};
+
+pub(crate) mod sprite;
+pub use sprite::Sprite;
+
+pub(crate) mod decal;
+pub use decal::Decal;
+
+pub(crate) mod text_renderer;
+pub use text_renderer::{TextRenderer, TextSpace};
+
+pub(crate) mod voxel_chunk;
+pub use voxel_chunk::VoxelChunk;
+
+pub(crate) mod draw_wireframe;
+pub use draw_wireframe::DrawWireframe;
+
+pub(crate) mod tween;
+pub use tween::{EasingFn, Lerp, Tween};
+
+pub(crate) mod spline_follower;
+pub use spline_follower::SplineFollower;
+
+pub(crate) mod spatial_audio_source;
+pub use spatial_audio_source::SpatialAudioSource;
+
+pub(crate) mod uv_animation;
+pub use uv_animation::UvAnimation;
+
 pub(crate) mod material;
 pub use material::Material;
 
@@ -18,3 +78,6 @@ pub use light_data::*;
 
 pub(crate) mod probe;
 pub use probe::*;
+
+pub(crate) mod reflection_probe;
+pub use reflection_probe::ReflectionProbe;
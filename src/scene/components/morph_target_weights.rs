@@ -0,0 +1,5 @@
+/// Overrides the morph target weights of the entity's `Mesh`, one weight per target.
+/// Missing weights are treated as 0. Without this component the weights stored on the mesh asset are used.
+/// Note: Like skinning the blended vertices are written back into the mesh asset so entities sharing a mesh share a shape.
+#[derive(Debug, Clone, Default)]
+pub struct MorphTargetWeights(pub Vec<f32>);
@@ -0,0 +1,61 @@
+use crate::graphics::resources::OceanBuffers;
+use nalgebra_glm::Vec2;
+use std::sync::Arc;
+
+/// The smallest and largest FFT the ocean is simulated with.
+pub const MIN_OCEAN_RESOLUTION: u32 = 16;
+pub const MAX_OCEAN_RESOLUTION: u32 = 512;
+
+/// A square patch of ocean centered on the entity's `Transform`, the waves tile so patches can be placed side by side.
+/// The waves are simulated on the GPU with an FFT by the `simulate_ocean` system and drawn by `render_water`.
+pub struct OceanSurface {
+    /// The width of the patch in world units.
+    pub size: f32,
+    /// How many samples the FFT and the grid have along each side, rounded up to a power of two.
+    pub resolution: u32,
+    /// Scales the height of the waves.
+    pub amplitude: f32,
+    /// The direction the wind blows in on the xz plane, it's length is the wind speed.
+    /// Faster winds create longer waves.
+    pub wind_direction: Vec2,
+    // Seconds since the ocean started.
+    pub(crate) time: f32,
+    // Created by the `simulate_ocean` system and recreated when any of the settings change.
+    pub(crate) buffers: Option<Arc<OceanBuffers>>,
+}
+
+impl OceanSurface {
+    pub fn new(size: f32, resolution: u32, amplitude: f32, wind_direction: Vec2) -> Self {
+        Self {
+            size,
+            resolution,
+            amplitude,
+            wind_direction,
+            time: 0.0,
+            buffers: None,
+        }
+    }
+
+    /// The size of the FFT, `resolution` clamped and rounded up to a power of two.
+    pub fn fft_size(&self) -> u32 {
+        self.resolution
+            .max(MIN_OCEAN_RESOLUTION)
+            .min(MAX_OCEAN_RESOLUTION)
+            .next_power_of_two()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OceanSurface;
+    use nalgebra_glm::Vec2;
+
+    #[test]
+    fn should_round_fft_size() {
+        let ocean = |resolution| OceanSurface::new(100.0, resolution, 1.0, Vec2::new(10.0, 0.0));
+        assert_eq!(ocean(0).fft_size(), 16);
+        assert_eq!(ocean(100).fft_size(), 128);
+        assert_eq!(ocean(256).fft_size(), 256);
+        assert_eq!(ocean(4096).fft_size(), 512);
+    }
+}
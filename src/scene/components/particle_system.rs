@@ -0,0 +1,120 @@
+use crate::graphics::resources::ParticleBuffers;
+use nalgebra_glm::{Vec3, Vec4};
+use std::sync::Arc;
+
+/// Where new particles are placed relative to the entity's `Transform`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterShape {
+    /// Emits from the origin in every direction.
+    Point,
+    /// Emits from inside a sphere, moving away from it's center.
+    Sphere { radius: f32 },
+    /// Emits from inside a box, moving up along y.
+    Box { half_extents: Vec3 },
+    /// Emits from a disc on the xz plane, moving up along y and spreading out by up to `angle` degrees.
+    Cone { angle: f32, radius: f32 },
+}
+
+/// Colors placed along a particle's lifetime, 0.0 is when it's emitted and 1.0 is when it dies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    /// Sorted by position.
+    keys: Vec<(f32, Vec4)>,
+}
+
+impl Gradient {
+    /// Keys are sorted by position, positions are clamped to 0..1.
+    pub fn new(mut keys: Vec<(f32, Vec4)>) -> Self {
+        for key in keys.iter_mut() {
+            key.0 = key.0.max(0.0).min(1.0);
+        }
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Self { keys }
+    }
+
+    /// Fades between two colors over the whole lifetime.
+    pub fn linear(from: Vec4, to: Vec4) -> Self {
+        Self::new(vec![(0.0, from), (1.0, to)])
+    }
+
+    /// The color at `position`. Positions before the first or after the last key use that key's color.
+    pub fn sample(&self, position: f32) -> Vec4 {
+        let next = self.keys.iter().position(|(key, _)| *key > position);
+        match next {
+            None => self
+                .keys
+                .last()
+                .map_or(Vec4::new(1.0, 1.0, 1.0, 1.0), |key| key.1),
+            Some(0) => self.keys[0].1,
+            Some(next) => {
+                let (start, from) = self.keys[next - 1];
+                let (end, to) = self.keys[next];
+                nalgebra_glm::lerp(&from, &to, (position - start) / (end - start))
+            }
+        }
+    }
+}
+
+impl Default for Gradient {
+    /// White particles that fade out.
+    fn default() -> Self {
+        Self::linear(Vec4::new(1.0, 1.0, 1.0, 1.0), Vec4::new(1.0, 1.0, 1.0, 0.0))
+    }
+}
+
+/// Particles simulated and drawn entirely on the GPU, emitted from the entity's `Transform`.
+/// Each particle is emitted again when it dies, so `max_particles / lifetime` particles are emitted a second.
+/// Simulated by the `simulate_particles` system and drawn as camera facing quads by `render_particles`.
+pub struct ParticleSystem {
+    pub max_particles: u32,
+    pub emitter: EmitterShape,
+    /// How long each particle lives in seconds.
+    pub lifetime: f32,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub color_over_lifetime: Gradient,
+    /// The speed particles are emitted with.
+    pub start_speed: f32,
+    pub gravity: Vec3,
+    /// The fraction of a particle's velocity lost each second.
+    pub drag: f32,
+    // Seconds since the system started, used to seed the random emission.
+    pub(crate) time: f32,
+    // Created by the `simulate_particles` system and recreated if `max_particles` changes.
+    pub(crate) buffers: Option<Arc<ParticleBuffers>>,
+}
+
+impl ParticleSystem {
+    pub fn new(max_particles: u32, emitter: EmitterShape, lifetime: f32) -> Self {
+        Self {
+            max_particles,
+            emitter,
+            lifetime,
+            start_size: 0.1,
+            end_size: 0.1,
+            color_over_lifetime: Gradient::default(),
+            start_speed: 1.0,
+            gravity: Vec3::new(0.0, -9.81, 0.0),
+            drag: 0.0,
+            time: 0.0,
+            buffers: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Gradient;
+    use nalgebra_glm::Vec4;
+
+    #[test]
+    fn should_sample_gradient() {
+        let red = Vec4::new(1.0, 0.0, 0.0, 1.0);
+        let blue = Vec4::new(0.0, 0.0, 1.0, 1.0);
+        let gradient = Gradient::new(vec![(0.75, blue), (0.25, red)]);
+
+        assert_eq!(gradient.sample(0.0), red);
+        assert_eq!(gradient.sample(0.5), Vec4::new(0.5, 0.0, 0.5, 1.0));
+        assert_eq!(gradient.sample(1.0), blue);
+    }
+}
@@ -0,0 +1,18 @@
+use super::Transform;
+use nalgebra_glm::Mat4;
+
+/// The world matrix an entity's `Transform` had last frame, used to render motion vectors.
+/// Added to every mesh entity and updated at the end of each frame by the `update_previous_transforms` system.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreviousTransform {
+    pub matrix: Mat4,
+}
+
+impl PreviousTransform {
+    /// Starts out at the transform's current matrix so the entity doesn't appear to move on it's first frame.
+    pub fn new(transform: &Transform) -> Self {
+        Self {
+            matrix: transform.matrix,
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use nalgebra_glm::Vec3;
+
+/// Captures the scene around a point so nearby meshes get local reflections and ambient lighting
+/// instead of the skybox's. The capture is baked once and again whenever `dirty` is set.
+/// Meshes use the closest probe whose radius they're inside of, at most eight probes are used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflectionProbe {
+    /// Where the scene is captured from in world space.
+    pub position: Vec3,
+    /// Meshes further away than this from `position` don't use the probe.
+    pub radius: f32,
+    /// The width and height of each face of the captured cube map, rounded to the closest `ProbeQuality`.
+    pub resolution: u32,
+    /// Set to recapture the probe, for example after the scene around it changed.
+    pub dirty: bool,
+}
+
+impl ReflectionProbe {
+    /// Creates a low resolution probe that's captured on the next frame.
+    pub fn new(position: Vec3, radius: f32) -> Self {
+        Self {
+            position,
+            radius,
+            resolution: 512,
+            dirty: true,
+        }
+    }
+}
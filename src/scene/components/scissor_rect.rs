@@ -0,0 +1,208 @@
+/// Limits the mesh passes of the camera entity it's attached to to a rectangle of the render target, in pixels.
+/// Useful for portals or clipping the scene to part of the screen. Only the active camera's rect is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScissorRect {
+    /// The left edge, from the left of the render target.
+    pub x: u32,
+    /// The top edge, from the top of the render target.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScissorRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The part of the rect inside a `target_width` by `target_height` render target as (x, y, width, height).
+    /// wgpu rejects scissor rects outside the target, None means nothing is visible.
+    pub fn clamp(&self, target_width: u32, target_height: u32) -> Option<(u32, u32, u32, u32)> {
+        let x = self.x.min(target_width);
+        let y = self.y.min(target_height);
+        let width = self.width.min(target_width - x);
+        let height = self.height.min(target_height - y);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some((x, y, width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScissorRect;
+    use crate::graphics::resources::ReadbackBuffer;
+    use std::borrow::Cow;
+
+    const SIZE: u32 = 64;
+
+    const VERTEX_SHADER: &str = "#version 450
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}";
+
+    const FRAGMENT_SHADER: &str = "#version 450
+layout(location = 0) out vec4 color;
+void main() {
+    color = vec4(1.0);
+}";
+
+    #[test]
+    fn should_clamp_to_target() {
+        let rect = ScissorRect::new(10, 20, 100, 100);
+        assert_eq!(rect.clamp(50, 200), Some((10, 20, 40, 100)));
+        assert_eq!(rect.clamp(200, 200), Some((10, 20, 100, 100)));
+        assert_eq!(rect.clamp(10, 200), None);
+        assert_eq!(ScissorRect::new(0, 0, 0, 10).clamp(10, 10), None);
+    }
+
+    #[test]
+    fn should_not_draw_outside_scissor_rect() {
+        let (device, queue) = async_std::task::block_on(async {
+            let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::Default,
+                    compatible_surface: None,
+                })
+                .await
+                .unwrap();
+
+            adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        features: wgpu::Features::empty(),
+                        limits: wgpu::Limits::default(),
+                        shader_validation: true,
+                    },
+                    None,
+                )
+                .await
+                .unwrap()
+        });
+
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let mut compile = |source: &str, kind: shaderc::ShaderKind| {
+            let spirv = compiler
+                .compile_into_spirv(source, kind, "scissor_test.glsl", "main", None)
+                .unwrap();
+            device.create_shader_module(wgpu::ShaderModuleSource::SpirV(Cow::Owned(
+                spirv.as_binary().to_vec(),
+            )))
+        };
+        let vertex_module = compile(VERTEX_SHADER, shaderc::ShaderKind::Vertex);
+        let fragment_module = compile(FRAGMENT_SHADER, shaderc::ShaderKind::Fragment);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: Cow::Borrowed(&[]),
+            push_constant_ranges: Cow::Borrowed(&[]),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: &pipeline_layout,
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: &vertex_module,
+                entry_point: Cow::Borrowed("main"),
+            },
+            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+                module: &fragment_module,
+                entry_point: Cow::Borrowed("main"),
+            }),
+            rasterization_state: None,
+            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+            color_states: Cow::Borrowed(&[wgpu::ColorStateDescriptor {
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                color_blend: wgpu::BlendDescriptor::REPLACE,
+                alpha_blend: wgpu::BlendDescriptor::REPLACE,
+                write_mask: wgpu::ColorWrite::ALL,
+            }]),
+            depth_stencil_state: None,
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: wgpu::IndexFormat::Uint16,
+                vertex_buffers: Cow::Borrowed(&[]),
+            },
+            sample_count: 1,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        });
+
+        let extent = wgpu::Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = texture.create_default_view();
+
+        // Copied through a buffer since the readback buffer copies from buffers.
+        let pixels = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (SIZE * SIZE * 4) as u64,
+            usage: wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = ReadbackBuffer::new(&device, "scissor_readback", (SIZE * SIZE * 4) as u64);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: Cow::Borrowed(&[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }]),
+                depth_stencil_attachment: None,
+            });
+            // Only the left half of the full screen triangle is drawn.
+            let (x, y, width, height) = ScissorRect::new(0, 0, SIZE / 2, SIZE * 2)
+                .clamp(SIZE, SIZE)
+                .unwrap();
+            render_pass.set_scissor_rect(x, y, width, height);
+            render_pass.set_pipeline(&pipeline);
+            render_pass.draw(0..3, 0..1);
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &pixels,
+                layout: wgpu::TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: SIZE * 4,
+                    rows_per_image: SIZE,
+                },
+            },
+            extent,
+        );
+        readback.request_read(&mut encoder, &pixels);
+        queue.submit(Some(encoder.finish()));
+
+        let data = readback.map_blocking(&device);
+        let pixel = |x: u32, y: u32| data[((y * SIZE + x) * 4) as usize];
+        assert_eq!(pixel(10, 10), 255);
+        assert_eq!(pixel(SIZE / 2 - 1, SIZE - 1), 255);
+        assert_eq!(pixel(SIZE / 2, 10), 0);
+        assert_eq!(pixel(SIZE - 1, SIZE - 1), 0);
+    }
+}
@@ -0,0 +1,27 @@
+use crate::{assets::skeleton::Skeleton, graphics::resources::GPUResourceManager, Application, SkinCount};
+use std::sync::Arc;
+
+/// Deforms the entity's `Mesh` on the GPU using the joints of the skeleton.
+/// Note: The deformed vertices are written back into the mesh asset so entities sharing a mesh share a pose.
+#[derive(Debug, Clone)]
+pub struct SkinnedMesh {
+    /// Index of the joint buffer used internally.
+    pub(crate) index: u32,
+    pub skeleton: Skeleton,
+}
+
+impl SkinnedMesh {
+    pub fn new(app: &mut Application, skeleton: Skeleton) -> Self {
+        let mut index = app.resources.get_mut::<SkinCount>().unwrap();
+        index.0 += 1;
+
+        let resource_manager = app.resources.get::<Arc<GPUResourceManager>>().unwrap();
+        let device = app.resources.get::<Arc<wgpu::Device>>().unwrap();
+        resource_manager.add_joint_buffer(&device, index.0);
+
+        Self {
+            index: index.0,
+            skeleton,
+        }
+    }
+}
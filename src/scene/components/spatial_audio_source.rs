@@ -0,0 +1,26 @@
+/// Plays an audio clip from the entity's `Transform`.
+/// The clip is panned and attenuated relative to the `ActiveCamera` by the `tick_spatial_audio` system.
+/// The clip starts when the entity is first ticked and stops when the component is removed.
+#[derive(Debug, Clone)]
+pub struct SpatialAudioSource {
+    /// Path of an `.ogg` or `.wav` file in the asset directory, see `AssetManager::load_audio`.
+    pub asset: String,
+    pub volume: f32,
+    /// Sources further than this from the listener are silent.
+    pub max_distance: f32,
+    /// How quickly the volume falls off, the gain at a distance is `1 / (1 + rolloff * distance²)`.
+    pub rolloff: f32,
+    pub looping: bool,
+}
+
+impl SpatialAudioSource {
+    pub fn new<T: Into<String>>(asset: T) -> Self {
+        Self {
+            asset: asset.into(),
+            volume: 1.0,
+            max_distance: 50.0,
+            rolloff: 1.0,
+            looping: false,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+/// Moves the entity's `Transform` along a spline from the `Splines` resource.
+#[derive(Debug, Clone)]
+pub struct SplineFollower {
+    /// Name of the spline in the `Splines` resource.
+    pub spline_name: String,
+    /// Segments travelled per second, see `Spline`.
+    pub speed: f32,
+    /// Position along the spline in segments.
+    pub t: f32,
+    /// If false the follower stops at the end of the spline.
+    pub looping: bool,
+    /// Rotates the transform so its +Z axis points along the spline.
+    pub face_direction: bool,
+}
+
+impl SplineFollower {
+    pub fn new<T: Into<String>>(spline_name: T, speed: f32) -> Self {
+        Self {
+            spline_name: spline_name.into(),
+            speed,
+            t: 0.0,
+            looping: false,
+            face_direction: true,
+        }
+    }
+
+    // Moves the follower forward, wrapping or clamping it to the spline's length.
+    pub(crate) fn advance(&mut self, delta_time: f32, segment_count: usize) {
+        let length = segment_count as f32;
+        self.t += delta_time * self.speed;
+        if length <= 0.0 {
+            self.t = 0.0;
+        } else if self.looping {
+            self.t = self.t.rem_euclid(length);
+        } else {
+            self.t = self.t.max(0.0).min(length);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+/// A textured quad drawn by the `render_sprites` system when the active camera is a 2D camera.
+/// The quad is one unit wide and tall and is placed, rotated and scaled by the entity's `Transform`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    /// Path to the texture, relative to the asset folder.
+    pub texture: String,
+    /// The part of the texture to draw as x, y, width and height in uv coordinates.
+    pub rect: [f32; 4],
+    /// The point of the quad placed at the transform's position, (0, 0) is the bottom left and (1, 1) the top right.
+    pub pivot: [f32; 2],
+    /// Multiplied with the texture.
+    pub color: [f32; 4],
+}
+
+impl Sprite {
+    /// Draws the whole texture, centered on the transform.
+    pub fn new<T: Into<String>>(texture: T) -> Self {
+        Self {
+            texture: texture.into(),
+            rect: [0.0, 0.0, 1.0, 1.0],
+            pivot: [0.5, 0.5],
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::assets::{material::PBRMaterial, AssetHandle, Image};
+use nalgebra_glm::Vec2;
+use std::sync::Arc;
+
+/// A height map terrain. The `build_terrain_mesh` system splits it into chunk entities with a `Mesh`,
+/// `Material` and a copy of this entity's `Transform`.
+/// The terrain spans from the transform's position to `size` along x and z.
+pub struct Terrain {
+    /// Heights are read from the red channel, 8 bit images are scaled to 0..1.
+    pub heightmap: Arc<Image>,
+    /// The size of the terrain in world units along x and z.
+    pub size: Vec2,
+    /// The height of a full red texel.
+    pub max_height: f32,
+    /// How many height map texels each chunk covers along each side.
+    pub chunk_size: u32,
+    /// Used by every chunk, a plain white material is used if None.
+    pub material: Option<Arc<AssetHandle<PBRMaterial>>>,
+    pub(crate) built: bool,
+}
+
+impl Terrain {
+    pub fn new(heightmap: Arc<Image>, size: Vec2, max_height: f32, chunk_size: u32) -> Self {
+        Self {
+            heightmap,
+            size,
+            max_height,
+            chunk_size: chunk_size.max(1),
+            material: None,
+            built: false,
+        }
+    }
+
+    /// The number of chunks along x and z.
+    pub fn chunk_count(&self) -> (u32, u32) {
+        let quads_x = self.heightmap.width.saturating_sub(1);
+        let quads_z = self.heightmap.height.saturating_sub(1);
+        (
+            (quads_x + self.chunk_size - 1) / self.chunk_size,
+            (quads_z + self.chunk_size - 1) / self.chunk_size,
+        )
+    }
+
+    /// The height of the terrain at `x` and `z` relative to the terrain's position.
+    /// Positions outside of the terrain use the height of the closest edge.
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let (width, height) = (self.heightmap.width, self.heightmap.height);
+        if width == 0 || height == 0 {
+            return 0.0;
+        }
+
+        let u = (x / self.size.x).max(0.0).min(1.0) * (width - 1) as f32;
+        let v = (z / self.size.y).max(0.0).min(1.0) * (height - 1) as f32;
+        let (x0, z0) = (u.floor() as u32, v.floor() as u32);
+        let (x1, z1) = ((x0 + 1).min(width - 1), (z0 + 1).min(height - 1));
+        let (fx, fz) = (u - x0 as f32, v - z0 as f32);
+
+        let top = lerp(self.texel_height(x0, z0), self.texel_height(x1, z0), fx);
+        let bottom = lerp(self.texel_height(x0, z1), self.texel_height(x1, z1), fx);
+        lerp(top, bottom, fz)
+    }
+
+    /// The height of a single height map texel.
+    pub(crate) fn texel_height(&self, x: u32, z: u32) -> f32 {
+        let image = &self.heightmap;
+        let index = (z * image.width + x) as usize;
+        let bytes_per_pixel = image.data.len() / (image.width * image.height) as usize;
+        // Hdr images are stored as 4 f32 channels, everything else as 4 u8 channels.
+        let value = if bytes_per_pixel == 16 {
+            let offset = index * 16;
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(&image.data[offset..offset + 4]);
+            f32::from_ne_bytes(bytes)
+        } else {
+            image.data[index * bytes_per_pixel] as f32 / 255.0
+        };
+        value * self.max_height
+    }
+}
+
+fn lerp(a: f32, b: f32, factor: f32) -> f32 {
+    a + (b - a) * factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Terrain;
+    use crate::assets::{image::ImageFormat, Image};
+    use nalgebra_glm::Vec2;
+    use std::{path::PathBuf, sync::Arc};
+
+    fn heightmap(heights: &[u8], width: u32, height: u32) -> Arc<Image> {
+        Arc::new(Image {
+            data: heights.iter().flat_map(|h| vec![*h, 0, 0, 255]).collect(),
+            width,
+            height,
+            format: ImageFormat::RGB,
+            path: PathBuf::new(),
+        })
+    }
+
+    #[test]
+    fn should_sample_heights() {
+        let terrain = Terrain::new(
+            heightmap(&[0, 255, 255, 255], 2, 2),
+            Vec2::new(10.0, 10.0),
+            4.0,
+            8,
+        );
+        assert_eq!(terrain.height_at(0.0, 0.0), 0.0);
+        assert_eq!(terrain.height_at(10.0, 0.0), 4.0);
+        assert_eq!(terrain.height_at(5.0, 0.0), 2.0);
+        // Outside of the terrain the edge is used.
+        assert_eq!(terrain.height_at(-5.0, 20.0), 4.0);
+    }
+
+    #[test]
+    fn should_count_chunks() {
+        let terrain = Terrain::new(
+            heightmap(&[0; 33 * 17], 33, 17),
+            Vec2::new(1.0, 1.0),
+            1.0,
+            16,
+        );
+        assert_eq!(terrain.chunk_count(), (2, 1));
+    }
+}
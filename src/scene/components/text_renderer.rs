@@ -0,0 +1,44 @@
+/// Where a `TextRenderer` is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextSpace {
+    /// The text lies on the x y plane of the entity's `Transform`, `size` is in world units.
+    World,
+    /// The text is drawn over the scene, the transform's x and y are pixels from the top left of the screen and `size` is in pixels.
+    Screen,
+}
+
+/// Text drawn by the `render_text` system from a font's signed distance field atlas.
+/// The top left of the text is placed at the entity's `Transform`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRenderer {
+    /// Lines are separated by `\n`.
+    pub text: String,
+    /// Path to a ttf or otf font, relative to the asset folder.
+    pub font: String,
+    /// The height of an em.
+    pub size: f32,
+    pub color: [f32; 4],
+    pub space: TextSpace,
+}
+
+impl TextRenderer {
+    /// Creates white text one unit tall placed in the world.
+    pub fn new<T: Into<String>, F: Into<String>>(text: T, font: F) -> Self {
+        Self {
+            text: text.into(),
+            font: font.into(),
+            size: 1.0,
+            color: [1.0, 1.0, 1.0, 1.0],
+            space: TextSpace::World,
+        }
+    }
+
+    /// Creates white text `size` pixels tall drawn over the scene.
+    pub fn screen<T: Into<String>, F: Into<String>>(text: T, font: F, size: f32) -> Self {
+        Self {
+            size,
+            space: TextSpace::Screen,
+            ..Self::new(text, font)
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use crate::{
-    graphics::resources::{BindGroup, GPUResourceManager},
+    graphics::resources::{FramedBuffer, GPUResourceManager, DEFAULT_FRAME_COUNT, TRANSFORM_SLAB},
     Application, TransformCount,
 };
 use bytemuck::{Pod, Zeroable};
 use nalgebra_glm::{Mat4, Quat, Vec3};
-use std::{borrow::Cow, sync::Arc};
+use std::sync::Arc;
 
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
@@ -91,6 +91,48 @@ impl Transform {
         self
     }
 
+    /// Rotates the transform so it's +Z axis points at `target`, keeping it's +Y axis as close to `up` as possible.
+    /// Does nothing if the target is at the transform's position.
+    pub fn look_at<'a>(&'a mut self, target: Vec3, up: Vec3) -> &'a mut Self {
+        let forward = target - self.position;
+        if forward.norm_squared() <= std::f32::EPSILON {
+            return self;
+        }
+        let forward = forward.normalize();
+        let mut right = up.cross(&forward);
+        // Looking along `up` leaves the roll undefined, any perpendicular axis will do.
+        if right.norm_squared() <= std::f32::EPSILON {
+            right = Vec3::new(0.0, 0.0, 1.0).cross(&forward);
+            if right.norm_squared() <= std::f32::EPSILON {
+                right = Vec3::new(0.0, 1.0, 0.0).cross(&forward);
+            }
+        }
+        let right = right.normalize();
+        let up = forward.cross(&right);
+        self.rotation =
+            nalgebra_glm::mat3_to_quat(&nalgebra_glm::Mat3::from_columns(&[right, up, forward]));
+        self
+    }
+
+    /// Moves the transform by `delta` in world space.
+    pub fn translate<'a>(&'a mut self, delta: Vec3) -> &'a mut Self {
+        self.position += delta;
+        self
+    }
+
+    /// Rotates the transform around a world space axis, unlike `rotate_on_axis` which uses the transform's own axes.
+    pub fn rotate<'a>(&'a mut self, axis: Vec3, angle_radians: f32) -> &'a mut Self {
+        self.rotation =
+            nalgebra_glm::quat_angle_axis(angle_radians, &axis.normalize()) * self.rotation;
+        self
+    }
+
+    /// Multiplies the scale on every axis by `s`.
+    pub fn scale_uniform<'a>(&'a mut self, s: f32) -> &'a mut Self {
+        self.scale *= s;
+        self
+    }
+
     // pub fn update_euler(&mut self, rotation: Vec3) {
     //     self.rotation = *nalgebra::UnitQuaternion::from_euler_angles(rotation.x, rotation.y, rotation.z).quaternion();
     // }
@@ -114,25 +156,104 @@ impl Transform {
         let bind_group_layout = resource_manager.get_bind_group_layout("locals").unwrap();
         // This data needs to be saved and passed onto the pipeline.
         let device = app.resources.get_mut::<Arc<wgpu::Device>>().unwrap();
-        let local_buffer = device.create_buffer_with_data(
-            bytemuck::bytes_of(&LocalUniform::default()),
-            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-        );
+        let slab = resource_manager.get_slab(TRANSFORM_SLAB).unwrap();
+        let first_index = index as usize * DEFAULT_FRAME_COUNT;
+        // Transforms share the slab until it's full.
+        let local_buffer = if first_index + DEFAULT_FRAME_COUNT <= slab.capacity() as usize {
+            FramedBuffer::from_slab(
+                &device,
+                &bind_group_layout,
+                0,
+                resource_manager.frame_index(),
+                &slab,
+                first_index as u32,
+                DEFAULT_FRAME_COUNT,
+            )
+        } else {
+            FramedBuffer::new(
+                &device,
+                &bind_group_layout,
+                0,
+                resource_manager.frame_index(),
+                DEFAULT_FRAME_COUNT,
+                &LocalUniform::default(),
+            )
+        };
+        resource_manager.add_transform_buffer(local_buffer, index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transform;
+    use nalgebra_glm::{Mat4, Quat, Vec3};
+
+    // Transforms made with `new` need an application for their buffers.
+    fn transform() -> Transform {
+        Transform {
+            index: 0,
+            position: Vec3::zeros(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+            rotation: Quat::identity(),
+            matrix: Mat4::identity(),
+            cull: false,
+        }
+    }
 
-        let local_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: Cow::Borrowed(&[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(local_buffer.slice(..)),
-            }]),
-            label: None,
-        });
-
-        resource_manager.add_multi_bind_group(
-            "transform",
-            BindGroup::new(0, local_bind_group),
-            index,
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).norm() < 0.0001, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn should_look_at_target() {
+        let mut transform = transform();
+        transform.position = Vec3::new(1.0, 0.0, 0.0);
+        transform.look_at(Vec3::new(1.0, 0.0, -5.0), Vec3::new(0.0, 1.0, 0.0));
+        let forward = nalgebra_glm::quat_rotate_vec3(&transform.rotation, &Vec3::new(0.0, 0.0, 1.0));
+        assert_near(forward, Vec3::new(0.0, 0.0, -1.0));
+        let up = nalgebra_glm::quat_rotate_vec3(&transform.rotation, &Vec3::new(0.0, 1.0, 0.0));
+        assert_near(up, Vec3::new(0.0, 1.0, 0.0));
+
+        // Looking straight up still points at the target.
+        transform.look_at(Vec3::new(1.0, 3.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let forward = nalgebra_glm::quat_rotate_vec3(&transform.rotation, &Vec3::new(0.0, 0.0, 1.0));
+        assert_near(forward, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn should_translate() {
+        let mut transform = transform();
+        transform
+            .translate(Vec3::new(1.0, 2.0, 3.0))
+            .translate(Vec3::new(1.0, 0.0, 0.0));
+        transform.update();
+        assert_eq!(transform.position, Vec3::new(2.0, 2.0, 3.0));
+        assert_near(
+            (transform.matrix * nalgebra_glm::vec4(0.0, 0.0, 0.0, 1.0)).xyz(),
+            Vec3::new(2.0, 2.0, 3.0),
         );
-        resource_manager.add_multi_buffer("transform", local_buffer, index);
+    }
+
+    #[test]
+    fn should_rotate_around_world_axis() {
+        let mut transform = transform();
+        transform
+            .rotate_on_x(std::f32::consts::FRAC_PI_2)
+            .rotate(Vec3::new(0.0, 2.0, 0.0), std::f32::consts::FRAC_PI_2);
+        // +Z is first turned to -Y, which the world Y rotation leaves alone.
+        let forward = nalgebra_glm::quat_rotate_vec3(&transform.rotation, &Vec3::new(0.0, 0.0, 1.0));
+        assert_near(forward, Vec3::new(0.0, -1.0, 0.0));
+        let up = nalgebra_glm::quat_rotate_vec3(&transform.rotation, &Vec3::new(0.0, 1.0, 0.0));
+        assert_near(up, Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn should_scale_uniformly() {
+        let mut transform = transform();
+        transform.scale = Vec3::new(1.0, 2.0, 3.0);
+        transform.scale_uniform(2.0);
+        transform.update();
+        assert_eq!(transform.scale, Vec3::new(2.0, 4.0, 6.0));
+        assert_eq!(transform.matrix[(1, 1)], 4.0);
     }
 }
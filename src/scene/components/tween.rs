@@ -0,0 +1,187 @@
+use super::Transform;
+use nalgebra_glm::{Quat, Vec2, Vec3, Vec4};
+
+/// Values that can be blended by a `Tween`.
+pub trait Lerp: Clone {
+    /// Returns `self` when `t` is 0.0 and `end` when `t` is 1.0.
+    fn lerp(&self, end: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, end: &Self, t: f32) -> Self {
+        self + (end - self) * t
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(&self, end: &Self, t: f32) -> Self {
+        nalgebra_glm::lerp(self, end, t)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(&self, end: &Self, t: f32) -> Self {
+        nalgebra_glm::lerp(self, end, t)
+    }
+}
+
+impl Lerp for Vec4 {
+    fn lerp(&self, end: &Self, t: f32) -> Self {
+        nalgebra_glm::lerp(self, end, t)
+    }
+}
+
+impl Lerp for Quat {
+    fn lerp(&self, end: &Self, t: f32) -> Self {
+        nalgebra_glm::quat_slerp(self, end, t)
+    }
+}
+
+/// Blends the position, rotation and scale, everything else is copied from `self`.
+/// Create the start and end from the entity's own transform so it keeps it's index.
+impl Lerp for Transform {
+    fn lerp(&self, end: &Self, t: f32) -> Self {
+        let mut transform = self.clone();
+        transform.position = self.position.lerp(&end.position, t);
+        transform.rotation = self.rotation.lerp(&end.rotation, t);
+        transform.scale = self.scale.lerp(&end.scale, t);
+        transform
+    }
+}
+
+/// How a `Tween` moves from it's start to it's end over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EasingFn {
+    Linear,
+    EaseInQuad,
+    EaseOutCubic,
+    EaseInOutSine,
+}
+
+impl Default for EasingFn {
+    fn default() -> Self {
+        EasingFn::Linear
+    }
+}
+
+impl EasingFn {
+    /// Maps the tween's progress in 0..1 to how far it's blended towards the end.
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            EasingFn::Linear => t,
+            EasingFn::EaseInQuad => t * t,
+            EasingFn::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            EasingFn::EaseInOutSine => (1.0 - (t * std::f32::consts::PI).cos()) / 2.0,
+        }
+    }
+}
+
+/// Moves the entity's component of type `T` from `start` to `end` over `duration` seconds.
+/// Only tweens of types with a `tick_tweens::<T>` system in the schedule are advanced, `Transform` has one by default.
+/// A `TweenCompleted` event is sent whenever the tween reaches it's end.
+#[derive(Debug, Clone)]
+pub struct Tween<T: Lerp> {
+    pub start: T,
+    pub end: T,
+    /// Length of the tween in seconds.
+    pub duration: f32,
+    /// Seconds since the tween started.
+    pub elapsed: f32,
+    pub easing: EasingFn,
+    /// Starts over from `start` after reaching the end instead of stopping.
+    pub looping: bool,
+    finished: bool,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(start: T, end: T, duration: f32, easing: EasingFn) -> Self {
+        Self {
+            start,
+            end,
+            duration,
+            elapsed: 0.0,
+            easing,
+            looping: false,
+            finished: false,
+        }
+    }
+
+    /// Whether a tween that isn't looping has reached it's end.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The value at the current elapsed time.
+    pub fn value(&self) -> T {
+        let progress = if self.duration > 0.0 {
+            (self.elapsed / self.duration).max(0.0).min(1.0)
+        } else {
+            1.0
+        };
+        self.start.lerp(&self.end, self.easing.apply(progress))
+    }
+
+    // Moves the tween forward, returns the new value and whether the tween reached it's end.
+    // Finished tweens return None so their component can be changed by other systems.
+    pub(crate) fn advance(&mut self, delta_time: f32) -> Option<(T, bool)> {
+        if self.finished {
+            return None;
+        }
+
+        self.elapsed += delta_time;
+        if self.elapsed < self.duration {
+            return Some((self.value(), false));
+        }
+
+        let value = self.end.clone();
+        if self.looping && self.duration > 0.0 {
+            self.elapsed = self.elapsed.rem_euclid(self.duration);
+        } else {
+            self.elapsed = self.duration;
+            self.finished = true;
+        }
+        Some((value, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EasingFn, Tween};
+
+    #[test]
+    fn should_ease_between_ends() {
+        for easing in [
+            EasingFn::Linear,
+            EasingFn::EaseInQuad,
+            EasingFn::EaseOutCubic,
+            EasingFn::EaseInOutSine,
+        ]
+        .iter()
+        {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6);
+        }
+        assert_eq!(EasingFn::EaseInQuad.apply(0.5), 0.25);
+        assert_eq!(EasingFn::EaseOutCubic.apply(0.5), 0.875);
+        assert!((EasingFn::EaseInOutSine.apply(0.5) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn should_finish_once() {
+        let mut tween = Tween::new(2.0, 4.0, 2.0, EasingFn::Linear);
+        assert_eq!(tween.advance(1.0), Some((3.0, false)));
+        assert_eq!(tween.advance(1.5), Some((4.0, true)));
+        assert!(tween.is_finished());
+        assert_eq!(tween.advance(1.0), None);
+    }
+
+    #[test]
+    fn should_loop() {
+        let mut tween = Tween::new(0.0, 1.0, 1.0, EasingFn::Linear);
+        tween.looping = true;
+        assert_eq!(tween.advance(1.25), Some((1.0, true)));
+        assert_eq!(tween.elapsed, 0.25);
+        assert_eq!(tween.advance(0.25), Some((0.5, false)));
+        assert!(!tween.is_finished());
+    }
+}
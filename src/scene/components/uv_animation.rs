@@ -0,0 +1,96 @@
+use crate::assets::material::UvAnimUniform;
+use nalgebra_glm::Vec2;
+
+/// Scrolls and scales the texture coordinates of every material on the entity's `Mesh`, used for water, conveyor belts and the like.
+/// Materials are shared between meshes, so entities using the same material should use the same animation.
+#[derive(Debug, Clone)]
+pub struct UvAnimation {
+    /// How far the textures move each second in uv space.
+    pub scroll_x: f32,
+    pub scroll_y: f32,
+    /// How often the textures repeat across the mesh, 1.0 is the mesh's own uvs.
+    pub scale_x: f32,
+    pub scale_y: f32,
+    /// How many times a second the scale grows and shrinks.
+    pub pulse_frequency: f32,
+    /// How much the scale grows and shrinks, 0.0 turns pulsating off.
+    pub pulse_amplitude: f32,
+    // Kept in 0..1 so precision doesn't degrade the longer the animation runs.
+    pub(crate) offset: Vec2,
+    // Seconds into the current pulse.
+    pub(crate) time: f32,
+}
+
+impl Default for UvAnimation {
+    fn default() -> Self {
+        Self::constant(0.0, 0.0)
+    }
+}
+
+impl UvAnimation {
+    /// Scrolls the textures at a constant speed.
+    pub fn constant(scroll_x: f32, scroll_y: f32) -> Self {
+        Self {
+            scroll_x,
+            scroll_y,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            pulse_frequency: 0.0,
+            pulse_amplitude: 0.0,
+            offset: Vec2::zeros(),
+            time: 0.0,
+        }
+    }
+
+    /// Grows and shrinks the textures `frequency` times a second, by up to `amplitude` times their size.
+    pub fn pulsate(frequency: f32, amplitude: f32) -> Self {
+        Self {
+            pulse_frequency: frequency,
+            pulse_amplitude: amplitude,
+            ..Self::constant(0.0, 0.0)
+        }
+    }
+
+    pub(crate) fn advance(&mut self, delta_time: f32) {
+        let offset = self.offset + Vec2::new(self.scroll_x, self.scroll_y) * delta_time;
+        self.offset = Vec2::new(offset.x.rem_euclid(1.0), offset.y.rem_euclid(1.0));
+        self.time += delta_time;
+        if self.pulse_frequency > 0.0 {
+            self.time = self.time.rem_euclid(1.0 / self.pulse_frequency);
+        }
+    }
+
+    pub(crate) fn create_uniform(&self) -> UvAnimUniform {
+        let pulse = 1.0
+            + self.pulse_amplitude
+                * (self.time * self.pulse_frequency * std::f32::consts::PI * 2.0).sin();
+        UvAnimUniform {
+            offset: self.offset,
+            scale: Vec2::new(self.scale_x, self.scale_y) * pulse,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UvAnimation;
+    use nalgebra_glm::Vec2;
+
+    #[test]
+    fn should_wrap_offset() {
+        let mut animation = UvAnimation::constant(0.5, -0.25);
+        animation.advance(3.0);
+        let uniform = animation.create_uniform();
+        assert_eq!(uniform.offset, Vec2::new(0.5, 0.25));
+        assert_eq!(uniform.scale, Vec2::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn should_pulsate_scale() {
+        let mut animation = UvAnimation::pulsate(2.0, 0.5);
+        animation.advance(1.125);
+        let uniform = animation.create_uniform();
+        assert!((uniform.scale.x - 1.5).abs() < 1e-5);
+        assert_eq!(uniform.offset, Vec2::zeros());
+    }
+}
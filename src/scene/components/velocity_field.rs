@@ -0,0 +1,158 @@
+use crate::core::Aabb;
+use nalgebra_glm::Vec3;
+use std::sync::Arc;
+
+/// The format of velocity field textures, xyz is a direction that's scaled by `VelocityField::strength`.
+pub const VELOCITY_FIELD_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Snorm;
+
+// How many voxels apart the random velocities of `from_noise` are, the velocities between them are blended.
+const NOISE_CELL_SIZE: f32 = 4.0;
+
+/// Adds a velocity to every particle inside of `bounds`, like wind or a water current.
+/// The velocity is read from a 3D texture stretched over `bounds`.
+/// Up to `MAX_VELOCITY_FIELDS` fields affect particles at once, see the `bind_velocity_fields` system.
+pub struct VelocityField {
+    /// The world space box the texture covers.
+    pub bounds: Aabb,
+    pub texture_3d: Arc<wgpu::Texture>,
+    /// How fast particles are pushed, velocities in the texture are between -1 and 1.
+    pub strength: f32,
+    pub(crate) view: Arc<wgpu::TextureView>,
+}
+
+impl VelocityField {
+    /// `texture_3d` should be a 3D texture with the `VELOCITY_FIELD_FORMAT` format.
+    pub fn new(bounds: Aabb, texture_3d: Arc<wgpu::Texture>, strength: f32) -> Self {
+        let view = Arc::new(texture_3d.create_default_view());
+        Self {
+            bounds,
+            texture_3d,
+            strength,
+            view,
+        }
+    }
+
+    /// Creates a field with a `grid_size` cubed texture of smoothly changing random velocities.
+    /// The same `seed` always creates the same velocities.
+    pub fn from_noise(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        seed: u32,
+        grid_size: u32,
+        bounds: Aabb,
+        strength: f32,
+    ) -> Self {
+        let grid_size = grid_size.max(1);
+        let extent = wgpu::Extent3d {
+            width: grid_size,
+            height: grid_size,
+            depth: grid_size,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("velocity_field"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: VELOCITY_FIELD_FORMAT,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+        let velocities = noise_velocities(seed, grid_size);
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            bytemuck::cast_slice(&velocities),
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: grid_size * 4,
+                rows_per_image: grid_size,
+            },
+            extent,
+        );
+
+        Self::new(bounds, Arc::new(texture), strength)
+    }
+}
+
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
+
+// A random value between -1 and 1 for a point on the noise lattice.
+fn lattice_value(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+    let h = hash(seed ^ hash(x as u32 ^ hash(y as u32 ^ hash(z as u32))));
+    h as f32 / std::u32::MAX as f32 * 2.0 - 1.0
+}
+
+// Value noise, the lattice values around `point` blended with a smoothstep.
+fn value_noise(point: Vec3, seed: u32) -> f32 {
+    let cell = nalgebra_glm::floor(&point);
+    let t = point - cell;
+    let t = t.map(|t| t * t * (3.0 - 2.0 * t));
+    let (x, y, z) = (cell.x as i32, cell.y as i32, cell.z as i32);
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let corner = |dx: i32, dy: i32, dz: i32| lattice_value(x + dx, y + dy, z + dz, seed);
+    let bottom = lerp(
+        lerp(corner(0, 0, 0), corner(1, 0, 0), t.x),
+        lerp(corner(0, 1, 0), corner(1, 1, 0), t.x),
+        t.y,
+    );
+    let top = lerp(
+        lerp(corner(0, 0, 1), corner(1, 0, 1), t.x),
+        lerp(corner(0, 1, 1), corner(1, 1, 1), t.x),
+        t.y,
+    );
+    lerp(bottom, top, t.z)
+}
+
+/// The texels of a `grid_size` cubed noise texture, each axis of the velocity uses different noise.
+pub(crate) fn noise_velocities(seed: u32, grid_size: u32) -> Vec<[i8; 4]> {
+    let mut velocities = Vec::with_capacity((grid_size * grid_size * grid_size) as usize);
+    for z in 0..grid_size {
+        for y in 0..grid_size {
+            for x in 0..grid_size {
+                let point = Vec3::new(x as f32, y as f32, z as f32) / NOISE_CELL_SIZE;
+                let velocity = Vec3::new(
+                    value_noise(point, hash(seed)),
+                    value_noise(point, hash(seed.wrapping_add(1))),
+                    value_noise(point, hash(seed.wrapping_add(2))),
+                );
+                let to_snorm = |value: f32| (value.max(-1.0).min(1.0) * 127.0).round() as i8;
+                velocities.push([
+                    to_snorm(velocity.x),
+                    to_snorm(velocity.y),
+                    to_snorm(velocity.z),
+                    0,
+                ]);
+            }
+        }
+    }
+    velocities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::noise_velocities;
+
+    #[test]
+    fn should_create_repeatable_noise() {
+        let velocities = noise_velocities(7, 8);
+        assert_eq!(velocities.len(), 8 * 8 * 8);
+        assert_eq!(velocities, noise_velocities(7, 8));
+        assert_ne!(velocities, noise_velocities(8, 8));
+
+        // Neighboring voxels are blended from the same lattice values so they're close together.
+        for pair in velocities.windows(2).take(7) {
+            assert!((pair[0][0] as i32 - pair[1][0] as i32).abs() <= 96);
+        }
+    }
+}
@@ -0,0 +1,6 @@
+/// Marks an entity spawned by the `mesh_voxel_chunk` system to draw one chunk of the `VoxelWorld`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelChunk {
+    /// The chunk's position in chunks, not voxels.
+    pub position: [u32; 3],
+}
@@ -3,5 +3,20 @@ pub mod entities;
 pub mod resources;
 pub mod systems;
 
+mod bvh;
+pub use bvh::{Bvh, HitResult};
+
+mod voxel;
+pub use voxel::{VoxelId, VoxelWorld, EMPTY_VOXEL};
+
+mod spline;
+pub use spline::{Spline, Splines};
+
+mod audio;
+pub use audio::AudioManager;
+
 mod scene;
 pub use scene::Scene;
+
+mod serialization;
+pub use serialization::{SceneError, SCENE_VERSION};
@@ -1,2 +1,43 @@
+use legion::prelude::Entity;
+use std::time::Instant;
+
 #[derive(Default)]
 pub struct DeltaTime(pub f32);
+
+/// When the application started, shaders get the seconds since then through the global uniforms.
+pub struct EngineTime {
+    pub start: Instant,
+}
+
+impl Default for EngineTime {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl EngineTime {
+    /// Seconds since the application started.
+    pub fn elapsed(&self) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+}
+
+/// Set when a transform moves so the `update_bvh` system rebuilds the `Bvh`.
+#[derive(Default)]
+pub struct BvhDirty(pub bool);
+
+/// Sent by the `tick_tweens` systems when a `Tween` reaches it's end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TweenCompleted {
+    pub entity: Entity,
+}
+
+/// The `TweenCompleted` events of the current frame, cleared before the game systems run.
+#[derive(Debug, Default)]
+pub struct TweenEvents(pub Vec<TweenCompleted>);
+
+/// The entity with the `Camera` component that the scene is rendered from.
+#[derive(Default)]
+pub struct ActiveCamera(pub Option<Entity>);
@@ -21,7 +21,16 @@ impl Scene {
 
         // Add our systems here..
         let game_schedule_builder = schedule_builder.unwrap_or(Schedule::builder())
-            .add_system(super::systems::culling::create());
+            .add_system(super::systems::animation::create())
+            .add_thread_local_fn(super::systems::animation::create_state_machine())
+            .add_system(super::systems::culling::create())
+            .add_system(super::systems::bvh::create())
+            .add_system(super::systems::terrain::create())
+            .add_system(super::systems::uv_animation::create())
+            .add_system(super::systems::tween::create::<super::components::Transform>())
+            .add_system(super::systems::spline::create())
+            .add_system(super::systems::audio::create())
+            .add_system(super::systems::voxel::create());
         let game_schedule = game_schedule_builder.build();
 
         Scene {
@@ -35,6 +44,11 @@ impl Scene {
         {
             let mut delta = resources.get_mut::<resources::DeltaTime>().unwrap();
             *delta = resources::DeltaTime(delta_time);
+            resources
+                .get_mut::<resources::TweenEvents>()
+                .unwrap()
+                .0
+                .clear();
         }
 
         self.game_schedule.execute(&mut self.world, resources);
@@ -0,0 +1,334 @@
+use super::{
+    components::{
+        camera_data::ProjectionData, CameraData, DirectionalLightData, Material, Mesh, Transform,
+    },
+    Scene,
+};
+use crate::{Application, AssetManager};
+use legion::prelude::*;
+use nalgebra_glm::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// The version written by `Scene::save`. Bump this when the file layout changes.
+pub const SCENE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum SceneError {
+    // Thrown when the scene file can't be read or written.
+    Io(std::io::Error),
+    // Thrown when the scene file isn't valid ron or doesn't match the expected layout.
+    Ron(ron::Error),
+    // Thrown when the scene file was written by a different version.
+    UnsupportedVersion(u32),
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<ron::Error> for SceneError {
+    fn from(error: ron::Error) -> Self {
+        SceneError::Ron(error)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SceneRon {
+    version: u32,
+    entities: Vec<EntityRon>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EntityRon {
+    transform: Option<TransformRon>,
+    mesh: Option<MeshRon>,
+    material: Option<u32>,
+    directional_light: Option<DirectionalLightRon>,
+    camera: Option<CameraRon>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TransformRon {
+    position: Vec3,
+    rotation: Quat,
+    scale: Vec3,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MeshRon {
+    // Relative to the asset manager's path.
+    path: PathBuf,
+    mesh_index: Option<usize>,
+    lod_distances: Vec<f32>,
+    lod_mesh_names: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DirectionalLightRon {
+    direction: Vec3,
+    color: Vec3,
+    intensity: f32,
+    #[serde(default)]
+    cast_shadow: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CameraRon {
+    active: bool,
+    cull: bool,
+    position: Vec3,
+    yaw: f32,
+    pitch: f32,
+    width: f32,
+    height: f32,
+    projection: ProjectionData,
+}
+
+impl Scene {
+    /// Writes every entity with a transform, directional light or camera to a ron file.
+    /// Meshes are stored by path so the referenced files have to stay in the asset folder.
+    /// Generated lods are not stored, call `AssetManager::generate_mesh_lods` again after loading.
+    pub fn save<P: AsRef<Path>>(
+        world: &World,
+        asset_manager: &AssetManager,
+        path: P,
+    ) -> Result<(), SceneError> {
+        let mut seen = HashSet::new();
+        let mut entities = Vec::new();
+        for entity in <Read<Transform>>::query()
+            .iter_entities_immutable(world)
+            .map(|(entity, _)| entity)
+            .chain(
+                <Read<DirectionalLightData>>::query()
+                    .iter_entities_immutable(world)
+                    .map(|(entity, _)| entity),
+            )
+            .chain(
+                <Read<CameraData>>::query()
+                    .iter_entities_immutable(world)
+                    .map(|(entity, _)| entity),
+            )
+        {
+            if seen.insert(entity) {
+                entities.push(save_entity(world, asset_manager, entity));
+            }
+        }
+
+        let scene = SceneRon {
+            version: SCENE_VERSION,
+            entities,
+        };
+        let data = ron::ser::to_string_pretty(&scene, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Reads a file written by `Scene::save` and adds its entities to `app.current_scene`.
+    /// Referenced meshes start loading in the background, the returned entities are in file order.
+    pub fn load<P: AsRef<Path>>(app: &mut Application, path: P) -> Result<Vec<Entity>, SceneError> {
+        let data = std::fs::read(path)?;
+        let scene = parse(&data)?;
+
+        let mut loaded_objs = HashSet::new();
+        let mut entities = Vec::with_capacity(scene.entities.len());
+        for entity_ron in scene.entities {
+            let transform = entity_ron.transform.map(|transform_ron| {
+                let mut transform = Transform::new(app);
+                transform.position = transform_ron.position;
+                transform.rotation = transform_ron.rotation;
+                transform.scale = transform_ron.scale;
+                transform.update();
+                transform
+            });
+
+            let mesh = entity_ron.mesh.map(|mesh_ron| {
+                let asset_manager = app.resources.get::<AssetManager>().unwrap();
+                let is_obj = mesh_ron
+                    .path
+                    .extension()
+                    .map_or(false, |extension| extension == "obj");
+                if is_obj && loaded_objs.insert(mesh_ron.path.clone()) {
+                    if let Err(error) = asset_manager.load_obj(&mesh_ron.path.to_string_lossy()) {
+                        log::error!("Failed to load {:?}: {:?}", mesh_ron.path, error);
+                    }
+                }
+                Mesh {
+                    mesh_handle: asset_manager.get_mesh(mesh_ron.path),
+                    mesh_index: mesh_ron.mesh_index,
+                    lod_distances: mesh_ron.lod_distances,
+                    lod_mesh_names: mesh_ron.lod_mesh_names,
+                }
+            });
+
+            let world = &mut app.current_scene.world;
+            let mut entity = None;
+            if let Some(transform) = transform {
+                add(world, &mut entity, transform);
+            }
+            if let Some(mesh) = mesh {
+                add(world, &mut entity, mesh);
+            }
+            if let Some(index) = entity_ron.material {
+                add(world, &mut entity, Material::new(index));
+            }
+            if let Some(light) = entity_ron.directional_light {
+                add(
+                    world,
+                    &mut entity,
+                    DirectionalLightData {
+                        direction: light.direction,
+                        color: light.color,
+                        intensity: light.intensity,
+                        cast_shadow: light.cast_shadow,
+                    },
+                );
+            }
+            if let Some(camera_ron) = entity_ron.camera {
+                add(world, &mut entity, load_camera(camera_ron));
+            }
+
+            if let Some(entity) = entity {
+                entities.push(entity);
+            }
+        }
+
+        Ok(entities)
+    }
+}
+
+fn parse(data: &[u8]) -> Result<SceneRon, SceneError> {
+    // Check the version on its own first so older layouts don't show up as a parse error.
+    #[derive(Deserialize)]
+    struct VersionRon {
+        version: u32,
+    }
+    let version: VersionRon = ron::de::from_bytes(data)?;
+    if version.version != SCENE_VERSION {
+        return Err(SceneError::UnsupportedVersion(version.version));
+    }
+    Ok(ron::de::from_bytes(data)?)
+}
+
+fn save_entity(world: &World, asset_manager: &AssetManager, entity: Entity) -> EntityRon {
+    let transform = world
+        .get_component::<Transform>(entity)
+        .map(|transform| TransformRon {
+            position: transform.position,
+            rotation: transform.rotation,
+            scale: transform.scale,
+        });
+
+    let mesh = world.get_component::<Mesh>(entity).map(|mesh| {
+        let path = &mesh.mesh_handle.handle_id;
+        MeshRon {
+            path: path
+                .strip_prefix(asset_manager.asset_path())
+                .unwrap_or(path)
+                .to_path_buf(),
+            mesh_index: mesh.mesh_index,
+            lod_distances: mesh.lod_distances.clone(),
+            lod_mesh_names: mesh.lod_mesh_names.clone(),
+        }
+    });
+
+    let material = world
+        .get_component::<Material>(entity)
+        .map(|material| material.index);
+
+    let directional_light = world
+        .get_component::<DirectionalLightData>(entity)
+        .map(|light| DirectionalLightRon {
+            direction: light.direction,
+            color: light.color,
+            intensity: light.intensity,
+            cast_shadow: light.cast_shadow,
+        });
+
+    let camera = world
+        .get_component::<CameraData>(entity)
+        .map(|camera| CameraRon {
+            active: camera.active,
+            cull: camera.cull,
+            position: camera.position,
+            yaw: camera.yaw,
+            pitch: camera.pitch,
+            width: camera.width,
+            height: camera.height,
+            projection: camera.projection_data().clone(),
+        });
+
+    EntityRon {
+        transform,
+        mesh,
+        material,
+        directional_light,
+        camera,
+    }
+}
+
+fn load_camera(camera_ron: CameraRon) -> CameraData {
+    let mut camera = match camera_ron.projection {
+        ProjectionData::Perspective { fov, z_near, z_far } => {
+            CameraData::new_perspective(fov, camera_ron.width, camera_ron.height, z_near, z_far)
+        }
+        ProjectionData::Orthographic {
+            world_height,
+            z_near,
+            z_far,
+        } => CameraData::new_orthographic(
+            world_height,
+            camera_ron.width,
+            camera_ron.height,
+            z_near,
+            z_far,
+        ),
+    };
+    camera.active = camera_ron.active;
+    camera.cull = camera_ron.cull;
+    camera.position = camera_ron.position;
+    camera.yaw = camera_ron.yaw;
+    camera.pitch = camera_ron.pitch;
+    camera
+}
+
+// Creates the entity with the first component and adds every other component to it.
+fn add<T: Send + Sync + 'static>(world: &mut World, entity: &mut Option<Entity>, component: T) {
+    match entity {
+        Some(entity) => world.add_component(*entity, component).unwrap(),
+        None => *entity = Some(world.insert((), vec![(component,)])[0]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, SceneError, SCENE_VERSION};
+
+    #[test]
+    fn should_parse_scene() {
+        let data = format!(
+            "(version: {}, entities: [(material: Some(2)), (mesh: Some((path: \"cube.gltf\", mesh_index: None, lod_distances: [], lod_mesh_names: [])))])",
+            SCENE_VERSION
+        );
+        let scene = parse(data.as_bytes()).unwrap();
+        assert_eq!(scene.entities.len(), 2);
+        assert_eq!(scene.entities[0].material, Some(2));
+        assert!(scene.entities[0].mesh.is_none());
+        let mesh = scene.entities[1].mesh.as_ref().unwrap();
+        assert_eq!(mesh.path.to_str(), Some("cube.gltf"));
+    }
+
+    #[test]
+    fn should_reject_other_versions() {
+        let data = format!("(version: {}, entities: [])", SCENE_VERSION + 1);
+        match parse(data.as_bytes()) {
+            Err(SceneError::UnsupportedVersion(version)) => assert_eq!(version, SCENE_VERSION + 1),
+            result => panic!("Expected an unsupported version, got {:?}", result),
+        }
+    }
+}
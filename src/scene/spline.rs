@@ -0,0 +1,159 @@
+use nalgebra_glm::Vec3;
+use std::collections::HashMap;
+
+/// A smooth path through `control_points` made of cubic Hermite segments.
+/// Positions along the spline are measured in segments, 1.5 is halfway between the second and third point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spline {
+    pub control_points: Vec<Vec3>,
+    /// The direction and speed the spline passes through each control point with.
+    pub tangents: Vec<Vec3>,
+}
+
+impl Spline {
+    pub fn new(control_points: Vec<Vec3>, tangents: Vec<Vec3>) -> Self {
+        Self {
+            control_points,
+            tangents,
+        }
+    }
+
+    /// Creates a Catmull-Rom spline, the tangent of each point is taken from its neighbours.
+    /// `closed` splines wrap around to the first point, use it for splines that are followed while looping.
+    pub fn catmull_rom(control_points: Vec<Vec3>, closed: bool) -> Self {
+        let count = control_points.len();
+        let tangents = (0..count)
+            .map(|index| {
+                let (previous, next) = if closed {
+                    ((index + count - 1) % count, (index + 1) % count)
+                } else {
+                    (index.saturating_sub(1), (index + 1).min(count - 1))
+                };
+                let span = if closed {
+                    2.0
+                } else {
+                    (next - previous).max(1) as f32
+                };
+                (control_points[next] - control_points[previous]) / span
+            })
+            .collect();
+        Self::new(control_points, tangents)
+    }
+
+    /// How many segments long the spline is, a looping spline has an extra segment back to the first point.
+    pub fn segment_count(&self, looping: bool) -> usize {
+        match self.control_points.len() {
+            0 | 1 => 0,
+            count if looping => count,
+            count => count - 1,
+        }
+    }
+
+    /// Returns the position at `t` segments along the spline.
+    pub fn evaluate(&self, t: f32, looping: bool) -> Vec3 {
+        match self.segment(t, looping) {
+            Some((p0, m0, p1, m1, s)) => {
+                let (s2, s3) = (s * s, s * s * s);
+                p0 * (2.0 * s3 - 3.0 * s2 + 1.0)
+                    + m0 * (s3 - 2.0 * s2 + s)
+                    + p1 * (-2.0 * s3 + 3.0 * s2)
+                    + m1 * (s3 - s2)
+            }
+            None => self
+                .control_points
+                .first()
+                .cloned()
+                .unwrap_or_else(Vec3::zeros),
+        }
+    }
+
+    /// Returns the derivative of the spline at `t`, which points in the direction of travel.
+    pub fn tangent(&self, t: f32, looping: bool) -> Vec3 {
+        match self.segment(t, looping) {
+            Some((p0, m0, p1, m1, s)) => {
+                let s2 = s * s;
+                p0 * (6.0 * s2 - 6.0 * s)
+                    + m0 * (3.0 * s2 - 4.0 * s + 1.0)
+                    + p1 * (-6.0 * s2 + 6.0 * s)
+                    + m1 * (3.0 * s2 - 2.0 * s)
+            }
+            None => Vec3::zeros(),
+        }
+    }
+
+    // Returns the points and tangents on either side of `t` and how far between them it is.
+    fn segment(&self, t: f32, looping: bool) -> Option<(Vec3, Vec3, Vec3, Vec3, f32)> {
+        let segment_count = self.segment_count(looping);
+        if segment_count == 0 {
+            return None;
+        }
+
+        let t = if looping {
+            t.rem_euclid(segment_count as f32)
+        } else {
+            t.max(0.0).min(segment_count as f32)
+        };
+        let index = (t.floor() as usize).min(segment_count - 1);
+        let next = (index + 1) % self.control_points.len();
+        let tangent = |index: usize| {
+            self.tangents
+                .get(index)
+                .cloned()
+                .unwrap_or_else(Vec3::zeros)
+        };
+
+        Some((
+            self.control_points[index],
+            tangent(index),
+            self.control_points[next],
+            tangent(next),
+            t - index as f32,
+        ))
+    }
+}
+
+/// Splines that `SplineFollower`s can follow, looked up by name.
+#[derive(Debug, Default)]
+pub struct Splines(pub HashMap<String, Spline>);
+
+#[cfg(test)]
+mod tests {
+    use super::Spline;
+    use nalgebra_glm::Vec3;
+
+    fn square() -> Vec<Vec3> {
+        vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ]
+    }
+
+    #[test]
+    fn should_pass_through_control_points() {
+        let spline = Spline::catmull_rom(square(), false);
+        assert_eq!(spline.segment_count(false), 3);
+        assert_eq!(spline.evaluate(0.0, false), square()[0]);
+        assert_eq!(spline.evaluate(2.0, false), square()[2]);
+        assert_eq!(spline.evaluate(3.0, false), square()[3]);
+        // Clamped past the end.
+        assert_eq!(spline.evaluate(5.0, false), square()[3]);
+
+        let closed = Spline::catmull_rom(square(), true);
+        assert_eq!(closed.segment_count(true), 4);
+        assert_eq!(closed.evaluate(4.0, true), square()[0]);
+        assert_eq!(closed.evaluate(5.0, true), square()[1]);
+    }
+
+    #[test]
+    fn should_follow_tangents() {
+        let spline = Spline::new(
+            vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)],
+            vec![Vec3::new(2.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)],
+        );
+        // Tangents matching the straight line move along it at a constant speed.
+        assert_eq!(spline.evaluate(0.5, false), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(spline.tangent(0.25, false), Vec3::new(2.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,103 @@
+use legion::prelude::*;
+use std::collections::HashMap;
+
+use crate::scene::{components, resources::DeltaTime};
+
+/// Advances every `Animator` and poses its skinned mesh's skeleton.
+/// The skinning system uploads the resulting joints when the frame is rendered.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("tick_animations")
+        .read_resource::<DeltaTime>()
+        .with_query(<(
+            Write<components::Animator>,
+            Write<components::SkinnedMesh>,
+            Read<components::Mesh>,
+        )>::query())
+        .build(|_, mut world, delta_time, animator_query| {
+            for (mut animator, mut skinned_mesh, mesh) in animator_query.iter_mut(&mut world) {
+                let gltf = match mesh.mesh_handle.get() {
+                    Ok(gltf) => gltf,
+                    Err(_) => continue,
+                };
+                let clip = match gltf.animation(&animator.clip) {
+                    Some(clip) => clip,
+                    None => continue,
+                };
+
+                animator.advance(delta_time.0, clip.duration);
+
+                let skeleton = &mut skinned_mesh.skeleton;
+                let mut pose = skeleton.rest_pose.clone();
+                clip.sample(animator.time, skeleton, &mut pose);
+                skeleton.apply_pose(&pose);
+            }
+        })
+}
+
+/// The `tick_state_machine` system. Takes the transitions of every `AnimationStateMachine`
+/// whose condition passes and blends the skeleton's pose while switching clips.
+/// Runs after `tick_animations` on the main thread because conditions need the whole world.
+pub fn create_state_machine() -> Box<dyn Fn(&mut World, &mut Resources) -> ()> {
+    Box::new(|world: &mut World, resources: &mut Resources| {
+        let delta_time = resources.get::<DeltaTime>().unwrap().0;
+
+        let transitions: HashMap<Entity, usize> = {
+            let world = &*world;
+            <Read<components::AnimationStateMachine>>::query()
+                .iter_entities(world)
+                .filter_map(|(entity, state_machine)| {
+                    state_machine
+                        .find_transition(world, entity)
+                        .map(|index| (entity, index))
+                })
+                .collect()
+        };
+
+        let query = <(
+            Write<components::AnimationStateMachine>,
+            Write<components::Animator>,
+            Write<components::SkinnedMesh>,
+            Read<components::Mesh>,
+        )>::query();
+        for (entity, (mut state_machine, mut animator, mut skinned_mesh, mesh)) in
+            query.iter_entities_mut(world)
+        {
+            if let Some(index) = transitions.get(&entity) {
+                state_machine.start_transition(*index, &mut animator);
+            }
+
+            let blend = match state_machine.blend.as_mut() {
+                Some(blend) => blend,
+                None => continue,
+            };
+            let gltf = match mesh.mesh_handle.get() {
+                Ok(gltf) => gltf,
+                Err(_) => continue,
+            };
+            let from_clip = gltf.animation(&blend.from.clip);
+            let weight = blend.advance(delta_time, from_clip.map_or(0.0, |clip| clip.duration));
+            let from_time = blend.from.time;
+            if weight >= 1.0 {
+                // `tick_animations` already posed the new clip.
+                state_machine.blend = None;
+                continue;
+            }
+            let (from_clip, to_clip) = match (from_clip, gltf.animation(&animator.clip)) {
+                (Some(from_clip), Some(to_clip)) => (from_clip, to_clip),
+                _ => continue,
+            };
+
+            let skeleton = &mut skinned_mesh.skeleton;
+            let mut from_pose = skeleton.rest_pose.clone();
+            from_clip.sample(from_time, skeleton, &mut from_pose);
+            let mut to_pose = skeleton.rest_pose.clone();
+            to_clip.sample(animator.time, skeleton, &mut to_pose);
+            let pose: Vec<_> = from_pose
+                .iter()
+                .zip(to_pose.iter())
+                .map(|(from, to)| from.lerp(to, weight))
+                .collect();
+            skeleton.apply_pose(&pose);
+        }
+    })
+}
@@ -0,0 +1,98 @@
+use legion::prelude::*;
+use nalgebra_glm::{Quat, Vec3};
+use std::collections::HashSet;
+
+use crate::{
+    scene::{components, resources::ActiveCamera, AudioManager},
+    AssetManager,
+};
+
+/// Starts the clips of new `SpatialAudioSource`s and pans and attenuates every source relative to the `ActiveCamera`.
+/// Clips of entities that no longer have a source are stopped.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("tick_spatial_audio")
+        .write_resource::<AudioManager>()
+        .read_resource::<AssetManager>()
+        .read_resource::<ActiveCamera>()
+        .read_component::<components::Transform>()
+        .with_query(<(
+            Read<components::SpatialAudioSource>,
+            Read<components::Transform>,
+        )>::query())
+        .build(
+            |_, world, (audio_manager, asset_manager, active_camera), source_query| {
+                // Without a camera the listener stands at the origin.
+                let (listener_position, listener_rotation) = active_camera
+                    .0
+                    .and_then(|entity| world.get_component::<components::Transform>(entity))
+                    .map(|transform| (transform.position, transform.rotation))
+                    .unwrap_or_else(|| (Vec3::zeros(), Quat::identity()));
+
+                let mut sources = HashSet::new();
+                for (entity, (source, transform)) in source_query.iter_entities(&world) {
+                    sources.insert(entity);
+                    if !audio_manager.has_sink(entity) {
+                        let clip = asset_manager.load_audio(&source.asset);
+                        if let Err(error) = &clip {
+                            log::error!("Couldn't load audio {}: {:?}", source.asset, error);
+                        }
+                        audio_manager.play(entity, clip.ok().as_ref(), source.looping);
+                    }
+
+                    let offset = transform.position - listener_position;
+                    let gain = attenuation(offset.norm(), source.max_distance, source.rolloff);
+                    let (left, right) = pan(&listener_rotation, &offset);
+                    audio_manager.set_spatial(entity, source.volume * gain, left, right);
+                }
+                audio_manager.retain(|entity| sources.contains(entity));
+            },
+        )
+}
+
+// Falls off with the inverse square of the distance, sources past `max_distance` are silent.
+fn attenuation(distance: f32, max_distance: f32, rolloff: f32) -> f32 {
+    if distance >= max_distance {
+        return 0.0;
+    }
+    1.0 / (1.0 + rolloff * distance * distance)
+}
+
+// Returns equal power left and right gains for a source at `offset` from the listener.
+fn pan(listener_rotation: &Quat, offset: &Vec3) -> (f32, f32) {
+    let balance = if offset.norm_squared() <= std::f32::EPSILON {
+        0.0
+    } else {
+        let right = nalgebra_glm::quat_rotate_vec3(listener_rotation, &Vec3::new(1.0, 0.0, 0.0));
+        offset.normalize().dot(&right)
+    };
+    let angle = (balance + 1.0) * std::f32::consts::FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{attenuation, pan};
+    use nalgebra_glm::{Quat, Vec3};
+
+    #[test]
+    fn should_attenuate_with_distance() {
+        assert_eq!(attenuation(0.0, 10.0, 1.0), 1.0);
+        assert_eq!(attenuation(2.0, 10.0, 1.0), 0.2);
+        assert_eq!(attenuation(10.0, 10.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn should_pan_towards_the_source() {
+        let (left, right) = pan(&Quat::identity(), &Vec3::new(5.0, 0.0, 0.0));
+        assert!(left.abs() < 0.0001 && (right - 1.0).abs() < 0.0001);
+
+        // Sources ahead play equally in both ears.
+        let (left, right) = pan(&Quat::identity(), &Vec3::new(0.0, 0.0, 5.0));
+        assert!((left - right).abs() < 0.0001);
+
+        // Turning around swaps the ears.
+        let turned = nalgebra_glm::quat_angle_axis(std::f32::consts::PI, &Vec3::new(0.0, 1.0, 0.0));
+        let (left, right) = pan(&turned, &Vec3::new(5.0, 0.0, 0.0));
+        assert!((left - 1.0).abs() < 0.0001 && right.abs() < 0.0001);
+    }
+}
@@ -0,0 +1,34 @@
+use legion::prelude::*;
+
+use crate::scene::{components, resources::BvhDirty, Bvh};
+
+/// Rebuilds the `Bvh` when `BvhDirty` is set or when meshes are added, removed or finish loading.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("update_bvh")
+        .write_resource::<Bvh>()
+        .write_resource::<BvhDirty>()
+        .with_query(<(Read<components::Mesh>, Read<components::Transform>)>::query())
+        .build(|_, world, (bvh, bvh_dirty), mesh_query| {
+            // Meshes that are still loading are added once they're loaded.
+            let loaded: Vec<_> = mesh_query
+                .iter_entities(&world)
+                .filter_map(|(entity, (mesh, transform))| {
+                    let gltf = mesh.mesh_handle.get().ok()?;
+                    Some((entity, mesh.clone(), transform.matrix, gltf))
+                })
+                .collect();
+            if !bvh_dirty.0 && loaded.len() == bvh.entity_count {
+                return;
+            }
+
+            bvh.clear();
+            for (entity, mesh, matrix, gltf) in loaded.iter() {
+                for gltf_mesh in mesh.get_meshes(gltf) {
+                    bvh.add_mesh(*entity, matrix, gltf_mesh);
+                }
+            }
+            bvh.entity_count = loaded.len();
+            bvh.build();
+            bvh_dirty.0 = false;
+        })
+}
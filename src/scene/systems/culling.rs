@@ -2,20 +2,39 @@ use legion::prelude::*;
 use nalgebra_glm::Vec4;
 
 use crate::{
+    graphics::{resources::RenderStats, RenderGraph},
     scene::components,
 };
 
 pub fn create() -> Box<dyn Schedulable> {
     SystemBuilder::new("culling")
         .write_resource::<crate::core::PerformanceMetrics>()
+        .write_resource::<RenderStats>()
+        .read_resource::<RenderGraph>()
         .with_query(<Read<components::CameraData>>::query())
         .with_query(<(Write<components::Transform>, Read<components::Mesh>)>::query())
         .build(
-            |_, mut world, perf_metrics, (camera_query, transform_mesh_query)| {
+            |_,
+             mut world,
+             (perf_metrics, render_stats, render_graph),
+             (camera_query, transform_mesh_query)| {
                 let cull_time = std::time::Instant::now();
 
-                // TODO: store and display this stat somewhere..
+                // The mesh system culls on the GPU instead, it needs every transform uploaded.
+                // The GPU's results aren't read back so everything is counted as visible.
+                if render_graph.use_gpu_driven {
+                    let mut count = 0;
+                    for (mut transform, _) in transform_mesh_query.iter_mut(&mut world) {
+                        transform.cull = false;
+                        count += 1;
+                    }
+                    render_stats.visible_entities = count;
+                    render_stats.culled_entities = 0;
+                    return;
+                }
+
                 let mut total = 0;
+                let mut visible = 0;
                 let camera_frustum = {
                     let filtered_camera_data: Vec<_> =
                         camera_query
@@ -25,14 +44,21 @@ pub fn create() -> Box<dyn Schedulable> {
                         let camera_data: Option<&legion::borrow::Ref<'_, components::CameraData>
                     > = filtered_camera_data.first();
                     
-                    if camera_data.is_none() {
+                    camera_data.map(|camera_data| camera_data.frustum.clone())
+                };
+                let camera_frustum = match camera_frustum {
+                    Some(camera_frustum) => camera_frustum,
+                    None => {
+                        // Nothing is culled without a culling camera.
+                        render_stats.visible_entities =
+                            transform_mesh_query.iter_mut(&mut world).count() as u32;
+                        render_stats.culled_entities = 0;
                         return;
                     }
-                    camera_data.unwrap().frustum.clone()
                 };
 
-                for (mut transform, mesh) in transform_mesh_query.iter_mut(&mut world) {
-                    let mesh = mesh.mesh_handle.get();
+                for (mut transform, mesh_component) in transform_mesh_query.iter_mut(&mut world) {
+                    let mesh = mesh_component.mesh_handle.get();
 
                     if mesh.is_err() {
                         continue;
@@ -40,15 +66,18 @@ pub fn create() -> Box<dyn Schedulable> {
 
                     let mesh = mesh.unwrap();
                     
-                    let mut bounding_sphere = mesh.bounding_sphere.clone();
+                    let mut bounding_sphere = mesh_component.get_bounding_sphere(&mesh);
                     bounding_sphere.center = (transform.matrix * Vec4::new(bounding_sphere.center.x, bounding_sphere.center.y, bounding_sphere.center.z, 1.0)).xyz();
                     transform.cull = !camera_frustum.contains_sphere(bounding_sphere);
                     if transform.cull {
                         total += 1;
+                    } else {
+                        visible += 1;
                     }
                 }
 
                 perf_metrics.insert("frustum cull", std::time::Instant::now().duration_since(cull_time));
-                perf_metrics.insert("meshes culled", std::time::Duration::new(total, 0));
+                render_stats.visible_entities = visible;
+                render_stats.culled_entities = total;
            })
 }
\ No newline at end of file
@@ -1 +1,9 @@
+pub mod animation;
+pub mod audio;
+pub mod bvh;
 pub mod culling;
+pub mod spline;
+pub mod terrain;
+pub mod tween;
+pub mod uv_animation;
+pub mod voxel;
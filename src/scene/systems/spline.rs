@@ -0,0 +1,68 @@
+use legion::prelude::*;
+use nalgebra_glm::{Mat3, Quat, Vec3};
+
+use crate::scene::{components, resources::DeltaTime, Splines};
+
+/// Moves every `SplineFollower` along its spline and writes the position into the entity's `Transform`.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("tick_spline_followers")
+        .read_resource::<DeltaTime>()
+        .read_resource::<Splines>()
+        .with_query(<(
+            Write<components::SplineFollower>,
+            Write<components::Transform>,
+        )>::query())
+        .build(|_, mut world, (delta_time, splines), follower_query| {
+            for (mut follower, mut transform) in follower_query.iter_mut(&mut world) {
+                let spline = match splines.0.get(&follower.spline_name) {
+                    Some(spline) => spline,
+                    None => continue,
+                };
+
+                let looping = follower.looping;
+                follower.advance(delta_time.0, spline.segment_count(looping));
+                transform.position = spline.evaluate(follower.t, looping);
+                if follower.face_direction {
+                    if let Some(rotation) = face_direction(&spline.tangent(follower.t, looping)) {
+                        transform.rotation = rotation;
+                    }
+                }
+            }
+        })
+}
+
+// Returns the rotation that points +Z along `direction` while keeping +Y up, None if there is no direction.
+fn face_direction(direction: &Vec3) -> Option<Quat> {
+    if direction.norm_squared() <= std::f32::EPSILON {
+        return None;
+    }
+    let forward = direction.normalize();
+    // Paths going straight up or down use +Z as their up instead.
+    let up = if forward.y.abs() > 0.999 {
+        Vec3::new(0.0, 0.0, 1.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+    let right = up.cross(&forward).normalize();
+    let up = forward.cross(&right);
+    Some(nalgebra_glm::mat3_to_quat(&Mat3::from_columns(&[
+        right, up, forward,
+    ])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::face_direction;
+    use nalgebra_glm::Vec3;
+
+    #[test]
+    fn should_face_along_the_spline() {
+        let rotation = face_direction(&Vec3::new(2.0, 0.0, 0.0)).unwrap();
+        let forward = nalgebra_glm::quat_rotate_vec3(&rotation, &Vec3::new(0.0, 0.0, 1.0));
+        assert!((forward - Vec3::new(1.0, 0.0, 0.0)).norm() < 0.0001);
+        let up = nalgebra_glm::quat_rotate_vec3(&rotation, &Vec3::new(0.0, 1.0, 0.0));
+        assert!((up - Vec3::new(0.0, 1.0, 0.0)).norm() < 0.0001);
+
+        assert!(face_direction(&Vec3::zeros()).is_none());
+    }
+}
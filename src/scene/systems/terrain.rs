@@ -0,0 +1,33 @@
+use legion::prelude::*;
+
+use crate::{assets::AssetManager, scene::components};
+
+/// Builds the chunk meshes of every new `Terrain` and spawns an entity for each chunk.
+/// Chunks are positioned by a copy of the terrain's transform.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("build_terrain_mesh")
+        .read_resource::<AssetManager>()
+        .with_query(<(Write<components::Terrain>, Read<components::Transform>)>::query())
+        .build(|command_buffer, mut world, asset_manager, terrain_query| {
+            for (entity, (mut terrain, transform)) in terrain_query.iter_entities_mut(&mut world) {
+                if terrain.built {
+                    continue;
+                }
+
+                let name = format!("terrain#{}", entity);
+                let mesh_handle = asset_manager.insert_terrain(&name, &terrain);
+                let (chunks_x, chunks_z) = terrain.chunk_count();
+                for index in 0..(chunks_x * chunks_z) as usize {
+                    command_buffer.insert(
+                        (),
+                        vec![(
+                            components::Mesh::new_with_index(mesh_handle.clone(), index),
+                            components::Material::default(),
+                            (*transform).clone(),
+                        )],
+                    );
+                }
+                terrain.built = true;
+            }
+        })
+}
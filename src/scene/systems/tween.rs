@@ -0,0 +1,25 @@
+use legion::prelude::*;
+
+use crate::scene::{
+    components::{Lerp, Tween},
+    resources::{DeltaTime, TweenCompleted, TweenEvents},
+};
+
+/// Advances every `Tween<T>` and writes it's value into the entity's `T` component.
+/// Add one to the schedule for each type you want to tween.
+pub fn create<T: Lerp + Send + Sync + 'static>() -> Box<dyn Schedulable> {
+    SystemBuilder::new(format!("tick_tweens_{}", std::any::type_name::<T>()))
+        .read_resource::<DeltaTime>()
+        .write_resource::<TweenEvents>()
+        .with_query(<(Write<Tween<T>>, Write<T>)>::query())
+        .build(|_, mut world, (delta_time, tween_events), tween_query| {
+            for (entity, (mut tween, mut value)) in tween_query.iter_entities_mut(&mut world) {
+                if let Some((new_value, completed)) = tween.advance(delta_time.0) {
+                    *value = new_value;
+                    if completed {
+                        tween_events.0.push(TweenCompleted { entity });
+                    }
+                }
+            }
+        })
+}
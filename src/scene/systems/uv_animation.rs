@@ -0,0 +1,30 @@
+use legion::prelude::*;
+use std::sync::Arc;
+
+use crate::scene::{components, resources::DeltaTime};
+
+/// Advances every `UvAnimation` and writes it to the materials of the entity's mesh.
+pub fn create() -> Box<dyn Schedulable> {
+    SystemBuilder::new("tick_uv_animations")
+        .read_resource::<DeltaTime>()
+        .read_resource::<Arc<wgpu::Queue>>()
+        .with_query(<(Write<components::UvAnimation>, Read<components::Mesh>)>::query())
+        .build(|_, mut world, (delta_time, queue), uv_animation_query| {
+            for (mut uv_animation, mesh_component) in uv_animation_query.iter_mut(&mut world) {
+                uv_animation.advance(delta_time.0);
+
+                let gltf = match mesh_component.mesh_handle.get() {
+                    Ok(gltf) => gltf,
+                    Err(_) => continue,
+                };
+                let uniform = uv_animation.create_uniform();
+                for mesh in mesh_component.get_meshes(&gltf) {
+                    for material_handle in mesh.meshes.keys() {
+                        if let Ok(material) = material_handle.get() {
+                            material.update_uv_animation(&queue, &uniform);
+                        }
+                    }
+                }
+            }
+        })
+}
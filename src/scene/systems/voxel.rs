@@ -0,0 +1,92 @@
+use futures::executor::ThreadPoolBuilder;
+use legion::prelude::*;
+use nalgebra_glm::Vec3;
+use std::collections::HashMap;
+
+use crate::{
+    assets::{
+        voxel::{greedy_mesh, VoxelFaces},
+        AssetManager,
+    },
+    scene::{components, resources::BvhDirty, VoxelWorld},
+};
+
+/// Remeshes the chunks changed with `VoxelWorld::set_voxel` and spawns an entity with a `Mesh` and `Material` for each chunk.
+/// The greedy meshing runs on a thread pool, finished chunks are uploaded and attached on a later frame.
+pub fn create() -> Box<dyn Schedulable> {
+    let pool = ThreadPoolBuilder::new().pool_size(2).create().unwrap();
+    let (sender, receiver) = crossbeam::channel::unbounded::<([u32; 3], u64, VoxelFaces)>();
+
+    SystemBuilder::new("mesh_voxel_chunk")
+        .write_resource::<VoxelWorld>()
+        .write_resource::<BvhDirty>()
+        .read_resource::<AssetManager>()
+        .with_query(<Read<components::VoxelChunk>>::query())
+        .build(
+            move |command_buffer, world, (voxel_world, bvh_dirty, asset_manager), chunk_query| {
+                let transform = match voxel_world.transform() {
+                    Some(transform) => transform.clone(),
+                    None => return,
+                };
+
+                // Chunks changed again while they were meshed have a newer job on the way.
+                let finished: Vec<_> = receiver
+                    .try_iter()
+                    .filter(|(chunk, version, _)| {
+                        voxel_world.chunk_versions.get(chunk) == Some(version)
+                    })
+                    .collect();
+                if !finished.is_empty() {
+                    let chunk_entities: HashMap<[u32; 3], Entity> = chunk_query
+                        .iter_entities(&world)
+                        .map(|(entity, chunk)| (chunk.position, entity))
+                        .collect();
+                    for (chunk, _, faces) in finished {
+                        let entity = chunk_entities.get(&chunk);
+                        if faces.is_empty() {
+                            if let Some(entity) = entity {
+                                command_buffer.delete(*entity);
+                            }
+                            continue;
+                        }
+
+                        // The mesh is replaced under the same name, so existing chunk entities draw the new one.
+                        let name = format!("voxel_chunk_{}_{}_{}", chunk[0], chunk[1], chunk[2]);
+                        let mesh_handle =
+                            asset_manager.insert_voxel_chunk(&name, faces, &mut **voxel_world);
+                        if entity.is_none() {
+                            command_buffer.insert(
+                                (),
+                                vec![(
+                                    components::Mesh::new(mesh_handle),
+                                    components::Material::default(),
+                                    transform.clone(),
+                                    components::VoxelChunk { position: chunk },
+                                )],
+                            );
+                        }
+                    }
+                    bvh_dirty.0 = true;
+                }
+
+                for chunk in voxel_world.take_dirty_chunks() {
+                    let version = voxel_world.chunk_versions.entry(chunk).or_insert(0);
+                    *version += 1;
+                    let version = *version;
+
+                    let voxels = voxel_world.chunk_voxels(chunk);
+                    let chunk_size = voxel_world.chunk_size;
+                    let origin = Vec3::new(
+                        (chunk[0] * chunk_size) as f32,
+                        (chunk[1] * chunk_size) as f32,
+                        (chunk[2] * chunk_size) as f32,
+                    );
+                    let sender = sender.clone();
+                    pool.spawn_ok(async move {
+                        let faces = greedy_mesh(&voxels, chunk_size, origin);
+                        sender.send((chunk, version, faces)).unwrap();
+                    });
+                }
+            },
+        )
+}
@@ -0,0 +1,185 @@
+use super::components::Transform;
+use crate::{
+    assets::{material::PBRMaterial, AssetHandle},
+    core::SparseOctree,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Identifies the kind of a voxel, `EMPTY_VOXEL` is air.
+pub type VoxelId = u16;
+
+pub const EMPTY_VOXEL: VoxelId = 0;
+
+/// A world made of cubes one unit wide, split into chunks which are meshed by the `mesh_voxel_chunk` system.
+/// The `Application` inserts an empty world, replace it with `VoxelWorld::new` to start building one.
+pub struct VoxelWorld {
+    /// Change voxels with `set_voxel` so the affected chunks are remeshed.
+    pub octree: SparseOctree<VoxelId>,
+    /// How many voxels a chunk is wide along each axis.
+    pub chunk_size: u32,
+    /// The material of each kind of voxel, kinds without one are drawn white.
+    pub materials: HashMap<VoxelId, Arc<AssetHandle<PBRMaterial>>>,
+    // Copied onto every chunk entity, None for the default world which has no chunks.
+    transform: Option<Transform>,
+    dirty_chunks: HashSet<[u32; 3]>,
+    // Bumped whenever a chunk is queued for meshing, so meshes of outdated jobs are dropped.
+    pub(crate) chunk_versions: HashMap<[u32; 3], u64>,
+    pub(crate) default_material: Option<Arc<AssetHandle<PBRMaterial>>>,
+}
+
+impl Default for VoxelWorld {
+    fn default() -> Self {
+        Self {
+            octree: SparseOctree::default(),
+            chunk_size: 16,
+            materials: HashMap::new(),
+            transform: None,
+            dirty_chunks: HashSet::new(),
+            chunk_versions: HashMap::new(),
+            default_material: None,
+        }
+    }
+}
+
+impl VoxelWorld {
+    /// Creates an empty world `size` voxels wide, rounded up to the next power of two.
+    /// Chunks are positioned by a copy of `transform`.
+    pub fn new(transform: Transform, size: u32, chunk_size: u32) -> Self {
+        Self {
+            octree: SparseOctree::new(size),
+            chunk_size: chunk_size.max(1),
+            materials: HashMap::new(),
+            transform: Some(transform),
+            dirty_chunks: HashSet::new(),
+            chunk_versions: HashMap::new(),
+            default_material: None,
+        }
+    }
+
+    pub(crate) fn transform(&self) -> Option<&Transform> {
+        self.transform.as_ref()
+    }
+
+    /// Returns `EMPTY_VOXEL` for positions outside the world.
+    pub fn get_voxel(&self, x: u32, y: u32, z: u32) -> VoxelId {
+        self.octree.get(x, y, z)
+    }
+
+    /// Changes a voxel and marks it's chunk for remeshing, along with the neighbouring chunks that touch it.
+    /// Returns false if the position is outside the world.
+    pub fn set_voxel(&mut self, x: u32, y: u32, z: u32, id: VoxelId) -> bool {
+        if !self.octree.contains(x, y, z) {
+            return false;
+        }
+        if self.octree.get(x, y, z) == id {
+            return true;
+        }
+        self.octree.set(x, y, z, id);
+
+        // Faces of a voxel on a chunk's edge are hidden or shown by the voxels of the next chunk.
+        let chunk_count = self.chunk_count();
+        let position = [x, y, z];
+        let chunk = [
+            x / self.chunk_size,
+            y / self.chunk_size,
+            z / self.chunk_size,
+        ];
+        self.dirty_chunks.insert(chunk);
+        for axis in 0..3 {
+            let local = position[axis] % self.chunk_size;
+            if local == 0 && chunk[axis] > 0 {
+                let mut neighbour = chunk;
+                neighbour[axis] -= 1;
+                self.dirty_chunks.insert(neighbour);
+            }
+            if local == self.chunk_size - 1 && chunk[axis] + 1 < chunk_count {
+                let mut neighbour = chunk;
+                neighbour[axis] += 1;
+                self.dirty_chunks.insert(neighbour);
+            }
+        }
+        true
+    }
+
+    /// How many chunks the world is made of along each axis.
+    pub fn chunk_count(&self) -> u32 {
+        (self.octree.size() + self.chunk_size - 1) / self.chunk_size
+    }
+
+    /// Takes the chunks changed since the last call.
+    pub(crate) fn take_dirty_chunks(&mut self) -> Vec<[u32; 3]> {
+        self.dirty_chunks.drain().collect()
+    }
+
+    /// Copies the voxels of a chunk plus a one voxel border, x first, as expected by the greedy mesher.
+    pub(crate) fn chunk_voxels(&self, chunk: [u32; 3]) -> Vec<VoxelId> {
+        let padded_size = self.chunk_size as i64 + 2;
+        let origin: Vec<i64> = chunk
+            .iter()
+            .map(|coordinate| (coordinate * self.chunk_size) as i64 - 1)
+            .collect();
+
+        let mut voxels = Vec::with_capacity((padded_size * padded_size * padded_size) as usize);
+        for z in origin[2]..origin[2] + padded_size {
+            for y in origin[1]..origin[1] + padded_size {
+                for x in origin[0]..origin[0] + padded_size {
+                    if x < 0 || y < 0 || z < 0 {
+                        voxels.push(EMPTY_VOXEL);
+                    } else {
+                        voxels.push(self.get_voxel(x as u32, y as u32, z as u32));
+                    }
+                }
+            }
+        }
+        voxels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VoxelWorld, EMPTY_VOXEL};
+    use std::collections::HashSet;
+
+    fn create_world() -> VoxelWorld {
+        let mut world = VoxelWorld::default();
+        world.octree = crate::core::SparseOctree::new(32);
+        world
+    }
+
+    #[test]
+    fn should_mark_touching_chunks_dirty() {
+        let mut world = create_world();
+        assert!(world.set_voxel(5, 5, 5, 1));
+        assert_eq!(world.get_voxel(5, 5, 5), 1);
+        assert_eq!(world.take_dirty_chunks(), vec![[0, 0, 0]]);
+
+        // Setting the same voxel again changes nothing.
+        world.set_voxel(5, 5, 5, 1);
+        assert!(world.take_dirty_chunks().is_empty());
+
+        world.set_voxel(15, 16, 0, 2);
+        let dirty: HashSet<[u32; 3]> = world.take_dirty_chunks().into_iter().collect();
+        let expected: HashSet<[u32; 3]> =
+            vec![[0, 1, 0], [1, 1, 0], [0, 0, 0]].into_iter().collect();
+        assert_eq!(dirty, expected);
+
+        assert!(!world.set_voxel(32, 0, 0, 1));
+        assert_eq!(world.get_voxel(32, 0, 0), EMPTY_VOXEL);
+    }
+
+    #[test]
+    fn should_copy_chunk_with_border() {
+        let mut world = create_world();
+        world.set_voxel(15, 0, 0, 1);
+        world.set_voxel(16, 0, 0, 2);
+
+        let voxels = world.chunk_voxels([1, 0, 0]);
+        assert_eq!(voxels.len(), 18 * 18 * 18);
+        // Index (0, 1, 1) is the left neighbour's voxel, (1, 1, 1) the chunk's first voxel.
+        assert_eq!(voxels[18 + 18 * 18], 1);
+        assert_eq!(voxels[1 + 18 + 18 * 18], 2);
+    }
+}